@@ -92,6 +92,54 @@ CREATE TABLE IF NOT EXISTS credit_cards_tombstones (
     time_deleted    INTEGER NOT NULL
 ) WITHOUT ROWID;
 
+-- Values the user has entered into web form fields, offered when they fill in
+-- a field with the same name. Desktop calls this "form history".
+CREATE TABLE IF NOT EXISTS form_history_data (
+    guid                TEXT NOT NULL PRIMARY KEY CHECK(length(guid) != 0),
+    field_name          TEXT NOT NULL CHECK(length(field_name) != 0),
+    value               TEXT NOT NULL CHECK(length(value) != 0),
+
+    -- Only the field name and value are synced, so these are local to this
+    -- device.
+    time_created        INTEGER NOT NULL,
+    time_last_used      INTEGER NOT NULL,
+    time_last_modified  INTEGER NOT NULL,
+    times_used          INTEGER NOT NULL,
+
+    sync_change_counter INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS form_history_field_name_value
+ON form_history_data(field_name, value);
+
+CREATE TABLE IF NOT EXISTS form_history_mirror (
+    guid                TEXT NOT NULL PRIMARY KEY CHECK(length(guid) != 0),
+    payload             TEXT NOT NULL CHECK(length(payload) != 0)
+);
+
+CREATE TABLE IF NOT EXISTS form_history_tombstones (
+    guid            TEXT PRIMARY KEY CHECK(length(guid) != 0),
+    time_deleted    INTEGER NOT NULL
+) WITHOUT ROWID;
+
+-- The preferences this app syncs. There's at most one row, for this app's
+-- record on the server - other apps (and desktop) have their own records,
+-- which we never download into here. Prefs can't be deleted, so there are no
+-- tombstones.
+CREATE TABLE IF NOT EXISTS prefs_data (
+    guid                TEXT NOT NULL PRIMARY KEY CHECK(length(guid) != 0),
+    -- A JSON object mapping pref names to values, where `null` means the pref
+    -- has its default value. Only prefs in the allowlist are stored here.
+    prefs               TEXT NOT NULL,
+    time_last_modified  INTEGER NOT NULL,
+    sync_change_counter INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS prefs_mirror (
+    guid                TEXT NOT NULL PRIMARY KEY CHECK(length(guid) != 0),
+    payload             TEXT NOT NULL CHECK(length(payload) != 0)
+);
+
 -- This table holds key-value metadata for the Autofill component and its consumers.
 CREATE TABLE IF NOT EXISTS moz_meta (
     key TEXT PRIMARY KEY,
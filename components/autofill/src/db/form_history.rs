@@ -0,0 +1,297 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::db::{
+    models::{form_history::InternalFormHistoryEntry, Metadata},
+    schema::{FORM_HISTORY_COMMON_COLS, FORM_HISTORY_COMMON_VALS},
+};
+use crate::error::*;
+
+use rusqlite::{Connection, Transaction};
+use sql_support::ConnExt;
+use sync_guid::Guid;
+use types::Timestamp;
+
+/// Records that the user entered `value` into a field named `field_name`.
+/// If they've entered it before, the existing entry's usage is updated
+/// instead of adding a duplicate.
+pub(crate) fn add_form_history_entry(
+    conn: &Connection,
+    field_name: &str,
+    value: &str,
+) -> Result<InternalFormHistoryEntry> {
+    let tx = conn.unchecked_transaction()?;
+    let now = Timestamp::now();
+    let entry = match find_form_history_entry(&tx, field_name, value)? {
+        Some(mut entry) => {
+            // Only the field name and value are synced, so using an entry
+            // doesn't need to mark it as changed.
+            tx.execute(
+                "UPDATE form_history_data
+                SET time_last_used = :time_last_used,
+                    times_used     = times_used + 1
+                WHERE guid         = :guid",
+                rusqlite::named_params! {
+                    ":time_last_used": now,
+                    ":guid": entry.guid,
+                },
+            )?;
+            entry.metadata.time_last_used = now;
+            entry.metadata.times_used += 1;
+            entry
+        }
+        None => {
+            let entry = InternalFormHistoryEntry {
+                guid: Guid::random(),
+                field_name: field_name.to_string(),
+                value: value.to_string(),
+                metadata: Metadata {
+                    time_created: now,
+                    time_last_used: now,
+                    time_last_modified: now,
+                    times_used: 1,
+                    ..Default::default()
+                },
+            };
+            add_internal_form_history_entry(&tx, &entry)?;
+            entry
+        }
+    };
+    tx.commit()?;
+    Ok(entry)
+}
+
+pub(crate) fn add_internal_form_history_entry(
+    tx: &Transaction<'_>,
+    entry: &InternalFormHistoryEntry,
+) -> Result<()> {
+    tx.execute(
+        &format!(
+            "INSERT INTO form_history_data (
+                {common_cols},
+                sync_change_counter
+            ) VALUES (
+                {common_vals},
+                :sync_change_counter
+            )",
+            common_cols = FORM_HISTORY_COMMON_COLS,
+            common_vals = FORM_HISTORY_COMMON_VALS,
+        ),
+        rusqlite::named_params! {
+            ":guid": entry.guid,
+            ":field_name": entry.field_name,
+            ":value": entry.value,
+            ":time_created": entry.metadata.time_created,
+            ":time_last_used": entry.metadata.time_last_used,
+            ":time_last_modified": entry.metadata.time_last_modified,
+            ":times_used": entry.metadata.times_used,
+            ":sync_change_counter": entry.metadata.sync_change_counter,
+        },
+    )?;
+    Ok(())
+}
+
+fn find_form_history_entry(
+    conn: &Connection,
+    field_name: &str,
+    value: &str,
+) -> Result<Option<InternalFormHistoryEntry>> {
+    let sql = format!(
+        "SELECT
+            {common_cols},
+            sync_change_counter
+        FROM form_history_data
+        WHERE field_name = :field_name
+            AND value = :value
+        LIMIT 1",
+        common_cols = FORM_HISTORY_COMMON_COLS
+    );
+    conn.try_query_row(
+        &sql,
+        rusqlite::named_params! {
+            ":field_name": field_name,
+            ":value": value,
+        },
+        |row| -> Result<_> { Ok(InternalFormHistoryEntry::from_row(row)?) },
+        true,
+    )
+}
+
+pub(crate) fn get_form_history_entry(
+    conn: &Connection,
+    guid: &Guid,
+) -> Result<InternalFormHistoryEntry> {
+    let sql = format!(
+        "SELECT
+            {common_cols},
+            sync_change_counter
+        FROM form_history_data
+        WHERE guid = :guid",
+        common_cols = FORM_HISTORY_COMMON_COLS
+    );
+    conn.query_row(&sql, [guid], InternalFormHistoryEntry::from_row)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Error::NoSuchRecord(guid.to_string()),
+            e => e.into(),
+        })
+}
+
+/// Returns the values entered into fields named `field_name`, most recently
+/// used first.
+pub(crate) fn get_form_history(
+    conn: &Connection,
+    field_name: &str,
+) -> Result<Vec<InternalFormHistoryEntry>> {
+    let sql = format!(
+        "SELECT
+            {common_cols},
+            sync_change_counter
+        FROM form_history_data
+        WHERE field_name = :field_name
+        ORDER BY time_last_used DESC",
+        common_cols = FORM_HISTORY_COMMON_COLS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let entries = stmt
+        .query_map(
+            rusqlite::named_params! { ":field_name": field_name },
+            InternalFormHistoryEntry::from_row,
+        )?
+        .collect::<std::result::Result<Vec<InternalFormHistoryEntry>, _>>()?;
+    Ok(entries)
+}
+
+pub(crate) fn get_all_form_history(conn: &Connection) -> Result<Vec<InternalFormHistoryEntry>> {
+    let sql = format!(
+        "SELECT
+            {common_cols},
+            sync_change_counter
+        FROM form_history_data",
+        common_cols = FORM_HISTORY_COMMON_COLS
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let entries = stmt
+        .query_map([], InternalFormHistoryEntry::from_row)?
+        .collect::<std::result::Result<Vec<InternalFormHistoryEntry>, _>>()?;
+    Ok(entries)
+}
+
+/// Updates the synced fields of an entry - the usage metadata is local to
+/// this device, so Sync never changes it.
+pub(crate) fn update_internal_form_history_entry(
+    tx: &Transaction<'_>,
+    entry: &InternalFormHistoryEntry,
+    flag_as_changed: bool,
+) -> Result<()> {
+    let change_counter_increment = flag_as_changed as u32; // will be 1 or 0
+    let rows_changed = tx.execute(
+        "UPDATE form_history_data SET
+            field_name          = :field_name,
+            value               = :value,
+            time_last_modified  = :time_last_modified,
+            sync_change_counter = sync_change_counter + :change_incr
+        WHERE guid              = :guid",
+        rusqlite::named_params! {
+            ":field_name": entry.field_name,
+            ":value": entry.value,
+            ":time_last_modified": Timestamp::now(),
+            ":change_incr": change_counter_increment,
+            ":guid": entry.guid,
+        },
+    )?;
+    // Something went badly wrong if we are asking to update a row that doesn't
+    // exist, or somehow we updated more than 1!
+    assert_eq!(rows_changed, 1);
+    Ok(())
+}
+
+pub(crate) fn delete_form_history_entry(conn: &Connection, guid: &Guid) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+
+    // execute returns how many rows were affected.
+    let exists = tx.execute(
+        "DELETE FROM form_history_data
+            WHERE guid = :guid",
+        rusqlite::named_params! {
+            ":guid": guid,
+        },
+    )? != 0;
+    // Unlike addresses and credit cards, we write the tombstone here rather
+    // than in a trigger, because the shared triggers are created after each
+    // schema upgrade and can't reference tables older versions don't have.
+    if exists {
+        tx.execute(
+            "INSERT OR IGNORE INTO form_history_tombstones (guid, time_deleted)
+                SELECT guid, now() FROM form_history_mirror
+                WHERE guid = :guid",
+            rusqlite::named_params! {
+                ":guid": guid,
+            },
+        )?;
+    }
+    tx.commit()?;
+    Ok(exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test::new_mem_db;
+
+    #[test]
+    fn test_form_history_add_and_read() -> Result<()> {
+        let db = new_mem_db();
+
+        let entry = add_form_history_entry(&db, "email", "jane@example.com")?;
+        assert_ne!(Guid::default(), entry.guid);
+        assert_eq!(entry.metadata.times_used, 1);
+        assert_eq!(entry.metadata.sync_change_counter, 0);
+        add_form_history_entry(&db, "email", "john@example.com")?;
+        add_form_history_entry(&db, "name", "jane")?;
+
+        // Entering the same value again updates the existing entry.
+        let again = add_form_history_entry(&db, "email", "jane@example.com")?;
+        assert_eq!(again.guid, entry.guid);
+        assert_eq!(again.metadata.times_used, 2);
+        assert_eq!(again.metadata.sync_change_counter, 0);
+
+        let retrieved = get_form_history_entry(&db, &entry.guid)?;
+        assert_eq!(retrieved.value, "jane@example.com");
+        assert_eq!(retrieved.metadata.times_used, 2);
+
+        let emails = get_form_history(&db, "email")?;
+        assert_eq!(emails.len(), 2);
+        assert_eq!(get_all_form_history(&db)?.len(), 3);
+        assert!(get_form_history(&db, "tel")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_form_history_delete() -> Result<()> {
+        let db = new_mem_db();
+        let synced = add_form_history_entry(&db, "email", "jane@example.com")?;
+        let unsynced = add_form_history_entry(&db, "email", "john@example.com")?;
+        db.execute(
+            "INSERT INTO form_history_mirror (guid, payload) VALUES (:guid, '{}')",
+            rusqlite::named_params! { ":guid": synced.guid },
+        )?;
+
+        assert!(delete_form_history_entry(&db, &synced.guid)?);
+        assert!(delete_form_history_entry(&db, &unsynced.guid)?);
+        assert!(!delete_form_history_entry(&db, &unsynced.guid)?);
+        assert!(matches!(
+            get_form_history_entry(&db, &synced.guid),
+            Err(Error::NoSuchRecord(_))
+        ));
+
+        // Only the entry we'd synced needs a tombstone.
+        let tombstones: Vec<Guid> =
+            db.query_rows_and_then("SELECT guid FROM form_history_tombstones", [], |row| {
+                row.get(0)
+            })?;
+        assert_eq!(tombstones, vec![synced.guid]);
+        Ok(())
+    }
+}
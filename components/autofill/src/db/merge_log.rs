@@ -0,0 +1,94 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::error::*;
+
+use rusqlite::{named_params, Connection};
+use sql_support::ConnExt;
+use sync15::{MergeLogEntry, MERGE_LOG_MAX_ENTRIES};
+
+/// Adds a conflict that sync resolved to the merge log, dropping the oldest
+/// entries so the log never grows past `MERGE_LOG_MAX_ENTRIES`.
+pub(crate) fn add_merge_log_entry(conn: &Connection, entry: &MergeLogEntry) -> Result<()> {
+    conn.execute_cached(
+        "INSERT INTO merge_log (collection, guid, fields, winner, timestamp)
+         VALUES (:collection, :guid, :fields, :winner, :timestamp)",
+        named_params! {
+            ":collection": entry.collection,
+            ":guid": entry.guid,
+            ":fields": serde_json::to_string(&entry.fields)?,
+            ":winner": entry.winner.as_str(),
+            ":timestamp": entry.timestamp,
+        },
+    )?;
+    conn.execute_cached(
+        "DELETE FROM merge_log
+         WHERE id NOT IN (
+             SELECT id FROM merge_log ORDER BY id DESC LIMIT :max_entries
+         )",
+        named_params! { ":max_entries": MERGE_LOG_MAX_ENTRIES as i64 },
+    )?;
+    Ok(())
+}
+
+/// Returns the conflicts that sync resolved for every autofill collection,
+/// most recent first.
+pub(crate) fn get_merge_log(conn: &Connection) -> Result<Vec<MergeLogEntry>> {
+    conn.query_rows_and_then_cached(
+        "SELECT collection, guid, fields, winner, timestamp
+         FROM merge_log
+         ORDER BY id DESC",
+        [],
+        |row| -> Result<MergeLogEntry> {
+            let winner: String = row.get("winner")?;
+            Ok(MergeLogEntry {
+                collection: row.get("collection")?,
+                guid: row.get("guid")?,
+                fields: serde_json::from_str(&row.get::<_, String>("fields")?)?,
+                winner: winner.parse().map_err(|_| Error::BadMergeWinner(winner))?,
+                timestamp: row.get("timestamp")?,
+            })
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test::new_mem_db;
+    use sync15::MergeWinner;
+
+    #[test]
+    fn test_merge_log() -> Result<()> {
+        let db = new_mem_db();
+        for i in 0..MERGE_LOG_MAX_ENTRIES + 5 {
+            add_merge_log_entry(
+                &db,
+                &MergeLogEntry {
+                    collection: "addresses".to_string(),
+                    guid: format!("address{i}"),
+                    fields: vec!["street_address".to_string(), "tel".to_string()],
+                    winner: MergeWinner::Both,
+                    timestamp: i as i64,
+                },
+            )?;
+        }
+        // Only the most recent entries are kept, newest first.
+        let log = get_merge_log(&db)?;
+        assert_eq!(log.len(), MERGE_LOG_MAX_ENTRIES);
+        assert_eq!(
+            log[0],
+            MergeLogEntry {
+                collection: "addresses".to_string(),
+                guid: format!("address{}", MERGE_LOG_MAX_ENTRIES + 4),
+                fields: vec!["street_address".to_string(), "tel".to_string()],
+                winner: MergeWinner::Both,
+                timestamp: (MERGE_LOG_MAX_ENTRIES + 4) as i64,
+            }
+        );
+        assert_eq!(log[MERGE_LOG_MAX_ENTRIES - 1].guid, "address5");
+        Ok(())
+    }
+}
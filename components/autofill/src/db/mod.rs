@@ -4,7 +4,10 @@
 
 pub mod addresses;
 pub mod credit_cards;
+pub mod form_history;
+pub mod merge_log;
 pub mod models;
+pub mod prefs;
 pub mod schema;
 pub mod store;
 
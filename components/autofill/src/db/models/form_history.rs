@@ -0,0 +1,60 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use super::Metadata;
+use rusqlite::Row;
+use sync_guid::Guid;
+
+// "FormHistoryEntry" is what we return to consumers - a value the user
+// entered into a form field with the given name.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct FormHistoryEntry {
+    pub guid: String,
+    pub field_name: String,
+    pub value: String,
+    // We expose some of the metadata
+    pub time_created: i64,
+    pub time_last_used: i64,
+    pub times_used: i64,
+}
+
+impl From<InternalFormHistoryEntry> for FormHistoryEntry {
+    fn from(ie: InternalFormHistoryEntry) -> Self {
+        FormHistoryEntry {
+            guid: ie.guid.to_string(),
+            field_name: ie.field_name,
+            value: ie.value,
+            // note we can't use u64 in uniffi
+            time_created: u64::from(ie.metadata.time_created) as i64,
+            time_last_used: u64::from(ie.metadata.time_last_used) as i64,
+            times_used: ie.metadata.times_used,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct InternalFormHistoryEntry {
+    pub guid: Guid,
+    pub field_name: String,
+    pub value: String,
+    pub metadata: Metadata,
+}
+
+impl InternalFormHistoryEntry {
+    pub fn from_row(row: &Row<'_>) -> Result<InternalFormHistoryEntry, rusqlite::Error> {
+        Ok(Self {
+            guid: row.get("guid")?,
+            field_name: row.get("field_name")?,
+            value: row.get("value")?,
+            metadata: Metadata {
+                time_created: row.get("time_created")?,
+                time_last_used: row.get("time_last_used")?,
+                time_last_modified: row.get("time_last_modified")?,
+                times_used: row.get("times_used")?,
+                sync_change_counter: row.get("sync_change_counter")?,
+            },
+        })
+    }
+}
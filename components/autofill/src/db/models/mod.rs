@@ -5,6 +5,8 @@
 
 pub mod address;
 pub mod credit_card;
+pub mod form_history;
+pub mod prefs;
 use types::Timestamp;
 
 /// Metadata that's common between the records.
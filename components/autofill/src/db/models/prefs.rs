@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use super::Metadata;
+use crate::error::Result;
+use rusqlite::Row;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use sync_guid::Guid;
+
+// The value of a synced pref. These are the types desktop prefs can have.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum PrefValue {
+    Bool { value: bool },
+    Int { value: i64 },
+    String { value: String },
+}
+
+// "SyncedPref" is what we exchange with consumers. A `None` value means the
+// pref has its default value, which is also how desktop syncs a pref that's
+// been reset.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct SyncedPref {
+    pub name: String,
+    pub value: Option<PrefValue>,
+}
+
+impl SyncedPref {
+    // Returns `None` for JSON values that aren't valid pref values, which we
+    // skip rather than failing the whole record.
+    pub(crate) fn from_json(name: String, value: &Value) -> Option<Self> {
+        let value = match value {
+            Value::Null => None,
+            Value::Bool(value) => Some(PrefValue::Bool { value: *value }),
+            Value::Number(n) => Some(PrefValue::Int { value: n.as_i64()? }),
+            Value::String(value) => Some(PrefValue::String {
+                value: value.clone(),
+            }),
+            _ => return None,
+        };
+        Some(Self { name, value })
+    }
+
+    pub(crate) fn json_value(&self) -> Value {
+        match &self.value {
+            None => Value::Null,
+            Some(PrefValue::Bool { value }) => Value::from(*value),
+            Some(PrefValue::Int { value }) => Value::from(*value),
+            Some(PrefValue::String { value }) => Value::from(value.as_str()),
+        }
+    }
+}
+
+// All of the prefs in this app's record. The values are kept as JSON so
+// that merging and round-tripping never loses anything we don't understand.
+#[derive(Default, Debug, Clone)]
+pub struct InternalPrefs {
+    pub guid: Guid,
+    pub prefs: BTreeMap<String, Value>,
+    // Only the change counter and modification time are meaningful for prefs.
+    pub metadata: Metadata,
+}
+
+impl InternalPrefs {
+    pub fn from_row(row: &Row<'_>) -> Result<InternalPrefs> {
+        let prefs: String = row.get("prefs")?;
+        Ok(Self {
+            guid: row.get("guid")?,
+            prefs: serde_json::from_str(&prefs)?,
+            metadata: Metadata {
+                time_last_modified: row.get("time_last_modified")?,
+                sync_change_counter: row.get("sync_change_counter")?,
+                ..Default::default()
+            },
+        })
+    }
+
+    pub fn synced_prefs(&self) -> Vec<SyncedPref> {
+        self.prefs
+            .iter()
+            .filter_map(|(name, value)| SyncedPref::from_json(name.clone(), value))
+            .collect()
+    }
+
+    // Drops any prefs that aren't in the allowlist.
+    pub(crate) fn retain_allowed(&mut self, allowlist: &[String]) {
+        self.prefs.retain(|name, _| allowlist.contains(name));
+    }
+}
@@ -0,0 +1,230 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::db::models::prefs::{InternalPrefs, SyncedPref};
+use crate::db::store::{get_meta, put_meta};
+use crate::error::*;
+
+use base64::{engine::general_purpose::URL_SAFE, Engine};
+use rusqlite::{Connection, Transaction};
+use sql_support::ConnExt;
+use sync_guid::Guid;
+use types::Timestamp;
+
+// The names of the prefs the app syncs, as a JSON array.
+const ALLOWLIST_META_KEY: &str = "synced_prefs_allowlist";
+
+/// Returns the id of an app's prefs record on the server. Each app has its
+/// own record, with an id derived from the app id the same way desktop does
+/// it, so apps which share an id share their prefs.
+pub(crate) fn prefs_record_guid(app_id: &str) -> Guid {
+    Guid::from_string(URL_SAFE.encode(app_id))
+}
+
+/// Sets the app id and the allowlist of prefs to sync. Apps should call this
+/// on startup, before setting any prefs. Prefs which are no longer in the
+/// allowlist are forgotten.
+pub(crate) fn configure_synced_prefs(
+    conn: &Connection,
+    app_id: &str,
+    allowlist: Vec<String>,
+) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    put_meta(&tx, ALLOWLIST_META_KEY, &serde_json::to_string(&allowlist)?)?;
+    let guid = prefs_record_guid(app_id);
+    match get_prefs(&tx)? {
+        Some(mut prefs) => {
+            let count = prefs.prefs.len();
+            prefs.retain_allowed(&allowlist);
+            if prefs.guid != guid {
+                // The app id changed, so we need to upload our prefs to the
+                // new record. We leave the old record on the server alone,
+                // in case other devices still use it.
+                tx.execute(
+                    "UPDATE prefs_data
+                    SET guid = :new_guid
+                    WHERE guid = :old_guid",
+                    rusqlite::named_params! {
+                        ":new_guid": guid,
+                        ":old_guid": prefs.guid,
+                    },
+                )?;
+                prefs.guid = guid;
+                update_internal_prefs(&tx, &prefs, true)?;
+            } else if prefs.prefs.len() != count {
+                update_internal_prefs(&tx, &prefs, false)?;
+            }
+        }
+        None => add_internal_prefs(
+            &tx,
+            &InternalPrefs {
+                guid,
+                ..Default::default()
+            },
+        )?,
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub(crate) fn get_allowlist(conn: &Connection) -> Result<Vec<String>> {
+    Ok(match get_meta::<String>(conn, ALLOWLIST_META_KEY)? {
+        Some(json) => serde_json::from_str(&json)?,
+        None => vec![],
+    })
+}
+
+/// Returns this app's prefs, or `None` if the app hasn't configured synced
+/// prefs yet.
+pub(crate) fn get_prefs(conn: &Connection) -> Result<Option<InternalPrefs>> {
+    conn.try_query_row(
+        "SELECT guid, prefs, time_last_modified, sync_change_counter
+        FROM prefs_data",
+        [],
+        InternalPrefs::from_row,
+        true,
+    )
+}
+
+/// Sets the value of a pref in the allowlist, which is uploaded on the next
+/// sync if it changed.
+pub(crate) fn set_synced_pref(conn: &Connection, pref: &SyncedPref) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    if !get_allowlist(&tx)?.contains(&pref.name) {
+        return Err(Error::PrefNotSynced(pref.name.clone()));
+    }
+    let mut prefs = get_prefs(&tx)?.ok_or_else(|| Error::PrefNotSynced(pref.name.clone()))?;
+    let value = pref.json_value();
+    if prefs.prefs.get(&pref.name) != Some(&value) {
+        prefs.prefs.insert(pref.name.clone(), value);
+        update_internal_prefs(&tx, &prefs, true)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub(crate) fn add_internal_prefs(tx: &Transaction<'_>, prefs: &InternalPrefs) -> Result<()> {
+    tx.execute(
+        "INSERT INTO prefs_data (
+            guid,
+            prefs,
+            time_last_modified,
+            sync_change_counter
+        ) VALUES (
+            :guid,
+            :prefs,
+            :time_last_modified,
+            :sync_change_counter
+        )",
+        rusqlite::named_params! {
+            ":guid": prefs.guid,
+            ":prefs": serde_json::to_string(&prefs.prefs)?,
+            ":time_last_modified": Timestamp::now(),
+            ":sync_change_counter": prefs.metadata.sync_change_counter,
+        },
+    )?;
+    Ok(())
+}
+
+pub(crate) fn update_internal_prefs(
+    tx: &Transaction<'_>,
+    prefs: &InternalPrefs,
+    flag_as_changed: bool,
+) -> Result<()> {
+    let change_counter_increment = flag_as_changed as u32; // will be 1 or 0
+    let rows_changed = tx.execute(
+        "UPDATE prefs_data SET
+            prefs               = :prefs,
+            time_last_modified  = :time_last_modified,
+            sync_change_counter = sync_change_counter + :change_incr
+        WHERE guid              = :guid",
+        rusqlite::named_params! {
+            ":prefs": serde_json::to_string(&prefs.prefs)?,
+            ":time_last_modified": Timestamp::now(),
+            ":change_incr": change_counter_increment,
+            ":guid": prefs.guid,
+        },
+    )?;
+    // Something went badly wrong if we are asking to update a row that doesn't
+    // exist, or somehow we updated more than 1!
+    assert_eq!(rows_changed, 1);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::prefs::PrefValue;
+    use crate::db::test::new_mem_db;
+
+    fn bool_pref(name: &str, value: bool) -> SyncedPref {
+        SyncedPref {
+            name: name.to_string(),
+            value: Some(PrefValue::Bool { value }),
+        }
+    }
+
+    #[test]
+    fn test_prefs_record_guid() {
+        // This is the id desktop Firefox uses for its prefs record.
+        assert_eq!(
+            prefs_record_guid("{ec8030f7-c20a-464f-9b0e-13a3a9e97384}"),
+            "e2VjODAzMGY3LWMyMGEtNDY0Zi05YjBlLTEzYTNhOWU5NzM4NH0="
+        );
+    }
+
+    #[test]
+    fn test_set_synced_pref() -> Result<()> {
+        let db = new_mem_db();
+
+        // Nothing can be set until the app configures the allowlist.
+        assert!(matches!(
+            set_synced_pref(&db, &bool_pref("a", true)),
+            Err(Error::PrefNotSynced(_))
+        ));
+        assert!(get_prefs(&db)?.is_none());
+
+        configure_synced_prefs(&db, "app", vec!["a".to_string(), "b".to_string()])?;
+        let prefs = get_prefs(&db)?.expect("should exist");
+        assert_eq!(prefs.guid, prefs_record_guid("app"));
+        assert!(prefs.prefs.is_empty());
+        assert_eq!(prefs.metadata.sync_change_counter, 0);
+
+        set_synced_pref(&db, &bool_pref("a", true))?;
+        set_synced_pref(
+            &db,
+            &SyncedPref {
+                name: "b".to_string(),
+                value: None,
+            },
+        )?;
+        assert!(matches!(
+            set_synced_pref(&db, &bool_pref("c", true)),
+            Err(Error::PrefNotSynced(_))
+        ));
+        // Setting a pref to the same value isn't a change.
+        set_synced_pref(&db, &bool_pref("a", true))?;
+
+        let prefs = get_prefs(&db)?.expect("should exist");
+        assert_eq!(prefs.metadata.sync_change_counter, 2);
+        assert_eq!(
+            prefs.synced_prefs(),
+            vec![
+                bool_pref("a", true),
+                SyncedPref {
+                    name: "b".to_string(),
+                    value: None,
+                },
+            ]
+        );
+
+        // Removing a pref from the allowlist forgets it.
+        configure_synced_prefs(&db, "app", vec!["a".to_string()])?;
+        let prefs = get_prefs(&db)?.expect("should exist");
+        assert_eq!(prefs.synced_prefs(), vec![bool_pref("a", true)]);
+        assert_eq!(prefs.metadata.sync_change_counter, 2);
+        Ok(())
+    }
+}
@@ -70,15 +70,50 @@ pub const CREDIT_CARD_COMMON_VALS: &str = "
     :time_last_modified,
     :times_used";
 
+pub const FORM_HISTORY_COMMON_COLS: &str = "
+    guid,
+    field_name,
+    value,
+    time_created,
+    time_last_used,
+    time_last_modified,
+    times_used";
+
+pub const FORM_HISTORY_COMMON_VALS: &str = "
+    :guid,
+    :field_name,
+    :value,
+    :time_created,
+    :time_last_used,
+    :time_last_modified,
+    :times_used";
+
 const CREATE_SHARED_SCHEMA_SQL: &str = include_str!("../../sql/create_shared_schema.sql");
 const CREATE_SHARED_TRIGGERS_SQL: &str = include_str!("../../sql/create_shared_triggers.sql");
 const CREATE_SYNC_TEMP_TABLES_SQL: &str = include_str!("../../sql/create_sync_temp_tables.sql");
 
+// The most recent conflicts that sync resolved between local and incoming
+// records, so support can see why a record changed. Only the names of the
+// conflicting fields are stored, never their values. This isn't in the shared
+// schema, because earlier upgrades run that to create their tables.
+const CREATE_MERGE_LOG_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS merge_log (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection  TEXT NOT NULL,
+        guid        TEXT NOT NULL,
+        -- A JSON array of field names.
+        fields      TEXT NOT NULL,
+        -- A `sync15::MergeWinner`, as a string.
+        winner      TEXT NOT NULL,
+        timestamp   INTEGER NOT NULL
+    );
+";
+
 pub struct AutofillConnectionInitializer;
 
 impl ConnectionInitializer for AutofillConnectionInitializer {
     const NAME: &'static str = "autofill db";
-    const END_VERSION: u32 = 2;
+    const END_VERSION: u32 = 4;
 
     fn prepare(&self, conn: &Connection, _db_empty: bool) -> Result<()> {
         define_functions(conn)?;
@@ -98,7 +133,8 @@ impl ConnectionInitializer for AutofillConnectionInitializer {
     }
 
     fn init(&self, db: &Transaction<'_>) -> Result<()> {
-        Ok(db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?)
+        db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+        Ok(db.execute_batch(CREATE_MERGE_LOG_SQL)?)
     }
 
     fn upgrade_from(&self, db: &Transaction<'_>, version: u32) -> Result<()> {
@@ -107,6 +143,8 @@ impl ConnectionInitializer for AutofillConnectionInitializer {
             // upgrade_from_v0() for more details.
             0 => upgrade_from_v0(db),
             1 => upgrade_from_v1(db),
+            2 => upgrade_from_v2(db),
+            3 => upgrade_from_v3(db),
             _ => Err(Error::IncompatibleVersion(version)),
         }
     }
@@ -193,6 +231,20 @@ fn upgrade_from_v1(db: &Connection) -> Result<()> {
     Ok(())
 }
 
+fn upgrade_from_v2(db: &Connection) -> Result<()> {
+    // v3 added the form history and prefs tables. Every statement in the
+    // shared schema is `IF NOT EXISTS`, so running it again only creates
+    // the new tables.
+    db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+    Ok(())
+}
+
+fn upgrade_from_v3(db: &Connection) -> Result<()> {
+    // v4 added the merge log.
+    db.execute_batch(CREATE_MERGE_LOG_SQL)?;
+    Ok(())
+}
+
 pub fn create_empty_sync_temp_tables(db: &Connection) -> Result<()> {
     log::debug!("Initializing sync temp tables");
     db.execute_batch(CREATE_SYNC_TEMP_TABLES_SQL)?;
@@ -217,6 +269,8 @@ mod tests {
         let db = new_mem_db();
         db.execute_batch(CREATE_SHARED_SCHEMA_SQL)
             .expect("should allow running main schema creation twice");
+        db.execute_batch(CREATE_MERGE_LOG_SQL)
+            .expect("should allow running merge log creation twice");
         // sync tables aren't created by default, so do it twice here.
         db.execute_batch(CREATE_SYNC_TEMP_TABLES_SQL)
             .expect("should allow running sync temp tables first time");
@@ -299,4 +353,44 @@ mod tests {
         db.execute("UPDATE credit_cards_data SET cc_number_enc='x'", [])
             .expect_err("cc_number_enc should be invalid");
     }
+
+    #[test]
+    fn test_upgrade_version_2() {
+        let db_file = MigratedDatabaseFile::new(AutofillConnectionInitializer, CREATE_V1_DB);
+        db_file.upgrade_to(2);
+        let select_new_tables = "
+            SELECT guid FROM form_history_data;
+            SELECT guid FROM form_history_tombstones;
+            SELECT guid FROM prefs_data;
+        ";
+        db_file
+            .open()
+            .execute_batch(select_new_tables)
+            .expect_err("v2 shouldn't have the form history and prefs tables");
+
+        db_file.upgrade_to(3);
+
+        db_file
+            .open()
+            .execute_batch(select_new_tables)
+            .expect("select should now work");
+    }
+
+    #[test]
+    fn test_upgrade_version_3() {
+        let db_file = MigratedDatabaseFile::new(AutofillConnectionInitializer, CREATE_V1_DB);
+        db_file.upgrade_to(3);
+        let select_merge_log = "SELECT collection, guid, fields, winner, timestamp FROM merge_log";
+        db_file
+            .open()
+            .execute_batch(select_merge_log)
+            .expect_err("v3 shouldn't have the merge log");
+
+        db_file.upgrade_to(4);
+
+        db_file
+            .open()
+            .execute_batch(select_merge_log)
+            .expect("select should now work");
+    }
 }
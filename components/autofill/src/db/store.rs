@@ -4,7 +4,9 @@
 
 use crate::db::models::address::{Address, UpdatableAddressFields};
 use crate::db::models::credit_card::{CreditCard, UpdatableCreditCardFields};
-use crate::db::{addresses, credit_cards, AutofillDb};
+use crate::db::models::form_history::FormHistoryEntry;
+use crate::db::models::prefs::SyncedPref;
+use crate::db::{addresses, credit_cards, form_history, merge_log, prefs, AutofillDb};
 use crate::error::*;
 use error_support::handle_error;
 use rusqlite::{
@@ -15,6 +17,7 @@ use sql_support::{self, ConnExt};
 use std::path::Path;
 use std::sync::{Arc, Mutex, Weak};
 use sync15::engine::{SyncEngine, SyncEngineId};
+use sync15::MergeLogEntry;
 use sync_guid::Guid;
 
 // Our "sync manager" will use whatever is stashed here.
@@ -36,6 +39,8 @@ pub fn get_registered_sync_engine(engine_id: &SyncEngineId) -> Option<Box<dyn Sy
             SyncEngineId::CreditCards => {
                 Some(Box::new(crate::sync::credit_card::create_engine(store)))
             }
+            SyncEngineId::Forms => Some(Box::new(crate::sync::form_history::create_engine(store))),
+            SyncEngineId::Prefs => Some(Box::new(crate::sync::prefs::create_engine(store))),
             // panicing here seems reasonable - it's a static error if this
             // it hit, not something that runtime conditions can influence.
             _ => unreachable!("can't provide unknown engine: {}", engine_id),
@@ -151,6 +156,56 @@ impl Store {
         addresses::touch(&self.db.lock().unwrap().writer, &Guid::new(&guid))
     }
 
+    #[handle_error(Error)]
+    pub fn add_form_history_entry(
+        &self,
+        field_name: String,
+        value: String,
+    ) -> ApiResult<FormHistoryEntry> {
+        Ok(form_history::add_form_history_entry(
+            &self.db.lock().unwrap().writer,
+            &field_name,
+            &value,
+        )?
+        .into())
+    }
+
+    #[handle_error(Error)]
+    pub fn get_form_history(&self, field_name: String) -> ApiResult<Vec<FormHistoryEntry>> {
+        let entries = form_history::get_form_history(&self.db.lock().unwrap().writer, &field_name)?
+            .into_iter()
+            .map(|x| x.into())
+            .collect();
+        Ok(entries)
+    }
+
+    #[handle_error(Error)]
+    pub fn delete_form_history_entry(&self, guid: String) -> ApiResult<bool> {
+        form_history::delete_form_history_entry(&self.db.lock().unwrap().writer, &Guid::new(&guid))
+    }
+
+    #[handle_error(Error)]
+    pub fn configure_synced_prefs(&self, app_id: String, allowlist: Vec<String>) -> ApiResult<()> {
+        prefs::configure_synced_prefs(&self.db.lock().unwrap().writer, &app_id, allowlist)
+    }
+
+    #[handle_error(Error)]
+    pub fn set_synced_pref(&self, pref: SyncedPref) -> ApiResult<()> {
+        prefs::set_synced_pref(&self.db.lock().unwrap().writer, &pref)
+    }
+
+    #[handle_error(Error)]
+    pub fn get_synced_prefs(&self) -> ApiResult<Vec<SyncedPref>> {
+        Ok(prefs::get_prefs(&self.db.lock().unwrap().writer)?
+            .map(|prefs| prefs.synced_prefs())
+            .unwrap_or_default())
+    }
+
+    #[handle_error(Error)]
+    pub fn get_merge_log(&self) -> ApiResult<Vec<MergeLogEntry>> {
+        merge_log::get_merge_log(&self.db.lock().unwrap().writer)
+    }
+
     #[handle_error(Error)]
     pub fn scrub_encrypted_data(self: Arc<Self>) -> ApiResult<()> {
         // scrub the data on disk
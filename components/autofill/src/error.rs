@@ -65,6 +65,12 @@ pub enum Error {
 
     #[error("No record with guid exists: {0}")]
     NoSuchRecord(String),
+
+    #[error("Pref isn't in the synced prefs allowlist: {0}")]
+    PrefNotSynced(String),
+
+    #[error("The `winner` column in the merge log has an illegal value: {0}")]
+    BadMergeWinner(String),
 }
 
 // Define how our internal errors are handled and converted to external errors
@@ -126,6 +132,20 @@ impl GetErrorHandling for Error {
                 ErrorHandling::convert(AutofillApiError::NoSuchRecord { guid: guid.clone() })
                     .log_warning()
             }
+
+            Self::PrefNotSynced(name) => {
+                ErrorHandling::convert(AutofillApiError::UnexpectedAutofillApiError {
+                    reason: format!("Pref isn't synced: {name}"),
+                })
+                .log_warning()
+            }
+
+            Self::BadMergeWinner(winner) => {
+                ErrorHandling::convert(AutofillApiError::UnexpectedAutofillApiError {
+                    reason: format!("Invalid merge log winner: {winner}"),
+                })
+                .report_error("autofill-bad-merge-winner")
+            }
         }
     }
 }
@@ -17,8 +17,11 @@ pub use crate::db::store::get_registered_sync_engine;
 // Expose stuff needed by the uniffi generated code.
 use crate::db::models::address::*;
 use crate::db::models::credit_card::*;
+use crate::db::models::form_history::*;
+use crate::db::models::prefs::*;
 use crate::db::store::Store;
 use crate::encryption::{create_autofill_key, decrypt_string, encrypt_string};
 pub use error::{ApiResult, AutofillApiError, Error, Result};
+use sync15::MergeLogEntry;
 
 uniffi::include_scaffolding!("autofill");
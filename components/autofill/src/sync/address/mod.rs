@@ -200,18 +200,92 @@ impl SyncRecord for InternalAddress {
 
         merged_record.guid = incoming.guid.clone();
 
-        sync_merge_field_check!(given_name, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(additional_name, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(family_name, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(organization, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(street_address, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(address_level3, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(address_level2, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(address_level1, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(postal_code, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(country, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(tel, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(email, incoming, local, mirror, merged_record);
+        let mut conflicts = vec![];
+        sync_merge_field_check!(
+            given_name,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            additional_name,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            family_name,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            organization,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            street_address,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            address_level3,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            address_level2,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            address_level1,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            postal_code,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(country, incoming, local, mirror, merged_record, conflicts);
+        sync_merge_field_check!(tel, incoming, local, mirror, merged_record, conflicts);
+        sync_merge_field_check!(email, incoming, local, mirror, merged_record, conflicts);
+
+        if !conflicts.is_empty() {
+            // There are conflicting differences, so we "fork" the record - we
+            // will end up giving the local one a new guid and save the remote
+            // one with its incoming ID.
+            return MergeResult::Forked {
+                forked: get_forked_record(local.clone()),
+                conflicts,
+            };
+        }
 
         merged_record.metadata = incoming.metadata;
         merged_record
@@ -220,6 +294,7 @@ impl SyncRecord for InternalAddress {
 
         MergeResult::Merged {
             merged: merged_record,
+            conflicts: vec![],
         }
     }
 }
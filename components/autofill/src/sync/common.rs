@@ -138,6 +138,8 @@ pub(super) fn common_mirror_staged_records(
 // A macro for our record merge implementation.
 // We allow all "common" fields from the sub-types to be getters on the
 // InsertableItem type.
+// Fields that changed differently on both sides are added to `$conflicts`,
+// so the caller can fork the record once every field has been checked.
 // Macros don't have fine-grained visibility and is visible to the entire
 // crate, so we give it a very specific name.
 #[macro_export]
@@ -146,7 +148,8 @@ macro_rules! sync_merge_field_check {
     $incoming:ident,
     $local:ident,
     $mirror:ident,
-    $merged_record:ident
+    $merged_record:ident,
+    $conflicts:ident
     ) => {
         let incoming_field = &$incoming.$field_name;
         let local_field = &$local.$field_name;
@@ -172,12 +175,8 @@ macro_rules! sync_merge_field_check {
         } else if should_use_local {
             $merged_record.$field_name = local_field.clone();
         } else {
-            // There are conflicting differences, so we "fork" the record - we
-            // will end up giving the local one a new guid and save the remote
-            // one with its incoming ID.
-            return MergeResult::Forked {
-                forked: get_forked_record($local.clone()),
-            };
+            // There are conflicting differences, so we'll "fork" the record.
+            $conflicts.push(stringify!($field_name).to_string());
         }
     };
 }
@@ -201,15 +201,54 @@ impl SyncRecord for InternalCreditCard {
 
         merged_record.guid = incoming.guid.clone();
 
-        sync_merge_field_check!(cc_name, incoming, local, mirror, merged_record);
+        let mut conflicts = vec![];
+        sync_merge_field_check!(cc_name, incoming, local, mirror, merged_record, conflicts);
         // XXX - It looks like this will allow us to merge a locally changed
         // cc_number_enc and remotely changed cc_number_last_4, which is nonsensical.
         // Given sync itself is populating this it needs more thought.
-        sync_merge_field_check!(cc_number_enc, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(cc_number_last_4, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(cc_exp_month, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(cc_exp_year, incoming, local, mirror, merged_record);
-        sync_merge_field_check!(cc_type, incoming, local, mirror, merged_record);
+        sync_merge_field_check!(
+            cc_number_enc,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            cc_number_last_4,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            cc_exp_month,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(
+            cc_exp_year,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(cc_type, incoming, local, mirror, merged_record, conflicts);
+
+        if !conflicts.is_empty() {
+            // There are conflicting differences, so we "fork" the record - we
+            // will end up giving the local one a new guid and save the remote
+            // one with its incoming ID.
+            return MergeResult::Forked {
+                forked: get_forked_record(local.clone()),
+                conflicts,
+            };
+        }
 
         merged_record.metadata = incoming.metadata;
         merged_record
@@ -218,6 +257,7 @@ impl SyncRecord for InternalCreditCard {
 
         MergeResult::Merged {
             merged: merged_record,
+            conflicts: vec![],
         }
     }
 }
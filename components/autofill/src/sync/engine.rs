@@ -3,15 +3,19 @@
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use super::{plan_incoming, ProcessIncomingRecordImpl, ProcessOutgoingRecordImpl, SyncRecord};
+use crate::db::merge_log::add_merge_log_entry;
 use crate::error::*;
 use crate::Store;
 use rusqlite::{
     types::{FromSql, ToSql},
     Connection, Transaction,
 };
+use std::cell::Cell;
 use std::sync::Arc;
-use sync15::bso::{IncomingBso, OutgoingBso};
-use sync15::engine::{CollSyncIds, CollectionRequest, EngineSyncAssociation, SyncEngine};
+use sync15::bso::IncomingBso;
+use sync15::engine::{
+    CollSyncIds, CollectionRequest, EngineSyncAssociation, OutgoingRecords, SyncEngine,
+};
 use sync15::{telemetry, CollectionName, ServerTimestamp};
 use sync_guid::Guid;
 
@@ -46,6 +50,7 @@ pub struct ConfigSyncEngine<T> {
     pub(crate) store: Arc<Store>,
     pub(crate) storage_impl: Box<dyn SyncEngineStorageImpl<T>>,
     local_enc_key: Option<String>,
+    conflicts_resolved: Cell<usize>,
 }
 
 impl<T> ConfigSyncEngine<T> {
@@ -59,6 +64,7 @@ impl<T> ConfigSyncEngine<T> {
             store,
             storage_impl,
             local_enc_key: None,
+            conflicts_resolved: Cell::new(0),
         }
     }
     fn put_meta(&self, conn: &Connection, tail: &str, value: &dyn ToSql) -> Result<()> {
@@ -125,11 +131,11 @@ impl<T: SyncRecord + std::fmt::Debug> SyncEngine for ConfigSyncEngine<T> {
         Ok(())
     }
 
-    fn apply(
+    fn apply_chunked(
         &self,
         timestamp: ServerTimestamp,
         _telem: &mut telemetry::Engine,
-    ) -> anyhow::Result<Vec<OutgoingBso>> {
+    ) -> anyhow::Result<OutgoingRecords<'_>> {
         let db = &self.store.db.lock().unwrap();
         let signal = db.begin_interrupt_scope()?;
         let tx = db.writer.unchecked_transaction()?;
@@ -137,10 +143,15 @@ impl<T: SyncRecord + std::fmt::Debug> SyncEngine for ConfigSyncEngine<T> {
         let outgoing_impl = self.storage_impl.get_outgoing_impl(&self.local_enc_key)?;
 
         // Get "states" for each record...
+        let mut conflicts_resolved = 0;
         for state in incoming_impl.fetch_incoming_states(&tx)? {
             signal.err_if_interrupted()?;
             // Finally get a "plan" and apply it.
             let action = plan_incoming(&*incoming_impl, &tx, state)?;
+            if let Some(entry) = action.merge_log_entry(&self.config.collection) {
+                add_merge_log_entry(&tx, &entry)?;
+                conflicts_resolved += 1;
+            }
             super::apply_incoming_action(&*incoming_impl, &tx, action)?;
         }
 
@@ -158,7 +169,8 @@ impl<T: SyncRecord + std::fmt::Debug> SyncEngine for ConfigSyncEngine<T> {
         // doesn't require the transaction to stay alive, so we commit now and start a new
         // transaction once complete
         tx.commit()?;
-        Ok(outgoing)
+        self.conflicts_resolved.set(conflicts_resolved);
+        Ok(outgoing.into())
     }
 
     fn set_uploaded(&self, new_timestamp: ServerTimestamp, ids: Vec<Guid>) -> anyhow::Result<()> {
@@ -227,6 +239,10 @@ impl<T: SyncRecord + std::fmt::Debug> SyncEngine for ConfigSyncEngine<T> {
         Ok(())
     }
 
+    fn conflicts_resolved(&self) -> usize {
+        self.conflicts_resolved.get()
+    }
+
     fn wipe(&self) -> anyhow::Result<()> {
         log::warn!("not implemented as there isn't a valid use case for it");
         Ok(())
@@ -0,0 +1,334 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use super::FormHistoryPayload;
+use crate::db::form_history::{
+    add_internal_form_history_entry, update_internal_form_history_entry,
+};
+use crate::db::models::form_history::InternalFormHistoryEntry;
+use crate::db::schema::FORM_HISTORY_COMMON_COLS;
+use crate::error::*;
+use crate::sync::common::*;
+use crate::sync::{
+    IncomingBso, IncomingContent, IncomingEnvelope, IncomingKind, IncomingState, LocalRecordInfo,
+    ProcessIncomingRecordImpl, ServerTimestamp, SyncRecord,
+};
+use interrupt_support::Interruptee;
+use rusqlite::{named_params, Transaction};
+use sql_support::ConnExt;
+use sync_guid::Guid as SyncGuid;
+
+// Takes a raw payload, as stored in our database, and returns an
+// InternalFormHistoryEntry or a tombstone.
+fn raw_payload_to_incoming(
+    id: SyncGuid,
+    raw: String,
+) -> Result<IncomingContent<InternalFormHistoryEntry>> {
+    let bso = IncomingBso {
+        envelope: IncomingEnvelope {
+            id,
+            modified: ServerTimestamp::default(),
+            sortindex: None,
+            ttl: None,
+        },
+        payload: raw,
+    };
+    let payload_content = bso.into_content::<FormHistoryPayload>();
+    Ok(match payload_content.kind {
+        IncomingKind::Content(content) => IncomingContent {
+            envelope: payload_content.envelope,
+            kind: IncomingKind::Content(InternalFormHistoryEntry::from_payload(content)?),
+        },
+        IncomingKind::Tombstone => IncomingContent {
+            envelope: payload_content.envelope,
+            kind: IncomingKind::Tombstone,
+        },
+        IncomingKind::Malformed => IncomingContent {
+            envelope: payload_content.envelope,
+            kind: IncomingKind::Malformed,
+        },
+    })
+}
+
+pub(super) struct IncomingFormHistoryImpl {}
+
+impl ProcessIncomingRecordImpl for IncomingFormHistoryImpl {
+    type Record = InternalFormHistoryEntry;
+
+    /// The first step in the "apply incoming" process - stage the records
+    fn stage_incoming(
+        &self,
+        tx: &Transaction<'_>,
+        incoming: Vec<IncomingBso>,
+        signal: &dyn Interruptee,
+    ) -> Result<()> {
+        let to_stage = incoming
+            .into_iter()
+            .map(|bso| (bso.envelope.id, bso.payload, bso.envelope.modified))
+            .collect();
+        common_stage_incoming_records(tx, "form_history_sync_staging", to_stage, signal)
+    }
+
+    fn finish_incoming(&self, tx: &Transaction<'_>) -> Result<()> {
+        common_mirror_staged_records(tx, "form_history_sync_staging", "form_history_mirror")
+    }
+
+    /// The second step in the "apply incoming" process for syncing form history.
+    /// Incoming items are retrieved from the temp tables, deserialized, and
+    /// assigned `IncomingState` values.
+    fn fetch_incoming_states(
+        &self,
+        tx: &Transaction<'_>,
+    ) -> Result<Vec<IncomingState<Self::Record>>> {
+        let sql = "
+        SELECT
+            s.guid as guid,
+            l.guid as l_guid,
+            t.guid as t_guid,
+            s.payload as s_payload,
+            m.payload as m_payload,
+            l.field_name,
+            l.value,
+            l.time_created,
+            l.time_last_used,
+            l.time_last_modified,
+            l.times_used,
+            l.sync_change_counter
+        FROM temp.form_history_sync_staging s
+        LEFT JOIN form_history_mirror m ON s.guid = m.guid
+        LEFT JOIN form_history_data l ON s.guid = l.guid
+        LEFT JOIN form_history_tombstones t ON s.guid = t.guid";
+
+        tx.query_rows_and_then(sql, [], |row| -> Result<IncomingState<Self::Record>> {
+            // the 'guid' and 's_payload' rows must be non-null.
+            let guid: SyncGuid = row.get("guid")?;
+            let incoming = raw_payload_to_incoming(guid.clone(), row.get("s_payload")?)?;
+            Ok(IncomingState {
+                incoming,
+                local: match row.get_unwrap::<_, Option<String>>("l_guid") {
+                    Some(l_guid) => {
+                        assert_eq!(l_guid, guid);
+                        // local record exists, check the state.
+                        let record = InternalFormHistoryEntry::from_row(row)?;
+                        let has_changes = record.metadata().sync_change_counter != 0;
+                        if has_changes {
+                            LocalRecordInfo::Modified { record }
+                        } else {
+                            LocalRecordInfo::Unmodified { record }
+                        }
+                    }
+                    None => {
+                        // no local record - maybe a tombstone?
+                        match row.get::<_, Option<String>>("t_guid")? {
+                            Some(t_guid) => {
+                                assert_eq!(guid, t_guid);
+                                LocalRecordInfo::Tombstone { guid: guid.clone() }
+                            }
+                            None => LocalRecordInfo::Missing,
+                        }
+                    }
+                },
+                mirror: {
+                    match row.get::<_, Option<String>>("m_payload")? {
+                        Some(m_payload) => {
+                            // a tombstone in the mirror can be treated as though it's missing.
+                            raw_payload_to_incoming(guid, m_payload)?.content()
+                        }
+                        None => None,
+                    }
+                },
+            })
+        })
+    }
+
+    /// Returns a local entry with the same field name and value as the given
+    /// incoming record, but a different guid - typically because the user
+    /// entered the same value on this device and another before syncing.
+    fn get_local_dupe(
+        &self,
+        tx: &Transaction<'_>,
+        incoming: &Self::Record,
+    ) -> Result<Option<Self::Record>> {
+        let sql = format!(
+            "
+            SELECT
+                {common_cols},
+                sync_change_counter
+            FROM form_history_data
+            WHERE
+                -- `guid <> :guid` is a pre-condition for this being called, but...
+                guid <> :guid
+                -- only non-synced records are candidates, which means can't already be in the mirror.
+                AND guid NOT IN (
+                    SELECT guid
+                    FROM form_history_mirror
+                )
+                AND field_name == :field_name
+                AND value == :value
+            LIMIT 1",
+            common_cols = FORM_HISTORY_COMMON_COLS
+        );
+
+        let params = named_params! {
+            ":guid": incoming.guid,
+            ":field_name": incoming.field_name,
+            ":value": incoming.value,
+        };
+
+        tx.try_query_row(
+            &sql,
+            params,
+            |row| -> Result<_> { Ok(Self::Record::from_row(row)?) },
+            true,
+        )
+    }
+
+    fn update_local_record(
+        &self,
+        tx: &Transaction<'_>,
+        new_record: Self::Record,
+        flag_as_changed: bool,
+    ) -> Result<()> {
+        update_internal_form_history_entry(tx, &new_record, flag_as_changed)
+    }
+
+    fn insert_local_record(&self, tx: &Transaction<'_>, new_record: Self::Record) -> Result<()> {
+        add_internal_form_history_entry(tx, &new_record)
+    }
+
+    /// Changes the guid of the local record for the given `old_guid` to the given `new_guid` used
+    /// for the `HasLocalDupe` incoming state, and mark the item as dirty.
+    /// We also update the mirror record if it exists in forking scenarios
+    fn change_record_guid(
+        &self,
+        tx: &Transaction<'_>,
+        old_guid: &SyncGuid,
+        new_guid: &SyncGuid,
+    ) -> Result<()> {
+        common_change_guid(
+            tx,
+            "form_history_data",
+            "form_history_mirror",
+            old_guid,
+            new_guid,
+        )
+    }
+
+    fn remove_record(&self, tx: &Transaction<'_>, guid: &SyncGuid) -> Result<()> {
+        common_remove_record(tx, "form_history_data", guid)
+    }
+
+    fn remove_tombstone(&self, tx: &Transaction<'_>, guid: &SyncGuid) -> Result<()> {
+        common_remove_record(tx, "form_history_tombstones", guid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::test::new_syncable_mem_db;
+    use super::*;
+    use crate::db::form_history::get_form_history_entry;
+    use crate::sync::common::tests::*;
+
+    use serde_json::{json, Value};
+
+    impl InternalFormHistoryEntry {
+        fn into_test_incoming_bso(self) -> IncomingBso {
+            IncomingBso::from_test_content(self.into_payload().expect("is json"))
+        }
+    }
+
+    fn test_json_record(guid_prefix: char) -> Value {
+        json! {{
+            "id": expand_test_guid(guid_prefix),
+            "name": "email",
+            "value": format!("{}@example.com", guid_prefix),
+        }}
+    }
+
+    fn test_record(guid_prefix: char) -> InternalFormHistoryEntry {
+        let json = test_json_record(guid_prefix);
+        let payload = serde_json::from_value(json).unwrap();
+        InternalFormHistoryEntry::from_payload(payload).expect("should be valid")
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        let payload = serde_json::from_value(json! {{
+            "id": expand_test_guid('A'),
+            "name": "email",
+        }})
+        .unwrap();
+        assert!(matches!(
+            InternalFormHistoryEntry::from_payload(payload),
+            Err(Error::InvalidSyncPayload(_))
+        ));
+    }
+
+    #[test]
+    fn test_change_record_guid() -> Result<()> {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction()?;
+        let ri = IncomingFormHistoryImpl {};
+
+        ri.insert_local_record(&tx, test_record('C'))?;
+
+        ri.change_record_guid(
+            &tx,
+            &SyncGuid::new(&expand_test_guid('C')),
+            &SyncGuid::new(&expand_test_guid('B')),
+        )?;
+        tx.commit()?;
+        assert!(get_form_history_entry(&db.writer, &expand_test_guid('C').into()).is_err());
+        assert!(get_form_history_entry(&db.writer, &expand_test_guid('B').into()).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_local_dupe() -> Result<()> {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction()?;
+        let ri = IncomingFormHistoryImpl {};
+
+        let mut local = test_record('C');
+        local.guid = SyncGuid::new(&expand_test_guid('D'));
+        ri.insert_local_record(&tx, local)?;
+
+        let dupe = ri
+            .get_local_dupe(&tx, &test_record('C'))?
+            .expect("should find the dupe");
+        assert_eq!(dupe.guid, expand_test_guid('D'));
+        assert!(ri.get_local_dupe(&tx, &test_record('E'))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_incoming() {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction().expect("should get tx");
+        let ri = IncomingFormHistoryImpl {};
+        let record = test_record('C');
+        let bso = record.clone().into_test_incoming_bso();
+        do_test_incoming_same(&ri, &tx, record, bso);
+    }
+
+    #[test]
+    fn test_incoming_tombstone() {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction().expect("should get tx");
+        let ri = IncomingFormHistoryImpl {};
+        do_test_incoming_tombstone(&ri, &tx, test_record('C'));
+    }
+
+    #[test]
+    fn test_staged_to_mirror() {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction().expect("should get tx");
+        let ri = IncomingFormHistoryImpl {};
+        let record = test_record('C');
+        let bso = record.clone().into_test_incoming_bso();
+        do_test_staged_to_mirror(&ri, &tx, record, bso, "form_history_mirror");
+    }
+}
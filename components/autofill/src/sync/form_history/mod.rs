@@ -0,0 +1,186 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+pub mod incoming;
+pub mod outgoing;
+
+use super::engine::{ConfigSyncEngine, EngineConfig, SyncEngineStorageImpl};
+use super::{
+    MergeResult, Metadata, ProcessIncomingRecordImpl, ProcessOutgoingRecordImpl, SyncRecord,
+    UnknownFields,
+};
+use crate::db::models::form_history::InternalFormHistoryEntry;
+use crate::error::*;
+use crate::sync_merge_field_check;
+use incoming::IncomingFormHistoryImpl;
+use outgoing::OutgoingFormHistoryImpl;
+use rusqlite::Transaction;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use sync_guid::Guid;
+use types::Timestamp;
+
+// The engine.
+pub(crate) fn create_engine(
+    store: Arc<crate::Store>,
+) -> ConfigSyncEngine<InternalFormHistoryEntry> {
+    ConfigSyncEngine::new(
+        EngineConfig {
+            namespace: "forms".to_string(),
+            collection: "forms".into(),
+        },
+        store,
+        Box::new(FormHistoryEngineStorageImpl {}),
+    )
+}
+
+pub(super) struct FormHistoryEngineStorageImpl {}
+
+impl SyncEngineStorageImpl<InternalFormHistoryEntry> for FormHistoryEngineStorageImpl {
+    fn get_incoming_impl(
+        &self,
+        enc_key: &Option<String>,
+    ) -> Result<Box<dyn ProcessIncomingRecordImpl<Record = InternalFormHistoryEntry>>> {
+        assert!(enc_key.is_none());
+        Ok(Box::new(IncomingFormHistoryImpl {}))
+    }
+
+    fn reset_storage(&self, tx: &Transaction<'_>) -> Result<()> {
+        tx.execute_batch(
+            "DELETE FROM form_history_mirror;
+            DELETE FROM form_history_tombstones;",
+        )?;
+        Ok(())
+    }
+
+    fn get_outgoing_impl(
+        &self,
+        enc_key: &Option<String>,
+    ) -> Result<Box<dyn ProcessOutgoingRecordImpl<Record = InternalFormHistoryEntry>>> {
+        assert!(enc_key.is_none());
+        Ok(Box::new(OutgoingFormHistoryImpl {}))
+    }
+}
+
+// This struct is a representation of what's stored on the sync server for
+// non-tombstone records. Desktop only syncs the field name and value.
+#[derive(Default, Deserialize, Serialize)]
+pub struct FormHistoryPayload {
+    id: Guid,
+    name: String,
+    value: String,
+    // Fields that the current schema did not expect, we store them only internally
+    // to round-trip them back to sync without processing them in any way
+    #[serde(flatten)]
+    unknown_fields: UnknownFields,
+}
+
+impl InternalFormHistoryEntry {
+    fn from_payload(p: FormHistoryPayload) -> Result<Self> {
+        if p.name.is_empty() || p.value.is_empty() {
+            return Err(Error::InvalidSyncPayload(
+                "form history entries need a name and value".to_string(),
+            ));
+        }
+        // The usage metadata isn't synced, so an entry we learn about from
+        // the server counts as being used once, now - which is also what
+        // desktop does.
+        let now = Timestamp::now();
+        Ok(InternalFormHistoryEntry {
+            guid: p.id,
+            field_name: p.name,
+            value: p.value,
+            metadata: Metadata {
+                time_created: now,
+                time_last_used: now,
+                time_last_modified: now,
+                times_used: 1,
+                sync_change_counter: 0,
+            },
+        })
+    }
+
+    fn into_payload(self) -> Result<FormHistoryPayload> {
+        Ok(FormHistoryPayload {
+            id: self.guid,
+            name: self.field_name,
+            value: self.value,
+            unknown_fields: Default::default(),
+        })
+    }
+}
+
+impl SyncRecord for InternalFormHistoryEntry {
+    fn record_name() -> &'static str {
+        "FormHistoryEntry"
+    }
+
+    fn id(&self) -> &Guid {
+        &self.guid
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Performs a three-way merge between an incoming, local, and mirror record.
+    /// Desktop never changes the name or value of an entry, so in practice
+    /// this only forks if another client did something unexpected.
+    fn merge(incoming: &Self, local: &Self, mirror: &Option<Self>) -> MergeResult<Self> {
+        let mut merged_record: Self = Default::default();
+        // guids must be identical
+        assert_eq!(incoming.guid, local.guid);
+
+        if let Some(m) = mirror {
+            assert_eq!(incoming.guid, m.guid)
+        };
+
+        merged_record.guid = incoming.guid.clone();
+
+        let mut conflicts = vec![];
+        sync_merge_field_check!(
+            field_name,
+            incoming,
+            local,
+            mirror,
+            merged_record,
+            conflicts
+        );
+        sync_merge_field_check!(value, incoming, local, mirror, merged_record, conflicts);
+
+        if !conflicts.is_empty() {
+            // There are conflicting differences, so we "fork" the record - we
+            // will end up giving the local one a new guid and save the remote
+            // one with its incoming ID.
+            return MergeResult::Forked {
+                forked: get_forked_record(local.clone()),
+                conflicts,
+            };
+        }
+
+        // The usage metadata isn't synced, so it's always the local metadata.
+        merged_record.metadata = local.metadata;
+
+        MergeResult::Merged {
+            merged: merged_record,
+            conflicts: vec![],
+        }
+    }
+}
+
+/// Returns a record with the given local record's data but with a new guid and
+/// fresh sync metadata.
+fn get_forked_record(local_record: InternalFormHistoryEntry) -> InternalFormHistoryEntry {
+    let mut local_record_data = local_record;
+    local_record_data.guid = Guid::random();
+    local_record_data.metadata.time_last_modified = Timestamp::now();
+    local_record_data.metadata.sync_change_counter = 1;
+
+    local_record_data
+}
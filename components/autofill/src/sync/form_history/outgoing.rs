@@ -0,0 +1,242 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::db::models::form_history::InternalFormHistoryEntry;
+use crate::db::schema::FORM_HISTORY_COMMON_COLS;
+use crate::error::*;
+use crate::sync::{common::*, form_history::FormHistoryPayload};
+use crate::sync::{OutgoingBso, ProcessOutgoingRecordImpl};
+use rusqlite::{Row, Transaction};
+use sync_guid::Guid as SyncGuid;
+
+const DATA_TABLE_NAME: &str = "form_history_data";
+const MIRROR_TABLE_NAME: &str = "form_history_mirror";
+const STAGING_TABLE_NAME: &str = "form_history_sync_outgoing_staging";
+
+pub(super) struct OutgoingFormHistoryImpl {}
+
+impl ProcessOutgoingRecordImpl for OutgoingFormHistoryImpl {
+    type Record = InternalFormHistoryEntry;
+
+    /// Gets the local records that have unsynced changes or don't have corresponding mirror
+    /// records and upserts them to the mirror table
+    fn fetch_outgoing_records(&self, tx: &Transaction<'_>) -> anyhow::Result<Vec<OutgoingBso>> {
+        let data_sql = format!(
+            "SELECT
+                l.{common_cols},
+                m.payload,
+                l.sync_change_counter
+            FROM form_history_data l
+            LEFT JOIN form_history_mirror m
+            ON l.guid = m.guid
+            WHERE sync_change_counter > 0
+                OR l.guid NOT IN (
+                    SELECT m.guid
+                    FROM form_history_mirror m
+                )",
+            common_cols = FORM_HISTORY_COMMON_COLS,
+        );
+        let record_from_data_row: &dyn Fn(&Row<'_>) -> Result<(OutgoingBso, i64)> = &|row| {
+            let mut record = InternalFormHistoryEntry::from_row(row)?.into_payload()?;
+            // If the server had unknown fields we fetch it and add it to the record
+            // we'll be uploading
+            if let Some(s) = row.get::<_, Option<String>>("payload")? {
+                let mirror_payload: FormHistoryPayload = serde_json::from_str(&s)?;
+                record.unknown_fields = mirror_payload.unknown_fields;
+            };
+
+            Ok((
+                OutgoingBso::from_content_with_id(record)?,
+                row.get::<_, i64>("sync_change_counter")?,
+            ))
+        };
+
+        let tombstones_sql = "SELECT guid FROM form_history_tombstones";
+
+        let staging_records = common_get_outgoing_staging_records(
+            tx,
+            &data_sql,
+            tombstones_sql,
+            record_from_data_row,
+        )?
+        .into_iter()
+        .map(|(bso, change_counter)| (bso.envelope.id, bso.payload, change_counter))
+        .collect::<Vec<_>>();
+        common_save_outgoing_records(tx, STAGING_TABLE_NAME, staging_records)?;
+
+        // return outgoing changes
+        Ok(
+            common_get_outgoing_records(tx, &data_sql, tombstones_sql, record_from_data_row)?
+                .into_iter()
+                .map(|(bso, _change_counter)| bso)
+                .collect::<Vec<OutgoingBso>>(),
+        )
+    }
+
+    fn finish_synced_items(
+        &self,
+        tx: &Transaction<'_>,
+        records_synced: Vec<SyncGuid>,
+    ) -> anyhow::Result<()> {
+        common_finish_synced_items(
+            tx,
+            DATA_TABLE_NAME,
+            MIRROR_TABLE_NAME,
+            STAGING_TABLE_NAME,
+            records_synced,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::form_history::add_internal_form_history_entry;
+    use crate::sync::{common::tests::*, test::new_syncable_mem_db, UnknownFields};
+    use rusqlite::Connection;
+    use serde_json::{json, Map, Value};
+    use types::Timestamp;
+
+    fn test_insert_mirror_record(
+        conn: &Connection,
+        entry: InternalFormHistoryEntry,
+        unknown_fields: UnknownFields,
+    ) {
+        let guid = entry.guid.clone();
+        let mut payload = entry.into_payload().unwrap();
+        payload.unknown_fields = unknown_fields;
+        let payload = serde_json::to_string(&payload).expect("is json");
+        conn.execute(
+            "INSERT OR IGNORE INTO form_history_mirror (guid, payload)
+             VALUES (:guid, :payload)",
+            rusqlite::named_params! {
+                ":guid": guid,
+                ":payload": &payload,
+            },
+        )
+        .expect("should insert");
+    }
+
+    fn test_record(guid_prefix: char) -> InternalFormHistoryEntry {
+        let json = json! {{
+            "id": expand_test_guid(guid_prefix),
+            "name": "email",
+            "value": format!("{}@example.com", guid_prefix),
+        }};
+        let payload = serde_json::from_value(json).unwrap();
+        InternalFormHistoryEntry::from_payload(payload).expect("should be valid")
+    }
+
+    #[test]
+    fn test_outgoing_never_synced() {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction().expect("should get tx");
+        let ro = OutgoingFormHistoryImpl {};
+        let test_record = test_record('C');
+
+        // create data record
+        assert!(add_internal_form_history_entry(&tx, &test_record).is_ok());
+        do_test_outgoing_never_synced(
+            &tx,
+            &ro,
+            &test_record.guid,
+            DATA_TABLE_NAME,
+            MIRROR_TABLE_NAME,
+            STAGING_TABLE_NAME,
+        );
+    }
+
+    #[test]
+    fn test_outgoing_tombstone() {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction().expect("should get tx");
+        let ro = OutgoingFormHistoryImpl {};
+        let test_record = test_record('C');
+
+        // create tombstone record
+        assert!(tx
+            .execute(
+                "INSERT INTO form_history_tombstones (
+                    guid,
+                    time_deleted
+                ) VALUES (
+                    :guid,
+                    :time_deleted
+                )",
+                rusqlite::named_params! {
+                    ":guid": test_record.guid,
+                    ":time_deleted": Timestamp::now(),
+                },
+            )
+            .is_ok());
+        do_test_outgoing_tombstone(
+            &tx,
+            &ro,
+            &test_record.guid,
+            DATA_TABLE_NAME,
+            MIRROR_TABLE_NAME,
+            STAGING_TABLE_NAME,
+        );
+    }
+
+    #[test]
+    fn test_outgoing_synced_with_no_change() {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction().expect("should get tx");
+        let ro = OutgoingFormHistoryImpl {};
+
+        // create synced record with no changes (sync_change_counter = 0)
+        let test_record = test_record('C');
+        assert!(add_internal_form_history_entry(&tx, &test_record).is_ok());
+        test_insert_mirror_record(&tx, test_record.clone(), Default::default());
+
+        do_test_outgoing_synced_with_no_change(
+            &tx,
+            &ro,
+            &test_record.guid,
+            DATA_TABLE_NAME,
+            STAGING_TABLE_NAME,
+        );
+    }
+
+    #[test]
+    fn test_outgoing_roundtrip_unknown() {
+        let mut db = new_syncable_mem_db();
+        let tx = db.transaction().expect("should get tx");
+        let ro = OutgoingFormHistoryImpl {};
+
+        // create synced record with non-zero sync_change_counter
+        let mut test_record = test_record('D');
+        let initial_change_counter_val = 2;
+        test_record.metadata.sync_change_counter = initial_change_counter_val;
+        assert!(add_internal_form_history_entry(&tx, &test_record).is_ok());
+        // put "unknown_fields" into the mirror payload to imitate the server
+        let unknown_fields: UnknownFields =
+            serde_json::from_value(json! {{ "foo": "bar", "baz": "qux"}}).unwrap();
+        test_insert_mirror_record(&tx, test_record.clone(), unknown_fields);
+        exists_with_counter_value_in_table(
+            &tx,
+            DATA_TABLE_NAME,
+            &test_record.guid,
+            initial_change_counter_val,
+        );
+
+        let outgoing = &ro.fetch_outgoing_records(&tx).unwrap();
+        // Ensure we have our unknown values for the roundtrip
+        let bso_payload: Map<String, Value> = serde_json::from_str(&outgoing[0].payload).unwrap();
+        assert_eq!(bso_payload.get("name").unwrap(), "email");
+        assert_eq!(bso_payload.get("foo").unwrap(), "bar");
+        assert_eq!(bso_payload.get("baz").unwrap(), "qux");
+        do_test_outgoing_synced_with_local_change(
+            &tx,
+            &ro,
+            &test_record.guid,
+            DATA_TABLE_NAME,
+            MIRROR_TABLE_NAME,
+            STAGING_TABLE_NAME,
+        );
+    }
+}
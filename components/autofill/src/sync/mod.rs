@@ -7,13 +7,15 @@ pub mod address;
 mod common;
 pub mod credit_card;
 pub mod engine;
+pub mod form_history;
+pub mod prefs;
 
 pub(crate) use crate::db::models::Metadata;
 use crate::error::Result;
 use interrupt_support::Interruptee;
 use rusqlite::Transaction;
 use sync15::bso::{IncomingBso, IncomingContent, IncomingEnvelope, IncomingKind, OutgoingBso};
-use sync15::ServerTimestamp;
+use sync15::{MergeLogEntry, MergeWinner, ServerTimestamp};
 use sync_guid::Guid;
 use types::Timestamp;
 
@@ -185,11 +187,13 @@ enum LocalRecordInfo<T> {
 }
 
 // An enum for the return value from our "merge" function, which might either
-// update the record, or might fork it.
+// update the record, or might fork it. `conflicts` are the names of the fields
+// that changed differently on both sides - a merged record with conflicts took
+// the incoming values for them.
 #[derive(Debug)]
 pub enum MergeResult<T> {
-    Merged { merged: T },
-    Forked { forked: T },
+    Merged { merged: T, conflicts: Vec<String> },
+    Forked { forked: T, conflicts: Vec<String> },
 }
 
 // This ties the 3 possible records together and is what we expect the
@@ -209,28 +213,75 @@ pub struct IncomingState<T> {
 #[derive(Debug, PartialEq)]
 enum IncomingAction<T> {
     // Remove the local record with this GUID.
-    DeleteLocalRecord { guid: Guid },
+    DeleteLocalRecord {
+        guid: Guid,
+    },
     // Insert a new record.
-    Insert { record: T },
+    Insert {
+        record: T,
+    },
     // Update an existing record. If `was_merged` was true, then the updated
     // record isn't identical to the incoming one, so needs to be flagged as
-    // dirty.
-    Update { record: T, was_merged: bool },
+    // dirty. `conflicts` are the fields where the incoming values won.
+    Update {
+        record: T,
+        was_merged: bool,
+        conflicts: Vec<String>,
+    },
     // We forked a record because we couldn't merge it. `forked` will have
     // a new guid, while `incoming` is the unmodified version of the incoming
-    // record which we need to apply.
-    Fork { forked: T, incoming: T },
+    // record which we need to apply. `conflicts` are the fields we couldn't
+    // merge.
+    Fork {
+        forked: T,
+        incoming: T,
+        conflicts: Vec<String>,
+    },
     // An existing record with old_guid needs to be replaced with this record.
-    UpdateLocalGuid { old_guid: Guid, record: T },
+    UpdateLocalGuid {
+        old_guid: Guid,
+        record: T,
+    },
     // There's a remote tombstone, but our copy of the record is dirty. The
     // remote tombstone should be replaced with this.
-    ResurrectRemoteTombstone { record: T },
+    ResurrectRemoteTombstone {
+        record: T,
+    },
     // There's a local tombstone - it should be removed and replaced with this.
-    ResurrectLocalTombstone { record: T },
+    ResurrectLocalTombstone {
+        record: T,
+    },
     // Nothing to do.
     DoNothing,
 }
 
+impl<T: SyncRecord> IncomingAction<T> {
+    // The merge log entry for this action, if it resolved any conflicts.
+    fn merge_log_entry(&self, collection: &str) -> Option<MergeLogEntry> {
+        let (guid, conflicts, winner) = match self {
+            IncomingAction::Update {
+                record, conflicts, ..
+            } => (record.id(), conflicts, MergeWinner::Remote),
+            IncomingAction::Fork {
+                incoming,
+                conflicts,
+                ..
+            } => (incoming.id(), conflicts, MergeWinner::Both),
+            _ => return None,
+        };
+        if conflicts.is_empty() {
+            return None;
+        }
+        Some(MergeLogEntry {
+            collection: collection.to_string(),
+            guid: guid.to_string(),
+            fields: conflicts.clone(),
+            winner,
+            timestamp: Timestamp::now().as_millis_i64(),
+        })
+    }
+}
+
 /// Convert a IncomingState to an IncomingAction - this is where the "policy"
 /// lives for when we resurrect, or merge etc.
 fn plan_incoming<T: std::fmt::Debug + SyncRecord>(
@@ -296,24 +347,27 @@ fn plan_incoming<T: std::fmt::Debug + SyncRecord>(
                     IncomingAction::Update {
                         record: incoming_record,
                         was_merged: false,
+                        conflicts: vec![],
                     }
                 }
                 LocalRecordInfo::Modified {
                     record: local_record,
                 } => {
                     match SyncRecord::merge(&incoming_record, &local_record, &mirror) {
-                        MergeResult::Merged { merged } => {
+                        MergeResult::Merged { merged, conflicts } => {
                             // The record we save locally has material differences
                             // from the incoming one, so we are going to need to
                             // reupload it.
                             IncomingAction::Update {
                                 record: merged,
                                 was_merged: true,
+                                conflicts,
                             }
                         }
-                        MergeResult::Forked { forked } => IncomingAction::Fork {
+                        MergeResult::Forked { forked, conflicts } => IncomingAction::Fork {
                             forked,
                             incoming: incoming_record,
+                            conflicts,
                         },
                     }
                 }
@@ -363,10 +417,14 @@ fn apply_incoming_action<T: std::fmt::Debug + SyncRecord>(
 ) -> Result<()> {
     log::trace!("applying action: {:?}", action);
     match action {
-        IncomingAction::Update { record, was_merged } => {
+        IncomingAction::Update {
+            record, was_merged, ..
+        } => {
             rec_impl.update_local_record(tx, record, was_merged)?;
         }
-        IncomingAction::Fork { forked, incoming } => {
+        IncomingAction::Fork {
+            forked, incoming, ..
+        } => {
             // `forked` exists in the DB with the same guid as `incoming`, so fix that.
             // change_record_guid will also update the mirror (if it exists) to prevent
             // the server from overriding the forked mirror record (and losing any unknown fields)
@@ -0,0 +1,235 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use super::PrefsPayload;
+use crate::db::models::prefs::InternalPrefs;
+use crate::db::prefs::{add_internal_prefs, get_allowlist, get_prefs, update_internal_prefs};
+use crate::error::*;
+use crate::sync::common::*;
+use crate::sync::{
+    IncomingBso, IncomingContent, IncomingEnvelope, IncomingKind, IncomingState, LocalRecordInfo,
+    ProcessIncomingRecordImpl, ServerTimestamp, SyncRecord,
+};
+use interrupt_support::Interruptee;
+use rusqlite::Transaction;
+use sql_support::ConnExt;
+use sync_guid::Guid as SyncGuid;
+
+// Takes a raw payload, as stored in our database, and returns InternalPrefs
+// or a tombstone.
+fn raw_payload_to_incoming(id: SyncGuid, raw: String) -> Result<IncomingContent<InternalPrefs>> {
+    let bso = IncomingBso {
+        envelope: IncomingEnvelope {
+            id,
+            modified: ServerTimestamp::default(),
+            sortindex: None,
+            ttl: None,
+        },
+        payload: raw,
+    };
+    let payload_content = bso.into_content::<PrefsPayload>();
+    Ok(match payload_content.kind {
+        IncomingKind::Content(content) => IncomingContent {
+            envelope: payload_content.envelope,
+            kind: IncomingKind::Content(InternalPrefs::from_payload(content)?),
+        },
+        IncomingKind::Tombstone => IncomingContent {
+            envelope: payload_content.envelope,
+            kind: IncomingKind::Tombstone,
+        },
+        IncomingKind::Malformed => IncomingContent {
+            envelope: payload_content.envelope,
+            kind: IncomingKind::Malformed,
+        },
+    })
+}
+
+pub(super) struct IncomingPrefsImpl {}
+
+impl ProcessIncomingRecordImpl for IncomingPrefsImpl {
+    type Record = InternalPrefs;
+
+    /// The first step in the "apply incoming" process - stage the records.
+    /// The collection has a record for every app that syncs prefs, but we
+    /// only stage the one for this app.
+    fn stage_incoming(
+        &self,
+        tx: &Transaction<'_>,
+        incoming: Vec<IncomingBso>,
+        signal: &dyn Interruptee,
+    ) -> Result<()> {
+        let our_guid = get_prefs(tx)?.map(|prefs| prefs.guid);
+        let to_stage = incoming
+            .into_iter()
+            .filter(|bso| Some(&bso.envelope.id) == our_guid.as_ref())
+            .map(|bso| (bso.envelope.id, bso.payload, bso.envelope.modified))
+            .collect();
+        common_stage_incoming_records(tx, "prefs_sync_staging", to_stage, signal)
+    }
+
+    fn finish_incoming(&self, tx: &Transaction<'_>) -> Result<()> {
+        common_mirror_staged_records(tx, "prefs_sync_staging", "prefs_mirror")
+    }
+
+    /// The second step in the "apply incoming" process for syncing prefs.
+    /// Prefs that aren't in the allowlist are dropped from the incoming and
+    /// mirror records, so they're never applied or merged. They still
+    /// round-trip, because outgoing records start from the mirror.
+    fn fetch_incoming_states(
+        &self,
+        tx: &Transaction<'_>,
+    ) -> Result<Vec<IncomingState<Self::Record>>> {
+        let allowlist = get_allowlist(tx)?;
+        let sql = "
+        SELECT
+            s.guid as guid,
+            l.guid as l_guid,
+            s.payload as s_payload,
+            m.payload as m_payload,
+            l.prefs,
+            l.time_last_modified,
+            l.sync_change_counter
+        FROM temp.prefs_sync_staging s
+        LEFT JOIN prefs_mirror m ON s.guid = m.guid
+        LEFT JOIN prefs_data l ON s.guid = l.guid";
+
+        tx.query_rows_and_then(sql, [], |row| -> Result<IncomingState<Self::Record>> {
+            // the 'guid' and 's_payload' rows must be non-null.
+            let guid: SyncGuid = row.get("guid")?;
+            let mut incoming = raw_payload_to_incoming(guid.clone(), row.get("s_payload")?)?;
+            if let IncomingKind::Content(prefs) = &mut incoming.kind {
+                prefs.retain_allowed(&allowlist);
+            }
+            Ok(IncomingState {
+                incoming,
+                local: match row.get::<_, Option<String>>("l_guid")? {
+                    Some(l_guid) => {
+                        assert_eq!(l_guid, guid);
+                        let record = InternalPrefs::from_row(row)?;
+                        let has_changes = record.metadata().sync_change_counter != 0;
+                        if has_changes {
+                            LocalRecordInfo::Modified { record }
+                        } else {
+                            LocalRecordInfo::Unmodified { record }
+                        }
+                    }
+                    // Prefs have no tombstones.
+                    None => LocalRecordInfo::Missing,
+                },
+                mirror: {
+                    match row.get::<_, Option<String>>("m_payload")? {
+                        Some(m_payload) => {
+                            // a tombstone in the mirror can be treated as though it's missing.
+                            raw_payload_to_incoming(guid, m_payload)?
+                                .content()
+                                .map(|mut prefs| {
+                                    prefs.retain_allowed(&allowlist);
+                                    prefs
+                                })
+                        }
+                        None => None,
+                    }
+                },
+            })
+        })
+    }
+
+    /// There's only one prefs record, so there's never a dupe.
+    fn get_local_dupe(
+        &self,
+        _tx: &Transaction<'_>,
+        _incoming: &Self::Record,
+    ) -> Result<Option<Self::Record>> {
+        Ok(None)
+    }
+
+    fn update_local_record(
+        &self,
+        tx: &Transaction<'_>,
+        new_record: Self::Record,
+        flag_as_changed: bool,
+    ) -> Result<()> {
+        update_internal_prefs(tx, &new_record, flag_as_changed)
+    }
+
+    fn insert_local_record(&self, tx: &Transaction<'_>, new_record: Self::Record) -> Result<()> {
+        add_internal_prefs(tx, &new_record)
+    }
+
+    fn change_record_guid(
+        &self,
+        tx: &Transaction<'_>,
+        old_guid: &SyncGuid,
+        new_guid: &SyncGuid,
+    ) -> Result<()> {
+        common_change_guid(tx, "prefs_data", "prefs_mirror", old_guid, new_guid)
+    }
+
+    /// Prefs can't be deleted - desktop ignores tombstones for prefs records,
+    /// and so do we.
+    fn remove_record(&self, _tx: &Transaction<'_>, guid: &SyncGuid) -> Result<()> {
+        log::warn!("ignoring incoming tombstone for prefs record {}", guid);
+        Ok(())
+    }
+
+    fn remove_tombstone(&self, _tx: &Transaction<'_>, _guid: &SyncGuid) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::test::new_syncable_mem_db;
+    use super::*;
+    use crate::db::prefs::{configure_synced_prefs, prefs_record_guid};
+    use interrupt_support::NeverInterrupts;
+    use serde_json::json;
+
+    #[test]
+    fn test_stage_only_our_record() -> Result<()> {
+        let mut db = new_syncable_mem_db();
+        let ri = IncomingPrefsImpl {};
+        let ours = prefs_record_guid("app");
+        let incoming = || {
+            vec![
+                IncomingBso::from_test_content(json!({
+                    "id": ours,
+                    "type": "pref",
+                    "value": {"a": true, "b": 1},
+                })),
+                IncomingBso::from_test_content(json!({
+                    "id": prefs_record_guid("other-app"),
+                    "type": "pref",
+                    "value": {"a": false},
+                })),
+            ]
+        };
+
+        // Nothing is staged until the app configures prefs.
+        let tx = db.transaction()?;
+        ri.stage_incoming(&tx, incoming(), &NeverInterrupts)?;
+        assert!(ri.fetch_incoming_states(&tx)?.is_empty());
+        tx.rollback()?;
+
+        configure_synced_prefs(&db, "app", vec!["a".to_string()])?;
+        let tx = db.transaction()?;
+        ri.stage_incoming(&tx, incoming(), &NeverInterrupts)?;
+        let states = ri.fetch_incoming_states(&tx)?;
+        assert_eq!(states.len(), 1);
+        // "b" isn't in the allowlist, so it's dropped.
+        match &states[0].incoming.kind {
+            IncomingKind::Content(prefs) => {
+                assert_eq!(prefs.guid, ours);
+                assert_eq!(serde_json::to_value(&prefs.prefs)?, json!({"a": true}));
+            }
+            _ => panic!("should be content"),
+        }
+        assert!(matches!(
+            states[0].local,
+            LocalRecordInfo::Unmodified { .. }
+        ));
+        Ok(())
+    }
+}
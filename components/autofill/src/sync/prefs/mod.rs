@@ -0,0 +1,238 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+pub mod incoming;
+pub mod outgoing;
+
+use super::engine::{ConfigSyncEngine, EngineConfig, SyncEngineStorageImpl};
+use super::{
+    MergeResult, Metadata, ProcessIncomingRecordImpl, ProcessOutgoingRecordImpl, SyncRecord,
+    UnknownFields,
+};
+use crate::db::models::prefs::InternalPrefs;
+use crate::error::*;
+use incoming::IncomingPrefsImpl;
+use outgoing::OutgoingPrefsImpl;
+use rusqlite::Transaction;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use sync_guid::Guid;
+
+// Unlike the other engines, which sync every record in their collection, the
+// prefs engine only syncs one record - the one for this app - and only the
+// prefs in the allowlist the app configured. See `db::prefs`.
+
+// The engine.
+pub(crate) fn create_engine(store: Arc<crate::Store>) -> ConfigSyncEngine<InternalPrefs> {
+    ConfigSyncEngine::new(
+        EngineConfig {
+            namespace: "prefs".to_string(),
+            collection: "prefs".into(),
+        },
+        store,
+        Box::new(PrefsEngineStorageImpl {}),
+    )
+}
+
+pub(super) struct PrefsEngineStorageImpl {}
+
+impl SyncEngineStorageImpl<InternalPrefs> for PrefsEngineStorageImpl {
+    fn get_incoming_impl(
+        &self,
+        enc_key: &Option<String>,
+    ) -> Result<Box<dyn ProcessIncomingRecordImpl<Record = InternalPrefs>>> {
+        assert!(enc_key.is_none());
+        Ok(Box::new(IncomingPrefsImpl {}))
+    }
+
+    fn reset_storage(&self, tx: &Transaction<'_>) -> Result<()> {
+        tx.execute_batch("DELETE FROM prefs_mirror;")?;
+        Ok(())
+    }
+
+    fn get_outgoing_impl(
+        &self,
+        enc_key: &Option<String>,
+    ) -> Result<Box<dyn ProcessOutgoingRecordImpl<Record = InternalPrefs>>> {
+        assert!(enc_key.is_none());
+        Ok(Box::new(OutgoingPrefsImpl {}))
+    }
+}
+
+// What's stored on the sync server for a prefs record - the same as desktop.
+#[derive(Default, Deserialize, Serialize)]
+pub struct PrefsPayload {
+    id: Guid,
+    // always "pref"
+    #[serde(rename = "type")]
+    kind: String,
+    value: BTreeMap<String, Value>,
+    // Fields that the current schema did not expect, we store them only internally
+    // to round-trip them back to sync without processing them in any way
+    #[serde(flatten)]
+    unknown_fields: UnknownFields,
+}
+
+impl InternalPrefs {
+    fn from_payload(p: PrefsPayload) -> Result<Self> {
+        if p.kind != "pref" {
+            return Err(Error::InvalidSyncPayload(format!(
+                "invalid prefs record type - {}",
+                p.kind
+            )));
+        }
+        Ok(InternalPrefs {
+            guid: p.id,
+            prefs: p.value,
+            metadata: Default::default(),
+        })
+    }
+
+    fn into_payload(self) -> Result<PrefsPayload> {
+        Ok(PrefsPayload {
+            id: self.guid,
+            kind: "pref".to_string(),
+            value: self.prefs,
+            unknown_fields: Default::default(),
+        })
+    }
+}
+
+impl SyncRecord for InternalPrefs {
+    fn record_name() -> &'static str {
+        "Prefs"
+    }
+
+    fn id(&self) -> &Guid {
+        &self.guid
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Performs a three-way merge of each pref between an incoming, local, and
+    /// mirror record. Prefs changed only locally keep their local values, and
+    /// everything else takes the incoming value - desktop applies incoming
+    /// prefs over local changes, so we do the same for conflicts rather than
+    /// forking, and report them as conflicts the incoming record won.
+    fn merge(incoming: &Self, local: &Self, mirror: &Option<Self>) -> MergeResult<Self> {
+        // guids must be identical
+        assert_eq!(incoming.guid, local.guid);
+
+        let mut merged_record = incoming.clone();
+        let mut conflicts = vec![];
+        for (name, local_value) in &local.prefs {
+            let incoming_value = incoming.prefs.get(name);
+            if incoming_value == Some(local_value) {
+                continue;
+            }
+            let (changed_locally, changed_remotely) = match mirror {
+                Some(m) => {
+                    let mirror_value = m.prefs.get(name);
+                    (
+                        mirror_value != Some(local_value),
+                        mirror_value != incoming_value,
+                    )
+                }
+                // Without a mirror we can't tell what changed, so local
+                // values only win for prefs the server doesn't have.
+                None => (true, incoming_value.is_some()),
+            };
+            match (changed_locally, changed_remotely) {
+                (true, false) => {
+                    merged_record
+                        .prefs
+                        .insert(name.clone(), local_value.clone());
+                }
+                // Changed on both sides, so the incoming value wins.
+                (true, true) => conflicts.push(name.clone()),
+                _ => {}
+            }
+        }
+        merged_record.metadata = local.metadata;
+
+        MergeResult::Merged {
+            merged: merged_record,
+            conflicts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn prefs(value: Value) -> InternalPrefs {
+        InternalPrefs {
+            guid: Guid::new("prefs"),
+            prefs: serde_json::from_value(value).unwrap(),
+            metadata: Default::default(),
+        }
+    }
+
+    fn merged(incoming: Value, local: Value, mirror: Option<Value>) -> (Value, Vec<String>) {
+        match InternalPrefs::merge(&prefs(incoming), &prefs(local), &mirror.map(prefs)) {
+            MergeResult::Merged { merged, conflicts } => {
+                (serde_json::to_value(merged.prefs).unwrap(), conflicts)
+            }
+            MergeResult::Forked { .. } => panic!("prefs should never fork"),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        // Local and remote changes to different prefs.
+        assert_eq!(
+            merged(
+                json!({"a": true, "b": 2}),
+                json!({"a": false, "b": 1}),
+                Some(json!({"a": false, "b": 2})),
+            ),
+            (json!({"a": true, "b": 1}), vec![]),
+        );
+        // Conflicting changes take the incoming value.
+        assert_eq!(
+            merged(
+                json!({"a": "x", "b": 1}),
+                json!({"a": "y", "b": 1}),
+                Some(json!({"a": "z", "b": 0}))
+            ),
+            (json!({"a": "x", "b": 1}), vec!["a".to_string()]),
+        );
+        // Resetting a pref locally is a change, too.
+        assert_eq!(
+            merged(json!({"a": 1}), json!({"a": null}), Some(json!({"a": 1}))),
+            (json!({"a": null}), vec![]),
+        );
+        // Without a mirror, local values survive only where the server
+        // doesn't have the pref.
+        assert_eq!(
+            merged(json!({"a": 1}), json!({"a": 2, "b": 3}), None),
+            (json!({"a": 1, "b": 3}), vec!["a".to_string()]),
+        );
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        let payload = serde_json::from_value(json!({
+            "id": "prefs",
+            "type": "something-else",
+            "value": {},
+        }))
+        .unwrap();
+        assert!(matches!(
+            InternalPrefs::from_payload(payload),
+            Err(Error::InvalidSyncPayload(_))
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::db::models::prefs::InternalPrefs;
+use crate::error::*;
+use crate::sync::{common::*, prefs::PrefsPayload};
+use crate::sync::{OutgoingBso, ProcessOutgoingRecordImpl};
+use rusqlite::Transaction;
+use sql_support::ConnExt;
+use sync_guid::Guid as SyncGuid;
+
+const DATA_TABLE_NAME: &str = "prefs_data";
+const MIRROR_TABLE_NAME: &str = "prefs_mirror";
+const STAGING_TABLE_NAME: &str = "prefs_sync_outgoing_staging";
+
+pub(super) struct OutgoingPrefsImpl {}
+
+impl ProcessOutgoingRecordImpl for OutgoingPrefsImpl {
+    type Record = InternalPrefs;
+
+    /// Gets this app's prefs record if it has unsynced changes or has never
+    /// been uploaded, and stages it for the mirror.
+    fn fetch_outgoing_records(&self, tx: &Transaction<'_>) -> anyhow::Result<Vec<OutgoingBso>> {
+        let sql = "
+            SELECT
+                l.guid,
+                l.prefs,
+                l.time_last_modified,
+                l.sync_change_counter,
+                m.payload
+            FROM prefs_data l
+            LEFT JOIN prefs_mirror m
+            ON l.guid = m.guid
+            WHERE sync_change_counter > 0
+                OR l.guid NOT IN (
+                    SELECT m.guid
+                    FROM prefs_mirror m
+                )";
+        let outgoing = tx.query_rows_and_then(sql, [], |row| -> Result<(OutgoingBso, i64)> {
+            let local = InternalPrefs::from_row(row)?;
+            let change_counter = local.metadata.sync_change_counter;
+            let mut record = local.into_payload()?;
+            // Start from what's on the server, so the prefs that aren't in
+            // our allowlist (and any unknown fields) round-trip.
+            if let Some(s) = row.get::<_, Option<String>>("payload")? {
+                if let Ok(mut mirror_payload) = serde_json::from_str::<PrefsPayload>(&s) {
+                    mirror_payload.value.append(&mut record.value);
+                    record.value = mirror_payload.value;
+                    record.unknown_fields = mirror_payload.unknown_fields;
+                }
+            }
+            Ok((OutgoingBso::from_content_with_id(record)?, change_counter))
+        })?;
+
+        common_save_outgoing_records(
+            tx,
+            STAGING_TABLE_NAME,
+            outgoing
+                .iter()
+                .map(|(bso, change_counter)| {
+                    (
+                        bso.envelope.id.clone(),
+                        bso.payload.clone(),
+                        *change_counter,
+                    )
+                })
+                .collect(),
+        )?;
+        Ok(outgoing.into_iter().map(|(bso, _)| bso).collect())
+    }
+
+    fn finish_synced_items(
+        &self,
+        tx: &Transaction<'_>,
+        records_synced: Vec<SyncGuid>,
+    ) -> anyhow::Result<()> {
+        common_finish_synced_items(
+            tx,
+            DATA_TABLE_NAME,
+            MIRROR_TABLE_NAME,
+            STAGING_TABLE_NAME,
+            records_synced,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::prefs::{PrefValue, SyncedPref};
+    use crate::db::prefs::{configure_synced_prefs, prefs_record_guid, set_synced_pref};
+    use crate::sync::{common::tests::*, test::new_syncable_mem_db};
+    use serde_json::{json, Map, Value};
+
+    #[test]
+    fn test_outgoing_never_synced() -> Result<()> {
+        let mut db = new_syncable_mem_db();
+        configure_synced_prefs(&db, "app", vec!["a".to_string()])?;
+        let tx = db.transaction()?;
+        let ro = OutgoingPrefsImpl {};
+        do_test_outgoing_never_synced(
+            &tx,
+            &ro,
+            &prefs_record_guid("app"),
+            DATA_TABLE_NAME,
+            MIRROR_TABLE_NAME,
+            STAGING_TABLE_NAME,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_outgoing_roundtrip_other_prefs() -> Result<()> {
+        let mut db = new_syncable_mem_db();
+        configure_synced_prefs(&db, "app", vec!["a".to_string()])?;
+        set_synced_pref(
+            &db,
+            &SyncedPref {
+                name: "a".to_string(),
+                value: Some(PrefValue::Int { value: 2 }),
+            },
+        )?;
+        let guid = prefs_record_guid("app");
+        // The server has a pref we don't sync, and an unknown field.
+        db.execute(
+            "INSERT INTO prefs_mirror (guid, payload) VALUES (:guid, :payload)",
+            rusqlite::named_params! {
+                ":guid": guid,
+                ":payload": json!({
+                    "id": guid,
+                    "type": "pref",
+                    "value": {"a": 1, "b": "x"},
+                    "foo": "bar",
+                })
+                .to_string(),
+            },
+        )?;
+
+        let tx = db.transaction()?;
+        let ro = OutgoingPrefsImpl {};
+        let outgoing = ro.fetch_outgoing_records(&tx).unwrap();
+        assert_eq!(outgoing.len(), 1);
+        let bso_payload: Map<String, Value> = serde_json::from_str(&outgoing[0].payload).unwrap();
+        assert_eq!(bso_payload["value"], json!({"a": 2, "b": "x"}));
+        assert_eq!(bso_payload["foo"], "bar");
+        do_test_outgoing_synced_with_local_change(
+            &tx,
+            &ro,
+            &guid,
+            DATA_TABLE_NAME,
+            MIRROR_TABLE_NAME,
+            STAGING_TABLE_NAME,
+        );
+        Ok(())
+    }
+}
@@ -16,9 +16,15 @@
 // To further help understanding this, a few of the testcases are annotated.
 
 use crate::db::addresses;
+use crate::db::form_history;
+use crate::db::models::form_history::InternalFormHistoryEntry;
+use crate::db::models::prefs::{PrefValue, SyncedPref};
+use crate::db::prefs;
 use crate::db::schema::create_empty_sync_temp_tables;
 use crate::error::Result;
 use crate::sync::address::create_engine as create_address_engine;
+use crate::sync::form_history::create_engine as create_form_history_engine;
+use crate::sync::prefs::create_engine as create_prefs_engine;
 use crate::sync::{IncomingBso, Metadata};
 use crate::{InternalAddress, Store};
 use sync15::engine::SyncEngine;
@@ -728,3 +734,174 @@ fn test_reconcile_addresses() -> Result<()> {
     }
     Ok(())
 }
+
+// Form history entries only have a name and a value, so there's much less to
+// reconcile than for addresses. These use the same "parent", "local",
+// "remote", "reconciled" and "forked" layout as the address test cases, but
+// with at most one local record.
+lazy_static::lazy_static! {
+    static ref FORM_HISTORY_RECONCILE_TESTCASES: Value = json!([
+        {
+            "description": "Local change",
+            "parent": {"name": "email", "value": "a@example.com"},
+            "local": {"name": "email", "value": "b@example.com"},
+            "remote": {"name": "email", "value": "a@example.com"},
+            "reconciled": {"name": "email", "value": "b@example.com"},
+        },
+        {
+            "description": "Remote change",
+            "parent": {"name": "email", "value": "a@example.com"},
+            "local": {"name": "email", "value": "a@example.com"},
+            "remote": {"name": "email", "value": "c@example.com"},
+            "reconciled": {"name": "email", "value": "c@example.com"},
+        },
+        {
+            "description": "Same change on both sides",
+            "parent": {"name": "email", "value": "a@example.com"},
+            "local": {"name": "email", "value": "b@example.com"},
+            "remote": {"name": "email", "value": "b@example.com"},
+            "reconciled": {"name": "email", "value": "b@example.com"},
+        },
+        {
+            "description": "Conflicting changes fork the local entry",
+            "parent": {"name": "email", "value": "a@example.com"},
+            "local": {"name": "email", "value": "b@example.com"},
+            "remote": {"name": "email", "value": "c@example.com"},
+            "reconciled": {"name": "email", "value": "c@example.com"},
+            "forked": {"name": "email", "value": "b@example.com"},
+        },
+    ]);
+}
+
+fn check_form_history_as_expected(entry: &InternalFormHistoryEntry, expected: &Value) {
+    assert_eq!(expected["name"].as_str().unwrap(), entry.field_name);
+    assert_eq!(expected["value"].as_str().unwrap(), entry.value);
+}
+
+#[test]
+fn test_reconcile_form_history() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    for test_case in FORM_HISTORY_RECONCILE_TESTCASES.as_array().unwrap() {
+        let desc = test_case["description"].as_str().unwrap();
+        log::info!("starting test case: {}", desc);
+        let store = Arc::new(Store::new_memory());
+        let db = store.db.lock().unwrap();
+        let tx = db.unchecked_transaction().unwrap();
+        create_empty_sync_temp_tables(&tx)?;
+
+        let guid = SyncGuid::random();
+        let local = &test_case["local"];
+        form_history::add_internal_form_history_entry(
+            &tx,
+            &InternalFormHistoryEntry {
+                guid: guid.clone(),
+                field_name: local["name"].as_str().unwrap().to_string(),
+                value: local["value"].as_str().unwrap().to_string(),
+                metadata: Metadata {
+                    // all these tests assume local has changed.
+                    sync_change_counter: 1,
+                    ..Default::default()
+                },
+            },
+        )?;
+
+        let mut parent = test_case["parent"].clone();
+        parent["id"] = serde_json::to_value(&guid)?;
+        tx.execute(
+            "INSERT INTO form_history_mirror (guid, payload) VALUES (:guid, :payload)",
+            rusqlite::named_params! {
+                ":guid": guid,
+                ":payload": parent.to_string(),
+            },
+        )?;
+        tx.commit().expect("should commit");
+
+        let mut remote = test_case["remote"].clone();
+        remote["id"] = serde_json::to_value(&guid)?;
+        let mut telem = telemetry::Engine::new("forms");
+
+        std::mem::drop(db); // unlock the mutex for the engine.
+        let engine = create_form_history_engine(Arc::clone(&store));
+        engine
+            .stage_incoming(vec![IncomingBso::from_test_content(remote)], &mut telem)
+            .expect("should stage");
+        engine
+            .apply(ServerTimestamp(0), &mut telem)
+            .expect("should apply");
+
+        let db = store.db.lock().unwrap();
+        let all = form_history::get_all_form_history(&db)?;
+        let (reconciled, others): (Vec<_>, Vec<_>) =
+            all.into_iter().partition(|entry| entry.guid == guid);
+        assert_eq!(reconciled.len(), 1, "lost the local entry? ({desc})");
+        check_form_history_as_expected(&reconciled[0], &test_case["reconciled"]);
+        match test_case.get("forked") {
+            Some(forked) => {
+                assert_eq!(others.len(), 1, "should get a forked entry ({desc})");
+                check_form_history_as_expected(&others[0], forked);
+            }
+            None => assert!(others.is_empty(), "shouldn't fork ({desc})"),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_reconcile_prefs() -> Result<()> {
+    let _ = env_logger::try_init();
+
+    let store = Arc::new(Store::new_memory());
+    let guid = prefs::prefs_record_guid("app");
+    {
+        let db = store.db.lock().unwrap();
+        prefs::configure_synced_prefs(&db, "app", vec!["a".to_string(), "b".to_string()])?;
+        for (name, value) in [("a", 2), ("b", 1)] {
+            prefs::set_synced_pref(
+                &db,
+                &SyncedPref {
+                    name: name.to_string(),
+                    value: Some(PrefValue::Int { value }),
+                },
+            )?;
+        }
+        // "c" isn't in our allowlist, so we never change it, but we must
+        // upload whatever the server has for it.
+        db.execute(
+            "INSERT INTO prefs_mirror (guid, payload) VALUES (:guid, :payload)",
+            rusqlite::named_params! {
+                ":guid": guid,
+                ":payload": json!({
+                    "id": guid,
+                    "type": "pref",
+                    "value": {"a": 1, "b": 1, "c": "x"},
+                })
+                .to_string(),
+            },
+        )?;
+    }
+
+    let mut telem = telemetry::Engine::new("prefs");
+    let engine = create_prefs_engine(Arc::clone(&store));
+    // "a" changed locally and "b" and "c" changed remotely.
+    let remote = json!({
+        "id": guid,
+        "type": "pref",
+        "value": {"a": 1, "b": 3, "c": "y"},
+    });
+    engine
+        .stage_incoming(vec![IncomingBso::from_test_content(remote)], &mut telem)
+        .expect("should stage");
+    let outgoing = engine
+        .apply(ServerTimestamp(0), &mut telem)
+        .expect("should apply");
+
+    assert_eq!(outgoing.len(), 1);
+    let payload: Value = serde_json::from_str(&outgoing[0].payload)?;
+    assert_eq!(payload["value"], json!({"a": 2, "b": 3, "c": "y"}));
+
+    let db = store.db.lock().unwrap();
+    let local = prefs::get_prefs(&db)?.expect("should have prefs");
+    assert_eq!(serde_json::to_value(&local.prefs)?, json!({"a": 2, "b": 3}));
+    Ok(())
+}
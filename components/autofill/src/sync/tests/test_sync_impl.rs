@@ -162,10 +162,12 @@ impl SyncRecord for TestStruct {
         if incoming.value == local.value {
             MergeResult::Merged {
                 merged: TestStruct::new(&incoming.guid, incoming.value),
+                conflicts: vec![],
             }
         } else {
             MergeResult::Forked {
                 forked: TestStruct::new(&SyncGuid::random(), incoming.value + local.value),
+                conflicts: vec!["value".to_string()],
             }
         }
     }
@@ -213,7 +215,8 @@ fn test_plan_incoming_record() -> Result<()> {
         plan_incoming(&testimpl, &tx, state)?,
         IncomingAction::Update {
             record: TestStruct::new(&guid, 0),
-            was_merged: false
+            was_merged: false,
+            conflicts: vec![],
         }
     );
 
@@ -229,7 +232,8 @@ fn test_plan_incoming_record() -> Result<()> {
         plan_incoming(&testimpl, &tx, state)?,
         IncomingAction::Update {
             record: TestStruct::new(&guid, 0),
-            was_merged: false
+            was_merged: false,
+            conflicts: vec![],
         }
     );
 
@@ -241,13 +245,17 @@ fn test_plan_incoming_record() -> Result<()> {
         },
         mirror: None,
     };
+    let action = plan_incoming(&testimpl, &tx, state)?;
     assert_eq!(
-        plan_incoming(&testimpl, &tx, state)?,
+        action,
         IncomingAction::Update {
             record: TestStruct::new(&guid, 0),
-            was_merged: true
+            was_merged: true,
+            conflicts: vec![],
         }
     );
+    // Merges without conflicts aren't logged.
+    assert_eq!(action.merge_log_entry("test"), None);
 
     // LocalRecordInfo::Modified and they need to be "forked"
     let state = IncomingState {
@@ -258,11 +266,22 @@ fn test_plan_incoming_record() -> Result<()> {
         mirror: None,
     };
 
-    match plan_incoming(&testimpl, &tx, state)? {
-        IncomingAction::Fork { forked, incoming } => {
+    let action = plan_incoming(&testimpl, &tx, state)?;
+    let entry = action.merge_log_entry("test").expect("forks are logged");
+    assert_eq!(entry.collection, "test");
+    assert_eq!(entry.guid, guid.to_string());
+    assert_eq!(entry.fields, vec!["value".to_string()]);
+    assert_eq!(entry.winner, MergeWinner::Both);
+    match action {
+        IncomingAction::Fork {
+            forked,
+            incoming,
+            conflicts,
+        } => {
             assert_eq!(incoming, TestStruct::new(&guid, 1));
             // `forked` has a new guid, so can't check the entire struct.
             assert_eq!(forked.value, 3);
+            assert_eq!(conflicts, vec!["value".to_string()]);
         }
         _ => unreachable!(),
     }
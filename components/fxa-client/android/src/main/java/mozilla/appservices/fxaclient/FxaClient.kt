@@ -453,11 +453,10 @@ class FxaClient(inner: FirefoxAccount, persistCallback: PersistCallback?) : Auto
 
     /**
      * Ensure that the supported capabilities described earlier in `initializeDevice` are A-OK.
-     * A set of capabilities to be supported by the Device must also be passed (at this time only
-     * Send Tab).
+     * A set of capabilities to be supported by the Device must also be passed.
      *
-     * As for now there's only the Send Tab capability, so we ensure the command is registered with the server.
-     * This method should be called at least every time the sync keys change (because Send Tab relies on them).
+     * We ensure the command for each capability is registered with the server.
+     * This method should be called at least every time the sync keys change (because the commands rely on them).
      *
      * This performs network requests, and should not be used on the main thread.
      */
@@ -483,6 +482,20 @@ class FxaClient(inner: FirefoxAccount, persistCallback: PersistCallback?) : Auto
         }
     }
 
+    /**
+     * Ask another device identified by its device ID to close the tabs with the given URLs.
+     *
+     * This performs network requests, and should not be used on the main thread.
+     *
+     * @param targetDeviceId The target Device ID
+     * @param urls The urls of the tabs to close
+     */
+    fun closeTabs(targetDeviceId: String, urls: List<String>) {
+        withMetrics {
+            this.inner.closeTabs(targetDeviceId, urls)
+        }
+    }
+
     /**
      * Gather any telemetry which has been collected internally and return
      * the result as a JSON string.
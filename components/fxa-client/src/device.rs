@@ -232,10 +232,11 @@ pub struct Device {
 /// so consumers simply need to select which ones they want to support, and can
 /// use the variants of this enum to do so.
 ///
-/// In practice, the only currently-supported command is the ability to receive a tab.
+/// Devices can currently receive tabs, and close tabs at the request of other devices.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum DeviceCapability {
     SendTab,
+    CloseTabs,
 }
 
 /// A client connected to the user's account.
@@ -100,8 +100,8 @@ pub enum Error {
     #[error("Unknown command: {0}")]
     UnknownCommand(String),
 
-    #[error("Send Tab diagnosis error: {0}")]
-    SendTabDiagnosisError(&'static str),
+    #[error("Device command keys diagnosis error: {0}")]
+    CommandKeysDiagnosisError(&'static str),
 
     #[error("Cannot xor arrays with different lengths: {0} and {1}")]
     XorLengthMismatch(usize, usize),
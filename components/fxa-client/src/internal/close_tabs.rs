@@ -0,0 +1,40 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::{
+    commands::{
+        close_tabs::{self, CloseTabsPayload},
+        keys, IncomingDeviceCommand,
+    },
+    http_client::GetDeviceResponse,
+    scopes, FirefoxAccount,
+};
+use crate::{Error, Result};
+
+impl FirefoxAccount {
+    /// Ask another device, designated by its device ID, to close the tabs
+    /// with the given URLs.
+    pub fn close_tabs(&mut self, target_device_id: &str, urls: Vec<String>) -> Result<()> {
+        let devices = self.get_devices(false)?;
+        let target = devices
+            .iter()
+            .find(|d| d.id == target_device_id)
+            .ok_or_else(|| Error::UnknownTargetDevice(target_device_id.to_owned()))?;
+        let payload = CloseTabsPayload::with_urls(urls);
+        let oldsync_key = self.get_scoped_key(scopes::OLD_SYNC)?;
+        let command_payload =
+            keys::build_command(oldsync_key, target, close_tabs::COMMAND_NAME, &payload)?;
+        self.invoke_command(close_tabs::COMMAND_NAME, target, &command_payload)
+    }
+
+    pub(crate) fn handle_close_tabs_command(
+        &mut self,
+        sender: Option<GetDeviceResponse>,
+        payload: serde_json::Value,
+    ) -> Result<IncomingDeviceCommand> {
+        let payload: CloseTabsPayload =
+            self.decrypt_command_payload(close_tabs::COMMAND_NAME, payload)?;
+        Ok(IncomingDeviceCommand::TabsClosed { sender, payload })
+    }
+}
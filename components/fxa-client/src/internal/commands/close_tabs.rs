@@ -0,0 +1,43 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/// The Close Tabs functionality is backed by Firefox Accounts device commands.
+/// A device shows it can close tabs on request of another device by advertising
+/// the "close-uri" command in its own device record. Like Send Tab, the
+/// `CloseTabsPayload` is encrypted with the target device's keys for the command,
+/// in the same format desktop Firefox uses.
+use serde_derive::*;
+
+pub const COMMAND_NAME: &str = "https://identity.mozilla.com/cmd/close-uri/v1";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CloseTabsPayload {
+    pub urls: Vec<String>,
+}
+
+impl From<CloseTabsPayload> for crate::CloseTabsPayload {
+    fn from(payload: CloseTabsPayload) -> Self {
+        crate::CloseTabsPayload { urls: payload.urls }
+    }
+}
+
+impl CloseTabsPayload {
+    pub fn with_urls(urls: Vec<String>) -> Self {
+        CloseTabsPayload { urls }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let payload = CloseTabsPayload::with_urls(vec!["https://example.com".into()]);
+        let json = serde_json::to_string(&payload).expect("should work");
+        assert_eq!(json, r#"{"urls":["https://example.com"]}"#);
+        let p2: CloseTabsPayload = serde_json::from_str(&json).expect("should work");
+        assert_eq!(p2.urls, payload.urls);
+    }
+}
@@ -0,0 +1,234 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/// Device commands are encrypted end-to-end between devices.
+/// A device shows it can handle a command by advertising the command's name in its
+/// own device record, with a command data bundle that contains a one-time generated
+/// `PublicCommandKeys` (while keeping locally `PrivateCommandKeys` containing the
+/// private key), wrapped by the account oldsync scope `kSync` to form a `CommandKeysPayload`.
+///
+/// When a device invokes a command on another, it decrypts that `CommandKeysPayload` using
+/// `kSync`, uses the obtained public key to encrypt the command's payload and finally forms
+/// the `EncryptedCommandPayload` that is then sent to the target device.
+///
+/// This scheme was first used by Send Tab, so it's also the one other clients use.
+use serde::{de::DeserializeOwned, Serialize};
+use serde_derive::*;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rc_crypto::ece::{self, EcKeyComponents};
+use sync15::{EncryptedPayload, KeyBundle};
+
+use super::super::{device::Device, scopes};
+use crate::{Error, Result, ScopedKey};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedCommandPayload {
+    /// URL Safe Base 64 encrypted command payload.
+    encrypted: String,
+}
+
+impl EncryptedCommandPayload {
+    pub(crate) fn decrypt<T: DeserializeOwned>(self, keys: &PrivateCommandKeysV1) -> Result<T> {
+        rc_crypto::ensure_initialized();
+        let encrypted = URL_SAFE_NO_PAD.decode(self.encrypted)?;
+        let decrypted = ece::decrypt(&keys.p256key, &keys.auth_secret, &encrypted)?;
+        Ok(serde_json::from_slice(&decrypted)?)
+    }
+
+    fn encrypt<T: Serialize>(payload: &T, keys: PublicCommandKeys) -> Result<Self> {
+        rc_crypto::ensure_initialized();
+        let bytes = serde_json::to_vec(payload)?;
+        let public_key = URL_SAFE_NO_PAD.decode(&keys.public_key)?;
+        let auth_secret = URL_SAFE_NO_PAD.decode(&keys.auth_secret)?;
+        let encrypted = ece::encrypt(&public_key, &auth_secret, &bytes)?;
+        let encrypted = URL_SAFE_NO_PAD.encode(encrypted);
+        Ok(Self { encrypted })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) enum VersionnedPrivateCommandKeys {
+    V1(PrivateCommandKeysV1),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct PrivateCommandKeysV1 {
+    p256key: EcKeyComponents,
+    auth_secret: Vec<u8>,
+}
+pub(crate) type PrivateCommandKeys = PrivateCommandKeysV1;
+
+impl PrivateCommandKeys {
+    // We define this method so the type-checker prevents us from
+    // trying to serialize `PrivateCommandKeys` directly since
+    // `serde_json::to_string` would compile because both types derive
+    // `Serialize`.
+    pub(crate) fn serialize(&self) -> Result<String> {
+        Ok(serde_json::to_string(&VersionnedPrivateCommandKeys::V1(
+            self.clone(),
+        ))?)
+    }
+
+    pub(crate) fn deserialize(s: &str) -> Result<Self> {
+        let versionned: VersionnedPrivateCommandKeys = serde_json::from_str(s)?;
+        match versionned {
+            VersionnedPrivateCommandKeys::V1(prv_key) => Ok(prv_key),
+        }
+    }
+}
+
+impl PrivateCommandKeys {
+    pub fn from_random() -> Result<Self> {
+        rc_crypto::ensure_initialized();
+        let (key_pair, auth_secret) = ece::generate_keypair_and_auth_secret()?;
+        Ok(Self {
+            p256key: key_pair.raw_components()?,
+            auth_secret: auth_secret.to_vec(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CommandKeysPayload {
+    /// Hex encoded kid.
+    kid: String,
+    /// Base 64 encoded IV.
+    #[serde(rename = "IV")]
+    iv: String,
+    /// Hex encoded hmac.
+    hmac: String,
+    /// Base 64 encoded ciphertext.
+    ciphertext: String,
+}
+
+impl CommandKeysPayload {
+    pub(crate) fn decrypt(self, scoped_key: &ScopedKey) -> Result<PublicCommandKeys> {
+        let (ksync, kxcs) = extract_oldsync_key_components(scoped_key)?;
+        if hex::decode(self.kid)? != kxcs {
+            return Err(Error::MismatchedKeys);
+        }
+        let key = KeyBundle::from_ksync_bytes(&ksync)?;
+        let encrypted_payload = EncryptedPayload {
+            iv: self.iv,
+            hmac: self.hmac,
+            ciphertext: self.ciphertext,
+        };
+        Ok(encrypted_payload.decrypt_into(&key)?)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PublicCommandKeys {
+    /// URL Safe Base 64 encoded push public key.
+    #[serde(rename = "publicKey")]
+    public_key: String,
+    /// URL Safe Base 64 encoded auth secret.
+    #[serde(rename = "authSecret")]
+    auth_secret: String,
+}
+
+impl PublicCommandKeys {
+    fn encrypt(&self, scoped_key: &ScopedKey) -> Result<CommandKeysPayload> {
+        let (ksync, kxcs) = extract_oldsync_key_components(scoped_key)?;
+        let key = KeyBundle::from_ksync_bytes(&ksync)?;
+        let encrypted_payload = EncryptedPayload::from_cleartext_payload(&key, &self)?;
+        Ok(CommandKeysPayload {
+            kid: hex::encode(kxcs),
+            iv: encrypted_payload.iv,
+            hmac: encrypted_payload.hmac,
+            ciphertext: encrypted_payload.ciphertext,
+        })
+    }
+    pub fn as_command_data(&self, scoped_key: &ScopedKey) -> Result<String> {
+        let encrypted_public_keys = self.encrypt(scoped_key)?;
+        Ok(serde_json::to_string(&encrypted_public_keys)?)
+    }
+    pub(crate) fn public_key(&self) -> &str {
+        &self.public_key
+    }
+    pub(crate) fn auth_secret(&self) -> &str {
+        &self.auth_secret
+    }
+}
+
+impl From<PrivateCommandKeys> for PublicCommandKeys {
+    fn from(internal: PrivateCommandKeys) -> Self {
+        Self {
+            public_key: URL_SAFE_NO_PAD.encode(internal.p256key.public_key()),
+            auth_secret: URL_SAFE_NO_PAD.encode(&internal.auth_secret),
+        }
+    }
+}
+
+/// Builds the encrypted payload for invoking the `command_name` command on `target`,
+/// using the public keys `target` registered for that command.
+pub fn build_command<T: Serialize>(
+    scoped_key: &ScopedKey,
+    target: &Device,
+    command_name: &'static str,
+    payload: &T,
+) -> Result<serde_json::Value> {
+    let command = target
+        .available_commands
+        .get(command_name)
+        .ok_or(Error::UnsupportedCommand(command_name))?;
+    let bundle: CommandKeysPayload = serde_json::from_str(command)?;
+    let public_keys = bundle.decrypt(scoped_key)?;
+    let encrypted_payload = EncryptedCommandPayload::encrypt(payload, public_keys)?;
+    Ok(serde_json::to_value(encrypted_payload)?)
+}
+
+fn extract_oldsync_key_components(oldsync_key: &ScopedKey) -> Result<(Vec<u8>, Vec<u8>)> {
+    if oldsync_key.scope != scopes::OLD_SYNC {
+        return Err(Error::IllegalState(
+            "Only oldsync scoped keys are supported at the moment.",
+        ));
+    }
+    let kxcs: &str = oldsync_key.kid.splitn(2, '-').collect::<Vec<_>>()[1];
+    let kxcs = URL_SAFE_NO_PAD.decode(kxcs)?;
+    let ksync = oldsync_key.key_bytes()?;
+    Ok((ksync, kxcs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+    struct TestPayload {
+        value: String,
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_payload() {
+        let keys = PrivateCommandKeys::from_random().expect("should generate keys");
+        let payload = TestPayload {
+            value: "hello".into(),
+        };
+        let encrypted = EncryptedCommandPayload::encrypt(&payload, keys.clone().into())
+            .expect("should encrypt");
+        let decrypted: TestPayload = encrypted.decrypt(&keys).expect("should decrypt");
+        assert_eq!(decrypted, payload);
+
+        // Payloads can't be decrypted with some other command's keys.
+        let other_keys = PrivateCommandKeys::from_random().expect("should generate keys");
+        let encrypted =
+            EncryptedCommandPayload::encrypt(&payload, keys.into()).expect("should encrypt");
+        assert!(encrypted.decrypt::<TestPayload>(&other_keys).is_err());
+    }
+
+    #[test]
+    fn test_serialize_private_keys() {
+        let keys = PrivateCommandKeys::from_random().expect("should generate keys");
+        let serialized = keys.serialize().expect("should serialize");
+        // Keys are versioned, so that Send Tab keys persisted by older versions still load.
+        assert!(serialized.starts_with(r#"{"V1":"#));
+        let deserialized = PrivateCommandKeys::deserialize(&serialized).expect("should work");
+        let public: PublicCommandKeys = keys.into();
+        let public2: PublicCommandKeys = deserialized.into();
+        assert_eq!(public.public_key(), public2.public_key());
+        assert_eq!(public.auth_secret(), public2.auth_secret());
+    }
+}
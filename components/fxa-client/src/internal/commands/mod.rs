@@ -2,11 +2,44 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+//! Device commands let devices on the account invoke commands on each other.
+//!
+//! Each command type has its own module, with its name and payload. A command is
+//! registered with the server under its name, by a device that advertises the
+//! [`DeviceCapability`] for it. Every command has its own keys, and its payloads
+//! are encrypted with them - see [`keys`].
+
+pub mod close_tabs;
+pub mod keys;
 pub mod send_tab;
+pub use close_tabs::CloseTabsPayload;
 pub use send_tab::SendTabPayload;
 
-use super::device::Device;
-use crate::{Error, Result};
+use serde::de::DeserializeOwned;
+
+use self::keys::{
+    CommandKeysPayload, EncryptedCommandPayload, PrivateCommandKeys, PublicCommandKeys,
+};
+use super::{device::Device, scopes, FirefoxAccount};
+use crate::{DeviceCapability, Error, Result};
+
+impl DeviceCapability {
+    /// The name of the command this capability registers with the server.
+    pub(crate) fn command_name(&self) -> &'static str {
+        match self {
+            DeviceCapability::SendTab => send_tab::COMMAND_NAME,
+            DeviceCapability::CloseTabs => close_tabs::COMMAND_NAME,
+        }
+    }
+
+    pub(crate) fn from_command_name(command_name: &str) -> Option<Self> {
+        match command_name {
+            send_tab::COMMAND_NAME => Some(DeviceCapability::SendTab),
+            close_tabs::COMMAND_NAME => Some(DeviceCapability::CloseTabs),
+            _ => None,
+        }
+    }
+}
 
 // Currently public for use by example crates, but should be made private eventually.
 #[derive(Clone, Debug)]
@@ -15,6 +48,10 @@ pub enum IncomingDeviceCommand {
         sender: Option<Device>,
         payload: SendTabPayload,
     },
+    TabsClosed {
+        sender: Option<Device>,
+        payload: CloseTabsPayload,
+    },
 }
 
 impl TryFrom<IncomingDeviceCommand> for crate::IncomingDeviceCommand {
@@ -27,6 +64,125 @@ impl TryFrom<IncomingDeviceCommand> for crate::IncomingDeviceCommand {
                     payload: payload.into(),
                 }
             }
+            IncomingDeviceCommand::TabsClosed { sender, payload } => {
+                crate::IncomingDeviceCommand::TabsClosed {
+                    sender: sender.map(crate::Device::try_from).transpose()?,
+                    payload: payload.into(),
+                }
+            }
         })
     }
 }
+
+impl FirefoxAccount {
+    /// Generate the data for a command to be registered with the server.
+    ///
+    /// **💾 This method alters the persisted account state.**
+    pub(crate) fn generate_command_data(&mut self, command_name: &str) -> Result<String> {
+        let own_keys = self.load_or_generate_command_keys(command_name)?;
+        let public_keys: PublicCommandKeys = own_keys.into();
+        let oldsync_key = self.get_scoped_key(scopes::OLD_SYNC)?;
+        public_keys.as_command_data(oldsync_key)
+    }
+
+    fn load_or_generate_command_keys(&mut self, command_name: &str) -> Result<PrivateCommandKeys> {
+        if let Some(s) = self.state.get_commands_data(command_name) {
+            match PrivateCommandKeys::deserialize(s) {
+                Ok(keys) => return Ok(keys),
+                Err(_) => {
+                    error_support::report_error!(
+                        "fxaclient-command-key-deserialize",
+                        "Could not deserialize the keys for {}. Re-creating them.",
+                        command_name
+                    );
+                }
+            }
+        }
+        let keys = PrivateCommandKeys::from_random()?;
+        self.state
+            .set_commands_data(command_name, keys.serialize()?);
+        Ok(keys)
+    }
+
+    /// Decrypts the payload of an incoming `command_name` command.
+    ///
+    /// If the payload can't be decrypted, we reset our keys for the command and
+    /// register the new ones, so that the sender can use them next time.
+    ///
+    /// **💾 This method alters the persisted account state.**
+    pub(crate) fn decrypt_command_payload<T: DeserializeOwned>(
+        &mut self,
+        command_name: &'static str,
+        payload: serde_json::Value,
+    ) -> Result<T> {
+        let command_keys: PrivateCommandKeys = match self.state.get_commands_data(command_name) {
+            Some(s) => PrivateCommandKeys::deserialize(s)?,
+            None => {
+                return Err(Error::IllegalState(
+                    "Cannot find command keys. Has initialize_device been called before?",
+                ));
+            }
+        };
+        let encrypted_payload: EncryptedCommandPayload = serde_json::from_value(payload)?;
+        match encrypted_payload.decrypt(&command_keys) {
+            Ok(payload) => Ok(payload),
+            Err(e) => {
+                // XXX - this seems ripe for telemetry collection!?
+                // It also seems like it might be possible to recover - ie, one
+                // of the reasons is that there are key mismatches. Doesn't that
+                // mean the "other" key might work?
+                log::warn!(
+                    "Could not decrypt {} payload. Diagnosing then resetting the keys.",
+                    command_name
+                );
+                match self.diagnose_remote_keys(command_name, command_keys) {
+                    Ok(_) => {
+                        error_support::report_error!(
+                            "fxaclient-command-decrypt",
+                            "Could not find the cause of the {} keys issue.",
+                            command_name
+                        );
+                    }
+                    Err(e) => {
+                        error_support::report_error!("fxaclient-command-decrypt", "{}", e);
+                    }
+                };
+                // Reset the keys for the command.
+                self.state.clear_commands_data(command_name);
+                self.reregister_current_capabilities()?;
+                Err(e)
+            }
+        }
+    }
+
+    fn diagnose_remote_keys(
+        &mut self,
+        command_name: &str,
+        local_command_keys: PrivateCommandKeys,
+    ) -> Result<()> {
+        let own_device = &mut self
+            .get_current_device()?
+            .ok_or(Error::CommandKeysDiagnosisError("No remote device."))?;
+
+        let command = own_device
+            .available_commands
+            .get(command_name)
+            .ok_or(Error::CommandKeysDiagnosisError("No remote command."))?;
+        let bundle: CommandKeysPayload = serde_json::from_str(command)?;
+        let oldsync_key = self.get_scoped_key(scopes::OLD_SYNC)?;
+        let public_keys_remote = bundle.decrypt(oldsync_key).map_err(|_| {
+            Error::CommandKeysDiagnosisError("Unable to decrypt public key bundle.")
+        })?;
+
+        let public_keys_local: PublicCommandKeys = local_command_keys.into();
+
+        if public_keys_local.public_key() != public_keys_remote.public_key() {
+            return Err(Error::CommandKeysDiagnosisError("Mismatch in public key."));
+        }
+
+        if public_keys_local.auth_secret() != public_keys_remote.auth_secret() {
+            return Err(Error::CommandKeysDiagnosisError("Mismatch in auth secret."));
+        }
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 pub use super::http_client::{
     DeviceLocation as Location, GetDeviceResponse as Device, PushSubscription,
@@ -70,19 +70,11 @@ impl FirefoxAccount {
         &mut self,
         capabilities: &[DeviceCapability],
     ) -> Result<HashMap<String, String>> {
-        let mut capabilities_set = HashSet::new();
         let mut commands = HashMap::new();
         for capability in capabilities {
-            match capability {
-                DeviceCapability::SendTab => {
-                    let send_tab_command = self.generate_send_tab_command_data()?;
-                    commands.insert(
-                        commands::send_tab::COMMAND_NAME.to_owned(),
-                        send_tab_command.to_owned(),
-                    );
-                    capabilities_set.insert(DeviceCapability::SendTab);
-                }
-            }
+            let command_name = capability.command_name();
+            let command_data = self.generate_command_data(command_name)?;
+            commands.insert(command_name.to_owned(), command_data);
         }
         Ok(commands)
     }
@@ -110,9 +102,9 @@ impl FirefoxAccount {
 
     /// Register a set of device capabilities against the current device.
     ///
-    /// As the only capability is Send Tab now, its command is registered with the server.
+    /// The command for each capability is registered with the server.
     /// Don't forget to also call this if the Sync Keys change as they
-    /// encrypt the Send Tab command data.
+    /// encrypt the command data.
     ///
     /// **💾 This method alters the persisted account state.**
     pub fn ensure_capabilities(
@@ -252,6 +244,9 @@ impl FirefoxAccount {
             commands::send_tab::COMMAND_NAME => {
                 self.handle_send_tab_command(sender, command_data.payload, telem_reason)
             }
+            commands::close_tabs::COMMAND_NAME => {
+                self.handle_close_tabs_command(sender, command_data.payload)
+            }
             _ => Err(Error::UnknownCommand(command_data.command)),
         }
     }
@@ -333,10 +328,7 @@ impl TryFrom<String> for DeviceCapability {
     type Error = Error;
 
     fn try_from(command: String) -> Result<Self> {
-        match command.as_str() {
-            commands::send_tab::COMMAND_NAME => Ok(DeviceCapability::SendTab),
-            _ => Err(Error::UnknownCommand(command)),
-        }
+        DeviceCapability::from_command_name(&command).ok_or(Error::UnknownCommand(command))
     }
 }
 
@@ -369,10 +361,7 @@ impl TryFrom<Device> for crate::Device {
         let capabilities: Vec<_> = d
             .available_commands
             .keys()
-            .filter_map(|k| match k.as_str() {
-                commands::send_tab::COMMAND_NAME => Some(DeviceCapability::SendTab),
-                _ => None,
-            })
+            .filter_map(|k| DeviceCapability::from_command_name(k))
             .map(Into::into)
             .collect();
         Ok(crate::Device {
@@ -768,6 +757,120 @@ mod tests {
             .unwrap();
     }
 
+    fn device_with_commands(
+        id: &str,
+        is_current_device: bool,
+        available_commands: HashMap<String, String>,
+    ) -> Device {
+        Device {
+            common: DeviceResponseCommon {
+                id: id.into(),
+                display_name: id.into(),
+                device_type: DeviceType::Desktop,
+                push_subscription: None,
+                available_commands,
+                push_endpoint_expired: false,
+            },
+            is_current_device,
+            location: DeviceLocation {
+                city: None,
+                country: None,
+                state: None,
+                state_code: None,
+            },
+            last_access_time: None,
+        }
+    }
+
+    #[test]
+    fn test_close_tabs_command() {
+        let mut fxa = setup();
+        let command_data = fxa
+            .generate_command_data(commands::close_tabs::COMMAND_NAME)
+            .unwrap();
+        let available_commands =
+            HashMap::from([(commands::close_tabs::COMMAND_NAME.to_owned(), command_data)]);
+        assert_eq!(
+            DeviceCapability::try_from(commands::close_tabs::COMMAND_NAME.to_owned()).unwrap(),
+            DeviceCapability::CloseTabs
+        );
+
+        // Another device closes tabs on this one...
+        let this_device = device_with_commands("device1", true, available_commands.clone());
+        let oldsync_key = fxa
+            .get_scoped_key(crate::internal::scopes::OLD_SYNC)
+            .unwrap()
+            .clone();
+        let payload = commands::keys::build_command(
+            &oldsync_key,
+            &this_device,
+            commands::close_tabs::COMMAND_NAME,
+            &commands::CloseTabsPayload::with_urls(vec!["https://example.com".into()]),
+        )
+        .unwrap();
+
+        let mut client = MockFxAClient::new();
+        client
+            .expect_get_devices()
+            .with(always(), always())
+            .times(1)
+            .returning(move |_, _| {
+                Ok(vec![
+                    this_device.clone(),
+                    device_with_commands("device2", false, available_commands.clone()),
+                ])
+            });
+        client
+            .expect_get_pending_commands()
+            .with(always(), always(), eq(1), eq(None))
+            .times(1)
+            .returning(move |_, _, _, _| {
+                Ok(PendingCommandsResponse {
+                    index: 1,
+                    last: Some(true),
+                    messages: vec![PendingCommand {
+                        index: 1,
+                        data: CommandData {
+                            command: commands::close_tabs::COMMAND_NAME.to_owned(),
+                            payload: payload.clone(),
+                            sender: Some("device2".into()),
+                        },
+                    }],
+                })
+            });
+        // ...and this device closes tabs on the other one.
+        client
+            .expect_invoke_command()
+            .with(
+                always(),
+                always(),
+                eq(commands::close_tabs::COMMAND_NAME),
+                eq("device2"),
+                always(),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        fxa.set_client(Arc::new(client));
+
+        let cmds = fxa.poll_device_commands(CommandFetchReason::Poll).unwrap();
+        assert_eq!(cmds.len(), 1);
+        match &cmds[0] {
+            IncomingDeviceCommand::TabsClosed { sender, payload } => {
+                assert_eq!(sender.as_ref().unwrap().id, "device2");
+                assert_eq!(payload.urls, vec!["https://example.com".to_string()]);
+            }
+            cmd => panic!("Unexpected command: {cmd:?}"),
+        }
+
+        fxa.close_tabs("device2", vec!["https://example.com".into()])
+            .unwrap();
+        // We can't close tabs on devices that don't support it.
+        assert!(matches!(
+            fxa.close_tabs("device3", vec![]),
+            Err(Error::UnknownTargetDevice(_))
+        ));
+    }
+
     #[test]
     fn test_get_devices() {
         let mut fxa = setup();
@@ -100,6 +100,24 @@ impl FirefoxAccount {
             .lock()
             .send_single_tab(target_device_id, title, url)
     }
+
+    /// Use device commands to close tabs on another device.
+    ///
+    /// **💾 This method alters the persisted account state.**
+    ///
+    /// If a device on the account has registered the [`CloseTabs`](DeviceCapability::CloseTabs)
+    /// capability, this method can be used to ask it to close the tabs with the given URLs.
+    ///
+    /// # Notes
+    ///
+    ///    - If the given device id does not exist or is not capable of closing tabs,
+    ///      this method will throw an [`Other`](FxaError::Other) error.
+    ///    - Device commands functionality is only available to applications that have been
+    ///      granted the `https://identity.mozilla.com/apps/oldsync` scope.
+    #[handle_error(Error)]
+    pub fn close_tabs(&self, target_device_id: &str, urls: Vec<String>) -> ApiResult<()> {
+        self.internal.lock().close_tabs(target_device_id, urls)
+    }
 }
 
 /// Details of a web-push subscription endpoint.
@@ -189,6 +207,11 @@ pub enum IncomingDeviceCommand {
         sender: Option<Device>,
         payload: SendTabPayload,
     },
+    /// Indicates that another device asked this device to close some tabs.
+    TabsClosed {
+        sender: Option<Device>,
+        payload: CloseTabsPayload,
+    },
 }
 
 /// The payload sent when invoking a "send tab" command.
@@ -210,6 +233,13 @@ pub struct SendTabPayload {
     pub stream_id: String,
 }
 
+/// The payload sent when invoking a "close tabs" command.
+#[derive(Debug)]
+pub struct CloseTabsPayload {
+    /// The URLs of the tabs to close.
+    pub urls: Vec<String>,
+}
+
 /// An individual entry in the navigation history of a sent tab.
 #[derive(Debug)]
 pub struct TabHistoryEntry {
@@ -4,6 +4,8 @@
 
 package mozilla.appservices.logins
 
+import mozilla.appservices.sync15.MergeLogEntry
+
 /**
  * Import some private Glean types, so that we can use them in type declarations.
  *
@@ -78,6 +80,16 @@ class DatabaseLoginsStorage(dbPath: String) : AutoCloseable {
         }
     }
 
+    /**
+     * The conflicts that sync resolved between local and incoming logins, most recent first.
+     */
+    @Throws(LoginsApiException::class)
+    fun getMergeLog(): List<MergeLogEntry> {
+        return readQueryCounters.measure {
+            store.getMergeLog()
+        }
+    }
+
     @Throws(LoginsApiException::class)
     fun findLoginToUpdate(look: LoginEntry, encryptionKey: String): Login? {
         return readQueryCounters.measure {
@@ -82,6 +82,13 @@ open class LoginsStorage {
         }
     }
 
+    /// Get the conflicts that sync resolved between local and incoming logins, most recent first.
+    open func getMergeLog() throws -> [MergeLogEntry] {
+        return try queue.sync {
+            try self.store.getMergeLog()
+        }
+    }
+
     /// Register with the sync manager
     open func registerWithSyncManager() {
         return queue.sync {
@@ -40,6 +40,7 @@ use std::ops::Deref;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::SystemTime;
+use sync15::MergeLogEntry;
 use sync_guid::Guid;
 use url::{Host, Url};
 
@@ -132,6 +133,26 @@ impl LoginDb {
         Ok(())
     }
 
+    /// The conflicts that sync resolved, most recent first.
+    pub fn get_merge_log(&self) -> Result<Vec<MergeLogEntry>> {
+        let mut stmt = self.db.prepare_cached(
+            "SELECT guid, fields, winner, timestamp
+             FROM loginsMergeLog
+             ORDER BY id DESC",
+        )?;
+        let rows = stmt.query_and_then([], |row| -> Result<MergeLogEntry> {
+            let winner: String = row.get("winner")?;
+            Ok(MergeLogEntry {
+                collection: "passwords".to_string(),
+                guid: row.get("guid")?,
+                fields: serde_json::from_str(&row.get::<_, String>("fields")?)?,
+                winner: winner.parse().map_err(|_| Error::BadMergeWinner(winner))?,
+                timestamp: row.get("timestamp")?,
+            })
+        })?;
+        rows.collect::<Result<_>>()
+    }
+
     pub fn get_all(&self) -> Result<Vec<EncryptedLogin>> {
         let mut stmt = self.db.prepare_cached(&GET_ALL_SQL)?;
         let rows = stmt.query_and_then([], EncryptedLogin::from_row)?;
@@ -710,6 +731,7 @@ impl LoginDb {
             "DELETE FROM loginsL",
             "DELETE FROM loginsM",
             "DELETE FROM loginsSyncMeta",
+            "DELETE FROM loginsMergeLog",
         ])?;
         tx.commit()?;
         Ok(())
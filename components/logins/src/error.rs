@@ -48,6 +48,9 @@ pub enum Error {
     #[error("The `sync_status` column in DB has an illegal value: {0}")]
     BadSyncStatus(u8),
 
+    #[error("The `winner` column in the merge log has an illegal value: {0}")]
+    BadMergeWinner(String),
+
     #[error("No record with guid exists (when one was required): {0:?}")]
     NoSuchRecord(String),
 
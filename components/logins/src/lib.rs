@@ -24,6 +24,7 @@ pub use crate::error::*;
 pub use crate::login::*;
 pub use crate::store::*;
 pub use crate::sync::LoginsSyncEngine;
+use sync15::MergeLogEntry;
 
 // Public encryption functions.  We publish these as top-level functions to expose them across
 // UniFFI
@@ -6,11 +6,12 @@
 //! ================
 //!
 //! The schema we use is a evolution of the firefox-ios logins database format.
-//! There are three tables:
+//! There are four tables:
 //!
 //! - `loginsL`: The local table.
 //! - `loginsM`: The mirror table.
 //! - `loginsSyncMeta`: The table used to to store various sync metadata.
+//! - `loginsMergeLog`: The log of conflicts resolved while syncing.
 //!
 //! ## `loginsL`
 //!
@@ -87,6 +88,24 @@
 //!    [GLOBAL_STATE_META_KEY]. This is a `sync15::GlobalState` stored as
 //!    JSON.
 //!
+//! ## `loginsMergeLog`
+//!
+//! This stores the most recent conflicts that sync resolved between local and
+//! incoming logins, so that support can see why a login changed. It was added
+//! in version 3, and only ever holds the last
+//! [sync15::MERGE_LOG_MAX_ENTRIES] entries.
+//!
+//! ### `loginsMergeLog` Columns
+//!
+//! - `guid`: The guid of the login that was merged.
+//!
+//! - `fields`: A JSON array of the names of the fields that conflicted. Their
+//!   values are never stored, so the log never holds passwords.
+//!
+//! - `winner`: Which side won - a `sync15::MergeWinner` as a string.
+//!
+//! - `timestamp`: When the conflict was resolved, in milliseconds.
+//!
 
 use crate::error::*;
 use lazy_static::lazy_static;
@@ -95,7 +114,8 @@ use sql_support::ConnExt;
 
 /// Version 1: SQLCipher -> plaintext migration.
 /// Version 2: addition of `loginsM.enc_unknown_fields`.
-pub(super) const VERSION: i64 = 2;
+/// Version 3: addition of `loginsMergeLog`.
+pub(super) const VERSION: i64 = 3;
 
 /// Every column shared by both tables except for `id`
 ///
@@ -180,6 +200,16 @@ const CREATE_META_TABLE_SQL: &str = "
     )
 ";
 
+const CREATE_MERGE_LOG_TABLE_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS loginsMergeLog (
+        id        INTEGER PRIMARY KEY AUTOINCREMENT,
+        guid      TEXT NOT NULL,
+        fields    TEXT NOT NULL,
+        winner    TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    )
+";
+
 const CREATE_OVERRIDE_ORIGIN_INDEX_SQL: &str = "
     CREATE INDEX IF NOT EXISTS idx_loginsM_is_overridden_origin
     ON loginsM (is_overridden, origin)
@@ -218,7 +248,7 @@ pub(crate) fn init(db: &Connection) -> Result<()> {
 
 // Allow the redundant Ok() here.  It will make more sense once we have an actual upgrade function.
 #[allow(clippy::unnecessary_wraps)]
-fn upgrade(db: &Connection, from: i64) -> Result<()> {
+fn upgrade(db: &Connection, mut from: i64) -> Result<()> {
     log::debug!("Upgrading schema from {} to {}", from, VERSION);
     if from == VERSION {
         return Ok(());
@@ -232,10 +262,14 @@ fn upgrade(db: &Connection, from: i64) -> Result<()> {
     if from == 1 {
         // Just one new nullable column makes this fairly easy
         db.execute_batch("ALTER TABLE loginsM ADD enc_unknown_fields TEXT;")?;
+        from = 2;
+    }
+    if from == 2 {
+        db.execute_batch(CREATE_MERGE_LOG_TABLE_SQL)?;
     }
     // XXX - next migration, be sure to:
-    // from = 2;
-    // if from == 2 ...
+    // from = 3;
+    // if from == 3 ...
     db.execute_batch(&SET_VERSION_SQL)?;
     Ok(())
 }
@@ -248,6 +282,7 @@ pub(crate) fn create(db: &Connection) -> Result<()> {
         CREATE_OVERRIDE_ORIGIN_INDEX_SQL,
         CREATE_DELETED_ORIGIN_INDEX_SQL,
         CREATE_META_TABLE_SQL,
+        CREATE_MERGE_LOG_TABLE_SQL,
         &*SET_VERSION_SQL,
     ])?;
     Ok(())
@@ -313,4 +348,20 @@ mod tests {
         db.execute_batch("SELECT enc_unknown_fields FROM loginsM")
             .unwrap();
     }
+
+    #[test]
+    fn test_upgrade_v2() {
+        // A V2 schema is the current one without the merge log.
+        let connection = Connection::open_in_memory().unwrap();
+        create(&connection).unwrap();
+        connection
+            .execute_batch("DROP TABLE loginsMergeLog; PRAGMA user_version = 2;")
+            .unwrap();
+
+        let db = LoginDb::with_connection(connection).unwrap();
+        let version = db.query_one::<i64>("PRAGMA user_version").unwrap();
+        assert_eq!(version, VERSION);
+        db.execute_batch("SELECT guid, fields, winner, timestamp FROM loginsMergeLog")
+            .unwrap();
+    }
 }
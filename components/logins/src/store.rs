@@ -10,6 +10,7 @@ use parking_lot::Mutex;
 use std::path::Path;
 use std::sync::{Arc, Weak};
 use sync15::engine::{EngineSyncAssociation, SyncEngine, SyncEngineId};
+use sync15::MergeLogEntry;
 
 // Our "sync manager" will use whatever is stashed here.
 lazy_static::lazy_static! {
@@ -92,6 +93,13 @@ impl LoginStore {
         self.db.lock().find_login_to_update(entry, &encdec)
     }
 
+    /// The conflicts that sync resolved between local and incoming logins,
+    /// most recent first.
+    #[handle_error(Error)]
+    pub fn get_merge_log(&self) -> ApiResult<Vec<MergeLogEntry>> {
+        self.db.lock().get_merge_log()
+    }
+
     #[handle_error(Error)]
     pub fn touch(&self, id: &str) -> ApiResult<()> {
         self.db.lock().touch(id)
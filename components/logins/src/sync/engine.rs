@@ -16,11 +16,13 @@ use crate::LoginStore;
 use interrupt_support::SqlInterruptScope;
 use rusqlite::named_params;
 use sql_support::ConnExt;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
 use std::sync::Arc;
 use sync15::bso::{IncomingBso, OutgoingBso, OutgoingEnvelope};
-use sync15::engine::{CollSyncIds, CollectionRequest, EngineSyncAssociation, SyncEngine};
+use sync15::engine::{
+    CollSyncIds, CollectionRequest, EngineSyncAssociation, OutgoingRecords, SyncEngine,
+};
 use sync15::{telemetry, ServerTimestamp};
 use sync_guid::Guid;
 
@@ -29,6 +31,8 @@ pub struct LoginsSyncEngine {
     pub store: Arc<LoginStore>,
     pub scope: SqlInterruptScope,
     pub staged: RefCell<Vec<IncomingBso>>,
+    // The number of conflicts we added to the merge log this sync.
+    conflicts_resolved: Cell<usize>,
     // It's unfortunate this is an Option<>, but tricky to change because sometimes we construct
     // an engine for, say, a `reset()` where this isn't needed or known.
     encdec: Option<EncryptorDecryptor>,
@@ -48,6 +52,7 @@ impl LoginsSyncEngine {
             store,
             scope,
             staged: RefCell::new(vec![]),
+            conflicts_resolved: Cell::new(0),
             encdec: None,
         })
     }
@@ -92,7 +97,7 @@ impl LoginsSyncEngine {
                 }
                 (None, Some(local)) => {
                     log::debug!("  Conflicting record without shared parent, using newer");
-                    plan.plan_two_way_merge(&local.login, (upstream, upstream_time));
+                    plan.plan_two_way_merge(&local.login, (upstream, upstream_time), encdec)?;
                     telem.reconciled(1);
                 }
                 (None, None) => {
@@ -102,7 +107,7 @@ impl LoginsSyncEngine {
                             upstream.guid(),
                             dupe.guid()
                         );
-                        plan.plan_two_way_merge(&dupe, (upstream, upstream_time));
+                        plan.plan_two_way_merge(&dupe, (upstream, upstream_time), encdec)?;
                     } else {
                         log::debug!("  No dupe found, inserting into mirror");
                         plan.plan_mirror_insert(upstream, upstream_time, false);
@@ -122,6 +127,8 @@ impl LoginsSyncEngine {
         let tx = db.unchecked_transaction()?;
         plan.execute(&tx, &self.scope)?;
         tx.commit()?;
+        self.conflicts_resolved
+            .set(self.conflicts_resolved.get() + plan.merge_log.len());
         Ok(())
     }
 
@@ -447,13 +454,14 @@ impl SyncEngine for LoginsSyncEngine {
         Ok(())
     }
 
-    fn apply(
+    fn apply_chunked(
         &self,
         timestamp: ServerTimestamp,
         telem: &mut telemetry::Engine,
-    ) -> anyhow::Result<Vec<OutgoingBso>> {
+    ) -> anyhow::Result<OutgoingRecords<'_>> {
+        // There are few enough logins that we can fetch them all at once.
         let inbound = (*self.staged.borrow_mut()).drain(..).collect();
-        Ok(self.do_apply_incoming(inbound, timestamp, telem)?)
+        Ok(self.do_apply_incoming(inbound, timestamp, telem)?.into())
     }
 
     fn set_uploaded(&self, new_timestamp: ServerTimestamp, ids: Vec<Guid>) -> anyhow::Result<()> {
@@ -463,6 +471,10 @@ impl SyncEngine for LoginsSyncEngine {
         )?)
     }
 
+    fn conflicts_resolved(&self) -> usize {
+        self.conflicts_resolved.get()
+    }
+
     fn get_collection_request(
         &self,
         server_timestamp: ServerTimestamp,
@@ -512,6 +524,7 @@ mod tests {
     use crate::{LoginEntry, LoginFields, RecordFields, SecureLoginFields};
     use std::collections::HashMap;
     use std::sync::Arc;
+    use sync15::MergeWinner;
 
     // Wrap sync functions for easier testing
     fn run_fetch_login_data(
@@ -705,6 +718,46 @@ mod tests {
         assert_eq!(res[1].guid, "dummy_000003");
     }
 
+    #[test]
+    fn test_merge_log() {
+        let store = Arc::new(LoginStore::new_in_memory().unwrap());
+        insert_login(
+            &store.db.lock(),
+            "changed_on_both",
+            Some("new-local-password"),
+            Some("password"),
+        );
+        insert_login(&store.db.lock(), "changed_remotely", None, Some("password"));
+        let mut engine = LoginsSyncEngine::new(Arc::clone(&store)).unwrap();
+        engine
+            .set_local_encryption_key(&TEST_ENCRYPTION_KEY)
+            .unwrap();
+
+        // The incoming records are much older than the local change, so the
+        // local password wins.
+        let incoming = ["changed_on_both", "changed_remotely"]
+            .iter()
+            .map(|guid| {
+                enc_login(guid, "new-remote-password")
+                    .into_bso(&TEST_ENCRYPTOR, None)
+                    .unwrap()
+                    .to_test_incoming()
+            })
+            .collect();
+        let now = ServerTimestamp(util::system_time_ms_i64(std::time::SystemTime::now()));
+        let mut telem = telemetry::Engine::new(engine.collection_name());
+        engine.do_apply_incoming(incoming, now, &mut telem).unwrap();
+
+        // Only the record that changed on both sides is a conflict.
+        assert_eq!(engine.conflicts_resolved(), 1);
+        let log = store.get_merge_log().unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].collection, "passwords");
+        assert_eq!(log[0].guid, "changed_on_both");
+        assert_eq!(log[0].fields, vec!["password".to_string()]);
+        assert_eq!(log[0].winner, MergeWinner::Local);
+    }
+
     fn make_enc_login(
         username: &str,
         password: &str,
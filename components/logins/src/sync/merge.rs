@@ -231,6 +231,62 @@ impl LoginDelta {
     }
 }
 
+macro_rules! changed_field {
+    ($names:ident, $delta:ident, $field:ident) => {
+        if $delta.$field.is_some() {
+            $names.push(stringify!($field));
+        }
+    };
+}
+
+macro_rules! conflicting_field {
+    ($names:ident, $a:ident, $b:ident, $field:ident) => {
+        if $a.$field.is_some() && $b.$field.is_some() && $a.$field != $b.$field {
+            $names.push(stringify!($field));
+        }
+    };
+}
+
+impl LoginDelta {
+    /// The names of the fields this delta changes, for the merge log. The
+    /// commutative `times_used` is never a conflict, so isn't included.
+    pub fn changed_fields(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+        changed_field!(names, self, origin);
+        changed_field!(names, self, password);
+        changed_field!(names, self, username);
+        changed_field!(names, self, http_realm);
+        changed_field!(names, self, form_action_origin);
+
+        changed_field!(names, self, time_created);
+        changed_field!(names, self, time_last_used);
+        changed_field!(names, self, time_password_changed);
+
+        changed_field!(names, self, password_field);
+        changed_field!(names, self, username_field);
+        names
+    }
+
+    /// The names of the fields both deltas change, to different values. When
+    /// these are merged, only one side's values survive.
+    pub fn conflicting_fields(&self, b: &LoginDelta) -> Vec<&'static str> {
+        let mut names = vec![];
+        conflicting_field!(names, self, b, origin);
+        conflicting_field!(names, self, b, password);
+        conflicting_field!(names, self, b, username);
+        conflicting_field!(names, self, b, http_realm);
+        conflicting_field!(names, self, b, form_action_origin);
+
+        conflicting_field!(names, self, b, time_created);
+        conflicting_field!(names, self, b, time_last_used);
+        conflicting_field!(names, self, b, time_password_changed);
+
+        conflicting_field!(names, self, b, password_field);
+        conflicting_field!(names, self, b, username_field);
+        names
+    }
+}
+
 macro_rules! apply_field {
     ($login:ident, $delta:ident, $field:ident) => {
         if let Some($field) = $delta.$field.take() {
@@ -354,6 +410,30 @@ mod tests {
     use super::*;
     use crate::encryption::test_utils::TEST_ENCRYPTOR;
 
+    #[test]
+    fn test_delta_fields() {
+        let local = LoginDelta {
+            password: Some("local".into()),
+            username: Some("same".into()),
+            time_last_used: Some(1),
+            times_used: 2,
+            ..Default::default()
+        };
+        let remote = LoginDelta {
+            password: Some("remote".into()),
+            username: Some("same".into()),
+            origin: Some("https://www.example.com".into()),
+            times_used: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            local.changed_fields(),
+            vec!["password", "username", "time_last_used"]
+        );
+        // Changing a field to the same value on both sides isn't a conflict.
+        assert_eq!(local.conflicting_fields(&remote), vec!["password"]);
+    }
+
     #[test]
     fn test_invalid_payload_timestamps() {
         #[allow(clippy::unreadable_literal)]
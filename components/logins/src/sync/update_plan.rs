@@ -11,7 +11,7 @@ use crate::util;
 use interrupt_support::SqlInterruptScope;
 use rusqlite::{named_params, Connection};
 use std::time::SystemTime;
-use sync15::ServerTimestamp;
+use sync15::{MergeLogEntry, MergeWinner, ServerTimestamp, MERGE_LOG_MAX_ENTRIES};
 use sync_guid::Guid;
 
 #[derive(Default, Debug)]
@@ -22,6 +22,7 @@ pub(super) struct UpdatePlan {
     // the bool is the `is_overridden` flag, the i64 is ServerTimestamp in millis
     pub mirror_inserts: Vec<(IncomingLogin, i64, bool)>,
     pub mirror_updates: Vec<(IncomingLogin, i64)>,
+    pub merge_log: Vec<MergeLogEntry>,
 }
 
 impl UpdatePlan {
@@ -29,14 +30,23 @@ impl UpdatePlan {
         &mut self,
         local: &EncryptedLogin,
         upstream: (IncomingLogin, ServerTimestamp),
-    ) {
+        encdec: &EncryptorDecryptor,
+    ) -> Result<()> {
         let is_override =
             local.record.time_password_changed > upstream.0.login.record.time_password_changed;
+        // Without a shared parent, every field that differs is a conflict.
+        let conflicts = upstream.0.login.delta(local, encdec)?.changed_fields();
+        if is_override {
+            self.log_merge(local.guid_str(), conflicts, MergeWinner::Local);
+        } else {
+            self.log_merge(upstream.0.login.guid_str(), conflicts, MergeWinner::Remote);
+        }
         self.mirror_inserts
             .push((upstream.0, upstream.1.as_millis(), is_override));
         if !is_override {
             self.delete_local.push(local.guid());
         }
+        Ok(())
     }
 
     pub fn plan_three_way_merge(
@@ -56,7 +66,17 @@ impl UpdatePlan {
         let local_delta = local.login.delta(&shared.login, encdec)?;
         let upstream_delta = upstream.login.delta(&shared.login, encdec)?;
 
-        let merged_delta = local_delta.merge(upstream_delta, remote_age < local_age);
+        let remote_is_newer = remote_age < local_age;
+        self.log_merge(
+            shared.guid_str(),
+            local_delta.conflicting_fields(&upstream_delta),
+            if remote_is_newer {
+                MergeWinner::Remote
+            } else {
+                MergeWinner::Local
+            },
+        );
+        let merged_delta = local_delta.merge(upstream_delta, remote_is_newer);
 
         // Update mirror to upstream
         self.mirror_updates
@@ -69,6 +89,21 @@ impl UpdatePlan {
         Ok(())
     }
 
+    // Records a resolved conflict in the merge log. Only the names of the
+    // fields are logged, never their values.
+    fn log_merge(&mut self, guid: &str, fields: Vec<&'static str>, winner: MergeWinner) {
+        if fields.is_empty() {
+            return;
+        }
+        self.merge_log.push(MergeLogEntry {
+            collection: "passwords".to_string(),
+            guid: guid.to_string(),
+            fields: fields.into_iter().map(String::from).collect(),
+            winner,
+            timestamp: util::system_time_ms_i64(SystemTime::now()),
+        });
+    }
+
     pub fn plan_delete(&mut self, id: Guid) {
         self.delete_local.push(id.clone());
         self.delete_mirror.push(id);
@@ -261,6 +296,35 @@ impl UpdatePlan {
         Ok(())
     }
 
+    fn perform_merge_log_inserts(
+        &self,
+        conn: &Connection,
+        scope: &SqlInterruptScope,
+    ) -> Result<()> {
+        let sql = "
+            INSERT INTO loginsMergeLog (guid, fields, winner, timestamp)
+            VALUES (:guid, :fields, :winner, :timestamp)";
+        let mut stmt = conn.prepare_cached(sql)?;
+        for entry in &self.merge_log {
+            stmt.execute(named_params! {
+                ":guid": entry.guid,
+                ":fields": serde_json::to_string(&entry.fields)?,
+                ":winner": entry.winner.as_str(),
+                ":timestamp": entry.timestamp,
+            })?;
+            scope.err_if_interrupted()?;
+        }
+        // Only keep the most recent entries.
+        conn.execute(
+            "DELETE FROM loginsMergeLog
+             WHERE id NOT IN (
+                 SELECT id FROM loginsMergeLog ORDER BY id DESC LIMIT :max_entries
+             )",
+            named_params! { ":max_entries": MERGE_LOG_MAX_ENTRIES as i64 },
+        )?;
+        Ok(())
+    }
+
     pub fn execute(&self, conn: &Connection, scope: &SqlInterruptScope) -> Result<()> {
         log::debug!(
             "UpdatePlan: deleting {} records...",
@@ -282,6 +346,11 @@ impl UpdatePlan {
             self.local_updates.len()
         );
         self.perform_local_updates(conn, scope)?;
+        log::debug!(
+            "UpdatePlan: Logging {} resolved conflicts...",
+            self.merge_log.len()
+        );
+        self.perform_merge_log_inserts(conn, scope)?;
         Ok(())
     }
 }
@@ -366,6 +435,27 @@ mod tests {
         check_mirror_login(&db, "login2", "new-password2", 21000, true);
     }
 
+    #[test]
+    fn test_merge_log_inserts() {
+        let db = LoginDb::open_in_memory().unwrap();
+        let mut plan = UpdatePlan::default();
+        for i in 0..MERGE_LOG_MAX_ENTRIES + 5 {
+            plan.log_merge(&format!("login{i}"), vec!["password"], MergeWinner::Remote);
+        }
+        // Merges without conflicts aren't logged.
+        plan.log_merge("no-conflicts", vec![], MergeWinner::Local);
+        plan.execute(&db, &db.begin_interrupt_scope().unwrap())
+            .unwrap();
+
+        // Only the most recent entries are kept, newest first.
+        let log = db.get_merge_log().unwrap();
+        assert_eq!(log.len(), MERGE_LOG_MAX_ENTRIES);
+        assert_eq!(log[0].guid, format!("login{}", MERGE_LOG_MAX_ENTRIES + 4));
+        assert_eq!(log[MERGE_LOG_MAX_ENTRIES - 1].guid, "login5");
+        assert_eq!(log[0].fields, vec!["password".to_string()]);
+        assert_eq!(log[0].winner, MergeWinner::Remote);
+    }
+
     #[test]
     fn test_local_updates() {
         let db = LoginDb::open_in_memory().unwrap();
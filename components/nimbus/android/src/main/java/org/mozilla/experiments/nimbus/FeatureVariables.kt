@@ -24,11 +24,13 @@ import org.mozilla.experiments.nimbus.internal.mapValuesNotNull
  *
  *  - `String`
  *  - `Int`
+ *  - `Double`
  *  - `Boolean`
  *
  * ### Types coerced from `String` values:
  *
  *  - `Enum<T>`
+ *  - Colors, as ARGB `Int`s, with `String.asColorInt()`
  *  - Resources
  *      These use `getString` to look up an experiment value, then the app's `Context` to
  *      find a resource. These are `Text` and `Drawable`.
@@ -110,6 +112,28 @@ interface Variables {
 
     fun asIntMap(): Map<String, Int>? = null
 
+    /**
+     * Finds a number typed value for this key. If none exists, `null` is returned.
+     * Integer values are converted to `Double`.
+     *
+     * N.B. the `key` and type `Float` should be listed in the experiment manifest.
+     */
+    fun getDouble(key: String): Double? = null
+
+    /**
+     * Find an array for this key, and returns all the numbers in that array. If none exists, `null`
+     * is returned.
+     */
+    fun getDoubleList(key: String): List<Double>? = null
+
+    /**
+     * Find a map for this key, and returns a map containing all the entries that have numbers
+     * as their values. If none exists, then `null` is returned.
+     */
+    fun getDoubleMap(key: String): Map<String, Double>? = null
+
+    fun asDoubleMap(): Map<String, Double>? = null
+
     /**
      * Finds a boolean typed value for this key. If none exists, `null` is returned.
      *
@@ -284,6 +308,29 @@ interface Variables {
     fun getImageMap(key: String): Map<String, Res<Drawable>>? = getDrawableMap(key)
 }
 
+private val colorRegex = Regex("^#([0-9a-fA-F]{6}|[0-9a-fA-F]{8})$")
+
+/**
+ * Parses a `#RRGGBB` or `#AARRGGBB` string into an ARGB color int, as used by
+ * `android.graphics.Color`. Colors without an alpha component are fully opaque.
+ */
+fun String.asColorInt(): Int? {
+    if (!colorRegex.matches(this)) {
+        return null
+    }
+    val argb = this.substring(1).toLong(16)
+    return if (this.length == 7) {
+        (argb or 0xFF000000L).toInt()
+    } else {
+        argb.toInt()
+    }
+}
+
+/**
+ * The inverse of `String.asColorInt()`.
+ */
+fun Int.asColorString(): String = "#%08X".format(this)
+
 inline fun <reified T : Enum<T>> String.asEnum(): T? = try {
     enumValueOf<T>(this)
 } catch (e: IllegalArgumentException) {
@@ -413,6 +460,11 @@ class JSONVariables(
     override fun getIntMap(key: String) = json.mapOf<Int>(key)
     override fun asIntMap() = json.asMap<Int>()
 
+    override fun getDouble(key: String) = json.value<Number>(key)?.toDouble()
+    override fun getDoubleList(key: String) = json.values<Number>(key)?.map(Number::toDouble)
+    override fun getDoubleMap(key: String) = json.mapOf<Number>(key)?.mapValues { it.value.toDouble() }
+    override fun asDoubleMap() = json.asMap<Number>()?.mapValues { it.value.toDouble() }
+
     override fun getBool(key: String) = json.value<Boolean>(key)
     override fun getBoolList(key: String) = json.values<Boolean>(key)
     override fun getBoolMap(key: String) = json.mapOf<Boolean>(key)
@@ -89,3 +89,35 @@ public extension UIImage {
         accessibilityIdentifier ?? "unknown-image"
     }
 }
+
+public extension UIColor {
+    /// Parses a `#RRGGBB` or `#AARRGGBB` string. Colors without an alpha component are opaque.
+    static func fromHexString(_ hex: String) -> UIColor? {
+        let digits = hex.dropFirst()
+        guard hex.hasPrefix("#"),
+              digits.count == 6 || digits.count == 8,
+              digits.allSatisfy(\.isHexDigit),
+              let argb = UInt32(digits, radix: 16)
+        else {
+            return nil
+        }
+        let alpha = digits.count == 6 ? 0xFF : (argb >> 24) & 0xFF
+        return UIColor(
+            red: CGFloat((argb >> 16) & 0xFF) / 255.0,
+            green: CGFloat((argb >> 8) & 0xFF) / 255.0,
+            blue: CGFloat(argb & 0xFF) / 255.0,
+            alpha: CGFloat(alpha) / 255.0
+        )
+    }
+
+    /// The inverse of ``fromHexString(_:)``, always in the `#AARRGGBB` form.
+    var hexString: String {
+        var red: CGFloat = 0, green: CGFloat = 0, blue: CGFloat = 0, alpha: CGFloat = 0
+        _ = getRed(&red, green: &green, blue: &blue, alpha: &alpha)
+        let component = { (c: CGFloat) in Int((c * 255.0).rounded()) }
+        return String(
+            format: "#%02X%02X%02X%02X",
+            component(alpha), component(red), component(green), component(blue)
+        )
+    }
+}
@@ -53,6 +53,23 @@ public extension String {
     }
 }
 
+public extension Int {
+    func map<V>(_ transform: (Self) throws -> V?) rethrows -> V? {
+        return try transform(self)
+    }
+}
+
+/// Durations are passed around in the `Variables` object as a whole number of milliseconds.
+public extension TimeInterval {
+    static func fromMilliseconds(_ ms: Int) -> TimeInterval {
+        return TimeInterval(ms) / 1000.0
+    }
+
+    var inWholeMilliseconds: Int {
+        return Int((self * 1000.0).rounded())
+    }
+}
+
 public extension Variables {
     func map<V>(_ transform: (Self) throws -> V) rethrows -> V {
         return try transform(self)
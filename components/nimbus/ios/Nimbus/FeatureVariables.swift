@@ -66,6 +66,25 @@ public protocol Variables {
     ///  - Returns: a `[String:Int]` dictonary representing the whole variables object
     func asIntMap() -> [String: Int]?
 
+    /// Finds a number typed value for this key. If none exists, `nil` is returned.
+    ///
+    /// N.B. the `key` and type `Float` should be listed in the experiment manifest.
+    func getDouble(_ key: String) -> Double?
+
+    /// Find an array for this key, and returns all the numbers in that array. If none exists, `nil`
+    /// is returned.
+    func getDoubleList(_ key: String) -> [Double]?
+
+    /// Find a map for this key, and returns a map containing all the entries that have numbers
+    /// as their values. If none exists, then `nil` is returned.
+    func getDoubleMap(_ key: String) -> [String: Double]?
+
+    /// Returns the whole variables object as a Double map
+    /// will return `nil` if it cannot be converted
+    ///  - Note: This function will omit any variables that could not be converted to Doubles
+    ///  - Returns: a `[String:Double]` dictonary representing the whole variables object
+    func asDoubleMap() -> [String: Double]?
+
     /// Finds a boolean typed value for this key. If none exists, `nil` is returned.
     ///
     /// N.B. the `key` and type `String` should be listed in the experiment manifest.
@@ -346,6 +365,22 @@ class JSONVariables: VariablesWithBundle {
         return nil
     }
 
+    func getDouble(_ key: String) -> Double? {
+        return value(key)
+    }
+
+    func getDoubleList(_ key: String) -> [Double]? {
+        return values(key)
+    }
+
+    func getDoubleMap(_ key: String) -> [String: Double]? {
+        return valueMap(key)
+    }
+
+    func asDoubleMap() -> [String: Double]? {
+        return nil
+    }
+
     func getBool(_ key: String) -> Bool? {
         return value(key)
     }
@@ -455,6 +490,22 @@ public class NilVariables: Variables {
         return nil
     }
 
+    public func getDouble(_: String) -> Double? {
+        return nil
+    }
+
+    public func getDoubleList(_: String) -> [Double]? {
+        return nil
+    }
+
+    public func getDoubleMap(_: String) -> [String: Double]? {
+        return nil
+    }
+
+    public func asDoubleMap() -> [String: Double]? {
+        return nil
+    }
+
     public func getBool(_: String) -> Bool? {
         return nil
     }
@@ -49,6 +49,32 @@ BEGIN
     WHERE fk = OLD.place_id;
 END;
 
+-- Sync associates keywords with bookmarks, so these bump the change counter
+-- for all bookmarks with the URL when a keyword is added, changed or removed.
+CREATE TEMP TRIGGER moz_keywords_afterinsert_sync_trigger
+AFTER INSERT ON moz_keywords
+BEGIN
+    UPDATE moz_bookmarks SET
+        syncChangeCounter = syncChangeCounter + 1
+    WHERE fk = NEW.place_id;
+END;
+
+CREATE TEMP TRIGGER moz_keywords_afterupdate_sync_trigger
+AFTER UPDATE ON moz_keywords
+BEGIN
+    UPDATE moz_bookmarks SET
+        syncChangeCounter = syncChangeCounter + 1
+    WHERE fk IN (OLD.place_id, NEW.place_id);
+END;
+
+CREATE TEMP TRIGGER moz_keywords_afterdelete_sync_trigger
+AFTER DELETE ON moz_keywords
+BEGIN
+    UPDATE moz_bookmarks SET
+        syncChangeCounter = syncChangeCounter + 1
+    WHERE fk = OLD.place_id;
+END;
+
 -- Our "global" sync change counter.
 -- It's "global" in the sense that it applies to all bookmarks across all
 -- connections to the same DB.
@@ -74,3 +100,112 @@ AFTER INSERT ON moz_bookmarks_deleted FOR EACH ROW
 BEGIN
     SELECT note_bookmarks_sync_change();
 END;
+
+-- These triggers keep the full-text search index in `moz_places_fts` up to
+-- date. Each one rebuilds the rows for the affected pages from
+-- `moz_places_fts_source`, rather than trying to patch individual columns.
+CREATE TEMP TRIGGER moz_places_fts_afterinsert_trigger
+AFTER INSERT ON moz_places FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = NEW.id;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = NEW.id;
+END;
+
+CREATE TEMP TRIGGER moz_places_fts_afterupdate_trigger
+AFTER UPDATE OF url, title ON moz_places FOR EACH ROW
+WHEN OLD.url IS NOT NEW.url OR OLD.title IS NOT NEW.title
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = NEW.id;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = NEW.id;
+END;
+
+CREATE TEMP TRIGGER moz_places_fts_afterdelete_trigger
+AFTER DELETE ON moz_places FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = OLD.id;
+END;
+
+CREATE TEMP TRIGGER moz_bookmarks_fts_afterinsert_trigger
+AFTER INSERT ON moz_bookmarks FOR EACH ROW WHEN NEW.fk NOT NULL
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = NEW.fk;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = NEW.fk;
+END;
+
+CREATE TEMP TRIGGER moz_bookmarks_fts_afterupdate_trigger
+AFTER UPDATE OF fk, title ON moz_bookmarks FOR EACH ROW
+WHEN NEW.fk NOT NULL OR OLD.fk NOT NULL
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid IN (OLD.fk, NEW.fk);
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id IN (OLD.fk, NEW.fk);
+END;
+
+CREATE TEMP TRIGGER moz_bookmarks_fts_afterdelete_trigger
+AFTER DELETE ON moz_bookmarks FOR EACH ROW WHEN OLD.fk NOT NULL
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = OLD.fk;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = OLD.fk;
+END;
+
+CREATE TEMP TRIGGER moz_tags_relation_fts_afterinsert_trigger
+AFTER INSERT ON moz_tags_relation FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = NEW.place_id;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = NEW.place_id;
+END;
+
+CREATE TEMP TRIGGER moz_tags_relation_fts_afterupdate_trigger
+AFTER UPDATE ON moz_tags_relation FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid IN (OLD.place_id, NEW.place_id);
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id IN (OLD.place_id, NEW.place_id);
+END;
+
+CREATE TEMP TRIGGER moz_tags_relation_fts_afterdelete_trigger
+AFTER DELETE ON moz_tags_relation FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = OLD.place_id;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = OLD.place_id;
+END;
+
+CREATE TEMP TRIGGER moz_tags_fts_afterupdate_trigger
+AFTER UPDATE OF tag ON moz_tags FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts
+    WHERE rowid IN (SELECT place_id FROM moz_tags_relation WHERE tag_id = NEW.id);
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source
+    WHERE id IN (SELECT place_id FROM moz_tags_relation WHERE tag_id = NEW.id);
+END;
+
+CREATE TEMP TRIGGER moz_keywords_fts_afterinsert_trigger
+AFTER INSERT ON moz_keywords FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = NEW.place_id;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = NEW.place_id;
+END;
+
+CREATE TEMP TRIGGER moz_keywords_fts_afterupdate_trigger
+AFTER UPDATE ON moz_keywords FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid IN (OLD.place_id, NEW.place_id);
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id IN (OLD.place_id, NEW.place_id);
+END;
+
+CREATE TEMP TRIGGER moz_keywords_fts_afterdelete_trigger
+AFTER DELETE ON moz_keywords FOR EACH ROW
+BEGIN
+    DELETE FROM moz_places_fts WHERE rowid = OLD.place_id;
+    INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+    SELECT * FROM moz_places_fts_source WHERE id = OLD.place_id;
+END;
@@ -261,6 +261,7 @@ CREATE TABLE IF NOT EXISTS moz_places_metadata (
     document_type INTEGER NOT NULL DEFAULT 0, -- 0=generic, 1=media
     typing_time INTEGER NOT NULL DEFAULT 0,
     key_presses INTEGER NOT NULL DEFAULT 0,
+    unknown_fields TEXT,
 
     FOREIGN KEY(place_id) REFERENCES moz_places(id) ON DELETE CASCADE,
     FOREIGN KEY(search_query_id) REFERENCES moz_places_metadata_search_queries(id) ON DELETE CASCADE,
@@ -273,3 +274,105 @@ CREATE TABLE IF NOT EXISTS moz_places_metadata_search_queries (
     id INTEGER PRIMARY KEY,
     term TEXT NOT NULL UNIQUE
 );
+
+-- History journeys group related metadata records, like pages reached from the
+-- same search, or by following links from one page to the next. Each record
+-- belongs to at most one journey. A journey's title, dates and items are
+-- derived from its records, and it's deleted along with its last record.
+CREATE TABLE IF NOT EXISTS moz_places_metadata_groups (
+    id INTEGER PRIMARY KEY,
+    -- The search that started the journey, if any.
+    search_query_id INTEGER,
+
+    FOREIGN KEY(search_query_id) REFERENCES moz_places_metadata_search_queries(id) ON DELETE SET NULL
+);
+
+CREATE TABLE IF NOT EXISTS moz_places_metadata_group_items (
+    metadata_id INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL,
+
+    FOREIGN KEY(metadata_id) REFERENCES moz_places_metadata(id) ON DELETE CASCADE,
+    FOREIGN KEY(group_id) REFERENCES moz_places_metadata_groups(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS moz_places_metadata_group_items_group_id
+ON moz_places_metadata_group_items(group_id);
+
+----------------------------------------------------------------------
+--------------------Favicons------------------------------------------
+----------------------------------------------------------------------
+
+-- These tables store favicons, in the same shape as Desktop's `moz_icons`,
+-- `moz_pages_w_icons` and `moz_icons_to_pages`. Page URLs are stored
+-- separately from `moz_places`, since we might be given an icon for a page
+-- before we've recorded a visit to it. None of this data is synced.
+CREATE TABLE IF NOT EXISTS moz_icons (
+    id INTEGER PRIMARY KEY,
+    icon_url TEXT NOT NULL,
+    -- The width of the icon in pixels; icons are assumed to be square. 0 means
+    -- the size isn't known, for example for SVG icons.
+    width INTEGER NOT NULL DEFAULT 0,
+    -- For root icons, like `/favicon.ico`, the origin that the icon applies
+    -- to. These are used for pages on that origin without icons of their own.
+    root_origin TEXT,
+    mime_type TEXT,
+    data BLOB NOT NULL,
+    -- When the icon should be fetched again, in milliseconds since the epoch.
+    expire_ms INTEGER NOT NULL DEFAULT 0,
+
+    UNIQUE(icon_url, width)
+);
+
+CREATE INDEX IF NOT EXISTS moz_icons_root_origin ON moz_icons(root_origin)
+                                                 WHERE root_origin NOT NULL;
+
+CREATE TABLE IF NOT EXISTS moz_pages_w_icons (
+    id INTEGER PRIMARY KEY,
+    page_url TEXT NOT NULL,
+    page_url_hash INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS moz_pages_w_icons_urlhashindex ON moz_pages_w_icons(page_url_hash);
+
+CREATE TABLE IF NOT EXISTS moz_icons_to_pages (
+    page_id INTEGER NOT NULL REFERENCES moz_pages_w_icons(id)
+                             ON DELETE CASCADE,
+    icon_id INTEGER NOT NULL REFERENCES moz_icons(id)
+                             ON DELETE CASCADE,
+    PRIMARY KEY(page_id, icon_id)
+) WITHOUT ROWID;
+
+----------------------------------------------------------------------
+--------------------Full-text search----------------------------------
+----------------------------------------------------------------------
+
+-- A full-text index over page titles, URLs, bookmark titles, tags and
+-- keywords, used by `search`. Each row's `rowid` is the `moz_places.id` of the
+-- page it describes. The main connection keeps this up to date with triggers;
+-- Sync doesn't have those triggers, so it updates the index for the pages it
+-- changes explicitly (see `storage::search::update_search_index`).
+CREATE VIRTUAL TABLE IF NOT EXISTS moz_places_fts USING fts5(
+    url,
+    title,
+    -- The title of the most recently modified bookmark for the page.
+    bookmark_title,
+    tags,
+    keyword,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+-- The indexed content for each page, in the same order as the columns of
+-- `moz_places_fts`. Triggers and Sync use this to (re)build rows in the index.
+CREATE VIEW IF NOT EXISTS moz_places_fts_source(id, url, title, bookmark_title, tags, keyword) AS
+SELECT h.id, h.url, h.title,
+       (SELECT b.title FROM moz_bookmarks b
+        WHERE b.fk = h.id
+        ORDER BY b.lastModified DESC
+        LIMIT 1),
+       (SELECT group_concat(t.tag, ' ') FROM moz_tags t
+        JOIN moz_tags_relation r ON r.tag_id = t.id
+        WHERE r.place_id = h.id),
+       (SELECT k.keyword FROM moz_keywords k
+        WHERE k.place_id = h.id)
+FROM moz_places h;
@@ -44,3 +44,29 @@ CREATE TEMP TABLE moz_updateoriginsupdate_temp (
     frecency_delta INTEGER NOT NULL,
     PRIMARY KEY (prefix, host)
 ) WITHOUT ROWID;
+
+-- This table records changes to history and bookmarks for places observers.
+-- It's filled by the moz_places_changes_* triggers, only when observers are
+-- registered, and emptied after every commit, when the changes are delivered.
+-- Columns are only set for the kinds of change which use them; the kinds are
+-- the `PlacesChange` variants, as defined in `observer.rs`.
+CREATE TEMP TABLE moz_places_changes_temp (
+    id INTEGER PRIMARY KEY,
+    kind INTEGER NOT NULL,
+    guid TEXT NOT NULL,
+    url TEXT,
+    title TEXT,
+    -- The `moz_bookmarks` ids of the item and its parent, used to find the
+    -- parent of an item removed along with its parent folder.
+    item_id INTEGER,
+    parent_id INTEGER,
+    parent_guid TEXT,
+    old_parent_guid TEXT,
+    position INTEGER,
+    old_position INTEGER,
+    visit_type INTEGER,
+    visit_date INTEGER,
+    is_local INTEGER,
+    frecency INTEGER,
+    is_removed_from_store INTEGER
+);
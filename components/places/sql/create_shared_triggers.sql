@@ -350,3 +350,161 @@ BEGIN
         SELECT id FROM moz_places_metadata pm WHERE pm.search_query_id = OLD.search_query_id
     );
 END;
+
+-- This trigger removes history journeys once their last metadata record is
+-- deleted. Records are removed from their journeys by `ON DELETE CASCADE`.
+CREATE TEMP TRIGGER moz_places_metadata_group_items_afterdelete_trigger
+AFTER DELETE ON moz_places_metadata_group_items
+FOR EACH ROW WHEN NOT EXISTS (
+    SELECT 1 FROM moz_places_metadata_group_items WHERE group_id = OLD.group_id
+)
+BEGIN
+    DELETE FROM moz_places_metadata_groups WHERE id = OLD.group_id;
+END;
+
+-- The triggers below record changes in moz_places_changes_temp for places
+-- observers. Using triggers means we catch changes from every write path,
+-- including the Sync connection, and since temp tables are transactional too,
+-- changes which are rolled back are never delivered.
+CREATE TEMP TRIGGER moz_places_changes_afterinsert_visit_trigger
+AFTER INSERT ON moz_historyvisits
+FOR EACH ROW WHEN has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, visit_type, visit_date, is_local)
+    SELECT 1, -- VisitAdded
+           guid, url, NEW.visit_type, NEW.visit_date, NEW.is_local
+    FROM moz_places
+    WHERE id = NEW.place_id;
+END;
+
+CREATE TEMP TRIGGER moz_places_changes_afterdelete_page_trigger
+AFTER DELETE ON moz_places
+FOR EACH ROW WHEN has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, is_removed_from_store)
+    VALUES (2, -- PageRemoved
+            OLD.guid, OLD.url, 1);
+END;
+
+-- A page which loses all its visits, but is kept because it's bookmarked, is
+-- also removed from history, but not from the store.
+CREATE TEMP TRIGGER moz_places_changes_afterupdate_visits_trigger
+AFTER UPDATE OF last_visit_date_local, last_visit_date_remote ON moz_places
+FOR EACH ROW WHEN (OLD.last_visit_date_local > 0 OR OLD.last_visit_date_remote > 0) AND
+                  NEW.last_visit_date_local = 0 AND
+                  NEW.last_visit_date_remote = 0 AND
+                  has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, is_removed_from_store)
+    VALUES (2, -- PageRemoved
+            NEW.guid, NEW.url, 0);
+END;
+
+CREATE TEMP TRIGGER moz_places_changes_afterupdate_title_trigger
+AFTER UPDATE OF title ON moz_places
+FOR EACH ROW WHEN OLD.title IS NOT NEW.title AND has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, title)
+    VALUES (3, -- TitleChanged
+            NEW.guid, NEW.url, NEW.title);
+END;
+
+CREATE TEMP TRIGGER moz_places_changes_afterupdate_frecency_trigger
+AFTER UPDATE OF frecency ON moz_places
+FOR EACH ROW WHEN OLD.frecency <> NEW.frecency AND has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, frecency)
+    VALUES (4, -- FrecencyChanged
+            NEW.guid, NEW.url, NEW.frecency);
+END;
+
+-- The roots are inserted before any observers could be registered, so we
+-- don't need to worry about items without parents here. Sync inserts new items
+-- with a position of -1, and moves them into place later, so those are
+-- reported as added when they're moved.
+CREATE TEMP TRIGGER moz_places_changes_afterinsert_bookmark_trigger
+AFTER INSERT ON moz_bookmarks
+FOR EACH ROW WHEN NEW.position >= 0 AND has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, title, item_id, parent_id,
+                                        parent_guid, position)
+    VALUES (5, -- BookmarkAdded
+            NEW.guid,
+            (SELECT url FROM moz_places WHERE id = NEW.fk),
+            NEW.title,
+            NEW.id,
+            NEW.parent,
+            (SELECT guid FROM moz_bookmarks WHERE id = NEW.parent),
+            NEW.position);
+END;
+
+-- Inserting, moving or removing an item shifts the positions of its siblings,
+-- but we don't want to report those as moves. Unlike the siblings, the item
+-- itself always has its last modified time changed when it's moved.
+CREATE TEMP TRIGGER moz_places_changes_afterupdate_bookmark_position_trigger
+AFTER UPDATE OF parent, position ON moz_bookmarks
+FOR EACH ROW WHEN OLD.position >= 0 AND
+                  (OLD.parent IS NOT NEW.parent OR
+                   (OLD.position <> NEW.position AND OLD.lastModified <> NEW.lastModified)) AND
+                  has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, item_id, parent_id, parent_guid,
+                                        old_parent_guid, position, old_position)
+    VALUES (6, -- BookmarkMoved
+            NEW.guid,
+            NEW.id,
+            NEW.parent,
+            (SELECT guid FROM moz_bookmarks WHERE id = NEW.parent),
+            (SELECT guid FROM moz_bookmarks WHERE id = OLD.parent),
+            NEW.position,
+            OLD.position);
+END;
+
+CREATE TEMP TRIGGER moz_places_changes_afterupdate_synced_bookmark_trigger
+AFTER UPDATE OF position ON moz_bookmarks
+FOR EACH ROW WHEN OLD.position < 0 AND NEW.position >= 0 AND has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, title, item_id, parent_id,
+                                        parent_guid, position)
+    VALUES (5, -- BookmarkAdded
+            NEW.guid,
+            (SELECT url FROM moz_places WHERE id = NEW.fk),
+            NEW.title,
+            NEW.id,
+            NEW.parent,
+            (SELECT guid FROM moz_bookmarks WHERE id = NEW.parent),
+            NEW.position);
+END;
+
+CREATE TEMP TRIGGER moz_places_changes_afterupdate_bookmark_trigger
+AFTER UPDATE OF fk, title ON moz_bookmarks
+FOR EACH ROW WHEN (OLD.fk IS NOT NEW.fk OR OLD.title IS NOT NEW.title) AND
+                  has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, title, item_id)
+    VALUES (7, -- BookmarkChanged
+            NEW.guid,
+            (SELECT url FROM moz_places WHERE id = NEW.fk),
+            NEW.title,
+            NEW.id);
+END;
+
+-- When a folder is removed, its children are removed by the foreign key
+-- cascade, after the folder itself is gone. That's why we also record
+-- `parent_id`: the parent is then found among the removed items when the
+-- changes are delivered. This runs before the delete, so that we can still
+-- find the URL of a bookmark whose page is removed along with it.
+CREATE TEMP TRIGGER moz_places_changes_beforedelete_bookmark_trigger
+BEFORE DELETE ON moz_bookmarks
+FOR EACH ROW WHEN has_places_observers()
+BEGIN
+    INSERT INTO moz_places_changes_temp(kind, guid, url, item_id, parent_id,
+                                        parent_guid, position)
+    VALUES (8, -- BookmarkRemoved
+            OLD.guid,
+            (SELECT url FROM moz_places WHERE id = OLD.fk),
+            OLD.id,
+            OLD.parent,
+            (SELECT guid FROM moz_bookmarks WHERE id = OLD.parent),
+            OLD.position);
+END;
@@ -0,0 +1,45 @@
+-- This Source Code Form is subject to the terms of the Mozilla Public
+-- License, v. 2.0. If a copy of the MPL was not distributed with this
+-- file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+-- A minimal Chromium `History` database, with only the tables and columns
+-- used by the import.
+
+CREATE TABLE urls(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url LONGVARCHAR,
+    title LONGVARCHAR,
+    visit_count INTEGER DEFAULT 0 NOT NULL,
+    typed_count INTEGER DEFAULT 0 NOT NULL,
+    last_visit_time INTEGER NOT NULL,
+    hidden INTEGER DEFAULT 0 NOT NULL
+);
+
+CREATE TABLE visits(
+    id INTEGER PRIMARY KEY,
+    url INTEGER NOT NULL,
+    visit_time INTEGER NOT NULL,
+    from_visit INTEGER,
+    transition INTEGER DEFAULT 0 NOT NULL,
+    segment_id INTEGER,
+    visit_duration INTEGER DEFAULT 0 NOT NULL
+);
+
+INSERT INTO urls(id, url, title, visit_count, last_visit_time) VALUES
+    (1, 'https://example.com', 'Example', 2, 13244473700000000),
+    (2, 'https://www.mozillä.org/', '', 1, 13244473800000000),
+    (3, 'https://example.com/frame', 'Frame', 1, 13244473900000000),
+    (4, 'not a url', 'Invalid', 1, 13244474000000000);
+
+-- Times are 1600000000000ms and up, in microseconds since 1601-01-01.
+INSERT INTO visits(id, url, visit_time, from_visit, transition) VALUES
+    -- TYPED | CHAIN_START | CHAIN_END
+    (1, 1, 13244473600000000, 0, 805306369),
+    -- LINK | CHAIN_START | CHAIN_END
+    (2, 1, 13244473700000000, 0, 805306368),
+    -- LINK | CHAIN_END | SERVER_REDIRECT, stored as a signed 32-bit value.
+    (3, 2, 13244473800000000, 2, -1610612736),
+    -- AUTO_SUBFRAME
+    (4, 3, 13244473900000000, 3, 3),
+    -- LINK
+    (5, 4, 13244474000000000, 0, 0);
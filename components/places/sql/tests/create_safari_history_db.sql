@@ -0,0 +1,46 @@
+-- This Source Code Form is subject to the terms of the Mozilla Public
+-- License, v. 2.0. If a copy of the MPL was not distributed with this
+-- file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+-- A minimal Safari `History.db`, with only the tables and columns used by
+-- the import.
+
+CREATE TABLE history_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL UNIQUE,
+    domain_expansion TEXT NULL,
+    visit_count INTEGER NOT NULL
+);
+
+CREATE TABLE history_visits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    history_item INTEGER NOT NULL REFERENCES history_items(id) ON DELETE CASCADE,
+    visit_time REAL NOT NULL,
+    title TEXT NULL,
+    load_successful BOOLEAN NOT NULL DEFAULT 1,
+    http_non_get BOOLEAN NOT NULL DEFAULT 0,
+    synthesized BOOLEAN NOT NULL DEFAULT 0,
+    redirect_source INTEGER NULL UNIQUE REFERENCES history_visits(id) ON DELETE CASCADE,
+    redirect_destination INTEGER NULL UNIQUE REFERENCES history_visits(id) ON DELETE CASCADE,
+    origin INTEGER NOT NULL DEFAULT 0,
+    generation INTEGER NOT NULL DEFAULT 0,
+    attributes INTEGER NOT NULL DEFAULT 0,
+    score INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO history_items(id, url, domain_expansion, visit_count) VALUES
+    (1, 'http://example.com/', 'example', 2),
+    (2, 'https://example.com/', 'example', 1),
+    (3, 'https://failed.example.com/', NULL, 1),
+    (4, 'not a url', NULL, 1);
+
+-- Times are 1600000000000ms and up, in seconds since 2001-01-01.
+INSERT INTO history_visits(id, history_item, visit_time, title, load_successful,
+                           synthesized, redirect_source, redirect_destination, origin) VALUES
+    (1, 1, 621692800.0, 'Old title', 1, 0, NULL, NULL, 0),
+    -- Synced from another device.
+    (2, 1, 621692900.5, 'Example', 1, 0, NULL, 3, 1),
+    (3, 2, 621692900.6, NULL, 1, 0, 2, NULL, 1),
+    (4, 3, 621693000.0, 'Failed', 0, 0, NULL, NULL, 0),
+    (5, 1, 621693100.0, 'Synthesized', 1, 1, NULL, NULL, 0),
+    (6, 4, 621693200.0, 'Invalid', 1, 0, NULL, NULL, 0);
@@ -5,10 +5,13 @@
 use crate::bookmark_sync::BookmarksSyncEngine;
 use crate::db::db::{PlacesDb, SharedPlacesDb};
 use crate::error::*;
+use crate::frecency::FrecencySettings;
 use crate::history_sync::HistorySyncEngine;
+use crate::observer::{self, PlacesObserver};
 use crate::storage::{
     self, bookmarks::bookmark_sync, delete_meta, get_meta, history::history_sync, put_meta,
 };
+use crate::sync_validation::{self, SyncValidationMode};
 use crate::util::normalize_path;
 use error_support::handle_error;
 use interrupt_support::register_interrupt;
@@ -134,6 +137,14 @@ pub fn places_api_new(db_name: impl AsRef<Path>) -> ApiResult<Arc<PlacesApi>> {
     PlacesApi::new(db_name)
 }
 
+#[handle_error(crate::Error)]
+pub fn places_api_new_with_frecency_settings(
+    db_name: impl AsRef<Path>,
+    frecency_settings: FrecencySettings,
+) -> ApiResult<Arc<PlacesApi>> {
+    PlacesApi::new_with_frecency_settings(db_name, frecency_settings)
+}
+
 /// The entry-point to the places API. This object gives access to database
 /// connections and other helpers. It enforces that only 1 write connection
 /// can exist to the database at once.
@@ -152,13 +163,26 @@ pub struct PlacesApi {
     //   ran that at the same time there would be issues.
     sync_connection: Mutex<Weak<SharedPlacesDb>>,
     id: usize,
+    frecency_settings: Arc<FrecencySettings>,
 }
 
 impl PlacesApi {
     /// Create a new, or fetch an already open, PlacesApi backed by a file on disk.
     pub fn new(db_name: impl AsRef<Path>) -> Result<Arc<Self>> {
+        Self::new_with_frecency_settings(db_name, FrecencySettings::default())
+    }
+
+    /// Like `new`, but calculates frecencies with the given settings instead
+    /// of the defaults. If the settings are different from the last time the
+    /// database was opened, all frecencies are flagged as stale, and will be
+    /// recalculated by `recalculate_stale_frecencies`. If an API for this
+    /// file is already open, it's returned as-is, and keeps its settings.
+    pub fn new_with_frecency_settings(
+        db_name: impl AsRef<Path>,
+        frecency_settings: FrecencySettings,
+    ) -> Result<Arc<Self>> {
         let db_name = normalize_path(db_name)?;
-        Self::new_or_existing(db_name)
+        Self::new_or_existing(db_name, frecency_settings)
     }
 
     /// Create a new, or fetch an already open, memory-based PlacesApi. You must
@@ -166,11 +190,12 @@ impl PlacesApi {
     ///  reader connections to the same memory DB open.
     pub fn new_memory(db_name: &str) -> Result<Arc<Self>> {
         let name = PathBuf::from(format!("file:{}?mode=memory&cache=shared", db_name));
-        Self::new_or_existing(name)
+        Self::new_or_existing(name, FrecencySettings::default())
     }
     fn new_or_existing_into(
         target: &mut HashMap<PathBuf, Weak<PlacesApi>>,
         db_name: PathBuf,
+        frecency_settings: FrecencySettings,
     ) -> Result<Arc<Self>> {
         let id = ID_COUNTER.fetch_add(1, Ordering::SeqCst);
         match target.get(&db_name).and_then(Weak::upgrade) {
@@ -179,12 +204,15 @@ impl PlacesApi {
                 // We always create a new read-write connection for an initial open so
                 // we can create the schema and/or do version upgrades.
                 let coop_tx_lock = Arc::new(Mutex::new(()));
+                let frecency_settings = Arc::new(frecency_settings);
                 let connection = PlacesDb::open(
                     &db_name,
                     ConnectionType::ReadWrite,
                     id,
                     coop_tx_lock.clone(),
+                    frecency_settings.clone(),
                 )?;
+                storage::note_frecency_settings(&connection)?;
                 let new = PlacesApi {
                     db_name: db_name.clone(),
                     write_connection: Mutex::new(Some(connection)),
@@ -192,6 +220,7 @@ impl PlacesApi {
                     sync_connection: Mutex::new(Weak::new()),
                     id,
                     coop_tx_lock,
+                    frecency_settings,
                 };
                 let arc = Arc::new(new);
                 target.insert(db_name, Arc::downgrade(&arc));
@@ -200,9 +229,9 @@ impl PlacesApi {
         }
     }
 
-    fn new_or_existing(db_name: PathBuf) -> Result<Arc<Self>> {
+    fn new_or_existing(db_name: PathBuf, frecency_settings: FrecencySettings) -> Result<Arc<Self>> {
         let mut guard = APIS.lock();
-        Self::new_or_existing_into(&mut guard, db_name)
+        Self::new_or_existing_into(&mut guard, db_name, frecency_settings)
     }
 
     /// Open a connection to the database.
@@ -215,6 +244,7 @@ impl PlacesApi {
                     ConnectionType::ReadOnly,
                     self.id,
                     self.coop_tx_lock.clone(),
+                    self.frecency_settings.clone(),
                 )
             }
             ConnectionType::ReadWrite => {
@@ -251,6 +281,7 @@ impl PlacesApi {
                     ConnectionType::Sync,
                     self.id,
                     self.coop_tx_lock.clone(),
+                    self.frecency_settings.clone(),
                 )?));
                 register_interrupt(Arc::<SharedPlacesDb>::downgrade(&db));
                 // Store a weakref for next time
@@ -295,6 +326,18 @@ impl PlacesApi {
         *PLACES_API_FOR_SYNC_MANAGER.lock() = Arc::downgrade(&self);
     }
 
+    /// Registers an observer to be notified of changes to history and
+    /// bookmarks made through any of this API's connections, including the
+    /// sync connection. Returns an id which can be passed to
+    /// `unregister_observer`.
+    pub fn register_observer(&self, observer: Box<dyn PlacesObserver>) -> u64 {
+        observer::register_observer(self.id, observer.into())
+    }
+
+    pub fn unregister_observer(&self, observer_id: u64) {
+        observer::unregister_observer(self.id, observer_id)
+    }
+
     // NOTE: These should be deprecated as soon as possible - that will be once
     // all consumers have been updated to use the .sync() method below, and/or
     // we have implemented the sync manager and migrated consumers to that.
@@ -465,6 +508,23 @@ impl PlacesApi {
         history_sync::reset(&conn.lock(), &EngineSyncAssociation::Disconnected)?;
         Ok(())
     }
+
+    /// Sets whether the bookmarks and history engines validate their data
+    /// against the server when they sync, at most once a day, and whether they
+    /// repair the problems they find. The mode is remembered between launches.
+    #[handle_error(crate::Error)]
+    pub fn set_sync_validation_mode(&self, mode: SyncValidationMode) -> ApiResult<()> {
+        let conn = self.get_sync_connection()?;
+        sync_validation::set_sync_validation_mode(&conn.lock(), mode)?;
+        Ok(())
+    }
+}
+
+impl Drop for PlacesApi {
+    fn drop(&mut self) {
+        // Observers are only notified while their API is alive.
+        observer::unregister_all_observers(self.id);
+    }
 }
 
 #[cfg(test)]
@@ -7,16 +7,21 @@ use super::record::{
     BookmarkItemRecord, BookmarkRecord, BookmarkRecordId, FolderRecord, QueryRecord,
     SeparatorRecord,
 };
+use super::validation::{self, LAST_VALIDATION_META_KEY};
 use super::{SyncedBookmarkKind, SyncedBookmarkValidity};
 use crate::db::{GlobalChangeCounterTracker, PlacesDb, SharedPlacesDb};
 use crate::error::*;
-use crate::frecency::{calculate_frecency, DEFAULT_FRECENCY_SETTINGS};
+use crate::frecency::calculate_frecency;
 use crate::storage::{
     bookmarks::{
         bookmark_sync::{create_synced_bookmark_roots, reset},
         BookmarkRootGuid,
     },
     delete_pending_temp_tables, get_meta, put_meta,
+    search::update_search_index,
+};
+use crate::sync_validation::{
+    get_sync_validation_mode, note_validated, validation_mode_if_due, SyncValidationMode,
 };
 use crate::types::{BookmarkType, SyncStatus, UnknownFields};
 use dogear::{
@@ -31,7 +36,9 @@ use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 use sync15::bso::{IncomingBso, OutgoingBso};
-use sync15::engine::{CollSyncIds, CollectionRequest, EngineSyncAssociation, SyncEngine};
+use sync15::engine::{
+    CollSyncIds, CollectionRequest, EngineSyncAssociation, OutgoingRecords, SyncEngine,
+};
 use sync15::{telemetry, CollectionName, ServerTimestamp};
 use sync_guid::Guid as SyncGuid;
 use types::Timestamp;
@@ -48,6 +55,9 @@ pub const COLLECTION_NAME: &str = "bookmarks";
 /// blocking writes from other connections.
 const MAX_FRECENCIES_TO_RECALCULATE_PER_CHUNK: usize = 400;
 
+/// The maximum number of outgoing records to read from the database at once.
+const OUTGOING_CHUNK_SIZE: usize = 500;
+
 /// Adapts an interruptee to a Dogear abort signal.
 struct MergeInterruptee<'a>(&'a SqlInterruptScope);
 
@@ -533,6 +543,20 @@ fn apply_remote_items(db: &PlacesDb, scope: &SqlInterruptScope, now: Timestamp)
          JOIN moz_bookmarks_synced_tag_relation r ON r.itemId = n.remoteId",
     )?;
 
+    // The Sync connection doesn't have the triggers that maintain the search
+    // index, so we need to update it for changed URLs ourselves. This
+    // includes URLs for deleted bookmarks, which were flagged as stale above.
+    log::debug!("Updating the search index for changed URLs");
+    scope.err_if_interrupted()?;
+    update_search_index(
+        db,
+        "SELECT oldPlaceId FROM itemsToApply WHERE oldPlaceId NOT NULL
+         UNION
+         SELECT newPlaceId FROM itemsToApply WHERE newPlaceId NOT NULL
+         UNION
+         SELECT place_id FROM moz_places_stale_frecencies",
+    )?;
+
     Ok(())
 }
 
@@ -639,135 +663,180 @@ fn stage_items_to_upload(
     Ok(())
 }
 
-/// Inflates Sync records for all staged outgoing items.
-fn fetch_outgoing_records(db: &PlacesDb, scope: &SqlInterruptScope) -> Result<Vec<OutgoingBso>> {
-    let mut changes = Vec::new();
-    let mut child_record_ids_by_local_parent_id: HashMap<i64, Vec<BookmarkRecordId>> =
-        HashMap::new();
-    let mut tags_by_local_id: HashMap<i64, Vec<String>> = HashMap::new();
+/// Inflates Sync records for the staged outgoing items, a chunk at a time.
+/// The structure and tags for the items are small, so we read them all up
+/// front, but the items themselves are only read as each chunk is fetched.
+struct OutgoingItems {
+    child_record_ids_by_local_parent_id: HashMap<i64, Vec<BookmarkRecordId>>,
+    tags_by_local_id: HashMap<i64, Vec<String>>,
+    // The local ID of the last item we fetched.
+    last_id: i64,
+}
 
-    let mut stmt = db.prepare(
-        "SELECT parentId, guid FROM structureToUpload
-         ORDER BY parentId, position",
-    )?;
-    let mut results = stmt.query([])?;
-    while let Some(row) = results.next()? {
-        scope.err_if_interrupted()?;
-        let local_parent_id = row.get::<_, i64>("parentId")?;
-        let child_guid = row.get::<_, SyncGuid>("guid")?;
-        let child_record_ids = child_record_ids_by_local_parent_id
-            .entry(local_parent_id)
-            .or_default();
-        child_record_ids.push(child_guid.into());
-    }
+impl OutgoingItems {
+    fn new(db: &PlacesDb, scope: &SqlInterruptScope) -> Result<Self> {
+        let mut child_record_ids_by_local_parent_id: HashMap<i64, Vec<BookmarkRecordId>> =
+            HashMap::new();
+        let mut tags_by_local_id: HashMap<i64, Vec<String>> = HashMap::new();
 
-    let mut stmt = db.prepare("SELECT id, tag FROM tagsToUpload")?;
-    let mut results = stmt.query([])?;
-    while let Some(row) = results.next()? {
-        scope.err_if_interrupted()?;
-        let local_id = row.get::<_, i64>("id")?;
-        let tag = row.get::<_, String>("tag")?;
-        let tags = tags_by_local_id.entry(local_id).or_default();
-        tags.push(tag);
-    }
+        let mut stmt = db.prepare(
+            "SELECT parentId, guid FROM structureToUpload
+             ORDER BY parentId, position",
+        )?;
+        let mut results = stmt.query([])?;
+        while let Some(row) = results.next()? {
+            scope.err_if_interrupted()?;
+            let local_parent_id = row.get::<_, i64>("parentId")?;
+            let child_guid = row.get::<_, SyncGuid>("guid")?;
+            let child_record_ids = child_record_ids_by_local_parent_id
+                .entry(local_parent_id)
+                .or_default();
+            child_record_ids.push(child_guid.into());
+        }
 
-    let mut stmt = db.prepare(
-        "SELECT i.id, i.syncChangeCounter, i.guid, i.isDeleted, i.kind, i.keyword,
-                i.url, IFNULL(i.title, '') AS title, i.position, i.parentGuid,
-                IFNULL(i.parentTitle, '') AS parentTitle, i.dateAdded, m.unknownFields
-         FROM itemsToUpload i
-         LEFT JOIN moz_bookmarks_synced m ON i.guid == m.guid
-         ",
-    )?;
-    let mut results = stmt.query([])?;
-    while let Some(row) = results.next()? {
-        scope.err_if_interrupted()?;
-        let guid = row.get::<_, SyncGuid>("guid")?;
-        let is_deleted = row.get::<_, bool>("isDeleted")?;
-        if is_deleted {
-            changes.push(OutgoingBso::new_tombstone(
-                BookmarkRecordId::from(guid).as_guid().clone().into(),
-            ));
-            continue;
+        let mut stmt = db.prepare("SELECT id, tag FROM tagsToUpload")?;
+        let mut results = stmt.query([])?;
+        while let Some(row) = results.next()? {
+            scope.err_if_interrupted()?;
+            let local_id = row.get::<_, i64>("id")?;
+            let tag = row.get::<_, String>("tag")?;
+            let tags = tags_by_local_id.entry(local_id).or_default();
+            tags.push(tag);
         }
-        let parent_guid = row.get::<_, SyncGuid>("parentGuid")?;
-        let parent_title = row.get::<_, String>("parentTitle")?;
-        let date_added = row.get::<_, i64>("dateAdded")?;
-        let unknown_fields = match row.get::<_, Option<String>>("unknownFields")? {
-            None => UnknownFields::new(),
-            Some(s) => serde_json::from_str(&s)?,
-        };
-        let record: BookmarkItemRecord = match SyncedBookmarkKind::from_u8(row.get("kind")?)? {
-            SyncedBookmarkKind::Bookmark => {
-                let local_id = row.get::<_, i64>("id")?;
-                let title = row.get::<_, String>("title")?;
-                let url = row.get::<_, String>("url")?;
-                BookmarkRecord {
-                    record_id: guid.into(),
-                    parent_record_id: Some(parent_guid.into()),
-                    parent_title: Some(parent_title),
-                    date_added: Some(date_added),
-                    has_dupe: true,
-                    title: Some(title),
-                    url: Some(url),
-                    keyword: row.get::<_, Option<String>>("keyword")?,
-                    tags: tags_by_local_id.remove(&local_id).unwrap_or_default(),
-                    unknown_fields,
-                }
-                .into()
+
+        Ok(Self {
+            child_record_ids_by_local_parent_id,
+            tags_by_local_id,
+            last_id: i64::MIN,
+        })
+    }
+
+    /// Returns records for the next `limit` staged items, or `None` once
+    /// they've all been fetched.
+    fn fetch_chunk(
+        &mut self,
+        db: &PlacesDb,
+        scope: &SqlInterruptScope,
+        limit: usize,
+    ) -> Result<Option<Vec<OutgoingBso>>> {
+        let mut changes = Vec::new();
+        let mut stmt = db.prepare_cached(
+            "SELECT i.id, i.syncChangeCounter, i.guid, i.isDeleted, i.kind, i.keyword,
+                    i.url, IFNULL(i.title, '') AS title, i.position, i.parentGuid,
+                    IFNULL(i.parentTitle, '') AS parentTitle, i.dateAdded, m.unknownFields
+             FROM itemsToUpload i
+             LEFT JOIN moz_bookmarks_synced m ON i.guid == m.guid
+             WHERE i.id > :last_id
+             ORDER BY i.id
+             LIMIT :limit",
+        )?;
+        let mut results = stmt.query(rusqlite::named_params! {
+            ":last_id": self.last_id,
+            ":limit": limit as i64,
+        })?;
+        let mut has_rows = false;
+        while let Some(row) = results.next()? {
+            scope.err_if_interrupted()?;
+            has_rows = true;
+            self.last_id = row.get::<_, i64>("id")?;
+            let guid = row.get::<_, SyncGuid>("guid")?;
+            let is_deleted = row.get::<_, bool>("isDeleted")?;
+            if is_deleted {
+                changes.push(OutgoingBso::new_tombstone(
+                    BookmarkRecordId::from(guid).as_guid().clone().into(),
+                ));
+                continue;
             }
-            SyncedBookmarkKind::Query => {
-                let title = row.get::<_, String>("title")?;
-                let url = row.get::<_, String>("url")?;
-                QueryRecord {
-                    record_id: guid.into(),
-                    parent_record_id: Some(parent_guid.into()),
-                    parent_title: Some(parent_title),
-                    date_added: Some(date_added),
-                    has_dupe: true,
-                    title: Some(title),
-                    url: Some(url),
-                    tag_folder_name: None,
-                    unknown_fields,
+            let parent_guid = row.get::<_, SyncGuid>("parentGuid")?;
+            let parent_title = row.get::<_, String>("parentTitle")?;
+            let date_added = row.get::<_, i64>("dateAdded")?;
+            let unknown_fields = match row.get::<_, Option<String>>("unknownFields")? {
+                None => UnknownFields::new(),
+                Some(s) => serde_json::from_str(&s)?,
+            };
+            let record: BookmarkItemRecord = match SyncedBookmarkKind::from_u8(row.get("kind")?)? {
+                SyncedBookmarkKind::Bookmark => {
+                    let local_id = row.get::<_, i64>("id")?;
+                    let title = row.get::<_, String>("title")?;
+                    let url = row.get::<_, String>("url")?;
+                    BookmarkRecord {
+                        record_id: guid.into(),
+                        parent_record_id: Some(parent_guid.into()),
+                        parent_title: Some(parent_title),
+                        date_added: Some(date_added),
+                        has_dupe: true,
+                        title: Some(title),
+                        url: Some(url),
+                        keyword: row.get::<_, Option<String>>("keyword")?,
+                        tags: self.tags_by_local_id.remove(&local_id).unwrap_or_default(),
+                        unknown_fields,
+                    }
+                    .into()
                 }
-                .into()
-            }
-            SyncedBookmarkKind::Folder => {
-                let title = row.get::<_, String>("title")?;
-                let local_id = row.get::<_, i64>("id")?;
-                let children = child_record_ids_by_local_parent_id
-                    .remove(&local_id)
-                    .unwrap_or_default();
-                FolderRecord {
-                    record_id: guid.into(),
-                    parent_record_id: Some(parent_guid.into()),
-                    parent_title: Some(parent_title),
-                    date_added: Some(date_added),
-                    has_dupe: true,
-                    title: Some(title),
-                    children,
-                    unknown_fields,
+                SyncedBookmarkKind::Query => {
+                    let title = row.get::<_, String>("title")?;
+                    let url = row.get::<_, String>("url")?;
+                    QueryRecord {
+                        record_id: guid.into(),
+                        parent_record_id: Some(parent_guid.into()),
+                        parent_title: Some(parent_title),
+                        date_added: Some(date_added),
+                        has_dupe: true,
+                        title: Some(title),
+                        url: Some(url),
+                        tag_folder_name: None,
+                        unknown_fields,
+                    }
+                    .into()
                 }
-                .into()
-            }
-            SyncedBookmarkKind::Livemark => continue,
-            SyncedBookmarkKind::Separator => {
-                let position = row.get::<_, i64>("position")?;
-                SeparatorRecord {
-                    record_id: guid.into(),
-                    parent_record_id: Some(parent_guid.into()),
-                    parent_title: Some(parent_title),
-                    date_added: Some(date_added),
-                    has_dupe: true,
-                    position: Some(position),
-                    unknown_fields,
+                SyncedBookmarkKind::Folder => {
+                    let title = row.get::<_, String>("title")?;
+                    let local_id = row.get::<_, i64>("id")?;
+                    let children = self
+                        .child_record_ids_by_local_parent_id
+                        .remove(&local_id)
+                        .unwrap_or_default();
+                    FolderRecord {
+                        record_id: guid.into(),
+                        parent_record_id: Some(parent_guid.into()),
+                        parent_title: Some(parent_title),
+                        date_added: Some(date_added),
+                        has_dupe: true,
+                        title: Some(title),
+                        children,
+                        unknown_fields,
+                    }
+                    .into()
                 }
-                .into()
-            }
-        };
-        changes.push(OutgoingBso::from_content_with_id(record)?);
+                SyncedBookmarkKind::Livemark => continue,
+                SyncedBookmarkKind::Separator => {
+                    let position = row.get::<_, i64>("position")?;
+                    SeparatorRecord {
+                        record_id: guid.into(),
+                        parent_record_id: Some(parent_guid.into()),
+                        parent_title: Some(parent_title),
+                        date_added: Some(date_added),
+                        has_dupe: true,
+                        position: Some(position),
+                        unknown_fields,
+                    }
+                    .into()
+                }
+            };
+            changes.push(OutgoingBso::from_content_with_id(record)?);
+        }
+        Ok(if has_rows { Some(changes) } else { None })
     }
+}
 
+/// Inflates Sync records for all staged outgoing items at once.
+#[cfg(test)]
+fn fetch_outgoing_records(db: &PlacesDb, scope: &SqlInterruptScope) -> Result<Vec<OutgoingBso>> {
+    let mut items = OutgoingItems::new(db, scope)?;
+    let mut changes = Vec::new();
+    while let Some(chunk) = items.fetch_chunk(db, scope, OUTGOING_CHUNK_SIZE)? {
+        changes.extend(chunk);
+    }
     Ok(changes)
 }
 
@@ -834,8 +903,7 @@ pub(crate) fn update_frecencies(db: &PlacesDb, scope: &SqlInterruptScope) -> Res
             // Frecency recalculation runs several statements, so check to
             // make sure we aren't interrupted before each calculation.
             scope.err_if_interrupted()?;
-            let frecency =
-                calculate_frecency(db, &DEFAULT_FRECENCY_SETTINGS, place_id, Some(false))?;
+            let frecency = calculate_frecency(db, db.frecency_settings(), place_id, Some(false))?;
             frecencies.push((place_id, frecency));
         }
         if frecencies.is_empty() {
@@ -921,11 +989,11 @@ impl SyncEngine for BookmarksSyncEngine {
         Ok(())
     }
 
-    fn apply(
+    fn apply_chunked(
         &self,
         timestamp: ServerTimestamp,
         telem: &mut telemetry::Engine,
-    ) -> anyhow::Result<Vec<OutgoingBso>> {
+    ) -> anyhow::Result<OutgoingRecords<'_>> {
         let conn = self.db.lock();
         // write the timestamp now, so if we are interrupted merging or
         // creating outgoing changesets we don't need to re-apply the same
@@ -935,7 +1003,13 @@ impl SyncEngine for BookmarksSyncEngine {
         // Merge.
         let mut merger = Merger::with_telemetry(&conn, &self.scope, timestamp, telem);
         merger.merge()?;
-        Ok(fetch_outgoing_records(&conn, &self.scope)?)
+
+        // The merge staged the outgoing items in `itemsToUpload`, which we
+        // turn into records as they're uploaded.
+        let mut items = OutgoingItems::new(&conn, &self.scope)?;
+        Ok(OutgoingRecords::from_fn(move || {
+            Ok(items.fetch_chunk(&self.db.lock(), &self.scope, OUTGOING_CHUNK_SIZE)?)
+        }))
     }
 
     fn set_uploaded(
@@ -972,6 +1046,32 @@ impl SyncEngine for BookmarksSyncEngine {
         })
     }
 
+    fn get_validation_request(&self) -> anyhow::Result<Option<CollectionRequest>> {
+        let conn = self.db.lock();
+        // There's nothing to compare until we've synced.
+        if get_meta::<i64>(&conn, LAST_SYNC_META_KEY)?.is_none() {
+            return Ok(None);
+        }
+        let mode = validation_mode_if_due(&conn, LAST_VALIDATION_META_KEY, Timestamp::now())?;
+        Ok(match mode {
+            SyncValidationMode::Disabled => None,
+            _ => Some(CollectionRequest::new(self.collection_name()).full()),
+        })
+    }
+
+    fn validate(
+        &self,
+        server_records: Vec<IncomingBso>,
+        telem: &mut telemetry::Engine,
+    ) -> anyhow::Result<Vec<SyncGuid>> {
+        let conn = self.db.lock();
+        let now = Timestamp::now();
+        let mode = get_sync_validation_mode(&conn)?;
+        let redownload = validation::validate(&conn, &self.scope, mode, server_records, telem)?;
+        note_validated(&conn, LAST_VALIDATION_META_KEY, now)?;
+        Ok(redownload)
+    }
+
     fn get_sync_assoc(&self) -> anyhow::Result<EngineSyncAssociation> {
         let conn = self.db.lock();
         let global = get_meta(&conn, GLOBAL_SYNCID_META_KEY)?;
@@ -2156,6 +2256,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_apply_notifies_observers() -> Result<()> {
+        use crate::observer::{test::CommittedObserver, PlacesChange};
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let api = PlacesApi::new(tmpdir.path().join("places.sqlite"))?;
+        let observer = CommittedObserver::register(&api)?;
+        let engine = create_sync_engine(&api);
+        engine_apply_incoming(
+            &engine,
+            vec![
+                IncomingBso::from_test_content(json!({
+                    "id": "bookmark1___",
+                    "type": "bookmark",
+                    "parentid": "unfiled",
+                    "parentName": "Unfiled Bookmarks",
+                    "dateAdded": 1_381_542_355_843u64,
+                    "title": "Some bookmark",
+                    "bmkUri": "http://example.com",
+                })),
+                IncomingBso::from_test_content(json!({
+                    "id": "unfiled",
+                    "type": "folder",
+                    "parentid": "places",
+                    "dateAdded": 1_381_542_355_843u64,
+                    "title": "Unfiled",
+                    "children": ["bookmark1___"],
+                })),
+            ],
+        );
+
+        // `take` checks that the bookmark was committed before we were told
+        // about it.
+        let added: Vec<_> = observer
+            .take()
+            .into_iter()
+            .flatten()
+            .filter(|change| matches!(change, PlacesChange::BookmarkAdded { .. }))
+            .collect();
+        assert_eq!(
+            added,
+            vec![PlacesChange::BookmarkAdded {
+                guid: "bookmark1___".into(),
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: 0,
+                url: Some(Url::parse("http://example.com")?),
+                title: Some("Some bookmark".into()),
+            }]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_apply_complex_bookmark_tags() -> Result<()> {
         let api = new_mem_api();
@@ -3207,6 +3359,88 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_upload_local_tag_and_keyword_changes() -> Result<()> {
+        use crate::storage::keywords;
+
+        let api = new_mem_api();
+        let writer = api.open_connection(ConnectionType::ReadWrite)?;
+        let url = Url::parse("http://example.com/a/%s")?;
+
+        let records = vec![
+            json!({
+                "id": "toolbar",
+                "type": "folder",
+                "parentid": "places",
+                "parentName": "",
+                "dateAdded": 0,
+                "title": "toolbar",
+                "children": ["bookmarkAAAA"],
+            }),
+            json!({
+                "id": "bookmarkAAAA",
+                "type": "bookmark",
+                "parentid": "toolbar",
+                "parentName": "toolbar",
+                "dateAdded": 1_552_183_116_885u64,
+                "title": "A",
+                "bmkUri": "http://example.com/a/%s",
+                "keyword": "a",
+                "tags": ["one"],
+            }),
+        ];
+
+        let engine = create_sync_engine(&api);
+        let incoming = records
+            .into_iter()
+            .map(IncomingBso::from_test_content)
+            .collect();
+        let outgoing = engine_apply_incoming(&engine, incoming);
+        let outgoing_ids = outgoing
+            .iter()
+            .map(|p| p.envelope.id.clone())
+            .collect::<Vec<_>>();
+        engine
+            .set_uploaded(ServerTimestamp(0), outgoing_ids)
+            .expect("Should push synced changes back to the engine");
+        engine.sync_finished().expect("should work");
+
+        tags::rename_tag(&writer, "one", "uno")?;
+        tags::tag_url(&writer, &url, "two")?;
+        keywords::set_keyword_for_url(&writer, &url, "b")?;
+
+        let outgoing = engine_apply_incoming(&engine, vec![]);
+        assert_eq!(outgoing.len(), 1);
+        let bk = outgoing[0].to_test_incoming_t::<BookmarkRecord>();
+        assert_eq!(bk.record_id.as_guid(), "bookmarkAAAA");
+        assert_eq!(bk.keyword.as_deref(), Some("b"));
+        let mut tags = bk.tags.clone();
+        tags.sort();
+        assert_eq!(tags, vec!["two".to_owned(), "uno".to_owned()]);
+
+        engine
+            .set_uploaded(ServerTimestamp(0), vec![outgoing[0].envelope.id.clone()])
+            .expect("Should push synced changes back to the engine");
+        engine.sync_finished().expect("should work");
+
+        // The uploaded tags and keyword should be written back to the synced
+        // bookmarks table.
+        let synced_item =
+            SyncedBookmarkItem::get(&writer, &"bookmarkAAAA".into())?.expect("A should exist");
+        assert_eq!(
+            synced_item,
+            *SyncedBookmarkItem::new()
+                .validity(SyncedBookmarkValidity::Valid)
+                .keyword(Some("b"))
+                .tags(vec!["two".into(), "uno".into()])
+        );
+
+        // And there should be nothing left to upload.
+        assert!(engine_apply_incoming(&engine, vec![]).is_empty());
+
+        Ok(())
+    }
+
     #[test]
     fn test_apply_complex_bookmark_keywords() -> Result<()> {
         use crate::storage::bookmarks::bookmarks_get_url_for_keyword;
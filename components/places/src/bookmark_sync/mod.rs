@@ -5,6 +5,7 @@
 pub mod engine;
 mod incoming;
 pub mod record;
+mod validation;
 
 #[cfg(test)]
 mod tests;
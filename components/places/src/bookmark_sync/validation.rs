@@ -0,0 +1,621 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Validates the local bookmark tree against every record on the server.
+//
+// The merge already reports problems in the synced tree, but only sees the
+// records we've downloaded. The validator fetches the whole collection, and
+// looks for problems in the server's tree, differences between the server
+// and the local tree, and server records that are missing from, or stale
+// in, `moz_bookmarks_synced`. Server records changed since the last sync,
+// and local items with unsynced changes, aren't compared, because this sync
+// will merge them anyway.
+//
+// In repair mode, problems are fixed by flagging the local items for upload,
+// so the local tree replaces what's on the server, and by downloading server
+// records that we're missing again.
+
+use super::engine::LAST_SYNC_META_KEY;
+use super::record::{BookmarkItemRecord, BookmarkRecordId};
+use crate::db::PlacesDb;
+use crate::error::*;
+use crate::storage::bookmarks::{BookmarkRootGuid, USER_CONTENT_ROOTS};
+use crate::storage::get_meta;
+use crate::sync_validation::SyncValidationMode;
+use crate::types::{BookmarkType, SyncStatus};
+use interrupt_support::SqlInterruptScope;
+use sql_support::ConnExt;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use sync15::bso::{IncomingBso, IncomingKind};
+use sync15::{telemetry, ServerTimestamp};
+use sync_guid::Guid as SyncGuid;
+
+/// The version reported in the validation telemetry. Problems reported by the
+/// merge don't have a version.
+const VALIDATION_VERSION: u32 = 1;
+
+pub const LAST_VALIDATION_META_KEY: &str = "bookmarks_last_validation_time";
+
+struct ServerItem {
+    parent: Option<SyncGuid>,
+    is_folder: bool,
+    children: Vec<SyncGuid>,
+    modified: ServerTimestamp,
+}
+
+#[derive(Default)]
+struct ServerTree {
+    items: HashMap<SyncGuid, ServerItem>,
+    tombstones: HashMap<SyncGuid, ServerTimestamp>,
+    malformed: Vec<SyncGuid>,
+}
+
+impl ServerTree {
+    fn new(records: Vec<IncomingBso>) -> Self {
+        let mut tree = ServerTree::default();
+        for record in records {
+            let modified = record.envelope.modified;
+            let content = record.into_content::<BookmarkItemRecord>();
+            let guid = BookmarkRecordId::from_payload_id(content.envelope.id.clone()).into();
+            match content.kind {
+                IncomingKind::Tombstone => {
+                    tree.tombstones.insert(guid, modified);
+                }
+                IncomingKind::Content(item) => {
+                    let (parent, is_folder, children) = match item {
+                        BookmarkItemRecord::Folder(f) => (
+                            f.parent_record_id,
+                            true,
+                            f.children.into_iter().map(Into::into).collect(),
+                        ),
+                        BookmarkItemRecord::Bookmark(b) => (b.parent_record_id, false, vec![]),
+                        BookmarkItemRecord::Query(q) => (q.parent_record_id, false, vec![]),
+                        BookmarkItemRecord::Livemark(l) => (l.parent_record_id, false, vec![]),
+                        BookmarkItemRecord::Separator(s) => (s.parent_record_id, false, vec![]),
+                    };
+                    tree.items.insert(
+                        guid,
+                        ServerItem {
+                            parent: parent.map(Into::into),
+                            is_folder,
+                            children,
+                            modified,
+                        },
+                    );
+                }
+                IncomingKind::Malformed => tree.malformed.push(guid),
+            }
+        }
+        tree
+    }
+
+    fn modified(&self, guid: &SyncGuid) -> Option<ServerTimestamp> {
+        self.items
+            .get(guid)
+            .map(|item| item.modified)
+            .or_else(|| self.tombstones.get(guid).copied())
+    }
+}
+
+struct LocalItem {
+    parent: Option<SyncGuid>,
+    is_folder: bool,
+    // Whether the item has been uploaded, and hasn't changed since.
+    is_synced: bool,
+}
+
+struct LocalTree {
+    items: HashMap<SyncGuid, LocalItem>,
+    children: HashMap<SyncGuid, Vec<SyncGuid>>,
+    tombstones: HashSet<SyncGuid>,
+    // The `serverModified` time of each item in `moz_bookmarks_synced`.
+    mirror: HashMap<SyncGuid, ServerTimestamp>,
+}
+
+impl LocalTree {
+    fn fetch(db: &PlacesDb) -> Result<Self> {
+        let mut items = HashMap::new();
+        let mut children: HashMap<SyncGuid, Vec<SyncGuid>> = HashMap::new();
+        let rows = db.query_rows_and_then(
+            "SELECT b.guid, p.guid AS parentGuid, b.type, b.syncStatus, b.syncChangeCounter
+             FROM moz_bookmarks b
+             LEFT JOIN moz_bookmarks p ON p.id = b.parent
+             ORDER BY b.parent, b.position",
+            [],
+            |row| -> Result<_> {
+                Ok((
+                    row.get::<_, SyncGuid>("guid")?,
+                    row.get::<_, Option<SyncGuid>>("parentGuid")?,
+                    row.get::<_, BookmarkType>("type")?,
+                    row.get::<_, SyncStatus>("syncStatus")?,
+                    row.get::<_, i64>("syncChangeCounter")?,
+                ))
+            },
+        )?;
+        for (guid, parent, kind, sync_status, change_counter) in rows {
+            if let Some(parent) = &parent {
+                children
+                    .entry(parent.clone())
+                    .or_default()
+                    .push(guid.clone());
+            }
+            items.insert(
+                guid,
+                LocalItem {
+                    parent,
+                    is_folder: kind == BookmarkType::Folder,
+                    is_synced: sync_status == SyncStatus::Normal && change_counter == 0,
+                },
+            );
+        }
+        let tombstones = db
+            .query_rows_and_then("SELECT guid FROM moz_bookmarks_deleted", [], |row| {
+                row.get::<_, SyncGuid>(0)
+            })?
+            .into_iter()
+            .collect();
+        let mirror = db
+            .query_rows_and_then(
+                "SELECT guid, serverModified FROM moz_bookmarks_synced",
+                [],
+                |row| -> Result<_> {
+                    Ok((row.get::<_, SyncGuid>(0)?, ServerTimestamp(row.get(1)?)))
+                },
+            )?
+            .into_iter()
+            .collect();
+        Ok(Self {
+            items,
+            children,
+            tombstones,
+            mirror,
+        })
+    }
+}
+
+/// The problems found by validation, and how to repair them.
+#[derive(Debug, Default)]
+struct Problems {
+    malformed: usize,
+    root_on_server: usize,
+    missing_roots: usize,
+    orphans: usize,
+    missing_children: usize,
+    duplicate_children: usize,
+    multiple_parents: usize,
+    non_folder_parents: usize,
+    parent_child_mismatches: usize,
+    structural_differences: usize,
+    server_missing: usize,
+    server_deleted: usize,
+    client_missing: usize,
+    mirror_differences: usize,
+    // Local items to upload again. Ordered so repairs are deterministic.
+    reupload: BTreeSet<SyncGuid>,
+    // Server records to download again.
+    redownload: BTreeSet<SyncGuid>,
+}
+
+impl Problems {
+    // Uploads the local item, and its local parent, so that the server gets
+    // both sides of the parent-child relationship.
+    fn reupload_with_parent(&mut self, local: &LocalTree, guid: &SyncGuid) {
+        if let Some(item) = local.items.get(guid) {
+            self.reupload.insert(guid.clone());
+            if let Some(parent) = &item.parent {
+                self.reupload.insert(parent.clone());
+            }
+        }
+    }
+
+    fn record(&self, telem: &mut telemetry::Engine) {
+        let mut validation = telemetry::Validation::with_version(VALIDATION_VERSION);
+        validation
+            .problem("malformed", self.malformed)
+            .problem("rootOnServer", self.root_on_server)
+            .problem("missingRoots", self.missing_roots)
+            .problem("orphans", self.orphans)
+            .problem("missingChildren", self.missing_children)
+            .problem("duplicateChildren", self.duplicate_children)
+            .problem("multipleParents", self.multiple_parents)
+            .problem("nonFolderParents", self.non_folder_parents)
+            .problem("parentChildMismatches", self.parent_child_mismatches)
+            .problem("structuralDifferences", self.structural_differences)
+            .problem("serverMissing", self.server_missing)
+            .problem("serverDeleted", self.server_deleted)
+            .problem("clientMissing", self.client_missing)
+            .problem("mirrorDifferences", self.mirror_differences);
+        telem.validation(validation);
+    }
+}
+
+// Problems with the server's tree, on its own.
+fn check_server_tree(server: &ServerTree, local: &LocalTree, problems: &mut Problems) {
+    let root = BookmarkRootGuid::Root.guid();
+    for guid in &server.malformed {
+        problems.malformed += 1;
+        problems.reupload_with_parent(local, guid);
+    }
+    // The Places root is never uploaded. Other clients treat it as a
+    // regular folder, so we just report it.
+    if server.items.contains_key(root) {
+        problems.root_on_server += 1;
+    }
+    if !server.items.is_empty() {
+        for user_root in USER_CONTENT_ROOTS {
+            if !server.items.contains_key(user_root.guid()) {
+                problems.missing_roots += 1;
+                problems.reupload.insert(user_root.as_guid());
+            }
+        }
+    }
+
+    let mut parents_by_child: HashMap<&SyncGuid, Vec<&SyncGuid>> = HashMap::new();
+    let mut mismatched_children = HashSet::new();
+    for (guid, item) in &server.items {
+        let mut seen = HashSet::new();
+        for child_guid in &item.children {
+            if !seen.insert(child_guid) {
+                problems.duplicate_children += 1;
+                problems.reupload_with_parent(local, guid);
+                continue;
+            }
+            parents_by_child.entry(child_guid).or_default().push(guid);
+            match server.items.get(child_guid) {
+                Some(child) => {
+                    if child.parent.as_ref() != Some(guid) {
+                        mismatched_children.insert(child_guid);
+                    }
+                }
+                None => {
+                    problems.missing_children += 1;
+                    problems.reupload_with_parent(local, child_guid);
+                    problems.reupload_with_parent(local, guid);
+                }
+            }
+        }
+
+        if guid == root {
+            continue;
+        }
+        match &item.parent {
+            Some(parent_guid) if parent_guid == root => {}
+            Some(parent_guid) => match server.items.get(parent_guid) {
+                Some(parent) if !parent.is_folder => {
+                    problems.non_folder_parents += 1;
+                    problems.reupload_with_parent(local, guid);
+                }
+                Some(parent) => {
+                    if !parent.children.contains(guid) {
+                        mismatched_children.insert(guid);
+                    }
+                }
+                None => {
+                    problems.orphans += 1;
+                    problems.reupload_with_parent(local, guid);
+                }
+            },
+            None => {
+                problems.orphans += 1;
+                problems.reupload_with_parent(local, guid);
+            }
+        }
+    }
+    for guid in mismatched_children {
+        problems.parent_child_mismatches += 1;
+        problems.reupload_with_parent(local, guid);
+        if let Some(item) = server.items.get(guid) {
+            if let Some(server_parent) = &item.parent {
+                problems.reupload_with_parent(local, server_parent);
+            }
+        }
+    }
+    for (child_guid, parents) in parents_by_child {
+        if parents.len() > 1 {
+            problems.multiple_parents += 1;
+            problems.reupload_with_parent(local, child_guid);
+            for parent_guid in parents {
+                problems.reupload_with_parent(local, parent_guid);
+            }
+        }
+    }
+}
+
+// Differences between the server and the local tree, and between the server
+// and the mirror.
+fn check_against_local(
+    server: &ServerTree,
+    local: &LocalTree,
+    last_sync: ServerTimestamp,
+    problems: &mut Problems,
+) {
+    let root = BookmarkRootGuid::Root.guid();
+    let no_children = Vec::new();
+    for (guid, item) in &local.items {
+        if guid == root || !item.is_synced {
+            continue;
+        }
+        match server.items.get(guid) {
+            Some(server_item) if server_item.modified > last_sync => {}
+            Some(server_item) => {
+                let local_children = local.children.get(guid).unwrap_or(&no_children);
+                if server_item.parent != item.parent
+                    || server_item.is_folder != item.is_folder
+                    || (item.is_folder && server_item.children != *local_children)
+                {
+                    problems.structural_differences += 1;
+                    problems.reupload_with_parent(local, guid);
+                }
+            }
+            None => match server.tombstones.get(guid) {
+                Some(modified) if *modified > last_sync => {}
+                Some(_) => {
+                    // The item might have been deleted on another device, but
+                    // we don't delete local items when repairing, so it's
+                    // revived.
+                    problems.server_deleted += 1;
+                    problems.reupload_with_parent(local, guid);
+                }
+                None => {
+                    problems.server_missing += 1;
+                    problems.reupload_with_parent(local, guid);
+                }
+            },
+        }
+    }
+
+    for (guid, item) in &server.items {
+        if guid == root || item.modified > last_sync {
+            continue;
+        }
+        if !local.items.contains_key(guid) && !local.tombstones.contains(guid) {
+            problems.client_missing += 1;
+            problems.redownload.insert(guid.clone());
+        }
+    }
+
+    for guid in server.items.keys().chain(server.tombstones.keys()) {
+        let modified = match server.modified(guid) {
+            Some(modified) if modified <= last_sync => modified,
+            _ => continue,
+        };
+        if local.mirror.get(guid) != Some(&modified) {
+            problems.mirror_differences += 1;
+            problems.redownload.insert(guid.clone());
+        }
+    }
+}
+
+// Flags the local items for upload, so the next merge uploads them.
+fn flag_for_upload(db: &PlacesDb, scope: &SqlInterruptScope, guids: &[SyncGuid]) -> Result<()> {
+    let mut tx = db.begin_transaction()?;
+    sql_support::each_chunk(guids, |chunk, _| -> Result<()> {
+        db.execute(
+            &format!(
+                "UPDATE moz_bookmarks SET
+                     syncChangeCounter = syncChangeCounter + 1
+                 WHERE guid IN ({vars}) AND
+                       guid <> '{root}'",
+                vars = sql_support::repeat_sql_vars(chunk.len()),
+                root = BookmarkRootGuid::Root.as_str(),
+            ),
+            rusqlite::params_from_iter(chunk),
+        )?;
+        tx.maybe_commit()?;
+        scope.err_if_interrupted()?;
+        Ok(())
+    })?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn find_problems(db: &PlacesDb, server_records: Vec<IncomingBso>) -> Result<Problems> {
+    let last_sync = ServerTimestamp(get_meta::<i64>(db, LAST_SYNC_META_KEY)?.unwrap_or_default());
+    let server = ServerTree::new(server_records);
+    let local = LocalTree::fetch(db)?;
+    let mut problems = Problems::default();
+    check_server_tree(&server, &local, &mut problems);
+    check_against_local(&server, &local, last_sync, &mut problems);
+    Ok(problems)
+}
+
+/// Validates the local tree against every record on the server, and records
+/// the problems in `telem`. In repair mode, local items that fix the
+/// problems are flagged for upload, and the IDs of the server records to
+/// download again are returned.
+pub(crate) fn validate(
+    db: &PlacesDb,
+    scope: &SqlInterruptScope,
+    mode: SyncValidationMode,
+    server_records: Vec<IncomingBso>,
+    telem: &mut telemetry::Engine,
+) -> Result<Vec<SyncGuid>> {
+    let problems = find_problems(db, server_records)?;
+    scope.err_if_interrupted()?;
+    log::debug!("bookmark validation found {:?}", problems);
+    problems.record(telem);
+
+    if mode != SyncValidationMode::Repair {
+        return Ok(vec![]);
+    }
+    let reupload = problems.reupload.into_iter().collect::<Vec<_>>();
+    if !reupload.is_empty() {
+        log::info!("Repairing bookmarks: uploading {} items", reupload.len());
+        flag_for_upload(db, scope, &reupload)?;
+    }
+    Ok(problems
+        .redownload
+        .into_iter()
+        .map(|guid| BookmarkRecordId::from(guid).into_payload_id())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::bookmark_sync::incoming::IncomingApplicator;
+    use crate::storage::put_meta;
+    use crate::tests::insert_json_tree;
+    use serde_json::{json, Value};
+
+    const LAST_SYNC: ServerTimestamp = ServerTimestamp(1000);
+
+    fn server_records(records: &[Value]) -> Vec<IncomingBso> {
+        records
+            .iter()
+            .map(|record| IncomingBso::from_test_content_ts(record.clone(), LAST_SYNC))
+            .collect()
+    }
+
+    fn roots(unfiled_children: Value) -> Vec<Value> {
+        vec![
+            json!({"id": "menu", "type": "folder", "parentid": "places", "children": []}),
+            json!({"id": "toolbar", "type": "folder", "parentid": "places", "children": []}),
+            json!({"id": "unfiled", "type": "folder", "parentid": "places", "children": unfiled_children}),
+            json!({"id": "mobile", "type": "folder", "parentid": "places", "children": []}),
+        ]
+    }
+
+    // Creates a local tree, and a mirror with `records`, as if we'd synced
+    // them and then uploaded every local item.
+    fn setup(conn: &PlacesDb, records: &[Value]) {
+        insert_json_tree(
+            conn,
+            json!({
+                "guid": BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "guid": "folderAAAAAA",
+                        "title": "A",
+                        "children": [
+                            {"guid": "bookmarkBBBB", "url": "https://example.com/b"},
+                        ],
+                    },
+                    {"guid": "bookmarkCCCC", "url": "https://example.com/c"},
+                ],
+            }),
+        );
+        conn.execute_batch("UPDATE moz_bookmarks SET syncStatus = 2, syncChangeCounter = 0")
+            .unwrap();
+        let applicator = IncomingApplicator::new(conn);
+        for record in server_records(records) {
+            applicator.apply_bso(record).unwrap();
+        }
+        put_meta(conn, LAST_SYNC_META_KEY, &LAST_SYNC.as_millis()).unwrap();
+    }
+
+    fn change_counter(conn: &PlacesDb, guid: &str) -> i64 {
+        conn.query_row(
+            "SELECT syncChangeCounter FROM moz_bookmarks WHERE guid = ?",
+            [guid],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_no_problems() -> Result<()> {
+        let conn = new_mem_connection();
+        let mut records = roots(json!(["folderAAAAAA", "bookmarkCCCC"]));
+        records.extend([
+            json!({"id": "folderAAAAAA", "type": "folder", "parentid": "unfiled", "title": "A",
+                   "children": ["bookmarkBBBB"]}),
+            json!({"id": "bookmarkBBBB", "type": "bookmark", "parentid": "folderAAAAAA",
+                   "bmkUri": "https://example.com/b"}),
+            json!({"id": "bookmarkCCCC", "type": "bookmark", "parentid": "unfiled",
+                   "bmkUri": "https://example.com/c"}),
+        ]);
+        setup(&conn, &records);
+
+        let problems = find_problems(&conn, server_records(&records))?;
+        assert_eq!(problems.missing_roots, 0);
+        assert_eq!(problems.orphans, 0);
+        assert_eq!(problems.parent_child_mismatches, 0);
+        assert_eq!(problems.structural_differences, 0);
+        assert_eq!(problems.server_missing, 0);
+        assert_eq!(problems.client_missing, 0);
+        assert_eq!(problems.mirror_differences, 0);
+        assert!(problems.reupload.is_empty());
+        assert!(problems.redownload.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair() -> Result<()> {
+        let conn = new_mem_connection();
+        // The server is missing the toolbar and `bookmarkCCCC`, lists
+        // `bookmarkBBBB` in two folders, and has an orphan we don't have.
+        let mut records = roots(json!(["folderAAAAAA", "bookmarkCCCC", "bookmarkBBBB"]));
+        records.retain(|record| record["id"] != "toolbar");
+        records.extend([
+            json!({"id": "folderAAAAAA", "type": "folder", "parentid": "unfiled", "title": "A",
+                   "children": ["bookmarkBBBB"]}),
+            json!({"id": "bookmarkBBBB", "type": "bookmark", "parentid": "folderAAAAAA",
+                   "bmkUri": "https://example.com/b"}),
+            json!({"id": "orphanDDDDDD", "type": "bookmark", "parentid": "missingEEEEE",
+                   "bmkUri": "https://example.com/d"}),
+        ]);
+        setup(&conn, &records);
+
+        let problems = find_problems(&conn, server_records(&records))?;
+        assert_eq!(problems.missing_roots, 1);
+        assert_eq!(problems.missing_children, 1);
+        assert_eq!(problems.multiple_parents, 1);
+        assert_eq!(problems.parent_child_mismatches, 1);
+        assert_eq!(problems.orphans, 1);
+        // The toolbar and `bookmarkCCCC`.
+        assert_eq!(problems.server_missing, 2);
+        assert_eq!(problems.structural_differences, 1);
+        assert_eq!(problems.client_missing, 1);
+        assert_eq!(problems.mirror_differences, 0);
+
+        let scope = conn.begin_interrupt_scope()?;
+        let mut telem = telemetry::Engine::new("bookmarks");
+        let redownload = validate(
+            &conn,
+            &scope,
+            SyncValidationMode::Repair,
+            server_records(&records),
+            &mut telem,
+        )?;
+        assert_eq!(redownload, vec![SyncGuid::from("orphanDDDDDD")]);
+        for guid in [
+            "toolbar_____",
+            "unfiled_____",
+            "folderAAAAAA",
+            "bookmarkBBBB",
+            "bookmarkCCCC",
+        ] {
+            assert_eq!(
+                change_counter(&conn, guid),
+                1,
+                "{} should be uploaded",
+                guid
+            );
+        }
+        assert_eq!(change_counter(&conn, "menu________"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_only() -> Result<()> {
+        let conn = new_mem_connection();
+        let records = roots(json!([]));
+        setup(&conn, &records);
+
+        let scope = conn.begin_interrupt_scope()?;
+        let mut telem = telemetry::Engine::new("bookmarks");
+        let redownload = validate(
+            &conn,
+            &scope,
+            SyncValidationMode::Validate,
+            server_records(&records),
+            &mut telem,
+        )?;
+        assert!(redownload.is_empty());
+        assert_eq!(change_counter(&conn, "bookmarkCCCC"), 0);
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@
 use super::schema;
 use crate::api::places_api::ConnectionType;
 use crate::error::*;
+use crate::frecency::FrecencySettings;
 use interrupt_support::{SqlInterruptHandle, SqlInterruptScope};
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
@@ -125,6 +126,7 @@ pub struct PlacesDb {
     interrupt_handle: Arc<SqlInterruptHandle>,
     api_id: usize,
     pub(super) coop_tx_lock: Arc<Mutex<()>>,
+    frecency_settings: Arc<FrecencySettings>,
 }
 
 impl PlacesDb {
@@ -133,6 +135,7 @@ impl PlacesDb {
         conn_type: ConnectionType,
         api_id: usize,
         coop_tx_lock: Arc<Mutex<()>>,
+        frecency_settings: Arc<FrecencySettings>,
     ) -> Self {
         Self {
             interrupt_handle: Arc::new(SqlInterruptHandle::new(&db)),
@@ -141,6 +144,7 @@ impl PlacesDb {
             // The API sets this explicitly.
             api_id,
             coop_tx_lock,
+            frecency_settings,
         }
     }
 
@@ -149,10 +153,17 @@ impl PlacesDb {
         conn_type: ConnectionType,
         api_id: usize,
         coop_tx_lock: Arc<Mutex<()>>,
+        frecency_settings: Arc<FrecencySettings>,
     ) -> Result<Self> {
         let initializer = PlacesInitializer { api_id, conn_type };
         let conn = open_database_with_flags(path, conn_type.rusqlite_flags(), &initializer)?;
-        Ok(Self::with_connection(conn, conn_type, api_id, coop_tx_lock))
+        Ok(Self::with_connection(
+            conn,
+            conn_type,
+            api_id,
+            coop_tx_lock,
+            frecency_settings,
+        ))
     }
 
     #[cfg(test)]
@@ -172,6 +183,7 @@ impl PlacesDb {
             conn_type,
             0,
             Arc::new(Mutex::new(())),
+            Arc::default(),
         ))
     }
 
@@ -201,6 +213,13 @@ impl PlacesDb {
     pub fn api_id(&self) -> usize {
         self.api_id
     }
+
+    /// The settings used to calculate frecencies, which are shared by all
+    /// connections for the same API.
+    #[inline]
+    pub fn frecency_settings(&self) -> &FrecencySettings {
+        &self.frecency_settings
+    }
 }
 
 impl Drop for PlacesDb {
@@ -352,6 +371,12 @@ fn define_functions(c: &Connection, api_id: usize) -> rusqlite::Result<()> {
         FunctionFlags::SQLITE_UTF8,
         move |ctx| -> rusqlite::Result<i64> { sql_fns::note_bookmarks_sync_change(ctx, api_id) },
     )?;
+    c.create_scalar_function(
+        "has_places_observers",
+        0,
+        FunctionFlags::SQLITE_UTF8,
+        move |ctx| -> rusqlite::Result<bool> { sql_fns::has_places_observers(ctx, api_id) },
+    )?;
     Ok(())
 }
 
@@ -528,6 +553,11 @@ pub(crate) mod sql_fns {
         // Because we only ever check for equality, we can use Relaxed ordering.
         Ok(counter.fetch_add(1, Ordering::Relaxed))
     }
+
+    #[inline(never)]
+    pub fn has_places_observers(_ctx: &Context<'_>, api_id: usize) -> Result<bool> {
+        Ok(crate::observer::has_observers(api_id))
+    }
 }
 
 #[cfg(test)]
@@ -7,11 +7,12 @@ use crate::bookmark_sync::engine::LAST_SYNC_META_KEY;
 use crate::storage::bookmarks::{
     bookmark_sync::create_synced_bookmark_roots, create_bookmark_roots,
 };
+use crate::storage::search::update_search_index;
 use crate::types::SyncStatus;
 use rusqlite::Connection;
 use sql_support::ConnExt;
 
-pub const VERSION: u32 = 17;
+pub const VERSION: u32 = 21;
 
 // Shared schema and temp tables for the read-write and Sync connections.
 const CREATE_SHARED_SCHEMA_SQL: &str = include_str!("../../sql/create_shared_schema.sql");
@@ -288,6 +289,30 @@ pub fn upgrade_from(db: &Connection, from: u32) -> rusqlite::Result<()> {
                 (),
             )?;
         }
+        17 => {
+            // Add the favicon tables
+            db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+        }
+        18 => {
+            // Add the full-text search index, and index all existing pages.
+            db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+            update_search_index(db, "SELECT id FROM moz_places")?;
+        }
+        19 => {
+            // Add the history journey tables
+            db.execute_batch(CREATE_SHARED_SCHEMA_SQL)?;
+        }
+        20 => {
+            // Add the `unknown_fields` column for synced history metadata.
+            //
+            // Databases older than v14 get `moz_places_metadata` from the shared schema, which
+            // already has the column.
+            let exists_sql = "SELECT 1 FROM pragma_table_info('moz_places_metadata') WHERE name = 'unknown_fields'";
+            let add_column_sql = "ALTER TABLE moz_places_metadata ADD COLUMN unknown_fields TEXT";
+            if !db.exists(exists_sql, [])? {
+                db.execute(add_column_sql, [])?;
+            }
+        }
         // Add more migrations here...
 
         // Any other from value indicates that something very wrong happened
@@ -881,6 +906,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_upgrade_schema_18_19() {
+        let db_file = MigratedDatabaseFile::new(PlacesInitializer::new_for_test(), CREATE_V15_DB);
+        db_file.upgrade_to(18);
+        let db = db_file.open();
+        db.execute_batch(
+            "INSERT INTO moz_places(id, guid, url, title)
+             VALUES(1, 'placeAAAAAAA', 'https://example.com/', 'Example page');
+             INSERT INTO moz_keywords(place_id, keyword)
+             VALUES(1, 'ex');",
+        )
+        .unwrap();
+        drop(db);
+
+        db_file.upgrade_to(19);
+        let db = db_file.open();
+
+        // Test that existing pages were indexed
+        assert_eq!(
+            db.query_one::<String>(
+                "SELECT keyword FROM moz_places_fts WHERE moz_places_fts MATCH 'page'"
+            )
+            .unwrap(),
+            "ex"
+        );
+    }
+
+    #[test]
+    fn test_upgrade_schema_20_21() {
+        let db_file = MigratedDatabaseFile::new(PlacesInitializer::new_for_test(), CREATE_V15_DB);
+        db_file.upgrade_to(20);
+        let db = db_file.open();
+        db.execute_batch(
+            "INSERT INTO moz_places(id, guid, url, title)
+             VALUES(1, 'placeAAAAAAA', 'https://example.com/', 'Example page');
+             INSERT INTO moz_places_metadata(place_id, created_at, updated_at, total_view_time)
+             VALUES(1, 1000, 2000, 500);",
+        )
+        .unwrap();
+        drop(db);
+
+        db_file.upgrade_to(21);
+        let db = db_file.open();
+
+        // Test the unknown_fields column was added, and existing metadata kept.
+        assert_eq!(
+            db.query_one::<String>("SELECT type FROM pragma_table_info('moz_places_metadata') WHERE name = 'unknown_fields'").unwrap(),
+            "TEXT"
+        );
+        assert_eq!(
+            db.query_one::<i64>(
+                "SELECT total_view_time FROM moz_places_metadata WHERE unknown_fields IS NULL"
+            )
+            .unwrap(),
+            500
+        );
+    }
+
     #[test]
     fn test_gh5464() {
         // Test the gh-5464 error case: A user with the `v16` schema, but with `user_version` set
@@ -926,6 +1009,12 @@ mod tests {
             "moz_keywords",
             "moz_places_metadata",
             "moz_places_metadata_search_queries",
+            "moz_icons",
+            "moz_pages_w_icons",
+            "moz_icons_to_pages",
+            "moz_places_fts",
+            "moz_places_metadata_groups",
+            "moz_places_metadata_group_items",
         ];
         #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
         struct ColumnInfo {
@@ -144,24 +144,25 @@ impl<'conn> ChunkedCoopTransaction<'conn> {
     }
 
     /// Checks to see if we have held a transaction for longer than the
-    /// requested time, and if so, commits the current transaction and opens
-    /// another.
+    /// requested time, and if so, commits the current transaction, calls
+    /// `after_commit`, and opens another.
     #[inline]
-    pub fn maybe_commit(&mut self) -> Result<()> {
+    pub fn maybe_commit(&mut self, after_commit: impl FnOnce()) -> Result<()> {
         if self.should_commit() {
             log::debug!("ChunkedCoopTransaction commiting after taking allocated time");
-            self.commit_and_start_new_tx()?;
+            self.commit_and_start_new_tx(after_commit)?;
         }
         Ok(())
     }
 
-    fn commit_and_start_new_tx(&mut self) -> Result<()> {
+    fn commit_and_start_new_tx(&mut self, after_commit: impl FnOnce()) -> Result<()> {
         // We can't call self.tx.commit() here as it wants to consume
         // self.tx, and we can't set up the new self.tx first as then
         // we'll be trying to start a new transaction while the current
         // one is in progress. So explicitly set the finished flag on it.
         self.tx.finished = true;
         self.tx.execute_batch("COMMIT")?;
+        after_commit();
         // acquire a lock on our cooperator - if our only other writer
         // thread holds a write lock we'll block until it is released.
         // Note however that sqlite might still return a locked error if the
@@ -6,13 +6,17 @@ mod coop_transaction;
 
 use crate::api::places_api::ConnectionType;
 use crate::error::*;
+use crate::observer;
 use coop_transaction::ChunkedCoopTransaction;
 use rusqlite::Connection;
 use sql_support::{ConnExt, UncheckedTransaction};
 
 /// High level transaction type which "does the right thing" for you.
 /// Construct one with `PlacesDb::begin_transaction()`.
-pub struct PlacesTransaction<'conn>(PlacesTransactionRepr<'conn>);
+pub struct PlacesTransaction<'conn> {
+    repr: PlacesTransactionRepr<'conn>,
+    db: &'conn super::PlacesDb,
+}
 
 /// Only separated from PlacesTransaction so that the internals of the former
 /// are private (so that it can't be `matched` on, for example)
@@ -29,7 +33,7 @@ impl<'conn> PlacesTransaction<'conn> {
     /// earliest opportunity.
     #[inline]
     pub fn should_commit(&self) -> bool {
-        match &self.0 {
+        match &self.repr {
             PlacesTransactionRepr::ChunkedWrite(tx) => tx.should_commit(),
             _ => true,
         }
@@ -42,8 +46,11 @@ impl<'conn> PlacesTransaction<'conn> {
     ///   warning and does nothing.
     #[inline]
     pub fn maybe_commit(&mut self) -> Result<()> {
-        if let PlacesTransactionRepr::ChunkedWrite(tx) = &mut self.0 {
-            tx.maybe_commit()?;
+        if let PlacesTransactionRepr::ChunkedWrite(tx) = &mut self.repr {
+            // Deliver the changes from each chunk as soon as it's committed,
+            // so that rolling back a later chunk doesn't hold them up.
+            let db = self.db;
+            tx.maybe_commit(|| notify_observers(db))?;
         } else {
             error_support::report_error!(
                 "places-nonchunked-maybe-commit",
@@ -56,13 +63,15 @@ impl<'conn> PlacesTransaction<'conn> {
         Ok(())
     }
 
-    /// Consumes and commits a PlacesTransaction transaction.
+    /// Consumes and commits a PlacesTransaction transaction, then notifies
+    /// observers of the changes it made.
     pub fn commit(self) -> Result<()> {
-        match self.0 {
+        match self.repr {
             PlacesTransactionRepr::ChunkedWrite(t) => t.commit()?,
             PlacesTransactionRepr::UnchunkedWrite(t) => t.commit()?,
             PlacesTransactionRepr::ReadOnly(t) => t.commit()?,
         };
+        notify_observers(self.db);
         Ok(())
     }
 
@@ -70,7 +79,7 @@ impl<'conn> PlacesTransaction<'conn> {
     /// maybe_commit has been called, this may only roll back as far as that
     /// call.
     pub fn rollback(self) -> Result<()> {
-        match self.0 {
+        match self.repr {
             PlacesTransactionRepr::ChunkedWrite(t) => t.rollback()?,
             PlacesTransactionRepr::UnchunkedWrite(t) => t.rollback()?,
             PlacesTransactionRepr::ReadOnly(t) => t.rollback()?,
@@ -79,6 +88,20 @@ impl<'conn> PlacesTransaction<'conn> {
     }
 }
 
+// Must only be called outside of a transaction, once the changes are
+// committed, so failing to deliver them shouldn't fail the transaction.
+fn notify_observers(db: &super::PlacesDb) {
+    if db.conn_type() != ConnectionType::ReadOnly {
+        if let Err(e) = observer::notify_observers(db) {
+            error_support::report_error!(
+                "places-notify-observers",
+                "Failed to notify observers: {}",
+                e
+            );
+        }
+    }
+}
+
 impl super::PlacesDb {
     /// Begin the "correct" transaction type for this connection.
     ///
@@ -86,7 +109,7 @@ impl super::PlacesDb {
     /// - for ReadWrite connections, begins a normal coop transaction
     /// - for ReadOnly connections, begins an unchecked transaction.
     pub fn begin_transaction(&self) -> Result<PlacesTransaction<'_>> {
-        Ok(PlacesTransaction(match self.conn_type() {
+        let repr = match self.conn_type() {
             ConnectionType::Sync => {
                 PlacesTransactionRepr::ChunkedWrite(self.chunked_coop_trransaction()?)
             }
@@ -97,7 +120,8 @@ impl super::PlacesDb {
                 // Use an unchecked transaction with no locking.
                 PlacesTransactionRepr::ReadOnly(self.unchecked_transaction()?)
             }
-        }))
+        };
+        Ok(PlacesTransaction { repr, db: self })
     }
 }
 
@@ -105,7 +129,7 @@ impl<'conn> std::ops::Deref for PlacesTransaction<'conn> {
     type Target = Connection;
 
     fn deref(&self) -> &Connection {
-        match &self.0 {
+        match &self.repr {
             PlacesTransactionRepr::ChunkedWrite(t) => t,
             PlacesTransactionRepr::UnchunkedWrite(t) => t,
             PlacesTransactionRepr::ReadOnly(t) => t,
@@ -44,6 +44,11 @@ pub enum PlacesApiError {
     ///  - Attempting to insert a child under BookmarkRoot.Root,
     #[error("Invalid bookmark operation: {reason}")]
     InvalidBookmarkOperation { reason: String },
+
+    /// Thrown when restoring from an undo token which has already been
+    /// used, or whose undo window has expired.
+    #[error("Can't undo: {reason}")]
+    CannotUndo { reason: String },
 }
 
 /// Error enum used internally
@@ -108,11 +113,17 @@ pub enum Error {
     #[error("Can not import from database version {0}")]
     UnsupportedDatabaseVersion(i64),
 
+    #[error("Error parsing property list: {0}")]
+    InvalidPropertyList(String),
+
     #[error("Error opening database: {0}")]
     OpenDatabaseError(#[from] sql_support::open_database::Error),
 
     #[error("Invalid metadata observation: {0}")]
     InvalidMetadataObservation(#[from] InvalidMetadataObservation),
+
+    #[error("Can't undo: {0}")]
+    CannotUndo(&'static str),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -147,6 +158,10 @@ pub enum InvalidPlaceInfo {
     // Like Urls, a tag is considered private info, so the value isn't in the error.
     #[error("The tag value is invalid")]
     InvalidTag,
+
+    // Keywords are private info too.
+    #[error("The keyword value is invalid")]
+    InvalidKeyword,
     #[error("Cannot change the '{0}' property of a bookmark of type {1:?}")]
     IllegalChange(&'static str, BookmarkType),
 
@@ -241,6 +256,10 @@ impl GetErrorHandling for Error {
                 })
                 .log_info()
             }
+            Error::CannotUndo(reason) => ErrorHandling::convert(PlacesApiError::CannotUndo {
+                reason: reason.to_string(),
+            })
+            .log_info(),
             Error::Corruption(e) => {
                 ErrorHandling::convert(PlacesApiError::UnexpectedPlacesException {
                     reason: e.to_string(),
@@ -5,20 +5,32 @@
 // This module implement the traits that make the FFI code easier to manage.
 
 use crate::api::matcher::{self, search_frecent, SearchParams};
-pub use crate::api::places_api::places_api_new;
+pub use crate::api::places_api::{places_api_new, places_api_new_with_frecency_settings};
 pub use crate::error::Result;
 pub use crate::error::{ApiResult, PlacesApiError};
+pub use crate::frecency::{FrecencyAlgorithm, FrecencySettings};
 pub use crate::import::common::HistoryMigrationResult;
-use crate::import::import_ios_history;
+use crate::import::{
+    import_chromium_bookmarks, import_chromium_history, import_ios_history,
+    import_safari_bookmarks, import_safari_history,
+};
+pub use crate::observer::{PlacesChange, PlacesObserver};
 use crate::storage;
 use crate::storage::bookmarks;
 pub use crate::storage::bookmarks::BookmarkPosition;
+pub use crate::storage::favicons::Favicon;
 pub use crate::storage::history_metadata::{
-    DocumentType, HistoryHighlight, HistoryHighlightWeights, HistoryMetadata,
+    DocumentType, HistoryHighlight, HistoryHighlightWeights, HistoryJourney, HistoryMetadata,
     HistoryMetadataObservation,
 };
-pub use crate::storage::RunMaintenanceMetrics;
-use crate::storage::{history, history_metadata};
+pub use crate::storage::maintenance::{MaintenanceBudget, MaintenanceMetrics, MaintenanceStep};
+pub use crate::storage::search::SearchMatch;
+pub use crate::storage::undo::UndoToken;
+use crate::storage::{
+    favicons, history, history_metadata, keywords, maintenance, search, tags, undo,
+};
+pub use crate::storage::{FrecencyRecalculationMetrics, RunMaintenanceMetrics};
+pub use crate::sync_validation::SyncValidationMode;
 use crate::types::VisitTransitionSet;
 use crate::ConnectionType;
 use crate::UniffiCustomTypeConverter;
@@ -238,6 +250,11 @@ impl PlacesConnection {
         self.with_conn(|conn| history_metadata::query(conn, query.as_str(), limit))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn search(&self, query: String, limit: i32) -> ApiResult<Vec<SearchMatch>> {
+        self.with_conn(|conn| search::search(conn, &query, limit))
+    }
+
     #[handle_error(crate::Error)]
     pub fn get_history_highlights(
         &self,
@@ -247,6 +264,15 @@ impl PlacesConnection {
         self.with_conn(|conn| history_metadata::get_highlights(conn, weights, limit))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn get_history_journeys(
+        &self,
+        since: PlacesTimestamp,
+        limit: i32,
+    ) -> ApiResult<Vec<HistoryJourney>> {
+        self.with_conn(|conn| history_metadata::get_journeys(conn, since.as_millis_i64(), limit))
+    }
+
     #[handle_error(crate::Error)]
     pub fn note_history_metadata_observation(
         &self,
@@ -258,7 +284,10 @@ impl PlacesConnection {
 
     #[handle_error(crate::Error)]
     pub fn metadata_delete_older_than(&self, older_than: PlacesTimestamp) -> ApiResult<()> {
-        self.with_conn(|conn| history_metadata::delete_older_than(conn, older_than.as_millis_i64()))
+        self.with_conn(|conn| {
+            history_metadata::delete_older_than(conn, older_than.as_millis_i64())?;
+            Ok(())
+        })
     }
 
     #[handle_error(crate::Error)]
@@ -381,6 +410,39 @@ impl PlacesConnection {
         self.with_conn(|conn| history::delete_visits_between(conn, start, end))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn delete_visits_for_with_undo(&self, url: String) -> ApiResult<Option<Arc<UndoToken>>> {
+        self.with_conn(|conn| {
+            let guid = match Url::parse(&url) {
+                Ok(url) => history::url_to_guid(conn, &url)?,
+                Err(e) => {
+                    log::warn!(
+                        "Invalid URL passed to places_delete_visits_for_with_undo, {}",
+                        e
+                    );
+                    history::href_to_guid(conn, url.clone().as_str())?
+                }
+            };
+            Ok(match guid {
+                Some(guid) => Some(Arc::new(undo::delete_visits_for_with_undo(conn, &guid)?)),
+                None => None,
+            })
+        })
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn delete_visits_between_with_undo(
+        &self,
+        start: PlacesTimestamp,
+        end: PlacesTimestamp,
+    ) -> ApiResult<Arc<UndoToken>> {
+        self.with_conn(|conn| {
+            Ok(Arc::new(undo::delete_visits_between_with_undo(
+                conn, start, end,
+            )?))
+        })
+    }
+
     #[handle_error(crate::Error)]
     pub fn delete_visit(&self, url: String, timestamp: PlacesTimestamp) -> ApiResult<()> {
         self.with_conn(|conn| {
@@ -442,6 +504,43 @@ impl PlacesConnection {
         self.with_conn(storage::run_maintenance_checkpoint)
     }
 
+    #[handle_error(crate::Error)]
+    pub fn run_maintenance(&self, budget: MaintenanceBudget) -> ApiResult<MaintenanceMetrics> {
+        self.with_conn(|conn| maintenance::run_maintenance(conn, &budget))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn recalculate_stale_frecencies(
+        &self,
+        max_pages: u32,
+    ) -> ApiResult<FrecencyRecalculationMetrics> {
+        self.with_conn(|conn| storage::recalculate_stale_frecencies(conn, max_pages))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn set_favicons_for_page(&self, page_url: Url, icons: Vec<Favicon>) -> ApiResult<()> {
+        self.with_conn(|conn| favicons::set_favicons_for_page(conn, &page_url, &icons))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn get_favicon_for_page(
+        &self,
+        page_url: Url,
+        desired_width: u32,
+    ) -> ApiResult<Option<Favicon>> {
+        self.with_conn(|conn| favicons::get_favicon_for_page(conn, &page_url, desired_width))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn delete_favicons_for_page(&self, page_url: Url) -> ApiResult<()> {
+        self.with_conn(|conn| favicons::delete_favicons_for_page(conn, &page_url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn delete_favicons_expired_before(&self, before: PlacesTimestamp) -> ApiResult<()> {
+        self.with_conn(|conn| favicons::delete_favicons_expired_before(conn, before))
+    }
+
     #[handle_error(crate::Error)]
     pub fn query_autocomplete(&self, search: String, limit: i32) -> ApiResult<Vec<SearchResult>> {
         self.with_conn(|conn| {
@@ -541,6 +640,16 @@ impl PlacesConnection {
         self.with_conn(|conn| bookmarks::delete_bookmark(conn, &id))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_delete_with_undo(&self, id: Guid) -> ApiResult<Option<Arc<UndoToken>>> {
+        self.with_conn(|conn| Ok(undo::delete_bookmark_with_undo(conn, &id)?.map(Arc::new)))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn restore(&self, token: Arc<UndoToken>) -> ApiResult<()> {
+        self.with_conn(|conn| undo::restore(conn, &token))
+    }
+
     #[handle_error(crate::Error)]
     pub fn bookmarks_delete_everything(&self) -> ApiResult<()> {
         self.with_conn(bookmarks::delete_everything)
@@ -551,6 +660,77 @@ impl PlacesConnection {
         self.with_conn(|conn| bookmarks::bookmarks_get_url_for_keyword(conn, keyword.as_str()))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_get_all_with_tag(&self, tag: String) -> ApiResult<Vec<BookmarkItem>> {
+        self.with_conn(|conn| {
+            // XXX - We should return the exact type - ie, BookmarkData rather than BookmarkItem.
+            Ok(bookmarks::fetch::fetch_bookmarks_with_tag(conn, &tag)?
+                .into_iter()
+                .map(|b| BookmarkItem::Bookmark { b })
+                .collect())
+        })
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_add_to_url(&self, url: Url, tag: String) -> ApiResult<()> {
+        self.with_conn(|conn| tags::tag_url(conn, &url, &tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_remove_from_url(&self, url: Url, tag: String) -> ApiResult<()> {
+        self.with_conn(|conn| tags::untag_url(conn, &url, &tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_remove_all_from_url(&self, url: Url) -> ApiResult<()> {
+        self.with_conn(|conn| tags::remove_all_tags_from_url(conn, &url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_remove(&self, tag: String) -> ApiResult<()> {
+        self.with_conn(|conn| tags::remove_tag(conn, &tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_rename(&self, old_tag: String, new_tag: String) -> ApiResult<()> {
+        self.with_conn(|conn| tags::rename_tag(conn, &old_tag, &new_tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_get_for_url(&self, url: Url) -> ApiResult<Vec<String>> {
+        self.with_conn(|conn| tags::get_tags_for_url(conn, &url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_get_urls_with_tag(&self, tag: String) -> ApiResult<Vec<Url>> {
+        self.with_conn(|conn| tags::get_urls_with_tag(conn, &tag))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn tags_get_all(&self) -> ApiResult<Vec<String>> {
+        self.with_conn(tags::get_all_tags)
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn keywords_set_for_url(&self, url: Url, keyword: String) -> ApiResult<()> {
+        self.with_conn(|conn| keywords::set_keyword_for_url(conn, &url, &keyword))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn keywords_get_for_url(&self, url: Url) -> ApiResult<Option<String>> {
+        self.with_conn(|conn| keywords::get_keyword_for_url(conn, &url))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn keywords_remove(&self, keyword: String) -> ApiResult<()> {
+        self.with_conn(|conn| keywords::remove_keyword(conn, &keyword))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn keywords_remove_for_url(&self, url: Url) -> ApiResult<()> {
+        self.with_conn(|conn| keywords::remove_keyword_for_url(conn, &url))
+    }
+
     #[handle_error(crate::Error)]
     pub fn bookmarks_insert(&self, data: InsertableBookmarkItem) -> ApiResult<Guid> {
         self.with_conn(|conn| bookmarks::insert_bookmark(conn, data))
@@ -566,6 +746,16 @@ impl PlacesConnection {
         self.with_conn(|conn| bookmarks::count_bookmarks_in_trees(conn, guids))
     }
 
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_import_html(&self, html: String) -> ApiResult<u32> {
+        self.with_conn(|conn| bookmarks::html::import_html(conn, &html))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn bookmarks_export_html(&self) -> ApiResult<String> {
+        self.with_conn(bookmarks::html::export_html)
+    }
+
     #[handle_error(crate::Error)]
     pub fn places_history_import_from_ios(
         &self,
@@ -574,6 +764,32 @@ impl PlacesConnection {
     ) -> ApiResult<HistoryMigrationResult> {
         self.with_conn(|conn| import_ios_history(conn, &db_path, last_sync_timestamp))
     }
+
+    #[handle_error(crate::Error)]
+    pub fn places_history_import_from_chromium(
+        &self,
+        db_path: String,
+    ) -> ApiResult<HistoryMigrationResult> {
+        self.with_conn(|conn| import_chromium_history(conn, &db_path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_bookmarks_import_from_chromium(&self, path: String) -> ApiResult<u32> {
+        self.with_conn(|conn| import_chromium_bookmarks(conn, &path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_history_import_from_safari(
+        &self,
+        db_path: String,
+    ) -> ApiResult<HistoryMigrationResult> {
+        self.with_conn(|conn| import_safari_history(conn, &db_path))
+    }
+
+    #[handle_error(crate::Error)]
+    pub fn places_bookmarks_import_from_safari(&self, path: String) -> ApiResult<u32> {
+        self.with_conn(|conn| import_safari_bookmarks(conn, &path))
+    }
 }
 
 impl AsRef<SqlInterruptHandle> for PlacesConnection {
@@ -6,6 +6,7 @@ use crate::error::*;
 use crate::types::VisitType;
 use error_support::trace_error;
 use rusqlite::Connection;
+use serde_derive::*;
 use types::Timestamp;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -15,8 +16,22 @@ enum RedirectBonus {
     Normal,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How the weight of a visit decays as it gets older.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FrecencyAlgorithm {
+    /// Desktop's algorithm, where each visit's weight is taken from the bucket
+    /// its age falls into.
+    Buckets,
+    /// Visits lose weight continuously, halving every `half_life_days`, so
+    /// that visits just on either side of a bucket cutoff aren't weighted
+    /// very differently. Recent visits still have `first_bucket_weight`.
+    Exponential,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct FrecencySettings {
+    pub algorithm: FrecencyAlgorithm,
+    pub half_life_days: i32,
     // TODO: These probably should not all be i32s...
     pub num_visits: i32,                     // from "places.frecency.numVisits"
     pub first_bucket_cutoff_days: i32,       // from "places.frecency.firstBucketCutoff"
@@ -44,6 +59,8 @@ pub struct FrecencySettings {
 }
 
 pub const DEFAULT_FRECENCY_SETTINGS: FrecencySettings = FrecencySettings {
+    algorithm: FrecencyAlgorithm::Buckets,
+    half_life_days: 30,
     // These are the default values of the preferences.
     num_visits: 10,
     first_bucket_cutoff_days: 4,
@@ -106,7 +123,17 @@ impl FrecencySettings {
         }
     }
 
-    fn get_frecency_aged_weight(&self, age_in_days: i32) -> i32 {
+    fn get_frecency_aged_weight(&self, age_in_days: f64) -> f32 {
+        match self.algorithm {
+            FrecencyAlgorithm::Buckets => self.get_bucket_weight(age_in_days.round() as i32) as f32,
+            FrecencyAlgorithm::Exponential => {
+                let half_lives = age_in_days.max(0.0) / f64::from(self.half_life_days.max(1));
+                (f64::from(self.first_bucket_weight) * 0.5f64.powf(half_lives)) as f32
+            }
+        }
+    }
+
+    fn get_bucket_weight(&self, age_in_days: i32) -> i32 {
         if age_in_days <= self.first_bucket_cutoff_days {
             self.first_bucket_weight
         } else if age_in_days <= self.second_bucket_cutoff_days {
@@ -215,7 +242,7 @@ impl<'db, 's> FrecencyComputation<'db, 's> {
                 Ok((
                     VisitType::from_primitive(visit_type),
                     VisitType::from_primitive(target_visit_type),
-                    age_in_days.round() as i32,
+                    age_in_days,
                 ))
             },
         )?;
@@ -249,7 +276,7 @@ impl<'db, 's> FrecencyComputation<'db, 's> {
                     .get_transition_bonus(Some(VisitType::Bookmark), true, false);
             }
             if bonus != 0 {
-                let weight = self.settings.get_frecency_aged_weight(age_in_days) as f32;
+                let weight = self.settings.get_frecency_aged_weight(age_in_days);
                 points_for_sampled_visits += weight * (bonus as f32 / 100.0)
             }
             num_sampled_visits += 1;
@@ -328,3 +355,30 @@ pub fn calculate_frecency(
         fc.compute_unvisited_bookmark_frecency()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aged_weight() {
+        let buckets = DEFAULT_FRECENCY_SETTINGS;
+        assert_eq!(buckets.get_frecency_aged_weight(0.0), 100.0);
+        assert_eq!(buckets.get_frecency_aged_weight(4.4), 100.0);
+        assert_eq!(buckets.get_frecency_aged_weight(4.6), 70.0);
+        assert_eq!(buckets.get_frecency_aged_weight(365.0), 10.0);
+
+        let exponential = FrecencySettings {
+            algorithm: FrecencyAlgorithm::Exponential,
+            half_life_days: 10,
+            ..DEFAULT_FRECENCY_SETTINGS
+        };
+        assert_eq!(exponential.get_frecency_aged_weight(0.0), 100.0);
+        assert_eq!(exponential.get_frecency_aged_weight(10.0), 50.0);
+        assert_eq!(exponential.get_frecency_aged_weight(20.0), 25.0);
+        // No cliff at the bucket cutoffs.
+        let before = exponential.get_frecency_aged_weight(4.4);
+        let after = exponential.get_frecency_aged_weight(4.6);
+        assert!(before > after && before - after < 1.0);
+    }
+}
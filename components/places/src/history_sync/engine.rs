@@ -6,16 +6,22 @@ use crate::db::{PlacesDb, SharedPlacesDb};
 use crate::error::*;
 use crate::storage::history::{delete_everything, history_sync::reset};
 use crate::storage::{get_meta, put_meta};
+use crate::sync_validation::{
+    get_sync_validation_mode, note_validated, validation_mode_if_due, SyncValidationMode,
+};
 use interrupt_support::SqlInterruptScope;
 use std::sync::Arc;
-use sync15::bso::{IncomingBso, OutgoingBso};
+use sync15::bso::IncomingBso;
 use sync15::engine::{
-    CollSyncIds, CollectionRequest, EngineSyncAssociation, RequestOrder, SyncEngine,
+    CollSyncIds, CollectionRequest, EngineSyncAssociation, OutgoingRecords, RequestOrder,
+    SyncEngine,
 };
 use sync15::{telemetry, Guid, ServerTimestamp};
 
-use super::plan::{apply_plan, finish_plan, get_planned_outgoing};
+use super::plan::{apply_plan, finish_plan, get_planned_outgoing_chunk, stage_planned_outgoing};
+use super::validation::{self, LAST_VALIDATION_META_KEY, MAX_VALIDATION_RECORDS};
 use super::MAX_INCOMING_PLACES;
+use types::Timestamp;
 
 pub const LAST_SYNC_META_KEY: &str = "history_last_sync_time";
 // Note that all engines in this crate should use a *different* meta key
@@ -93,16 +99,21 @@ impl SyncEngine for HistorySyncEngine {
         Ok(())
     }
 
-    fn apply(
+    fn apply_chunked(
         &self,
         timestamp: ServerTimestamp,
         _telem: &mut telemetry::Engine,
-    ) -> anyhow::Result<Vec<OutgoingBso>> {
+    ) -> anyhow::Result<OutgoingRecords<'_>> {
         let conn = self.db.lock();
         // We know we've seen everything incoming, so it's safe to write the timestamp now.
         // If we are interrupted creating outgoing BSOs we won't re-apply what we just did.
         put_meta(&conn, LAST_SYNC_META_KEY, &timestamp.as_millis())?;
-        Ok(get_planned_outgoing(&conn)?)
+        stage_planned_outgoing(&conn)?;
+        // The records are created as they're uploaded, taking the lock for each chunk.
+        Ok(OutgoingRecords::from_fn(move || {
+            self.scope.err_if_interrupted()?;
+            Ok(get_planned_outgoing_chunk(&self.db.lock())?)
+        }))
     }
 
     fn set_uploaded(&self, new_timestamp: ServerTimestamp, ids: Vec<Guid>) -> anyhow::Result<()> {
@@ -132,6 +143,36 @@ impl SyncEngine for HistorySyncEngine {
         })
     }
 
+    fn get_validation_request(&self) -> anyhow::Result<Option<CollectionRequest>> {
+        let conn = self.db.lock();
+        // There's nothing to compare until we've synced.
+        if get_meta::<i64>(&conn, LAST_SYNC_META_KEY)?.is_none() {
+            return Ok(None);
+        }
+        let mode = validation_mode_if_due(&conn, LAST_VALIDATION_META_KEY, Timestamp::now())?;
+        Ok(match mode {
+            SyncValidationMode::Disabled => None,
+            _ => Some(
+                CollectionRequest::new("history".into())
+                    .full()
+                    .limit(MAX_VALIDATION_RECORDS, RequestOrder::Newest),
+            ),
+        })
+    }
+
+    fn validate(
+        &self,
+        server_records: Vec<IncomingBso>,
+        telem: &mut telemetry::Engine,
+    ) -> anyhow::Result<Vec<Guid>> {
+        let conn = self.db.lock();
+        let now = Timestamp::now();
+        let mode = get_sync_validation_mode(&conn)?;
+        let redownload = validation::validate(&conn, &self.scope, mode, server_records, telem)?;
+        note_validated(&conn, LAST_VALIDATION_META_KEY, now)?;
+        Ok(redownload)
+    }
+
     fn get_sync_assoc(&self) -> anyhow::Result<EngineSyncAssociation> {
         let conn = self.db.lock();
         let global = get_meta(&conn, GLOBAL_SYNCID_META_KEY)?;
@@ -12,12 +12,15 @@ pub mod engine;
 mod payload_evolution_tests;
 mod plan;
 pub mod record;
+mod validation;
 
 pub use engine::HistorySyncEngine;
 
 const MAX_INCOMING_PLACES: usize = 5000;
 const MAX_OUTGOING_PLACES: usize = 5000;
 const MAX_VISITS: usize = 20;
+// How many outgoing records we read from the database at a time.
+const OUTGOING_CHUNK_SIZE: usize = 500;
 pub const HISTORY_TTL: u32 = 5_184_000; // 60 days in milliseconds
 
 /// Visit timestamps on the server are *microseconds* since the epoch.
@@ -318,6 +318,52 @@ fn test_history_record_dupes() {
     .run()
 }
 
+#[test]
+fn test_history_metadata_roundtrip_fields() {
+    // Test that we roundtrip unknown fields from remote metadata when we upload the record for a
+    // local visit.
+    let test = RoundtripTest {
+        initial_remote_records: vec![json!({
+            "id": "a___________",
+            "title": "dogs",
+            "histUri": "https://dogs.com/",
+            "visits": [
+                {
+                    "date": timestamp(1),
+                    "type": 1,
+                },
+            ],
+            "metadata": [
+                {
+                    "createdAt": 1000,
+                    "updatedAt": 2000,
+                    "totalViewTime": 300,
+                    // How far down the page did the user read?
+                    "scrollDepth": 50,
+                },
+            ],
+        })],
+        local_visits: vec![visit("https://dogs.com/", timestamp(10))],
+        incoming_remote_records: vec![],
+        outgoing_unknown_fields: vec![],
+    };
+    let api = new_mem_api();
+    let engine = HistorySyncEngine::new(api.get_sync_connection().unwrap()).unwrap();
+    test.process_incoming_records(&engine, &test.initial_remote_records);
+    test.make_local_updates(&api);
+    let outgoing = test.process_incoming_records(&engine, &test.incoming_remote_records);
+    assert_eq!(outgoing.len(), 1);
+    let record = outgoing[0].to_test_incoming_t::<HistoryRecord>();
+    assert_eq!(record.metadata.len(), 1);
+    assert_eq!(record.metadata[0].total_view_time, 300);
+    assert_eq!(
+        record.metadata[0].unknown_fields,
+        unknown_fields!({
+            "scrollDepth": 50,
+        })
+    );
+}
+
 // Note: we purposely don't support updating the unknown for existing visits.  Visits record an
 // event at some moment in time, so it doesn't really make sense for clients to go back later and
 // change the data.
@@ -2,21 +2,22 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use super::record::{HistoryRecord, HistoryRecordVisit};
-use super::{MAX_OUTGOING_PLACES, MAX_VISITS};
+use super::record::{HistoryRecord, HistoryRecordMetadata, HistoryRecordVisit};
+use super::{MAX_OUTGOING_PLACES, MAX_VISITS, OUTGOING_CHUNK_SIZE};
 use crate::api::history::can_add_url;
 use crate::db::PlacesDb;
 use crate::error::*;
 use crate::storage::{
     delete_pending_temp_tables,
     history::history_sync::{
-        apply_synced_deletion, apply_synced_reconciliation, apply_synced_visits, fetch_outgoing,
-        fetch_visits, finish_outgoing, FetchedVisit, FetchedVisitPage,
+        apply_synced_deletion, apply_synced_metadata, apply_synced_reconciliation,
+        apply_synced_visits, fetch_outgoing_chunk, fetch_synced_metadata, fetch_visits,
+        finish_outgoing, stage_outgoing, FetchedVisit, FetchedVisitPage,
     },
 };
 use crate::types::{UnknownFields, VisitType};
 use interrupt_support::Interruptee;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap};
 use std::time::{SystemTime, UNIX_EPOCH};
 use sync15::bso::{IncomingBso, IncomingKind, OutgoingBso};
 use sync15::telemetry;
@@ -37,6 +38,33 @@ fn clamp_visit_date(visit_date: Timestamp) -> std::result::Result<Timestamp, ()>
     Ok(visit_date)
 }
 
+/// How far apart two visits of the same type can be and still be considered
+/// the same visit. Desktop records visits with microsecond precision, but we
+/// only keep milliseconds, and clients don't agree on how to round between
+/// them - so a visit can come back to us from another device a millisecond
+/// off.
+const VISIT_DEDUPE_TOLERANCE_MS: u64 = 1;
+
+/// The visits we know about for a page, by type.
+#[derive(Default)]
+struct VisitDates(HashMap<VisitType, BTreeSet<Timestamp>>);
+
+impl VisitDates {
+    fn insert(&mut self, transition: VisitType, date: Timestamp) {
+        self.0.entry(transition).or_default().insert(date);
+    }
+
+    /// Returns true if we know about a visit of the same type within
+    /// `VISIT_DEDUPE_TOLERANCE_MS` of `date`.
+    fn contains_near(&self, transition: VisitType, date: Timestamp) -> bool {
+        self.0.get(&transition).map_or(false, |dates| {
+            let start = Timestamp(date.0.saturating_sub(VISIT_DEDUPE_TOLERANCE_MS));
+            let end = Timestamp(date.0.saturating_add(VISIT_DEDUPE_TOLERANCE_MS));
+            dates.range(start..=end).next().is_some()
+        })
+    }
+}
+
 /// This is the action we will take *locally* for each incoming record.
 /// For example, IncomingPlan::Delete means we will be deleting a local record
 /// and not that we will be uploading a tombstone or deleting the record itself.
@@ -55,6 +83,7 @@ pub enum IncomingPlan {
         url: Url,
         new_title: Option<String>,
         visits: Vec<HistoryRecordVisit>,
+        metadata: Vec<HistoryRecordMetadata>,
         unknown_fields: UnknownFields,
     },
     /// Entry exists locally and it's the same as the incoming record. This is
@@ -98,13 +127,12 @@ fn plan_incoming_record(conn: &PlacesDb, record: HistoryRecord, max_visits: usiz
             Some((p, v)) => (Some(p), v),
         };
 
-    let guid_changed = match existing_page {
+    let guid_changed = match &existing_page {
         Some(p) => p.guid != record.id,
         None => false,
     };
 
-    let mut cur_visit_map: HashSet<(VisitType, Timestamp)> =
-        HashSet::with_capacity(existing_visits.len());
+    let mut cur_visits = VisitDates::default();
     for visit in &existing_visits {
         // it should be impossible for us to have invalid visits locally, but...
         let transition = match visit.visit_type {
@@ -113,7 +141,7 @@ fn plan_incoming_record(conn: &PlacesDb, record: HistoryRecord, max_visits: usiz
         };
         match clamp_visit_date(visit.visit_date) {
             Ok(date_use) => {
-                cur_visit_map.insert((transition, date_use));
+                cur_visits.insert(transition, date_use);
             }
             Err(_) => {
                 log::warn!("Ignored visit before 1993-01-23");
@@ -144,15 +172,15 @@ fn plan_incoming_record(conn: &PlacesDb, record: HistoryRecord, max_visits: usiz
                 if earliest_allowed > timestamp.into() {
                     continue;
                 }
-                // If the entry isn't in our map we should add it.
-                let key = (transition, timestamp);
-                if !cur_visit_map.contains(&key) {
+                // If we don't already have this visit, or one that only
+                // differs in precision, we should add it.
+                if !cur_visits.contains_near(transition, timestamp) {
                     to_apply.push(HistoryRecordVisit {
                         date: timestamp.into(),
                         transition: transition as u8,
                         unknown_fields: incoming_visit.unknown_fields,
                     });
-                    cur_visit_map.insert(key);
+                    cur_visits.insert(transition, timestamp);
                 }
             }
             Err(()) => {
@@ -160,15 +188,42 @@ fn plan_incoming_record(conn: &PlacesDb, record: HistoryRecord, max_visits: usiz
             }
         }
     }
+    // Work out which of the incoming metadata is new, or was updated more
+    // recently than ours. We only apply metadata for pages that we'll have
+    // visits for.
+    let metadata = match &existing_page {
+        Some(page) if !record.metadata.is_empty() => {
+            let local_updated_at = match fetch_synced_metadata(conn, page.row_id, max_visits) {
+                Ok(m) => m
+                    .into_iter()
+                    .map(|m| (m.created_at, m.updated_at))
+                    .collect::<HashMap<_, _>>(),
+                Err(e) => return IncomingPlan::Failed(e),
+            };
+            record
+                .metadata
+                .into_iter()
+                .filter(|m| {
+                    local_updated_at
+                        .get(&m.created_at)
+                        .map_or(true, |updated_at| m.updated_at > *updated_at)
+                })
+                .collect()
+        }
+        Some(_) => Vec::new(),
+        None if to_apply.is_empty() => Vec::new(),
+        None => record.metadata,
+    };
     // Now we need to check the other attributes.
     // Check if we should update title? For now, assume yes. It appears
     // as though desktop always updates it.
-    if guid_changed || !to_apply.is_empty() {
+    if guid_changed || !to_apply.is_empty() || !metadata.is_empty() {
         let new_title = Some(record.title);
         IncomingPlan::Apply {
             url,
             new_title,
             visits: to_apply,
+            metadata,
             unknown_fields: record.unknown_fields,
         }
     } else {
@@ -239,12 +294,14 @@ pub fn apply_plan(
                 url,
                 new_title,
                 visits,
+                metadata,
                 unknown_fields,
             } => {
                 log::trace!(
-                    "incoming: will apply {guid:?}: url={url:?}, title={new_title:?}, to_add={visits:?}, unknown_fields={unknown_fields:?}"
+                    "incoming: will apply {guid:?}: url={url:?}, title={new_title:?}, to_add={visits:?}, metadata={metadata:?}, unknown_fields={unknown_fields:?}"
                 );
                 apply_synced_visits(db, &guid, url, new_title, visits, unknown_fields)?;
+                apply_synced_metadata(db, url, metadata)?;
                 telem.applied(1);
             }
             IncomingPlan::Reconciled => {
@@ -269,15 +326,22 @@ pub fn apply_plan(
     Ok(())
 }
 
-pub fn get_planned_outgoing(db: &PlacesDb) -> Result<Vec<OutgoingBso>> {
-    // It might make sense for fetch_outgoing to manage its own
-    // begin_transaction - even though doesn't seem a large bottleneck
-    // at this time, the fact we hold a single transaction for the entire call
-    // really is used only for performance, so it's certainly a candidate.
+/// Stages the outgoing records, for `get_planned_outgoing_chunk` to fetch.
+pub fn stage_planned_outgoing(db: &PlacesDb) -> Result<usize> {
     let tx = db.begin_transaction()?;
-    let outgoing = fetch_outgoing(db, MAX_OUTGOING_PLACES, MAX_VISITS)?;
+    let num_staged = stage_outgoing(db, MAX_OUTGOING_PLACES)?;
     tx.commit()?;
-    Ok(outgoing)
+    Ok(num_staged)
+}
+
+/// Fetches the next chunk of outgoing records, or `None` if they've all
+/// been fetched. Each chunk is fetched in its own transaction, so we don't
+/// hold one open while the previous chunk is uploaded.
+pub fn get_planned_outgoing_chunk(db: &PlacesDb) -> Result<Option<Vec<OutgoingBso>>> {
+    let tx = db.begin_transaction()?;
+    let chunk = fetch_outgoing_chunk(db, MAX_VISITS, OUTGOING_CHUNK_SIZE)?;
+    tx.commit()?;
+    Ok(chunk)
 }
 
 pub fn finish_plan(db: &PlacesDb) -> Result<()> {
@@ -352,7 +416,12 @@ mod tests {
             &NeverInterrupts,
         )
         .expect("should apply");
-        get_planned_outgoing(db).expect("should get outgoing")
+        stage_planned_outgoing(db).expect("should stage outgoing");
+        let mut outgoing = Vec::new();
+        while let Some(chunk) = get_planned_outgoing_chunk(db).expect("should get outgoing") {
+            outgoing.extend(chunk);
+        }
+        outgoing
     }
 
     #[test]
@@ -364,6 +433,7 @@ mod tests {
             title: "title".into(),
             hist_uri: "http://example.com".into(),
             visits: vec![],
+            metadata: vec![],
             unknown_fields: UnknownFields::new(),
         };
 
@@ -383,6 +453,7 @@ mod tests {
             title: "title".into(),
             hist_uri: "invalid".into(),
             visits: vec![],
+            metadata: vec![],
             unknown_fields: UnknownFields::new(),
         };
 
@@ -407,6 +478,7 @@ mod tests {
             title: "title".into(),
             hist_uri: "https://example.com".into(),
             visits,
+            metadata: vec![],
             unknown_fields: UnknownFields::new(),
         };
 
@@ -444,6 +516,7 @@ mod tests {
             title: "title".into(),
             hist_uri: "https://example.com".into(),
             visits,
+            metadata: vec![],
             unknown_fields: UnknownFields::new(),
         };
         // We should have reconciled it.
@@ -473,6 +546,7 @@ mod tests {
             title: "title".into(),
             hist_uri: "https://example.com".into(),
             visits: vec![],
+            metadata: vec![],
             unknown_fields: UnknownFields::new(),
         };
         // Even though there are no visits we should record that it will be
@@ -703,6 +777,7 @@ mod tests {
             title: "title".into(),
             hist_uri: "http://example.com".into(),
             visits,
+            metadata: vec![],
             unknown_fields: UnknownFields::new(),
         };
         let plan = plan_incoming_record(&db, record, 10);
@@ -853,6 +928,189 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_visit_reconciliation_different_precision() -> Result<()> {
+        let _ = env_logger::try_init();
+        let db = PlacesDb::open_in_memory(ConnectionType::Sync)?;
+        let ts: Timestamp = (SystemTime::now() - Duration::new(5, 0)).into();
+        let url = Url::parse("https://example.com")?;
+
+        let obs = VisitObservation::new(url.clone())
+            .with_visit_type(VisitType::Link)
+            .with_at(Some(ts));
+        apply_observation(&db, obs)?;
+        let guid = get_existing_guid(&db, &url);
+
+        // An incoming record with our visit, as another client rounded it,
+        // and a visit that's really different.
+        let json = json!({
+            "id": guid,
+            "title": "title",
+            "histUri": url.as_str(),
+            "visits": [
+                {"date": ServerVisitTimestamp((ts.0 + 1) * 1000 + 500), "type": 1},
+                {"date": ServerVisitTimestamp::from(Timestamp(ts.0 + 10)), "type": 1},
+            ]
+        });
+
+        apply_and_get_outgoing(&db, vec![IncomingBso::from_test_content(json)]);
+
+        let (_page, visits) = fetch_visits(&db, &url, 3)?.expect("page exists");
+        let mut dates = visits.iter().map(|v| v.visit_date).collect::<Vec<_>>();
+        dates.sort();
+        assert_eq!(dates, vec![ts, Timestamp(ts.0 + 10)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_incoming_metadata() -> Result<()> {
+        let _ = env_logger::try_init();
+        let db = PlacesDb::open_in_memory(ConnectionType::Sync)?;
+        let ts: Timestamp = (SystemTime::now() - Duration::new(5, 0)).into();
+        let url = Url::parse("https://example.com")?;
+
+        let obs = VisitObservation::new(url.clone())
+            .with_visit_type(VisitType::Link)
+            .with_at(Some(ts));
+        apply_observation(&db, obs)?;
+        let guid = get_existing_guid(&db, &url);
+
+        let record_with_metadata = |updated_at: i64, total_view_time: i64| {
+            json!({
+                "id": guid,
+                "title": "title",
+                "histUri": url.as_str(),
+                "visits": [ {"date": ServerVisitTimestamp::from(ts), "type": 1}],
+                "metadata": [{
+                    "createdAt": 1000,
+                    "updatedAt": updated_at,
+                    "totalViewTime": total_view_time,
+                    "searchTerm": "Example",
+                    "referrerUrl": "https://unknown.example.com/",
+                    "documentType": 1,
+                    "scrollDepth": 50,
+                }],
+            })
+        };
+        let get_total_view_time = || -> Result<i64> {
+            Ok(db.query_one("SELECT total_view_time FROM moz_places_metadata")?)
+        };
+
+        // Metadata is applied even though there are no new visits.
+        let outgoing = apply_and_get_outgoing(
+            &db,
+            vec![IncomingBso::from_test_content(record_with_metadata(
+                2000, 300,
+            ))],
+        );
+        assert_eq!(get_total_view_time()?, 300);
+        assert_eq!(
+            db.query_one::<String>(
+                "SELECT q.term FROM moz_places_metadata m
+                 JOIN moz_places_metadata_search_queries q ON q.id = m.search_query_id"
+            )?,
+            "example"
+        );
+        assert!(!db.exists(
+            "SELECT 1 FROM moz_places_metadata WHERE referrer_place_id NOT NULL",
+            []
+        )?);
+
+        // Our local visit is still outgoing, and takes the metadata with it.
+        assert_eq!(outgoing.len(), 1);
+        let record = outgoing[0].to_test_incoming_t::<HistoryRecord>();
+        assert_eq!(record.metadata.len(), 1);
+        assert_eq!(record.metadata[0].total_view_time, 300);
+        assert_eq!(record.metadata[0].search_term.as_deref(), Some("example"));
+        // We don't know the referrer page, but still upload its URL.
+        assert_eq!(
+            record.metadata[0].referrer_url.as_deref(),
+            Some("https://unknown.example.com/")
+        );
+        assert!(!record.metadata[0]
+            .unknown_fields
+            .contains_key("referrerUrl"));
+        assert_eq!(
+            record.metadata[0].unknown_fields.get("scrollDepth"),
+            Some(&json!(50))
+        );
+
+        // Older metadata is ignored...
+        let record = serde_json::from_value::<HistoryRecord>(record_with_metadata(1500, 100))?;
+        assert!(matches!(
+            plan_incoming_record(&db, record, 10),
+            IncomingPlan::Reconciled
+        ));
+
+        // ...but newer metadata replaces ours.
+        apply_and_get_outgoing(
+            &db,
+            vec![IncomingBso::from_test_content(record_with_metadata(
+                3000, 600,
+            ))],
+        );
+        assert_eq!(get_total_view_time()?, 600);
+        Ok(())
+    }
+
+    #[test]
+    fn test_outgoing_local_metadata() -> Result<()> {
+        use crate::storage::history_metadata::{
+            apply_metadata_observation, HistoryMetadataObservation,
+        };
+
+        let _ = env_logger::try_init();
+        let db = PlacesDb::open_in_memory(ConnectionType::Sync)?;
+        let ts: Timestamp = (SystemTime::now() - Duration::new(5, 0)).into();
+        let url = Url::parse("https://example.com")?;
+
+        // Sync a page, so that it doesn't have anything to upload.
+        let outgoing = apply_and_get_outgoing(
+            &db,
+            vec![IncomingBso::from_test_content(json!({
+                "id": "aaaaaaaaaaaa",
+                "title": "title",
+                "histUri": url.as_str(),
+                "visits": [{"date": ServerVisitTimestamp::from(ts), "type": 1}],
+            }))],
+        );
+        assert_eq!(outgoing.len(), 0);
+        assert_eq!(get_sync(&db, &url), (SyncStatus::Normal, 0));
+
+        // Recording metadata for it should upload it again, with the metadata.
+        let observe = |view_time| {
+            apply_metadata_observation(
+                &db,
+                HistoryMetadataObservation {
+                    url: url.to_string(),
+                    view_time: Some(view_time),
+                    search_term: None,
+                    document_type: None,
+                    referrer_url: None,
+                    title: None,
+                },
+            )
+        };
+        observe(100)?;
+        assert_eq!(get_sync(&db, &url), (SyncStatus::Normal, 1));
+        let outgoing = apply_and_get_outgoing(&db, vec![]);
+        assert_eq!(outgoing.len(), 1);
+        let record = outgoing[0].to_test_incoming_t::<HistoryRecord>();
+        assert_eq!(record.metadata.len(), 1);
+        assert_eq!(record.metadata[0].total_view_time, 100);
+        finish_plan(&db)?;
+        assert_eq!(get_sync(&db, &url), (SyncStatus::Normal, 0));
+
+        // And so should updating it.
+        observe(50)?;
+        let outgoing = apply_and_get_outgoing(&db, vec![]);
+        assert_eq!(outgoing.len(), 1);
+        let record = outgoing[0].to_test_incoming_t::<HistoryRecord>();
+        assert_eq!(record.metadata.len(), 1);
+        assert_eq!(record.metadata[0].total_view_time, 150);
+        Ok(())
+    }
+
     #[test]
     fn test_incoming_tombstone_local_new() -> Result<()> {
         let _ = env_logger::try_init();
@@ -943,4 +1201,82 @@ mod tests {
         let ts = Timestamp::now();
         assert_eq!(clamp_visit_date(ts), Ok(ts));
     }
+
+    fn history_record(guid: &str, url: &str, date: Timestamp) -> IncomingBso {
+        IncomingBso::from_test_content(json!({
+            "id": guid,
+            "title": "title",
+            "histUri": url,
+            "visits": [{"date": ServerVisitTimestamp::from(date), "type": 1}],
+        }))
+    }
+
+    // Interrupts on the call after the first `n`.
+    struct InterruptAfter(std::cell::Cell<usize>);
+
+    impl Interruptee for InterruptAfter {
+        fn was_interrupted(&self) -> bool {
+            let remaining = self.0.get();
+            if remaining == 0 {
+                return true;
+            }
+            self.0.set(remaining - 1);
+            false
+        }
+    }
+
+    #[test]
+    fn test_apply_plan_notifies_observers() -> Result<()> {
+        use crate::api::places_api::PlacesApi;
+        use crate::observer::{test::CommittedObserver, PlacesChange};
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let api = PlacesApi::new(tmpdir.path().join("places.sqlite"))?;
+        let observer = CommittedObserver::register(&api)?;
+        let sync_conn = api.get_sync_connection()?;
+        let db = sync_conn.lock();
+        let now = Timestamp::now();
+        let incoming = || {
+            vec![
+                history_record("aaaaaaaaaaaa", "https://example.com/a", now),
+                history_record("bbbbbbbbbbbb", "https://example.com/b", now),
+            ]
+        };
+
+        // Both records are planned, then we're interrupted while applying the
+        // second one, which rolls back the first, so nothing is delivered.
+        let err = apply_plan(
+            &db,
+            incoming(),
+            &mut telemetry::EngineIncoming::new(),
+            &InterruptAfter(std::cell::Cell::new(3)),
+        )
+        .expect_err("should be interrupted");
+        assert!(matches!(err, Error::InterruptedError(_)), "{:?}", err);
+        assert_eq!(observer.take(), Vec::<Vec<PlacesChange>>::new());
+
+        // Applying them again delivers the new visits once, after committing.
+        apply_plan(
+            &db,
+            incoming(),
+            &mut telemetry::EngineIncoming::new(),
+            &NeverInterrupts,
+        )?;
+        let batches = observer.take();
+        assert_eq!(batches.len(), 1);
+        let visits: Vec<_> = batches[0]
+            .iter()
+            .filter_map(|change| match change {
+                PlacesChange::VisitAdded {
+                    guid,
+                    visit_date,
+                    is_local: false,
+                    ..
+                } if *visit_date == now => Some(guid.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(visits, vec!["aaaaaaaaaaaa", "bbbbbbbbbbbb"]);
+        Ok(())
+    }
 }
@@ -18,6 +18,29 @@ pub struct HistoryRecordVisit {
     pub unknown_fields: UnknownFields,
 }
 
+/// A `moz_places_metadata` entry for the page. Clients that don't know about
+/// metadata keep it in the record's unknown fields, so it roundtrips through them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecordMetadata {
+    /// Milliseconds since the epoch, identifies the entry on every device.
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub total_view_time: i64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_term: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referrer_url: Option<String>,
+
+    #[serde(default)]
+    pub document_type: i64,
+
+    #[serde(flatten)]
+    pub unknown_fields: UnknownFields,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HistoryRecord {
@@ -33,6 +56,9 @@ pub struct HistoryRecord {
 
     pub visits: Vec<HistoryRecordVisit>,
 
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metadata: Vec<HistoryRecordMetadata>,
+
     #[serde(flatten)]
     pub unknown_fields: UnknownFields,
 }
@@ -78,4 +104,51 @@ mod tests {
         let rec = serde_json::from_value::<HistoryRecord>(json).expect("should deser");
         assert!(rec.title.is_empty());
     }
+
+    #[test]
+    fn test_metadata() {
+        let json = serde_json::json!({
+            "id": "foo",
+            "histUri": "https://example.com",
+            "visits": [],
+            "metadata": [{
+                "createdAt": 1000,
+                "updatedAt": 2000,
+                "totalViewTime": 300,
+                "searchTerm": "example",
+                "documentType": 1,
+                "scrollDepth": 50,
+            }, {
+                "createdAt": 3000,
+                "updatedAt": 3000,
+                "totalViewTime": 0,
+            }],
+        });
+
+        let rec = serde_json::from_value::<HistoryRecord>(json.clone()).expect("should deser");
+        assert_eq!(rec.metadata.len(), 2);
+        assert_eq!(rec.metadata[0].search_term.as_deref(), Some("example"));
+        assert_eq!(rec.metadata[0].referrer_url, None);
+        assert_eq!(rec.metadata[1].document_type, 0);
+        assert_eq!(
+            rec.metadata[0].unknown_fields.get("scrollDepth"),
+            Some(&serde_json::json!(50))
+        );
+
+        let ser = serde_json::to_value(&rec).expect("should ser");
+        assert_eq!(ser["metadata"][0], json["metadata"][0]);
+    }
+
+    #[test]
+    fn test_no_metadata() {
+        // Records without metadata don't grow an empty `metadata` field.
+        let json = serde_json::json!({
+            "id": "foo",
+            "histUri": "https://example.com",
+            "visits": [],
+        });
+        let rec = serde_json::from_value::<HistoryRecord>(json.clone()).expect("should deser");
+        assert!(rec.metadata.is_empty());
+        assert_eq!(serde_json::to_value(&rec).expect("should ser"), json);
+    }
 }
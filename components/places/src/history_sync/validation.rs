@@ -0,0 +1,352 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Validates local history against the newest records on the server.
+//
+// History doesn't have a tree, and both the server and the local database
+// expire old history, so we only look for problems we can be sure of:
+// server records with invalid or duplicate URLs, pages that have a
+// different GUID locally, recently visited pages that the server is missing,
+// and pages that the server says were deleted. As for bookmarks, server
+// records changed since the last sync, and local pages with unsynced
+// changes, aren't compared.
+//
+// In repair mode, missing pages, and pages whose server records have invalid
+// URLs, are flagged for upload. Records for pages with a different GUID
+// locally, and tombstones for pages we still have, are downloaded again.
+
+use super::engine::LAST_SYNC_META_KEY;
+use super::record::HistoryRecord;
+use crate::db::PlacesDb;
+use crate::error::*;
+use crate::storage::get_meta;
+use crate::sync_validation::SyncValidationMode;
+use crate::types::SyncStatus;
+use interrupt_support::SqlInterruptScope;
+use sql_support::ConnExt;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+use sync15::bso::{IncomingBso, IncomingKind};
+use sync15::{telemetry, ServerTimestamp};
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+use url::Url;
+
+/// The version reported in the validation telemetry.
+const VALIDATION_VERSION: u32 = 1;
+
+pub const LAST_VALIDATION_META_KEY: &str = "history_last_validation_time";
+
+/// The most records we fetch to validate against, newest first. Servers can
+/// have far more history than we'd want to hold in memory.
+pub const MAX_VALIDATION_RECORDS: usize = 5000;
+
+/// Records expire from the server `HISTORY_TTL` after they're uploaded, so
+/// only pages visited more recently than this are certain to be there.
+const SERVER_MISSING_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+#[derive(Debug, Default)]
+struct Problems {
+    malformed: usize,
+    invalid_urls: usize,
+    duplicate_urls: usize,
+    guid_mismatches: usize,
+    server_missing: usize,
+    server_deleted: usize,
+    // Local pages to upload again.
+    reupload: BTreeSet<SyncGuid>,
+    // Server records to download again.
+    redownload: BTreeSet<SyncGuid>,
+}
+
+impl Problems {
+    fn record(&self, telem: &mut telemetry::Engine) {
+        let mut validation = telemetry::Validation::with_version(VALIDATION_VERSION);
+        validation
+            .problem("malformed", self.malformed)
+            .problem("invalidURLs", self.invalid_urls)
+            .problem("duplicateURLs", self.duplicate_urls)
+            .problem("guidMismatches", self.guid_mismatches)
+            .problem("serverMissing", self.server_missing)
+            .problem("serverDeleted", self.server_deleted);
+        telem.validation(validation);
+    }
+}
+
+// Returns the GUID of the local page matching `where_clause`, if there is
+// one, and whether it's been uploaded and hasn't changed since.
+fn fetch_local_page(
+    db: &PlacesDb,
+    where_clause: &str,
+    value: &str,
+) -> Result<Option<(SyncGuid, bool)>> {
+    db.try_query_row(
+        &format!(
+            "SELECT guid, sync_status = {normal} AND sync_change_counter = 0
+             FROM moz_places
+             WHERE {where_clause}",
+            normal = SyncStatus::Normal as u8,
+        ),
+        &[(":value", &value)],
+        |row| -> Result<_> { Ok((row.get(0)?, row.get(1)?)) },
+        true,
+    )
+}
+
+fn fetch_page_by_guid(db: &PlacesDb, guid: &SyncGuid) -> Result<Option<(SyncGuid, bool)>> {
+    fetch_local_page(db, "guid = :value", guid.as_str())
+}
+
+fn fetch_page_by_url(db: &PlacesDb, url: &Url) -> Result<Option<(SyncGuid, bool)>> {
+    fetch_local_page(db, "url_hash = hash(:value) AND url = :value", url.as_str())
+}
+
+fn find_problems(
+    db: &PlacesDb,
+    scope: &SqlInterruptScope,
+    server_records: Vec<IncomingBso>,
+) -> Result<Problems> {
+    let last_sync = ServerTimestamp(get_meta::<i64>(db, LAST_SYNC_META_KEY)?.unwrap_or_default());
+    let mut problems = Problems::default();
+    // A record is modified when a page is uploaded, which is after all its
+    // visits. So if we only have the newest records, we can still tell if a
+    // page visited after the oldest of them is missing.
+    let oldest_modified = if server_records.len() >= MAX_VALIDATION_RECORDS {
+        server_records
+            .iter()
+            .map(|record| record.envelope.modified.as_millis())
+            .min()
+    } else {
+        None
+    };
+    let mut server_guids = HashSet::with_capacity(server_records.len());
+    let mut guids_by_url: HashMap<Url, Vec<SyncGuid>> = HashMap::new();
+
+    for record in server_records {
+        scope.err_if_interrupted()?;
+        let modified = record.envelope.modified;
+        let content = record.into_content::<HistoryRecord>();
+        let guid = content.envelope.id.clone();
+        server_guids.insert(guid.clone());
+        match content.kind {
+            IncomingKind::Tombstone => {
+                if modified > last_sync {
+                    continue;
+                }
+                if let Some((_, true)) = fetch_page_by_guid(db, &guid)? {
+                    problems.server_deleted += 1;
+                    problems.redownload.insert(guid);
+                }
+            }
+            IncomingKind::Content(record) => match Url::parse(&record.hist_uri) {
+                Ok(url) => {
+                    if modified <= last_sync {
+                        guids_by_url.entry(url).or_default().push(guid);
+                    }
+                }
+                Err(_) => {
+                    problems.invalid_urls += 1;
+                    if fetch_page_by_guid(db, &guid)?.is_some() {
+                        problems.reupload.insert(guid);
+                    }
+                }
+            },
+            IncomingKind::Malformed => {
+                problems.malformed += 1;
+                if fetch_page_by_guid(db, &guid)?.is_some() {
+                    problems.reupload.insert(guid);
+                }
+            }
+        }
+    }
+
+    for (url, guids) in &guids_by_url {
+        scope.err_if_interrupted()?;
+        let local_page = fetch_page_by_url(db, url)?;
+        if let Some((local_guid, _)) = &local_page {
+            // The server has the page, even if it's under a different GUID.
+            server_guids.insert(local_guid.clone());
+        }
+        if guids.len() > 1 {
+            // We can't tell which record is right, so we just report these.
+            problems.duplicate_urls += 1;
+            continue;
+        }
+        if let Some((local_guid, true)) = local_page {
+            if local_guid != guids[0] {
+                problems.guid_mismatches += 1;
+                problems.redownload.insert(guids[0].clone());
+            }
+        }
+    }
+
+    let mut cutoff = Timestamp::now()
+        .checked_sub(SERVER_MISSING_MAX_AGE)
+        .unwrap_or_default();
+    if let Some(oldest_modified) = oldest_modified {
+        cutoff = cutoff.max(Timestamp(oldest_modified as u64));
+    }
+    let recent = db.query_rows_and_then_cached(
+        &format!(
+            "SELECT guid FROM moz_places
+             WHERE sync_status = {normal} AND
+                   sync_change_counter = 0 AND
+                   NOT hidden AND
+                   MAX(last_visit_date_local, last_visit_date_remote) > :cutoff",
+            normal = SyncStatus::Normal as u8,
+        ),
+        &[(":cutoff", &cutoff)],
+        |row| row.get::<_, SyncGuid>(0),
+    )?;
+    for guid in recent {
+        if !server_guids.contains(&guid) {
+            problems.server_missing += 1;
+            problems.reupload.insert(guid);
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Validates local history against the newest records on the server, and records
+/// the problems in `telem`. In repair mode, local pages that fix the problems
+/// are flagged for upload, and the IDs of the server records to download
+/// again are returned.
+pub(crate) fn validate(
+    db: &PlacesDb,
+    scope: &SqlInterruptScope,
+    mode: SyncValidationMode,
+    server_records: Vec<IncomingBso>,
+    telem: &mut telemetry::Engine,
+) -> Result<Vec<SyncGuid>> {
+    let problems = find_problems(db, scope, server_records)?;
+    log::debug!("history validation found {:?}", problems);
+    problems.record(telem);
+
+    if mode != SyncValidationMode::Repair {
+        return Ok(vec![]);
+    }
+    let reupload = problems.reupload.into_iter().collect::<Vec<_>>();
+    if !reupload.is_empty() {
+        log::info!("Repairing history: uploading {} pages", reupload.len());
+        let mut tx = db.begin_transaction()?;
+        sql_support::each_chunk(&reupload, |chunk, _| -> Result<()> {
+            db.execute(
+                &format!(
+                    "UPDATE moz_places SET
+                         sync_change_counter = sync_change_counter + 1
+                     WHERE guid IN ({vars})",
+                    vars = sql_support::repeat_sql_vars(chunk.len()),
+                ),
+                rusqlite::params_from_iter(chunk),
+            )?;
+            tx.maybe_commit()?;
+            scope.err_if_interrupted()?;
+            Ok(())
+        })?;
+        tx.commit()?;
+    }
+    Ok(problems.redownload.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::history::{apply_observation, url_to_guid};
+    use crate::storage::put_meta;
+    use crate::types::VisitType;
+    use serde_json::json;
+
+    const LAST_SYNC: ServerTimestamp = ServerTimestamp(1000);
+
+    fn add_synced_page(conn: &PlacesDb, url: &str) -> SyncGuid {
+        let url = Url::parse(url).unwrap();
+        apply_observation(
+            conn,
+            VisitObservation::new(url.clone()).with_visit_type(VisitType::Link),
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE moz_places SET sync_status = 2, sync_change_counter = 0
+             WHERE url_hash = hash(?1) AND url = ?1",
+            [url.as_str()],
+        )
+        .unwrap();
+        url_to_guid(conn, &url).unwrap().unwrap()
+    }
+
+    fn record(guid: &SyncGuid, url: &str) -> IncomingBso {
+        IncomingBso::from_test_content_ts(
+            json!({"id": guid, "histUri": url, "visits": [{"date": 1, "type": 1}]}),
+            LAST_SYNC,
+        )
+    }
+
+    fn change_counter(conn: &PlacesDb, guid: &SyncGuid) -> i64 {
+        conn.query_row(
+            "SELECT sync_change_counter FROM moz_places WHERE guid = ?",
+            [guid],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let conn = new_mem_connection();
+        let synced = add_synced_page(&conn, "https://example.com/synced");
+        add_synced_page(&conn, "https://example.com/other-guid");
+        let missing = add_synced_page(&conn, "https://example.com/missing");
+        let deleted = add_synced_page(&conn, "https://example.com/deleted");
+        put_meta(&conn, LAST_SYNC_META_KEY, &LAST_SYNC.as_millis())?;
+
+        let other_guid = SyncGuid::from("otherGuidAAA");
+        let server_records = || {
+            vec![
+                record(&synced, "https://example.com/synced"),
+                record(&other_guid, "https://example.com/other-guid"),
+                record(&SyncGuid::from("invalidUrlAA"), "not a url"),
+                record(&SyncGuid::from("duplicateAAA"), "https://example.com/dupe"),
+                record(&SyncGuid::from("duplicateBBB"), "https://example.com/dupe"),
+                IncomingBso::new_test_tombstone(deleted.clone()),
+            ]
+        };
+
+        let scope = conn.begin_interrupt_scope()?;
+        let problems = find_problems(&conn, &scope, server_records())?;
+        assert_eq!(problems.invalid_urls, 1);
+        assert_eq!(problems.duplicate_urls, 1);
+        assert_eq!(problems.guid_mismatches, 1);
+        assert_eq!(problems.server_missing, 1);
+        assert_eq!(problems.server_deleted, 1);
+
+        // Validating doesn't change anything.
+        let mut telem = telemetry::Engine::new("history");
+        let redownload = validate(
+            &conn,
+            &scope,
+            SyncValidationMode::Validate,
+            server_records(),
+            &mut telem,
+        )?;
+        assert!(redownload.is_empty());
+        assert_eq!(change_counter(&conn, &missing), 0);
+
+        let redownload = validate(
+            &conn,
+            &scope,
+            SyncValidationMode::Repair,
+            server_records(),
+            &mut telem,
+        )?;
+        let mut expected = vec![deleted, other_guid];
+        expected.sort();
+        assert_eq!(redownload, expected);
+        assert_eq!(change_counter(&conn, &missing), 1);
+        assert_eq!(change_counter(&conn, &synced), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+pub mod bookmarks;
+pub mod history;
+pub use bookmarks::import as import_bookmarks;
+pub use history::import as import_history;
+
+use types::Timestamp;
+
+// Chromium stores times as microseconds since the Windows epoch
+// (1601-01-01), which is this many milliseconds before the Unix epoch.
+const WINDOWS_EPOCH_OFFSET_MS: i64 = 11_644_473_600_000;
+
+/// Converts a Chromium timestamp to one of ours, returning `None` for
+/// timestamps which are missing or out of range.
+fn timestamp_from_chromium(micros: i64) -> Option<Timestamp> {
+    let millis = (micros / 1000).checked_sub(WINDOWS_EPOCH_OFFSET_MS)?;
+    let ts = Timestamp(u64::try_from(millis).ok()?);
+    if Timestamp::EARLIEST <= ts && ts <= Timestamp::now() {
+        Some(ts)
+    } else {
+        None
+    }
+}
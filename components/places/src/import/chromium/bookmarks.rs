@@ -0,0 +1,253 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::timestamp_from_chromium;
+use crate::error::Result;
+use crate::storage::bookmarks::json_tree::{
+    insert_tree, BookmarkNode, BookmarkTreeNode, FolderNode,
+};
+use crate::storage::bookmarks::BookmarkRootGuid;
+use crate::storage::URL_LENGTH_MAX;
+use crate::PlacesDb;
+use serde_derive::*;
+use std::fs::File;
+use std::io::BufReader;
+use url::Url;
+
+// The subset of Chromium's `Bookmarks` JSON file we care about.
+#[derive(Debug, Deserialize)]
+struct ChromiumBookmarks {
+    roots: ChromiumRoots,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ChromiumRoots {
+    bookmark_bar: Option<ChromiumItem>,
+    other: Option<ChromiumItem>,
+    synced: Option<ChromiumItem>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ChromiumItem {
+    #[serde(rename = "type")]
+    item_type: String,
+    name: Option<String>,
+    url: Option<String>,
+    // Times are strings holding microseconds since 1601-01-01.
+    date_added: Option<String>,
+    date_modified: Option<String>,
+    children: Vec<ChromiumItem>,
+}
+
+/// Imports bookmarks from the `Bookmarks` JSON file of a Chromium-based
+/// browser.
+///
+/// The bookmarks bar is imported into the toolbar, "Other bookmarks" into
+/// unfiled and "Mobile bookmarks" into the mobile root. Chromium doesn't have
+/// separators, keywords or tags. Existing bookmarks are not touched, so
+/// importing the same file twice will create duplicates.
+///
+/// Returns the number of bookmarks (not folders) imported.
+pub fn import(conn: &PlacesDb, path: impl AsRef<std::path::Path>) -> Result<u32> {
+    let reader = BufReader::new(File::open(path)?);
+    let bookmarks: ChromiumBookmarks = serde_json::from_reader(reader)?;
+    let mut num_imported = 0;
+    for (item, root) in [
+        (bookmarks.roots.bookmark_bar, BookmarkRootGuid::Toolbar),
+        (bookmarks.roots.other, BookmarkRootGuid::Unfiled),
+        (bookmarks.roots.synced, BookmarkRootGuid::Mobile),
+    ] {
+        let children: Vec<_> = match item {
+            Some(item) => item
+                .children
+                .into_iter()
+                .filter_map(|child| convert_item(child, &mut num_imported))
+                .collect(),
+            None => continue,
+        };
+        if children.is_empty() {
+            continue;
+        }
+        insert_tree(
+            conn,
+            FolderNode {
+                guid: Some(root.as_guid()),
+                children,
+                ..Default::default()
+            },
+        )?;
+    }
+    Ok(num_imported)
+}
+
+fn parse_time(time: Option<&str>) -> Option<types::Timestamp> {
+    time.and_then(|t| t.parse().ok())
+        .and_then(timestamp_from_chromium)
+}
+
+fn convert_item(item: ChromiumItem, num_bookmarks: &mut u32) -> Option<BookmarkTreeNode> {
+    let date_added = parse_time(item.date_added.as_deref());
+    Some(match item.item_type.as_str() {
+        "url" => {
+            let url = match item.url.as_deref().map(Url::parse) {
+                Some(Ok(url)) if url.as_str().len() <= URL_LENGTH_MAX => url,
+                _ => {
+                    log::warn!("ignoring bookmark with a missing or invalid URL");
+                    return None;
+                }
+            };
+            *num_bookmarks += 1;
+            BookmarkNode {
+                guid: None,
+                date_added,
+                last_modified: None,
+                title: item.name,
+                url,
+            }
+            .into()
+        }
+        "folder" => FolderNode {
+            guid: None,
+            date_added,
+            last_modified: parse_time(item.date_modified.as_deref()),
+            title: item.name,
+            children: item
+                .children
+                .into_iter()
+                .filter_map(|child| convert_item(child, num_bookmarks))
+                .collect(),
+        }
+        .into(),
+        t => {
+            log::warn!("ignoring bookmark item with unknown type {:?}", t);
+            return None;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::tests::assert_json_tree;
+    use serde_json::json;
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("Bookmarks");
+        // 13244473600000000 is 1600000000000ms since the Unix epoch.
+        std::fs::write(
+            &path,
+            json!({
+                "checksum": "00000000000000000000000000000000",
+                "roots": {
+                    "bookmark_bar": {
+                        "children": [
+                            {
+                                "date_added": "13244473600000000",
+                                "guid": "0bc5d13f-2cba-5d74-951f-3f233fe6c908",
+                                "id": "4",
+                                "name": "Example",
+                                "type": "url",
+                                "url": "https://example.com/"
+                            },
+                            {
+                                "children": [
+                                    {
+                                        "date_added": "0",
+                                        "id": "6",
+                                        "name": "IDN",
+                                        "type": "url",
+                                        "url": "https://www.mozillä.org/"
+                                    },
+                                    {
+                                        "id": "7",
+                                        "name": "Invalid",
+                                        "type": "url",
+                                        "url": "not a url"
+                                    }
+                                ],
+                                "date_added": "13244473600000000",
+                                "date_modified": "13244473700000000",
+                                "id": "5",
+                                "name": "A folder",
+                                "type": "folder"
+                            }
+                        ],
+                        "id": "1",
+                        "name": "Bookmarks bar",
+                        "type": "folder"
+                    },
+                    "other": {
+                        "children": [
+                            {
+                                "id": "8",
+                                "name": "Other",
+                                "type": "url",
+                                "url": "https://other.example.com/"
+                            }
+                        ],
+                        "id": "2",
+                        "name": "Other bookmarks",
+                        "type": "folder"
+                    },
+                    "synced": {
+                        "children": [],
+                        "id": "3",
+                        "name": "Mobile bookmarks",
+                        "type": "folder"
+                    }
+                },
+                "version": 1
+            })
+            .to_string(),
+        )?;
+
+        let conn = new_mem_connection();
+        assert_eq!(import(&conn, &path)?, 3);
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Toolbar.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Toolbar.as_guid(),
+                "children": [
+                    {
+                        "title": "Example",
+                        "url": "https://example.com/",
+                        "date_added": 1_600_000_000_000u64,
+                    },
+                    {
+                        "title": "A folder",
+                        "date_added": 1_600_000_000_000u64,
+                        "last_modified": 1_600_000_100_000u64,
+                        "children": [
+                            {
+                                "title": "IDN",
+                                "url": "https://www.xn--mozill-gua.org/",
+                            },
+                        ],
+                    },
+                ],
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Unfiled.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "title": "Other",
+                        "url": "https://other.example.com/",
+                    },
+                ],
+            }),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::import::common::{
+    attached_database, define_history_migration_functions, select_count, ExecuteOnDrop,
+    HistoryMigrationResult,
+};
+use crate::storage::update_all_frecencies_at_once;
+use crate::PlacesDb;
+use types::Timestamp;
+use url::Url;
+
+/// Imports history from the `History` database of a Chromium-based browser
+/// (Chrome, Edge, Brave, etc).
+///
+/// Chromium keeps this database locked while it's running, so callers will
+/// typically want to import from a copy of it.
+///
+/// ### Basic process
+///
+/// - Attach the Chromium database (read-only).
+/// - Slurp the most recent visits into a temp table, mapping Chromium's page
+///   transitions onto our `VisitType`s as we go.
+/// - Slurp the URLs for those visits into a staging table, which normalizes
+///   (ie, punycodes) them.
+/// - Add any entries to moz_places that are needed, and insert the visits we
+///   don't already have.
+/// - Update frecency for the affected pages.
+/// - Cleanup (drop the temp tables, detach the database).
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<HistoryMigrationResult> {
+    let url = crate::util::ensure_url_path(path)?;
+    do_import(conn, url)
+}
+
+fn do_import(conn: &PlacesDb, mut chromium_db_file_url: Url) -> Result<HistoryMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    define_history_migration_functions(conn)?;
+    chromium_db_file_url
+        .query_pairs_mut()
+        .append_pair("mode", "ro");
+    let import_start = Instant::now();
+    log::info!("Attaching database {}", chromium_db_file_url);
+    let auto_detach = attached_database(conn, &chromium_db_file_url, "chromium")?;
+    let auto_drop = ExecuteOnDrop::new(conn, DROP_TEMP_TABLES.to_string());
+    let tx = conn.begin_transaction()?;
+    let num_total = select_count(conn, &COUNT_CHROMIUM_HISTORY_VISITS)?;
+    let num_existing = select_count(conn, &COUNT_PLACES_HISTORY_VISITS)?;
+    log::info!("The number of visits is: {:?}", num_total);
+
+    log::info!("Creating and populating staging tables");
+    tx.execute_batch(&CREATE_TEMP_VISIT_TABLE)?;
+    tx.execute_batch(&FILL_VISIT_TABLE)?;
+    tx.execute_batch(&CREATE_STAGING_TABLE)?;
+    tx.execute_batch(&FILL_STAGING)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Updating old titles that may be missing, but now are available");
+    tx.execute_batch(&UPDATE_PLACES_TITLES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Populating missing entries in moz_places");
+    tx.execute_batch(&FILL_MOZ_PLACES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Inserting the history visits");
+    tx.execute_batch(&INSERT_HISTORY_VISITS)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Marking imported pages as changed and their frecencies as stale");
+    tx.execute_batch(&BUMP_CHANGE_COUNTERS)?;
+    let now = Timestamp::now().as_millis();
+    tx.execute(&ADD_TO_STALE_FRECENCIES, &[(":now", &now)])?;
+    scope.err_if_interrupted()?;
+
+    tx.commit()?;
+    log::info!("Successfully imported history visits!");
+
+    let num_succeeded =
+        select_count(conn, &COUNT_PLACES_HISTORY_VISITS)?.saturating_sub(num_existing);
+    let num_failed = num_total.saturating_sub(num_succeeded);
+
+    // As for iOS, frecencies are updated in their own transaction so readers
+    // can see the imported history without waiting for them.
+    log::info!("Updating all frecencies");
+    update_all_frecencies_at_once(conn, &scope)?;
+    log::info!("Frecencies updated!");
+    auto_drop.execute_now()?;
+    auto_detach.execute_now()?;
+
+    Ok(HistoryMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed,
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+lazy_static::lazy_static! {
+   // Subframe visits are for content loaded inside a page rather than
+   // something the user navigated to, and we never store them.
+   static ref COUNT_CHROMIUM_HISTORY_VISITS: &'static str =
+       "SELECT COUNT(*) FROM chromium.visits v
+        JOIN chromium.urls u ON v.url = u.id
+        WHERE (v.transition & 255) <> 3"
+   ;
+
+   static ref CREATE_TEMP_VISIT_TABLE: &'static str = "
+    CREATE TEMP TABLE IF NOT EXISTS temp.chromiumVisits(
+        id INTEGER PRIMARY KEY,
+        urlID INTEGER NOT NULL,
+        date INTEGER NOT NULL,
+        type INTEGER NOT NULL
+    ) WITHOUT ROWID;
+   ";
+
+   // Chromium's transitions are a "core" type in the low byte, plus
+   // qualifier flags in the high bits. We map the core types the same way
+   // desktop's Chrome migrator does, except that a visit which was the target
+   // of a server redirect becomes a temporary redirect (Chromium doesn't
+   // record whether the redirect was permanent).
+   // Times are microseconds since 1601-01-01.
+   // Like the iOS import, we only take the most recent 10000 visits.
+   static ref FILL_VISIT_TABLE: &'static str = "
+    INSERT OR IGNORE INTO temp.chromiumVisits(id, urlID, date, type)
+        SELECT
+            v.id,
+            v.url,
+            sanitize_timestamp(v.visit_time / 1000 - 11644473600000),
+            CASE
+                WHEN v.transition & 0x80000000 THEN 6 -- SERVER_REDIRECT => RedirectTemporary
+                ELSE CASE v.transition & 255
+                    WHEN 1 THEN 2  -- TYPED => Typed
+                    WHEN 2 THEN 3  -- AUTO_BOOKMARK => Bookmark
+                    WHEN 4 THEN 8  -- MANUAL_SUBFRAME => FramedLink
+                    WHEN 5 THEN 2  -- GENERATED => Typed
+                    WHEN 8 THEN 9  -- RELOAD => Reload
+                    WHEN 9 THEN 2  -- KEYWORD => Typed
+                    WHEN 10 THEN 2 -- KEYWORD_GENERATED => Typed
+                    ELSE 1         -- Everything else is a Link
+                END
+            END
+        FROM chromium.visits v
+        WHERE (v.transition & 255) <> 3 -- AUTO_SUBFRAME
+        ORDER BY v.visit_time DESC
+        LIMIT 10000
+   ";
+
+   static ref CREATE_STAGING_TABLE: &'static str = "
+        CREATE TEMP TABLE IF NOT EXISTS temp.chromiumHistoryStaging(
+            id INTEGER PRIMARY KEY,
+            url TEXT,
+            url_hash INTEGER NOT NULL,
+            title TEXT
+        ) WITHOUT ROWID;";
+
+   static ref FILL_STAGING: &'static str = "
+    INSERT OR IGNORE INTO temp.chromiumHistoryStaging(id, url, url_hash, title)
+        SELECT
+            u.id,
+            validate_url(u.url),
+            hash(validate_url(u.url)),
+            NULLIF(sanitize_utf8(u.title), '')
+        FROM chromium.urls u
+        WHERE u.id IN (SELECT urlID FROM temp.chromiumVisits)
+        AND validate_url(u.url) IS NOT NULL
+        "
+   ;
+
+   static ref UPDATE_PLACES_TITLES: &'static str =
+   "UPDATE main.moz_places
+        SET title = IFNULL((SELECT t.title
+                            FROM temp.chromiumHistoryStaging t
+                            WHERE t.url_hash = main.moz_places.url_hash AND t.url = main.moz_places.url), title)"
+    ;
+
+   static ref FILL_MOZ_PLACES: &'static str =
+   "INSERT OR IGNORE INTO main.moz_places(guid, url, url_hash, title, frecency, sync_change_counter)
+        SELECT
+            IFNULL(
+                (SELECT p.guid FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+                generate_guid()
+            ),
+            t.url,
+            t.url_hash,
+            t.title,
+            -1,
+            1
+        FROM temp.chromiumHistoryStaging t
+   "
+   ;
+
+   // Skips visits we already have, so importing the same profile twice
+   // doesn't duplicate them.
+   static ref INSERT_HISTORY_VISITS: &'static str =
+   "INSERT INTO main.moz_historyvisits(from_visit, place_id, visit_date, visit_type, is_local)
+        SELECT
+            NULL, -- We don't attempt to rebuild redirect chains.
+            p.id,
+            v.date,
+            v.type,
+            1
+        FROM temp.chromiumVisits v
+        JOIN temp.chromiumHistoryStaging t ON v.urlID = t.id
+        JOIN main.moz_places p ON p.url_hash = t.url_hash AND p.url = t.url
+        WHERE NOT EXISTS(SELECT 1 FROM main.moz_historyvisits e
+                         WHERE e.place_id = p.id AND e.visit_date = v.date)
+    "
+   ;
+
+   // Pages we just created (with a frecency of -1) already have a change
+   // counter of 1, but existing pages need to be re-uploaded with their new
+   // visits.
+   static ref BUMP_CHANGE_COUNTERS: &'static str =
+   "UPDATE main.moz_places
+        SET sync_change_counter = sync_change_counter + 1
+        WHERE frecency <> -1
+        AND id IN (SELECT p.id FROM temp.chromiumHistoryStaging t
+                   JOIN main.moz_places p ON p.url_hash = t.url_hash AND p.url = t.url)"
+    ;
+
+   static ref COUNT_PLACES_HISTORY_VISITS: &'static str =
+       "SELECT COUNT(*) FROM main.moz_historyvisits"
+   ;
+
+   static ref ADD_TO_STALE_FRECENCIES: &'static str =
+   "INSERT OR IGNORE INTO main.moz_places_stale_frecencies(place_id, stale_at)
+    SELECT
+        p.id,
+        :now
+    FROM temp.chromiumHistoryStaging t
+    JOIN main.moz_places p ON p.url_hash = t.url_hash AND p.url = t.url"
+    ;
+
+   static ref DROP_TEMP_TABLES: &'static str =
+   "DROP TABLE IF EXISTS temp.chromiumVisits;
+    DROP TABLE IF EXISTS temp.chromiumHistoryStaging;"
+    ;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::history::get_visit_infos;
+    use crate::types::{VisitTransitionSet, VisitType};
+    use pretty_assertions::assert_eq;
+    use rusqlite::Connection;
+
+    const CREATE_CHROMIUM_HISTORY_DB: &str =
+        include_str!("../../../sql/tests/create_chromium_history_db.sql");
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("History");
+        Connection::open(&path)?.execute_batch(CREATE_CHROMIUM_HISTORY_DB)?;
+
+        let conn = new_mem_connection();
+        let result = import(&conn, &path)?;
+        // 5 visits, one of which is a subframe (which isn't counted) and one
+        // of which has an invalid URL.
+        assert_eq!(result.num_total, 4);
+        assert_eq!(result.num_succeeded, 3);
+        assert_eq!(result.num_failed, 1);
+
+        let visits = get_visit_infos(
+            &conn,
+            Timestamp::EARLIEST,
+            Timestamp::now(),
+            VisitTransitionSet::empty(),
+        )?;
+        let mut visits: Vec<_> = visits
+            .into_iter()
+            .map(|v| {
+                (
+                    v.url.to_string(),
+                    v.title,
+                    v.timestamp.as_millis(),
+                    v.visit_type,
+                )
+            })
+            .collect();
+        visits.sort_by_key(|v| v.2);
+        assert_eq!(
+            visits,
+            vec![
+                (
+                    "https://example.com/".to_string(),
+                    Some("Example".to_string()),
+                    1_600_000_000_000,
+                    VisitType::Typed
+                ),
+                (
+                    "https://example.com/".to_string(),
+                    Some("Example".to_string()),
+                    1_600_000_100_000,
+                    VisitType::Link
+                ),
+                (
+                    "https://www.xn--mozill-gua.org/".to_string(),
+                    None,
+                    1_600_000_200_000,
+                    VisitType::RedirectTemporary
+                ),
+            ]
+        );
+
+        // Frecencies were recomputed.
+        let stale: u32 = conn.query_row(
+            "SELECT COUNT(*) FROM moz_places_stale_frecencies",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(stale, 0);
+
+        // Importing again doesn't duplicate anything.
+        let result = import(&conn, &path)?;
+        assert_eq!(result.num_succeeded, 0);
+        Ok(())
+    }
+}
@@ -2,6 +2,12 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+pub mod chromium;
 pub mod common;
 pub mod ios;
+pub mod safari;
+pub use chromium::import_bookmarks as import_chromium_bookmarks;
+pub use chromium::import_history as import_chromium_history;
 pub use ios::import_history as import_ios_history;
+pub use safari::import_bookmarks as import_safari_bookmarks;
+pub use safari::import_history as import_safari_history;
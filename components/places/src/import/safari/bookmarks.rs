@@ -0,0 +1,217 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::plist::{self, Value};
+use crate::error::Result;
+use crate::storage::bookmarks::json_tree::{
+    insert_tree, BookmarkNode, BookmarkTreeNode, FolderNode,
+};
+use crate::storage::bookmarks::BookmarkRootGuid;
+use crate::storage::URL_LENGTH_MAX;
+use crate::PlacesDb;
+use url::Url;
+
+const READING_LIST_TITLE: &str = "com.apple.ReadingList";
+
+/// Imports bookmarks from Safari's `Bookmarks.plist`.
+///
+/// The favorites bar is imported into the toolbar and the bookmarks menu
+/// into the menu. The reading list becomes a "Reading List" folder in
+/// unfiled, as does everything else at the top level (ie, the items in
+/// Safari's sidebar). Safari doesn't keep dates for bookmarks, and doesn't
+/// have separators, keywords or tags. Existing bookmarks are not touched, so
+/// importing the same file twice will create duplicates.
+///
+/// Returns the number of bookmarks (not folders) imported.
+pub fn import(conn: &PlacesDb, path: impl AsRef<std::path::Path>) -> Result<u32> {
+    let root = plist::parse(&std::fs::read(path)?)?;
+    let mut num_imported = 0;
+    let mut menu = Vec::new();
+    let mut toolbar = Vec::new();
+    let mut unfiled = Vec::new();
+    for item in children(&root) {
+        match (item_type(item), item.get("Title").and_then(Value::as_str)) {
+            (Some("WebBookmarkTypeList"), Some("BookmarksBar")) => {
+                toolbar.extend(convert_children(item, &mut num_imported))
+            }
+            (Some("WebBookmarkTypeList"), Some("BookmarksMenu")) => {
+                menu.extend(convert_children(item, &mut num_imported))
+            }
+            (Some("WebBookmarkTypeList"), Some(READING_LIST_TITLE)) => unfiled.push(
+                FolderNode {
+                    title: Some("Reading List".to_string()),
+                    children: convert_children(item, &mut num_imported),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            _ => unfiled.extend(convert_item(item, &mut num_imported)),
+        }
+    }
+    for (root, children) in [
+        (BookmarkRootGuid::Menu, menu),
+        (BookmarkRootGuid::Toolbar, toolbar),
+        (BookmarkRootGuid::Unfiled, unfiled),
+    ] {
+        if children.is_empty() {
+            continue;
+        }
+        insert_tree(
+            conn,
+            FolderNode {
+                guid: Some(root.as_guid()),
+                children,
+                ..Default::default()
+            },
+        )?;
+    }
+    Ok(num_imported)
+}
+
+fn item_type(item: &Value) -> Option<&str> {
+    item.get("WebBookmarkType").and_then(Value::as_str)
+}
+
+fn children(item: &Value) -> &[Value] {
+    item.get("Children")
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+}
+
+fn convert_children(item: &Value, num_bookmarks: &mut u32) -> Vec<BookmarkTreeNode> {
+    children(item)
+        .iter()
+        .filter_map(|child| convert_item(child, num_bookmarks))
+        .collect()
+}
+
+fn convert_item(item: &Value, num_bookmarks: &mut u32) -> Option<BookmarkTreeNode> {
+    Some(match item_type(item) {
+        Some("WebBookmarkTypeLeaf") => {
+            let url = match item
+                .get("URLString")
+                .and_then(Value::as_str)
+                .map(Url::parse)
+            {
+                Some(Ok(url)) if url.as_str().len() <= URL_LENGTH_MAX => url,
+                _ => {
+                    log::warn!("ignoring bookmark with a missing or invalid URL");
+                    return None;
+                }
+            };
+            *num_bookmarks += 1;
+            BookmarkNode {
+                guid: None,
+                date_added: None,
+                last_modified: None,
+                title: item
+                    .get("URIDictionary")
+                    .and_then(|d| d.get("title"))
+                    .and_then(Value::as_str)
+                    .map(ToString::to_string),
+                url,
+            }
+            .into()
+        }
+        Some("WebBookmarkTypeList") => FolderNode {
+            title: item
+                .get("Title")
+                .and_then(Value::as_str)
+                .map(ToString::to_string),
+            children: convert_children(item, num_bookmarks),
+            ..Default::default()
+        }
+        .into(),
+        // Proxies are for things like History, which aren't bookmarks.
+        Some("WebBookmarkTypeProxy") => return None,
+        t => {
+            log::warn!("ignoring bookmark item with unknown type {:?}", t);
+            return None;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::tests::assert_json_tree;
+    use serde_json::json;
+
+    const BOOKMARKS_PLIST: &[u8] = include_bytes!("fixtures/Bookmarks.plist");
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("Bookmarks.plist");
+        std::fs::write(&path, BOOKMARKS_PLIST)?;
+
+        let conn = new_mem_connection();
+        assert_eq!(import(&conn, &path)?, 5);
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Toolbar.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Toolbar.as_guid(),
+                "children": [
+                    {
+                        "title": "Example",
+                        "url": "https://example.com/",
+                    },
+                    {
+                        "title": "A folder",
+                        "children": [
+                            {
+                                "title": "IDN",
+                                "url": "https://www.xn--mozill-gua.org/",
+                            },
+                        ],
+                    },
+                ],
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Menu.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Menu.as_guid(),
+                "children": [
+                    {
+                        "title": "On the menu",
+                        "url": "https://menu.example.com/",
+                    },
+                ],
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Unfiled.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "title": "Reading List",
+                        "children": [
+                            {
+                                "title": "Read me",
+                                "url": "https://read.example.com/",
+                            },
+                        ],
+                    },
+                    {
+                        "title": "Top level folder",
+                        "children": [
+                            {
+                                "title": "In a folder",
+                                "url": "https://folder.example.com/",
+                            },
+                        ],
+                    },
+                ],
+            }),
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::time::Instant;
+
+use crate::error::Result;
+use crate::import::common::{
+    attached_database, define_history_migration_functions, select_count, ExecuteOnDrop,
+    HistoryMigrationResult,
+};
+use crate::storage::update_all_frecencies_at_once;
+use crate::PlacesDb;
+use types::Timestamp;
+use url::Url;
+
+/// Imports history from Safari's `History.db`.
+///
+/// ### Basic process
+///
+/// - Attach the Safari database (read-only).
+/// - Slurp the most recent visits into a temp table, converting Safari's
+///   redirect information into our `VisitType`s as we go.
+/// - Slurp the URLs and titles for those visits into a staging table, which
+///   normalizes (ie, punycodes) them.
+/// - Add any entries to moz_places that are needed, and insert the visits we
+///   don't already have.
+/// - Update frecency for the affected pages.
+/// - Cleanup (drop the temp tables, detach the database).
+pub fn import(
+    conn: &PlacesDb,
+    path: impl AsRef<std::path::Path>,
+) -> Result<HistoryMigrationResult> {
+    let url = crate::util::ensure_url_path(path)?;
+    do_import(conn, url)
+}
+
+fn do_import(conn: &PlacesDb, mut safari_db_file_url: Url) -> Result<HistoryMigrationResult> {
+    let scope = conn.begin_interrupt_scope()?;
+    define_history_migration_functions(conn)?;
+    safari_db_file_url
+        .query_pairs_mut()
+        .append_pair("mode", "ro");
+    let import_start = Instant::now();
+    log::info!("Attaching database {}", safari_db_file_url);
+    let auto_detach = attached_database(conn, &safari_db_file_url, "safari")?;
+    let auto_drop = ExecuteOnDrop::new(conn, DROP_TEMP_TABLES.to_string());
+    let tx = conn.begin_transaction()?;
+    let num_total = select_count(conn, &COUNT_SAFARI_HISTORY_VISITS)?;
+    let num_existing = select_count(conn, &COUNT_PLACES_HISTORY_VISITS)?;
+    log::info!("The number of visits is: {:?}", num_total);
+
+    log::info!("Creating and populating staging tables");
+    tx.execute_batch(&CREATE_TEMP_VISIT_TABLE)?;
+    tx.execute_batch(&FILL_VISIT_TABLE)?;
+    tx.execute_batch(&CREATE_STAGING_TABLE)?;
+    tx.execute_batch(&FILL_STAGING)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Updating old titles that may be missing, but now are available");
+    tx.execute_batch(&UPDATE_PLACES_TITLES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Populating missing entries in moz_places");
+    tx.execute_batch(&FILL_MOZ_PLACES)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Inserting the history visits");
+    tx.execute_batch(&INSERT_HISTORY_VISITS)?;
+    scope.err_if_interrupted()?;
+
+    log::info!("Marking imported pages as changed and their frecencies as stale");
+    tx.execute_batch(&BUMP_CHANGE_COUNTERS)?;
+    let now = Timestamp::now().as_millis();
+    tx.execute(&ADD_TO_STALE_FRECENCIES, &[(":now", &now)])?;
+    scope.err_if_interrupted()?;
+
+    tx.commit()?;
+    log::info!("Successfully imported history visits!");
+
+    let num_succeeded =
+        select_count(conn, &COUNT_PLACES_HISTORY_VISITS)?.saturating_sub(num_existing);
+    let num_failed = num_total.saturating_sub(num_succeeded);
+
+    // As for iOS, frecencies are updated in their own transaction so readers
+    // can see the imported history without waiting for them.
+    log::info!("Updating all frecencies");
+    update_all_frecencies_at_once(conn, &scope)?;
+    log::info!("Frecencies updated!");
+    auto_drop.execute_now()?;
+    auto_detach.execute_now()?;
+
+    Ok(HistoryMigrationResult {
+        num_total,
+        num_succeeded,
+        num_failed,
+        total_duration: import_start.elapsed().as_millis() as u64,
+    })
+}
+
+lazy_static::lazy_static! {
+   // Visits which failed to load, and those Safari synthesized itself,
+   // aren't things the user actually visited.
+   static ref COUNT_SAFARI_HISTORY_VISITS: &'static str =
+       "SELECT COUNT(*) FROM safari.history_visits v
+        JOIN safari.history_items i ON v.history_item = i.id
+        WHERE v.load_successful AND NOT v.synthesized"
+   ;
+
+   static ref CREATE_TEMP_VISIT_TABLE: &'static str = "
+    CREATE TEMP TABLE IF NOT EXISTS temp.safariVisits(
+        id INTEGER PRIMARY KEY,
+        itemID INTEGER NOT NULL,
+        date INTEGER NOT NULL,
+        type INTEGER NOT NULL,
+        is_local TINYINT NOT NULL
+    ) WITHOUT ROWID;
+   ";
+
+   // Safari doesn't record how the user got to a page, only whether the
+   // visit was the target of a redirect, so every other visit is a Link.
+   // Visits with a non-zero origin were synced from another device.
+   // Times are (fractional) seconds since 2001-01-01.
+   // Like the iOS import, we only take the most recent 10000 visits.
+   static ref FILL_VISIT_TABLE: &'static str = "
+    INSERT OR IGNORE INTO temp.safariVisits(id, itemID, date, type, is_local)
+        SELECT
+            v.id,
+            v.history_item,
+            sanitize_float_timestamp((v.visit_time + 978307200) * 1000),
+            CASE WHEN v.redirect_source IS NOT NULL THEN 6 ELSE 1 END,
+            v.origin = 0
+        FROM safari.history_visits v
+        WHERE v.load_successful AND NOT v.synthesized
+        ORDER BY v.visit_time DESC
+        LIMIT 10000
+   ";
+
+   static ref CREATE_STAGING_TABLE: &'static str = "
+        CREATE TEMP TABLE IF NOT EXISTS temp.safariHistoryStaging(
+            id INTEGER PRIMARY KEY,
+            url TEXT,
+            url_hash INTEGER NOT NULL,
+            title TEXT
+        ) WITHOUT ROWID;";
+
+   // Safari records titles per visit, so we take the most recent one.
+   static ref FILL_STAGING: &'static str = "
+    INSERT OR IGNORE INTO temp.safariHistoryStaging(id, url, url_hash, title)
+        SELECT
+            i.id,
+            validate_url(i.url),
+            hash(validate_url(i.url)),
+            (SELECT NULLIF(sanitize_utf8(v.title), '')
+             FROM safari.history_visits v
+             WHERE v.history_item = i.id AND v.title IS NOT NULL
+             AND v.load_successful AND NOT v.synthesized
+             ORDER BY v.visit_time DESC
+             LIMIT 1)
+        FROM safari.history_items i
+        WHERE i.id IN (SELECT itemID FROM temp.safariVisits)
+        AND validate_url(i.url) IS NOT NULL
+        "
+   ;
+
+   static ref UPDATE_PLACES_TITLES: &'static str =
+   "UPDATE main.moz_places
+        SET title = IFNULL((SELECT t.title
+                            FROM temp.safariHistoryStaging t
+                            WHERE t.url_hash = main.moz_places.url_hash AND t.url = main.moz_places.url), title)"
+    ;
+
+   static ref FILL_MOZ_PLACES: &'static str =
+   "INSERT OR IGNORE INTO main.moz_places(guid, url, url_hash, title, frecency, sync_change_counter)
+        SELECT
+            IFNULL(
+                (SELECT p.guid FROM main.moz_places p WHERE p.url_hash = t.url_hash AND p.url = t.url),
+                generate_guid()
+            ),
+            t.url,
+            t.url_hash,
+            t.title,
+            -1,
+            1
+        FROM temp.safariHistoryStaging t
+   "
+   ;
+
+   // Skips visits we already have, so importing the same profile twice
+   // doesn't duplicate them.
+   static ref INSERT_HISTORY_VISITS: &'static str =
+   "INSERT INTO main.moz_historyvisits(from_visit, place_id, visit_date, visit_type, is_local)
+        SELECT
+            NULL, -- We don't attempt to rebuild redirect chains.
+            p.id,
+            v.date,
+            v.type,
+            v.is_local
+        FROM temp.safariVisits v
+        JOIN temp.safariHistoryStaging t ON v.itemID = t.id
+        JOIN main.moz_places p ON p.url_hash = t.url_hash AND p.url = t.url
+        WHERE NOT EXISTS(SELECT 1 FROM main.moz_historyvisits e
+                         WHERE e.place_id = p.id AND e.visit_date = v.date)
+    "
+   ;
+
+   // Pages we just created (with a frecency of -1) already have a change
+   // counter of 1, but existing pages need to be re-uploaded with their new
+   // visits.
+   static ref BUMP_CHANGE_COUNTERS: &'static str =
+   "UPDATE main.moz_places
+        SET sync_change_counter = sync_change_counter + 1
+        WHERE frecency <> -1
+        AND id IN (SELECT p.id FROM temp.safariHistoryStaging t
+                   JOIN main.moz_places p ON p.url_hash = t.url_hash AND p.url = t.url)"
+    ;
+
+   static ref COUNT_PLACES_HISTORY_VISITS: &'static str =
+       "SELECT COUNT(*) FROM main.moz_historyvisits"
+   ;
+
+   static ref ADD_TO_STALE_FRECENCIES: &'static str =
+   "INSERT OR IGNORE INTO main.moz_places_stale_frecencies(place_id, stale_at)
+    SELECT
+        p.id,
+        :now
+    FROM temp.safariHistoryStaging t
+    JOIN main.moz_places p ON p.url_hash = t.url_hash AND p.url = t.url"
+    ;
+
+   static ref DROP_TEMP_TABLES: &'static str =
+   "DROP TABLE IF EXISTS temp.safariVisits;
+    DROP TABLE IF EXISTS temp.safariHistoryStaging;"
+    ;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::history::get_visit_infos;
+    use crate::types::{VisitTransitionSet, VisitType};
+    use pretty_assertions::assert_eq;
+    use rusqlite::Connection;
+
+    const CREATE_SAFARI_HISTORY_DB: &str =
+        include_str!("../../../sql/tests/create_safari_history_db.sql");
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("History.db");
+        Connection::open(&path)?.execute_batch(CREATE_SAFARI_HISTORY_DB)?;
+
+        let conn = new_mem_connection();
+        let result = import(&conn, &path)?;
+        // 6 visits, one of which failed to load and one of which was
+        // synthesized (neither of which are counted), and one of which has
+        // an invalid URL.
+        assert_eq!(result.num_total, 4);
+        assert_eq!(result.num_succeeded, 3);
+        assert_eq!(result.num_failed, 1);
+
+        let visits = get_visit_infos(
+            &conn,
+            Timestamp::EARLIEST,
+            Timestamp::now(),
+            VisitTransitionSet::empty(),
+        )?;
+        let mut visits: Vec<_> = visits
+            .into_iter()
+            .map(|v| {
+                (
+                    v.url.to_string(),
+                    v.title,
+                    v.timestamp.as_millis(),
+                    v.visit_type,
+                    v.is_remote,
+                )
+            })
+            .collect();
+        visits.sort_by_key(|v| v.2);
+        assert_eq!(
+            visits,
+            vec![
+                (
+                    "http://example.com/".to_string(),
+                    Some("Example".to_string()),
+                    1_600_000_000_000,
+                    VisitType::Link,
+                    false
+                ),
+                (
+                    "http://example.com/".to_string(),
+                    Some("Example".to_string()),
+                    1_600_000_100_500,
+                    VisitType::Link,
+                    true
+                ),
+                (
+                    "https://example.com/".to_string(),
+                    None,
+                    1_600_000_100_600,
+                    VisitType::RedirectTemporary,
+                    true
+                ),
+            ]
+        );
+
+        // Importing again doesn't duplicate anything.
+        let result = import(&conn, &path)?;
+        assert_eq!(result.num_succeeded, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// A minimal reader for Apple's binary property list format ("bplist00"),
+// which is what Safari uses for `Bookmarks.plist`. It supports everything
+// that format can contain, but only what we need to read it - there's no
+// support for XML plists or for writing.
+//
+// See CFBinaryPList.c in Apple's CoreFoundation sources for the format.
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+
+const MAGIC: &[u8] = b"bplist00";
+const TRAILER_SIZE: usize = 32;
+// Objects can reference each other, so a malicious (or corrupt) file could
+// send us into infinite recursion without a limit.
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Integer(i128),
+    Real(f64),
+    // Seconds since 2001-01-01.
+    Date(f64),
+    Data(Vec<u8>),
+    String(String),
+    Uid(u64),
+    Array(Vec<Value>),
+    Dictionary(HashMap<String, Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dictionary(d) => d.get(key),
+            _ => None,
+        }
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidPropertyList(reason.into())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset_size: usize,
+    ref_size: usize,
+    offsets_start: usize,
+    num_objects: u64,
+}
+
+/// Parses a binary property list, returning its top object.
+pub fn parse(bytes: &[u8]) -> Result<Value> {
+    if bytes.len() < MAGIC.len() + TRAILER_SIZE || !bytes.starts_with(MAGIC) {
+        return Err(invalid("not a binary property list"));
+    }
+    let trailer = &bytes[bytes.len() - TRAILER_SIZE..];
+    let offset_size = trailer[6] as usize;
+    let ref_size = trailer[7] as usize;
+    let num_objects = be_uint(&trailer[8..16]);
+    let top_object = be_uint(&trailer[16..24]);
+    let offsets_start = be_uint(&trailer[24..32]);
+    if !(1..=8).contains(&offset_size) || !(1..=8).contains(&ref_size) {
+        return Err(invalid("invalid trailer"));
+    }
+    let offsets_len = num_objects
+        .checked_mul(offset_size as u64)
+        .and_then(|len| len.checked_add(offsets_start));
+    match offsets_len {
+        Some(end) if end <= (bytes.len() - TRAILER_SIZE) as u64 => (),
+        _ => return Err(invalid("invalid offset table")),
+    }
+    let reader = Reader {
+        bytes,
+        offset_size,
+        ref_size,
+        offsets_start: offsets_start as usize,
+        num_objects,
+    };
+    reader.object(top_object, 0)
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |n, b| (n << 8) | u64::from(*b))
+}
+
+impl<'a> Reader<'a> {
+    fn slice(&self, start: usize, len: usize) -> Result<&'a [u8]> {
+        start
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(start..end))
+            .ok_or_else(|| invalid("unexpected end of data"))
+    }
+
+    fn object(&self, index: u64, depth: usize) -> Result<Value> {
+        if index >= self.num_objects {
+            return Err(invalid("invalid object reference"));
+        }
+        if depth > MAX_DEPTH {
+            return Err(invalid("too deeply nested"));
+        }
+        let offset = be_uint(self.slice(
+            self.offsets_start + index as usize * self.offset_size,
+            self.offset_size,
+        )?) as usize;
+        let marker = *self
+            .bytes
+            .get(offset)
+            .ok_or_else(|| invalid("invalid object offset"))?;
+        let info = marker & 0x0F;
+        let start = offset + 1;
+        Ok(match marker >> 4 {
+            0x0 => match info {
+                0x0 => Value::Null,
+                0x8 => Value::Boolean(false),
+                0x9 => Value::Boolean(true),
+                _ => return Err(invalid(format!("unknown marker {:#x}", marker))),
+            },
+            0x1 => Value::Integer(self.integer(start, info)?.0),
+            0x2 => Value::Real(self.real(start, info)?),
+            0x3 if info == 3 => Value::Date(self.real(start, 3)?),
+            0x4 => {
+                let (len, start) = self.length(start, info)?;
+                Value::Data(self.slice(start, len)?.to_vec())
+            }
+            0x5 => {
+                let (len, start) = self.length(start, info)?;
+                // "ASCII" strings are really Latin-1 in practice.
+                Value::String(self.slice(start, len)?.iter().map(|b| *b as char).collect())
+            }
+            0x6 => {
+                let (len, start) = self.length(start, info)?;
+                let bytes = self.slice(start, len.saturating_mul(2))?;
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                Value::String(String::from_utf16_lossy(&units))
+            }
+            0x8 => Value::Uid(be_uint(self.slice(start, info as usize + 1)?)),
+            // Arrays and sets.
+            0xA | 0xC => {
+                let (len, start) = self.length(start, info)?;
+                Value::Array(
+                    (0..len)
+                        .map(|i| self.object(self.object_ref(start, i)?, depth + 1))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            0xD => {
+                let (len, start) = self.length(start, info)?;
+                let mut dict = HashMap::with_capacity(len.min(1024));
+                for i in 0..len {
+                    let key = match self.object(self.object_ref(start, i)?, depth + 1)? {
+                        Value::String(s) => s,
+                        _ => return Err(invalid("dictionary key isn't a string")),
+                    };
+                    let value =
+                        self.object(self.object_ref(start, len.saturating_add(i))?, depth + 1)?;
+                    dict.insert(key, value);
+                }
+                Value::Dictionary(dict)
+            }
+            _ => return Err(invalid(format!("unknown marker {:#x}", marker))),
+        })
+    }
+
+    // Returns the integer and the offset just past it.
+    fn integer(&self, start: usize, info: u8) -> Result<(i128, usize)> {
+        let len = 1usize
+            .checked_shl(info.into())
+            .filter(|len| *len <= 16)
+            .ok_or_else(|| invalid("invalid integer size"))?;
+        let bytes = self.slice(start, len)?;
+        let value = match len {
+            // 8 byte integers are signed, smaller ones are unsigned.
+            8 => i128::from(i64::from_be_bytes(bytes.try_into().unwrap())),
+            16 => i128::from_be_bytes(bytes.try_into().unwrap()),
+            _ => i128::from(be_uint(bytes)),
+        };
+        Ok((value, start + len))
+    }
+
+    fn real(&self, start: usize, info: u8) -> Result<f64> {
+        Ok(match info {
+            2 => f64::from(f32::from_be_bytes(
+                self.slice(start, 4)?.try_into().unwrap(),
+            )),
+            3 => f64::from_be_bytes(self.slice(start, 8)?.try_into().unwrap()),
+            _ => return Err(invalid("invalid real size")),
+        })
+    }
+
+    // Returns the length of a variable-sized object, and where its content
+    // starts. Lengths of 15 or more are stored as a following integer object.
+    fn length(&self, start: usize, info: u8) -> Result<(usize, usize)> {
+        if info != 0x0F {
+            return Ok((info as usize, start));
+        }
+        let marker = *self
+            .bytes
+            .get(start)
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        if marker >> 4 != 0x1 {
+            return Err(invalid("invalid length"));
+        }
+        let (len, start) = self.integer(start + 1, marker & 0x0F)?;
+        let len = usize::try_from(len).map_err(|_| invalid("invalid length"))?;
+        Ok((len, start))
+    }
+
+    fn object_ref(&self, start: usize, index: usize) -> Result<u64> {
+        let offset = index
+            .checked_mul(self.ref_size)
+            .and_then(|o| o.checked_add(start))
+            .ok_or_else(|| invalid("invalid object reference"))?;
+        Ok(be_uint(self.slice(offset, self.ref_size)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        // Generated with Python's plistlib:
+        // plistlib.dumps({"Title": "Tëst", "Children": [1, 2.5, True, b"\x00"],
+        //                 "Long": "x" * 20}, fmt=plistlib.FMT_BINARY)
+        let bytes = [
+            0x62, 0x70, 0x6c, 0x69, 0x73, 0x74, 0x30, 0x30, 0xd3, 0x01, 0x02, 0x03, 0x04, 0x09,
+            0x0a, 0x58, 0x43, 0x68, 0x69, 0x6c, 0x64, 0x72, 0x65, 0x6e, 0x54, 0x4c, 0x6f, 0x6e,
+            0x67, 0x55, 0x54, 0x69, 0x74, 0x6c, 0x65, 0xa4, 0x05, 0x06, 0x07, 0x08, 0x10, 0x01,
+            0x23, 0x40, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, 0x41, 0x00, 0x5f, 0x10,
+            0x14, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78,
+            0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x78, 0x64, 0x00, 0x54, 0x00, 0xeb, 0x00, 0x73,
+            0x00, 0x74, 0x08, 0x0f, 0x18, 0x1d, 0x23, 0x28, 0x2a, 0x33, 0x34, 0x36, 0x4d, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x56,
+        ];
+        let value = parse(&bytes).expect("should parse");
+        assert_eq!(value.get("Title").and_then(Value::as_str), Some("Tëst"));
+        assert_eq!(
+            value.get("Children").and_then(Value::as_array),
+            Some(
+                &[
+                    Value::Integer(1),
+                    Value::Real(2.5),
+                    Value::Boolean(true),
+                    Value::Data(vec![0])
+                ][..]
+            )
+        );
+        assert_eq!(
+            value.get("Long").and_then(Value::as_str),
+            Some("xxxxxxxxxxxxxxxxxxxx")
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse(b"").is_err());
+        assert!(parse(b"<?xml version=\"1.0\"?><plist></plist>").is_err());
+        // A valid header and trailer, but an object which references itself.
+        let mut bytes = b"bplist00".to_vec();
+        bytes.extend([0xa1, 0x00, 0x08]);
+        bytes.extend([0, 0, 0, 0, 0, 0, 1, 1]);
+        bytes.extend(1u64.to_be_bytes());
+        bytes.extend(0u64.to_be_bytes());
+        bytes.extend(10u64.to_be_bytes());
+        assert!(parse(&bytes).is_err());
+    }
+}
@@ -19,7 +19,9 @@ pub mod history_sync;
 pub mod import;
 pub mod match_impl;
 pub mod observation;
+pub mod observer;
 pub mod storage;
+pub mod sync_validation;
 #[cfg(test)]
 mod tests;
 mod util;
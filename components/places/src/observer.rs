@@ -0,0 +1,610 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Change notifications for history and bookmarks.
+//
+// Changes are recorded by the `moz_places_changes_*` triggers in
+// `create_shared_triggers.sql` into a temp table, so we see them no matter
+// which write path made them. When a `PlacesTransaction` commits, the recorded
+// changes are read back and delivered to every observer registered on the
+// `PlacesApi` which owns the connection.
+
+use crate::db::PlacesDb;
+use crate::error::Result;
+use crate::types::VisitType;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+use url::Url;
+
+/// A change to history or bookmarks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlacesChange {
+    VisitAdded {
+        url: Url,
+        guid: SyncGuid,
+        visit_type: VisitType,
+        visit_date: Timestamp,
+        is_local: bool,
+    },
+    /// A page was removed from history. If the page is still bookmarked, it's
+    /// kept in the store, but all its visits were removed.
+    PageRemoved {
+        url: Url,
+        guid: SyncGuid,
+        is_removed_from_store: bool,
+    },
+    TitleChanged {
+        url: Url,
+        guid: SyncGuid,
+        title: Option<String>,
+    },
+    FrecencyChanged {
+        url: Url,
+        guid: SyncGuid,
+        frecency: i64,
+    },
+    BookmarkAdded {
+        guid: SyncGuid,
+        parent_guid: SyncGuid,
+        position: u32,
+        url: Option<Url>,
+        title: Option<String>,
+    },
+    BookmarkMoved {
+        guid: SyncGuid,
+        old_parent_guid: SyncGuid,
+        old_position: u32,
+        new_parent_guid: SyncGuid,
+        new_position: u32,
+    },
+    /// The title or URL of a bookmark item changed.
+    BookmarkChanged {
+        guid: SyncGuid,
+        url: Option<Url>,
+        title: Option<String>,
+    },
+    BookmarkRemoved {
+        guid: SyncGuid,
+        parent_guid: SyncGuid,
+        position: u32,
+        url: Option<Url>,
+    },
+}
+
+// The values of `moz_places_changes_temp.kind` written by the triggers.
+const VISIT_ADDED: u8 = 1;
+const PAGE_REMOVED: u8 = 2;
+const TITLE_CHANGED: u8 = 3;
+const FRECENCY_CHANGED: u8 = 4;
+const BOOKMARK_ADDED: u8 = 5;
+const BOOKMARK_MOVED: u8 = 6;
+const BOOKMARK_CHANGED: u8 = 7;
+const BOOKMARK_REMOVED: u8 = 8;
+
+impl PlacesChange {
+    // Returns `None` for rows we can't make sense of, which shouldn't happen,
+    // but shouldn't stop the other changes from being delivered either.
+    fn from_row(row: &Row<'_>) -> Result<Option<Self>> {
+        let kind: u8 = row.get("kind")?;
+        let guid: SyncGuid = row.get("guid")?;
+        let url = match row.get::<_, Option<String>>("url")? {
+            Some(url) => Some(Url::parse(&url)?),
+            None => None,
+        };
+        let parent_guid: Option<SyncGuid> = row.get("parent_guid")?;
+        let change = match (kind, url, parent_guid) {
+            (VISIT_ADDED, Some(url), _) => {
+                match VisitType::from_primitive(row.get("visit_type")?) {
+                    Some(visit_type) => Some(PlacesChange::VisitAdded {
+                        url,
+                        guid,
+                        visit_type,
+                        visit_date: row.get("visit_date")?,
+                        is_local: row.get("is_local")?,
+                    }),
+                    None => {
+                        log::warn!("Ignoring visit with an unknown type to {}", guid);
+                        None
+                    }
+                }
+            }
+            (PAGE_REMOVED, Some(url), _) => Some(PlacesChange::PageRemoved {
+                url,
+                guid,
+                is_removed_from_store: row.get("is_removed_from_store")?,
+            }),
+            (TITLE_CHANGED, Some(url), _) => Some(PlacesChange::TitleChanged {
+                url,
+                guid,
+                title: row.get("title")?,
+            }),
+            (FRECENCY_CHANGED, Some(url), _) => Some(PlacesChange::FrecencyChanged {
+                url,
+                guid,
+                frecency: row.get("frecency")?,
+            }),
+            (BOOKMARK_ADDED, url, Some(parent_guid)) => Some(PlacesChange::BookmarkAdded {
+                guid,
+                parent_guid,
+                position: row.get("position")?,
+                url,
+                title: row.get("title")?,
+            }),
+            (BOOKMARK_MOVED, _, Some(new_parent_guid)) => {
+                match row.get::<_, Option<SyncGuid>>("old_parent_guid")? {
+                    Some(old_parent_guid) => Some(PlacesChange::BookmarkMoved {
+                        guid,
+                        old_parent_guid,
+                        old_position: row.get("old_position")?,
+                        new_parent_guid,
+                        new_position: row.get("position")?,
+                    }),
+                    None => {
+                        log::warn!("Ignoring move without an old parent for {}", guid);
+                        None
+                    }
+                }
+            }
+            (BOOKMARK_CHANGED, url, _) => Some(PlacesChange::BookmarkChanged {
+                guid,
+                url,
+                title: row.get("title")?,
+            }),
+            (BOOKMARK_REMOVED, url, Some(parent_guid)) => Some(PlacesChange::BookmarkRemoved {
+                guid,
+                parent_guid,
+                position: row.get("position")?,
+                url,
+            }),
+            _ => {
+                log::warn!("Ignoring unexpected change of kind {} for {}", kind, guid);
+                None
+            }
+        };
+        Ok(change)
+    }
+}
+
+/// Implemented by consumers who want to know about changes to history and
+/// bookmarks.
+///
+/// Observers are called on the thread which committed the changes, while it
+/// still holds the connection, so they shouldn't call back into places
+/// directly.
+pub trait PlacesObserver: Send + Sync {
+    /// Called with the changes from a transaction, in the order they were
+    /// made, once it's been committed. Only the latest title, frecency and
+    /// removal of each page are included.
+    fn on_changes(&self, changes: Vec<PlacesChange>);
+}
+
+lazy_static! {
+    // Like the bookmark change counters, observers are registered on a
+    // `PlacesApi`, but notified from its connections, so they're indexed by the
+    // "api id" of the API.
+    static ref OBSERVERS: RwLock<HashMap<usize, Vec<(u64, Arc<dyn PlacesObserver>)>>> =
+        RwLock::new(HashMap::new());
+}
+
+static OBSERVER_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn register_observer(api_id: usize, observer: Arc<dyn PlacesObserver>) -> u64 {
+    let observer_id = OBSERVER_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+    OBSERVERS
+        .write()
+        .entry(api_id)
+        .or_default()
+        .push((observer_id, observer));
+    observer_id
+}
+
+pub(crate) fn unregister_observer(api_id: usize, observer_id: u64) {
+    let mut map = OBSERVERS.write();
+    if let Some(observers) = map.get_mut(&api_id) {
+        observers.retain(|(id, _)| *id != observer_id);
+        if observers.is_empty() {
+            map.remove(&api_id);
+        }
+    }
+}
+
+pub(crate) fn unregister_all_observers(api_id: usize) {
+    OBSERVERS.write().remove(&api_id);
+}
+
+pub(crate) fn has_observers(api_id: usize) -> bool {
+    OBSERVERS.read().contains_key(&api_id)
+}
+
+/// Delivers the changes recorded by the triggers. Must only be called after a
+/// commit, and never on a read-only connection, which doesn't have the temp
+/// table.
+pub(crate) fn notify_observers(db: &PlacesDb) -> Result<()> {
+    let changes = take_changes(db)?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    // Don't hold the lock while calling out, in case an observer wants to
+    // register or unregister observers.
+    let observers: Vec<Arc<dyn PlacesObserver>> = match OBSERVERS.read().get(&db.api_id()) {
+        Some(observers) => observers.iter().map(|(_, o)| o.clone()).collect(),
+        None => return Ok(()),
+    };
+    for observer in observers {
+        observer.on_changes(changes.clone());
+    }
+    Ok(())
+}
+
+fn take_changes(db: &PlacesDb) -> Result<Vec<PlacesChange>> {
+    // Removed items whose parent was removed along with them find their
+    // parent's guid in the parent's own removal, which is recorded first.
+    let changes = db.query_rows_and_then_cached(
+        "SELECT c.kind, c.guid, c.url, c.title,
+                IFNULL(c.parent_guid,
+                       (SELECT r.guid FROM moz_places_changes_temp r
+                        WHERE r.kind = :bookmark_removed AND
+                              r.item_id = c.parent_id AND
+                              r.id < c.id
+                        ORDER BY r.id DESC
+                        LIMIT 1)) AS parent_guid,
+                c.old_parent_guid, c.position, c.old_position, c.visit_type,
+                c.visit_date, c.is_local, c.frecency, c.is_removed_from_store
+         FROM moz_places_changes_temp c
+         WHERE c.kind NOT IN (:page_removed, :title_changed, :frecency_changed) OR
+               NOT EXISTS(SELECT 1 FROM moz_places_changes_temp l
+                          WHERE l.kind = c.kind AND l.guid = c.guid AND l.id > c.id)
+         ORDER BY c.id",
+        rusqlite::named_params! {
+            ":bookmark_removed": BOOKMARK_REMOVED,
+            ":page_removed": PAGE_REMOVED,
+            ":title_changed": TITLE_CHANGED,
+            ":frecency_changed": FRECENCY_CHANGED,
+        },
+        PlacesChange::from_row,
+    )?;
+    db.execute_cached("DELETE FROM moz_places_changes_temp", [])?;
+    Ok(changes.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::api::places_api::{ConnectionType, PlacesApi};
+    use parking_lot::Mutex;
+
+    /// An observer which records each batch of changes, and checks that the
+    /// changes can already be seen from another connection when they're
+    /// delivered, which means they were committed.
+    #[derive(Clone)]
+    pub(crate) struct CommittedObserver {
+        reader: Arc<Mutex<PlacesDb>>,
+        batches: Arc<Mutex<Vec<Vec<PlacesChange>>>>,
+        uncommitted: Arc<Mutex<Vec<PlacesChange>>>,
+    }
+
+    impl CommittedObserver {
+        pub(crate) fn register(api: &PlacesApi) -> Result<Self> {
+            let observer = Self {
+                reader: Arc::new(Mutex::new(api.open_connection(ConnectionType::ReadOnly)?)),
+                batches: Default::default(),
+                uncommitted: Default::default(),
+            };
+            api.register_observer(Box::new(observer.clone()));
+            Ok(observer)
+        }
+
+        /// Returns the batches delivered since the last call, after checking
+        /// that all their changes were committed.
+        pub(crate) fn take(&self) -> Vec<Vec<PlacesChange>> {
+            assert_eq!(
+                std::mem::take(&mut *self.uncommitted.lock()),
+                vec![],
+                "Observers should only be notified of committed changes"
+            );
+            std::mem::take(&mut *self.batches.lock())
+        }
+
+        // Returns `None` for the kinds of changes we don't check.
+        fn is_committed(&self, change: &PlacesChange) -> Result<Option<bool>> {
+            let reader = self.reader.lock();
+            Ok(match change {
+                PlacesChange::VisitAdded {
+                    guid, visit_date, ..
+                } => Some(reader.exists(
+                    "SELECT 1 FROM moz_historyvisits v
+                     JOIN moz_places h ON h.id = v.place_id
+                     WHERE h.guid = :guid AND v.visit_date = :visit_date",
+                    rusqlite::named_params! {
+                        ":guid": guid,
+                        ":visit_date": visit_date,
+                    },
+                )?),
+                PlacesChange::PageRemoved {
+                    guid,
+                    is_removed_from_store,
+                    ..
+                } => Some(
+                    reader.exists(
+                        "SELECT 1 FROM moz_places WHERE guid = :guid",
+                        &[(":guid", guid)],
+                    )? != *is_removed_from_store,
+                ),
+                PlacesChange::BookmarkAdded { guid, .. } => Some(reader.exists(
+                    "SELECT 1 FROM moz_bookmarks WHERE guid = :guid",
+                    &[(":guid", guid)],
+                )?),
+                _ => None,
+            })
+        }
+    }
+
+    impl PlacesObserver for CommittedObserver {
+        fn on_changes(&self, changes: Vec<PlacesChange>) {
+            for change in &changes {
+                if let Some(false) = self
+                    .is_committed(change)
+                    .expect("Should check if the change was committed")
+                {
+                    self.uncommitted.lock().push(change.clone());
+                }
+            }
+            self.batches.lock().push(changes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_api;
+    use crate::api::places_api::{ConnectionType, PlacesApi};
+    use crate::observation::VisitObservation;
+    use crate::storage::bookmarks::{
+        delete_bookmark, insert_bookmark, update_bookmark_from_info, BookmarkPosition,
+        BookmarkRootGuid, BookmarkUpdateInfo, InsertableBookmark, InsertableFolder,
+    };
+    use crate::storage::history::{apply_observation, delete_visits_for};
+    use parking_lot::Mutex;
+    use pretty_assertions::assert_eq;
+
+    #[derive(Clone, Default)]
+    struct RecordingObserver(Arc<Mutex<Vec<Vec<PlacesChange>>>>);
+
+    impl RecordingObserver {
+        fn register(api: &PlacesApi) -> (Self, u64) {
+            let observer = Self::default();
+            let id = api.register_observer(Box::new(observer.clone()));
+            (observer, id)
+        }
+
+        fn take(&self) -> Vec<Vec<PlacesChange>> {
+            std::mem::take(&mut *self.0.lock())
+        }
+    }
+
+    impl PlacesObserver for RecordingObserver {
+        fn on_changes(&self, changes: Vec<PlacesChange>) {
+            self.0.lock().push(changes);
+        }
+    }
+
+    #[test]
+    fn test_history_changes() -> Result<()> {
+        let api = new_mem_api();
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        let (observer, _) = RecordingObserver::register(&api);
+
+        let url = Url::parse("https://www.example.com/")?;
+        apply_observation(
+            &conn,
+            VisitObservation::new(url.clone())
+                .with_visit_type(VisitType::Link)
+                .with_at(Timestamp(1000))
+                .with_title(Some("Example".to_string())),
+        )?;
+        let batches = observer.take();
+        assert_eq!(batches.len(), 1);
+        let guid = match &batches[0][..] {
+            [PlacesChange::VisitAdded {
+                url: visit_url,
+                guid,
+                visit_type: VisitType::Link,
+                visit_date: Timestamp(1000),
+                is_local: true,
+            }, PlacesChange::TitleChanged { title, .. }, PlacesChange::FrecencyChanged { .. }] => {
+                assert_eq!(visit_url, &url);
+                assert_eq!(title.as_deref(), Some("Example"));
+                guid.clone()
+            }
+            changes => panic!("Unexpected changes: {:?}", changes),
+        };
+
+        delete_visits_for(&conn, &guid)?;
+        assert_eq!(
+            observer.take(),
+            vec![vec![PlacesChange::PageRemoved {
+                url,
+                guid,
+                is_removed_from_store: true,
+            }]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bookmark_changes() -> Result<()> {
+        let api = new_mem_api();
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        let (observer, _) = RecordingObserver::register(&api);
+
+        let url = Url::parse("https://www.example.com/")?;
+        let folder_guid = insert_bookmark(
+            &conn,
+            InsertableFolder {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: Some(SyncGuid::from("folder______")),
+                title: Some("Folder".to_string()),
+                children: vec![],
+            }
+            .into(),
+        )?;
+        let bookmark_guid = insert_bookmark(
+            &conn,
+            InsertableBookmark {
+                parent_guid: folder_guid.clone(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: Some(SyncGuid::from("bookmark____")),
+                url: url.clone(),
+                title: Some("Bookmark".to_string()),
+            }
+            .into(),
+        )?;
+        assert_eq!(
+            observer.take(),
+            vec![
+                vec![PlacesChange::BookmarkAdded {
+                    guid: folder_guid.clone(),
+                    parent_guid: BookmarkRootGuid::Unfiled.into(),
+                    position: 0,
+                    url: None,
+                    title: Some("Folder".to_string()),
+                }],
+                vec![PlacesChange::BookmarkAdded {
+                    guid: bookmark_guid.clone(),
+                    parent_guid: folder_guid.clone(),
+                    position: 0,
+                    url: Some(url.clone()),
+                    title: Some("Bookmark".to_string()),
+                }],
+            ]
+        );
+
+        update_bookmark_from_info(
+            &conn,
+            BookmarkUpdateInfo {
+                guid: bookmark_guid.clone(),
+                title: Some("New title".to_string()),
+                url: None,
+                parent_guid: Some(BookmarkRootGuid::Menu.into()),
+                position: None,
+            },
+        )?;
+        // Both changes are made by the same statement, so their order is up
+        // to SQLite.
+        let batches = observer.take();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].len(), 2);
+        assert!(batches[0].contains(&PlacesChange::BookmarkMoved {
+            guid: bookmark_guid.clone(),
+            old_parent_guid: folder_guid.clone(),
+            old_position: 0,
+            new_parent_guid: BookmarkRootGuid::Menu.into(),
+            new_position: 0,
+        }));
+        assert!(batches[0].contains(&PlacesChange::BookmarkChanged {
+            guid: bookmark_guid.clone(),
+            url: Some(url.clone()),
+            title: Some("New title".to_string()),
+        }));
+
+        // Move the bookmark back, and remove its folder, so that it's removed
+        // along with it.
+        update_bookmark_from_info(
+            &conn,
+            BookmarkUpdateInfo {
+                guid: bookmark_guid.clone(),
+                title: Some("New title".to_string()),
+                url: None,
+                parent_guid: Some(folder_guid.clone()),
+                position: None,
+            },
+        )?;
+        observer.take();
+        delete_bookmark(&conn, &folder_guid)?;
+        let batches = observer.take();
+        assert_eq!(batches.len(), 1);
+        let removed: Vec<_> = batches[0]
+            .iter()
+            .filter(|c| matches!(c, PlacesChange::BookmarkRemoved { .. }))
+            .cloned()
+            .collect();
+        assert_eq!(
+            removed,
+            vec![
+                PlacesChange::BookmarkRemoved {
+                    guid: folder_guid.clone(),
+                    parent_guid: BookmarkRootGuid::Unfiled.into(),
+                    position: 0,
+                    url: None,
+                },
+                PlacesChange::BookmarkRemoved {
+                    guid: bookmark_guid,
+                    parent_guid: folder_guid,
+                    position: 0,
+                    url: Some(url),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback_and_unregister() -> Result<()> {
+        let api = new_mem_api();
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        let (observer, observer_id) = RecordingObserver::register(&api);
+
+        let url = Url::parse("https://www.example.com/")?;
+        apply_observation(
+            &conn,
+            VisitObservation::new(url.clone()).with_visit_type(VisitType::Link),
+        )?;
+        observer.take();
+
+        // Changes which are rolled back aren't delivered.
+        let tx = conn.begin_transaction()?;
+        conn.execute_cached(
+            "UPDATE moz_places SET title = 'Rolled back' WHERE url = :url",
+            &[(":url", &url.as_str())],
+        )?;
+        tx.rollback()?;
+        let tx = conn.begin_transaction()?;
+        conn.execute_cached(
+            "UPDATE moz_places SET title = 'Committed' WHERE url = :url",
+            &[(":url", &url.as_str())],
+        )?;
+        tx.commit()?;
+        match &observer.take()[..] {
+            [batch] => assert!(matches!(
+                &batch[..],
+                [PlacesChange::TitleChanged { title: Some(title), .. }] if title == "Committed"
+            )),
+            batches => panic!("Unexpected changes: {:?}", batches),
+        }
+
+        api.unregister_observer(observer_id);
+        assert!(!has_observers(conn.api_id()));
+        apply_observation(
+            &conn,
+            VisitObservation::new(url).with_visit_type(VisitType::Link),
+        )?;
+        assert_eq!(observer.take(), Vec::<Vec<PlacesChange>>::new());
+        Ok(())
+    }
+}
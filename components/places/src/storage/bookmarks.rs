@@ -25,6 +25,7 @@ pub use root_guid::{BookmarkRootGuid, USER_CONTENT_ROOTS};
 
 mod conversions;
 pub mod fetch;
+pub mod html;
 pub mod json_tree;
 mod root_guid;
 
@@ -241,8 +242,9 @@ impl InsertableItem {
     impl_common_bookmark_getter!(last_modified, Option<Timestamp>);
     impl_common_bookmark_getter!(guid, Option<SyncGuid>);
 
-    // We allow a setter for parent_guid and timestamps to help when inserting a tree.
-    fn set_parent_guid(&mut self, guid: SyncGuid) {
+    // We allow a setter for parent_guid, position and timestamps to help when
+    // inserting a tree.
+    pub(crate) fn set_parent_guid(&mut self, guid: SyncGuid) {
         match self {
             InsertableItem::Bookmark { b } => b.parent_guid = guid,
             InsertableItem::Separator { s } => s.parent_guid = guid,
@@ -250,6 +252,14 @@ impl InsertableItem {
         }
     }
 
+    pub(crate) fn set_position(&mut self, position: BookmarkPosition) {
+        match self {
+            InsertableItem::Bookmark { b } => b.position = position,
+            InsertableItem::Separator { s } => s.position = position,
+            InsertableItem::Folder { f } => f.position = position,
+        }
+    }
+
     fn set_last_modified(&mut self, ts: Timestamp) {
         match self {
             InsertableItem::Bookmark { b } => b.last_modified = Some(ts),
@@ -284,7 +294,7 @@ pub fn maybe_truncate_title<'a>(t: &Option<&'a str>) -> Option<&'a str> {
     t.map(|title| slice_up_to(title, TITLE_LENGTH_MAX))
 }
 
-fn insert_bookmark_in_tx(db: &PlacesDb, bm: InsertableItem) -> Result<SyncGuid> {
+pub(crate) fn insert_bookmark_in_tx(db: &PlacesDb, bm: InsertableItem) -> Result<SyncGuid> {
     // find the row ID of the parent.
     if bm.parent_guid() == BookmarkRootGuid::Root {
         return Err(InvalidPlaceInfo::CannotUpdateRoot(BookmarkRootGuid::Root).into());
@@ -424,7 +434,7 @@ pub fn delete_bookmark(db: &PlacesDb, guid: &SyncGuid) -> Result<bool> {
     result
 }
 
-fn delete_bookmark_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<bool> {
+pub(crate) fn delete_bookmark_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<bool> {
     // Can't delete a root.
     if let Some(root) = BookmarkRootGuid::well_known(guid.as_str()) {
         return Err(InvalidPlaceInfo::CannotUpdateRoot(root).into());
@@ -4,7 +4,9 @@
 
 use super::super::bookmarks::json_tree::{self, FetchDepth};
 use super::*;
+use crate::storage::tags::{get_tags_for_url, validate_tag};
 use rusqlite::Row;
+use std::collections::HashMap;
 
 // A helper that will ensure tests fail, but in production will make log noise instead.
 fn noisy_debug_assert_eq<T: std::cmp::PartialEq + std::fmt::Debug>(a: &T, b: &T, msg: &str) {
@@ -41,6 +43,7 @@ pub struct BookmarkData {
     pub last_modified: Timestamp,
     pub url: Url,
     pub title: Option<String>,
+    pub tags: Vec<String>,
 }
 
 impl From<BookmarkData> for Item {
@@ -58,6 +61,7 @@ impl PartialEq for BookmarkData {
             && self.position == other.position
             && self.url == other.url
             && self.title == other.title
+            && self.tags == other.tags
     }
 }
 
@@ -197,6 +201,8 @@ fn item_from_node_with_parent_info(
             title: b.title,
             date_added: b.date_added.expect("always get dates"),
             last_modified: b.last_modified.expect("always get dates"),
+            // Filled in by `fetch_tree_with_depth`.
+            tags: Vec::new(),
         }
         .into(),
         json_tree::BookmarkTreeNode::Separator { s } => Separator {
@@ -234,7 +240,7 @@ pub fn fetch_tree_with_depth(
         return Ok(None);
     };
     // parent_guid being an Option<> is a bit if a pain :(
-    Ok(Some(match tree {
+    let mut item = match tree {
         json_tree::BookmarkTreeNode::Folder { f } => {
             noisy_debug_assert(
                 parent_guid.is_none() ^ (f.guid.as_ref() != Some(BookmarkRootGuid::Root.guid())),
@@ -252,10 +258,73 @@ pub fn fetch_tree_with_depth(
             position,
             0,
         ),
-    }))
+    };
+    match &mut item {
+        Item::Bookmark { b } => b.tags = get_tags_for_url(db, &b.url)?,
+        Item::Folder { f } => {
+            if let Some(children) = &mut f.child_nodes {
+                let tags_by_url = fetch_tags_by_url(db)?;
+                for child in children {
+                    set_tags_in_tree(child, &tags_by_url);
+                }
+            }
+        }
+        Item::Separator { .. } => (),
+    }
+    Ok(Some(item))
+}
+
+// Returns the tags for every tagged URL. When we return more than one
+// bookmark, it's cheaper to fetch these all at once than to query the tags
+// for each bookmark, since there are usually far fewer tagged URLs than
+// bookmarks.
+fn fetch_tags_by_url(db: &PlacesDb) -> Result<HashMap<String, Vec<String>>> {
+    let mut tags_by_url: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = db.prepare_cached(
+        "SELECT h.url, t.tag
+         FROM moz_tags t
+         JOIN moz_tags_relation r ON r.tag_id = t.id
+         JOIN moz_places h ON h.id = r.place_id
+         ORDER BY t.lastModified DESC",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        tags_by_url
+            .entry(row.get("url")?)
+            .or_default()
+            .push(row.get("tag")?);
+    }
+    Ok(tags_by_url)
+}
+
+fn set_tags_in_tree(item: &mut Item, tags_by_url: &HashMap<String, Vec<String>>) {
+    match item {
+        Item::Bookmark { b } => set_tags(b, tags_by_url),
+        Item::Folder { f } => {
+            for child in f.child_nodes.iter_mut().flatten() {
+                set_tags_in_tree(child, tags_by_url);
+            }
+        }
+        Item::Separator { .. } => (),
+    }
+}
+
+fn set_tags(b: &mut BookmarkData, tags_by_url: &HashMap<String, Vec<String>>) {
+    b.tags = tags_by_url.get(b.url.as_str()).cloned().unwrap_or_default();
+}
+
+fn add_tags(db: &PlacesDb, mut bookmarks: Vec<BookmarkData>) -> Result<Vec<BookmarkData>> {
+    if !bookmarks.is_empty() {
+        let tags_by_url = fetch_tags_by_url(db)?;
+        for b in &mut bookmarks {
+            set_tags(b, &tags_by_url);
+        }
+    }
+    Ok(bookmarks)
 }
 
 pub fn fetch_bookmarks_by_url(db: &PlacesDb, url: &Url) -> Result<Vec<BookmarkData>> {
+    let tags = get_tags_for_url(db, url)?;
     let nodes = crate::storage::bookmarks::get_raw_bookmarks_for_url(db, url)?
         .into_iter()
         .map(|rb| {
@@ -280,6 +349,7 @@ pub fn fetch_bookmarks_by_url(db: &PlacesDb, url: &Url) -> Result<Vec<BookmarkDa
                 last_modified: rb.date_modified,
                 url: url.clone(),
                 title: rb.title,
+                tags: tags.clone(),
             }
         })
         .collect::<Vec<_>>();
@@ -318,6 +388,8 @@ fn bookmark_from_row(row: &Row<'_>) -> Result<Option<BookmarkData>> {
                 last_modified: row.get("lastModified")?,
                 title: row.get("title")?,
                 url,
+                // Filled in by `add_tags`.
+                tags: Vec::new(),
             }),
             None => None,
         },
@@ -326,7 +398,7 @@ fn bookmark_from_row(row: &Row<'_>) -> Result<Option<BookmarkData>> {
 
 pub fn search_bookmarks(db: &PlacesDb, search: &str, limit: u32) -> Result<Vec<BookmarkData>> {
     let scope = db.begin_interrupt_scope()?;
-    Ok(db
+    let bookmarks = db
         .query_rows_into_cached::<Vec<Option<BookmarkData>>, _, _, _, _>(
             &SEARCH_QUERY,
             &[
@@ -340,12 +412,13 @@ pub fn search_bookmarks(db: &PlacesDb, search: &str, limit: u32) -> Result<Vec<B
         )?
         .into_iter()
         .flatten()
-        .collect())
+        .collect();
+    add_tags(db, bookmarks)
 }
 
 pub fn recent_bookmarks(db: &PlacesDb, limit: u32) -> Result<Vec<BookmarkData>> {
     let scope = db.begin_interrupt_scope()?;
-    Ok(db
+    let bookmarks = db
         .query_rows_into_cached::<Vec<Option<BookmarkData>>, _, _, _, _>(
             &RECENT_BOOKMARKS_QUERY,
             &[(":limit", &limit as &dyn rusqlite::ToSql)],
@@ -356,7 +429,28 @@ pub fn recent_bookmarks(db: &PlacesDb, limit: u32) -> Result<Vec<BookmarkData>>
         )?
         .into_iter()
         .flatten()
-        .collect())
+        .collect();
+    add_tags(db, bookmarks)
+}
+
+/// Fetches all bookmarks for URLs with the specified tag, most recently added
+/// first.
+pub fn fetch_bookmarks_with_tag(db: &PlacesDb, tag: &str) -> Result<Vec<BookmarkData>> {
+    let tag = validate_tag(tag).ensure_valid()?;
+    let scope = db.begin_interrupt_scope()?;
+    let bookmarks = db
+        .query_rows_into_cached::<Vec<Option<BookmarkData>>, _, _, _, _>(
+            &BOOKMARKS_WITH_TAG_QUERY,
+            &[(":tag", &tag as &dyn rusqlite::ToSql)],
+            |row| -> Result<_> {
+                scope.err_if_interrupted()?;
+                bookmark_from_row(row)
+            },
+        )?
+        .into_iter()
+        .flatten()
+        .collect();
+    add_tags(db, bookmarks)
 }
 
 lazy_static::lazy_static! {
@@ -413,6 +507,26 @@ lazy_static::lazy_static! {
         LIMIT :limit",
         bookmark_type = BookmarkType::Bookmark as u8
     );
+
+    pub static ref BOOKMARKS_WITH_TAG_QUERY: String = format!(
+        "SELECT
+            b.guid,
+            p.guid AS parentGuid,
+            b.position,
+            b.dateAdded,
+            b.lastModified,
+            NULLIF(b.title, '') AS title,
+            h.url AS url
+        FROM moz_bookmarks b
+        JOIN moz_bookmarks p ON p.id = b.parent
+        JOIN moz_places h ON h.id = b.fk
+        JOIN moz_tags_relation r ON r.place_id = h.id
+        JOIN moz_tags t ON t.id = r.tag_id
+        WHERE b.type = {bookmark_type}
+            AND t.tag = :tag
+        ORDER BY b.dateAdded DESC",
+        bookmark_type = BookmarkType::Bookmark as u8
+    );
 }
 
 #[cfg(test)]
@@ -467,6 +581,7 @@ mod test {
                 // Ignored by our PartialEq
                 date_added: Timestamp(0),
                 last_modified: Timestamp(0),
+                tags: vec![],
             }
         );
         assert_eq!(
@@ -480,6 +595,7 @@ mod test {
                 // Ignored by our PartialEq
                 date_added: Timestamp(0),
                 last_modified: Timestamp(0),
+                tags: vec![],
             }
         );
 
@@ -488,6 +604,70 @@ mod test {
 
         Ok(())
     }
+    #[test]
+    fn test_tags() -> Result<()> {
+        let conns = new_mem_connections();
+        insert_json_tree(
+            &conns.write,
+            json!({
+                "guid": String::from(BookmarkRootGuid::Unfiled.as_str()),
+                "children": [
+                    {
+                        "guid": "bookmark1___",
+                        "url": "https://www.example1.com/",
+                        "title": "tagged",
+                    },
+                    {
+                        "guid": "folder1_____",
+                        "title": "folder",
+                        "children": [
+                            {
+                                "guid": "bookmark2___",
+                                "url": "https://www.example1.com/",
+                                "title": "tagged too",
+                            },
+                            {
+                                "guid": "bookmark3___",
+                                "url": "https://www.example2.com/",
+                                "title": "untagged",
+                            },
+                        ]
+                    },
+                ]
+            }),
+        );
+        let url = Url::parse("https://www.example1.com/")?;
+        crate::storage::tags::tag_url(&conns.write, &url, "foo")?;
+
+        let b = match fetch_bookmark(&conns.read, &"bookmark1___".into(), false)? {
+            Some(Item::Bookmark { b }) => b,
+            _ => panic!("should be a bookmark"),
+        };
+        assert_eq!(b.tags, vec!["foo".to_string()]);
+
+        let children = match fetch_tree(&conns.read, &"folder1_____".into())? {
+            Some(Item::Folder { f }) => f.child_nodes.expect("should have children"),
+            _ => panic!("should be a folder"),
+        };
+        assert_eq!(children.len(), 2);
+        match (&children[0], &children[1]) {
+            (Item::Bookmark { b: b2 }, Item::Bookmark { b: b3 }) => {
+                assert_eq!(b2.tags, vec!["foo".to_string()]);
+                assert!(b3.tags.is_empty());
+            }
+            _ => panic!("should be bookmarks"),
+        }
+
+        let mut with_tag = fetch_bookmarks_with_tag(&conns.read, "foo")?;
+        with_tag.sort_by_key(|b| b.guid.as_str().to_string());
+        assert_eq!(
+            with_tag.iter().map(|b| b.guid.as_str()).collect::<Vec<_>>(),
+            vec!["bookmark1___", "bookmark2___"]
+        );
+        assert!(fetch_bookmarks_with_tag(&conns.read, "bar")?.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_search() -> Result<()> {
         let conns = new_mem_connections();
@@ -856,6 +1036,7 @@ mod test {
                 // Ignored by our PartialEq
                 date_added: Timestamp(0),
                 last_modified: Timestamp(0),
+                tags: vec![],
             }
         );
         assert_eq!(
@@ -869,6 +1050,7 @@ mod test {
                 // Ignored by our PartialEq
                 date_added: Timestamp(0),
                 last_modified: Timestamp(0),
+                tags: vec![],
             }
         );
         assert_eq!(
@@ -882,6 +1064,7 @@ mod test {
                 // Ignored by our PartialEq
                 date_added: Timestamp(0),
                 last_modified: Timestamp(0),
+                tags: vec![],
             }
         );
         Ok(())
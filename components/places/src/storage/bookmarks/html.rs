@@ -0,0 +1,976 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Import and export of bookmarks in the "Netscape bookmark file" HTML format.
+// This is the format every browser knows how to exchange, so it's what users
+// will hand us when moving from (or to) another browser.
+//
+// The format is barely HTML - it's a flat stream of tags where `<DL>` opens a
+// folder's children, `<H3>` is a folder title, `<A>` is a bookmark and `<HR>`
+// is a separator. Real-world files are frequently malformed (unclosed `<DT>`
+// and `<p>` tags are the norm), so rather than pulling in a full HTML parser
+// we use a small, very forgiving tokenizer and only look at the tags we care
+// about - this is roughly what desktop's BookmarkHTMLUtils does too.
+//
+// Import builds `json_tree` nodes and inserts them via `insert_tree_in_tx`,
+// then applies keywords and tags, which the tree itself has no way to
+// represent. It all happens in one transaction, so a failed import doesn't
+// leave some of the bookmarks behind.
+
+use super::json_tree::{
+    fetch_tree, insert_tree_in_tx, BookmarkNode, BookmarkTreeNode, FetchDepth, FolderNode,
+    SeparatorNode,
+};
+use super::BookmarkRootGuid;
+use crate::db::PlacesDb;
+use crate::error::Result;
+use crate::storage::keywords::{set_keyword_for_url_in_tx, validate_keyword};
+use crate::storage::tags::validate_tag;
+use crate::storage::URL_LENGTH_MAX;
+use sql_support::ConnExt;
+use std::collections::HashMap;
+use std::fmt::Write;
+use types::Timestamp;
+use url::Url;
+
+// Folders with these attributes, when they appear at the top level of the
+// file, map to our roots rather than being imported as regular folders.
+// The first two are what desktop writes; the mobile one is our own - other
+// browsers will just import it as a regular folder.
+const TOOLBAR_FOLDER_ATTR: &str = "PERSONAL_TOOLBAR_FOLDER";
+const UNFILED_FOLDER_ATTR: &str = "UNFILED_BOOKMARKS_FOLDER";
+const MOBILE_FOLDER_ATTR: &str = "MOBILE_BOOKMARKS_FOLDER";
+
+/// Imports bookmarks from a Netscape bookmark HTML file.
+///
+/// Top-level items are appended to the bookmarks menu, and the toolbar,
+/// unfiled and mobile folders (if marked as such in the file) are appended
+/// to the corresponding roots. Existing bookmarks are not touched, so
+/// importing the same file twice will create duplicates, just like desktop.
+///
+/// Returns the number of bookmarks (not folders or separators) imported.
+pub fn import_html(db: &PlacesDb, html: &str) -> Result<u32> {
+    let parsed = Parser::default().parse(html);
+    let tx = db.begin_transaction()?;
+    let result = import_parsed(db, parsed);
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    result
+}
+
+fn import_parsed(db: &PlacesDb, parsed: Parsed) -> Result<u32> {
+    for (root, children) in parsed.roots {
+        if children.is_empty() {
+            continue;
+        }
+        insert_tree_in_tx(
+            db,
+            FolderNode {
+                guid: Some(root.as_guid()),
+                children,
+                ..Default::default()
+            },
+        )?;
+    }
+
+    for (url, keyword) in parsed.keywords {
+        // If the keyword was used by a different URL, it's moved to this one,
+        // and the bookmarks for that URL are re-uploaded without it.
+        set_keyword_for_url_in_tx(db, &url, &keyword)?;
+    }
+    for (url, tags) in parsed.tags {
+        for tag in tags {
+            db.execute_cached(
+                "INSERT OR IGNORE INTO moz_tags(tag, lastModified)
+                 VALUES(:tag, now())",
+                &[(":tag", &tag)],
+            )?;
+            db.execute_cached(
+                "INSERT OR IGNORE INTO moz_tags_relation(tag_id, place_id)
+                 SELECT t.id, h.id FROM moz_tags t, moz_places h
+                 WHERE t.tag = :tag
+                 AND h.url_hash = hash(:url) AND h.url = :url",
+                rusqlite::named_params! { ":tag": tag, ":url": url.as_str() },
+            )?;
+        }
+    }
+    Ok(parsed.num_bookmarks)
+}
+
+/// Exports all bookmarks as a Netscape bookmark HTML file, in the same shape
+/// desktop writes it, so the result can be imported by any browser.
+pub fn export_html(db: &PlacesDb) -> Result<String> {
+    let root = match fetch_tree(db, BookmarkRootGuid::Root.guid(), &FetchDepth::Deepest)? {
+        Some((BookmarkTreeNode::Folder { f }, _, _)) => f,
+        _ => {
+            // The roots are created with the database, so this can't really
+            // happen - but an empty file is better than failing the export.
+            log::warn!("export_html: no root folder");
+            FolderNode::default()
+        }
+    };
+
+    let keywords = fetch_keywords(db)?;
+    let tags = fetch_tags(db)?;
+    let writer = Writer {
+        keywords: &keywords,
+        tags: &tags,
+    };
+
+    let mut out = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <!-- This is an automatically generated file.\n     \
+         It will be read and overwritten.\n     \
+         DO NOT EDIT! -->\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks Menu</H1>\n\n\
+         <DL><p>\n",
+    );
+    // The menu's children are written at the top level, and the other roots
+    // become specially marked folders after them.
+    for child in root.children {
+        let f = match child {
+            BookmarkTreeNode::Folder { f } => f,
+            _ => continue,
+        };
+        let (title, attr) = match f.guid.as_ref().and_then(BookmarkRootGuid::from_guid) {
+            Some(BookmarkRootGuid::Menu) => {
+                writer.write_children(&mut out, &f.children, 1);
+                continue;
+            }
+            Some(BookmarkRootGuid::Toolbar) => ("Bookmarks Toolbar", TOOLBAR_FOLDER_ATTR),
+            Some(BookmarkRootGuid::Unfiled) => ("Other Bookmarks", UNFILED_FOLDER_ATTR),
+            Some(BookmarkRootGuid::Mobile) => ("Mobile Bookmarks", MOBILE_FOLDER_ATTR),
+            _ => {
+                log::warn!("export_html: ignoring unexpected child of the root");
+                continue;
+            }
+        };
+        let root_folder = FolderNode {
+            title: Some(title.to_string()),
+            ..f
+        };
+        writer.write_folder(&mut out, &root_folder, Some(attr), 1);
+    }
+    out.push_str("</DL>\n");
+    Ok(out)
+}
+
+fn fetch_keywords(db: &PlacesDb) -> Result<HashMap<String, String>> {
+    db.query_rows_and_then(
+        "SELECT h.url, k.keyword FROM moz_keywords k
+         JOIN moz_places h ON h.id = k.place_id",
+        [],
+        |row| -> Result<_> { Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)) },
+    )
+    .map(|rows| rows.into_iter().collect())
+}
+
+fn fetch_tags(db: &PlacesDb) -> Result<HashMap<String, Vec<String>>> {
+    let rows = db.query_rows_and_then(
+        "SELECT h.url, t.tag FROM moz_tags_relation r
+         JOIN moz_tags t ON t.id = r.tag_id
+         JOIN moz_places h ON h.id = r.place_id
+         ORDER BY t.tag",
+        [],
+        |row| -> Result<_> { Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)) },
+    )?;
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for (url, tag) in rows {
+        tags.entry(url).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+struct Writer<'a> {
+    keywords: &'a HashMap<String, String>,
+    tags: &'a HashMap<String, Vec<String>>,
+}
+
+impl<'a> Writer<'a> {
+    fn write_children(&self, out: &mut String, children: &[BookmarkTreeNode], depth: usize) {
+        for child in children {
+            match child {
+                BookmarkTreeNode::Bookmark { b } => self.write_bookmark(out, b, depth),
+                BookmarkTreeNode::Separator { .. } => {
+                    write_indent(out, depth);
+                    out.push_str("<HR>\n");
+                }
+                BookmarkTreeNode::Folder { f } => self.write_folder(out, f, None, depth),
+            }
+        }
+    }
+
+    fn write_folder(&self, out: &mut String, f: &FolderNode, attr: Option<&str>, depth: usize) {
+        write_indent(out, depth);
+        out.push_str("<DT><H3");
+        write_dates(out, f.date_added, f.last_modified);
+        if let Some(attr) = attr {
+            let _ = write!(out, " {}=\"true\"", attr);
+        }
+        out.push('>');
+        out.push_str(&escape(f.title.as_deref().unwrap_or_default()));
+        out.push_str("</H3>\n");
+        write_indent(out, depth);
+        out.push_str("<DL><p>\n");
+        self.write_children(out, &f.children, depth + 1);
+        write_indent(out, depth);
+        out.push_str("</DL><p>\n");
+    }
+
+    fn write_bookmark(&self, out: &mut String, b: &BookmarkNode, depth: usize) {
+        write_indent(out, depth);
+        let _ = write!(out, "<DT><A HREF=\"{}\"", escape(b.url.as_str()));
+        write_dates(out, b.date_added, b.last_modified);
+        if let Some(keyword) = self.keywords.get(b.url.as_str()) {
+            let _ = write!(out, " SHORTCUTURL=\"{}\"", escape(keyword));
+        }
+        if let Some(tags) = self.tags.get(b.url.as_str()) {
+            let _ = write!(out, " TAGS=\"{}\"", escape(&tags.join(",")));
+        }
+        out.push('>');
+        out.push_str(&escape(b.title.as_deref().unwrap_or_default()));
+        out.push_str("</A>\n");
+    }
+}
+
+fn write_indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("    ");
+    }
+}
+
+// The format uses seconds, where we use milliseconds.
+fn write_dates(out: &mut String, date_added: Option<Timestamp>, last_modified: Option<Timestamp>) {
+    if let Some(t) = date_added {
+        let _ = write!(out, " ADD_DATE=\"{}\"", t.as_millis() / 1000);
+    }
+    if let Some(t) = last_modified {
+        let _ = write!(out, " LAST_MODIFIED=\"{}\"", t.as_millis() / 1000);
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|semi| {
+            let entity = &rest[1..semi];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => {
+                    let code = if let Some(hex) = entity
+                        .strip_prefix("#x")
+                        .or_else(|| entity.strip_prefix("#X"))
+                    {
+                        u32::from_str_radix(hex, 16).ok()
+                    } else {
+                        entity.strip_prefix('#').and_then(|dec| dec.parse().ok())
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            c.map(|c| (c, semi))
+        });
+        match decoded {
+            Some((c, semi)) => {
+                result.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                // Not an entity we know - keep the ampersand as-is.
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    // Tag and attribute names are upper-cased; attribute values are unescaped.
+    Start(String, HashMap<String, String>),
+    End(String),
+    Text(&'a str),
+}
+
+/// A forgiving tokenizer for the subset of HTML used by bookmark files.
+/// Comments, doctypes and processing instructions are skipped.
+struct Tokenizer<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(html: &'a str) -> Self {
+        Self { rest: html }
+    }
+
+    fn skip_past(&mut self, needle: &str) {
+        self.rest = match self.rest.find(needle) {
+            Some(i) => &self.rest[i + needle.len()..],
+            None => "",
+        };
+    }
+
+    fn name(&mut self) -> String {
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=')
+            .unwrap_or(self.rest.len());
+        let name = self.rest[..end].to_ascii_uppercase();
+        self.rest = &self.rest[end..];
+        name
+    }
+
+    fn attributes(&mut self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        loop {
+            self.rest = self
+                .rest
+                .trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+            if self.rest.is_empty() {
+                break;
+            }
+            if let Some(rest) = self.rest.strip_prefix('>') {
+                self.rest = rest;
+                break;
+            }
+            let name = self.name();
+            self.rest = self.rest.trim_start();
+            let value = match self.rest.strip_prefix('=') {
+                Some(rest) => {
+                    let rest = rest.trim_start();
+                    let (value, rest) = match rest.chars().next() {
+                        Some(quote @ ('"' | '\'')) => {
+                            let rest = &rest[1..];
+                            match rest.find(quote) {
+                                Some(end) => (&rest[..end], &rest[end + 1..]),
+                                None => (rest, ""),
+                            }
+                        }
+                        _ => {
+                            let end = rest
+                                .find(|c: char| c.is_whitespace() || c == '>')
+                                .unwrap_or(rest.len());
+                            (&rest[..end], &rest[end..])
+                        }
+                    };
+                    self.rest = rest;
+                    unescape(value)
+                }
+                None => String::new(),
+            };
+            if !name.is_empty() {
+                attrs.insert(name, value);
+            } else if !self.rest.is_empty() && !self.rest.starts_with('>') {
+                // Something we can't make sense of - skip a char so we
+                // always make progress.
+                let mut chars = self.rest.chars();
+                chars.next();
+                self.rest = chars.as_str();
+            }
+        }
+        attrs
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            if !self.rest.starts_with('<') {
+                let end = self.rest.find('<').unwrap_or(self.rest.len());
+                let text = &self.rest[..end];
+                self.rest = &self.rest[end..];
+                return Some(Token::Text(text));
+            }
+            if self.rest.starts_with("<!--") {
+                self.skip_past("-->");
+                continue;
+            }
+            if self.rest.starts_with("<!") || self.rest.starts_with("<?") {
+                self.skip_past(">");
+                continue;
+            }
+            if let Some(rest) = self.rest.strip_prefix("</") {
+                self.rest = rest;
+                let name = self.name();
+                self.skip_past(">");
+                return Some(Token::End(name));
+            }
+            match self.rest[1..].chars().next() {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    self.rest = &self.rest[1..];
+                    let name = self.name();
+                    let attrs = self.attributes();
+                    return Some(Token::Start(name, attrs));
+                }
+                _ => {
+                    // A literal '<' in text.
+                    let text = &self.rest[..1];
+                    self.rest = &self.rest[1..];
+                    return Some(Token::Text(text));
+                }
+            }
+        }
+    }
+}
+
+// Where the children of a `<DL>` end up once it's closed.
+enum Target {
+    Root(BookmarkRootGuid),
+    Parent,
+}
+
+// The element whose text we are currently collecting.
+enum Current {
+    Heading(HashMap<String, String>, String),
+    Link(HashMap<String, String>, String),
+}
+
+#[derive(Default)]
+struct Parser {
+    stack: Vec<(FolderNode, Target)>,
+    // A folder whose `<H3>` we've seen, but whose `<DL>` we haven't yet.
+    pending: Option<(FolderNode, Target)>,
+    current: Option<Current>,
+    result: Parsed,
+}
+
+struct Parsed {
+    roots: Vec<(BookmarkRootGuid, Vec<BookmarkTreeNode>)>,
+    keywords: Vec<(Url, String)>,
+    tags: Vec<(Url, Vec<String>)>,
+    num_bookmarks: u32,
+}
+
+impl Default for Parsed {
+    fn default() -> Self {
+        Self {
+            roots: [
+                BookmarkRootGuid::Menu,
+                BookmarkRootGuid::Toolbar,
+                BookmarkRootGuid::Unfiled,
+                BookmarkRootGuid::Mobile,
+            ]
+            .into_iter()
+            .map(|root| (root, Vec::new()))
+            .collect(),
+            keywords: Vec::new(),
+            tags: Vec::new(),
+            num_bookmarks: 0,
+        }
+    }
+}
+
+fn parse_date(attrs: &HashMap<String, String>, name: &str) -> Option<Timestamp> {
+    attrs
+        .get(name)
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .and_then(|secs| secs.checked_mul(1000))
+        .map(Timestamp)
+}
+
+fn normalize_title(title: String) -> Option<String> {
+    let title = title.trim();
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+impl Parser {
+    fn parse(mut self, html: &str) -> Parsed {
+        for token in Tokenizer::new(html) {
+            match token {
+                Token::Start(name, attrs) => self.start(&name, attrs),
+                Token::End(name) => self.end(&name),
+                Token::Text(text) => match &mut self.current {
+                    Some(Current::Heading(_, t)) | Some(Current::Link(_, t)) => t.push_str(text),
+                    None => (),
+                },
+            }
+        }
+        // Be forgiving of truncated files and close everything still open.
+        self.flush_pending();
+        while !self.stack.is_empty() {
+            self.close_folder();
+        }
+        self.result
+    }
+
+    fn start(&mut self, name: &str, attrs: HashMap<String, String>) {
+        match name {
+            "H3" => {
+                self.flush_pending();
+                self.current = Some(Current::Heading(attrs, String::new()));
+            }
+            "A" => {
+                self.flush_pending();
+                self.current = Some(Current::Link(attrs, String::new()));
+            }
+            "HR" => {
+                self.flush_pending();
+                self.push_node(SeparatorNode::default().into());
+            }
+            "DL" => {
+                let frame = match self.pending.take() {
+                    Some(frame) => frame,
+                    // The outermost list is the menu.
+                    None if self.stack.is_empty() => {
+                        (FolderNode::default(), Target::Root(BookmarkRootGuid::Menu))
+                    }
+                    // A list without a heading - treat it as an untitled folder.
+                    None => (FolderNode::default(), Target::Parent),
+                };
+                self.stack.push(frame);
+            }
+            _ => (),
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        match (name, self.current.take()) {
+            ("H3", Some(Current::Heading(attrs, title))) => {
+                let folder = FolderNode {
+                    guid: None,
+                    date_added: parse_date(&attrs, "ADD_DATE"),
+                    last_modified: parse_date(&attrs, "LAST_MODIFIED"),
+                    title: normalize_title(unescape(&title)),
+                    children: Vec::new(),
+                };
+                // Only top-level folders can be special.
+                let special = if self.stack.len() == 1 {
+                    [
+                        (TOOLBAR_FOLDER_ATTR, BookmarkRootGuid::Toolbar),
+                        (UNFILED_FOLDER_ATTR, BookmarkRootGuid::Unfiled),
+                        (MOBILE_FOLDER_ATTR, BookmarkRootGuid::Mobile),
+                    ]
+                    .into_iter()
+                    .find(|(attr, _)| {
+                        matches!(attrs.get(*attr), Some(v) if v.eq_ignore_ascii_case("true"))
+                    })
+                    .map(|(_, root)| root)
+                } else {
+                    None
+                };
+                self.pending = Some((folder, special.map_or(Target::Parent, Target::Root)));
+            }
+            ("A", Some(Current::Link(attrs, title))) => self.add_bookmark(attrs, title),
+            ("DL", current) => {
+                self.current = current;
+                self.flush_pending();
+                if !self.stack.is_empty() {
+                    self.close_folder();
+                }
+            }
+            (_, current) => self.current = current,
+        }
+    }
+
+    fn add_bookmark(&mut self, attrs: HashMap<String, String>, title: String) {
+        let url = match attrs.get("HREF").map(|href| Url::parse(href.trim())) {
+            Some(Ok(url)) if url.as_str().len() <= URL_LENGTH_MAX => url,
+            Some(Ok(_)) => {
+                log::warn!("ignoring bookmark with a URL that's too long");
+                return;
+            }
+            Some(Err(e)) => {
+                log::warn!("ignoring bookmark with an invalid URL: {:?}", e);
+                return;
+            }
+            None => {
+                log::warn!("ignoring bookmark without a URL");
+                return;
+            }
+        };
+        if let Some(keyword) = attrs.get("SHORTCUTURL").filter(|k| !k.trim().is_empty()) {
+            match validate_keyword(keyword) {
+                Ok(keyword) => self.result.keywords.push((url.clone(), keyword)),
+                Err(_) => log::warn!("ignoring bookmark's invalid keyword"),
+            }
+        }
+        if let Some(tags) = attrs.get("TAGS") {
+            let tags: Vec<String> = tags
+                .split(',')
+                .filter_map(|tag| validate_tag(tag).ensure_valid().ok())
+                .map(ToString::to_string)
+                .collect();
+            if !tags.is_empty() {
+                self.result.tags.push((url.clone(), tags));
+            }
+        }
+        self.result.num_bookmarks += 1;
+        self.push_node(
+            BookmarkNode {
+                guid: None,
+                date_added: parse_date(&attrs, "ADD_DATE"),
+                last_modified: parse_date(&attrs, "LAST_MODIFIED"),
+                title: normalize_title(unescape(&title)),
+                url,
+            }
+            .into(),
+        );
+    }
+
+    // A heading that isn't followed by a `<DL>` is an empty folder.
+    fn flush_pending(&mut self) {
+        if let Some(frame) = self.pending.take() {
+            self.stack.push(frame);
+            self.close_folder();
+        }
+    }
+
+    fn close_folder(&mut self) {
+        if let Some((folder, target)) = self.stack.pop() {
+            match target {
+                Target::Root(root) => {
+                    if let Some((_, children)) =
+                        self.result.roots.iter_mut().find(|(r, _)| *r == root)
+                    {
+                        children.extend(folder.children);
+                    }
+                }
+                Target::Parent => self.push_node(folder.into()),
+            }
+        }
+    }
+
+    fn push_node(&mut self, node: BookmarkTreeNode) {
+        match self.stack.last_mut() {
+            Some((parent, _)) => parent.children.push(node),
+            // Items before the first `<DL>` - put them in the menu.
+            None => self.result.roots[0].1.push(node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::bookmarks::bookmarks_get_url_for_keyword;
+    use crate::storage::bookmarks::json_tree::insert_tree;
+    use crate::storage::tags::get_tags_for_url;
+    use crate::tests::assert_json_tree;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    const FIREFOX_HTML: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+
+<DL><p>
+    <DT><A HREF="https://www.mozilla.org/" ADD_DATE="1600000000" LAST_MODIFIED="1600000100" SHORTCUTURL="moz" TAGS="mozilla,web">Mozilla &amp; Friends</A>
+    <DD>A description we ignore
+    <HR>
+    <DT><H3 ADD_DATE="1600000000" LAST_MODIFIED="1600000200">Menu folder</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com/a?b=1&amp;c=2" ADD_DATE="1600000300">Example &lt;A&gt;</A>
+        <DT><H3>Empty</H3>
+        <DT><A HREF="not a url">Invalid</A>
+    </DL><p>
+    <DT><H3 ADD_DATE="1600000000" LAST_MODIFIED="1600000000" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="https://toolbar.example.com/">On the toolbar</A>
+    </DL><p>
+    <DT><H3 UNFILED_BOOKMARKS_FOLDER="true">Other Bookmarks</H3>
+    <DL><p>
+        <DT><A HREF="https://unfiled.example.com/" TAGS="web">Unfiled</A>
+    </DL><p>
+</DL>
+"#;
+
+    #[test]
+    fn test_tokenizer() {
+        let tokens: Vec<_> =
+            Tokenizer::new("<!-- x --><dt><a href=\"u\" Add_Date=1 flag>t&amp;</a><p>").collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Start("DT".into(), HashMap::new()),
+                Token::Start(
+                    "A".into(),
+                    [
+                        ("HREF".to_string(), "u".to_string()),
+                        ("ADD_DATE".to_string(), "1".to_string()),
+                        ("FLAG".to_string(), String::new()),
+                    ]
+                    .into_iter()
+                    .collect()
+                ),
+                Token::Text("t&amp;"),
+                Token::End("A".into()),
+                Token::Start("P".into(), HashMap::new()),
+            ]
+        );
+        assert_eq!(unescape("a&amp;b&#39;&#x41;&bogus;&"), "a&b'A&bogus;&");
+    }
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let conn = new_mem_connection();
+        assert_eq!(import_html(&conn, FIREFOX_HTML)?, 4);
+
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Menu.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Menu.as_guid(),
+                "children": [
+                    {
+                        "title": "Mozilla & Friends",
+                        "url": "https://www.mozilla.org/",
+                        "date_added": 1_600_000_000_000u64,
+                        "last_modified": 1_600_000_100_000u64,
+                    },
+                    {
+                        "type": 3,
+                    },
+                    {
+                        "title": "Menu folder",
+                        "date_added": 1_600_000_000_000u64,
+                        "children": [
+                            {
+                                "title": "Example <A>",
+                                "url": "https://example.com/a?b=1&c=2",
+                                "date_added": 1_600_000_300_000u64,
+                            },
+                            {
+                                "title": "Empty",
+                                "children": [],
+                            },
+                        ],
+                    },
+                ],
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Toolbar.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Toolbar.as_guid(),
+                "children": [
+                    {
+                        "title": "On the toolbar",
+                        "url": "https://toolbar.example.com/",
+                    },
+                ],
+            }),
+        );
+        assert_json_tree(
+            &conn,
+            &BookmarkRootGuid::Unfiled.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Unfiled.as_guid(),
+                "children": [
+                    {
+                        "title": "Unfiled",
+                        "url": "https://unfiled.example.com/",
+                    },
+                ],
+            }),
+        );
+
+        let mozilla = Url::parse("https://www.mozilla.org/")?;
+        assert_eq!(
+            bookmarks_get_url_for_keyword(&conn, "moz")?,
+            Some(mozilla.clone())
+        );
+        assert_eq!(sorted_tags(&conn, &mozilla)?, vec!["mozilla", "web"]);
+        assert_eq!(
+            get_tags_for_url(&conn, &Url::parse("https://unfiled.example.com/")?)?,
+            vec!["web"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let conn = new_mem_connection();
+        import_html(&conn, FIREFOX_HTML)?;
+        let exported = export_html(&conn)?;
+
+        // Importing the export into a fresh database should give us the same
+        // tree (ignoring guids, which aren't part of the format)...
+        let conn2 = new_mem_connection();
+        assert_eq!(import_html(&conn2, &exported)?, 4);
+        for root in [
+            BookmarkRootGuid::Menu,
+            BookmarkRootGuid::Toolbar,
+            BookmarkRootGuid::Unfiled,
+            BookmarkRootGuid::Mobile,
+        ] {
+            assert_eq!(fetch_children(&conn, root)?, fetch_children(&conn2, root)?);
+        }
+        let mozilla = Url::parse("https://www.mozilla.org/")?;
+        assert_eq!(
+            bookmarks_get_url_for_keyword(&conn2, "moz")?,
+            Some(mozilla.clone())
+        );
+        assert_eq!(sorted_tags(&conn2, &mozilla)?, vec!["mozilla", "web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_keywords() -> Result<()> {
+        let conn = new_mem_connection();
+        import_html(
+            &conn,
+            r#"<DL><p>
+    <DT><A HREF="https://a.example.com/" SHORTCUTURL=" MoZ ">A</A>
+    <DT><A HREF="https://b.example.com/" SHORTCUTURL="two words">B</A>
+</DL>"#,
+        )?;
+        // Keywords are normalized like any other keyword, and invalid ones
+        // are skipped.
+        assert_eq!(
+            bookmarks_get_url_for_keyword(&conn, "moz")?,
+            Some(Url::parse("https://a.example.com/")?)
+        );
+        assert!(!conn.exists("SELECT 1 FROM moz_keywords WHERE keyword = 'two words'", [],)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_failure() -> Result<()> {
+        let conn = new_mem_connection();
+        // Make applying tags fail, after all the bookmarks were inserted.
+        conn.execute_batch(
+            "CREATE TEMP TRIGGER fail_tags BEFORE INSERT ON moz_tags
+             BEGIN SELECT RAISE(FAIL, 'no tags'); END;",
+        )?;
+        assert!(import_html(&conn, FIREFOX_HTML).is_err());
+        // Nothing was imported.
+        for root in [
+            BookmarkRootGuid::Menu,
+            BookmarkRootGuid::Toolbar,
+            BookmarkRootGuid::Unfiled,
+        ] {
+            assert!(fetch_children(&conn, root)?.is_empty());
+        }
+        assert_eq!(bookmarks_get_url_for_keyword(&conn, "moz")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_mobile() -> Result<()> {
+        let conn = new_mem_connection();
+        insert_tree(
+            &conn,
+            FolderNode {
+                guid: Some(BookmarkRootGuid::Mobile.into()),
+                children: vec![BookmarkNode {
+                    guid: None,
+                    date_added: None,
+                    last_modified: None,
+                    title: Some("\"Quoted\" <mobile>".into()),
+                    url: Url::parse("https://mobile.example.com/")?,
+                }
+                .into()],
+                ..Default::default()
+            },
+        )?;
+        let exported = export_html(&conn)?;
+        assert!(exported.contains("<DT><H3 ADD_DATE=\"",));
+        assert!(exported.contains(" MOBILE_BOOKMARKS_FOLDER=\"true\">Mobile Bookmarks</H3>"));
+        assert!(exported.contains(">&quot;Quoted&quot; &lt;mobile&gt;</A>"));
+
+        let conn2 = new_mem_connection();
+        assert_eq!(import_html(&conn2, &exported)?, 1);
+        assert_json_tree(
+            &conn2,
+            &BookmarkRootGuid::Mobile.into(),
+            json!({
+                "guid": &BookmarkRootGuid::Mobile.as_guid(),
+                "children": [
+                    {
+                        "title": "\"Quoted\" <mobile>",
+                        "url": "https://mobile.example.com/",
+                    },
+                ],
+            }),
+        );
+        Ok(())
+    }
+
+    fn sorted_tags(conn: &PlacesDb, url: &Url) -> Result<Vec<String>> {
+        let mut tags = get_tags_for_url(conn, url)?;
+        tags.sort();
+        Ok(tags)
+    }
+
+    // Fetches the children of a root with guids removed and dates truncated to
+    // seconds, which is all the format can represent.
+    fn fetch_children(conn: &PlacesDb, root: BookmarkRootGuid) -> Result<Vec<BookmarkTreeNode>> {
+        fn normalize(node: BookmarkTreeNode) -> BookmarkTreeNode {
+            let secs = |t: Option<Timestamp>| t.map(|t| Timestamp(t.as_millis() / 1000 * 1000));
+            match node {
+                BookmarkTreeNode::Bookmark { b } => BookmarkNode {
+                    guid: None,
+                    date_added: secs(b.date_added),
+                    last_modified: secs(b.last_modified),
+                    ..b
+                }
+                .into(),
+                BookmarkTreeNode::Separator { s } => SeparatorNode {
+                    guid: None,
+                    date_added: secs(s.date_added),
+                    last_modified: secs(s.last_modified),
+                }
+                .into(),
+                BookmarkTreeNode::Folder { f } => FolderNode {
+                    guid: None,
+                    date_added: secs(f.date_added),
+                    last_modified: secs(f.last_modified),
+                    title: f.title,
+                    children: f.children.into_iter().map(normalize).collect(),
+                }
+                .into(),
+            }
+        }
+        Ok(match fetch_tree(conn, root.guid(), &FetchDepth::Deepest)? {
+            Some((BookmarkTreeNode::Folder { f }, _, _)) => {
+                f.children.into_iter().map(normalize).collect()
+            }
+            _ => panic!("roots must be folders"),
+        })
+    }
+}
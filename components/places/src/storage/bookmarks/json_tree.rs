@@ -4,8 +4,8 @@
 
 // This supports inserting and fetching an entire bookmark tree via JSON
 // compatible data structures.
-// It's currently used only by tests, examples and our utilities for importing
-// from a desktop JSON exports.
+// It's currently used only by tests, examples, our utilities for importing
+// from a desktop JSON exports and the HTML import/export in `html.rs`.
 //
 // None of our "real" consumers currently require JSON compatibility, so try
 // and avoid using this if you can!
@@ -51,7 +51,7 @@ fn cmp_options<T: PartialEq>(s: &Option<T>, o: &Option<T>) -> bool {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BookmarkNode {
     pub guid: Option<SyncGuid>,
     pub date_added: Option<Timestamp>,
@@ -77,7 +77,7 @@ impl PartialEq for BookmarkNode {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct SeparatorNode {
     pub guid: Option<SyncGuid>,
     pub date_added: Option<Timestamp>,
@@ -99,7 +99,7 @@ impl PartialEq for SeparatorNode {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct FolderNode {
     pub guid: Option<SyncGuid>,
     pub date_added: Option<Timestamp>,
@@ -125,7 +125,7 @@ impl PartialEq for FolderNode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum BookmarkTreeNode {
     Bookmark { b: BookmarkNode },
@@ -404,8 +404,14 @@ mod test_serialize {
 pub fn insert_tree(db: &PlacesDb, tree: FolderNode) -> Result<()> {
     // This API is strange - we don't add `tree`, but just use it for the parent.
     // It's only used for json importing, so we can live with a strange API :)
-    let parent = tree.guid.expect("inserting a tree without the root guid");
     let tx = db.begin_transaction()?;
+    insert_tree_in_tx(db, tree)?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub(crate) fn insert_tree_in_tx(db: &PlacesDb, tree: FolderNode) -> Result<()> {
+    let parent = tree.guid.expect("inserting a tree without the root guid");
     for child in tree.children {
         let mut insertable: InsertableItem = child.into();
         assert!(
@@ -416,7 +422,6 @@ pub fn insert_tree(db: &PlacesDb, tree: FolderNode) -> Result<()> {
         crate::storage::bookmarks::insert_bookmark_in_tx(db, insertable)?;
     }
     crate::storage::delete_pending_temp_tables(db)?;
-    tx.commit()?;
     Ok(())
 }
 
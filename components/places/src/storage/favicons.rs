@@ -0,0 +1,410 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Favicon storage. Like Desktop, each icon URL may be stored in several sizes,
+// and the same icon may be used by many pages. Icons at the root of a site,
+// like `/favicon.ico`, are also used for any page on that origin which doesn't
+// have icons of its own.
+
+use super::URL_LENGTH_MAX;
+use crate::db::PlacesDb;
+use crate::error::{Error, InvalidPlaceInfo, Result};
+use rusqlite::{Connection, Row};
+use sql_support::ConnExt;
+use types::Timestamp;
+use url::Url;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Favicon {
+    pub icon_url: Url,
+    /// The width of the icon in pixels, or 0 if it isn't known (for example, for SVG icons).
+    pub width: u32,
+    pub mime_type: Option<String>,
+    pub data: Vec<u8>,
+    /// When the icon should be fetched again. Expired icons are still returned
+    /// until they're removed with `delete_favicons_expired_before`.
+    pub expires: Timestamp,
+}
+
+impl Favicon {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(Self {
+            icon_url: Url::parse(&row.get::<_, String>("icon_url")?)?,
+            width: row.get("width")?,
+            mime_type: row.get("mime_type")?,
+            data: row.get("data")?,
+            expires: row.get("expire_ms")?,
+        })
+    }
+
+    // Desktop treats icons at `/favicon.ico` as root icons, which apply to the whole origin.
+    fn is_root(&self) -> bool {
+        self.icon_url.path() == "/favicon.ico" && self.icon_url.query().is_none()
+    }
+}
+
+// Picks the icon closest to the desired width: the smallest one which is at
+// least as wide, otherwise the widest one.
+const ORDER_BY_BEST_SIZE_SQL: &str = "
+    ORDER BY i.width < :width,
+             CASE WHEN i.width < :width THEN -i.width ELSE i.width END
+    LIMIT 1";
+
+/// Sets the icons for a page, replacing any icons it had before.
+///
+/// Icons which are already stored, with the same URL and width, are updated
+/// with the new data.
+pub fn set_favicons_for_page(db: &PlacesDb, page_url: &Url, icons: &[Favicon]) -> Result<()> {
+    if page_url.as_str().len() > URL_LENGTH_MAX {
+        return Err(Error::InvalidPlaceInfo(InvalidPlaceInfo::UrlTooLong));
+    }
+    let tx = db.begin_transaction()?;
+    let page_id = get_or_insert_page(&tx, page_url)?;
+    tx.execute_cached(
+        "DELETE FROM moz_icons_to_pages WHERE page_id = :page_id",
+        &[(":page_id", &page_id)],
+    )?;
+    for icon in icons {
+        let icon_id = insert_or_update_icon(&tx, icon)?;
+        tx.execute_cached(
+            "INSERT OR IGNORE INTO moz_icons_to_pages(page_id, icon_id)
+             VALUES (:page_id, :icon_id)",
+            &[(":page_id", &page_id), (":icon_id", &icon_id)],
+        )?;
+    }
+    delete_orphans(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn get_or_insert_page(conn: &Connection, page_url: &Url) -> Result<i64> {
+    let existing = conn.try_query_one(
+        "SELECT id FROM moz_pages_w_icons
+         WHERE page_url_hash = hash(:page_url) AND page_url = :page_url",
+        &[(":page_url", &page_url.as_str())],
+        true,
+    )?;
+    Ok(match existing {
+        Some(id) => id,
+        None => {
+            conn.execute_cached(
+                "INSERT INTO moz_pages_w_icons(page_url, page_url_hash)
+                 VALUES (:page_url, hash(:page_url))",
+                &[(":page_url", &page_url.as_str())],
+            )?;
+            conn.last_insert_rowid()
+        }
+    })
+}
+
+fn insert_or_update_icon(conn: &Connection, icon: &Favicon) -> Result<i64> {
+    conn.execute_cached(
+        "INSERT INTO moz_icons(icon_url, width, root_origin, mime_type, data, expire_ms)
+         VALUES (:icon_url, :width,
+                 CASE WHEN :is_root THEN get_prefix(:icon_url) || get_host_and_port(:icon_url) END,
+                 :mime_type, :data, :expire_ms)
+         ON CONFLICT(icon_url, width) DO UPDATE SET
+             root_origin = excluded.root_origin,
+             mime_type = excluded.mime_type,
+             data = excluded.data,
+             expire_ms = excluded.expire_ms",
+        rusqlite::named_params! {
+            ":icon_url": icon.icon_url.as_str(),
+            ":width": icon.width,
+            ":is_root": icon.is_root(),
+            ":mime_type": icon.mime_type,
+            ":data": icon.data,
+            ":expire_ms": icon.expires,
+        },
+    )?;
+    Ok(conn.query_row_and_then_cachable(
+        "SELECT id FROM moz_icons WHERE icon_url = :icon_url AND width = :width",
+        rusqlite::named_params! {
+            ":icon_url": icon.icon_url.as_str(),
+            ":width": icon.width,
+        },
+        |row| row.get(0),
+        true,
+    )?)
+}
+
+/// Gets the icon for a page which is closest to `desired_width` pixels wide:
+/// the smallest icon at least that wide, or the widest icon if they're all
+/// smaller. If the page doesn't have any icons, then the root icon for its
+/// origin is used instead.
+pub fn get_favicon_for_page(
+    db: &PlacesDb,
+    page_url: &Url,
+    desired_width: u32,
+) -> Result<Option<Favicon>> {
+    let for_page = db.try_query_row(
+        &format!(
+            "SELECT i.icon_url, i.width, i.mime_type, i.data, i.expire_ms
+             FROM moz_icons i
+             JOIN moz_icons_to_pages ip ON ip.icon_id = i.id
+             JOIN moz_pages_w_icons p ON p.id = ip.page_id
+             WHERE p.page_url_hash = hash(:page_url) AND p.page_url = :page_url
+             {ORDER_BY_BEST_SIZE_SQL}"
+        ),
+        rusqlite::named_params! {
+            ":page_url": page_url.as_str(),
+            ":width": desired_width,
+        },
+        Favicon::from_row,
+        true,
+    )?;
+    if for_page.is_some() {
+        return Ok(for_page);
+    }
+    db.try_query_row(
+        &format!(
+            "SELECT i.icon_url, i.width, i.mime_type, i.data, i.expire_ms
+             FROM moz_icons i
+             WHERE i.root_origin = get_prefix(:page_url) || get_host_and_port(:page_url)
+             {ORDER_BY_BEST_SIZE_SQL}"
+        ),
+        rusqlite::named_params! {
+            ":page_url": page_url.as_str(),
+            ":width": desired_width,
+        },
+        Favicon::from_row,
+        true,
+    )
+}
+
+/// Removes the icons for a page. Root icons for its origin are left alone.
+pub fn delete_favicons_for_page(db: &PlacesDb, page_url: &Url) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    tx.execute_cached(
+        "DELETE FROM moz_pages_w_icons
+         WHERE page_url_hash = hash(:page_url) AND page_url = :page_url",
+        &[(":page_url", &page_url.as_str())],
+    )?;
+    delete_orphans(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Removes all icons which expired before the given time.
+pub fn delete_favicons_expired_before(db: &PlacesDb, before: Timestamp) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    tx.execute_cached(
+        "DELETE FROM moz_icons WHERE expire_ms < :before",
+        &[(":before", &before)],
+    )?;
+    delete_orphans(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Removes icons for pages and origins which are no longer in history or
+/// bookmarks. This is run as part of `run_maintenance_prune`.
+pub fn prune_favicons(db: &PlacesDb) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    tx.execute_batch(
+        "DELETE FROM moz_pages_w_icons
+         WHERE NOT EXISTS(SELECT 1 FROM moz_places h
+                          WHERE h.url_hash = page_url_hash AND
+                                h.url = page_url);
+
+         DELETE FROM moz_icons
+         WHERE root_origin NOT NULL AND
+               NOT EXISTS(SELECT 1 FROM moz_origins o
+                          WHERE o.prefix || o.host = root_origin);",
+    )?;
+    delete_orphans(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+// Removes pages without icons, and icons which aren't used by any page. Root
+// icons are kept, since they're used for every page on their origin.
+fn delete_orphans(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DELETE FROM moz_pages_w_icons
+         WHERE NOT EXISTS(SELECT 1 FROM moz_icons_to_pages
+                          WHERE page_id = moz_pages_w_icons.id);
+
+         DELETE FROM moz_icons
+         WHERE root_origin IS NULL AND
+               NOT EXISTS(SELECT 1 FROM moz_icons_to_pages
+                          WHERE icon_id = moz_icons.id);",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::history::{apply_observation, delete_everything};
+    use crate::types::VisitType;
+    use pretty_assertions::assert_eq;
+
+    fn icon(url: &str, width: u32, expires: u64) -> Favicon {
+        Favicon {
+            icon_url: Url::parse(url).unwrap(),
+            width,
+            mime_type: Some("image/png".into()),
+            data: format!("{url}#{width}").into_bytes(),
+            expires: Timestamp(expires),
+        }
+    }
+
+    fn best_width(conn: &PlacesDb, page_url: &Url, desired_width: u32) -> Option<u32> {
+        get_favicon_for_page(conn, page_url, desired_width)
+            .expect("should get")
+            .map(|i| i.width)
+    }
+
+    fn count(conn: &PlacesDb, table: &str) -> u32 {
+        conn.query_one(&format!("SELECT COUNT(*) FROM {table}"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_best_size() {
+        let conn = new_mem_connection();
+        let page = Url::parse("https://example.com/page").unwrap();
+        set_favicons_for_page(
+            &conn,
+            &page,
+            &[
+                icon("https://example.com/icon.png", 16, 1),
+                icon("https://example.com/icon.png", 32, 1),
+                icon("https://example.com/icon-large.png", 128, 1),
+            ],
+        )
+        .expect("should set");
+
+        assert_eq!(best_width(&conn, &page, 16), Some(16));
+        assert_eq!(best_width(&conn, &page, 24), Some(32));
+        assert_eq!(best_width(&conn, &page, 64), Some(128));
+        assert_eq!(best_width(&conn, &page, 256), Some(128));
+
+        let favicon = get_favicon_for_page(&conn, &page, 32).unwrap().unwrap();
+        assert_eq!(favicon, icon("https://example.com/icon.png", 32, 1));
+
+        let other_page = Url::parse("https://example.org/page").unwrap();
+        assert_eq!(best_width(&conn, &other_page, 16), None);
+    }
+
+    #[test]
+    fn test_root_icon_fallback() {
+        let conn = new_mem_connection();
+        let page = Url::parse("https://example.com/page").unwrap();
+        let other_page = Url::parse("https://example.com/other").unwrap();
+        set_favicons_for_page(
+            &conn,
+            &page,
+            &[
+                icon("https://example.com/favicon.ico", 16, 1),
+                icon("https://example.com/page-icon.png", 64, 1),
+            ],
+        )
+        .expect("should set");
+
+        // The page's own icons are preferred to the root icon...
+        assert_eq!(best_width(&conn, &page, 16), Some(16));
+        assert_eq!(best_width(&conn, &page, 32), Some(64));
+        // ...but other pages on the origin only get the root icon.
+        assert_eq!(best_width(&conn, &other_page, 32), Some(16));
+        // And pages on other origins don't get anything.
+        let other_origin = Url::parse("http://example.com/page").unwrap();
+        assert_eq!(best_width(&conn, &other_origin, 32), None);
+
+        // Removing the page's icons keeps the root icon.
+        delete_favicons_for_page(&conn, &page).expect("should delete");
+        assert_eq!(best_width(&conn, &page, 32), Some(16));
+        assert_eq!(count(&conn, "moz_icons"), 1);
+        assert_eq!(count(&conn, "moz_pages_w_icons"), 0);
+    }
+
+    #[test]
+    fn test_replace_icons() {
+        let conn = new_mem_connection();
+        let page = Url::parse("https://example.com/page").unwrap();
+        set_favicons_for_page(&conn, &page, &[icon("https://example.com/a.png", 16, 1)])
+            .expect("should set");
+        set_favicons_for_page(&conn, &page, &[icon("https://example.com/b.png", 16, 1)])
+            .expect("should set");
+
+        let favicon = get_favicon_for_page(&conn, &page, 16).unwrap().unwrap();
+        assert_eq!(favicon.icon_url.as_str(), "https://example.com/b.png");
+        // The old icon isn't used by any page, so it should have been removed.
+        assert_eq!(count(&conn, "moz_icons"), 1);
+
+        // Storing the same icon again updates it in place.
+        set_favicons_for_page(&conn, &page, &[icon("https://example.com/b.png", 16, 2)])
+            .expect("should set");
+        let favicon = get_favicon_for_page(&conn, &page, 16).unwrap().unwrap();
+        assert_eq!(favicon.expires, Timestamp(2));
+        assert_eq!(count(&conn, "moz_icons"), 1);
+    }
+
+    #[test]
+    fn test_delete_expired() {
+        let conn = new_mem_connection();
+        let page = Url::parse("https://example.com/page").unwrap();
+        set_favicons_for_page(
+            &conn,
+            &page,
+            &[
+                icon("https://example.com/favicon.ico", 16, 10),
+                icon("https://example.com/icon.png", 32, 20),
+            ],
+        )
+        .expect("should set");
+
+        delete_favicons_expired_before(&conn, Timestamp(15)).expect("should delete");
+        assert_eq!(best_width(&conn, &page, 16), Some(32));
+        assert_eq!(count(&conn, "moz_icons"), 1);
+
+        delete_favicons_expired_before(&conn, Timestamp(25)).expect("should delete");
+        assert_eq!(best_width(&conn, &page, 16), None);
+        assert_eq!(count(&conn, "moz_icons"), 0);
+        assert_eq!(count(&conn, "moz_pages_w_icons"), 0);
+    }
+
+    #[test]
+    fn test_prune() {
+        let conn = new_mem_connection();
+        let visited = Url::parse("https://example.com/visited").unwrap();
+        let unvisited = Url::parse("https://example.org/unvisited").unwrap();
+        apply_observation(
+            &conn,
+            VisitObservation::new(visited.clone()).with_visit_type(VisitType::Link),
+        )
+        .expect("should apply");
+        set_favicons_for_page(
+            &conn,
+            &visited,
+            &[
+                icon("https://example.com/favicon.ico", 16, 1),
+                icon("https://example.com/icon.png", 32, 1),
+            ],
+        )
+        .expect("should set");
+        set_favicons_for_page(
+            &conn,
+            &unvisited,
+            &[
+                icon("https://example.org/favicon.ico", 16, 1),
+                icon("https://example.org/icon.png", 32, 1),
+            ],
+        )
+        .expect("should set");
+
+        prune_favicons(&conn).expect("should prune");
+        assert_eq!(best_width(&conn, &visited, 32), Some(32));
+        assert_eq!(best_width(&conn, &unvisited, 32), None);
+        assert_eq!(count(&conn, "moz_icons"), 2);
+
+        delete_everything(&conn).expect("should delete history");
+        prune_favicons(&conn).expect("should prune");
+        assert_eq!(count(&conn, "moz_icons"), 0);
+        assert_eq!(count(&conn, "moz_pages_w_icons"), 0);
+    }
+}
@@ -158,7 +158,7 @@ pub fn apply_observation_direct(
 pub fn update_frecency(db: &PlacesDb, id: RowId, redirect_boost: Option<bool>) -> Result<()> {
     let score = frecency::calculate_frecency(
         db.conn(),
-        &frecency::DEFAULT_FRECENCY_SETTINGS,
+        db.frecency_settings(),
         id.0, // TODO: calculate_frecency should take a RowId here.
         redirect_boost,
     )?;
@@ -194,7 +194,7 @@ pub fn frecency_stale_at(db: &PlacesDb, url: &Url) -> Result<Option<Timestamp>>
 // Add a single visit - you must know the page rowid. Does not update the
 // page info - if you are calling this, you will also need to update the
 // parent page with an updated change counter etc.
-fn add_visit(
+pub(crate) fn add_visit(
     db: &PlacesDb,
     page_id: RowId,
     from_visit: Option<RowId>,
@@ -252,7 +252,7 @@ pub fn href_to_guid(db: &PlacesDb, url: &str) -> Result<Option<SyncGuid>> {
 
 /// Internal function for deleting a page, creating a tombstone if necessary.
 /// Assumes a transaction is already set up by the caller.
-fn delete_visits_for_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
+pub(crate) fn delete_visits_for_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
     // We only create tombstones for history which exists and with sync_status
     // == SyncStatus::Normal
     let to_clean = db.conn().try_query_row(
@@ -403,17 +403,66 @@ pub fn delete_place_visit_at_time_by_href(
 
 pub fn prune_older_visits(db: &PlacesDb, limit: u32) -> Result<()> {
     let tx = db.begin_transaction()?;
-
-    let result = DbAction::apply_all(
-        db,
-        db_actions_from_visits_to_delete(find_visits_to_prune(
-            db,
-            limit as usize,
-            Timestamp::now(),
-        )?),
-    );
+    let result = prune_older_visits_in_tx(db, limit, Timestamp::now());
     tx.commit()?;
-    result
+    result.map(|_| ())
+}
+
+/// Prunes up to `limit` visits, starting with the "exotic" ones. Returns the
+/// number of visits pruned.
+pub(crate) fn prune_older_visits_in_tx(db: &PlacesDb, limit: u32, now: Timestamp) -> Result<u32> {
+    let visits = find_visits_to_prune(db, limit as usize, now)?;
+    let count = visits.len() as u32;
+    DbAction::apply_all(db, db_actions_from_visits_to_delete(visits))?;
+    Ok(count)
+}
+
+/// Deletes up to `limit` visits from before `cutoff`, oldest first, and
+/// cleans up the pages they belonged to. Unlike `prune_older_visits`, this
+/// doesn't look at the database size, so it's used to expire history that's
+/// older than the app's retention period. Returns the number of visits deleted.
+pub(crate) fn expire_visits_in_tx(db: &PlacesDb, cutoff: Timestamp, limit: u32) -> Result<u32> {
+    let visits = db.query_rows_and_then(
+        "SELECT id, place_id
+         FROM moz_historyvisits
+         WHERE visit_date < :cutoff
+         ORDER BY visit_date
+         LIMIT :limit",
+        rusqlite::named_params! {
+            ":cutoff": cutoff,
+            ":limit": limit,
+        },
+        VisitToDelete::from_row,
+    )?;
+    let count = visits.len() as u32;
+    DbAction::apply_all(db, db_actions_from_visits_to_delete(visits))?;
+    Ok(count)
+}
+
+/// Deletes up to `limit` orphaned pages: pages without any visits, bookmarks
+/// or keywords, which aren't referenced by history metadata either. These are
+/// usually left behind by removed bookmarks. Returns the number of pages
+/// deleted.
+pub(crate) fn delete_orphaned_pages_in_tx(db: &PlacesDb, limit: u32) -> Result<u32> {
+    let pages = db.query_rows_and_then(
+        "SELECT h.id, 0 AS has_foreign, 0 AS has_visits, h.sync_status
+         FROM moz_places h
+         WHERE h.foreign_count = 0
+           AND h.last_visit_date_local = 0
+           AND h.last_visit_date_remote = 0
+           AND NOT EXISTS(SELECT 1 FROM moz_places_metadata m
+                          WHERE m.place_id = h.id OR m.referrer_place_id = h.id)
+           -- Pages with stale frecencies are removed once they're recalculated.
+           AND NOT EXISTS(SELECT 1 FROM moz_places_stale_frecencies s
+                          WHERE s.place_id = h.id)
+         LIMIT :limit",
+        &[(":limit", &limit)],
+        PageToClean::from_row,
+    )?;
+    let count = pages.len() as u32;
+    cleanup_pages(db, &pages)?;
+    delete_pending_temp_tables(db)?;
+    Ok(count)
 }
 
 fn find_visits_to_prune(db: &PlacesDb, limit: usize, now: Timestamp) -> Result<Vec<VisitToDelete>> {
@@ -774,8 +823,9 @@ pub mod history_sync {
     use sync15::bso::OutgoingEnvelope;
 
     use super::*;
-    use crate::history_sync::record::{HistoryRecord, HistoryRecordVisit};
+    use crate::history_sync::record::{HistoryRecord, HistoryRecordMetadata, HistoryRecordVisit};
     use crate::history_sync::HISTORY_TTL;
+    use crate::storage::search::update_search_index;
     use std::collections::HashSet;
 
     #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1000,10 +1050,163 @@ pub mod history_sync {
                 ),
             ],
         )?;
+        // The Sync connection doesn't have the triggers that maintain the
+        // search index, so we need to update it ourselves.
+        update_search_index(db, &page_info.row_id.to_string())?;
 
         Ok(())
     }
 
+    /// The key we store a metadata entry's referrer URL under, in its unknown
+    /// fields, when we don't have the referrer page.
+    const UNRESOLVED_REFERRER_URL_KEY: &str = "referrerUrl";
+
+    /// Fetches the most recently updated metadata entries for a page, to
+    /// upload with its visits.
+    pub fn fetch_synced_metadata(
+        db: &PlacesDb,
+        place_id: RowId,
+        limit: usize,
+    ) -> Result<Vec<HistoryRecordMetadata>> {
+        db.query_rows_and_then_cached(
+            "SELECT m.created_at, m.updated_at, m.total_view_time, m.document_type,
+                    q.term AS search_term, r.url AS referrer_url, m.unknown_fields
+             FROM moz_places_metadata m
+             LEFT JOIN moz_places_metadata_search_queries q ON q.id = m.search_query_id
+             LEFT JOIN moz_places r ON r.id = m.referrer_place_id
+             WHERE m.place_id = :place_id
+             ORDER BY m.updated_at DESC
+             LIMIT :limit",
+            &[
+                (":place_id", &place_id as &dyn rusqlite::ToSql),
+                (":limit", &(limit as u32)),
+            ],
+            |row| -> Result<_> {
+                let mut unknown_fields = match row.get::<_, Option<String>>("unknown_fields")? {
+                    None => UnknownFields::new(),
+                    Some(v) => serde_json::from_str(&v)?,
+                };
+                // A referrer we didn't know about when we applied the entry is
+                // kept with the unknown fields, so that we upload it again.
+                let unresolved_referrer_url = unknown_fields
+                    .remove(UNRESOLVED_REFERRER_URL_KEY)
+                    .and_then(|v| v.as_str().map(ToOwned::to_owned));
+                Ok(HistoryRecordMetadata {
+                    created_at: row.get("created_at")?,
+                    updated_at: row.get("updated_at")?,
+                    total_view_time: row.get("total_view_time")?,
+                    search_term: row.get("search_term")?,
+                    referrer_url: row
+                        .get::<_, Option<String>>("referrer_url")?
+                        .or(unresolved_referrer_url),
+                    document_type: row.get("document_type")?,
+                    unknown_fields,
+                })
+            },
+        )
+    }
+
+    /// Apply history metadata from sync. Entries are identified by their page
+    /// and creation time; we add entries we don't have, and replace ones
+    /// that were updated more recently on another device. Like visits,
+    /// applying metadata doesn't change the page's sync change counter.
+    pub fn apply_synced_metadata(
+        db: &PlacesDb,
+        url: &Url,
+        metadata: &[HistoryRecordMetadata],
+    ) -> Result<()> {
+        if metadata.is_empty() {
+            return Ok(());
+        }
+        let place_id = match fetch_page_info(db, url)? {
+            Some(info) => info.page.row_id,
+            // We only apply metadata for pages we have visits for.
+            None => return Ok(()),
+        };
+        for entry in metadata {
+            let existing_updated_at = db.try_query_one::<i64, _>(
+                "SELECT updated_at FROM moz_places_metadata
+                 WHERE place_id = :place_id AND created_at = :created_at",
+                &[
+                    (":place_id", &place_id as &dyn rusqlite::ToSql),
+                    (":created_at", &entry.created_at),
+                ],
+                true,
+            )?;
+            if existing_updated_at.map_or(false, |updated_at| updated_at >= entry.updated_at) {
+                continue;
+            }
+            let search_query_id = match &entry.search_term {
+                Some(term) => {
+                    let term = term.to_lowercase();
+                    db.execute_cached(
+                        "INSERT OR IGNORE INTO moz_places_metadata_search_queries(term)
+                         VALUES (:term)",
+                        &[(":term", &term)],
+                    )?;
+                    Some(db.query_row_and_then_cachable(
+                        "SELECT id FROM moz_places_metadata_search_queries WHERE term = :term",
+                        &[(":term", &term)],
+                        |row| row.get::<_, i64>(0),
+                        true,
+                    )?)
+                }
+                None => None,
+            };
+            // The referrer might be a page we don't know about, or (weirdly)
+            // the page itself, which the schema doesn't allow.
+            let referrer_place_id = match entry
+                .referrer_url
+                .as_deref()
+                .and_then(|u| Url::parse(u).ok())
+            {
+                Some(referrer_url) => fetch_page_info(db, &referrer_url)?
+                    .map(|info| info.page.row_id)
+                    .filter(|id| *id != place_id),
+                None => None,
+            };
+            let mut unknown_fields = entry.unknown_fields.clone();
+            if referrer_place_id.is_none() {
+                if let Some(referrer_url) = &entry.referrer_url {
+                    unknown_fields.insert(
+                        UNRESOLVED_REFERRER_URL_KEY.to_owned(),
+                        referrer_url.clone().into(),
+                    );
+                }
+            }
+            let unknown_fields = serialize_unknown_fields(&unknown_fields)?;
+            let params: &[(&str, &dyn rusqlite::ToSql)] = &[
+                (":place_id", &place_id),
+                (":created_at", &entry.created_at),
+                (":updated_at", &entry.updated_at),
+                (":total_view_time", &entry.total_view_time),
+                (":search_query_id", &search_query_id),
+                (":referrer_place_id", &referrer_place_id),
+                (":document_type", &entry.document_type),
+                (":unknown_fields", &unknown_fields),
+            ];
+            let sql = if existing_updated_at.is_some() {
+                "UPDATE moz_places_metadata
+                 SET updated_at = :updated_at,
+                     total_view_time = :total_view_time,
+                     search_query_id = :search_query_id,
+                     referrer_place_id = :referrer_place_id,
+                     document_type = :document_type,
+                     unknown_fields = :unknown_fields
+                 WHERE place_id = :place_id AND created_at = :created_at"
+            } else {
+                "INSERT INTO moz_places_metadata
+                     (place_id, created_at, updated_at, total_view_time, search_query_id,
+                      referrer_place_id, document_type, unknown_fields)
+                 VALUES (:place_id, :created_at, :updated_at, :total_view_time,
+                         :search_query_id, :referrer_place_id, :document_type,
+                         :unknown_fields)"
+            };
+            db.execute_cached(sql, params)?;
+        }
+        Ok(())
+    }
+
     pub fn apply_synced_reconciliation(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
         db.execute_cached(
             "UPDATE moz_places
@@ -1019,6 +1222,11 @@ pub mod history_sync {
     }
 
     pub fn apply_synced_deletion(db: &PlacesDb, guid: &SyncGuid) -> Result<()> {
+        db.execute_cached(
+            "DELETE FROM moz_places_fts
+             WHERE rowid = (SELECT id FROM moz_places WHERE guid = :guid)",
+            &[(":guid", guid)],
+        )?;
         db.execute_cached(
             "DELETE FROM moz_places WHERE guid = :guid",
             &[(":guid", guid)],
@@ -1026,90 +1234,130 @@ pub mod history_sync {
         Ok(())
     }
 
-    pub fn fetch_outgoing(
-        db: &PlacesDb,
-        max_places: usize,
-        max_visits: usize,
-    ) -> Result<Vec<OutgoingBso>> {
+    /// Stages the tombstones and pages to upload, for `fetch_outgoing_chunk`
+    /// to turn into records. Returns the number of items staged.
+    pub fn stage_outgoing(db: &PlacesDb, max_places: usize) -> Result<usize> {
+        // We write the items to upload to a temp table, so that we can create
+        // their records a chunk at a time. We also write info about the records
+        // we are updating to a temp table. While we could carry this around in
+        // memory, we'll need a temp table in `finish_outgoing` anyway, because
+        // we execute a `NOT IN` query there - which, in a worst-case scenario,
+        // is a very large `NOT IN` set.
+        db.execute_all(&[
+            "CREATE TEMP TABLE IF NOT EXISTS temp_sync_outgoing
+                    (pos INTEGER PRIMARY KEY,
+                     guid TEXT NOT NULL,
+                     -- NULL for tombstones.
+                     place_id INTEGER)",
+            "DELETE FROM temp_sync_outgoing",
+            "CREATE TEMP TABLE IF NOT EXISTS temp_sync_updated_meta
+                    (id INTEGER PRIMARY KEY,
+                     change_delta INTEGER NOT NULL)",
+        ])?;
+
+        // We want to limit to 5000 places - tombstones are arguably the
+        // most important, so we stage these first.
+        let num_tombstones = db.execute_cached(
+            "INSERT INTO temp_sync_outgoing(guid, place_id)
+             SELECT guid, NULL FROM moz_places_tombstones
+             LIMIT :max_places",
+            &[(":max_places", &(max_places as u32))],
+        )?;
+
+        // Max records is now limited by how many tombstones we found.
         // Note that we want *all* "new" regardless of change counter,
         // so that we do the right thing after a "reset". We also
         // exclude hidden URLs from syncing, to match Desktop
-        // (bug 1173359).
-        let places_sql = format!(
-            "
+        // (bug 1173359). A page shouldn't be both live and a tombstone, but
+        // if it is, we only upload the tombstone.
+        let num_places = db.execute_cached(
+            &format!(
+                "INSERT INTO temp_sync_outgoing(guid, place_id)
+                 SELECT guid, id FROM moz_places
+                 WHERE (sync_change_counter > 0 OR sync_status != {}) AND
+                       NOT hidden AND
+                       guid NOT IN (SELECT guid FROM moz_places_tombstones)
+                 ORDER BY frecency DESC
+                 LIMIT :max_places",
+                (SyncStatus::Normal as u8)
+            ),
+            &[(":max_places", &((max_places - num_tombstones) as u32))],
+        )?;
+        Ok(num_tombstones + num_places)
+    }
+
+    /// Creates records for up to `limit` of the items staged by
+    /// `stage_outgoing`, and removes them from the staging table. Returns
+    /// `None` once every staged item has been fetched. The returned chunk
+    /// might have fewer than `limit` records, or even be empty, if some
+    /// staged pages don't have visits to upload.
+    pub fn fetch_outgoing_chunk(
+        db: &PlacesDb,
+        max_visits: usize,
+        limit: usize,
+    ) -> Result<Option<Vec<OutgoingBso>>> {
+        let staged = db.query_rows_and_then_cached(
+            "SELECT pos, guid, place_id FROM temp_sync_outgoing
+             ORDER BY pos
+             LIMIT :limit",
+            &[(":limit", &(limit as u32))],
+            |row| -> rusqlite::Result<(i64, SyncGuid, Option<RowId>)> {
+                Ok((
+                    row.get("pos")?,
+                    row.get::<_, String>("guid")?.into(),
+                    row.get("place_id")?,
+                ))
+            },
+        )?;
+        let last_pos = match staged.last() {
+            Some((pos, _, _)) => *pos,
+            None => return Ok(None),
+        };
+
+        let page_sql = "
             SELECT guid, url, id, title, hidden, typed, frecency,
                 visit_count_local, visit_count_remote,
                 last_visit_date_local, last_visit_date_remote,
                 sync_status, sync_change_counter, preview_image_url,
                 unknown_fields
             FROM moz_places
-            WHERE (sync_change_counter > 0 OR sync_status != {}) AND
-                  NOT hidden
-            ORDER BY frecency DESC
-            LIMIT :max_places",
-            (SyncStatus::Normal as u8)
-        );
+            WHERE id = :place_id";
         let visits_sql = "
             SELECT visit_date as date, visit_type as transition, unknown_fields
             FROM moz_historyvisits
             WHERE place_id = :place_id
             ORDER BY visit_date DESC
             LIMIT :max_visits";
-        // tombstones
-        let tombstones_sql = "SELECT guid FROM moz_places_tombstones LIMIT :max_places";
-
-        let mut tombstone_ids = HashSet::new();
-        let mut result = Vec::new();
-
-        // We want to limit to 5000 places - tombstones are arguably the
-        // most important, so we fetch these first.
-        let ts_rows = db.query_rows_and_then(
-            tombstones_sql,
-            &[(":max_places", &(max_places as u32))],
-            |row| -> rusqlite::Result<SyncGuid> { Ok(row.get::<_, String>("guid")?.into()) },
-        )?;
-        // It's unfortunatee that query_rows_and_then returns a Vec instead of an iterator
-        // (which would be very hard to do), but as long as we have it, we might as well make use
-        // of it...
-        result.reserve(ts_rows.len());
-        tombstone_ids.reserve(ts_rows.len());
-        for guid in ts_rows {
-            log::trace!("outgoing tombstone {:?}", &guid);
-            let envelope = OutgoingEnvelope {
-                id: guid.clone(),
-                ttl: Some(HISTORY_TTL),
-                ..Default::default()
-            };
-            result.push(OutgoingBso::new_tombstone(envelope));
-            tombstone_ids.insert(guid);
-        }
-
-        // Max records is now limited by how many tombstones we found.
-        let max_places_left = max_places - result.len();
-
-        // We write info about the records we are updating to a temp table.
-        // While we could carry this around in memory, we'll need a temp table
-        // in `finish_outgoing` anyway, because we execute a `NOT IN` query
-        // there - which, in a worst-case scenario, is a very large `NOT IN`
-        // set.
-        db.execute(
-            "CREATE TEMP TABLE IF NOT EXISTS temp_sync_updated_meta
-                    (id INTEGER PRIMARY KEY,
-                     change_delta INTEGER NOT NULL)",
-            [],
-        )?;
-
         let insert_meta_sql = "
             INSERT INTO temp_sync_updated_meta VALUES (:row_id, :change_delta)";
 
-        let rows = db.query_rows_and_then(
-            &places_sql,
-            &[(":max_places", &(max_places_left as u32))],
-            PageInfo::from_row,
-        )?;
-        result.reserve(rows.len());
-        let mut ids_to_update = Vec::with_capacity(rows.len());
-        for page in rows {
+        let mut result = Vec::with_capacity(staged.len());
+        let mut ids_to_update = Vec::with_capacity(staged.len());
+        for (_, guid, place_id) in staged {
+            let place_id = match place_id {
+                Some(place_id) => place_id,
+                None => {
+                    log::trace!("outgoing tombstone {:?}", &guid);
+                    let envelope = OutgoingEnvelope {
+                        id: guid,
+                        ttl: Some(HISTORY_TTL),
+                        ..Default::default()
+                    };
+                    result.push(OutgoingBso::new_tombstone(envelope));
+                    continue;
+                }
+            };
+            // The page might have been removed since we staged it, in which
+            // case it will have a tombstone to upload next time.
+            let page = match db.try_query_row(
+                page_sql,
+                &[(":place_id", &place_id)],
+                PageInfo::from_row,
+                true,
+            )? {
+                Some(page) => page,
+                None => continue,
+            };
             let visits = db.query_rows_and_then_cached(
                 visits_sql,
                 &[
@@ -1127,11 +1375,6 @@ pub mod history_sync {
                     })
                 },
             )?;
-            if tombstone_ids.contains(&page.guid) {
-                // should be impossible!
-                log::warn!("Found {:?} in both tombstones and live records", &page.guid);
-                continue;
-            }
             if visits.is_empty() {
                 // This will be true for things like bookmarks which haven't
                 // had visits locally applied, and if we later prune old visits
@@ -1142,6 +1385,7 @@ pub mod history_sync {
                 );
                 continue;
             }
+            let metadata = fetch_synced_metadata(db, page.row_id, max_visits)?;
             log::trace!("outgoing record {:?}", &page.guid);
             ids_to_update.push(page.row_id);
             db.execute_cached(
@@ -1157,6 +1401,7 @@ pub mod history_sync {
                 title: page.title,
                 hist_uri: page.url.to_string(),
                 visits,
+                metadata,
                 unknown_fields: page.unknown_fields,
             };
 
@@ -1186,6 +1431,27 @@ pub mod history_sync {
             Ok(())
         })?;
 
+        db.execute_cached(
+            "DELETE FROM temp_sync_outgoing WHERE pos <= :last_pos",
+            &[(":last_pos", &last_pos)],
+        )?;
+
+        Ok(Some(result))
+    }
+
+    /// Fetches all the records to upload at once. Syncs use
+    /// `stage_outgoing` and `fetch_outgoing_chunk` instead, to avoid holding
+    /// every outgoing record in memory.
+    pub fn fetch_outgoing(
+        db: &PlacesDb,
+        max_places: usize,
+        max_visits: usize,
+    ) -> Result<Vec<OutgoingBso>> {
+        stage_outgoing(db, max_places)?;
+        let mut result = Vec::new();
+        while let Some(chunk) = fetch_outgoing_chunk(db, max_visits, max_places)? {
+            result.extend(chunk);
+        }
         Ok(result)
     }
 
@@ -2048,6 +2314,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_fetch_outgoing_chunks() -> Result<()> {
+        let _ = env_logger::try_init();
+        let mut conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite)?;
+        let pi = get_observed_page(&mut conn, "http://example.com/1")?;
+        let pi2 = get_observed_page(&mut conn, "http://example.com/2")?;
+        conn.execute_cached(
+            "INSERT INTO moz_places_tombstones(guid) VALUES (:guid)",
+            &[(":guid", &"tombstoneAAA")],
+        )?;
+
+        assert_eq!(stage_outgoing(&conn, 100)?, 3);
+        // Tombstones are staged first.
+        let chunk = fetch_outgoing_chunk(&conn, 10, 2)?.expect("should have a chunk");
+        assert_eq!(chunk.len(), 2);
+        assert_eq!(chunk[0].envelope.id, "tombstoneAAA");
+        let chunk = fetch_outgoing_chunk(&conn, 10, 2)?.expect("should have a chunk");
+        assert_eq!(chunk.len(), 1);
+        assert!(fetch_outgoing_chunk(&conn, 10, 2)?.is_none());
+
+        // Both pages were marked as synced as their records were created.
+        for page in [pi, pi2] {
+            let page = fetch_page_info(&conn, &page.url)?
+                .expect("page should exist")
+                .page;
+            assert_eq!(page.sync_status, SyncStatus::Normal);
+        }
+        finish_outgoing(&conn)?;
+        assert_eq!(get_tombstone_count(&conn), 0);
+        Ok(())
+    }
+
     #[test]
     fn test_delete_visits_for() -> Result<()> {
         use crate::storage::bookmarks::{
@@ -123,6 +123,20 @@ impl HistoryMetadata {
     }
 }
 
+/// A group of related history metadata, like pages reached from the same
+/// search, or by following links from one page to the next.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryJourney {
+    pub id: i64,
+    /// The journey's search term, or the title (or URL) of its first page.
+    pub title: String,
+    pub search_term: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// The journey's metadata, oldest first.
+    pub items: Vec<HistoryMetadata>,
+}
+
 enum PlaceEntry {
     Existing(i64),
     CreateFor(Url, Option<String>),
@@ -323,6 +337,11 @@ impl HistoryMetadataCompoundKey {
 
 const DEBOUNCE_WINDOW_MS: i64 = 2 * 60 * 1000; // 2 minutes
 const MAX_QUERY_RESULTS: i32 = 1000;
+// Metadata observed this long after the last activity in a journey starts a new one.
+const JOURNEY_WINDOW_MS: i64 = 30 * 60 * 1000; // 30 minutes
+
+// Single pages aren't much of a journey, so we don't return them.
+const MIN_JOURNEY_ITEMS: i64 = 2;
 
 const COMMON_METADATA_SELECT: &str = "
 SELECT
@@ -415,6 +434,13 @@ lazy_static! {
         common_select_sql = COMMON_METADATA_SELECT,
         max_limit = MAX_QUERY_RESULTS
     );
+    static ref GET_JOURNEY_ITEMS_SQL: String = format!(
+        "{common_select_sql}
+        JOIN moz_places_metadata_group_items i ON i.metadata_id = m.id
+        WHERE i.group_id = :group_id
+        ORDER BY created_at, metadata_id",
+        common_select_sql = COMMON_METADATA_SELECT
+    );
     static ref QUERY_SQL: String = format!(
         "{common_select_sql}
         WHERE
@@ -485,13 +511,15 @@ pub fn query(db: &PlacesDb, query: &str, limit: i32) -> Result<Vec<HistoryMetada
     )
 }
 
-pub fn delete_older_than(db: &PlacesDb, older_than: i64) -> Result<()> {
-    db.execute_cached(
+/// Deletes metadata last updated before `older_than`. Returns the number of
+/// entries deleted.
+pub fn delete_older_than(db: &PlacesDb, older_than: i64) -> Result<u32> {
+    let deleted = db.execute_cached(
         "DELETE FROM moz_places_metadata
          WHERE updated_at < :older_than",
         &[(":older_than", &older_than)],
     )?;
-    Ok(())
+    Ok(deleted as u32)
 }
 
 pub fn delete_between(db: &PlacesDb, start: i64, end: i64) -> Result<()> {
@@ -689,12 +717,29 @@ fn apply_metadata_observation_impl(
                     )?;
                 }
             }
+            // The lookup only matches metadata for pages that exist.
+            if let PlaceEntry::Existing(place_id) = compound_key.place_entry {
+                bump_sync_change_counter(tx, place_id)?;
+            }
             Ok(())
         }
         None => insert_metadata_in_tx(tx, compound_key, observation),
     }
 }
 
+// Metadata is uploaded with the page's visits, so local changes to it need to
+// bump the page's sync change counter, just like a new visit does. Metadata
+// applied from sync is written directly, and doesn't come through here.
+fn bump_sync_change_counter(tx: &PlacesTransaction<'_>, place_id: i64) -> Result<()> {
+    tx.execute_cached(
+        "UPDATE moz_places
+         SET sync_change_counter = sync_change_counter + 1
+         WHERE id = :place_id",
+        &[(":place_id", &place_id)],
+    )?;
+    Ok(())
+}
+
 fn insert_metadata_in_tx(
     tx: &PlacesTransaction<'_>,
     key: HistoryMetadataCompoundKey,
@@ -736,7 +781,190 @@ fn insert_metadata_in_tx(
             (":total_view_time", &observation.view_time.unwrap_or(0)),
         ],
     )?;
+    add_to_journey(
+        tx,
+        tx.conn().last_insert_rowid(),
+        place_id,
+        referrer_place_id,
+        search_query_id,
+        now.as_millis_i64(),
+    )?;
+    bump_sync_change_counter(tx, place_id)?;
+
+    Ok(())
+}
+
+/// Returns journeys with activity since `since`, most recent first.
+pub fn get_journeys(db: &PlacesDb, since: i64, limit: i32) -> Result<Vec<HistoryJourney>> {
+    let journeys = db.query_rows_and_then_cached(
+        "SELECT g.id, s.term AS search_term,
+                MIN(m.created_at) AS created_at, MAX(m.updated_at) AS updated_at
+         FROM moz_places_metadata_groups g
+         JOIN moz_places_metadata_group_items i ON i.group_id = g.id
+         JOIN moz_places_metadata m ON m.id = i.metadata_id
+         LEFT JOIN moz_places_metadata_search_queries s ON s.id = g.search_query_id
+         GROUP BY g.id
+         HAVING COUNT(*) >= :min_items AND MAX(m.updated_at) >= :since
+         ORDER BY updated_at DESC, g.id DESC
+         LIMIT :limit",
+        rusqlite::named_params! {
+            ":min_items": MIN_JOURNEY_ITEMS,
+            ":since": since,
+            ":limit": limit,
+        },
+        |row| -> Result<_> {
+            Ok((
+                row.get::<_, i64>("id")?,
+                row.get::<_, Option<String>>("search_term")?,
+                row.get::<_, i64>("created_at")?,
+                row.get::<_, i64>("updated_at")?,
+            ))
+        },
+    )?;
+    journeys
+        .into_iter()
+        .map(
+            |(id, search_term, created_at, updated_at)| -> Result<HistoryJourney> {
+                let items: Vec<HistoryMetadata> = db.query_rows_and_then_cached(
+                    GET_JOURNEY_ITEMS_SQL.as_str(),
+                    rusqlite::named_params! { ":group_id": id },
+                    HistoryMetadata::from_row,
+                )?;
+                let title = match (&search_term, items.first()) {
+                    (Some(term), _) => term.clone(),
+                    (None, Some(first)) => first
+                        .title
+                        .clone()
+                        .filter(|title| !title.is_empty())
+                        .unwrap_or_else(|| first.url.clone()),
+                    (None, None) => String::new(),
+                };
+                Ok(HistoryJourney {
+                    id,
+                    title,
+                    search_term,
+                    created_at,
+                    updated_at,
+                    items,
+                })
+            },
+        )
+        .collect()
+}
+
+/// Assigns metadata which isn't part of a journey yet to one, in the order it
+/// was observed. Local metadata is assigned when it's noted, so this only
+/// needs to run for synced metadata, and is called during maintenance.
+pub fn update_journeys(db: &PlacesDb) -> Result<()> {
+    let tx = db.begin_transaction()?;
+    let ungrouped = db.query_rows_and_then(
+        "SELECT m.id, m.place_id, m.referrer_place_id, m.search_query_id, m.created_at
+         FROM moz_places_metadata m
+         WHERE NOT EXISTS (SELECT 1 FROM moz_places_metadata_group_items
+                           WHERE metadata_id = m.id)
+         ORDER BY m.created_at, m.id",
+        [],
+        |row| -> Result<_> {
+            Ok((
+                row.get::<_, i64>("id")?,
+                row.get::<_, i64>("place_id")?,
+                row.get::<_, Option<i64>>("referrer_place_id")?,
+                row.get::<_, Option<i64>>("search_query_id")?,
+                row.get::<_, i64>("created_at")?,
+            ))
+        },
+    )?;
+    for (metadata_id, place_id, referrer_place_id, search_query_id, created_at) in ungrouped {
+        add_to_journey(
+            &tx,
+            metadata_id,
+            place_id,
+            referrer_place_id,
+            search_query_id,
+            created_at,
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
 
+/// Returns true if there's metadata that isn't part of a journey yet.
+pub fn has_ungrouped_metadata(db: &PlacesDb) -> Result<bool> {
+    Ok(db.exists(
+        "SELECT 1 FROM moz_places_metadata m
+         WHERE NOT EXISTS (SELECT 1 FROM moz_places_metadata_group_items
+                           WHERE metadata_id = m.id)",
+        [],
+    )?)
+}
+
+// Adds metadata to the journey it belongs to, or starts a new one. Existing
+// assignments never change, so journeys are stable.
+fn add_to_journey(
+    tx: &PlacesTransaction<'_>,
+    metadata_id: i64,
+    place_id: i64,
+    referrer_place_id: Option<i64>,
+    search_query_id: Option<i64>,
+    created_at: i64,
+) -> Result<()> {
+    let newer_than = created_at - JOURNEY_WINDOW_MS;
+    let group_id = match search_query_id {
+        // Pages for the same search belong together, and a new search
+        // starts a new journey.
+        Some(search_query_id) => tx.try_query_one::<i64, _>(
+            "SELECT g.id
+             FROM moz_places_metadata_groups g
+             JOIN moz_places_metadata_group_items i ON i.group_id = g.id
+             JOIN moz_places_metadata m ON m.id = i.metadata_id
+             WHERE g.search_query_id = :search_query_id
+             GROUP BY g.id
+             HAVING MAX(m.updated_at) >= :newer_than
+             ORDER BY MAX(m.updated_at) DESC
+             LIMIT 1",
+            rusqlite::named_params! {
+                ":search_query_id": search_query_id,
+                ":newer_than": newer_than,
+            },
+            true,
+        )?,
+        // Otherwise, follow the referrer back to its journey, or rejoin
+        // the journey the page is already part of.
+        None => tx.try_query_one::<i64, _>(
+            "SELECT i.group_id
+             FROM moz_places_metadata_group_items i
+             JOIN moz_places_metadata m ON m.id = i.metadata_id
+             WHERE m.place_id IN (:place_id, :referrer_place_id)
+               AND m.updated_at >= :newer_than
+             ORDER BY m.updated_at DESC
+             LIMIT 1",
+            rusqlite::named_params! {
+                ":place_id": place_id,
+                ":referrer_place_id": referrer_place_id,
+                ":newer_than": newer_than,
+            },
+            true,
+        )?,
+    };
+    let group_id = match group_id {
+        Some(id) => id,
+        None => {
+            tx.execute_cached(
+                "INSERT INTO moz_places_metadata_groups(search_query_id)
+                 VALUES (:search_query_id)",
+                rusqlite::named_params! { ":search_query_id": search_query_id },
+            )?;
+            tx.conn().last_insert_rowid()
+        }
+    };
+    tx.execute_cached(
+        "INSERT INTO moz_places_metadata_group_items(metadata_id, group_id)
+         VALUES (:metadata_id, :group_id)",
+        rusqlite::named_params! {
+            ":metadata_id": metadata_id,
+            ":group_id": group_id,
+        },
+    )?;
     Ok(())
 }
 
@@ -1884,6 +2112,177 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_journeys() {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).expect("memory db");
+        let serp = "https://www.google.com/search?q=cats";
+
+        // Two results for the same search, and a page linked from one of them.
+        note_observation!(&conn,
+            url "https://www.example.com/cats",
+            view_time Some(20000),
+            search_term Some("cats"),
+            document_type None,
+            referrer_url Some(serp),
+            title Some("Cats")
+        );
+        note_observation!(&conn,
+            url "https://www.example.com/more-cats",
+            view_time Some(20000),
+            search_term Some("Cats"),
+            document_type None,
+            referrer_url Some(serp),
+            title None
+        );
+        note_observation!(&conn,
+            url "https://www.example.com/kittens",
+            view_time Some(20000),
+            search_term None,
+            document_type None,
+            referrer_url Some("https://www.example.com/more-cats"),
+            title None
+        );
+        // An unrelated chain of links.
+        note_observation!(&conn,
+            url "https://news.example.com/",
+            view_time Some(20000),
+            search_term None,
+            document_type None,
+            referrer_url None,
+            title Some("News")
+        );
+        note_observation!(&conn,
+            url "https://news.example.com/story",
+            view_time Some(20000),
+            search_term None,
+            document_type None,
+            referrer_url Some("https://news.example.com/"),
+            title None
+        );
+        // A page on its own isn't a journey.
+        note_observation!(&conn,
+            url "https://www.mozilla.org/",
+            view_time Some(20000),
+            search_term None,
+            document_type None,
+            referrer_url None,
+            title None
+        );
+
+        let journeys = get_journeys(&conn, 0, 10).expect("should get journeys");
+        assert_eq!(journeys.len(), 2);
+        assert_eq!(journeys[0].title, "News");
+        assert_eq!(journeys[0].search_term, None);
+        assert_eq!(
+            journeys[0]
+                .items
+                .iter()
+                .map(|m| m.url.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "https://news.example.com/",
+                "https://news.example.com/story"
+            ]
+        );
+        assert_eq!(journeys[1].title, "cats");
+        assert_eq!(journeys[1].search_term, Some("cats".to_string()));
+        assert_eq!(
+            journeys[1]
+                .items
+                .iter()
+                .map(|m| m.url.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "https://www.example.com/cats",
+                "https://www.example.com/more-cats",
+                "https://www.example.com/kittens"
+            ]
+        );
+
+        // Journeys are stable between calls.
+        assert_eq!(get_journeys(&conn, 0, 10).unwrap(), journeys);
+        assert_eq!(get_journeys(&conn, 0, 1).unwrap(), journeys[..1].to_vec());
+
+        // Searching for the same thing much later starts a new journey.
+        conn.execute_cached(
+            "UPDATE moz_places_metadata
+             SET created_at = created_at - :age, updated_at = updated_at - :age",
+            rusqlite::named_params! { ":age": 2 * JOURNEY_WINDOW_MS },
+        )
+        .expect("should backdate metadata");
+        let now = Timestamp::now().as_millis() as i64;
+        note_observation!(&conn,
+            url "https://www.example.com/cats",
+            view_time Some(20000),
+            search_term Some("cats"),
+            document_type None,
+            referrer_url None,
+            title None
+        );
+        note_observation!(&conn,
+            url "https://www.example.com/cat-food",
+            view_time Some(20000),
+            search_term Some("cats"),
+            document_type None,
+            referrer_url None,
+            title None
+        );
+        let recent = get_journeys(&conn, now, 10).expect("should get journeys");
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].title, "cats");
+        assert_eq!(recent[0].items.len(), 2);
+        assert!(journeys.iter().all(|j| j.id != recent[0].id));
+        assert_eq!(get_journeys(&conn, 0, 10).unwrap().len(), 3);
+
+        // Journeys are deleted along with their metadata.
+        delete_older_than(&conn, now).expect("should delete metadata");
+        assert_eq!(get_journeys(&conn, 0, 10).unwrap(), recent);
+        assert_table_size!(&conn, "moz_places_metadata_groups", 1);
+    }
+
+    #[test]
+    fn test_journeys_for_synced_metadata() {
+        let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).expect("memory db");
+        note_observation!(&conn,
+            url "https://news.example.com/",
+            view_time Some(20000),
+            search_term None,
+            document_type None,
+            referrer_url None,
+            title Some("News")
+        );
+        note_observation!(&conn,
+            url "https://news.example.com/story",
+            view_time Some(20000),
+            search_term None,
+            document_type None,
+            referrer_url Some("https://news.example.com/"),
+            title None
+        );
+        assert!(!has_ungrouped_metadata(&conn).unwrap());
+        assert_eq!(get_journeys(&conn, 0, 10).unwrap().len(), 1);
+
+        // Synced metadata isn't grouped until maintenance runs.
+        conn.execute_batch(
+            "DELETE FROM moz_places_metadata_group_items;
+             DELETE FROM moz_places_metadata_groups;",
+        )
+        .expect("should ungroup metadata");
+        assert!(has_ungrouped_metadata(&conn).unwrap());
+        assert!(get_journeys(&conn, 0, 10).unwrap().is_empty());
+
+        let metrics = crate::storage::maintenance::run_maintenance(&conn, &Default::default())
+            .expect("should run maintenance");
+        assert!(metrics
+            .steps_run
+            .contains(&crate::storage::maintenance::MaintenanceStep::GroupJourneys));
+        assert!(!has_ungrouped_metadata(&conn).unwrap());
+        let journeys = get_journeys(&conn, 0, 10).unwrap();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].title, "News");
+        assert_eq!(journeys[0].items.len(), 2);
+    }
+
     #[test]
     fn test_delete_between_also_deletes_metadata() -> Result<()> {
         let conn = PlacesDb::open_in_memory(ConnectionType::ReadWrite).expect("memory db");
@@ -0,0 +1,255 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Keywords are shortcuts for URLs: typing the keyword into the address bar
+// loads the URL. A URL can have at most one keyword, and a keyword can only
+// be used for one URL. Sync stores keywords on bookmarks, so changing a
+// keyword flags all bookmarks for the URL for upload.
+
+use super::fetch_page_info;
+use crate::db::PlacesDb;
+use crate::error::{InvalidPlaceInfo, Result};
+use sql_support::ConnExt;
+use url::Url;
+
+/// Normalizes a keyword the same way Desktop and Sync do, by removing
+/// leading and trailing whitespace and lowercasing it. Returns an error if
+/// the keyword is empty or contains whitespace.
+pub fn validate_keyword(keyword: &str) -> Result<String> {
+    let keyword = keyword.trim().to_lowercase();
+    if keyword.is_empty() || keyword.contains(char::is_whitespace) {
+        return Err(InvalidPlaceInfo::InvalidKeyword.into());
+    }
+    Ok(keyword)
+}
+
+/// Sets the keyword for the specified URL, replacing its existing keyword.
+/// If another URL has the keyword, it's moved to this URL.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// * `url` - The URL for the keyword.
+///
+/// * `keyword` - The keyword to set for the URL.
+///
+/// # Returns
+///
+/// There is no success return value.
+pub fn set_keyword_for_url(db: &PlacesDb, url: &Url, keyword: &str) -> Result<()> {
+    let keyword = validate_keyword(keyword)?;
+    let tx = db.begin_transaction()?;
+    set_keyword_for_url_in_tx(db, url, &keyword)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Like `set_keyword_for_url`, but for a keyword that's already been
+/// validated, in an existing transaction.
+pub(crate) fn set_keyword_for_url_in_tx(db: &PlacesDb, url: &Url, keyword: &str) -> Result<()> {
+    // Like tags, this function will not create a new place.
+    let place_id = match fetch_page_info(db, url)? {
+        Some(info) => info.page.row_id,
+        None => return Err(InvalidPlaceInfo::NoSuchUrl.into()),
+    };
+
+    db.execute_cached(
+        "DELETE FROM moz_keywords
+         WHERE keyword = :keyword AND place_id <> :place_id",
+        &[
+            (":keyword", &keyword as &dyn rusqlite::ToSql),
+            (":place_id", &place_id),
+        ],
+    )?;
+    // We use an upsert instead of `REPLACE`, because `REPLACE` doesn't fire
+    // the delete triggers that maintain the foreign count.
+    db.execute_cached(
+        "INSERT INTO moz_keywords(place_id, keyword)
+         VALUES(:place_id, :keyword)
+         ON CONFLICT(place_id) DO UPDATE SET
+           keyword = excluded.keyword
+         WHERE keyword <> excluded.keyword",
+        &[
+            (":place_id", &place_id as &dyn rusqlite::ToSql),
+            (":keyword", &keyword),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Retrieves the keyword for the specified URL.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// * `url` - The URL to query.
+///
+/// # Returns
+///
+/// * The keyword, or `None` if the URL doesn't have one.
+pub fn get_keyword_for_url(db: &PlacesDb, url: &Url) -> Result<Option<String>> {
+    Ok(db.try_query_one(
+        "SELECT k.keyword
+         FROM moz_keywords k
+         JOIN moz_places h ON h.id = k.place_id
+         WHERE h.url_hash = hash(:url) AND h.url = :url",
+        &[(":url", &url.as_str())],
+        true,
+    )?)
+}
+
+/// Removes the specified keyword.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// * `keyword` - The keyword to remove.
+///
+/// # Returns
+///
+/// There is no success return value - the operation is ignored if the
+/// keyword doesn't exist.
+pub fn remove_keyword(db: &PlacesDb, keyword: &str) -> Result<()> {
+    let keyword = validate_keyword(keyword)?;
+    db.execute_cached(
+        "DELETE FROM moz_keywords
+         WHERE keyword = :keyword",
+        &[(":keyword", &keyword)],
+    )?;
+    Ok(())
+}
+
+/// Removes the keyword for the specified URL.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// * `url` - The URL from which the keyword should be removed.
+///
+/// # Returns
+///
+/// There is no success return value - the operation is ignored if the URL
+/// doesn't have a keyword.
+pub fn remove_keyword_for_url(db: &PlacesDb, url: &Url) -> Result<()> {
+    db.execute_cached(
+        "DELETE FROM moz_keywords
+         WHERE place_id = (SELECT id FROM moz_places
+                           WHERE url_hash = hash(:url)
+                           AND url = :url)",
+        &[(":url", &url.as_str())],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::storage::bookmarks::{
+        bookmarks_get_url_for_keyword, insert_bookmark, BookmarkPosition, BookmarkRootGuid,
+        InsertableBookmark,
+    };
+    use crate::storage::new_page_info;
+
+    fn get_foreign_count(db: &PlacesDb, url: &Url) -> i32 {
+        db.query_row_and_then_cachable(
+            "SELECT foreign_count FROM moz_places WHERE url = :url",
+            &[(":url", &url.as_str())],
+            |row| row.get(0),
+            false,
+        )
+        .expect("should get a value")
+    }
+
+    #[test]
+    fn test_validate_keyword() {
+        assert_eq!(validate_keyword("foo").unwrap(), "foo");
+        assert_eq!(validate_keyword(" FoO ").unwrap(), "foo");
+        assert!(validate_keyword("").is_err());
+        assert!(validate_keyword("   ").is_err());
+        assert!(validate_keyword("foo bar").is_err());
+    }
+
+    #[test]
+    fn test_keywords() -> Result<()> {
+        let conn = new_mem_connection();
+        let url1 = Url::parse("http://example.com").expect("valid url");
+        let url2 = Url::parse("http://example2.com").expect("valid url");
+        let missing = Url::parse("http://missing.example.com").expect("valid url");
+        new_page_info(&conn, &url1, None)?;
+        new_page_info(&conn, &url2, None)?;
+
+        assert!(set_keyword_for_url(&conn, &missing, "foo").is_err());
+
+        set_keyword_for_url(&conn, &url1, "Foo")?;
+        assert_eq!(get_keyword_for_url(&conn, &url1)?, Some("foo".to_string()));
+        assert_eq!(
+            bookmarks_get_url_for_keyword(&conn, "foo")?,
+            Some(url1.clone())
+        );
+        assert_eq!(get_foreign_count(&conn, &url1), 1);
+
+        // Changing the keyword for a URL replaces the old one.
+        set_keyword_for_url(&conn, &url1, "bar")?;
+        assert_eq!(get_keyword_for_url(&conn, &url1)?, Some("bar".to_string()));
+        assert_eq!(bookmarks_get_url_for_keyword(&conn, "foo")?, None);
+        assert_eq!(get_foreign_count(&conn, &url1), 1);
+
+        // Setting an existing keyword for a different URL moves it.
+        set_keyword_for_url(&conn, &url2, "bar")?;
+        assert_eq!(get_keyword_for_url(&conn, &url1)?, None);
+        assert_eq!(get_keyword_for_url(&conn, &url2)?, Some("bar".to_string()));
+        assert_eq!(get_foreign_count(&conn, &url1), 0);
+        assert_eq!(get_foreign_count(&conn, &url2), 1);
+
+        remove_keyword(&conn, "BAR")?;
+        assert_eq!(get_keyword_for_url(&conn, &url2)?, None);
+        assert_eq!(get_foreign_count(&conn, &url2), 0);
+
+        set_keyword_for_url(&conn, &url1, "foo")?;
+        remove_keyword_for_url(&conn, &url1)?;
+        assert_eq!(get_keyword_for_url(&conn, &url1)?, None);
+        assert_eq!(get_foreign_count(&conn, &url1), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_keywords_bump_change_counter() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("http://example.com").expect("valid url");
+        let guid = insert_bookmark(
+            &conn,
+            InsertableBookmark {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: url.clone(),
+                title: None,
+            }
+            .into(),
+        )?;
+        let change_counter = || -> Result<i64> {
+            Ok(conn.query_row_and_then_cachable(
+                "SELECT syncChangeCounter FROM moz_bookmarks WHERE guid = :guid",
+                &[(":guid", &guid)],
+                |row| row.get(0),
+                false,
+            )?)
+        };
+        let initial = change_counter()?;
+        set_keyword_for_url(&conn, &url, "foo")?;
+        assert_eq!(change_counter()?, initial + 1);
+        set_keyword_for_url(&conn, &url, "bar")?;
+        assert_eq!(change_counter()?, initial + 2);
+        remove_keyword_for_url(&conn, &url)?;
+        assert_eq!(change_counter()?, initial + 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,498 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// A single entry point for database maintenance. Instead of every app
+// calling and tuning the `run_maintenance_*()` functions, `run_maintenance`
+// remembers when each step last ran in `moz_meta`, decides which steps are
+// due, and runs as many of them as fit in the caller's time budget. Steps
+// that don't fit are left for the next call.
+
+use super::{
+    delete_meta, favicons, get_meta, history, history_metadata, put_meta,
+    recalculate_stale_frecencies, run_maintenance_checkpoint, run_maintenance_optimize,
+    run_maintenance_vacuum,
+};
+use crate::db::PlacesDb;
+use crate::error::Result;
+use std::time::{Duration, Instant};
+use types::Timestamp;
+
+// How often each step runs. Pruning because the database is too big and
+// checkpointing happen on every call.
+const EXPIRATION_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+const VACUUM_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+const OPTIMIZE_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
+// The `moz_meta` keys for when each step last finished.
+const LAST_EXPIRATION_META_KEY: &str = "maintenance_last_expiration";
+const LAST_VACUUM_META_KEY: &str = "maintenance_last_vacuum";
+const LAST_OPTIMIZE_META_KEY: &str = "maintenance_last_optimize";
+
+// The number of rows removed, or frecencies recalculated, in each
+// transaction. Keeping batches small lets us check the budget often.
+const BATCH_SIZE: u32 = 100;
+
+/// Limits and expiration policy for `run_maintenance`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceBudget {
+    /// The approximate amount of time maintenance may take. Steps aren't
+    /// started once it's used up, and removals stop between batches.
+    pub max_duration_ms: u32,
+    /// The approximate storage limit in bytes. If the database is using more
+    /// space than this, older visits are pruned. 0 means no limit.
+    pub db_size_limit: u32,
+    /// The maximum number of visits to remove in a single call, both when
+    /// pruning for size and when expiring old visits.
+    pub prune_limit: u32,
+    /// Visits older than this many days are removed. 0 keeps visits forever.
+    pub visit_expiration_days: u32,
+    /// History metadata that hasn't been updated for this many days is
+    /// removed. 0 keeps metadata forever.
+    pub metadata_expiration_days: u32,
+}
+
+impl Default for MaintenanceBudget {
+    fn default() -> Self {
+        Self {
+            max_duration_ms: 500,
+            db_size_limit: 0,
+            prune_limit: 100,
+            visit_expiration_days: 0,
+            metadata_expiration_days: 0,
+        }
+    }
+}
+
+/// The steps `run_maintenance` can take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceStep {
+    /// Removing visits, metadata, orphaned pages and favicons according to
+    /// the expiration policy.
+    Expire,
+    /// Pruning older visits because the database is over its size limit.
+    Prune,
+    /// Recalculating stale frecencies.
+    RecalculateFrecencies,
+    /// Grouping synced history metadata into journeys.
+    GroupJourneys,
+    Vacuum,
+    Optimize,
+    Checkpoint,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MaintenanceMetrics {
+    /// The steps that were run, in order. A step that ran out of time is
+    /// included, and will continue on the next call.
+    pub steps_run: Vec<MaintenanceStep>,
+    /// The steps that were due, but weren't started because the budget was
+    /// used up.
+    pub steps_skipped: Vec<MaintenanceStep>,
+    /// The number of visits removed, either because they expired or because
+    /// the database was too big.
+    pub pruned_visits: u32,
+    /// The number of orphaned pages removed.
+    pub removed_pages: u32,
+    /// The number of history metadata entries removed.
+    pub removed_metadata: u32,
+    pub recalculated_frecencies: u32,
+    pub db_size_before: u32,
+    pub db_size_after: u32,
+    pub duration_ms: u32,
+    /// Whether maintenance stopped early because it used up its budget.
+    pub budget_exhausted: bool,
+}
+
+struct Deadline(Instant);
+
+impl Deadline {
+    fn new(budget: &MaintenanceBudget) -> Self {
+        Self(Instant::now() + Duration::from_millis(budget.max_duration_ms.into()))
+    }
+
+    fn has_time(&self) -> bool {
+        Instant::now() < self.0
+    }
+}
+
+fn is_due(db: &PlacesDb, key: &str, interval: Duration, now: Timestamp) -> Result<bool> {
+    Ok(match get_meta::<Timestamp>(db, key)? {
+        // If the clock went backwards, run the step again.
+        Some(last) => now
+            .duration_since(last)
+            .map_or(true, |elapsed| elapsed >= interval),
+        None => true,
+    })
+}
+
+/// Runs whichever maintenance steps are due, within the time budget.
+///
+/// This is intended to be called during idle time, instead of the individual
+/// `run_maintenance_*()` functions. Steps are run in this order:
+///
+/// 1. Expiration, at most daily: visits and metadata older than the budget's
+///    policy, orphaned pages, and favicons no longer used by any page.
+/// 2. Pruning, whenever the database is over `db_size_limit`.
+/// 3. Recalculating stale frecencies, whenever there are any.
+/// 4. Grouping history metadata into journeys, whenever there's metadata
+///    that isn't part of one. This is only needed for synced metadata.
+/// 5. An incremental vacuum, at most daily.
+/// 6. `PRAGMA optimize`, at most weekly.
+/// 7. A WAL checkpoint.
+///
+/// Removals happen in small transactions, so interrupting maintenance, or
+/// running out of time, keeps the work that's already been done. Steps that
+/// don't finish are due again on the next call.
+pub fn run_maintenance(db: &PlacesDb, budget: &MaintenanceBudget) -> Result<MaintenanceMetrics> {
+    let start = Instant::now();
+    let deadline = Deadline::new(budget);
+    let scope = db.begin_interrupt_scope()?;
+    let now = Timestamp::now();
+    let mut metrics = MaintenanceMetrics {
+        db_size_before: db.get_db_size()?,
+        ..Default::default()
+    };
+
+    let mut due = Vec::new();
+    if is_due(db, LAST_EXPIRATION_META_KEY, EXPIRATION_INTERVAL, now)? {
+        due.push(MaintenanceStep::Expire);
+    }
+    if budget.db_size_limit > 0 && metrics.db_size_before > budget.db_size_limit {
+        due.push(MaintenanceStep::Prune);
+    }
+    if db.exists("SELECT 1 FROM moz_places_stale_frecencies", [])? {
+        due.push(MaintenanceStep::RecalculateFrecencies);
+    }
+    if history_metadata::has_ungrouped_metadata(db)? {
+        due.push(MaintenanceStep::GroupJourneys);
+    }
+    if is_due(db, LAST_VACUUM_META_KEY, VACUUM_INTERVAL, now)? {
+        due.push(MaintenanceStep::Vacuum);
+    }
+    if is_due(db, LAST_OPTIMIZE_META_KEY, OPTIMIZE_INTERVAL, now)? {
+        due.push(MaintenanceStep::Optimize);
+    }
+    due.push(MaintenanceStep::Checkpoint);
+
+    // Visits removed by expiration count against the prune limit, too.
+    let mut prune_limit = budget.prune_limit;
+    for step in due {
+        scope.err_if_interrupted()?;
+        if !deadline.has_time() {
+            metrics.steps_skipped.push(step);
+            continue;
+        }
+        metrics.steps_run.push(step);
+        match step {
+            MaintenanceStep::Expire => {
+                if run_expiration(db, budget, now, &deadline, &mut prune_limit, &mut metrics)? {
+                    put_meta(db, LAST_EXPIRATION_META_KEY, &now)?;
+                } else {
+                    // Make sure we pick up where we left off next time.
+                    delete_meta(db, LAST_EXPIRATION_META_KEY)?;
+                }
+            }
+            MaintenanceStep::Prune => {
+                while prune_limit > 0 && deadline.has_time() {
+                    scope.err_if_interrupted()?;
+                    let tx = db.begin_transaction()?;
+                    let pruned =
+                        history::prune_older_visits_in_tx(db, prune_limit.min(BATCH_SIZE), now)?;
+                    tx.commit()?;
+                    metrics.pruned_visits += pruned;
+                    prune_limit -= pruned;
+                    if pruned < BATCH_SIZE || db.get_db_size()? <= budget.db_size_limit {
+                        break;
+                    }
+                }
+            }
+            MaintenanceStep::RecalculateFrecencies => loop {
+                scope.err_if_interrupted()?;
+                let result = recalculate_stale_frecencies(db, BATCH_SIZE)?;
+                metrics.recalculated_frecencies += result.recalculated;
+                if result.remaining == 0 || !deadline.has_time() {
+                    break;
+                }
+            },
+            MaintenanceStep::GroupJourneys => history_metadata::update_journeys(db)?,
+            MaintenanceStep::Vacuum => {
+                run_maintenance_vacuum(db)?;
+                put_meta(db, LAST_VACUUM_META_KEY, &now)?;
+            }
+            MaintenanceStep::Optimize => {
+                run_maintenance_optimize(db)?;
+                put_meta(db, LAST_OPTIMIZE_META_KEY, &now)?;
+            }
+            MaintenanceStep::Checkpoint => run_maintenance_checkpoint(db)?,
+        }
+    }
+
+    metrics.budget_exhausted = !deadline.has_time();
+    metrics.db_size_after = db.get_db_size()?;
+    metrics.duration_ms = start.elapsed().as_millis().try_into().unwrap_or(u32::MAX);
+    log::debug!("Ran maintenance: {:?}", metrics);
+    Ok(metrics)
+}
+
+/// Removes expired visits and metadata, then orphaned pages and unused
+/// favicons. Returns `true` if everything that expired was removed, or
+/// `false` if we ran out of time or hit the prune limit.
+fn run_expiration(
+    db: &PlacesDb,
+    budget: &MaintenanceBudget,
+    now: Timestamp,
+    deadline: &Deadline,
+    prune_limit: &mut u32,
+    metrics: &mut MaintenanceMetrics,
+) -> Result<bool> {
+    let scope = db.begin_interrupt_scope()?;
+    if budget.visit_expiration_days > 0 {
+        let cutoff = expiration_cutoff(now, budget.visit_expiration_days);
+        loop {
+            if *prune_limit == 0 || !deadline.has_time() {
+                return Ok(false);
+            }
+            scope.err_if_interrupted()?;
+            let limit = (*prune_limit).min(BATCH_SIZE);
+            let tx = db.begin_transaction()?;
+            let expired = history::expire_visits_in_tx(db, cutoff, limit)?;
+            tx.commit()?;
+            metrics.pruned_visits += expired;
+            *prune_limit -= expired;
+            if expired < limit {
+                break;
+            }
+        }
+    }
+
+    if budget.metadata_expiration_days > 0 {
+        scope.err_if_interrupted()?;
+        let cutoff = expiration_cutoff(now, budget.metadata_expiration_days);
+        metrics.removed_metadata +=
+            history_metadata::delete_older_than(db, cutoff.as_millis_i64())?;
+    }
+
+    loop {
+        if !deadline.has_time() {
+            return Ok(false);
+        }
+        scope.err_if_interrupted()?;
+        let tx = db.begin_transaction()?;
+        let removed = history::delete_orphaned_pages_in_tx(db, BATCH_SIZE)?;
+        tx.commit()?;
+        metrics.removed_pages += removed;
+        if removed < BATCH_SIZE {
+            break;
+        }
+    }
+
+    scope.err_if_interrupted()?;
+    favicons::prune_favicons(db)?;
+    Ok(true)
+}
+
+fn expiration_cutoff(now: Timestamp, days: u32) -> Timestamp {
+    now.checked_sub(Duration::from_secs(60 * 60 * 24 * u64::from(days)))
+        .unwrap_or(Timestamp::EARLIEST)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::bookmarks::{
+        delete_bookmark, insert_bookmark, BookmarkPosition, BookmarkRootGuid, InsertableBookmark,
+    };
+    use crate::storage::fetch_page_info;
+    use crate::storage::history::apply_observation;
+    use crate::storage::history_metadata::{
+        apply_metadata_observation, get_latest_for_url, HistoryMetadataObservation,
+    };
+    use crate::types::VisitType;
+    use url::Url;
+
+    const DAY: Duration = Duration::from_secs(60 * 60 * 24);
+
+    fn add_visit(db: &PlacesDb, url: &Url, at: Timestamp) {
+        apply_observation(
+            db,
+            VisitObservation::new(url.clone())
+                .with_at(at)
+                .with_visit_type(VisitType::Link),
+        )
+        .expect("should apply visit");
+    }
+
+    fn visit_count(db: &PlacesDb) -> u32 {
+        db.query_one("SELECT COUNT(*) FROM moz_historyvisits")
+            .expect("should count visits")
+    }
+
+    #[test]
+    fn test_steps_are_scheduled() -> Result<()> {
+        let conn = new_mem_connection();
+        let budget = MaintenanceBudget {
+            max_duration_ms: 60_000,
+            ..Default::default()
+        };
+
+        let metrics = run_maintenance(&conn, &budget)?;
+        assert_eq!(
+            metrics.steps_run,
+            vec![
+                MaintenanceStep::Expire,
+                MaintenanceStep::Vacuum,
+                MaintenanceStep::Optimize,
+                MaintenanceStep::Checkpoint,
+            ]
+        );
+        assert!(metrics.steps_skipped.is_empty());
+        assert!(!metrics.budget_exhausted);
+
+        // Everything except the checkpoint ran recently, so it isn't due.
+        let metrics = run_maintenance(&conn, &budget)?;
+        assert_eq!(metrics.steps_run, vec![MaintenanceStep::Checkpoint]);
+
+        // Pretend the last vacuum was two days ago.
+        let two_days_ago = Timestamp::now().checked_sub(DAY * 2).unwrap();
+        put_meta(&conn, LAST_VACUUM_META_KEY, &two_days_ago)?;
+        let metrics = run_maintenance(&conn, &budget)?;
+        assert_eq!(
+            metrics.steps_run,
+            vec![MaintenanceStep::Vacuum, MaintenanceStep::Checkpoint]
+        );
+
+        // An exhausted budget skips everything that's due.
+        delete_meta(&conn, LAST_OPTIMIZE_META_KEY)?;
+        let metrics = run_maintenance(
+            &conn,
+            &MaintenanceBudget {
+                max_duration_ms: 0,
+                ..Default::default()
+            },
+        )?;
+        assert!(metrics.steps_run.is_empty());
+        assert_eq!(
+            metrics.steps_skipped,
+            vec![MaintenanceStep::Optimize, MaintenanceStep::Checkpoint]
+        );
+        assert!(metrics.budget_exhausted);
+        // The optimize step is still due.
+        assert!(is_due(
+            &conn,
+            LAST_OPTIMIZE_META_KEY,
+            OPTIMIZE_INTERVAL,
+            Timestamp::now()
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expiration() -> Result<()> {
+        let conn = new_mem_connection();
+        let now = Timestamp::now();
+        let old = Url::parse("https://example.com/old").unwrap();
+        let recent = Url::parse("https://example.com/recent").unwrap();
+        for days in 40..45 {
+            add_visit(&conn, &old, now.checked_sub(DAY * days).unwrap());
+        }
+        add_visit(&conn, &recent, now.checked_sub(DAY * 40).unwrap());
+        add_visit(&conn, &recent, now.checked_sub(DAY).unwrap());
+
+        // A page that's only left over from a deleted bookmark.
+        let orphan = Url::parse("https://example.com/orphan").unwrap();
+        let guid = insert_bookmark(
+            &conn,
+            InsertableBookmark {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: orphan.clone(),
+                title: None,
+            }
+            .into(),
+        )?;
+        delete_bookmark(&conn, &guid)?;
+        assert!(fetch_page_info(&conn, &orphan)?.is_some());
+
+        // Metadata for the recent page, which is too old to keep.
+        apply_metadata_observation(
+            &conn,
+            HistoryMetadataObservation {
+                url: recent.to_string(),
+                view_time: Some(1000),
+                search_term: None,
+                document_type: None,
+                referrer_url: None,
+                title: None,
+            },
+        )?;
+        conn.execute_cached(
+            "UPDATE moz_places_metadata SET updated_at = :updated_at",
+            &[(":updated_at", &now.checked_sub(DAY * 60).unwrap())],
+        )?;
+
+        let budget = MaintenanceBudget {
+            max_duration_ms: 60_000,
+            prune_limit: 4,
+            visit_expiration_days: 30,
+            metadata_expiration_days: 30,
+            ..Default::default()
+        };
+
+        // The first run only removes 4 visits, so expiration is still due.
+        let metrics = run_maintenance(&conn, &budget)?;
+        assert_eq!(metrics.pruned_visits, 4);
+        assert_eq!(metrics.removed_pages, 0);
+        assert_eq!(visit_count(&conn), 3);
+        assert!(is_due(
+            &conn,
+            LAST_EXPIRATION_META_KEY,
+            EXPIRATION_INTERVAL,
+            Timestamp::now()
+        )?);
+
+        let metrics = run_maintenance(&conn, &budget)?;
+        assert_eq!(metrics.steps_run[0], MaintenanceStep::Expire);
+        assert_eq!(metrics.pruned_visits, 2);
+        assert_eq!(metrics.removed_pages, 1);
+        assert_eq!(metrics.removed_metadata, 1);
+        assert_eq!(visit_count(&conn), 1);
+        assert!(fetch_page_info(&conn, &old)?.is_none());
+        assert!(fetch_page_info(&conn, &orphan)?.is_none());
+        assert!(fetch_page_info(&conn, &recent)?.is_some());
+        assert!(get_latest_for_url(&conn, &recent)?.is_none());
+        assert!(!is_due(
+            &conn,
+            LAST_EXPIRATION_META_KEY,
+            EXPIRATION_INTERVAL,
+            Timestamp::now()
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_keeps_everything_without_a_policy() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://example.com").unwrap();
+        add_visit(
+            &conn,
+            &url,
+            Timestamp::now().checked_sub(DAY * 365).unwrap(),
+        );
+        let metrics = run_maintenance(
+            &conn,
+            &MaintenanceBudget {
+                max_duration_ms: 60_000,
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(metrics.pruned_visits, 0);
+        assert_eq!(visit_count(&conn), 1);
+        Ok(())
+    }
+}
@@ -6,9 +6,14 @@
 // API and the database.
 
 pub mod bookmarks;
+pub mod favicons;
 pub mod history;
 pub mod history_metadata;
+pub mod keywords;
+pub mod maintenance;
+pub mod search;
 pub mod tags;
+pub mod undo;
 
 use crate::db::PlacesDb;
 use crate::error::{Error, InvalidPlaceInfo, Result};
@@ -33,6 +38,13 @@ pub const TITLE_LENGTH_MAX: usize = 4096;
 pub const TAG_LENGTH_MAX: usize = 100;
 // pub const DESCRIPTION_LENGTH_MAX: usize = 256;
 
+// The settings used to calculate frecencies the last time the database was
+// opened, as JSON. Missing if they were the defaults.
+static FRECENCY_SETTINGS_META_KEY: &str = "frecency_settings";
+// Pages with ids below this one still need their frecencies recalculated
+// after the settings changed. Missing if they're all up to date.
+static FRECENCY_SETTINGS_RECALC_BELOW_META_KEY: &str = "frecency_settings_recalc_below";
+
 // Typesafe way to manage RowIds. Does it make sense? A better way?
 #[derive(
     Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Deserialize, Serialize, Default, Hash,
@@ -247,6 +259,8 @@ pub struct RunMaintenanceMetrics {
 /// than this, some older visits will be deleted to free up space.  Pass in a 0 to skip this.
 ///
 /// prune_limit is the maximum number of visits to prune if the database is over db_size_limit
+///
+/// Favicons for pages and origins which are no longer in history or bookmarks are always removed.
 pub fn run_maintenance_prune(
     conn: &PlacesDb,
     db_size_limit: u32,
@@ -257,6 +271,7 @@ pub fn run_maintenance_prune(
     if should_prune {
         history::prune_older_visits(conn, prune_limit)?;
     }
+    favicons::prune_favicons(conn)?;
     let db_size_after = conn.get_db_size()?;
     Ok(RunMaintenanceMetrics {
         pruned_visits: should_prune,
@@ -309,6 +324,124 @@ pub fn run_maintenance_checkpoint(conn: &PlacesDb) -> Result<()> {
     Ok(())
 }
 
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FrecencyRecalculationMetrics {
+    /// The number of pages whose frecencies were recalculated.
+    pub recalculated: u32,
+    /// The number of pages with stale frecencies left to recalculate.
+    pub remaining: u32,
+}
+
+/// Recalculates up to `max_pages` stale frecencies, most recently flagged
+/// first, using the API's frecency settings.
+///
+/// Frecencies are flagged as stale when bookmarks are synced or imported, and
+/// for every page when the frecency settings change. This is intended to be
+/// run during idle time, in small batches, until `remaining` is 0.
+pub fn recalculate_stale_frecencies(
+    db: &PlacesDb,
+    max_pages: u32,
+) -> Result<FrecencyRecalculationMetrics> {
+    let scope = db.begin_interrupt_scope()?;
+    let tx = db.begin_transaction()?;
+    let update_frecency = |place_id: i64| -> Result<()> {
+        scope.err_if_interrupted()?;
+        let frecency = calculate_frecency(db, db.frecency_settings(), place_id, Some(false))?;
+        db.execute_cached(
+            "UPDATE moz_places SET frecency = :frecency WHERE id = :place_id",
+            &[
+                (":frecency", &i64::from(frecency)),
+                (":place_id", &place_id),
+            ],
+        )?;
+        Ok(())
+    };
+    let place_ids = db.query_rows_and_then(
+        "SELECT place_id FROM moz_places_stale_frecencies
+         ORDER BY stale_at DESC
+         LIMIT :max_pages",
+        &[(":max_pages", &max_pages)],
+        |row| row.get::<_, i64>(0),
+    )?;
+    for place_id in &place_ids {
+        update_frecency(*place_id)?;
+        db.execute_cached(
+            "DELETE FROM moz_places_stale_frecencies WHERE place_id = :place_id",
+            &[(":place_id", place_id)],
+        )?;
+    }
+    let mut recalculated = place_ids.len() as u32;
+
+    // Then, if there's room in this batch, work through the pages left to
+    // recalculate after the settings changed, newest first.
+    let mut recalc_below = get_meta::<i64>(db, FRECENCY_SETTINGS_RECALC_BELOW_META_KEY)?;
+    if let Some(below) = recalc_below.filter(|_| recalculated < max_pages) {
+        let limit = max_pages - recalculated;
+        let place_ids = db.query_rows_and_then(
+            "SELECT id FROM moz_places
+             WHERE id < :below
+             ORDER BY id DESC
+             LIMIT :limit",
+            rusqlite::named_params! {
+                ":below": below,
+                ":limit": limit,
+            },
+            |row| row.get::<_, i64>(0),
+        )?;
+        for place_id in &place_ids {
+            update_frecency(*place_id)?;
+        }
+        recalculated += place_ids.len() as u32;
+        recalc_below = match place_ids.last() {
+            Some(lowest) if place_ids.len() as u32 == limit => Some(*lowest),
+            _ => None,
+        };
+        match recalc_below {
+            Some(below) => put_meta(db, FRECENCY_SETTINGS_RECALC_BELOW_META_KEY, &below)?,
+            None => delete_meta(db, FRECENCY_SETTINGS_RECALC_BELOW_META_KEY)?,
+        }
+    }
+
+    let remaining = db.query_row_and_then_cachable(
+        "SELECT (SELECT COUNT(*) FROM moz_places_stale_frecencies) +
+                (SELECT COUNT(*) FROM moz_places WHERE id < :below)",
+        &[(":below", &recalc_below.unwrap_or(0))],
+        |row| row.get::<_, u32>(0),
+        false,
+    )?;
+    tx.commit()?;
+    Ok(FrecencyRecalculationMetrics {
+        recalculated,
+        remaining,
+    })
+}
+
+/// Flags all frecencies as stale if the frecency settings are different from
+/// the last time the database was opened, so that they're recalculated with
+/// the new settings. This is called when a `PlacesApi` is created.
+///
+/// Unlike other stale frecencies, these aren't added to
+/// `moz_places_stale_frecencies`, which bookmark sync recalculates all at
+/// once. Only `recalculate_stale_frecencies` recalculates them, in batches.
+pub(crate) fn note_frecency_settings(db: &PlacesDb) -> Result<()> {
+    let settings = serde_json::to_string(db.frecency_settings())?;
+    let previous = match get_meta::<String>(db, FRECENCY_SETTINGS_META_KEY)? {
+        Some(previous) => previous,
+        None => serde_json::to_string(&DEFAULT_FRECENCY_SETTINGS)?,
+    };
+    if settings == previous {
+        return Ok(());
+    }
+    log::info!("Frecency settings changed; flagging all frecencies as stale");
+    let tx = db.begin_transaction()?;
+    // Pages added from now on get frecencies with the new settings.
+    let recalc_below = db.query_one::<i64>("SELECT IFNULL(MAX(id), 0) + 1 FROM moz_places")?;
+    put_meta(db, FRECENCY_SETTINGS_RECALC_BELOW_META_KEY, &recalc_below)?;
+    put_meta(db, FRECENCY_SETTINGS_META_KEY, &settings)?;
+    tx.commit()?;
+    Ok(())
+}
+
 pub fn update_all_frecencies_at_once(db: &PlacesDb, scope: &SqlInterruptScope) -> Result<()> {
     let tx = db.begin_transaction()?;
 
@@ -324,7 +457,7 @@ pub fn update_all_frecencies_at_once(db: &PlacesDb, scope: &SqlInterruptScope) -
             scope.err_if_interrupted()?;
             Ok((
                 *places_id,
-                calculate_frecency(db, &DEFAULT_FRECENCY_SETTINGS, *places_id, Some(false))?,
+                calculate_frecency(db, db.frecency_settings(), *places_id, Some(false))?,
             ))
         })
         .collect::<Result<Vec<(i64, i32)>>>()?;
@@ -428,6 +561,152 @@ mod tests {
         delete_meta(&conn, "foo").expect("delete non-existing should work");
     }
 
+    #[test]
+    fn test_recalculate_stale_frecencies() -> Result<()> {
+        let conn = new_mem_connection();
+        let urls = ["https://a.example.com/", "https://b.example.com/"]
+            .iter()
+            .map(|url| Url::parse(url).unwrap())
+            .collect::<Vec<_>>();
+        for url in &urls {
+            apply_observation(
+                &conn,
+                VisitObservation::new(url.clone()).with_visit_type(VisitType::Typed),
+            )?;
+        }
+        conn.execute_batch(
+            "UPDATE moz_places SET frecency = 0;
+             INSERT INTO moz_places_stale_frecencies(place_id, stale_at)
+             SELECT id, now() FROM moz_places",
+        )?;
+
+        let metrics = recalculate_stale_frecencies(&conn, 1)?;
+        assert_eq!(
+            metrics,
+            FrecencyRecalculationMetrics {
+                recalculated: 1,
+                remaining: 1,
+            }
+        );
+        let metrics = recalculate_stale_frecencies(&conn, 10)?;
+        assert_eq!(
+            metrics,
+            FrecencyRecalculationMetrics {
+                recalculated: 1,
+                remaining: 0,
+            }
+        );
+        for url in &urls {
+            assert!(history::frecency_stale_at(&conn, url)?.is_none());
+        }
+        assert_eq!(
+            conn.query_one::<u32>("SELECT COUNT(*) FROM moz_places WHERE frecency <= 0")?,
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_frecency_settings_changed() -> Result<()> {
+        use crate::api::places_api::{ConnectionType, PlacesApi};
+        use crate::bookmark_sync::engine::update_frecencies;
+        use crate::frecency::{FrecencyAlgorithm, FrecencySettings};
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let path = tmpdir.path().join("places.sqlite");
+        let urls = ["https://a.example.com/", "https://b.example.com/"]
+            .iter()
+            .map(|url| Url::parse(url).unwrap())
+            .collect::<Vec<_>>();
+        let exponential = FrecencySettings {
+            algorithm: FrecencyAlgorithm::Exponential,
+            ..FrecencySettings::default()
+        };
+
+        let api = PlacesApi::new(&path)?;
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        for url in &urls {
+            apply_observation(
+                &conn,
+                VisitObservation::new(url.clone()).with_at(Timestamp::from(1)),
+            )?;
+        }
+        drop((conn, api));
+
+        // Reopening with the same settings shouldn't flag anything.
+        let api = PlacesApi::new_with_frecency_settings(&path, FrecencySettings::default())?;
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        assert_eq!(
+            recalculate_stale_frecencies(&conn, 10)?,
+            FrecencyRecalculationMetrics::default()
+        );
+        drop((conn, api));
+
+        // But changing them should, once. Bookmark sync recalculates stale
+        // frecencies all at once, so it should leave these to maintenance.
+        let api = PlacesApi::new_with_frecency_settings(&path, exponential.clone())?;
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        let sync_conn = api.get_sync_connection()?;
+        let sync_db = sync_conn.lock();
+        update_frecencies(&sync_db, &sync_db.begin_interrupt_scope()?)?;
+        drop(sync_db);
+        assert_eq!(
+            recalculate_stale_frecencies(&conn, 1)?,
+            FrecencyRecalculationMetrics {
+                recalculated: 1,
+                remaining: 1,
+            }
+        );
+        assert_eq!(
+            recalculate_stale_frecencies(&conn, 10)?,
+            FrecencyRecalculationMetrics {
+                recalculated: 1,
+                remaining: 0,
+            }
+        );
+        assert!(get_meta::<i64>(&conn, FRECENCY_SETTINGS_RECALC_BELOW_META_KEY)?.is_none());
+        drop((conn, sync_conn, api));
+
+        let api = PlacesApi::new_with_frecency_settings(&path, exponential)?;
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        assert_eq!(
+            recalculate_stale_frecencies(&conn, 10)?,
+            FrecencyRecalculationMetrics::default()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_maintenance_prune_notifies_observers() -> Result<()> {
+        use crate::api::places_api::{ConnectionType, PlacesApi};
+        use crate::observer::{test::CommittedObserver, PlacesChange};
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let api = PlacesApi::new(tmpdir.path().join("places.sqlite"))?;
+        let conn = api.open_connection(ConnectionType::ReadWrite)?;
+        let url = Url::parse("https://example.com/").unwrap();
+        apply_observation(
+            &conn,
+            VisitObservation::new(url.clone()).with_at(Timestamp::from(727_747_200_001)),
+        )?;
+        let guid = history::url_to_guid(&conn, &url)?.expect("should exist");
+        let observer = CommittedObserver::register(&api)?;
+
+        // Any database is bigger than a byte, so this prunes the old visit.
+        let metrics = run_maintenance_prune(&conn, 1, 6)?;
+        assert!(metrics.pruned_visits);
+        // `take` checks that the page was removed before we were told about it.
+        assert_eq!(
+            observer.take(),
+            vec![vec![PlacesChange::PageRemoved {
+                url,
+                guid,
+                is_removed_from_store: true,
+            }]]
+        );
+        Ok(())
+    }
+
     // Here we try and test that we replicate desktop behaviour, which isn't that obvious.
     // * create a bookmark
     // * remove the bookmark - this doesn't remove the place or origin - probably because in
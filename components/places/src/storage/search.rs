@@ -0,0 +1,328 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Full-text search over history and bookmarks, using the FTS5 index in
+// `moz_places_fts`. Unlike the autocomplete matcher, which scans `moz_places`
+// with `AUTOCOMPLETE_MATCH`, this only looks at the index, and ranks results
+// by how well they match, rather than only by frecency.
+
+use crate::db::PlacesDb;
+use crate::error::Result;
+use rusqlite::{Connection, Row};
+use sql_support::ConnExt;
+use url::Url;
+
+/// Inserted before each matching term in the highlighted fields of a
+/// `SearchMatch`.
+pub const HIGHLIGHT_START: &str = "\u{2}";
+/// Inserted after each matching term in the highlighted fields of a
+/// `SearchMatch`.
+pub const HIGHLIGHT_END: &str = "\u{3}";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub url: Url,
+    /// The bookmark title if the page is bookmarked, otherwise the page title.
+    pub title: String,
+    /// `title`, with each matching term wrapped in `HIGHLIGHT_START` and
+    /// `HIGHLIGHT_END`.
+    pub highlighted_title: String,
+    /// The URL, highlighted in the same way as `highlighted_title`.
+    pub highlighted_url: String,
+    pub is_bookmarked: bool,
+    /// The page's tags, sorted alphabetically.
+    pub tags: Vec<String>,
+    pub keyword: Option<String>,
+    pub frecency: i64,
+}
+
+impl SearchMatch {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        let (title, highlighted_title) = match row.get::<_, Option<String>>("bookmark_title")? {
+            Some(title) if !title.is_empty() => (title, row.get("highlighted_bookmark_title")?),
+            _ => (
+                row.get::<_, Option<String>>("title")?.unwrap_or_default(),
+                row.get::<_, Option<String>>("highlighted_title")?
+                    .unwrap_or_default(),
+            ),
+        };
+        Ok(Self {
+            url: Url::parse(&row.get::<_, String>("url")?)?,
+            title,
+            highlighted_title,
+            highlighted_url: row.get("highlighted_url")?,
+            is_bookmarked: row.get("is_bookmarked")?,
+            tags: serde_json::from_str(&row.get::<_, String>("tags")?)?,
+            keyword: row.get("keyword")?,
+            frecency: row.get("frecency")?,
+        })
+    }
+}
+
+// Column weights for `bm25`, in the order of the columns in `moz_places_fts`:
+// url, title, bookmark_title, tags, keyword. Matching a keyword or tag says
+// more about what the user is looking for than matching a title, and titles
+// say more than matching part of a URL.
+const SEARCH_SQL: &str = "
+    SELECT h.url, h.title, h.frecency, f.bookmark_title, f.keyword,
+           highlight(moz_places_fts, 0, :start, :end) AS highlighted_url,
+           highlight(moz_places_fts, 1, :start, :end) AS highlighted_title,
+           highlight(moz_places_fts, 2, :start, :end) AS highlighted_bookmark_title,
+           EXISTS(SELECT 1 FROM moz_bookmarks b
+                  WHERE b.fk = h.id) AS is_bookmarked,
+           (SELECT json_group_array(tag) FROM (
+               SELECT t.tag FROM moz_tags t
+               JOIN moz_tags_relation r ON r.tag_id = t.id
+               WHERE r.place_id = h.id
+               ORDER BY t.tag
+           )) AS tags
+    FROM moz_places_fts f
+    JOIN moz_places h ON h.id = f.rowid
+    WHERE moz_places_fts MATCH :query
+      AND (is_bookmarked OR
+           (NOT h.hidden AND
+            h.last_visit_date_local + h.last_visit_date_remote > 0))
+    ORDER BY bm25(moz_places_fts, 1.0, 4.0, 4.0, 6.0, 10.0), h.frecency DESC
+    LIMIT :limit";
+
+/// Searches the titles, URLs, bookmark titles, tags and keywords of visited
+/// and bookmarked pages, returning the best matches first.
+///
+/// Each word in `query` matches as a prefix, so "moz fire" matches
+/// "Mozilla Firefox", and all words must match somewhere for a page to be
+/// returned. Punctuation is ignored, and never treated as query syntax.
+pub fn search(db: &PlacesDb, query: &str, limit: i32) -> Result<Vec<SearchMatch>> {
+    let fts_query = match fts_query(query) {
+        Some(q) => q,
+        None => return Ok(Vec::new()),
+    };
+    db.query_rows_and_then_cached(
+        SEARCH_SQL,
+        rusqlite::named_params! {
+            ":query": fts_query,
+            ":start": HIGHLIGHT_START,
+            ":end": HIGHLIGHT_END,
+            ":limit": limit,
+        },
+        SearchMatch::from_row,
+    )
+}
+
+// Turns what the user typed into an FTS5 query. Each word is quoted, so that
+// characters like `:`, `-` and `"` are searched for instead of being parsed as
+// FTS5 syntax, and made into a prefix query, so that results appear while the
+// user is still typing. Words without any letters or numbers would never
+// match anything, so we drop them.
+fn fts_query(query: &str) -> Option<String> {
+    let terms = query
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Rebuilds the search index rows for the pages whose IDs are returned by
+/// `place_ids_sql`, which can be any SQL expression that's valid in an `IN`
+/// clause.
+///
+/// The main connection keeps the index up to date with triggers, so this is
+/// only needed by the Sync connection, which doesn't have them, and when
+/// building the index for the first time.
+pub(crate) fn update_search_index(db: &Connection, place_ids_sql: &str) -> rusqlite::Result<()> {
+    db.execute_batch(&format!(
+        "DELETE FROM moz_places_fts WHERE rowid IN ({ids});
+         INSERT INTO moz_places_fts(rowid, url, title, bookmark_title, tags, keyword)
+         SELECT * FROM moz_places_fts_source WHERE id IN ({ids});",
+        ids = place_ids_sql,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::bookmarks::{
+        delete_bookmark, insert_bookmark, update_bookmark, BookmarkPosition, BookmarkRootGuid,
+        InsertableBookmark, UpdatableBookmark,
+    };
+    use crate::storage::history::apply_observation;
+    use crate::storage::tags::tag_url;
+    use crate::types::VisitType;
+    use pretty_assertions::assert_eq;
+
+    fn visit(conn: &PlacesDb, url: &str, title: &str) -> Url {
+        let url = Url::parse(url).unwrap();
+        apply_observation(
+            conn,
+            VisitObservation::new(url.clone())
+                .with_title(title.to_string())
+                .with_visit_type(VisitType::Link),
+        )
+        .expect("should apply");
+        url
+    }
+
+    fn search_urls(conn: &PlacesDb, query: &str) -> Vec<String> {
+        search(conn, query, 10)
+            .expect("should search")
+            .into_iter()
+            .map(|m| m.url.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query(""), None);
+        assert_eq!(fts_query("  - : "), None);
+        assert_eq!(
+            fts_query("moz fire"),
+            Some("\"moz\"* \"fire\"*".to_string())
+        );
+        assert_eq!(
+            fts_query("title:\"quoted\" NOT"),
+            Some("\"title:\"\"quoted\"\"\"* \"NOT\"*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_search_history() {
+        let conn = new_mem_connection();
+        visit(&conn, "https://www.mozilla.org/firefox/", "Firefox Browser");
+        visit(&conn, "https://example.com/", "Example Domain");
+        visit(&conn, "https://example.com/café", "Café menu");
+
+        assert_eq!(
+            search_urls(&conn, "moz fire"),
+            vec!["https://www.mozilla.org/firefox/"]
+        );
+        // Diacritics and case are ignored.
+        assert_eq!(
+            search_urls(&conn, "CAFE"),
+            vec!["https://example.com/caf%C3%A9"]
+        );
+        assert_eq!(search_urls(&conn, "example").len(), 2);
+        assert_eq!(search_urls(&conn, "nothing"), Vec::<String>::new());
+        // Punctuation doesn't cause syntax errors.
+        assert_eq!(
+            search_urls(&conn, "\"fire* AND (NEAR"),
+            Vec::<String>::new()
+        );
+
+        let matches = search(&conn, "brow", 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].title, "Firefox Browser");
+        assert_eq!(
+            matches[0].highlighted_title,
+            format!("Firefox {}Browser{}", HIGHLIGHT_START, HIGHLIGHT_END)
+        );
+        assert_eq!(
+            matches[0].highlighted_url,
+            "https://www.mozilla.org/firefox/"
+        );
+        assert!(!matches[0].is_bookmarked);
+
+        // Changing the title updates the index.
+        visit(&conn, "https://www.mozilla.org/firefox/", "Firefox Nightly");
+        assert_eq!(search_urls(&conn, "brow"), Vec::<String>::new());
+        assert_eq!(
+            search_urls(&conn, "nightly"),
+            vec!["https://www.mozilla.org/firefox/"]
+        );
+    }
+
+    #[test]
+    fn test_search_bookmarks() {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://www.mozilla.org/").unwrap();
+        let guid = insert_bookmark(
+            &conn,
+            InsertableBookmark {
+                parent_guid: BookmarkRootGuid::Unfiled.into(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: url.clone(),
+                title: Some("Mozilla home".into()),
+            }
+            .into(),
+        )
+        .expect("should insert bookmark");
+        tag_url(&conn, &url, "nonprofit").expect("should tag");
+        tag_url(&conn, &url, "foundation").expect("should tag");
+        conn.execute_batch(
+            "INSERT INTO moz_keywords(place_id, keyword)
+             SELECT id, 'mozhome' FROM moz_places",
+        )
+        .expect("should add keyword");
+
+        // Bookmarked pages are returned even though they've never been
+        // visited, and match on their titles, tags and keywords.
+        let matches = search(&conn, "home", 10).unwrap();
+        assert_eq!(
+            matches,
+            vec![SearchMatch {
+                url: url.clone(),
+                title: "Mozilla home".into(),
+                highlighted_title: format!("Mozilla {}home{}", HIGHLIGHT_START, HIGHLIGHT_END),
+                highlighted_url: "https://www.mozilla.org/".into(),
+                is_bookmarked: true,
+                tags: vec!["foundation".into(), "nonprofit".into()],
+                keyword: Some("mozhome".into()),
+                frecency: matches[0].frecency,
+            }]
+        );
+        assert_eq!(search_urls(&conn, "nonprof"), vec![url.to_string()]);
+        assert_eq!(search_urls(&conn, "mozhome"), vec![url.to_string()]);
+
+        update_bookmark(
+            &conn,
+            &guid,
+            &UpdatableBookmark {
+                title: Some("Renamed".into()),
+                ..Default::default()
+            }
+            .into(),
+        )
+        .expect("should update bookmark");
+        assert_eq!(search_urls(&conn, "renamed"), vec![url.to_string()]);
+        assert_eq!(search_urls(&conn, "home"), Vec::<String>::new());
+
+        // Once the bookmark is removed, the page isn't returned, since it
+        // hasn't been visited.
+        conn.execute_batch("DELETE FROM moz_keywords")
+            .expect("should remove keyword");
+        delete_bookmark(&conn, &guid).expect("should delete bookmark");
+        assert_eq!(search_urls(&conn, "renamed"), Vec::<String>::new());
+        assert_eq!(search_urls(&conn, "nonprof"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let conn = new_mem_connection();
+        visit(&conn, "https://example.com/rust", "Something else");
+        visit(&conn, "https://example.org/", "Learning Rust");
+        let url = Url::parse("https://example.net/").unwrap();
+        visit(&conn, url.as_str(), "Example");
+        tag_url(&conn, &url, "rust").expect("should tag");
+
+        // Tag matches are better than title matches, which are better than
+        // URL matches.
+        assert_eq!(
+            search_urls(&conn, "rust"),
+            vec![
+                "https://example.net/",
+                "https://example.org/",
+                "https://example.com/rust",
+            ]
+        );
+        assert_eq!(search(&conn, "rust", 1).unwrap().len(), 1);
+    }
+}
@@ -228,6 +228,83 @@ pub fn get_tags_for_url(db: &PlacesDb, url: &Url) -> Result<Vec<String>> {
     Ok(tags)
 }
 
+/// Renames a tag for all URLs. If a tag with the new name already exists, the
+/// two are merged.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// * `old_tag` - The tag to rename.
+///
+/// * `new_tag` - The new name for the tag.
+///
+/// # Returns
+///
+/// There is no success return value - the operation is ignored if the old
+/// tag doesn't exist.
+pub fn rename_tag(db: &PlacesDb, old_tag: &str, new_tag: &str) -> Result<()> {
+    let old_tag = validate_tag(old_tag).ensure_valid()?;
+    let new_tag = validate_tag(new_tag).ensure_valid()?;
+    if old_tag == new_tag {
+        return Ok(());
+    }
+    let tx = db.begin_transaction()?;
+    let old_tag_id = match db.try_query_one::<i64, _>(
+        "SELECT id FROM moz_tags WHERE tag = :tag",
+        &[(":tag", &old_tag)],
+        true,
+    )? {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+    // Rather than updating the tag in place, we move the URLs over to the new
+    // tag and remove the old one, so that the triggers on `moz_tags_relation`
+    // flag the affected bookmarks for upload.
+    db.execute_cached(
+        "INSERT OR IGNORE INTO moz_tags(tag, lastModified)
+         VALUES(:tag, now())",
+        &[(":tag", &new_tag)],
+    )?;
+    db.execute_cached(
+        "INSERT OR IGNORE INTO moz_tags_relation(tag_id, place_id)
+         SELECT (SELECT id FROM moz_tags WHERE tag = :new_tag), place_id
+         FROM moz_tags_relation
+         WHERE tag_id = :old_tag_id",
+        &[
+            (":new_tag", &new_tag as &dyn rusqlite::ToSql),
+            (":old_tag_id", &old_tag_id),
+        ],
+    )?;
+    db.execute_cached(
+        "DELETE FROM moz_tags WHERE id = :old_tag_id",
+        &[(":old_tag_id", &old_tag_id)],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Retrieves all tags that are used for at least one URL.
+///
+/// # Arguments
+///
+/// * `conn` - A database connection on which to operate.
+///
+/// # Returns
+///
+/// * A Vec<String> with all tags, sorted alphabetically.
+pub fn get_all_tags(db: &PlacesDb) -> Result<Vec<String>> {
+    db.query_rows_and_then(
+        "SELECT t.tag
+         FROM moz_tags t
+         WHERE EXISTS(SELECT 1 FROM moz_tags_relation r
+                      WHERE r.tag_id = t.id)
+         ORDER BY t.tag",
+        [],
+        |row| -> Result<_> { Ok(row.get::<_, String>("tag")?) },
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -364,4 +441,39 @@ mod tests {
             .expect("should work")
             .expect("should exist");
     }
+
+    #[test]
+    fn test_rename_tag() {
+        let conn = new_mem_connection();
+        let url1 = Url::parse("http://example.com").expect("valid url");
+        let url2 = Url::parse("http://example2.com").expect("valid url");
+        new_page_info(&conn, &url1, None).expect("should create the page");
+        new_page_info(&conn, &url2, None).expect("should create the page");
+
+        tag_url(&conn, &url1, "old").expect("should work");
+        tag_url(&conn, &url2, "other").expect("should work");
+        assert_eq!(
+            get_all_tags(&conn).expect("should work"),
+            vec!["old".to_string(), "other".to_string()]
+        );
+
+        rename_tag(&conn, "old", "new").expect("should work");
+        check_tags_for_url(&conn, &url1, vec!["new".to_string()]);
+        assert_eq!(get_foreign_count(&conn, &url1), 1);
+
+        // Renaming to an existing tag merges them.
+        tag_url(&conn, &url2, "new").expect("should work");
+        rename_tag(&conn, "other", "new").expect("should work");
+        check_tags_for_url(&conn, &url2, vec!["new".to_string()]);
+        assert_eq!(get_foreign_count(&conn, &url2), 1);
+        check_urls_with_tag(&conn, "new", vec![url1.clone(), url2.clone()]);
+        assert_eq!(
+            get_all_tags(&conn).expect("should work"),
+            vec!["new".to_string()]
+        );
+
+        // Renaming a tag that doesn't exist is a no-op.
+        rename_tag(&conn, "missing", "new").expect("should work");
+        assert!(rename_tag(&conn, "new", " ").is_err());
+    }
 }
@@ -0,0 +1,781 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Support for undoing bookmark and history deletions. The `*_with_undo`
+// functions snapshot everything they're about to remove, in the same
+// transaction as the deletion, and return the snapshot wrapped in an opaque
+// `UndoToken`. Passing the token to `restore` within `UNDO_WINDOW` puts the
+// items back with their original GUIDs and positions.
+//
+// Restored items are flagged for upload, so restoring after the deletion was
+// synced replaces the tombstones on the server. Bookmarks are restored with
+// the tags and keywords their URLs had when they were deleted. History
+// metadata isn't part of the snapshot, and isn't restored.
+
+use super::bookmarks::json_tree::{fetch_tree, BookmarkTreeNode, FetchDepth};
+use super::bookmarks::{
+    delete_bookmark_in_tx, get_raw_bookmark, insert_bookmark_in_tx, BookmarkPosition,
+    BookmarkRootGuid, InsertableItem,
+};
+use super::history::{
+    add_visit, delete_visits_between_in_tx, delete_visits_for_in_tx, update_frecency,
+};
+use super::{delete_pending_temp_tables, fetch_page_info, new_page_info, RowId};
+use crate::db::PlacesDb;
+use crate::error::{Corruption, Error, InvalidPlaceInfo, Result};
+use crate::types::{BookmarkType, SyncStatus, VisitType};
+use parking_lot::Mutex;
+use rusqlite::Row;
+use sql_support::ConnExt;
+use std::time::Duration;
+use sync_guid::Guid as SyncGuid;
+use types::Timestamp;
+use url::Url;
+
+/// How long after a deletion it can still be undone.
+pub const UNDO_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// An opaque token for undoing a deletion, returned by the `*_with_undo`
+/// functions. A token can only be used to restore once.
+#[derive(Debug)]
+pub struct UndoToken {
+    deleted_at: Timestamp,
+    // `None` once the deletion has been undone.
+    snapshot: Mutex<Option<Snapshot>>,
+}
+
+impl UndoToken {
+    fn new(snapshot: Snapshot) -> Self {
+        Self {
+            deleted_at: Timestamp::now(),
+            snapshot: Mutex::new(Some(snapshot)),
+        }
+    }
+
+    /// When the deletion happened.
+    pub fn deleted_at(&self) -> Timestamp {
+        self.deleted_at
+    }
+
+    /// Returns true if the deletion hasn't been undone yet, and is still
+    /// within the undo window.
+    pub fn can_restore(&self) -> bool {
+        !self.is_expired() && self.snapshot.lock().is_some()
+    }
+
+    fn is_expired(&self) -> bool {
+        match Timestamp::now().duration_since(self.deleted_at) {
+            Some(elapsed) => elapsed > UNDO_WINDOW,
+            // The clock went backwards; give the user the benefit of the doubt.
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Snapshot {
+    Bookmarks(DeletedBookmarks),
+    History(Vec<DeletedPage>),
+}
+
+#[derive(Debug)]
+struct DeletedBookmarks {
+    tree: BookmarkTreeNode,
+    parent_guid: SyncGuid,
+    position: u32,
+    // The Sync status and change counter of every item in the tree.
+    sync_info: Vec<(SyncGuid, SyncStatus, u32)>,
+    // The `(url, tag)` and `(url, keyword)` pairs for bookmarked URLs in the
+    // tree.
+    tags: Vec<(String, String)>,
+    keywords: Vec<(String, String)>,
+}
+
+// Selects the ids of an item and all its descendants, for snapshotting.
+const DESCENDANTS_CTE: &str = "WITH RECURSIVE
+     descendants(id) AS (
+       SELECT id FROM moz_bookmarks WHERE guid = :guid
+       UNION ALL
+       SELECT b.id FROM moz_bookmarks b
+       JOIN descendants d ON b.parent = d.id
+     )";
+
+#[derive(Debug)]
+struct DeletedPage {
+    guid: SyncGuid,
+    url: String,
+    title: Option<String>,
+    hidden: bool,
+    typed: u32,
+    preview_image_url: Option<String>,
+    sync_status: SyncStatus,
+    sync_change_counter: u32,
+    visits: Vec<DeletedVisit>,
+}
+
+#[derive(Debug)]
+struct DeletedVisit {
+    visit_date: Timestamp,
+    visit_type: VisitType,
+    is_local: bool,
+    unknown_fields: Option<String>,
+}
+
+/// Like `bookmarks::delete_bookmark`, but returns a token for undoing the
+/// deletion of the item and all its descendants. Returns `None` if the item
+/// doesn't exist, and fails without deleting anything if the item or one of
+/// its descendants can't be snapshotted.
+pub fn delete_bookmark_with_undo(db: &PlacesDb, guid: &SyncGuid) -> Result<Option<UndoToken>> {
+    let tx = db.begin_transaction()?;
+    let result = delete_bookmark_with_undo_in_tx(db, guid);
+    match result {
+        Ok(_) => tx.commit()?,
+        Err(_) => tx.rollback()?,
+    }
+    result
+}
+
+fn delete_bookmark_with_undo_in_tx(db: &PlacesDb, guid: &SyncGuid) -> Result<Option<UndoToken>> {
+    // Can't delete a root.
+    if let Some(root) = BookmarkRootGuid::well_known(guid.as_str()) {
+        return Err(InvalidPlaceInfo::CannotUpdateRoot(root).into());
+    }
+    let (tree, parent_guid, position) = match fetch_tree(db, guid, &FetchDepth::Deepest)? {
+        Some(fetched) => fetched,
+        None => {
+            // Either the item doesn't exist, or it's a bookmark with an
+            // invalid URL that we can't snapshot. Don't delete it in that
+            // case, since the deletion couldn't be undone.
+            if get_raw_bookmark(db, guid)?.is_some() {
+                return Err(Error::CannotUndo("the bookmark can't be snapshotted"));
+            }
+            return Ok(None);
+        }
+    };
+    let parent_guid =
+        parent_guid.ok_or_else(|| Corruption::NonRootWithoutParent(guid.to_string()))?;
+    let sync_info = db.query_rows_and_then(
+        &format!(
+            "{}
+             SELECT guid, syncStatus, syncChangeCounter FROM moz_bookmarks
+             WHERE id IN (SELECT id FROM descendants)",
+            DESCENDANTS_CTE
+        ),
+        &[(":guid", guid)],
+        |row| -> Result<_> {
+            Ok((
+                row.get::<_, SyncGuid>("guid")?,
+                row.get::<_, SyncStatus>("syncStatus")?,
+                row.get::<_, u32>("syncChangeCounter")?,
+            ))
+        },
+    )?;
+    // `fetch_tree` skips descendants with invalid URLs, which we'd lose.
+    if count_nodes(&tree) != sync_info.len() {
+        return Err(Error::CannotUndo("a descendant can't be snapshotted"));
+    }
+    let tags = db.query_rows_and_then(
+        &format!(
+            "{}
+             SELECT DISTINCT h.url, t.tag FROM moz_bookmarks b
+             JOIN moz_places h ON h.id = b.fk
+             JOIN moz_tags_relation r ON r.place_id = h.id
+             JOIN moz_tags t ON t.id = r.tag_id
+             WHERE b.id IN (SELECT id FROM descendants)",
+            DESCENDANTS_CTE
+        ),
+        &[(":guid", guid)],
+        |row| -> Result<_> { Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)) },
+    )?;
+    let keywords = db.query_rows_and_then(
+        &format!(
+            "{}
+             SELECT DISTINCT h.url, k.keyword FROM moz_bookmarks b
+             JOIN moz_places h ON h.id = b.fk
+             JOIN moz_keywords k ON k.place_id = h.id
+             WHERE b.id IN (SELECT id FROM descendants)",
+            DESCENDANTS_CTE
+        ),
+        &[(":guid", guid)],
+        |row| -> Result<_> { Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)) },
+    )?;
+    delete_bookmark_in_tx(db, guid)?;
+    Ok(Some(UndoToken::new(Snapshot::Bookmarks(
+        DeletedBookmarks {
+            tree,
+            parent_guid,
+            position,
+            sync_info,
+            tags,
+            keywords,
+        },
+    ))))
+}
+
+fn count_nodes(node: &BookmarkTreeNode) -> usize {
+    match node {
+        BookmarkTreeNode::Folder { f } => 1 + f.children.iter().map(count_nodes).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+/// Like `history::delete_visits_for`, but returns a token for undoing the
+/// deletion.
+pub fn delete_visits_for_with_undo(db: &PlacesDb, guid: &SyncGuid) -> Result<UndoToken> {
+    let tx = db.begin_transaction()?;
+    let pages = fetch_pages_to_delete(
+        db,
+        "h.guid = :guid",
+        &[(":guid", guid as &dyn rusqlite::ToSql)],
+    )?;
+    delete_visits_for_in_tx(db, guid)?;
+    tx.commit()?;
+    Ok(UndoToken::new(Snapshot::History(pages)))
+}
+
+/// Like `history::delete_visits_between`, but returns a token for undoing the
+/// deletion.
+pub fn delete_visits_between_with_undo(
+    db: &PlacesDb,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<UndoToken> {
+    let tx = db.begin_transaction()?;
+    let pages = fetch_pages_to_delete(
+        db,
+        "v.visit_date BETWEEN :start AND :end",
+        &[(":start", &start as &dyn rusqlite::ToSql), (":end", &end)],
+    )?;
+    delete_visits_between_in_tx(db, start, end)?;
+    tx.commit()?;
+    Ok(UndoToken::new(Snapshot::History(pages)))
+}
+
+/// Fetches all pages with visits matching `filter`, along with those visits.
+fn fetch_pages_to_delete(
+    db: &PlacesDb,
+    filter: &str,
+    params: &[(&str, &dyn rusqlite::ToSql)],
+) -> Result<Vec<DeletedPage>> {
+    let rows = db.query_rows_and_then(
+        &format!(
+            "SELECT h.id, h.guid, h.url, h.title, h.hidden, h.typed,
+                    h.preview_image_url, h.sync_status, h.sync_change_counter,
+                    v.visit_date, v.visit_type, v.is_local, v.unknown_fields
+             FROM moz_historyvisits v
+             JOIN moz_places h ON h.id = v.place_id
+             WHERE {}
+             ORDER BY h.id, v.visit_date",
+            filter
+        ),
+        params,
+        |row| -> Result<_> { Ok((row.get::<_, RowId>("id")?, page_from_row(row)?)) },
+    )?;
+    let mut pages: Vec<DeletedPage> = Vec::new();
+    let mut last_page_id = None;
+    for (page_id, mut page) in rows {
+        match pages.last_mut() {
+            Some(last) if last_page_id == Some(page_id) => last.visits.append(&mut page.visits),
+            _ => pages.push(page),
+        }
+        last_page_id = Some(page_id);
+    }
+    Ok(pages)
+}
+
+// Returns a page with a single visit from a row.
+fn page_from_row(row: &Row<'_>) -> Result<DeletedPage> {
+    let visit_type =
+        VisitType::from_primitive(row.get::<_, u8>("visit_type")?).unwrap_or(VisitType::Link);
+    Ok(DeletedPage {
+        guid: row.get("guid")?,
+        url: row.get("url")?,
+        title: row.get("title")?,
+        hidden: row.get("hidden")?,
+        typed: row.get("typed")?,
+        preview_image_url: row.get("preview_image_url")?,
+        sync_status: row.get("sync_status")?,
+        sync_change_counter: row.get("sync_change_counter")?,
+        visits: vec![DeletedVisit {
+            visit_date: row.get("visit_date")?,
+            visit_type,
+            is_local: row.get("is_local")?,
+            unknown_fields: row.get("unknown_fields")?,
+        }],
+    })
+}
+
+/// Undoes the deletion that returned `token`. Fails if the token has already
+/// been used, or the deletion is older than `UNDO_WINDOW`.
+pub fn restore(db: &PlacesDb, token: &UndoToken) -> Result<()> {
+    if token.is_expired() {
+        return Err(Error::CannotUndo("the undo window has expired"));
+    }
+    // Hold the lock until we're done, so that the token can't be used twice.
+    let mut snapshot = token.snapshot.lock();
+    let tx = db.begin_transaction()?;
+    let result = match snapshot.as_ref() {
+        Some(Snapshot::Bookmarks(deleted)) => restore_bookmarks(db, deleted),
+        Some(Snapshot::History(pages)) => restore_history(db, pages),
+        None => Err(Error::CannotUndo("the deletion was already undone")),
+    };
+    match result {
+        Ok(_) => {
+            tx.commit()?;
+            *snapshot = None;
+        }
+        Err(_) => tx.rollback()?,
+    }
+    result
+}
+
+fn restore_bookmarks(db: &PlacesDb, deleted: &DeletedBookmarks) -> Result<()> {
+    if get_raw_bookmark(db, deleted.tree.guid())?.is_some() {
+        return Err(Error::CannotUndo("the bookmark already exists"));
+    }
+    let mut item: InsertableItem = deleted.tree.clone().into();
+    // If the original parent is gone, put the item back in Unfiled instead of
+    // losing it.
+    match get_raw_bookmark(db, &deleted.parent_guid)? {
+        Some(parent) if parent.bookmark_type == BookmarkType::Folder => {
+            item.set_parent_guid(deleted.parent_guid.clone());
+            item.set_position(BookmarkPosition::Specific {
+                pos: deleted.position,
+            });
+        }
+        _ => {
+            item.set_parent_guid(BookmarkRootGuid::Unfiled.as_guid());
+            item.set_position(BookmarkPosition::Append);
+        }
+    }
+    insert_bookmark_in_tx(db, item)?;
+
+    // Put back tags and keywords that were removed since the deletion. We
+    // don't take a keyword back from a URL that's using it now, or replace a
+    // keyword that the URL has now.
+    for (url, tag) in &deleted.tags {
+        db.execute_cached(
+            "INSERT OR IGNORE INTO moz_tags(tag, lastModified)
+             VALUES(:tag, now())",
+            &[(":tag", tag)],
+        )?;
+        db.execute_cached(
+            "INSERT OR IGNORE INTO moz_tags_relation(tag_id, place_id)
+             VALUES((SELECT id FROM moz_tags WHERE tag = :tag),
+                    (SELECT id FROM moz_places
+                     WHERE url_hash = hash(:url) AND url = :url))",
+            &[(":tag", tag), (":url", url)],
+        )?;
+    }
+    for (url, keyword) in &deleted.keywords {
+        db.execute_cached(
+            "INSERT OR IGNORE INTO moz_keywords(place_id, keyword)
+             VALUES((SELECT id FROM moz_places
+                     WHERE url_hash = hash(:url) AND url = :url),
+                    :keyword)",
+            &[(":url", url), (":keyword", keyword)],
+        )?;
+    }
+
+    // Inserting flags every item as new; restore the original Sync status, so
+    // that items which were on the server replace their tombstones instead of
+    // being treated as new. Either way, they're uploaded on the next sync.
+    for (guid, sync_status, sync_change_counter) in &deleted.sync_info {
+        let changed = db.execute_cached(
+            "UPDATE moz_bookmarks SET
+               syncStatus = :sync_status,
+               syncChangeCounter = :sync_change_counter + 1
+             WHERE guid = :guid",
+            &[
+                (":sync_status", sync_status as &dyn rusqlite::ToSql),
+                (":sync_change_counter", sync_change_counter),
+                (":guid", guid),
+            ],
+        )?;
+        // Only remove tombstones for items that were actually restored.
+        if changed > 0 {
+            db.execute_cached(
+                "DELETE FROM moz_bookmarks_deleted WHERE guid = :guid",
+                &[(":guid", guid)],
+            )?;
+        }
+    }
+    delete_pending_temp_tables(db)?;
+    Ok(())
+}
+
+fn restore_history(db: &PlacesDb, pages: &[DeletedPage]) -> Result<()> {
+    for page in pages {
+        let url = Url::parse(&page.url)?;
+        // Pages with bookmarks, tags or keywords are kept when their visits
+        // are deleted; other pages need to be recreated.
+        let page_id = match fetch_page_info(db, &url)? {
+            Some(info) => info.page.row_id,
+            None => {
+                let page_id = new_page_info(db, &url, Some(page.guid.clone()))?.row_id;
+                db.execute_cached(
+                    "UPDATE moz_places SET
+                       title = :title,
+                       hidden = :hidden,
+                       typed = :typed,
+                       preview_image_url = :preview_image_url,
+                       sync_status = :sync_status,
+                       sync_change_counter = :sync_change_counter
+                     WHERE id = :page_id",
+                    &[
+                        (":title", &page.title as &dyn rusqlite::ToSql),
+                        (":hidden", &page.hidden),
+                        (":typed", &page.typed),
+                        (":preview_image_url", &page.preview_image_url),
+                        (":sync_status", &page.sync_status),
+                        (":sync_change_counter", &page.sync_change_counter),
+                        (":page_id", &page_id),
+                    ],
+                )?;
+                page_id
+            }
+        };
+        for visit in &page.visits {
+            // Skip visits that came back some other way, like from a sync.
+            let exists = db.exists(
+                "SELECT 1 FROM moz_historyvisits
+                 WHERE place_id = :page_id AND visit_date = :visit_date",
+                &[
+                    (":page_id", &page_id as &dyn rusqlite::ToSql),
+                    (":visit_date", &visit.visit_date),
+                ],
+            )?;
+            if !exists {
+                // This also removes the visit tombstone, if there is one.
+                add_visit(
+                    db,
+                    page_id,
+                    None,
+                    visit.visit_date,
+                    visit.visit_type,
+                    visit.is_local,
+                    visit.unknown_fields.clone(),
+                )?;
+            }
+        }
+        db.execute_cached(
+            "DELETE FROM moz_places_tombstones WHERE guid = :guid",
+            &[(":guid", &page.guid)],
+        )?;
+        db.execute_cached(
+            "UPDATE moz_places SET sync_change_counter = sync_change_counter + 1
+             WHERE id = :page_id",
+            &[(":page_id", &page_id)],
+        )?;
+        update_frecency(db, page_id, None)?;
+    }
+    delete_pending_temp_tables(db)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+    use crate::observation::VisitObservation;
+    use crate::storage::bookmarks::{insert_bookmark, InsertableBookmark, InsertableFolder};
+    use crate::storage::history::{apply_observation, url_to_guid};
+    use crate::storage::keywords::{
+        get_keyword_for_url, remove_keyword_for_url, set_keyword_for_url,
+    };
+    use crate::storage::tags::{get_tags_for_url, remove_all_tags_from_url, tag_url};
+    use pretty_assertions::assert_eq;
+
+    fn bookmark_sync_state(db: &PlacesDb, guid: &SyncGuid) -> (SyncStatus, u32) {
+        db.query_row_and_then_cachable(
+            "SELECT syncStatus, syncChangeCounter FROM moz_bookmarks WHERE guid = :guid",
+            &[(":guid", guid)],
+            |row| -> Result<_> { Ok((row.get(0)?, row.get(1)?)) },
+            false,
+        )
+        .expect("should get the sync state")
+    }
+
+    fn count_visits(db: &PlacesDb, url: &Url) -> u32 {
+        db.query_row_and_then_cachable(
+            "SELECT COUNT(*) FROM moz_historyvisits v
+             JOIN moz_places h ON h.id = v.place_id
+             WHERE h.url = :url",
+            &[(":url", &url.as_str())],
+            |row| row.get(0),
+            false,
+        )
+        .expect("should count visits")
+    }
+
+    #[test]
+    fn test_undo_bookmark_delete() -> Result<()> {
+        let conn = new_mem_connection();
+        let unfiled = BookmarkRootGuid::Unfiled.as_guid();
+        let insert = |guid: &str, position| -> Result<SyncGuid> {
+            insert_bookmark(
+                &conn,
+                InsertableBookmark {
+                    parent_guid: unfiled.clone(),
+                    position,
+                    date_added: None,
+                    last_modified: None,
+                    guid: Some(SyncGuid::from(guid)),
+                    url: Url::parse(&format!("https://{}.example.com", guid))?,
+                    title: None,
+                }
+                .into(),
+            )
+        };
+        insert("bookmark1___", BookmarkPosition::Append)?;
+        let folder_guid = insert_bookmark(
+            &conn,
+            InsertableFolder {
+                parent_guid: unfiled.clone(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: Some(SyncGuid::from("folder______")),
+                title: Some("folder".into()),
+                children: vec![InsertableBookmark {
+                    parent_guid: SyncGuid::from("folder______"),
+                    position: BookmarkPosition::Append,
+                    date_added: None,
+                    last_modified: None,
+                    guid: Some(SyncGuid::from("child_______")),
+                    url: Url::parse("https://child.example.com")?,
+                    title: Some("child".into()),
+                }
+                .into()],
+            }
+            .into(),
+        )?;
+        insert("bookmark2___", BookmarkPosition::Append)?;
+        let child_url = Url::parse("https://child.example.com")?;
+        tag_url(&conn, &child_url, "tag")?;
+        set_keyword_for_url(&conn, &child_url, "child")?;
+
+        // Pretend everything was synced.
+        conn.execute_cached(
+            "UPDATE moz_bookmarks SET syncStatus = :status, syncChangeCounter = 0",
+            &[(":status", &SyncStatus::Normal)],
+        )?;
+
+        let token =
+            delete_bookmark_with_undo(&conn, &folder_guid)?.expect("should return an undo token");
+        assert!(token.can_restore());
+        assert!(get_raw_bookmark(&conn, &folder_guid)?.is_none());
+        assert!(get_raw_bookmark(&conn, &SyncGuid::from("child_______"))?.is_none());
+        assert!(conn.exists(
+            "SELECT 1 FROM moz_bookmarks_deleted WHERE guid = 'folder______'",
+            [],
+        )?);
+        remove_all_tags_from_url(&conn, &child_url)?;
+        remove_keyword_for_url(&conn, &child_url)?;
+
+        restore(&conn, &token)?;
+        assert!(!token.can_restore());
+
+        let folder = get_raw_bookmark(&conn, &folder_guid)?.expect("folder should be restored");
+        assert_eq!(folder.position, 1);
+        assert_eq!(folder.title, Some("folder".to_string()));
+        let child = get_raw_bookmark(&conn, &SyncGuid::from("child_______"))?
+            .expect("child should be restored");
+        assert_eq!(child.parent_guid, Some(folder_guid.clone()));
+        assert_eq!(
+            get_tags_for_url(&conn, &child_url)?,
+            vec!["tag".to_string()]
+        );
+        assert_eq!(
+            get_keyword_for_url(&conn, &child_url)?,
+            Some("child".to_string())
+        );
+        assert_eq!(
+            get_raw_bookmark(&conn, &SyncGuid::from("bookmark2___"))?
+                .expect("should exist")
+                .position,
+            2
+        );
+
+        // The restored items replace their tombstones on the server.
+        assert!(!conn.exists("SELECT 1 FROM moz_bookmarks_deleted", [])?);
+        assert_eq!(
+            bookmark_sync_state(&conn, &folder_guid),
+            (SyncStatus::Normal, 1)
+        );
+        assert_eq!(
+            bookmark_sync_state(&conn, &SyncGuid::from("child_______")),
+            (SyncStatus::Normal, 1)
+        );
+
+        // A token can only be used once.
+        assert!(matches!(restore(&conn, &token), Err(Error::CannotUndo(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_bookmark_delete_missing_parent() -> Result<()> {
+        let conn = new_mem_connection();
+        let folder_guid = insert_bookmark(
+            &conn,
+            InsertableFolder {
+                parent_guid: BookmarkRootGuid::Menu.as_guid(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                title: Some("folder".into()),
+                children: vec![],
+            }
+            .into(),
+        )?;
+        let bookmark_guid = insert_bookmark(
+            &conn,
+            InsertableBookmark {
+                parent_guid: folder_guid.clone(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: Url::parse("https://example.com")?,
+                title: None,
+            }
+            .into(),
+        )?;
+        let token =
+            delete_bookmark_with_undo(&conn, &bookmark_guid)?.expect("should return an undo token");
+        delete_bookmark_with_undo(&conn, &folder_guid)?;
+
+        // The bookmark is restored into Unfiled, since its folder is gone.
+        restore(&conn, &token)?;
+        let bookmark =
+            get_raw_bookmark(&conn, &bookmark_guid)?.expect("bookmark should be restored");
+        assert_eq!(
+            bookmark.parent_guid,
+            Some(BookmarkRootGuid::Unfiled.as_guid())
+        );
+        assert_eq!(
+            bookmark_sync_state(&conn, &bookmark_guid),
+            (SyncStatus::New, 2)
+        );
+
+        assert!(delete_bookmark_with_undo(&conn, &SyncGuid::from("missing_____"))?.is_none());
+        assert!(delete_bookmark_with_undo(&conn, &BookmarkRootGuid::Menu.as_guid()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_bookmark_delete_invalid_url() -> Result<()> {
+        let conn = new_mem_connection();
+        let folder_guid = insert_bookmark(
+            &conn,
+            InsertableFolder {
+                parent_guid: BookmarkRootGuid::Unfiled.as_guid(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                title: Some("folder".into()),
+                children: vec![],
+            }
+            .into(),
+        )?;
+        let bookmark_guid = insert_bookmark(
+            &conn,
+            InsertableBookmark {
+                parent_guid: folder_guid.clone(),
+                position: BookmarkPosition::Append,
+                date_added: None,
+                last_modified: None,
+                guid: None,
+                url: Url::parse("https://example.com")?,
+                title: None,
+            }
+            .into(),
+        )?;
+        conn.execute_cached("UPDATE moz_places SET url = 'not a url'", [])?;
+
+        // Neither the bookmark nor its folder can be fully snapshotted, so
+        // they aren't deleted.
+        assert!(matches!(
+            delete_bookmark_with_undo(&conn, &bookmark_guid),
+            Err(Error::CannotUndo(_))
+        ));
+        assert!(matches!(
+            delete_bookmark_with_undo(&conn, &folder_guid),
+            Err(Error::CannotUndo(_))
+        ));
+        assert!(get_raw_bookmark(&conn, &bookmark_guid)?.is_some());
+        assert!(get_raw_bookmark(&conn, &folder_guid)?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_delete_visits() -> Result<()> {
+        let conn = new_mem_connection();
+        let url1 = Url::parse("https://www.example.com/1")?;
+        let url2 = Url::parse("https://www.example.com/2")?;
+        let now = Timestamp::now();
+        let earlier = Timestamp(now.0 - 10_000);
+        for (url, when) in &[(&url1, earlier), (&url1, now), (&url2, now)] {
+            apply_observation(
+                &conn,
+                VisitObservation::new((*url).clone())
+                    .with_visit_type(VisitType::Link)
+                    .with_at(Some(*when))
+                    .with_title(Some("title".to_string())),
+            )?;
+        }
+        let guid1 = url_to_guid(&conn, &url1)?.expect("should exist");
+        conn.execute_cached(
+            "UPDATE moz_places SET sync_status = :status, sync_change_counter = 0",
+            &[(":status", &SyncStatus::Normal)],
+        )?;
+
+        let token = delete_visits_for_with_undo(&conn, &guid1)?;
+        assert!(url_to_guid(&conn, &url1)?.is_none());
+        assert!(conn.exists("SELECT 1 FROM moz_places_tombstones", [])?);
+
+        restore(&conn, &token)?;
+        assert_eq!(url_to_guid(&conn, &url1)?, Some(guid1.clone()));
+        assert_eq!(count_visits(&conn, &url1), 2);
+        assert!(!conn.exists("SELECT 1 FROM moz_places_tombstones", [])?);
+        let page = fetch_page_info(&conn, &url1)?.expect("should exist").page;
+        assert_eq!(page.title, "title");
+        assert_eq!(page.sync_status, SyncStatus::Normal);
+        assert_eq!(page.sync_change_counter, 1);
+        assert!(page.frecency > 0);
+
+        // Deleting a range only removes, and restores, the visits in it.
+        let token = delete_visits_between_with_undo(&conn, now, now)?;
+        assert_eq!(count_visits(&conn, &url1), 1);
+        assert!(url_to_guid(&conn, &url2)?.is_none());
+        assert!(conn.exists("SELECT 1 FROM moz_historyvisit_tombstones", [])?);
+
+        restore(&conn, &token)?;
+        assert_eq!(count_visits(&conn, &url1), 2);
+        assert_eq!(count_visits(&conn, &url2), 1);
+        assert!(!conn.exists("SELECT 1 FROM moz_historyvisit_tombstones", [])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_expired() -> Result<()> {
+        let conn = new_mem_connection();
+        let url = Url::parse("https://www.example.com")?;
+        apply_observation(
+            &conn,
+            VisitObservation::new(url.clone()).with_visit_type(VisitType::Link),
+        )?;
+        let guid = url_to_guid(&conn, &url)?.expect("should exist");
+        let mut token = delete_visits_for_with_undo(&conn, &guid)?;
+        token.deleted_at = Timestamp::now()
+            .checked_sub(UNDO_WINDOW + Duration::from_secs(1))
+            .unwrap();
+        assert!(!token.can_restore());
+        assert!(matches!(restore(&conn, &token), Err(Error::CannotUndo(_))));
+        assert!(url_to_guid(&conn, &url)?.is_none());
+        Ok(())
+    }
+}
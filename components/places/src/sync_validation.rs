@@ -0,0 +1,125 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Settings shared by the bookmarks and history validators. Validating
+// fetches every record in the collection, so each engine validates at most
+// once a day, and only when the app has turned validation on.
+
+use crate::db::PlacesDb;
+use crate::error::*;
+use crate::storage::{get_meta, put_meta};
+use std::time::Duration;
+use types::Timestamp;
+
+/// How often each engine validates, if validation is turned on.
+const VALIDATION_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
+const MODE_META_KEY: &str = "sync_validation_mode";
+
+/// Whether the bookmarks and history sync engines validate their local data
+/// against the server, and what they do about the problems they find.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncValidationMode {
+    #[default]
+    Disabled = 0,
+    /// Problems are reported in the sync telemetry.
+    Validate = 1,
+    /// Problems are reported, and fixed by uploading the local items, or by
+    /// downloading the server records again.
+    Repair = 2,
+}
+
+impl SyncValidationMode {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Self::Validate,
+            2 => Self::Repair,
+            _ => Self::Disabled,
+        }
+    }
+}
+
+pub fn set_sync_validation_mode(db: &PlacesDb, mode: SyncValidationMode) -> Result<()> {
+    put_meta(db, MODE_META_KEY, &(mode as u8))
+}
+
+pub fn get_sync_validation_mode(db: &PlacesDb) -> Result<SyncValidationMode> {
+    Ok(get_meta::<u8>(db, MODE_META_KEY)?
+        .map(SyncValidationMode::from_u8)
+        .unwrap_or_default())
+}
+
+/// Returns the mode an engine should validate with on this sync, or
+/// `Disabled` if validation is off or the engine validated recently.
+/// `last_validation_key` is the `moz_meta` key for when the engine last
+/// validated.
+pub(crate) fn validation_mode_if_due(
+    db: &PlacesDb,
+    last_validation_key: &str,
+    now: Timestamp,
+) -> Result<SyncValidationMode> {
+    let mode = get_sync_validation_mode(db)?;
+    if mode == SyncValidationMode::Disabled {
+        return Ok(mode);
+    }
+    let due = match get_meta::<Timestamp>(db, last_validation_key)? {
+        // If the clock went backwards, validate again.
+        Some(last) => now
+            .duration_since(last)
+            .map_or(true, |elapsed| elapsed >= VALIDATION_INTERVAL),
+        None => true,
+    };
+    Ok(if due {
+        mode
+    } else {
+        SyncValidationMode::Disabled
+    })
+}
+
+pub(crate) fn note_validated(
+    db: &PlacesDb,
+    last_validation_key: &str,
+    now: Timestamp,
+) -> Result<()> {
+    put_meta(db, last_validation_key, &now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::places_api::test::new_mem_connection;
+
+    #[test]
+    fn test_validation_mode_if_due() -> Result<()> {
+        let conn = new_mem_connection();
+        let now = Timestamp::now();
+        assert_eq!(
+            get_sync_validation_mode(&conn)?,
+            SyncValidationMode::Disabled
+        );
+        assert_eq!(
+            validation_mode_if_due(&conn, "test_last_validation", now)?,
+            SyncValidationMode::Disabled
+        );
+
+        set_sync_validation_mode(&conn, SyncValidationMode::Repair)?;
+        assert_eq!(
+            validation_mode_if_due(&conn, "test_last_validation", now)?,
+            SyncValidationMode::Repair
+        );
+
+        note_validated(&conn, "test_last_validation", now)?;
+        assert_eq!(
+            validation_mode_if_due(&conn, "test_last_validation", now)?,
+            SyncValidationMode::Disabled
+        );
+        let tomorrow = now.checked_add(VALIDATION_INTERVAL).unwrap();
+        assert_eq!(
+            validation_mode_if_due(&conn, "test_last_validation", tomorrow)?,
+            SyncValidationMode::Repair
+        );
+        Ok(())
+    }
+}
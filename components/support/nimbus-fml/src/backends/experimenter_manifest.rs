@@ -6,6 +6,7 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Number;
 
 use crate::{
     command_line::commands::GenerateExperimenterManifestCmd,
@@ -36,6 +37,48 @@ pub(crate) struct ExperimenterFeatureProperty {
     #[serde(rename = "enum")]
     #[serde(skip_serializing_if = "Option::is_none")]
     variants: Option<BTreeSet<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    minimum: Option<Number>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    maximum: Option<Number>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<String>,
+}
+
+impl ExperimenterFeatureProperty {
+    /// Adds the constraints implied by the property's type, and its `min` and `max`.
+    ///
+    /// Collections are represented as `json`, so their items can't be constrained here.
+    fn add_constraints(&mut self, prop: &PropDef) {
+        let typ = match prop.typ() {
+            TypeRef::Option(inner) => *inner,
+            typ => typ,
+        };
+        match typ {
+            TypeRef::Int | TypeRef::Float => {
+                self.minimum = prop.min.clone();
+                self.maximum = prop.max.clone();
+            }
+            TypeRef::Duration => {
+                // Durations are never negative.
+                self.minimum = prop.min.clone().or_else(|| Some(0.into()));
+                self.maximum = prop.max.clone();
+            }
+            TypeRef::Url => {
+                self.format = Some("uri".to_string());
+            }
+            TypeRef::Color => {
+                self.pattern = Some("^#([0-9a-fA-F]{6}|[0-9a-fA-F]{8})$".to_string());
+            }
+            _ => (),
+        }
+    }
 }
 
 impl TryFrom<FeatureManifest> for ExperimenterManifest {
@@ -71,7 +114,13 @@ impl FeatureManifest {
         props.iter().try_for_each(|prop| -> Result<()> {
             let typ = ExperimentManifestPropType::from(prop.typ()).to_string();
 
-            let yaml_prop = if let TypeRef::Enum(e) = prop.typ() {
+            let mut yaml_prop = ExperimenterFeatureProperty {
+                description: prop.doc(),
+                property_type: typ,
+                ..Default::default()
+            };
+
+            if let TypeRef::Enum(e) = prop.typ() {
                 let enum_def = self
                     .find_enum(&e)
                     .ok_or(FMLError::InternalError("Found enum with no definition"))?;
@@ -82,18 +131,9 @@ impl FeatureManifest {
                     .map(|variant| variant.name())
                     .collect::<BTreeSet<String>>();
 
-                ExperimenterFeatureProperty {
-                    variants: Some(variants),
-                    description: prop.doc(),
-                    property_type: typ,
-                }
-            } else {
-                ExperimenterFeatureProperty {
-                    variants: None,
-                    description: prop.doc(),
-                    property_type: typ,
-                }
-            };
+                yaml_prop.variants = Some(variants);
+            }
+            yaml_prop.add_constraints(prop);
             map.insert(prop.name(), yaml_prop);
             Ok(())
         })?;
@@ -105,6 +145,7 @@ enum ExperimentManifestPropType {
     Json,
     Boolean,
     Int,
+    Float,
     String,
 }
 
@@ -113,6 +154,7 @@ impl Display for ExperimentManifestPropType {
         let s = match self {
             ExperimentManifestPropType::Boolean => "boolean",
             ExperimentManifestPropType::Int => "int",
+            ExperimentManifestPropType::Float => "float",
             ExperimentManifestPropType::Json => "json",
             ExperimentManifestPropType::String => "string",
         };
@@ -128,8 +170,11 @@ impl From<TypeRef> for ExperimentManifestPropType {
             | TypeRef::StringMap(_)
             | TypeRef::List(_) => Self::Json,
             TypeRef::Boolean => Self::Boolean,
-            TypeRef::Int => Self::Int,
+            TypeRef::Int | TypeRef::Duration => Self::Int,
+            TypeRef::Float => Self::Float,
             TypeRef::String
+            | TypeRef::Url
+            | TypeRef::Color
             | TypeRef::BundleImage
             | TypeRef::BundleText
             | TypeRef::StringAlias(_)
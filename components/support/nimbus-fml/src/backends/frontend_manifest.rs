@@ -117,6 +117,8 @@ impl From<PropDef> for FieldBody {
             description: value.doc,
             variable_type: value.typ.to_string(),
             default: Some(value.default),
+            min: value.min,
+            max: value.max,
         }
     }
 }
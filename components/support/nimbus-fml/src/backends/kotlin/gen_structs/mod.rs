@@ -150,6 +150,10 @@ impl ConcreteCodeOracle {
                 Box::new(primitives::StringCodeType)
             }
             TypeIdentifier::Int => Box::new(primitives::IntCodeType),
+            TypeIdentifier::Float => Box::new(primitives::FloatCodeType),
+            TypeIdentifier::Url => Box::new(primitives::UrlCodeType),
+            TypeIdentifier::Color => Box::new(primitives::ColorCodeType),
+            TypeIdentifier::Duration => Box::new(primitives::DurationCodeType),
 
             TypeIdentifier::BundleText => Box::new(bundled::TextCodeType),
             TypeIdentifier::BundleImage => Box::new(bundled::ImageCodeType),
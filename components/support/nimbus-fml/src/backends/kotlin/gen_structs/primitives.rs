@@ -6,7 +6,7 @@ use std::fmt::Display;
 
 use super::common::{code_type, quoted};
 use crate::backends::{CodeOracle, CodeType, LiteralRenderer, VariablesType};
-use crate::intermediate_representation::Literal;
+use crate::intermediate_representation::{parse_color, Literal};
 
 pub(crate) struct BooleanCodeType;
 
@@ -210,6 +210,277 @@ impl CodeType for StringCodeType {
     }
 }
 
+pub(crate) struct FloatCodeType;
+
+impl CodeType for FloatCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "Double".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Double
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            // The debug representation always has a decimal point or an exponent,
+            // so it is never mistaken for an `Int`.
+            serde_json::Value::Number(v) => format!("{:?}", v.as_f64().unwrap_or_default()),
+            _ => unreachable!("Expecting a number"),
+        }
+    }
+}
+
+pub(crate) struct UrlCodeType;
+
+impl CodeType for UrlCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "Uri".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::String
+    }
+
+    /// A function handle that is capable of turning the variables type to the TypeRef type.
+    fn create_transform(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some("Uri::parse".into())
+    }
+
+    fn as_json_transform(&self, _oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        Some(format!("{}.toString()", prop))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::String(v) => format!("Uri.parse({})", quoted(v)),
+            _ => unreachable!("Expecting a string"),
+        }
+    }
+
+    fn imports(&self, _oracle: &dyn CodeOracle) -> Option<Vec<String>> {
+        Some(vec!["android.net.Uri".to_string()])
+    }
+}
+
+/// Colors are represented as ARGB `Int`s, as used by `android.graphics.Color`.
+pub(crate) struct ColorCodeType;
+
+impl CodeType for ColorCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "Int".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::String
+    }
+
+    /// A function handle that is capable of turning the variables type to the TypeRef type.
+    fn create_transform(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some("String::asColorInt".into())
+    }
+
+    fn as_json_transform(&self, _oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        Some(format!("{}.asColorString()", prop))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::String(v) => {
+                let argb = parse_color(v).unwrap_or_else(|| unreachable!("Expecting a color"));
+                format!("0x{:08X}.toInt()", argb)
+            }
+            _ => unreachable!("Expecting a string"),
+        }
+    }
+
+    fn imports(&self, _oracle: &dyn CodeOracle) -> Option<Vec<String>> {
+        Some(vec![
+            "org.mozilla.experiments.nimbus.asColorInt".to_string(),
+            "org.mozilla.experiments.nimbus.asColorString".to_string(),
+        ])
+    }
+}
+
+/// Durations are represented in JSON as a whole number of milliseconds.
+pub(crate) struct DurationCodeType;
+
+impl CodeType for DurationCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "Duration".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Int
+    }
+
+    /// A function handle that is capable of turning the variables type to the TypeRef type.
+    ///
+    /// `Int.milliseconds` is an extension property declared in `Duration.Companion`, so it
+    /// can't be used as a function reference.
+    fn create_transform(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some("{ it.milliseconds }".into())
+    }
+
+    fn as_json_transform(&self, _oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        Some(format!("{}.inWholeMilliseconds", prop))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::Number(v) => format!("{}.milliseconds", v),
+            _ => unreachable!("Expecting a number"),
+        }
+    }
+
+    fn imports(&self, _oracle: &dyn CodeOracle) -> Option<Vec<String>> {
+        Some(vec![
+            "kotlin.time.Duration".to_string(),
+            "kotlin.time.Duration.Companion.milliseconds".to_string(),
+        ])
+    }
+}
+
 #[cfg(test)]
 mod unit_tests {
 
@@ -255,6 +526,22 @@ mod unit_tests {
         Box::new(IntCodeType) as Box<dyn CodeType>
     }
 
+    fn float_type() -> Box<dyn CodeType> {
+        Box::new(FloatCodeType) as Box<dyn CodeType>
+    }
+
+    fn url_type() -> Box<dyn CodeType> {
+        Box::new(UrlCodeType) as Box<dyn CodeType>
+    }
+
+    fn color_type() -> Box<dyn CodeType> {
+        Box::new(ColorCodeType) as Box<dyn CodeType>
+    }
+
+    fn duration_type() -> Box<dyn CodeType> {
+        Box::new(DurationCodeType) as Box<dyn CodeType>
+    }
+
     #[test]
     fn test_type_label() {
         let oracle = &*oracle();
@@ -267,6 +554,18 @@ mod unit_tests {
 
         let ct = int_type();
         assert_eq!("Int".to_string(), ct.type_label(oracle));
+
+        let ct = float_type();
+        assert_eq!("Double".to_string(), ct.type_label(oracle));
+
+        let ct = url_type();
+        assert_eq!("Uri".to_string(), ct.type_label(oracle));
+
+        let ct = color_type();
+        assert_eq!("Int".to_string(), ct.type_label(oracle));
+
+        let ct = duration_type();
+        assert_eq!("Duration".to_string(), ct.type_label(oracle));
     }
 
     #[test]
@@ -298,6 +597,38 @@ mod unit_tests {
         let ct = int_type();
         assert_eq!("1".to_string(), ct.literal(oracle, &ctx, finder, &json!(1)));
         assert_eq!("2".to_string(), ct.literal(oracle, &ctx, finder, &json!(2)));
+
+        let ct = float_type();
+        assert_eq!(
+            "0.5".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(0.5))
+        );
+        assert_eq!(
+            "2.0".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(2))
+        );
+
+        let ct = url_type();
+        assert_eq!(
+            r#"Uri.parse("https://example.com/")"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("https://example.com/"))
+        );
+
+        let ct = color_type();
+        assert_eq!(
+            "0xFF336699.toInt()".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("#336699"))
+        );
+        assert_eq!(
+            "0x80336699.toInt()".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("#80336699"))
+        );
+
+        let ct = duration_type();
+        assert_eq!(
+            "1500.milliseconds".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(1500))
+        );
     }
 
     #[test]
@@ -321,5 +652,51 @@ mod unit_tests {
             r#"v.getInt("the-property")"#.to_string(),
             ct.value_getter(oracle, &"v", &"the-property")
         );
+
+        let ct = float_type();
+        assert_eq!(
+            r#"v.getDouble("the-property")"#.to_string(),
+            ct.value_getter(oracle, &"v", &"the-property")
+        );
+    }
+
+    #[test]
+    fn test_property_getter() {
+        let oracle = &*oracle();
+
+        let ct = url_type();
+        assert_eq!(
+            r#"v.getString("the-property")?.let(Uri::parse) ?: def"#.to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+
+        let ct = color_type();
+        assert_eq!(
+            r#"v.getString("the-property")?.let(String::asColorInt) ?: def"#.to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+
+        let ct = duration_type();
+        assert_eq!(
+            r#"v.getInt("the-property")?.let({ it.milliseconds }) ?: def"#.to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+    }
+
+    #[test]
+    fn test_as_json() {
+        let oracle = &*oracle();
+
+        let ct = url_type();
+        assert_eq!("p.toString()".to_string(), ct.as_json(oracle, &"p"));
+
+        let ct = color_type();
+        assert_eq!("p.asColorString()".to_string(), ct.as_json(oracle, &"p"));
+
+        let ct = duration_type();
+        assert_eq!(
+            "p.inWholeMilliseconds".to_string(),
+            ct.as_json(oracle, &"p")
+        );
     }
 }
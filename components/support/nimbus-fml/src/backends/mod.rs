@@ -247,6 +247,7 @@ pub trait CodeDeclaration {
 /// This is the `Variables` object. This enum gives the underlying types that the `Variables` object supports.
 pub enum VariablesType {
     Bool,
+    Double,
     Image,
     Int,
     String,
@@ -261,6 +262,7 @@ impl Display for VariablesType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let nm = match self {
             VariablesType::Bool => "Bool",
+            VariablesType::Double => "Double",
             VariablesType::Image => "Image",
             VariablesType::Int => "Int",
             VariablesType::String => "String",
@@ -274,4 +276,6 @@ impl Display for VariablesType {
 pub(crate) mod experimenter_manifest;
 pub(crate) mod frontend_manifest;
 pub(crate) mod kotlin;
+pub(crate) mod rust;
 pub(crate) mod swift;
+pub(crate) mod typescript;
@@ -0,0 +1,113 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Rust consumers don't have a resource bundle like Android or iOS apps do, so bundled
+//! text and images are represented by the strings in the feature JSON: either the text
+//! itself or a resource identifier, which the consumer may resolve itself.
+
+use std::fmt::Display;
+
+use super::common::{code_type, quoted};
+use crate::backends::{CodeOracle, CodeType, LiteralRenderer, VariablesType};
+use crate::intermediate_representation::Literal;
+
+pub(crate) struct TextCodeType;
+
+impl CodeType for TextCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "String".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Text
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::String(v) => format!("{}.to_string()", quoted(v)),
+            _ => unreachable!("Expecting a string"),
+        }
+    }
+}
+
+pub(crate) struct ImageCodeType;
+
+impl CodeType for ImageCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "String".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Image
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::String(v) => format!("{}.to_string()", quoted(v)),
+            _ => unreachable!("Expecting a string"),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// /* This Source Code Form is subject to the terms of the Mozilla Public
+//  * License, v. 2.0. If a copy of the MPL was not distributed with this
+//  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+use heck::{CamelCase, SnakeCase};
+use std::fmt::Display;
+
+/// Get the idiomatic Rust rendering of a type name (for enums, objects, features, etc).
+pub fn class_name(nm: &dyn Display) -> String {
+    nm.to_string().to_camel_case()
+}
+
+/// Get the idiomatic Rust rendering of a field name.
+///
+/// Property names which collide with Rust keywords are rendered as raw identifiers.
+pub fn var_name(nm: &dyn Display) -> String {
+    let nm = nm.to_string().to_snake_case();
+    match nm.as_str() {
+        "as" | "async" | "await" | "box" | "break" | "const" | "continue" | "dyn" | "else"
+        | "enum" | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop"
+        | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "static" | "struct"
+        | "trait" | "true" | "try" | "type" | "unsafe" | "use" | "where" | "while" | "yield" => {
+            format!("r#{}", nm)
+        }
+        _ => nm,
+    }
+}
+
+/// Get the idiomatic Rust rendering of an individual enum variant.
+pub fn enum_variant_name(nm: &dyn Display) -> String {
+    nm.to_string().to_camel_case()
+}
+
+/// Surrounds a string with quotes.
+/// The `Debug` representation of a `str` is a valid Rust string literal, with all the
+/// escaping already done.
+pub fn quoted(string: &dyn Display) -> String {
+    format!("{:?}", string.to_string())
+}
+
+pub(crate) mod code_type {
+    use std::fmt::Display;
+
+    use crate::backends::{CodeOracle, CodeType};
+
+    use super::var_name;
+
+    /// The language specific expression that gets a value of the `prop` from the `vars` object.
+    ///
+    /// The generated structs are deserialized from the feature JSON with the defaults already
+    /// merged in, so the value is just a field of the struct.
+    pub(crate) fn property_getter(
+        ct: &dyn CodeType,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        _default: &dyn Display,
+    ) -> String {
+        ct.value_getter(oracle, vars, prop)
+    }
+
+    pub(crate) fn value_getter(
+        _ct: &dyn CodeType,
+        _oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        format!("{vars}.{prop}", vars = vars, prop = var_name(prop))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_var_name() {
+        assert_eq!(var_name(&"a-string"), "a_string");
+        assert_eq!(var_name(&"anObject"), "an_object");
+        assert_eq!(var_name(&"type"), "r#type");
+    }
+
+    #[test]
+    fn test_quoted() {
+        assert_eq!(quoted(&"simple"), r#""simple""#);
+        assert_eq!(quoted(&"a \"quoted\" string"), r#""a \"quoted\" string""#);
+        assert_eq!(quoted(&"a\nnewline"), r#""a\nnewline""#);
+    }
+}
@@ -0,0 +1,64 @@
+// /* This Source Code Form is subject to the terms of the Mozilla Public
+//  * License, v. 2.0. If a copy of the MPL was not distributed with this
+//  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+use super::{common, ConcreteCodeOracle};
+use std::borrow::Borrow;
+use std::fmt;
+
+use crate::backends::{CodeOracle, LiteralRenderer, TypeIdentifier};
+use crate::intermediate_representation::Literal;
+
+pub fn type_label(type_: impl Borrow<TypeIdentifier>) -> Result<String, askama::Error> {
+    let oracle = ConcreteCodeOracle;
+    Ok(oracle.find(type_.borrow()).type_label(&oracle))
+}
+
+pub fn literal(
+    type_: impl Borrow<TypeIdentifier>,
+    renderer: impl LiteralRenderer,
+    literal: impl Borrow<Literal>,
+    ctx: impl fmt::Display,
+) -> Result<String, askama::Error> {
+    let oracle = ConcreteCodeOracle;
+    Ok(oracle
+        .find(type_.borrow())
+        .literal(&oracle, &ctx, &renderer, literal.borrow()))
+}
+
+/// Get the idiomatic Rust rendering of a type name (for enums, objects, features, etc).
+pub fn class_name(nm: impl fmt::Display) -> Result<String, askama::Error> {
+    Ok(common::class_name(&nm))
+}
+
+/// Get the idiomatic Rust rendering of a field name.
+pub fn var_name(nm: impl fmt::Display) -> Result<String, askama::Error> {
+    Ok(common::var_name(&nm))
+}
+
+/// Get the idiomatic Rust rendering of an individual enum variant.
+pub fn enum_variant_name(nm: impl fmt::Display) -> Result<String, askama::Error> {
+    Ok(common::enum_variant_name(&nm))
+}
+
+/// Renders the text as `///` doc comment lines. The first line is not indented, as the template
+/// is expected to have already done that. Empty docs render as nothing at all.
+pub fn comment(txt: impl fmt::Display, spaces: &str) -> Result<String, askama::Error> {
+    use textwrap::{fill, Options};
+
+    let indent_start = "/// ".to_string();
+    let indent_mid = format!("{}/// ", spaces);
+
+    let options = Options::new(100)
+        .initial_indent(&indent_start)
+        .subsequent_indent(&indent_mid);
+
+    let txt = txt.to_string();
+    if txt.trim().is_empty() {
+        return Ok(String::new());
+    }
+    Ok(fill(txt.as_str(), options))
+}
+
+pub fn quoted(txt: impl fmt::Display) -> Result<String, askama::Error> {
+    Ok(common::quoted(&txt))
+}
@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use askama::Template;
+
+use crate::{
+    backends::{CodeDeclaration, CodeOracle, CodeType, TypeIdentifier},
+    intermediate_representation::{FeatureDef, FeatureManifest},
+};
+
+mod bundled;
+mod common;
+mod enum_;
+mod feature;
+mod filters;
+mod object;
+mod primitives;
+mod structural;
+
+/// Unlike the other backends, the Rust backend generates a single self-contained module.
+///
+/// Features, objects and enums from imported manifests are declared in the same module as those
+/// from the importing manifest, using the defaults as overridden by the importing manifest.
+#[derive(Template)]
+#[template(syntax = "rs", escape = "none", path = "FeatureManifestTemplate.rs")]
+pub struct FeatureManifestDeclaration<'a> {
+    fm: &'a FeatureManifest,
+    oracle: ConcreteCodeOracle,
+}
+
+impl<'a> FeatureManifestDeclaration<'a> {
+    pub fn new(fm: &'a FeatureManifest) -> Self {
+        Self {
+            fm,
+            oracle: Default::default(),
+        }
+    }
+
+    pub fn members(&self) -> Vec<Box<dyn CodeDeclaration + 'a>> {
+        let fm = self.fm;
+
+        fm.iter_all_feature_defs()
+            .map(|(fm, inner)| {
+                Box::new(feature::FeatureCodeDeclaration::new(fm, inner))
+                    as Box<dyn CodeDeclaration>
+            })
+            .chain(fm.iter_all_enum_defs().map(|(fm, inner)| {
+                Box::new(enum_::EnumCodeDeclaration::new(fm, inner)) as Box<dyn CodeDeclaration>
+            }))
+            .chain(fm.iter_all_object_defs().map(|(fm, inner)| {
+                Box::new(object::ObjectCodeDeclaration::new(fm, inner)) as Box<dyn CodeDeclaration>
+            }))
+            .collect()
+    }
+
+    pub fn iter_feature_defs(&self) -> Vec<&FeatureDef> {
+        self.fm.iter_all_feature_defs().map(|(_, f)| f).collect()
+    }
+
+    pub fn declaration_code(&self) -> Vec<String> {
+        let oracle = &self.oracle;
+        self.members()
+            .into_iter()
+            .filter_map(|member| member.definition_code(oracle))
+            .collect()
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct ConcreteCodeOracle;
+
+impl ConcreteCodeOracle {
+    fn create_code_type(&self, type_: TypeIdentifier) -> Box<dyn CodeType> {
+        match type_ {
+            TypeIdentifier::Boolean => Box::new(primitives::BooleanCodeType),
+            TypeIdentifier::String | TypeIdentifier::StringAlias(_) => {
+                Box::new(primitives::StringCodeType)
+            }
+            TypeIdentifier::Int => Box::new(primitives::IntCodeType),
+            TypeIdentifier::Float => Box::new(primitives::FloatCodeType),
+            TypeIdentifier::Duration => Box::new(primitives::DurationCodeType),
+            // URLs and colors have already been validated in the defaults, and there's no
+            // url or color type in `std`.
+            TypeIdentifier::Url | TypeIdentifier::Color => Box::new(primitives::StringCodeType),
+
+            TypeIdentifier::BundleText => Box::new(bundled::TextCodeType),
+            TypeIdentifier::BundleImage => Box::new(bundled::ImageCodeType),
+
+            TypeIdentifier::Enum(id) => Box::new(enum_::EnumCodeType::new(id)),
+            TypeIdentifier::Object(id) => Box::new(object::ObjectCodeType::new(id)),
+
+            TypeIdentifier::Option(ref inner) => Box::new(structural::OptionalCodeType::new(inner)),
+            TypeIdentifier::List(ref inner) => Box::new(structural::ListCodeType::new(inner)),
+            TypeIdentifier::StringMap(ref v_type) => {
+                let k_type = &TypeIdentifier::String;
+                Box::new(structural::MapCodeType::new(k_type, v_type))
+            }
+            TypeIdentifier::EnumMap(ref k_type, ref v_type) => {
+                Box::new(structural::MapCodeType::new(k_type, v_type))
+            }
+        }
+    }
+}
+
+impl CodeOracle for ConcreteCodeOracle {
+    fn find(&self, type_: &TypeIdentifier) -> Box<dyn CodeType> {
+        self.create_code_type(type_.clone())
+    }
+}
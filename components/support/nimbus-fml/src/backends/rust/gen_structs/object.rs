@@ -0,0 +1,231 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use askama::Template;
+use std::fmt::Display;
+
+use crate::backends::{
+    CodeDeclaration, CodeOracle, CodeType, LiteralRenderer, TypeIdentifier, VariablesType,
+};
+use crate::intermediate_representation::{FeatureManifest, Literal, ObjectDef};
+
+use super::filters;
+
+use super::common::{self, code_type};
+
+pub struct ObjectCodeType {
+    id: String,
+}
+
+impl ObjectCodeType {
+    pub fn new(id: String) -> Self {
+        Self { id }
+    }
+}
+
+impl CodeType for ObjectCodeType {
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        common::class_name(&self.id)
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Variables
+    }
+
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        ctx: &dyn Display,
+        renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        renderer.literal(
+            oracle,
+            &TypeIdentifier::Object(self.id.clone()),
+            literal,
+            ctx,
+        )
+    }
+}
+
+#[derive(Template)]
+#[template(syntax = "rs", escape = "none", path = "ObjectTemplate.rs")]
+pub(crate) struct ObjectCodeDeclaration {
+    inner: ObjectDef,
+    fm: FeatureManifest,
+}
+
+impl ObjectCodeDeclaration {
+    pub fn new(fm: &FeatureManifest, inner: &ObjectDef) -> Self {
+        Self {
+            fm: fm.clone(),
+            inner: inner.clone(),
+        }
+    }
+    pub fn inner(&self) -> ObjectDef {
+        self.inner.clone()
+    }
+}
+
+impl CodeDeclaration for ObjectCodeDeclaration {
+    fn definition_code(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some(self.render().unwrap())
+    }
+}
+
+impl LiteralRenderer for ObjectCodeDeclaration {
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        typ: &TypeIdentifier,
+        value: &Literal,
+        ctx: &dyn Display,
+    ) -> String {
+        object_literal(&self.fm, ctx, &self, oracle, typ, value)
+    }
+}
+
+/// Renders a struct expression for the object.
+///
+/// Literals in the manifest need not mention every property of the object, so any missing
+/// fields are taken from the object's own defaults, with the `..Default::default()` syntax.
+pub(crate) fn object_literal(
+    fm: &FeatureManifest,
+    ctx: &dyn Display,
+    renderer: &dyn LiteralRenderer,
+    oracle: &dyn CodeOracle,
+    typ: &TypeIdentifier,
+    value: &Literal,
+) -> String {
+    let id = if let TypeIdentifier::Object(id) = typ {
+        id
+    } else {
+        return oracle.find(typ).literal(oracle, ctx, renderer, value);
+    };
+    let literal_map = if let Literal::Object(map) = value {
+        map
+    } else {
+        unreachable!(
+            "An JSON object is expected for {} object literal",
+            oracle.find(typ).type_label(oracle)
+        )
+    };
+
+    let def = fm.find_object(id).unwrap();
+
+    let mut args: Vec<String> = literal_map
+        .iter()
+        .map(|(k, v)| {
+            let prop = def.find_prop(k);
+
+            format!(
+                "{var_name}: {var_value}",
+                var_name = common::var_name(k),
+                var_value = oracle.find(&prop.typ).literal(oracle, ctx, renderer, v)
+            )
+        })
+        .collect();
+
+    if literal_map.len() < def.props.len() {
+        args.push("..Default::default()".to_string());
+    }
+
+    format!(
+        "{typelabel} {{ {args} }}",
+        typelabel = oracle.find(typ).type_label(oracle),
+        args = args.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use serde_json::json;
+
+    use crate::{backends::TypeIdentifier, intermediate_representation::Literal};
+
+    use super::*;
+
+    struct TestCodeOracle;
+    impl CodeOracle for TestCodeOracle {
+        fn find(&self, _type_: &TypeIdentifier) -> Box<dyn CodeType> {
+            unreachable!()
+        }
+    }
+
+    struct TestRenderer;
+    impl LiteralRenderer for TestRenderer {
+        fn literal(
+            &self,
+            _oracle: &dyn CodeOracle,
+            typ: &TypeIdentifier,
+            _value: &Literal,
+            _ctx: &dyn Display,
+        ) -> String {
+            if let TypeIdentifier::Object(nm) = typ {
+                format!("{} {{ ..Default::default() }}", nm)
+            } else {
+                unreachable!()
+            }
+        }
+    }
+
+    fn oracle() -> Box<dyn CodeOracle> {
+        Box::new(TestCodeOracle) as Box<dyn CodeOracle>
+    }
+
+    fn code_type(name: &str) -> Box<dyn CodeType> {
+        Box::new(ObjectCodeType::new(name.to_string())) as Box<dyn CodeType>
+    }
+
+    #[test]
+    fn test_type_label() {
+        let ct = code_type("AnObject");
+        let oracle = &*oracle();
+        assert_eq!("AnObject".to_string(), ct.type_label(oracle))
+    }
+
+    #[test]
+    fn test_literal() {
+        let ct = code_type("AnObject");
+        let oracle = &*oracle();
+        let finder = &TestRenderer;
+        let ctx = "ctx".to_string();
+        assert_eq!(
+            "AnObject { ..Default::default() }".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!({}))
+        );
+    }
+
+    #[test]
+    fn test_get_value() {
+        let ct = code_type("AnObject");
+        let oracle = &*oracle();
+
+        assert_eq!(
+            "v.the_property".to_string(),
+            ct.value_getter(oracle, &"v", &"the-property")
+        );
+    }
+}
@@ -0,0 +1,405 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fmt::Display;
+
+use super::common::{code_type, quoted};
+use crate::backends::{CodeOracle, CodeType, LiteralRenderer, VariablesType};
+use crate::intermediate_representation::Literal;
+
+pub(crate) struct BooleanCodeType;
+
+impl CodeType for BooleanCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "bool".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Bool
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::Bool(v) => {
+                if *v {
+                    "true".to_string()
+                } else {
+                    "false".to_string()
+                }
+            }
+            _ => unreachable!("Expecting a boolean"),
+        }
+    }
+}
+
+pub(crate) struct IntCodeType;
+
+impl CodeType for IntCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "i64".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Int
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::Number(v) => {
+                format!("{:.0}", v)
+            }
+            _ => unreachable!("Expecting a number"),
+        }
+    }
+}
+
+pub(crate) struct StringCodeType;
+
+impl CodeType for StringCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "String".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::String
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::String(v) => {
+                format!("{}.to_string()", quoted(v))
+            }
+            _ => unreachable!("Expecting a string"),
+        }
+    }
+}
+
+pub(crate) struct FloatCodeType;
+
+impl CodeType for FloatCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "f64".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Double
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            // The debug representation always has a decimal point or an exponent,
+            // so it is never mistaken for an integer literal.
+            serde_json::Value::Number(v) => format!("{:?}", v.as_f64().unwrap_or_default()),
+            _ => unreachable!("Expecting a number"),
+        }
+    }
+}
+
+/// Durations use the `FMLDuration` wrapper from the generated runtime, so they can be
+/// serialized as milliseconds.
+pub(crate) struct DurationCodeType;
+
+impl CodeType for DurationCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "FMLDuration".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Int
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::Number(v) => format!("FMLDuration::from_millis({})", v),
+            _ => unreachable!("Expecting a number"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use serde_json::json;
+
+    use crate::backends::TypeIdentifier;
+
+    use super::*;
+
+    struct TestCodeOracle;
+    impl CodeOracle for TestCodeOracle {
+        fn find(&self, _type_: &TypeIdentifier) -> Box<dyn CodeType> {
+            unreachable!()
+        }
+    }
+
+    struct TestRenderer;
+    impl LiteralRenderer for TestRenderer {
+        fn literal(
+            &self,
+            _oracle: &dyn CodeOracle,
+            _typ: &TypeIdentifier,
+            _value: &Literal,
+            _ctx: &dyn Display,
+        ) -> String {
+            unreachable!()
+        }
+    }
+
+    fn oracle() -> Box<dyn CodeOracle> {
+        Box::new(TestCodeOracle) as Box<dyn CodeOracle>
+    }
+
+    fn bool_type() -> Box<dyn CodeType> {
+        Box::new(BooleanCodeType) as Box<dyn CodeType>
+    }
+
+    fn string_type() -> Box<dyn CodeType> {
+        Box::new(StringCodeType) as Box<dyn CodeType>
+    }
+
+    fn int_type() -> Box<dyn CodeType> {
+        Box::new(IntCodeType) as Box<dyn CodeType>
+    }
+
+    fn float_type() -> Box<dyn CodeType> {
+        Box::new(FloatCodeType) as Box<dyn CodeType>
+    }
+
+    fn duration_type() -> Box<dyn CodeType> {
+        Box::new(DurationCodeType) as Box<dyn CodeType>
+    }
+
+    #[test]
+    fn test_type_label() {
+        let oracle = &*oracle();
+
+        let ct = bool_type();
+        assert_eq!("bool".to_string(), ct.type_label(oracle));
+
+        let ct = string_type();
+        assert_eq!("String".to_string(), ct.type_label(oracle));
+
+        let ct = int_type();
+        assert_eq!("i64".to_string(), ct.type_label(oracle));
+
+        let ct = float_type();
+        assert_eq!("f64".to_string(), ct.type_label(oracle));
+
+        let ct = duration_type();
+        assert_eq!("FMLDuration".to_string(), ct.type_label(oracle));
+    }
+
+    #[test]
+    fn test_literal() {
+        let oracle = &*oracle();
+        let finder = &TestRenderer;
+
+        let ct = bool_type();
+        let ctx = "context".to_string();
+        assert_eq!(
+            "true".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(true))
+        );
+        assert_eq!(
+            "false".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(false))
+        );
+
+        let ct = string_type();
+        assert_eq!(
+            r#""no".to_string()"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("no"))
+        );
+        assert_eq!(
+            r#""yes".to_string()"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("yes"))
+        );
+
+        let ct = int_type();
+        assert_eq!("1".to_string(), ct.literal(oracle, &ctx, finder, &json!(1)));
+        assert_eq!("2".to_string(), ct.literal(oracle, &ctx, finder, &json!(2)));
+
+        let ct = float_type();
+        assert_eq!(
+            "0.5".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(0.5))
+        );
+        assert_eq!(
+            "2.0".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(2))
+        );
+
+        let ct = duration_type();
+        assert_eq!(
+            "FMLDuration::from_millis(1500)".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(1500))
+        );
+    }
+
+    #[test]
+    fn test_property_getter() {
+        let oracle = &*oracle();
+
+        let ct = bool_type();
+        assert_eq!(
+            "self.the_property".to_string(),
+            ct.property_getter(oracle, &"self", &"the-property", &"ignored")
+        );
+    }
+}
@@ -0,0 +1,375 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fmt::Display;
+
+use super::common::code_type;
+use crate::backends::{LiteralRenderer, VariablesType};
+use crate::{
+    backends::{CodeOracle, CodeType, TypeIdentifier},
+    intermediate_representation::Literal,
+};
+
+pub(crate) struct OptionalCodeType {
+    inner: TypeIdentifier,
+}
+
+impl OptionalCodeType {
+    pub(crate) fn new(inner: &TypeIdentifier) -> Self {
+        Self {
+            inner: inner.clone(),
+        }
+    }
+}
+
+impl CodeType for OptionalCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "Option<{item}>",
+            item = oracle.find(&self.inner).type_label(oracle),
+        )
+    }
+
+    /// The language specific expression that gets a value of the `prop` from the `vars` object.
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, oracle: &dyn CodeOracle) -> VariablesType {
+        oracle.find(&self.inner).variables_type(oracle)
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        ctx: &dyn Display,
+        renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::Null => "None".to_string(),
+            _ => format!(
+                "Some({})",
+                oracle
+                    .find(&self.inner)
+                    .literal(oracle, ctx, renderer, literal)
+            ),
+        }
+    }
+}
+
+// Map type
+
+pub(crate) struct MapCodeType {
+    k_type: TypeIdentifier,
+    v_type: TypeIdentifier,
+}
+
+impl MapCodeType {
+    pub(crate) fn new(k: &TypeIdentifier, v: &TypeIdentifier) -> Self {
+        Self {
+            k_type: k.clone(),
+            v_type: v.clone(),
+        }
+    }
+}
+
+impl CodeType for MapCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    ///
+    /// We use a `BTreeMap` so the keys are serialized in a stable order.
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "BTreeMap<{k}, {v}>",
+            k = oracle.find(&self.k_type).type_label(oracle),
+            v = oracle.find(&self.v_type).type_label(oracle),
+        )
+    }
+
+    /// The language specific expression that gets a value of the `prop` from the `vars` object.
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, oracle: &dyn CodeOracle) -> VariablesType {
+        oracle.find(&self.v_type).variables_type(oracle)
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        ctx: &dyn Display,
+        renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        let variant = match literal {
+            serde_json::Value::Object(v) => v,
+            _ => unreachable!(),
+        };
+        if variant.is_empty() {
+            return "BTreeMap::new()".to_string();
+        }
+        let k_type = oracle.find(&self.k_type);
+        let v_type = oracle.find(&self.v_type);
+        let src: Vec<String> = variant
+            .iter()
+            .map(|(k, v)| {
+                format!(
+                    "({k}, {v})",
+                    k = k_type.literal(oracle, ctx, renderer, &Literal::String(k.clone())),
+                    v = v_type.literal(oracle, ctx, renderer, v)
+                )
+            })
+            .collect();
+
+        format!("BTreeMap::from([{}])", src.join(", "))
+    }
+}
+
+// List type
+
+pub(crate) struct ListCodeType {
+    inner: TypeIdentifier,
+}
+
+impl ListCodeType {
+    pub(crate) fn new(inner: &TypeIdentifier) -> Self {
+        Self {
+            inner: inner.clone(),
+        }
+    }
+}
+
+impl CodeType for ListCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "Vec<{item}>",
+            item = oracle.find(&self.inner).type_label(oracle),
+        )
+    }
+
+    /// The language specific expression that gets a value of the `prop` from the `vars` object.
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, oracle: &dyn CodeOracle) -> VariablesType {
+        oracle.find(&self.inner).variables_type(oracle)
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        ctx: &dyn Display,
+        renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        let variant = match literal {
+            serde_json::Value::Array(v) => v,
+            _ => unreachable!(),
+        };
+
+        let v_type = oracle.find(&self.inner);
+        let src: Vec<String> = variant
+            .iter()
+            .map(|v| v_type.literal(oracle, ctx, renderer, v))
+            .collect();
+
+        format!("vec![{}]", src.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use serde_json::json;
+
+    use crate::backends::rust::gen_structs::{
+        enum_::EnumCodeType, object::ObjectCodeType, primitives::StringCodeType,
+    };
+    use crate::backends::TypeIdentifier;
+
+    use super::*;
+
+    struct TestCodeOracle;
+    impl CodeOracle for TestCodeOracle {
+        fn find(&self, type_: &TypeIdentifier) -> Box<dyn CodeType> {
+            match type_ {
+                TypeIdentifier::String => Box::new(StringCodeType) as Box<dyn CodeType>,
+                TypeIdentifier::Enum(s) => {
+                    Box::new(EnumCodeType::new(s.clone())) as Box<dyn CodeType>
+                }
+                TypeIdentifier::Object(s) => {
+                    Box::new(ObjectCodeType::new(s.clone())) as Box<dyn CodeType>
+                }
+                TypeIdentifier::List(i) => Box::new(ListCodeType::new(i)),
+                TypeIdentifier::EnumMap(k, v) => Box::new(MapCodeType::new(k, v)),
+                TypeIdentifier::Option(i) => Box::new(OptionalCodeType::new(i)),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    struct TestRenderer;
+    impl LiteralRenderer for TestRenderer {
+        fn literal(
+            &self,
+            _oracle: &dyn CodeOracle,
+            _typ: &TypeIdentifier,
+            _value: &Literal,
+            _ctx: &dyn Display,
+        ) -> String {
+            unreachable!()
+        }
+    }
+
+    fn oracle() -> Box<dyn CodeOracle> {
+        Box::new(TestCodeOracle) as Box<dyn CodeOracle>
+    }
+
+    fn type_(nm: &str) -> TypeIdentifier {
+        match nm {
+            "String" => TypeIdentifier::String,
+            "AnObject" => TypeIdentifier::Object("AnObject".to_string()),
+            nm => TypeIdentifier::Enum(nm.to_string()),
+        }
+    }
+
+    fn list_type(item: &str) -> Box<dyn CodeType> {
+        Box::new(ListCodeType::new(&type_(item)))
+    }
+
+    fn map_type(k: &str, v: &str) -> Box<dyn CodeType> {
+        Box::new(MapCodeType::new(&type_(k), &type_(v)))
+    }
+
+    fn optional_type(item: &str) -> Box<dyn CodeType> {
+        Box::new(OptionalCodeType::new(&type_(item)))
+    }
+
+    #[test]
+    fn test_type_label() {
+        let oracle = &*oracle();
+
+        let ct = list_type("String");
+        assert_eq!("Vec<String>".to_string(), ct.type_label(oracle));
+
+        let ct = list_type("AnEnum");
+        assert_eq!("Vec<AnEnum>".to_string(), ct.type_label(oracle));
+
+        let ct = map_type("String", "AnObject");
+        assert_eq!(
+            "BTreeMap<String, AnObject>".to_string(),
+            ct.type_label(oracle)
+        );
+
+        let ct = map_type("AnEnum", "String");
+        assert_eq!(
+            "BTreeMap<AnEnum, String>".to_string(),
+            ct.type_label(oracle)
+        );
+
+        let ct = optional_type("AnEnum");
+        assert_eq!("Option<AnEnum>".to_string(), ct.type_label(oracle));
+    }
+
+    #[test]
+    fn test_literal() {
+        let oracle = &*oracle();
+        let finder = &TestRenderer;
+        let ctx = "ctx".to_string();
+
+        let ct = list_type("String");
+        assert_eq!(
+            r#"vec!["a".to_string(), "b".to_string()]"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(["a", "b"]))
+        );
+        assert_eq!(
+            "vec![]".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!([]))
+        );
+
+        let ct = map_type("AnEnum", "String");
+        assert_eq!(
+            r#"BTreeMap::from([(AnEnum::Foo, "bar".to_string())])"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!({"foo": "bar"}))
+        );
+        assert_eq!(
+            "BTreeMap::new()".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!({}))
+        );
+
+        let ct = optional_type("AnEnum");
+        assert_eq!(
+            "Some(AnEnum::Foo)".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("foo"))
+        );
+        assert_eq!(
+            "None".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(null))
+        );
+    }
+}
@@ -0,0 +1,140 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::command_line::commands::GenerateStructCmd;
+use crate::error::{FMLError, Result};
+use crate::frontend::AboutBlock;
+use crate::intermediate_representation::FeatureManifest;
+use askama::Template;
+
+mod gen_structs;
+
+impl AboutBlock {
+    fn nimbus_object_name_rust(&self) -> String {
+        let rust_about = self.rust_about.as_ref().unwrap();
+        rust_about.class.clone()
+    }
+}
+
+pub(crate) fn generate_struct(manifest: &FeatureManifest, cmd: &GenerateStructCmd) -> Result<()> {
+    if manifest.about.rust_about.is_none() {
+        return Err(FMLError::ValidationError(
+            "about".to_string(),
+            format!(
+                "The `about` block is missing a valid `rust` entry: {}",
+                &cmd.manifest
+            ),
+        ));
+    }
+
+    let path = &cmd.output;
+    let path = if path.is_dir() {
+        use heck::SnakeCase;
+        path.join(format!(
+            "{}.rs",
+            manifest.about.nimbus_object_name_rust().to_snake_case()
+        ))
+    } else {
+        path.clone()
+    };
+
+    let rs = gen_structs::FeatureManifestDeclaration::new(manifest);
+
+    let contents = rs.render()?;
+
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use crate::util::{build_dir, join};
+    use anyhow::{bail, Context, Result};
+    use std::path::Path;
+    use std::process::Command;
+
+    fn detect_cargo() -> Result<bool> {
+        let output = Command::new("which").arg("cargo").output()?;
+
+        Ok(output.status.success())
+    }
+
+    // The generated code only depends on `serde` and `serde_json`.
+    // We keep it out of the main workspace, with the empty `[workspace]` table.
+    fn cargo_toml(name: &str) -> String {
+        format!(
+            r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+publish = false
+
+[dependencies]
+serde = {{ version = "1", features = ["derive"] }}
+serde_json = "1"
+
+[workspace]
+"#
+        )
+    }
+
+    // Make a binary crate with the test script as `main.rs`, and the generated
+    // manifests alongside it, so the script can declare them as modules.
+    pub fn create_crate(manifest_files: &[String], script: &Path, crate_dir: &Path) -> Result<()> {
+        let src_dir = crate_dir.join("src");
+        if src_dir.exists() {
+            std::fs::remove_dir_all(&src_dir)?;
+        }
+        std::fs::create_dir_all(&src_dir)?;
+        for file in manifest_files.iter().map(Path::new) {
+            let dest = src_dir.join(file.file_name().context("Expected a file name")?);
+            std::fs::copy(file, dest)?;
+        }
+        std::fs::copy(script, src_dir.join("main.rs"))?;
+
+        let stem = script
+            .file_stem()
+            .context("Expected a test script with a file name")?
+            .to_string_lossy()
+            .replace('_', "-");
+        std::fs::write(
+            crate_dir.join("Cargo.toml"),
+            cargo_toml(&format!("fml-test-{}", stem)),
+        )?;
+        Ok(())
+    }
+
+    pub fn run_script_with_generated_code(manifest_files: &[String], script: &Path) -> Result<()> {
+        if !detect_cargo()? {
+            eprintln!("SDK-446 Install cargo or add it the PATH to run tests");
+            return Ok(());
+        }
+        let stem = script
+            .file_stem()
+            .context("Expected a test script with a file name")?
+            .to_string_lossy()
+            .to_string();
+        let rust_dir = join(build_dir(), "rust");
+        let crate_dir = Path::new(&rust_dir).join(stem);
+        create_crate(manifest_files, script, &crate_dir)?;
+
+        // All the test crates share a target directory, so we only build serde once.
+        let status = Command::new("cargo")
+            .arg("run")
+            .arg("--quiet")
+            .arg("--manifest-path")
+            .arg(crate_dir.join("Cargo.toml"))
+            .env("CARGO_TARGET_DIR", Path::new(&rust_dir).join("target"))
+            .env("RUSTFLAGS", "-D warnings")
+            .spawn()
+            .context("Failed to spawn `cargo` when running script")?
+            .wait()
+            .context("Failed to wait for `cargo` when running script")?;
+        if !status.success() {
+            bail!("running `cargo` failed running a script")
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+{%- let inner = self.inner() %}
+{%- let class_name = inner.name()|class_name %}
+{{ inner.doc()|comment("") }}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum {{class_name}} {
+    {%- for variant in inner.variants() %}
+    {{ variant.doc()|comment("    ") }}
+    #[serde(rename = {{ variant.name()|quoted }})]
+    {{ variant.name()|enum_variant_name }},
+    {%- endfor %}
+}
+
+impl {{class_name}} {
+    /// All the variants of this enum, in the order they're declared in the manifest.
+    pub const ALL: &'static [{{class_name}}] = &[
+        {%- for variant in inner.variants() %}
+        {{class_name}}::{{ variant.name()|enum_variant_name }},
+        {%- endfor %}
+    ];
+
+    /// The string used to represent this variant in the feature JSON.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            {%- for variant in inner.variants() %}
+            {{class_name}}::{{ variant.name()|enum_variant_name }} => {{ variant.name()|quoted }},
+            {%- endfor %}
+        }
+    }
+}
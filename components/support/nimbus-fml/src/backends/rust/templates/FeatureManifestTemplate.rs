@@ -0,0 +1,74 @@
+// This file was autogenerated by the `nimbus-fml` crate.
+// Trust me, you don't want to mess with it!
+//
+// It is intended to be used as a module, e.g. `mod {{ self.fm.about.nimbus_object_name_rust()|var_name }};`,
+// with `serde` (with the `derive` feature) and `serde_json` as dependencies of the crate.
+#![allow(clippy::all, dead_code, unused_imports)]
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+{% include "RuntimeTemplate.rs" %}
+
+{%- let nimbus_object = self.fm.about.nimbus_object_name_rust() %}
+
+/// An object for safely accessing feature configuration from Nimbus.
+///
+/// This is generated.
+///
+/// ```ignore
+/// let nimbus: &dyn FeaturesInterface = &connect_to_nimbus_sdk();
+/// let feature: MyFeature = {{ nimbus_object }}::get_feature(nimbus);
+/// ```
+///
+/// This should not be edited manually, but changed by editing the `nimbus.fml.yaml` file, and
+/// re-running the `nimbus-fml` tool, which is likely already being used by the build script.
+pub struct {{ nimbus_object }};
+
+impl {{ nimbus_object }} {
+    /// Get the configuration for the feature from the Nimbus SDK, merged with the defaults
+    /// from the manifest.
+    pub fn get_feature<F: FMLFeature>(api: &dyn FeaturesInterface) -> F {
+        api.get_feature_config_variables(F::FEATURE_ID)
+            .map(|json| F::from_json_str(&json))
+            .unwrap_or_default()
+    }
+
+    /// Record that the user has been exposed to the feature.
+    pub fn record_exposure<F: FMLFeature>(api: &dyn FeaturesInterface) {
+        api.record_exposure_event(F::FEATURE_ID)
+    }
+
+    /// The ids of all the features in this manifest, and the manifests it imports.
+    pub fn get_feature_ids() -> Vec<&'static str> {
+        vec![
+            {%- for f in self.iter_feature_defs() %}
+            {{ f.name()|quoted }},
+            {%- endfor %}
+        ]
+    }
+
+    /// Get a list of feature ids where the feature allows co-enrollment.
+    pub fn get_coenrolling_feature_ids() -> Vec<&'static str> {
+        vec![
+            {%- for f in self.fm.get_coenrolling_feature_ids() %}
+            {{ f|quoted }},
+            {%- endfor %}
+        ]
+    }
+
+    /// The default configuration of every feature, keyed by feature id.
+    pub fn get_default_json() -> Value {
+        let mut features = Map::new();
+        {%- for f in self.iter_feature_defs() %}
+        features.insert({{ f.name()|quoted }}.to_string(), {{ f.name()|class_name }}::default().to_json());
+        {%- endfor %}
+        Value::Object(features)
+    }
+}
+
+// Public interface members begin here.
+{%- for code in self.declaration_code() %}
+{{ code }}
+{%- endfor %}
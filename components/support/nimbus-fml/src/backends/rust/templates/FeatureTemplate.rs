@@ -0,0 +1,6 @@
+{%- import "macros.rs" as rs %}
+{%- let inner = self.inner() %}
+{% call rs::render_struct(inner) %}
+impl FMLFeature for {{ inner.name()|class_name }} {
+    const FEATURE_ID: &'static str = {{ inner.name()|quoted }};
+}
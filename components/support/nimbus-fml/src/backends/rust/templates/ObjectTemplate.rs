@@ -0,0 +1,3 @@
+{%- import "macros.rs" as rs %}
+{%- let inner = self.inner() %}
+{% call rs::render_struct(inner) %}
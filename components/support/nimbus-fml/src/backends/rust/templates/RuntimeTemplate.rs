@@ -0,0 +1,128 @@
+/// The connection between the Nimbus SDK (and thus the Nimbus server) and the generated code.
+///
+/// In Rust, this is usually a thin wrapper around a `NimbusClient`, or a Cirrus client.
+pub trait FeaturesInterface {
+    /// The JSON for the feature's variables, as returned by the SDK's
+    /// `get_feature_config_variables`, or `None` if no experiment or rollout
+    /// is configuring the feature.
+    fn get_feature_config_variables(&self, feature_id: &str) -> Option<String>;
+
+    /// Record that the user has been exposed to the feature.
+    fn record_exposure_event(&self, _feature_id: &str) {}
+}
+
+/// Implemented by all the generated feature structs.
+///
+/// The `Default` for each feature is the configuration given in the manifest.
+pub trait FMLFeature: Default + Clone + Serialize + DeserializeOwned {
+    /// The feature id, as used by the Nimbus SDK and Experimenter.
+    const FEATURE_ID: &'static str;
+
+    /// Merge the given feature JSON over the defaults from the manifest.
+    ///
+    /// Any top-level properties which don't fit the schema are ignored.
+    fn from_json(overrides: Value) -> Self {
+        _merge_with_defaults(Self::default(), overrides)
+    }
+
+    /// Merge the given feature JSON string over the defaults from the manifest.
+    ///
+    /// If the string isn't valid JSON, then the defaults are returned.
+    fn from_json_str(json: &str) -> Self {
+        serde_json::from_str(json)
+            .map(Self::from_json)
+            .unwrap_or_default()
+    }
+
+    /// The JSON representation of this feature configuration.
+    fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// Deeply merge the `overrides` into the `target`. Objects are merged key by key; everything
+/// else, including lists, is replaced.
+///
+/// Feature objects and maps are both represented as JSON objects, so both get merged with the
+/// values from their defaults.
+fn _merge_json(target: &mut Value, overrides: Value) {
+    match (target, overrides) {
+        (Value::Object(target), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match target.get_mut(&key) {
+                    Some(existing) => _merge_json(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, overrides) => *target = overrides,
+    }
+}
+
+fn _merge_with_defaults<T: Serialize + DeserializeOwned>(defaults: T, overrides: Value) -> T {
+    let base = match serde_json::to_value(&defaults) {
+        Ok(base) => base,
+        Err(_) => return defaults,
+    };
+
+    let mut merged = base.clone();
+    _merge_json(&mut merged, overrides.clone());
+    if let Ok(value) = serde_json::from_value(merged) {
+        return value;
+    }
+
+    // At least one of the properties doesn't fit the schema. Try each property in turn,
+    // keeping only those which do.
+    let mut merged = base;
+    if let Value::Object(overrides) = overrides {
+        for (key, value) in overrides {
+            let mut candidate = merged.clone();
+            let mut single = Map::new();
+            single.insert(key, value);
+            _merge_json(&mut candidate, Value::Object(single));
+            if serde_json::from_value::<T>(candidate.clone()).is_ok() {
+                merged = candidate;
+            }
+        }
+    }
+    serde_json::from_value(merged).unwrap_or(defaults)
+}
+
+/// A `Duration` type in the manifest. This is a `std::time::Duration`, which is represented
+/// in the feature JSON as a whole number of milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FMLDuration(pub std::time::Duration);
+
+impl FMLDuration {
+    pub const fn from_millis(millis: u64) -> Self {
+        Self(std::time::Duration::from_millis(millis))
+    }
+}
+
+impl std::ops::Deref for FMLDuration {
+    type Target = std::time::Duration;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<FMLDuration> for std::time::Duration {
+    fn from(d: FMLDuration) -> Self {
+        d.0
+    }
+}
+
+impl Serialize for FMLDuration {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0.as_millis() as u64)
+    }
+}
+
+impl<'de> Deserialize<'de> for FMLDuration {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(Self::from_millis)
+    }
+}
@@ -0,0 +1,32 @@
+{#- This file contains macros needed to generate code for the FML.
+
+    It is the natural place to put commonalities between Object and Features.
+-#}
+
+{% macro render_struct(inner) %}
+{%- let class_name = inner.name()|class_name %}
+{{ inner.doc()|comment("") }}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct {{ class_name }} {
+    {%- for p in inner.props() %}
+    {%- let doc = p.doc() %}
+    {%- if !doc.is_empty() %}
+    {{ doc|comment("    ") }}
+    {%- endif %}
+    #[serde(rename = {{ p.name()|quoted }})]
+    pub {{ p.name()|var_name }}: {{ p.typ()|type_label }},
+    {%- endfor %}
+}
+
+{# The defaults come from the manifest. -#}
+impl Default for {{ class_name }} {
+    fn default() -> Self {
+        Self {
+            {%- for p in inner.props() %}
+            {{ p.name()|var_name }}: {{ p.typ()|literal(self, p.default(), "") }},
+            {%- endfor %}
+        }
+    }
+}
+{% endmacro %}
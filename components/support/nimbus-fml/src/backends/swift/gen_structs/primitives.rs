@@ -178,6 +178,260 @@ impl CodeType for StringCodeType {
     }
 }
 
+pub(crate) struct FloatCodeType;
+
+impl CodeType for FloatCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "Double".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Double
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            // The debug representation always has a decimal point or an exponent,
+            // so it is never mistaken for an `Int`.
+            serde_json::Value::Number(v) => format!("{:?}", v.as_f64().unwrap_or_default()),
+            _ => unreachable!("Expecting a number"),
+        }
+    }
+}
+
+pub(crate) struct UrlCodeType;
+
+impl CodeType for UrlCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "URL".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::String
+    }
+
+    /// A function handle that is capable of turning the variables type to the TypeRef type.
+    fn create_transform(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some("URL.init(string:)".into())
+    }
+
+    fn as_json_transform(&self, _oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        Some(format!("{}.absoluteString", prop))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            // The default has already been validated, so this won't fail at runtime.
+            serde_json::Value::String(v) => format!("URL(string: {})!", common::quoted(v)),
+            _ => unreachable!("Expecting a string"),
+        }
+    }
+}
+
+pub(crate) struct ColorCodeType;
+
+impl CodeType for ColorCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "UIColor".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::String
+    }
+
+    /// A function handle that is capable of turning the variables type to the TypeRef type.
+    fn create_transform(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some("UIColor.fromHexString".into())
+    }
+
+    fn as_json_transform(&self, _oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        Some(format!("{}.hexString", prop))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            // The default has already been validated, so this won't fail at runtime.
+            serde_json::Value::String(v) => {
+                format!("UIColor.fromHexString({})!", common::quoted(v))
+            }
+            _ => unreachable!("Expecting a string"),
+        }
+    }
+
+    fn imports(&self, _oracle: &dyn CodeOracle) -> Option<Vec<String>> {
+        Some(vec!["UIKit".to_string()])
+    }
+}
+
+/// Durations are represented in JSON as a whole number of milliseconds.
+pub(crate) struct DurationCodeType;
+
+impl CodeType for DurationCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, _oracle: &dyn CodeOracle) -> String {
+        "TimeInterval".into()
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        code_type::value_getter(self, oracle, vars, prop)
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        code_type::value_mapper(self, oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Int
+    }
+
+    /// A function handle that is capable of turning the variables type to the TypeRef type.
+    fn create_transform(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some("TimeInterval.fromMilliseconds".into())
+    }
+
+    fn as_json_transform(&self, _oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        Some(format!("{}.inWholeMilliseconds", prop))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        _oracle: &dyn CodeOracle,
+        _ctx: &dyn Display,
+        _renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::Number(v) => format!("TimeInterval.fromMilliseconds({})", v),
+            _ => unreachable!("Expecting a number"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod unit_tests {
 
@@ -223,6 +477,22 @@ mod unit_tests {
         Box::new(IntCodeType) as Box<dyn CodeType>
     }
 
+    fn float_type() -> Box<dyn CodeType> {
+        Box::new(FloatCodeType) as Box<dyn CodeType>
+    }
+
+    fn url_type() -> Box<dyn CodeType> {
+        Box::new(UrlCodeType) as Box<dyn CodeType>
+    }
+
+    fn color_type() -> Box<dyn CodeType> {
+        Box::new(ColorCodeType) as Box<dyn CodeType>
+    }
+
+    fn duration_type() -> Box<dyn CodeType> {
+        Box::new(DurationCodeType) as Box<dyn CodeType>
+    }
+
     #[test]
     fn test_type_label() {
         let oracle = &*oracle();
@@ -235,6 +505,18 @@ mod unit_tests {
 
         let ct = int_type();
         assert_eq!("Int".to_string(), ct.type_label(oracle));
+
+        let ct = float_type();
+        assert_eq!("Double".to_string(), ct.type_label(oracle));
+
+        let ct = url_type();
+        assert_eq!("URL".to_string(), ct.type_label(oracle));
+
+        let ct = color_type();
+        assert_eq!("UIColor".to_string(), ct.type_label(oracle));
+
+        let ct = duration_type();
+        assert_eq!("TimeInterval".to_string(), ct.type_label(oracle));
     }
 
     #[test]
@@ -265,6 +547,34 @@ mod unit_tests {
         let ct = int_type();
         assert_eq!("1".to_string(), ct.literal(oracle, &ctx, finder, &json!(1)));
         assert_eq!("2".to_string(), ct.literal(oracle, &ctx, finder, &json!(2)));
+
+        let ct = float_type();
+        assert_eq!(
+            "0.5".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(0.5))
+        );
+        assert_eq!(
+            "2.0".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(2))
+        );
+
+        let ct = url_type();
+        assert_eq!(
+            r#"URL(string: "https://example.com/")!"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("https://example.com/"))
+        );
+
+        let ct = color_type();
+        assert_eq!(
+            r##"UIColor.fromHexString("#336699")!"##.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!("#336699"))
+        );
+
+        let ct = duration_type();
+        assert_eq!(
+            "TimeInterval.fromMilliseconds(1500)".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(1500))
+        );
     }
 
     #[test]
@@ -288,5 +598,51 @@ mod unit_tests {
             r#"v.getInt("the-property")"#.to_string(),
             ct.value_getter(oracle, &"v", &"the-property")
         );
+
+        let ct = float_type();
+        assert_eq!(
+            r#"v.getDouble("the-property")"#.to_string(),
+            ct.value_getter(oracle, &"v", &"the-property")
+        );
+    }
+
+    #[test]
+    fn test_property_getter() {
+        let oracle = &*oracle();
+
+        let ct = url_type();
+        assert_eq!(
+            r#"v.getString("the-property")?.map(URL.init(string:)) ?? def"#.to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+
+        let ct = color_type();
+        assert_eq!(
+            r#"v.getString("the-property")?.map(UIColor.fromHexString) ?? def"#.to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+
+        let ct = duration_type();
+        assert_eq!(
+            r#"v.getInt("the-property")?.map(TimeInterval.fromMilliseconds) ?? def"#.to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+    }
+
+    #[test]
+    fn test_as_json() {
+        let oracle = &*oracle();
+
+        let ct = url_type();
+        assert_eq!("p.absoluteString".to_string(), ct.as_json(oracle, &"p"));
+
+        let ct = color_type();
+        assert_eq!("p.hexString".to_string(), ct.as_json(oracle, &"p"));
+
+        let ct = duration_type();
+        assert_eq!(
+            "p.inWholeMilliseconds".to_string(),
+            ct.as_json(oracle, &"p")
+        );
     }
 }
@@ -76,6 +76,10 @@ pub mod test {
         join(pkg_dir(), "fixtures/ios/runtime/UIImage.swift")
     }
 
+    fn mock_uicolor_swift() -> String {
+        join(pkg_dir(), "fixtures/ios/runtime/UIColor.swift")
+    }
+
     // The file with the swift implementation of FeatureVariables
     fn variables_swift() -> String {
         join(sdk_ios_dir(), "FeatureVariables.swift")
@@ -147,6 +151,7 @@ pub mod test {
             .arg(&collections_swift())
             .arg(&dictionaries_swift())
             .arg(&mock_uiimage_swift())
+            .arg(&mock_uicolor_swift())
             .arg(&variables_swift())
             .arg(&features_swift())
             .arg(&feature_holder())
@@ -0,0 +1,103 @@
+// /* This Source Code Form is subject to the terms of the Mozilla Public
+//  * License, v. 2.0. If a copy of the MPL was not distributed with this
+//  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+use heck::{CamelCase, MixedCase, ShoutySnakeCase};
+use std::fmt::Display;
+
+/// Get the idiomatic TypeScript rendering of a class name (for enums, objects, features, etc).
+pub fn class_name(nm: &dyn Display) -> String {
+    nm.to_string().to_camel_case()
+}
+
+/// Get the idiomatic TypeScript rendering of a variable name.
+pub fn var_name(nm: &dyn Display) -> String {
+    nm.to_string().to_mixed_case()
+}
+
+/// Get the idiomatic TypeScript rendering of an individual enum variant.
+pub fn enum_variant_name(nm: &dyn Display) -> String {
+    nm.to_string().to_shouty_snake_case()
+}
+
+/// Surrounds a string with quotes.
+/// TypeScript string literals share their escaping rules with JSON, so we let
+/// `serde_json` do the escaping for us.
+pub fn quoted(string: &dyn Display) -> String {
+    serde_json::Value::String(string.to_string()).to_string()
+}
+
+pub(crate) mod code_type {
+    use std::fmt::Display;
+
+    use crate::backends::{CodeOracle, CodeType};
+
+    /// The language specific expression that gets a value of the `prop` from the `vars` object.
+    pub(crate) fn property_getter(
+        ct: &dyn CodeType,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        let getter = ct.value_getter(oracle, vars, prop);
+        let mapper = ct.value_mapper(oracle);
+        let default = ct
+            .defaults_mapper(oracle, &default, vars)
+            .unwrap_or_else(|| default.to_string());
+        let merger = ct.value_merger(oracle, &default);
+
+        // TypeScript has no equivalent of Kotlin's `let` or Swift's `Optional.map`, so
+        // the mapper and merger are both functions, which are only called by the `_let`
+        // runtime helper if the value is present.
+        let getter = match mapper {
+            Some(mapper) => format!("_let({}, {})", getter, mapper),
+            None => getter,
+        };
+        let getter = match merger {
+            Some(merger) => format!("_let({}, {})", getter, merger),
+            None => getter,
+        };
+
+        format!(
+            "{getter} ?? {fallback}",
+            getter = getter,
+            fallback = default
+        )
+    }
+
+    pub(crate) fn value_getter(
+        ct: &dyn CodeType,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        let vt = ct.variables_type(oracle);
+        format!(
+            "{vars}.get{vt}(\"{prop}\")",
+            vars = vars,
+            vt = vt,
+            prop = prop
+        )
+    }
+
+    pub(crate) fn value_mapper(ct: &dyn CodeType, oracle: &dyn CodeOracle) -> Option<String> {
+        ct.create_transform(oracle)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_quoted() {
+        assert_eq!(
+            quoted(&"no-quotes".to_string()),
+            "\"no-quotes\"".to_string()
+        );
+        assert_eq!(
+            quoted(&"a \"quoted\"\nstring".to_string()),
+            "\"a \\\"quoted\\\"\\nstring\"".to_string()
+        );
+    }
+}
@@ -0,0 +1,73 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use super::{common, filters, object::object_literal};
+use crate::{
+    backends::{CodeDeclaration, CodeOracle, LiteralRenderer, TypeIdentifier},
+    intermediate_representation::{ImportedModule, Literal},
+};
+use askama::Template;
+
+#[derive(Template)]
+#[template(
+    syntax = "ts",
+    escape = "none",
+    path = "ImportedModuleInitializationTemplate.ts"
+)]
+pub(crate) struct ImportedModuleInitialization<'a> {
+    pub(crate) inner: ImportedModule<'a>,
+}
+
+impl<'a> ImportedModuleInitialization<'a> {
+    pub(crate) fn new(inner: ImportedModule<'a>) -> Self {
+        Self { inner }
+    }
+}
+
+impl CodeDeclaration for ImportedModuleInitialization<'_> {
+    fn imports(&self, _oracle: &dyn CodeOracle) -> Option<Vec<String>> {
+        let about = self.inner.about();
+        about.typescript_about.as_ref()?;
+        // ES modules have no wildcard import into the current scope, so we name
+        // everything the imported module declares that we might need: its nimbus object,
+        // the features we're overriding and any types that our features might share.
+        let fm = self.inner.fm;
+        let names = std::iter::once(about.nimbus_object_name_ts())
+            .chain(self.inner.features().iter().map(|f| f.name()))
+            .chain(fm.iter_enum_defs().map(|e| e.name()))
+            .chain(fm.iter_object_defs().map(|o| o.name()))
+            .map(|nm| common::class_name(&nm))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        Some(vec![format!(
+            "import {{ {names} }} from {module};",
+            names = names.join(", "),
+            module = common::quoted(&about.nimbus_module_specifier_ts()),
+        )])
+    }
+
+    fn initialization_code(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        Some(self.render().unwrap())
+    }
+
+    fn definition_code(&self, _oracle: &dyn CodeOracle) -> Option<String> {
+        None
+    }
+}
+
+impl LiteralRenderer for ImportedModuleInitialization<'_> {
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        typ: &TypeIdentifier,
+        value: &Literal,
+        ctx: &dyn Display,
+    ) -> String {
+        object_literal(self.inner.fm, ctx, &self, oracle, typ, value)
+    }
+}
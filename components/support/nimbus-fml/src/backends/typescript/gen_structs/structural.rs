@@ -0,0 +1,603 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fmt::Display;
+
+use super::common::{self, code_type};
+use crate::backends::{LiteralRenderer, VariablesType};
+use crate::{
+    backends::{CodeOracle, CodeType, TypeIdentifier},
+    intermediate_representation::Literal,
+};
+
+pub(crate) struct OptionalCodeType {
+    inner: TypeIdentifier,
+}
+
+impl OptionalCodeType {
+    pub(crate) fn new(inner: &TypeIdentifier) -> Self {
+        Self {
+            inner: inner.clone(),
+        }
+    }
+}
+
+impl CodeType for OptionalCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "{item} | null",
+            item = oracle.find(&self.inner).type_label(oracle),
+        )
+    }
+
+    /// The language specific expression that gets a value of the `prop` from the `vars` object.
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        // all getters are optional.
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    /// Unlike Kotlin and Swift, we defer entirely to the inner type, so lists and maps
+    /// can be optional too.
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        oracle.find(&self.inner).value_getter(oracle, vars, prop)
+    }
+
+    fn create_transform(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        oracle.find(&self.inner).create_transform(oracle)
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, oracle: &dyn CodeOracle) -> VariablesType {
+        oracle.find(&self.inner).variables_type(oracle)
+    }
+
+    /// The method call here will use the `create_transform` to transform the value coming out of
+    /// the `Variables` object into the desired type.
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        oracle.find(&self.inner).value_mapper(oracle)
+    }
+
+    /// The method call to merge the value with the defaults.
+    ///
+    /// The default may be `null`, in which case there is nothing to merge with.
+    fn value_merger(&self, oracle: &dyn CodeOracle, default: &dyn Display) -> Option<String> {
+        let merger = oracle.find(&self.inner).value_merger(oracle, &"_d")?;
+        Some(format!(
+            "(_o: {inner}) => _let({default}, (_d: {inner}) => ({merger})(_o)) ?? _o",
+            inner = oracle.find(&self.inner).type_label(oracle),
+            default = default,
+            merger = merger,
+        ))
+    }
+
+    fn defaults_type(&self, oracle: &dyn CodeOracle) -> String {
+        let inner = oracle.find(&self.inner).defaults_type(oracle);
+        format!("{} | null", inner)
+    }
+
+    /// Implement these in different code types, and call recursively from different code types.
+    fn as_json_transform(&self, oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        // We want to return None if the inner's json transform is none,
+        // but if it's not, then only call it if the value is not null.
+        let transform = oracle
+            .find(&self.inner)
+            .as_json_transform(oracle, &"_v".to_string())?;
+        Some(format!(
+            "_let({prop}, (_v: {inner}) => {transform}) ?? null",
+            prop = prop,
+            inner = oracle.find(&self.inner).type_label(oracle),
+            transform = transform,
+        ))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        ctx: &dyn Display,
+        renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        match literal {
+            serde_json::Value::Null => "null".to_string(),
+            _ => oracle
+                .find(&self.inner)
+                .literal(oracle, ctx, renderer, literal),
+        }
+    }
+}
+
+// Map type
+
+pub(crate) struct MapCodeType {
+    k_type: TypeIdentifier,
+    v_type: TypeIdentifier,
+}
+
+impl MapCodeType {
+    pub(crate) fn new(k: &TypeIdentifier, v: &TypeIdentifier) -> Self {
+        Self {
+            k_type: k.clone(),
+            v_type: v.clone(),
+        }
+    }
+
+    fn is_string_map(&self) -> bool {
+        matches!(self.k_type, TypeIdentifier::String)
+    }
+}
+
+impl CodeType for MapCodeType {
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    ///
+    /// Maps with enum keys may not have every variant as a key, so they are `Partial`.
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        let k = oracle.find(&self.k_type).type_label(oracle);
+        let v = oracle.find(&self.v_type).type_label(oracle);
+        if self.is_string_map() {
+            format!("Record<{k}, {v}>")
+        } else {
+            format!("Partial<Record<{k}, {v}>>")
+        }
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        let v_type = oracle.find(&self.v_type);
+        format!(
+            "{vars}.get{vt}Map({prop})",
+            vars = vars,
+            vt = v_type.variables_type(oracle),
+            prop = common::quoted(prop),
+        )
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        let k_type = oracle.find(&self.k_type);
+        let v_type = oracle.find(&self.v_type);
+        Some(
+            match (
+                k_type.create_transform(oracle),
+                v_type.create_transform(oracle),
+            ) {
+                (Some(k), Some(v)) => format!("(_m) => _mapEntriesNotNull(_m, {k}, {v})"),
+                (None, Some(v)) => format!("(_m) => _mapValuesNotNull(_m, {v})"),
+                // We could do something with keys, but it's only every strings and enums.
+                (Some(k), None) => format!("(_m) => _mapKeysNotNull(_m, {k})"),
+                _ => return None,
+            },
+        )
+    }
+
+    fn value_merger(&self, oracle: &dyn CodeOracle, default: &dyn Display) -> Option<String> {
+        let v_type = oracle.find(&self.v_type);
+        Some(match v_type.merge_transform(oracle) {
+            Some(transform) => format!("(_m) => _mergeWith(_m, {default}, {transform})"),
+            None => format!("(_m) => _mergeWith(_m, {default})"),
+        })
+    }
+
+    fn create_transform(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        let vtype = oracle.find(&self.v_type).variables_type(oracle);
+
+        self.value_mapper(oracle)
+            .map(|mapper| {
+                format!(
+                    "(_vars: Variables) => _let(_vars.as{vtype}Map(), {mapper})",
+                    vtype = vtype,
+                    mapper = mapper
+                )
+            })
+            .or_else(|| {
+                Some(format!(
+                    "(_vars: Variables) => _vars.as{vtype}Map()",
+                    vtype = vtype
+                ))
+            })
+    }
+
+    fn merge_transform(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        let v_type = oracle.find(&self.v_type);
+        Some(match v_type.merge_transform(oracle) {
+            Some(transform) => {
+                format!("(_o, _d) => _mergeWith(_o, _d, {transform})")
+            }
+            None => "(_o, _d) => _mergeWith(_o, _d)".to_string(),
+        })
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        VariablesType::Variables
+    }
+
+    fn as_json_transform(&self, oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        // Enum keys are string enums, so only the values ever need transforming.
+        let v = oracle
+            .find(&self.v_type)
+            .as_json_transform(oracle, &"_v".to_string())?;
+        Some(format!(
+            "_mapValuesNotNull({prop}, (_v: {v_type}) => {v})",
+            prop = prop,
+            v_type = oracle.find(&self.v_type).type_label(oracle),
+            v = v
+        ))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        ctx: &dyn Display,
+        renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        let variant = match literal {
+            serde_json::Value::Object(v) => v,
+            _ => unreachable!(),
+        };
+        let k_type = oracle.find(&self.k_type);
+        let v_type = oracle.find(&self.v_type);
+        let src: Vec<String> = variant
+            .iter()
+            .map(|(k, v)| {
+                format!(
+                    "[{k}]: {v}",
+                    k = k_type.literal(oracle, ctx, renderer, &Literal::String(k.clone())),
+                    v = v_type.literal(oracle, ctx, renderer, v)
+                )
+            })
+            .collect();
+
+        if src.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", src.join(", "))
+        }
+    }
+}
+
+// List type
+
+pub(crate) struct ListCodeType {
+    inner: TypeIdentifier,
+}
+
+impl ListCodeType {
+    pub(crate) fn new(inner: &TypeIdentifier) -> Self {
+        Self {
+            inner: inner.clone(),
+        }
+    }
+}
+
+impl CodeType for ListCodeType {
+    /// The language specific label used to reference this type. This will be used in
+    /// method signatures and property declarations.
+    fn type_label(&self, oracle: &dyn CodeOracle) -> String {
+        format!(
+            "Array<{item}>",
+            item = oracle.find(&self.inner).type_label(oracle),
+        )
+    }
+
+    fn property_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+        default: &dyn Display,
+    ) -> String {
+        code_type::property_getter(self, oracle, vars, prop, default)
+    }
+
+    fn value_getter(
+        &self,
+        oracle: &dyn CodeOracle,
+        vars: &dyn Display,
+        prop: &dyn Display,
+    ) -> String {
+        let vtype = oracle.find(&self.inner).variables_type(oracle);
+        format!(
+            "{vars}.get{vt}List(\"{prop}\")",
+            vars = vars,
+            vt = vtype,
+            prop = prop
+        )
+    }
+
+    fn value_mapper(&self, oracle: &dyn CodeOracle) -> Option<String> {
+        let transform = oracle.find(&self.inner).create_transform(oracle)?;
+        Some(format!("(_l) => _mapNotNull(_l, {})", transform))
+    }
+
+    fn value_merger(&self, _oracle: &dyn CodeOracle, _default: &dyn Display) -> Option<String> {
+        // We never merge lists.
+        None
+    }
+
+    /// The name of the type as it's represented in the `Variables` object.
+    /// The string return may be used to combine with an indentifier, e.g. a `Variables` method name.
+    fn variables_type(&self, _oracle: &dyn CodeOracle) -> VariablesType {
+        // Our current implementation of Variables doesn't have a getListList() or getListMap().
+        // We do allow getVariablesList and getVariablesMap, but not an vars.asList().
+        unimplemented!("Lists and maps of lists aren't supported. The workaround is to use a list of map of list holder objects")
+    }
+
+    fn defaults_type(&self, oracle: &dyn CodeOracle) -> String {
+        let inner = oracle.find(&self.inner).defaults_type(oracle);
+        format!("Array<{}>", inner)
+    }
+
+    fn as_json_transform(&self, oracle: &dyn CodeOracle, prop: &dyn Display) -> Option<String> {
+        let mapper = oracle
+            .find(&self.inner)
+            .as_json_transform(oracle, &"_v".to_string())?;
+        Some(format!(
+            "{prop}.map((_v: {inner}) => {mapper})",
+            prop = prop,
+            inner = oracle.find(&self.inner).type_label(oracle),
+            mapper = mapper
+        ))
+    }
+
+    /// A representation of the given literal for this type.
+    /// N.B. `Literal` is aliased from `serde_json::Value`.
+    fn literal(
+        &self,
+        oracle: &dyn CodeOracle,
+        ctx: &dyn Display,
+        renderer: &dyn LiteralRenderer,
+        literal: &Literal,
+    ) -> String {
+        let variant = match literal {
+            serde_json::Value::Array(v) => v,
+            _ => unreachable!(),
+        };
+
+        let v_type = oracle.find(&self.inner);
+        let src: Vec<String> = variant
+            .iter()
+            .map(|v| v_type.literal(oracle, ctx, renderer, v))
+            .collect();
+
+        format!("[{}]", src.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+
+    use serde_json::json;
+
+    use crate::backends::typescript::gen_structs::{
+        enum_::EnumCodeType, object::ObjectCodeType, primitives::StringCodeType,
+    };
+    use crate::backends::TypeIdentifier;
+
+    use super::*;
+
+    struct TestCodeOracle;
+    impl CodeOracle for TestCodeOracle {
+        fn find(&self, type_: &TypeIdentifier) -> Box<dyn CodeType> {
+            match type_ {
+                TypeIdentifier::String => Box::new(StringCodeType) as Box<dyn CodeType>,
+                TypeIdentifier::Enum(s) => {
+                    Box::new(EnumCodeType::new(s.clone())) as Box<dyn CodeType>
+                }
+                TypeIdentifier::Object(s) => {
+                    Box::new(ObjectCodeType::new(s.clone())) as Box<dyn CodeType>
+                }
+                TypeIdentifier::List(i) => Box::new(ListCodeType::new(i)),
+                TypeIdentifier::EnumMap(k, v) => Box::new(MapCodeType::new(k, v)),
+                TypeIdentifier::Option(i) => Box::new(OptionalCodeType::new(i)),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    struct TestRenderer;
+    impl LiteralRenderer for TestRenderer {
+        fn literal(
+            &self,
+            _oracle: &dyn CodeOracle,
+            _typ: &TypeIdentifier,
+            _value: &Literal,
+            _ctx: &dyn Display,
+        ) -> String {
+            unreachable!()
+        }
+    }
+
+    fn oracle() -> Box<dyn CodeOracle> {
+        Box::new(TestCodeOracle) as Box<dyn CodeOracle>
+    }
+
+    fn type_(nm: &str) -> TypeIdentifier {
+        match nm {
+            "String" => TypeIdentifier::String,
+            "AnObject" => TypeIdentifier::Object("AnObject".to_string()),
+            nm => TypeIdentifier::Enum(nm.to_string()),
+        }
+    }
+
+    fn list_type(item: &str) -> Box<dyn CodeType> {
+        Box::new(ListCodeType::new(&type_(item)))
+    }
+
+    fn map_type(k: &str, v: &str) -> Box<dyn CodeType> {
+        Box::new(MapCodeType::new(&type_(k), &type_(v)))
+    }
+
+    fn optional_type(item: &str) -> Box<dyn CodeType> {
+        Box::new(OptionalCodeType::new(&type_(item)))
+    }
+
+    #[test]
+    fn test_type_label() {
+        let oracle = &*oracle();
+
+        let ct = list_type("String");
+        assert_eq!("Array<string>".to_string(), ct.type_label(oracle));
+
+        let ct = list_type("AnEnum");
+        assert_eq!("Array<AnEnum>".to_string(), ct.type_label(oracle));
+
+        let ct = map_type("String", "AnEnum");
+        assert_eq!("Record<string, AnEnum>".to_string(), ct.type_label(oracle));
+
+        let ct = map_type("AnEnum", "String");
+        assert_eq!(
+            "Partial<Record<AnEnum, string>>".to_string(),
+            ct.type_label(oracle)
+        );
+
+        let ct = optional_type("String");
+        assert_eq!("string | null".to_string(), ct.type_label(oracle));
+    }
+
+    #[test]
+    fn test_literal() {
+        let oracle = &*oracle();
+        let finder = &TestRenderer;
+
+        let ctx = "context".to_string();
+        let ct = list_type("String");
+        assert_eq!(
+            r#"["x", "y", "z"]"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(["x", "y", "z"]))
+        );
+
+        let ct = list_type("AnEnum");
+        assert_eq!(
+            r#"[AnEnum.X, AnEnum.Y, AnEnum.Z]"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(["x", "y", "z"]))
+        );
+
+        let ct = map_type("String", "String");
+        assert_eq!(
+            r#"{ ["a"]: "A", ["b"]: "B" }"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!({"a": "A", "b": "B"}))
+        );
+
+        let ct = map_type("AnEnum", "String");
+        assert_eq!(
+            r#"{ [AnEnum.A]: "A" }"#.to_string(),
+            ct.literal(oracle, &ctx, finder, &json!({"a": "A"}))
+        );
+
+        let ct = map_type("String", "String");
+        assert_eq!(
+            "{}".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!({}))
+        );
+
+        let ct = optional_type("String");
+        assert_eq!(
+            "null".to_string(),
+            ct.literal(oracle, &ctx, finder, &json!(null))
+        );
+    }
+
+    #[test]
+    fn test_get_value() {
+        let oracle = &*oracle();
+
+        let ct = list_type("AnEnum");
+        assert_eq!(
+            r#"v.getStringList("the-property")"#.to_string(),
+            ct.value_getter(oracle, &"v", &"the-property")
+        );
+
+        let ct = map_type("AnEnum", "AnObject");
+        assert_eq!(
+            r#"v.getVariablesMap("the-property")"#.to_string(),
+            ct.value_getter(oracle, &"v", &"the-property")
+        );
+    }
+
+    #[test]
+    fn test_getter_with_fallback() {
+        let oracle = &*oracle();
+
+        let ct = list_type("AnEnum");
+        assert_eq!(
+            r#"_let(v.getStringList("the-property"), (_l) => _mapNotNull(_l, AnEnum.enumValue)) ?? def"#
+                .to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+
+        let ct = map_type("String", "String");
+        assert_eq!(
+            r#"_let(v.getStringMap("the-property"), (_m) => _mergeWith(_m, def)) ?? def"#
+                .to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+
+        let ct = map_type("AnEnum", "AnObject");
+        assert_eq!(
+            r#"_let(_let(v.getVariablesMap("the-property"), (_m) => _mapEntriesNotNull(_m, AnEnum.enumValue, AnObject.create)), (_m) => _mergeWith(_m, def, AnObject.mergeWith)) ?? def"#
+                .to_string(),
+            ct.property_getter(oracle, &"v", &"the-property", &"def")
+        );
+    }
+
+    #[test]
+    fn test_as_json() {
+        let oracle = &*oracle();
+
+        let ct = list_type("String");
+        assert_eq!("p".to_string(), ct.as_json(oracle, &"p"));
+
+        let ct = list_type("AnObject");
+        assert_eq!(
+            "p.map((_v: AnObject) => _v.toJSON())".to_string(),
+            ct.as_json(oracle, &"p")
+        );
+
+        let ct = map_type("AnEnum", "AnObject");
+        assert_eq!(
+            "_mapValuesNotNull(p, (_v: AnObject) => _v.toJSON())".to_string(),
+            ct.as_json(oracle, &"p")
+        );
+
+        let ct = optional_type("AnObject");
+        assert_eq!(
+            "_let(p, (_v: AnObject) => _v.toJSON()) ?? null".to_string(),
+            ct.as_json(oracle, &"p")
+        );
+    }
+}
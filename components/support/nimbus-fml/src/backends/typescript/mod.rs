@@ -0,0 +1,129 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::command_line::commands::GenerateStructCmd;
+use crate::error::{FMLError, Result};
+use crate::frontend::AboutBlock;
+use crate::intermediate_representation::FeatureManifest;
+use askama::Template;
+
+mod gen_structs;
+
+impl AboutBlock {
+    fn nimbus_object_name_ts(&self) -> String {
+        let ts_about = self.typescript_about.as_ref().unwrap();
+        ts_about.class.clone()
+    }
+
+    /// The module specifier used by other generated files to import this one.
+    ///
+    /// If no module is given in the manifest, we assume the generated files are siblings
+    /// of each other, named after the nimbus object.
+    fn nimbus_module_specifier_ts(&self) -> String {
+        let ts_about = self.typescript_about.as_ref().unwrap();
+        ts_about
+            .module
+            .clone()
+            .unwrap_or_else(|| format!("./{}", ts_about.class))
+    }
+}
+
+pub(crate) fn generate_struct(manifest: &FeatureManifest, cmd: &GenerateStructCmd) -> Result<()> {
+    if manifest.about.typescript_about.is_none() {
+        return Err(FMLError::ValidationError(
+            "about".to_string(),
+            format!(
+                "The `about` block is missing a valid `typescript` entry: {}",
+                &cmd.manifest
+            ),
+        ));
+    }
+
+    let path = &cmd.output;
+    let path = if path.is_dir() {
+        path.join(format!("{}.ts", manifest.about.nimbus_object_name_ts()))
+    } else {
+        path.clone()
+    };
+
+    let ts = gen_structs::FeatureManifestDeclaration::new(manifest);
+
+    let contents = ts.render()?;
+
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use anyhow::{bail, Context, Result};
+    use std::path::Path;
+    use std::process::Command;
+
+    fn detect_tsc() -> Result<bool> {
+        let tsc = Command::new("which").arg("tsc").output()?;
+        let node = Command::new("which").arg("node").output()?;
+
+        Ok(tsc.status.success() && node.status.success())
+    }
+
+    // Compile the generated manifests and the test script together, so the script can
+    // import the generated modules as siblings.
+    pub fn compile_manifest_ts(
+        manifest_files: &[String],
+        script: &Path,
+        out_dir: &Path,
+    ) -> Result<()> {
+        let src_dir = out_dir.join("src");
+        std::fs::create_dir_all(&src_dir)?;
+        let mut sources = Vec::new();
+        for file in manifest_files.iter().map(Path::new).chain([script]) {
+            let dest = src_dir.join(file.file_name().context("Expected a file name")?);
+            std::fs::copy(file, &dest)?;
+            sources.push(dest);
+        }
+
+        let status = Command::new("tsc")
+            // Our generated code should type check under the strictest settings.
+            .arg("--strict")
+            .arg("--target")
+            .arg("es2019")
+            .arg("--module")
+            .arg("commonjs")
+            .arg("--outDir")
+            .arg(out_dir)
+            .args(&sources)
+            .spawn()
+            .context("Failed to spawn `tsc` when compiling generated code")?
+            .wait()
+            .context("Failed to wait for `tsc` when compiling generated code")?;
+        if !status.success() {
+            bail!("running `tsc` failed compiling a generated manifest")
+        }
+        Ok(())
+    }
+
+    pub fn run_script_with_generated_code(manifest_files: &[String], script: &Path) -> Result<()> {
+        if !detect_tsc()? {
+            eprintln!("SDK-446 Install tsc and node or add them the PATH to run tests");
+            return Ok(());
+        }
+        let temp = tempfile::tempdir()?;
+        let build_dir = temp.path();
+        compile_manifest_ts(manifest_files, script, build_dir)?;
+
+        let script_js = build_dir.join(script.with_extension("js").file_name().unwrap());
+        let status = Command::new("node")
+            .arg(&script_js)
+            .spawn()
+            .context("Failed to spawn `node` when running script")?
+            .wait()
+            .context("Failed to wait for `node` when running script")?;
+        if !status.success() {
+            bail!("running `node` failed running a script")
+        }
+        Ok(())
+    }
+}
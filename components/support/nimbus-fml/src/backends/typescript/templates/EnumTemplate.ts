@@ -0,0 +1,23 @@
+{% let inner = self.inner() %}
+{% let class_name = inner.name()|class_name %}
+
+{{ inner.doc()|comment("") }}
+export enum {{class_name}} {
+    {%- for v in inner.variants() %}
+    {{ v.doc()|comment("    ") }}
+    {{ v.name()|enum_variant_name }} = {{ v.name()|quoted }},
+    {%- endfor %}
+}
+
+export namespace {{class_name}} {
+    export function enumValue(string: string): {{class_name}} | undefined {
+        switch (string) {
+            {%- for v in inner.variants() %}
+            case {{ v.name()|quoted }}:
+                return {{class_name}}.{{ v.name()|enum_variant_name }};
+            {%- endfor %}
+            default:
+                return undefined;
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// This file was autogenerated by the `nimbus-fml` crate.
+// Trust me, you don't want to mess with it!
+
+/* eslint-disable */
+{%- for import in self.imports() %}
+{{ import }}
+{%- endfor %}
+
+{% include "RuntimeTemplate.ts" %}
+
+{%- let nimbus_object = self.fm.about.nimbus_object_name_ts() %}
+
+/**
+ * An object for safely accessing feature configuration from Nimbus.
+ *
+ * This is generated.
+ *
+ * Before use to configure the application or any of its features, this class needs
+ * to be wired up to the SDK API. This is an object created by the application which connects to
+ * the Nimbus SDK and thence to the server.
+ *
+ * ```
+ * const nimbus: FeaturesInterface = connectToNimbusSDK();
+ * {{ nimbus_object }}.initialize(() => nimbus);
+ * ```
+ *
+ * Once initialized, this can be used to access typesafe configuration object via the `features` member.
+ *
+ * This class should not be edited manually, but changed by editing the `nimbus.fml.yaml` file, and
+ * re-running the `nimbus-fml` tool, which is likely already being used by the build script.
+ */
+export class {{ nimbus_object }} {
+    /**
+     * This is the connection between the Nimbus SDK (and thus the Nimbus server) and the generated code.
+     *
+     * The recommended method is to use the `initialize(getSdk)` method, early in the application
+     * startup process.
+     */
+    static api: FeaturesInterface | undefined = undefined;
+
+    private static getSdk: () => FeaturesInterface | undefined = () => {{ nimbus_object }}.api;
+
+    /**
+     * Accessor object for generated configuration classes extracted from Nimbus, with built-in
+     * default values.
+     */
+    static readonly features = {
+        {%- for f in self.iter_feature_defs() %}
+        {%- let raw_name = f.name() %}
+        {%- let class_name = raw_name|class_name %}
+        {{ f.doc()|comment("        ") }}
+        {{ raw_name|var_name }}: new FeatureHolder<{{ class_name }}>(
+            () => {{ nimbus_object }}.getSdk(),
+            {{ raw_name|quoted }},
+            (variables: Variables) => new {{ class_name }}({}, variables),
+        ),
+        {%- endfor %}
+    };
+
+    /**
+     * This method should be called as early in the startup sequence of the app as possible.
+     * This is to connect the Nimbus SDK (and thus server) with the `{{ nimbus_object }}`
+     * class.
+     */
+    static initialize(getSdk: () => FeaturesInterface | undefined): void {
+        {{ nimbus_object }}.getSdk = getSdk;
+        {%- for f in self.fm.iter_imported_files() %}
+        {{ f.about().nimbus_object_name_ts() }}.initialize(getSdk);
+        {%- endfor %}
+        {{ nimbus_object }}.reinitialize();
+        {{ nimbus_object }}.invalidateCachedValues();
+    }
+
+    /**
+     * Refresh the cache of configuration objects.
+     *
+     * For performance reasons, the feature configurations are constructed once then cached.
+     * This method is to clear that cache for all features configured with Nimbus.
+     *
+     * It must be called whenever the Nimbus SDK finishes applying pending experiments.
+     */
+    static invalidateCachedValues(): void {
+        {%- for f in self.iter_feature_defs() %}
+        {{ nimbus_object }}.features.{{- f.name()|var_name -}}.withCachedValue(undefined);
+        {%- endfor %}
+        {%- for f in self.fm.iter_imported_files() %}
+        {{ f.about().nimbus_object_name_ts() }}.invalidateCachedValues();
+        {%- endfor %}
+    }
+
+    /**
+     * Get a list of feature ids where the feature allows co-enrollment.
+     */
+    static getCoenrollingFeatureIds(): Array<string> {
+        return [
+            {%- for f in self.fm.get_coenrolling_feature_ids() %}
+            {{- f|quoted }}
+            {%- if !loop.last %}, {% endif %}
+            {%- endfor %}];
+    }
+
+    /**
+     * Introspection utility method.
+     */
+    static getFeature(featureId: string): FeatureHolder<FMLFeatureInterface> | undefined {
+        switch (featureId) {
+            {%- for f in self.iter_feature_defs() %}
+            {%- let raw_name = f.name() %}
+            case {{ raw_name|quoted }}:
+                return {{ nimbus_object }}.features.{{ raw_name|var_name }};
+            {%- endfor %}
+            default:
+                return undefined;
+        }
+    }
+
+    {% let blocks = self.initialization_code() -%}
+    /**
+     * All generated initialization code. Clients shouldn't need to override or call
+     * this.
+     * We put it in a separate method because we have to be quite careful about what order
+     * the initialization happens in— e.g. when importing other FML files.
+     */
+    static reinitialize(): void {
+        {%- if !blocks.is_empty() %}
+        {%- for code in blocks.iter() %}
+        {{ code }}
+        {%- endfor %}
+        {%- else %}
+        // Nothing left to do.
+        {%- endif %}
+    }
+}
+
+// Public interface members begin here.
+{%- for code in self.declaration_code() %}
+{{- code }}
+{%- endfor %}
+{%- if !blocks.is_empty() %}
+
+{{ nimbus_object }}.reinitialize();
+{%- endif %}
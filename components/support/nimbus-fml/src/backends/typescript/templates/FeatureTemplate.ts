@@ -0,0 +1,7 @@
+{%- import "macros.ts" as ts %}
+{%- let inner = self.inner() %}
+{% call ts::render_defaults(inner) %}
+{{ inner.doc()|comment("") }}
+export class {{ inner.name()|class_name }} implements FMLFeatureInterface {
+   {% call ts::render_class_body(inner) %}
+}
@@ -0,0 +1,10 @@
+{%- let class_name = self.inner.about().nimbus_object_name_ts() %}
+        {%- for f in self.inner.features() %}
+        {{ class_name }}.features.{{ f.name()|var_name }}.withInitializer((variables: Variables) =>
+            new {{ f.name()|class_name }}({
+                {%- for p in f.props() %}
+                {{ p.name()|var_name }}: {{ p.typ()|literal(self, p.default(), "") }},
+                {%- endfor %}
+            }, variables)
+        );
+        {%- endfor %}
@@ -0,0 +1,20 @@
+{%- import "macros.ts" as ts %}
+{%- let inner = self.inner() %}
+{%- let class_name = inner.name()|class_name %}
+{% call ts::render_defaults(inner) %}
+{{ inner.doc()|comment("") }}
+export class {{ class_name }} implements FMLObjectInterface {
+   {% call ts::render_class_body(inner) %}
+
+   _mergeWith(defaults: {{ class_name }} | undefined): {{ class_name }} {
+      return defaults !== undefined ? new {{ class_name }}(defaults._defaults, this._variables) : this;
+   }
+
+   static create(variables: Variables): {{ class_name }} {
+      return new {{ class_name }}({}, variables);
+   }
+
+   static mergeWith(overrides: {{ class_name }}, defaults: {{ class_name }}): {{ class_name }} {
+      return overrides._mergeWith(defaults);
+   }
+}
@@ -0,0 +1,252 @@
+{#- The runtime support needed by the generated code.
+
+    There is no TypeScript Nimbus SDK for the generated code to import, so every generated
+    module carries its own copy. It is deliberately small: a `Variables` wrapper around feature
+    JSON, a `FeatureHolder` to cache feature objects, and a handful of functions used to map
+    and merge values.
+-#}
+/**
+ * The JSON configuration of a feature, as delivered by Nimbus.
+ */
+export type JSONObject = { [key: string]: unknown };
+
+/**
+ * The connection between the generated code and the Nimbus SDK (or whatever is standing in
+ * for it in the application).
+ */
+export interface FeaturesInterface {
+    /**
+     * The feature configuration JSON for the given feature, or `undefined` if no experiment
+     * or rollout is configuring the feature.
+     */
+    getFeatureConfig(featureId: string): JSONObject | undefined;
+
+    /**
+     * Record that the user has been exposed to the given feature.
+     */
+    recordExposureEvent(featureId: string): void;
+}
+
+/**
+ * All generated objects and features can be turned back into JSON.
+ */
+export interface FMLObjectInterface {
+    toJSON(): JSONObject;
+}
+
+export interface FMLFeatureInterface extends FMLObjectInterface {}
+
+function _asString(value: unknown): string | undefined {
+    return typeof value === "string" ? value : undefined;
+}
+
+function _asInt(value: unknown): number | undefined {
+    return typeof value === "number" && Number.isInteger(value) ? value : undefined;
+}
+
+function _asNumber(value: unknown): number | undefined {
+    return typeof value === "number" ? value : undefined;
+}
+
+function _asURL(value: string): URL | undefined {
+    try {
+        return new URL(value);
+    } catch {
+        return undefined;
+    }
+}
+
+function _asBool(value: unknown): boolean | undefined {
+    return typeof value === "boolean" ? value : undefined;
+}
+
+function _asJSONObject(value: unknown): JSONObject | undefined {
+    return typeof value === "object" && value !== null && !Array.isArray(value)
+        ? (value as JSONObject)
+        : undefined;
+}
+
+function _asVariables(value: unknown): Variables | undefined {
+    const json = _asJSONObject(value);
+    return json !== undefined ? new Variables(json) : undefined;
+}
+
+function _asList<T>(value: unknown, transform: (item: unknown) => T | undefined): Array<T> | undefined {
+    return Array.isArray(value) ? _mapNotNull(value, transform) : undefined;
+}
+
+function _asMap<T>(value: unknown, transform: (item: unknown) => T | undefined): Record<string, T> | undefined {
+    const json = _asJSONObject(value);
+    return json !== undefined ? _mapValuesNotNull(json, transform) : undefined;
+}
+
+/**
+ * A typed view on to feature JSON. Values of the wrong type are treated as missing.
+ */
+export class Variables {
+    static readonly EMPTY: Variables = new Variables({});
+
+    // This is deliberately public: modules generated from imported manifests carry their own
+    // copy of this class, and TypeScript only treats classes without private members as
+    // interchangeable.
+    constructor(readonly json: JSONObject) {}
+
+    getString(key: string): string | undefined { return _asString(this.json[key]); }
+    getInt(key: string): number | undefined { return _asInt(this.json[key]); }
+    getDouble(key: string): number | undefined { return _asNumber(this.json[key]); }
+    getBool(key: string): boolean | undefined { return _asBool(this.json[key]); }
+    getText(key: string): string | undefined { return _asString(this.json[key]); }
+    getImage(key: string): string | undefined { return _asString(this.json[key]); }
+    getVariables(key: string): Variables | undefined { return _asVariables(this.json[key]); }
+
+    getStringList(key: string): Array<string> | undefined { return _asList(this.json[key], _asString); }
+    getIntList(key: string): Array<number> | undefined { return _asList(this.json[key], _asInt); }
+    getDoubleList(key: string): Array<number> | undefined { return _asList(this.json[key], _asNumber); }
+    getBoolList(key: string): Array<boolean> | undefined { return _asList(this.json[key], _asBool); }
+    getTextList(key: string): Array<string> | undefined { return _asList(this.json[key], _asString); }
+    getImageList(key: string): Array<string> | undefined { return _asList(this.json[key], _asString); }
+    getVariablesList(key: string): Array<Variables> | undefined { return _asList(this.json[key], _asVariables); }
+
+    getStringMap(key: string): Record<string, string> | undefined { return _asMap(this.json[key], _asString); }
+    getIntMap(key: string): Record<string, number> | undefined { return _asMap(this.json[key], _asInt); }
+    getDoubleMap(key: string): Record<string, number> | undefined { return _asMap(this.json[key], _asNumber); }
+    getBoolMap(key: string): Record<string, boolean> | undefined { return _asMap(this.json[key], _asBool); }
+    getTextMap(key: string): Record<string, string> | undefined { return _asMap(this.json[key], _asString); }
+    getImageMap(key: string): Record<string, string> | undefined { return _asMap(this.json[key], _asString); }
+    getVariablesMap(key: string): Record<string, Variables> | undefined { return _asMap(this.json[key], _asVariables); }
+
+    asStringMap(): Record<string, string> | undefined { return _asMap(this.json, _asString); }
+    asIntMap(): Record<string, number> | undefined { return _asMap(this.json, _asInt); }
+    asDoubleMap(): Record<string, number> | undefined { return _asMap(this.json, _asNumber); }
+    asBoolMap(): Record<string, boolean> | undefined { return _asMap(this.json, _asBool); }
+    asTextMap(): Record<string, string> | undefined { return _asMap(this.json, _asString); }
+    asImageMap(): Record<string, string> | undefined { return _asMap(this.json, _asString); }
+    asVariablesMap(): Record<string, Variables> | undefined { return _asMap(this.json, _asVariables); }
+
+    toJSON(): JSONObject {
+        return this.json;
+    }
+}
+
+/**
+ * Holds a feature object, constructing it from the SDK when first asked, and caching it until
+ * the cache is invalidated.
+ */
+export class FeatureHolder<T extends FMLFeatureInterface> {
+    private cachedValue: T | undefined = undefined;
+
+    constructor(
+        private getSdk: () => FeaturesInterface | undefined,
+        readonly featureId: string,
+        private create: (variables: Variables) => T,
+    ) {}
+
+    /**
+     * Get the feature configuration. This is cached until `invalidateCachedValues()` is
+     * called on the generated nimbus object.
+     */
+    value(): T {
+        if (this.cachedValue === undefined) {
+            const json = this.getSdk()?.getFeatureConfig(this.featureId);
+            const variables = json !== undefined ? new Variables(json) : Variables.EMPTY;
+            this.cachedValue = this.create(variables);
+        }
+        return this.cachedValue;
+    }
+
+    /**
+     * Send an exposure event for this feature.
+     */
+    recordExposure(): void {
+        this.getSdk()?.recordExposureEvent(this.featureId);
+    }
+
+    withSdk(getSdk: () => FeaturesInterface | undefined): void {
+        this.getSdk = getSdk;
+        this.cachedValue = undefined;
+    }
+
+    withCachedValue(value: T | undefined): void {
+        this.cachedValue = value;
+    }
+
+    withInitializer(create: (variables: Variables) => T): void {
+        this.create = create;
+        this.cachedValue = undefined;
+    }
+}
+
+function _let<A, B>(value: A | null | undefined, transform: (value: A) => B | null | undefined): B | undefined {
+    if (value === null || value === undefined) {
+        return undefined;
+    }
+    return transform(value) ?? undefined;
+}
+
+function _mapNotNull<A, B>(list: Array<A>, transform: (value: A) => B | null | undefined): Array<B> {
+    const result: Array<B> = [];
+    for (const item of list) {
+        const value = transform(item);
+        if (value !== null && value !== undefined) {
+            result.push(value);
+        }
+    }
+    return result;
+}
+
+function _mapValuesNotNull<K extends string, A, B>(
+    map: Partial<Record<K, A>>,
+    transform: (value: A) => B | null | undefined,
+): Record<K, B> {
+    return _mapEntriesNotNull(map, (key: K) => key, transform);
+}
+
+function _mapKeysNotNull<K extends string, A>(
+    map: Partial<Record<string, A>>,
+    transform: (key: string) => K | undefined,
+): Record<K, A> {
+    return _mapEntriesNotNull(map, transform, (value: A) => value);
+}
+
+function _mapEntriesNotNull<J extends string, K extends string, A, B>(
+    map: Partial<Record<J, A>>,
+    keyTransform: (key: J) => K | undefined,
+    valueTransform: (value: A) => B | null | undefined,
+): Record<K, B> {
+    const result = {} as Record<K, B>;
+    for (const key of Object.keys(map) as Array<J>) {
+        const value = map[key];
+        if (value === undefined) {
+            continue;
+        }
+        const k = keyTransform(key);
+        const v = valueTransform(value as A);
+        if (k !== undefined && v !== null && v !== undefined) {
+            result[k] = v;
+        }
+    }
+    return result;
+}
+
+/**
+ * Merge the `overrides` map into the `defaults` map. If a `merger` function is given, then
+ * values present in both maps are merged with it; otherwise the value from `overrides` wins.
+ */
+function _mergeWith<K extends string, V>(
+    overrides: Partial<Record<K, V>>,
+    defaults: Partial<Record<K, V>>,
+    merger?: (overrides: V, defaults: V) => V,
+): Record<K, V> {
+    const result = { ...defaults } as Record<K, V>;
+    for (const key of Object.keys(overrides) as Array<K>) {
+        const override = overrides[key];
+        if (override === undefined) {
+            continue;
+        }
+        const value = defaults[key];
+        result[key] = merger !== undefined && value !== undefined
+            ? merger(override as V, value as V)
+            : (override as V);
+    }
+    return result;
+}
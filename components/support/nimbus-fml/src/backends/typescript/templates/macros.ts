@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+ {#- This file contains macros needed to generate code for the FML.
+
+    It is the natural place to put commonalities between Object and Features,
+    and rendering literals for Objects.
+-#}
+
+{% macro render_defaults(inner) %}
+{%- let class_name = inner.name()|class_name %}
+{#- The defaults interface holds the default values that come from the manifest. They should
+    completely specify all values needed for the object or feature. #}
+/**
+ * The default values for `{{ class_name }}`.
+ */
+export interface {{ class_name }}Defaults {
+   {%- for p in inner.props() %}
+   {{ p.name()|var_name }}: {{ p.typ()|defaults_type_label }};
+   {%- endfor %}
+}
+{% endmacro %}
+
+{% macro render_class_body(inner) %}
+{%- let class_name = inner.name()|class_name %}
+   private readonly _variables: Variables;
+   private readonly _defaults: {{ class_name }}Defaults;
+
+   {#- A constructor for application tests to use.  #}
+   /**
+    * Any defaults not given here are taken from the manifest.
+    */
+   constructor(defaults: Partial<{{ class_name }}Defaults> = {}, variables: Variables = Variables.EMPTY) {
+      this._variables = variables;
+      this._defaults = {
+         {%- for p in inner.props() %}
+         {%- let nm = p.name()|var_name %}
+         {{ nm }}: defaults.{{ nm }} !== undefined ? defaults.{{ nm }} : {{ p.typ()|literal(self, p.default(), "") }},
+         {%- endfor %}
+      };
+   }
+
+   {# The property getters #}
+   {%- for p in inner.props() %}
+   {%- let prop_ts = p.name()|var_name %}
+   {%- let type_ts = p.typ()|type_label %}
+   {%- let defaults = format!("this._defaults.{}", prop_ts) %}
+   {%- let getter = p.typ()|property(p.name(), "this._variables", defaults) %}
+   {{ p.doc()|comment("   ") }}
+   get {{ prop_ts }}(): {{ type_ts }} {
+      return {{ getter }};
+   }
+{% endfor %}
+
+   {#- toJSON #}
+   toJSON(): JSONObject {
+      return {
+         {%- for p in inner.props() %}
+         {%- let prop_ts = p.name()|var_name %}
+         {%- let this_prop = format!("this.{}", prop_ts) %}
+         {{ p.name()|quoted }}: {{ this_prop|to_json(p.typ()) }},
+         {%- endfor %}
+      };
+   }
+{% endmacro %}
@@ -15,6 +15,7 @@ pub(crate) enum CliCmd {
     FetchFile(LoaderConfig, String),
     Validate(ValidateCmd),
     PrintChannels(PrintChannelsCmd),
+    Diff(DiffCmd),
 }
 
 #[derive(Clone)]
@@ -53,6 +54,15 @@ pub(crate) struct PrintChannelsCmd {
     pub(crate) as_json: bool,
 }
 
+pub(crate) struct DiffCmd {
+    pub(crate) old_manifest: String,
+    pub(crate) new_manifest: String,
+    pub(crate) old_loader: LoaderConfig,
+    pub(crate) new_loader: LoaderConfig,
+    pub(crate) experiments: Option<PathBuf>,
+    pub(crate) allow_breaking: bool,
+}
+
 impl TryFrom<&std::ffi::OsStr> for TargetLanguage {
     type Error = Error;
     fn try_from(value: &std::ffi::OsStr) -> Result<Self> {
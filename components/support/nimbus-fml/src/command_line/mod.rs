@@ -10,8 +10,8 @@ use crate::util::loaders::LoaderConfig;
 use anyhow::{bail, Result};
 use clap::{App, ArgMatches};
 use commands::{
-    CliCmd, GenerateExperimenterManifestCmd, GenerateSingleFileManifestCmd, GenerateStructCmd,
-    PrintChannelsCmd, ValidateCmd,
+    CliCmd, DiffCmd, GenerateExperimenterManifestCmd, GenerateSingleFileManifestCmd,
+    GenerateStructCmd, PrintChannelsCmd, ValidateCmd,
 };
 
 use std::{
@@ -41,6 +41,7 @@ fn process_command(cmd: &CliCmd) -> Result<()> {
         CliCmd::FetchFile(files, nm) => workflows::fetch_file(files, nm)?,
         CliCmd::Validate(params) => workflows::validate(params)?,
         CliCmd::PrintChannels(params) => workflows::print_channels(params)?,
+        CliCmd::Diff(params) => workflows::diff(params)?,
     };
     Ok(())
 }
@@ -72,6 +73,7 @@ where
         ("channels", Some(matches)) => {
             CliCmd::PrintChannels(create_print_channels_from_cli(matches, cwd)?)
         }
+        ("diff", Some(matches)) => CliCmd::Diff(create_diff_command_from_cli(matches, cwd)?),
         (word, _) => unimplemented!("Command {} not implemented", word),
     })
 }
@@ -146,6 +148,15 @@ fn create_generate_command_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<
 }
 
 fn create_loader(matches: &ArgMatches, cwd: &Path) -> Result<LoaderConfig> {
+    create_loader_with_ref(matches, cwd, &input_file(matches)?, matches.value_of("ref"))
+}
+
+fn create_loader_with_ref(
+    matches: &ArgMatches,
+    cwd: &Path,
+    manifest: &str,
+    ref_: Option<&str>,
+) -> Result<LoaderConfig> {
     let cwd = cwd.to_path_buf();
     let cache_dir = matches
         .value_of("cache-dir")
@@ -155,12 +166,11 @@ fn create_loader(matches: &ArgMatches, cwd: &Path) -> Result<LoaderConfig> {
     let files = matches.values_of("repo-file").unwrap_or_default();
     let repo_files = files.into_iter().map(|s| s.to_string()).collect();
 
-    let manifest = input_file(matches)?;
-
-    let _ref = matches.value_of("ref").map(String::from);
-
     let mut refs: BTreeMap<_, _> = Default::default();
-    match (LoaderConfig::repo_and_path(&manifest), _ref) {
+    match (
+        LoaderConfig::repo_and_path(manifest),
+        ref_.map(String::from),
+    ) {
         (Some((repo, _)), Some(ref_)) => refs.insert(repo, ref_),
         _ => None,
     };
@@ -190,6 +200,30 @@ fn create_print_channels_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<Pr
     })
 }
 
+fn create_diff_command_from_cli(matches: &ArgMatches, cwd: &Path) -> Result<DiffCmd> {
+    let old_manifest = matches
+        .value_of("OLD")
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("OLD file is needed, but not specified"))?;
+    let new_manifest = matches
+        .value_of("NEW")
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("NEW file is needed, but not specified"))?;
+    // The --ref only applies to the old manifest: the new manifest is usually the one being worked on.
+    let old_loader = create_loader_with_ref(matches, cwd, &old_manifest, matches.value_of("ref"))?;
+    let new_loader = create_loader_with_ref(matches, cwd, &new_manifest, None)?;
+    let experiments = file_path("experiments", matches, cwd).ok();
+    let allow_breaking = matches.is_present("allow-breaking");
+    Ok(DiffCmd {
+        old_manifest,
+        new_manifest,
+        old_loader,
+        new_loader,
+        experiments,
+        allow_breaking,
+    })
+}
+
 fn input_file(args: &ArgMatches) -> Result<String> {
     args.value_of("INPUT")
         .map(String::from)
@@ -379,6 +413,68 @@ mod cli_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_cli_generate_typescript_features_language_flag() -> Result<()> {
+        let cwd = package_dir()?;
+        for language in ["typescript", "ts"] {
+            let cmd = get_command_from_cli(
+                [
+                    FML_BIN,
+                    "generate",
+                    "--channel",
+                    "channel-test",
+                    "--language",
+                    language,
+                    TEST_FILE,
+                    "./build/generated",
+                ],
+                &cwd,
+            )?;
+
+            assert!(matches!(cmd, CliCmd::Generate(_)));
+
+            if let CliCmd::Generate(cmd) = cmd {
+                assert_eq!(cmd.channel, "channel-test");
+                assert_eq!(cmd.language, TargetLanguage::TypeScript);
+                assert!(!cmd.load_from_ir);
+                assert!(cmd.output.ends_with("build/generated"));
+                assert!(cmd.manifest.ends_with(TEST_FILE));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_generate_rust_features_language_flag() -> Result<()> {
+        let cwd = package_dir()?;
+        for language in ["rust", "rs"] {
+            let cmd = get_command_from_cli(
+                [
+                    FML_BIN,
+                    "generate",
+                    "--channel",
+                    "channel-test",
+                    "--language",
+                    language,
+                    TEST_FILE,
+                    "./build/generated",
+                ],
+                &cwd,
+            )?;
+
+            assert!(matches!(cmd, CliCmd::Generate(_)));
+
+            if let CliCmd::Generate(cmd) = cmd {
+                assert_eq!(cmd.channel, "channel-test");
+                assert_eq!(cmd.language, TargetLanguage::Rust);
+                assert!(!cmd.load_from_ir);
+                assert!(cmd.output.ends_with("build/generated"));
+                assert!(cmd.manifest.ends_with(TEST_FILE));
+            }
+        }
+        Ok(())
+    }
+
     ///////////////////////////////////////////////////////////////////////////
     #[test]
     fn test_cli_generate_experimenter_android() -> Result<()> {
@@ -564,4 +660,38 @@ mod cli_tests {
         );
         Ok(())
     }
+
+    ///////////////////////////////////////////////////////////////////////////
+
+    #[test]
+    fn test_cli_diff() -> Result<()> {
+        let cwd = package_dir()?;
+        let cmd = get_command_from_cli(
+            [
+                FML_BIN,
+                "diff",
+                "@mozilla-mobile/firefox-android/fenix/app/nimbus.fml.yaml",
+                TEST_FILE,
+                "--ref",
+                "v120.0",
+                "--experiments",
+                "experiments.json",
+            ],
+            &cwd,
+        )?;
+
+        assert!(matches!(cmd, CliCmd::Diff(_)));
+
+        if let CliCmd::Diff(cmd) = cmd {
+            assert!(cmd.new_manifest.ends_with(TEST_FILE));
+            assert_eq!(cmd.old_loader.refs.len(), 1);
+            assert!(cmd.new_loader.refs.is_empty());
+            assert!(cmd
+                .experiments
+                .map(|p| p.ends_with("experiments.json"))
+                .unwrap_or_default());
+            assert!(!cmd.allow_breaking);
+        }
+        Ok(())
+    }
 }
@@ -6,7 +6,7 @@ use glob::MatchOptions;
 use std::collections::HashSet;
 
 use super::commands::{
-    GenerateExperimenterManifestCmd, GenerateSingleFileManifestCmd, GenerateStructCmd,
+    DiffCmd, GenerateExperimenterManifestCmd, GenerateSingleFileManifestCmd, GenerateStructCmd,
     PrintChannelsCmd, ValidateCmd,
 };
 use crate::error::FMLError::CliError;
@@ -16,6 +16,7 @@ use crate::{
     error::{FMLError, Result},
     intermediate_representation::{FeatureManifest, TargetLanguage},
     parser::Parser,
+    schema::{ExperimentRecipe, RecipeValidator, SchemaDiffer},
     util::loaders::{FileLoader, FilePath, LoaderConfig},
 };
 use console::Term;
@@ -95,6 +96,8 @@ fn generate_struct_from_ir(ir: &FeatureManifest, cmd: &GenerateStructCmd) -> Res
         }
         TargetLanguage::Kotlin => backends::kotlin::generate_struct(ir, cmd)?,
         TargetLanguage::Swift => backends::swift::generate_struct(ir, cmd)?,
+        TargetLanguage::TypeScript => backends::typescript::generate_struct(ir, cmd)?,
+        TargetLanguage::Rust => backends::rust::generate_struct(ir, cmd)?,
         _ => unimplemented!(
             "Unsupported output language for structs: {}",
             language.extension()
@@ -317,6 +320,78 @@ pub(crate) fn print_channels(cmd: &PrintChannelsCmd) -> Result<()> {
     Ok(())
 }
 
+pub(crate) fn diff(cmd: &DiffCmd) -> Result<()> {
+    let term = Term::stdout();
+
+    let old = load_manifest_for_diff(&cmd.old_loader, &cmd.old_manifest)?;
+    let new = load_manifest_for_diff(&cmd.new_loader, &cmd.new_manifest)?;
+
+    term.write_line("Comparing manifests:")?;
+    let changes = SchemaDiffer::new(&old, &new).diff();
+    let mut breaking_count = 0;
+    for change in &changes {
+        if change.is_breaking() {
+            breaking_count += 1;
+            output_err(&term, &change.path, &change.message)?;
+        } else {
+            output_warn(&term, &change.path, &change.message)?;
+        }
+    }
+    if changes.is_empty() {
+        output_ok(&term, "No changes to the schema\n")?;
+    } else if breaking_count == 0 {
+        output_ok(&term, "All changes are compatible\n")?;
+    }
+
+    let mut invalid_count = 0;
+    if let Some(experiments) = &cmd.experiments {
+        term.write_line("Validating experiments against the new manifest:")?;
+        let string = std::fs::read_to_string(experiments)?;
+        let recipes = ExperimentRecipe::from_json(serde_json::from_str(&string)?)?;
+        let problems = RecipeValidator::new(&old, &new).validate(&recipes);
+        for problem in &problems {
+            output_err(&term, &problem.path, &problem.message)?;
+        }
+        invalid_count = problems.len();
+        if invalid_count == 0 {
+            output_ok(
+                &term,
+                &format!("All {} experiments are valid\n", recipes.len()),
+            )?;
+        }
+    }
+
+    let plural = |n: usize, word: &str| format!("{n} {word}{}", if n == 1 { "" } else { "s" });
+    if invalid_count > 0 {
+        return Err(CliError(format!(
+            "{} would become invalid with the new manifest",
+            plural(invalid_count, "experiment feature configuration")
+        )));
+    }
+    if breaking_count > 0 {
+        let message = format!(
+            "The new manifest has {}",
+            plural(breaking_count, "breaking change")
+        );
+        if !cmd.allow_breaking {
+            return Err(CliError(message));
+        }
+        output_note(&term, &message)?;
+    }
+
+    Ok(())
+}
+
+fn load_manifest_for_diff(loader: &LoaderConfig, manifest: &str) -> Result<FeatureManifest> {
+    let files: FileLoader = TryFrom::try_from(loader)?;
+    let path = files.file_path(manifest)?;
+    let load_from_ir = matches!(
+        TargetLanguage::from_extension(manifest),
+        Ok(TargetLanguage::ExperimenterJSON)
+    );
+    load_feature_manifest(files, path, load_from_ir, None)
+}
+
 #[cfg(test)]
 mod test {
     use std::fs;
@@ -327,7 +402,7 @@ mod test {
 
     use super::*;
     use crate::backends::experimenter_manifest::ExperimenterManifest;
-    use crate::backends::{kotlin, swift};
+    use crate::backends::{kotlin, rust, swift, typescript};
     use crate::frontend::AboutBlock;
     use crate::util::{generated_src_dir, join, pkg_dir};
 
@@ -356,7 +431,10 @@ mod test {
         Ok(())
     }
 
-    fn generate_struct_cli_overrides(from_cli: AboutBlock, cmd: &GenerateStructCmd) -> Result<()> {
+    pub(crate) fn generate_struct_cli_overrides(
+        from_cli: AboutBlock,
+        cmd: &GenerateStructCmd,
+    ) -> Result<()> {
         let files: FileLoader = TryFrom::try_from(&cmd.loader)?;
         let path = files.file_path(&cmd.manifest)?;
         let mut ir = load_feature_manifest(files, path, cmd.load_from_ir, Some(&cmd.channel))?;
@@ -368,9 +446,13 @@ mod test {
         let from_cli = from_cli;
         let kotlin_about = from_cli.kotlin_about.or(from_file.kotlin_about);
         let swift_about = from_cli.swift_about.or(from_file.swift_about);
+        let typescript_about = from_cli.typescript_about.or(from_file.typescript_about);
+        let rust_about = from_cli.rust_about.or(from_file.rust_about);
         let about = AboutBlock {
             kotlin_about,
             swift_about,
+            typescript_about,
+            rust_about,
             ..Default::default()
         };
         ir.about = about;
@@ -473,6 +555,13 @@ mod test {
             TargetLanguage::Swift => {
                 swift::test::run_script_with_generated_code(manifests_out, test_script.as_ref())?
             }
+            TargetLanguage::TypeScript => typescript::test::run_script_with_generated_code(
+                manifests_out,
+                test_script.as_ref(),
+            )?,
+            TargetLanguage::Rust => {
+                rust::test::run_script_with_generated_code(manifests_out, test_script.as_ref())?
+            }
             _ => unimplemented!(),
         }
         Ok(())
@@ -520,7 +609,7 @@ mod test {
             let cmd = create_experimenter_manifest_cmd(path)?;
             generate_experimenter_manifest(&cmd)?;
 
-            let generated = fs::read_to_string(&cmd.output)?;
+            let generated = std::fs::read_to_string(&cmd.output)?;
             let generated_yaml = serde_yaml::from_str(&generated)?;
             validate_against_experimenter_schema(
                 join(pkg_dir(), "ExperimentFeatureManifest.schema.json"),
@@ -570,6 +659,62 @@ mod test {
         Ok(())
     }
 
+    fn create_diff_cmd(new: &str, experiments: Option<&str>, allow_breaking: bool) -> DiffCmd {
+        DiffCmd {
+            old_manifest: join(pkg_dir(), "fixtures/fe/diff/old.fml.yaml"),
+            new_manifest: join(pkg_dir(), new),
+            old_loader: Default::default(),
+            new_loader: Default::default(),
+            experiments: experiments.map(|p| join(pkg_dir(), p).into()),
+            allow_breaking,
+        }
+    }
+
+    #[test]
+    fn test_diff_command_with_compatible_changes() -> Result<()> {
+        let cmd = create_diff_cmd(
+            "fixtures/fe/diff/compatible.fml.yaml",
+            Some("fixtures/fe/diff/experiments.json"),
+            false,
+        );
+        diff(&cmd)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_command_fails_on_breaking_changes() -> Result<()> {
+        let cmd = create_diff_cmd("fixtures/fe/diff/breaking.fml.yaml", None, false);
+        match diff(&cmd) {
+            Err(CliError(error)) => {
+                assert_eq!(error, "The new manifest has 4 breaking changes");
+            }
+            _ => panic!("Expected a CliError"),
+        };
+
+        let cmd = create_diff_cmd("fixtures/fe/diff/breaking.fml.yaml", None, true);
+        diff(&cmd)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_command_fails_on_invalid_experiments() -> Result<()> {
+        let cmd = create_diff_cmd(
+            "fixtures/fe/diff/breaking.fml.yaml",
+            Some("fixtures/fe/diff/experiments.json"),
+            true,
+        );
+        match diff(&cmd) {
+            Err(CliError(error)) => {
+                assert_eq!(
+                    error,
+                    "3 experiment feature configurations would become invalid with the new manifest"
+                );
+            }
+            _ => panic!("Expected a CliError"),
+        };
+        Ok(())
+    }
+
     fn create_experimenter_manifest_cmd(path: &str) -> Result<GenerateExperimenterManifestCmd> {
         let manifest = join(pkg_dir(), path);
         let file = Path::new(&manifest);
@@ -754,6 +899,17 @@ mod kts_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_with_typed_values_kts() -> Result<()> {
+        generate_and_assert(
+            "test/typed_values.kts",
+            "fixtures/fe/typed_values.yaml",
+            "release",
+            false,
+        )?;
+        Ok(())
+    }
+
     #[test]
     fn test_with_app_menu_from_ir() -> Result<()> {
         generate_and_assert(
@@ -945,6 +1101,17 @@ mod swift_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_with_typed_values_swift() -> Result<()> {
+        generate_and_assert(
+            "test/typed_values.swift",
+            "fixtures/fe/typed_values.yaml",
+            "release",
+            false,
+        )?;
+        Ok(())
+    }
+
     #[test]
     fn test_with_bundled_resources_swift() -> Result<()> {
         generate_and_assert(
@@ -1077,3 +1244,133 @@ mod swift_tests {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod typescript_tests {
+    use super::{test::generate_and_assert_with_config, *};
+    use crate::frontend::{AboutBlock, TypeScriptAboutBlock};
+
+    fn typescript_about() -> AboutBlock {
+        AboutBlock {
+            typescript_about: Some(TypeScriptAboutBlock {
+                class: "MyNimbus".to_string(),
+                module: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_with_app_menu_typescript_from_ir() -> Result<()> {
+        generate_and_assert_with_config(
+            "test/app_menu.ts",
+            "fixtures/ir/app_menu.json",
+            "release",
+            true,
+            typescript_about(),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_objects_typescript_from_ir() -> Result<()> {
+        generate_and_assert_with_config(
+            "test/with_objects.ts",
+            "fixtures/ir/with_objects.json",
+            "release",
+            true,
+            typescript_about(),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_typed_values_typescript() -> Result<()> {
+        generate_and_assert_with_config(
+            "test/typed_values.ts",
+            "fixtures/fe/typed_values.yaml",
+            "release",
+            false,
+            typescript_about(),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod rust_tests {
+    use super::{
+        test::{
+            create_command_from_test, generate_and_assert_with_config,
+            generate_struct_cli_overrides,
+        },
+        *,
+    };
+    use crate::frontend::{AboutBlock, RustAboutBlock};
+
+    fn rust_about() -> AboutBlock {
+        AboutBlock {
+            rust_about: Some(RustAboutBlock {
+                class: "MyNimbus".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_with_app_menu_rust_generates_code() -> Result<()> {
+        let cmd = create_command_from_test(
+            "test/app_menu.rs",
+            "fixtures/ir/app_menu.json",
+            "release",
+            true,
+        )?;
+        generate_struct_cli_overrides(rust_about(), &cmd)?;
+        let generated = std::fs::read_to_string(&cmd.output)?;
+        assert!(generated.contains("pub struct MyNimbus;"));
+        Ok(())
+    }
+
+    // The remaining tests build the generated code with `cargo`, which fetches `serde`
+    // from crates.io, so they need network access and are ignored by default.
+    // Run them with `cargo test -p nimbus-fml -- --ignored rust_tests`.
+
+    #[test]
+    #[ignore]
+    fn test_with_app_menu_rust_from_ir() -> Result<()> {
+        generate_and_assert_with_config(
+            "test/app_menu.rs",
+            "fixtures/ir/app_menu.json",
+            "release",
+            true,
+            rust_about(),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn test_with_objects_rust_from_ir() -> Result<()> {
+        generate_and_assert_with_config(
+            "test/with_objects.rs",
+            "fixtures/ir/with_objects.json",
+            "release",
+            true,
+            rust_about(),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    #[ignore]
+    fn test_with_typed_values_rust() -> Result<()> {
+        generate_and_assert_with_config(
+            "test/typed_values.rs",
+            "fixtures/fe/typed_values.yaml",
+            "release",
+            false,
+            rust_about(),
+        )?;
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::error::{did_you_mean, FMLError};
-use crate::intermediate_representation::{FeatureDef, PropDef, TypeRef};
+use crate::intermediate_representation::{parse_color, FeatureDef, PropDef, TypeRef};
 use crate::{
     error::Result,
     intermediate_representation::{EnumDef, ObjectDef},
@@ -32,6 +32,7 @@ impl<'a> DefaultsValidator<'a> {
             let path = format!("objects/{}.{}", object.name, prop.name);
             let error_path = vec![prop.name.to_string()];
             self.validate_types(path.as_str(), &error_path, &prop.typ, &prop.default)?;
+            self.validate_range(path.as_str(), &error_path, prop, &prop.default)?;
         }
         Ok(())
     }
@@ -41,6 +42,7 @@ impl<'a> DefaultsValidator<'a> {
             let path = format!("features/{}.{}", feature_def.name, prop.name);
             let error_path = vec![prop.name.to_string()];
             self.validate_types(path.as_str(), &error_path, &prop.typ, &prop.default)?;
+            self.validate_range(path.as_str(), &error_path, prop, &prop.default)?;
         }
 
         let string_aliases = feature_def.get_string_aliases();
@@ -88,7 +90,36 @@ impl<'a> DefaultsValidator<'a> {
             | (TypeRef::String, Value::String(_))
             | (TypeRef::StringAlias(_), Value::String(_))
             | (TypeRef::Int, Value::Number(_))
+            | (TypeRef::Float, Value::Number(_))
             | (TypeRef::Option(_), Value::Null) => Ok(()),
+            (TypeRef::Url, Value::String(s)) => match url::Url::parse(s) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(FMLError::FeatureValidationError {
+                    path: path.to_string(),
+                    message: format!("\"{s}\" is not a valid Url: {e}"),
+                    literals: append_quoted(error_path, s),
+                }),
+            },
+            (TypeRef::Color, Value::String(s)) => match parse_color(s) {
+                Some(_) => Ok(()),
+                None => Err(FMLError::FeatureValidationError {
+                    path: path.to_string(),
+                    message: format!(
+                        "\"{s}\" is not a valid Color; expected #RRGGBB or #AARRGGBB"
+                    ),
+                    literals: append_quoted(error_path, s),
+                }),
+            },
+            (TypeRef::Duration, Value::Number(n)) => match n.as_u64() {
+                Some(_) => Ok(()),
+                None => Err(FMLError::FeatureValidationError {
+                    path: path.to_string(),
+                    message: format!(
+                        "{n} is not a valid Duration; expected a whole number of milliseconds"
+                    ),
+                    literals: append1(error_path, &n.to_string()),
+                }),
+            },
             (TypeRef::Option(inner), v) => {
                 if let TypeRef::Option(_) = inner.as_ref() {
                     return Err(FMLError::ValidationError(
@@ -216,6 +247,7 @@ impl<'a> DefaultsValidator<'a> {
                             &["{".to_string(), format!("\"{}\"", &prop.name)],
                         );
                         self.validate_types(&path, &literals, &prop.typ, map_val)?;
+                        self.validate_range(&path, &literals, prop, map_val)?;
                     } else {
                         unseen.insert(nm.clone());
                     }
@@ -245,6 +277,77 @@ impl<'a> DefaultsValidator<'a> {
         }
     }
 
+    /// Checks numeric values against the `min` and `max` of the property. Collections of numbers
+    /// are checked item by item.
+    ///
+    /// This assumes that the value has already been type checked with `validate_types`.
+    fn validate_range(
+        &self,
+        path: &str,
+        error_path: &[String],
+        prop: &PropDef,
+        value: &Value,
+    ) -> Result<()> {
+        if prop.min.is_none() && prop.max.is_none() {
+            return Ok(());
+        }
+        self.validate_number_range(path, error_path, prop, &prop.typ, value)
+    }
+
+    fn validate_number_range(
+        &self,
+        path: &str,
+        error_path: &[String],
+        prop: &PropDef,
+        typ: &TypeRef,
+        value: &Value,
+    ) -> Result<()> {
+        match (typ, value) {
+            (TypeRef::Option(inner), _) => {
+                self.validate_number_range(path, error_path, prop, inner, value)
+            }
+            (TypeRef::List(inner), Value::Array(arr)) => {
+                let mut literals = append1(error_path, "[");
+                for (index, value) in arr.iter().enumerate() {
+                    let path = format!("{path}['{index}']");
+                    self.validate_number_range(&path, &literals, prop, inner, value)?;
+                    literals.push(",".to_string());
+                }
+                Ok(())
+            }
+            (TypeRef::EnumMap(_, inner), Value::Object(map))
+            | (TypeRef::StringMap(inner), Value::Object(map)) => {
+                for (key, value) in map {
+                    let path = format!("{path}['{key}']");
+                    let literals = append(error_path, &["{".to_string(), format!("\"{key}\"")]);
+                    self.validate_number_range(&path, &literals, prop, inner, value)?;
+                }
+                Ok(())
+            }
+            (TypeRef::Int | TypeRef::Float | TypeRef::Duration, Value::Number(n)) => {
+                let v = n.as_f64().unwrap_or_default();
+                let message = match (&prop.min, &prop.max) {
+                    (Some(min), _) if v < min.as_f64().unwrap_or_default() => {
+                        format!("{n} is less than the minimum value {min} of {}", prop.name)
+                    }
+                    (_, Some(max)) if v > max.as_f64().unwrap_or_default() => {
+                        format!(
+                            "{n} is greater than the maximum value {max} of {}",
+                            prop.name
+                        )
+                    }
+                    _ => return Ok(()),
+                };
+                Err(FMLError::FeatureValidationError {
+                    path: path.to_string(),
+                    message,
+                    literals: append1(error_path, &n.to_string()),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn validate_string_aliases(
         &self,
@@ -524,7 +627,8 @@ mod test_types {
     impl DefaultsValidator<'_> {
         fn validate_prop_defaults(&self, prop: &PropDef) -> Result<()> {
             let error_path = Default::default();
-            self.validate_types(prop.name.as_str(), &error_path, &prop.typ, &prop.default)
+            self.validate_types(prop.name.as_str(), &error_path, &prop.typ, &prop.default)?;
+            self.validate_range(prop.name.as_str(), &error_path, prop, &prop.default)
         }
     }
 
@@ -622,6 +726,154 @@ mod test_types {
         Ok(())
     }
 
+    #[test]
+    fn test_validate_prop_defaults_float() -> Result<()> {
+        let mut prop = PropDef::new("key", &TypeRef::Float, &json!(0.5));
+        let enums1 = Default::default();
+        let objs = Default::default();
+        let fm = DefaultsValidator::new(&enums1, &objs);
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!(2);
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!("0.5");
+
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, default is string when it should be a number");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_prop_defaults_url() -> Result<()> {
+        let mut prop = PropDef::new("key", &TypeRef::Url, &json!("https://mozilla.org/"));
+        let enums1 = Default::default();
+        let objs = Default::default();
+        let fm = DefaultsValidator::new(&enums1, &objs);
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!("mailto:someone@example.com");
+        fm.validate_prop_defaults(&prop)?;
+
+        prop.default = json!("not a url");
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, default is not a valid url");
+        prop.default = json!(100);
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, default is number when it should be a string");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_prop_defaults_color() -> Result<()> {
+        let mut prop = PropDef::new("key", &TypeRef::Color, &json!("#336699"));
+        let enums1 = Default::default();
+        let objs = Default::default();
+        let fm = DefaultsValidator::new(&enums1, &objs);
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!("#80ffFFff");
+        fm.validate_prop_defaults(&prop)?;
+
+        for invalid in ["336699", "#369", "#3366991", "#33669g", "red"] {
+            prop.default = json!(invalid);
+            fm.validate_prop_defaults(&prop)
+                .expect_err("Should error out, default is not a valid color");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_prop_defaults_duration() -> Result<()> {
+        let mut prop = PropDef::new("key", &TypeRef::Duration, &json!(1500));
+        let enums1 = Default::default();
+        let objs = Default::default();
+        let fm = DefaultsValidator::new(&enums1, &objs);
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!(0);
+        fm.validate_prop_defaults(&prop)?;
+
+        prop.default = json!(-1);
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, durations cannot be negative");
+        prop.default = json!(1.5);
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, durations are whole milliseconds");
+        prop.default = json!("1s");
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, default is string when it should be a number");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_prop_defaults_range() -> Result<()> {
+        let enums1 = Default::default();
+        let objs = Default::default();
+        let fm = DefaultsValidator::new(&enums1, &objs);
+
+        let mut prop = PropDef::with_range("key", &TypeRef::Int, &json!(5), &json!(1), &json!(10));
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!(1);
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!(10);
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!(0);
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, default is less than the minimum");
+        prop.default = json!(11);
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, default is greater than the maximum");
+
+        let mut prop = PropDef::with_range(
+            "key",
+            &TypeRef::StringMap(Box::new(TypeRef::Float)),
+            &json!({ "a": 0.0, "b": 1.0 }),
+            &json!(0.0),
+            &json!(1.0),
+        );
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!({ "a": 0.0, "b": 1.5 });
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, a map value is greater than the maximum");
+
+        let mut prop = PropDef::with_range(
+            "key",
+            &TypeRef::List(Box::new(TypeRef::Option(Box::new(TypeRef::Duration)))),
+            &json!([100, null, 200]),
+            &json!(100),
+            &json!(null),
+        );
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!([100, null, 50]);
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, a list item is less than the minimum");
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_object_prop_range() -> Result<()> {
+        let obj = ObjectDef::new(
+            "Thresholds",
+            &[PropDef::with_range(
+                "ratio",
+                &TypeRef::Float,
+                &json!(0.5),
+                &json!(0.0),
+                &json!(1.0),
+            )],
+        );
+        let enums1 = Default::default();
+        let objs = ObjectDef::into_map(&[obj]);
+        let fm = DefaultsValidator::new(&enums1, &objs);
+
+        let mut prop = PropDef::new(
+            "key",
+            &TypeRef::Object("Thresholds".into()),
+            &json!({ "ratio": 0.25 }),
+        );
+        fm.validate_prop_defaults(&prop)?;
+        prop.default = json!({ "ratio": 1.25 });
+        fm.validate_prop_defaults(&prop)
+            .expect_err("Should error out, the object's property is greater than the maximum");
+        Ok(())
+    }
+
     #[test]
     fn test_validate_prop_defaults_bundle_image() -> Result<()> {
         let mut prop = PropDef::new("key", &TypeRef::BundleImage, &json!("IconBlue"));
@@ -54,6 +54,8 @@ impl PropDef {
             doc: format!("{nm} property of type {typ}"),
             pref_key: None,
             string_alias: None,
+            min: None,
+            max: None,
         }
     }
 
@@ -65,6 +67,8 @@ impl PropDef {
             doc: nm.to_string(),
             pref_key: None,
             string_alias: Some(sa.clone()),
+            min: None,
+            max: None,
         }
     }
 
@@ -76,6 +80,22 @@ impl PropDef {
             default: default.clone(),
             pref_key: None,
             string_alias: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    pub(crate) fn with_range(
+        nm: &str,
+        typ: &TypeRef,
+        default: &Value,
+        min: &Value,
+        max: &Value,
+    ) -> Self {
+        PropDef {
+            min: min.as_number().cloned(),
+            max: max.as_number().cloned(),
+            ..Self::new(nm, typ, default)
         }
     }
 }
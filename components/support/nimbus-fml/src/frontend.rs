@@ -55,6 +55,12 @@ pub(crate) struct FieldBody {
     #[serde(rename = "type")]
     pub(crate) variable_type: String,
     pub(crate) default: Option<serde_json::Value>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) min: Option<serde_json::Number>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max: Option<serde_json::Number>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -88,11 +94,20 @@ pub(crate) struct AboutBlock {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "swift", alias = "ios")]
     pub(crate) swift_about: Option<SwiftAboutBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "typescript", alias = "ts")]
+    pub(crate) typescript_about: Option<TypeScriptAboutBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "rust")]
+    pub(crate) rust_about: Option<RustAboutBlock>,
 }
 
 impl AboutBlock {
     pub(crate) fn is_includable(&self) -> bool {
-        self.kotlin_about.is_none() && self.swift_about.is_none()
+        self.kotlin_about.is_none()
+            && self.swift_about.is_none()
+            && self.typescript_about.is_none()
+            && self.rust_about.is_none()
     }
 
     #[allow(unused)]
@@ -100,6 +115,8 @@ impl AboutBlock {
         match lang {
             TargetLanguage::Kotlin => self.kotlin_about.is_some(),
             TargetLanguage::Swift => self.swift_about.is_some(),
+            TargetLanguage::TypeScript => self.typescript_about.is_some(),
+            TargetLanguage::Rust => self.rust_about.is_some(),
             TargetLanguage::IR => true,
             TargetLanguage::ExperimenterYAML => true,
             TargetLanguage::ExperimenterJSON => true,
@@ -112,6 +129,8 @@ impl AboutBlock {
             description: self.description.clone(),
             kotlin_about: None,
             swift_about: None,
+            typescript_about: None,
+            rust_about: None,
         }
     }
 }
@@ -122,6 +141,19 @@ pub(crate) struct SwiftAboutBlock {
     pub(crate) class: String,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub(crate) struct TypeScriptAboutBlock {
+    pub(crate) class: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) module: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RustAboutBlock {
+    pub(crate) class: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
 pub(crate) struct KotlinAboutBlock {
     pub(crate) package: String,
@@ -309,6 +341,8 @@ impl ManifestFrontEnd {
             default: json!(body.default),
             pref_key: None,
             string_alias: None,
+            min: body.min.clone(),
+            max: body.max.clone(),
         }
     }
 
@@ -9,7 +9,7 @@ use crate::schema::{SchemaHasher, SchemaValidator};
 use crate::util::loaders::FilePath;
 use anyhow::{bail, Error, Result as AnyhowResult};
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use serde_json::{Map, Number, Value};
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Display;
 
@@ -17,6 +17,8 @@ use std::fmt::Display;
 pub enum TargetLanguage {
     Kotlin,
     Swift,
+    TypeScript,
+    Rust,
     IR,
     ExperimenterYAML,
     ExperimenterJSON,
@@ -27,6 +29,8 @@ impl TargetLanguage {
         match self {
             TargetLanguage::Kotlin => "kt",
             TargetLanguage::Swift => "swift",
+            TargetLanguage::TypeScript => "ts",
+            TargetLanguage::Rust => "rs",
             TargetLanguage::IR => "fml.json",
             TargetLanguage::ExperimenterJSON => "json",
             TargetLanguage::ExperimenterYAML => "yaml",
@@ -48,6 +52,8 @@ impl TryFrom<&str> for TargetLanguage {
         Ok(match value.to_ascii_lowercase().as_str() {
             "kotlin" | "kt" | "kts" => TargetLanguage::Kotlin,
             "swift" => TargetLanguage::Swift,
+            "typescript" | "ts" => TargetLanguage::TypeScript,
+            "rust" | "rs" => TargetLanguage::Rust,
             "fml.json" => TargetLanguage::IR,
             "yaml" => TargetLanguage::ExperimenterYAML,
             "json" => TargetLanguage::ExperimenterJSON,
@@ -93,6 +99,19 @@ pub enum TypeRef {
 
     List(Box<TypeRef>),
     Option(Box<TypeRef>),
+
+    // Newer types go after the existing ones, because the derived `Hash`
+    // includes each variant's position, and the schema hash must not change
+    // for features that don't use them.
+    Float,
+
+    // Primitives with a more specific representation in JSON.
+    // A URL string.
+    Url,
+    // A color string, `#RRGGBB` or `#AARRGGBB`.
+    Color,
+    // A whole number of milliseconds.
+    Duration,
 }
 
 impl Display for TypeRef {
@@ -101,6 +120,10 @@ impl Display for TypeRef {
             Self::String => f.write_str("String"),
             Self::Int => f.write_str("Int"),
             Self::Boolean => f.write_str("Boolean"),
+            Self::Float => f.write_str("Float"),
+            Self::Url => f.write_str("Url"),
+            Self::Color => f.write_str("Color"),
+            Self::Duration => f.write_str("Duration"),
             Self::BundleImage => f.write_str("Image"),
             Self::BundleText => f.write_str("Text"),
             Self::StringAlias(v) => f.write_str(v),
@@ -127,6 +150,18 @@ impl TypeRef {
         }
     }
 
+    /// Numeric types, and collections of numeric types, can be constrained with `min` and `max`.
+    pub(crate) fn supports_range(&self) -> bool {
+        match self {
+            Self::Int | Self::Float | Self::Duration => true,
+            Self::Option(inner) | Self::List(inner) | Self::StringMap(inner) => {
+                inner.supports_range()
+            }
+            Self::EnumMap(_, inner) => inner.supports_range(),
+            _ => false,
+        }
+    }
+
     pub(crate) fn name(&self) -> Option<&str> {
         match self {
             Self::Enum(s) | Self::Object(s) | Self::StringAlias(s) => Some(s),
@@ -633,6 +668,12 @@ pub struct PropDef {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) string_alias: Option<TypeRef>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) min: Option<Number>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max: Option<Number>,
 }
 
 impl PropDef {
@@ -664,6 +705,22 @@ impl TypeFinder for PropDef {
 
 pub type Literal = Value;
 
+/// Parses a `Color` literal, either `#RRGGBB` or `#AARRGGBB`, into an ARGB value.
+///
+/// Colors without an alpha component are fully opaque.
+pub(crate) fn parse_color(s: &str) -> Option<u32> {
+    let hex = s.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let argb = u32::from_str_radix(hex, 16).ok()?;
+    match hex.len() {
+        6 => Some(0xFF00_0000 | argb),
+        8 => Some(argb),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct ImportedModule<'a> {
     pub(crate) fm: &'a FeatureManifest,
@@ -19,7 +19,11 @@ fn parse_typeref_string(input: String) -> Result<(String, Option<String>)> {
     // This should be the TypeRef type (except for )
     let type_ref_name = object_type_iter.next().unwrap().trim();
 
-    if ["String", "Int", "Boolean"].contains(&type_ref_name) {
+    if [
+        "String", "Int", "Boolean", "Float", "Double", "Url", "Color", "Duration",
+    ]
+    .contains(&type_ref_name)
+    {
         return Ok((type_ref_name.to_string(), None));
     }
 
@@ -43,6 +47,10 @@ pub(crate) fn get_typeref_from_string(
         "String" => TypeRef::String,
         "Int" => TypeRef::Int,
         "Boolean" => TypeRef::Boolean,
+        "Float" | "Double" => TypeRef::Float,
+        "Url" => TypeRef::Url,
+        "Color" => TypeRef::Color,
+        "Duration" => TypeRef::Duration,
         "BundleText" | "Text" => TypeRef::BundleText,
         "BundleImage" | "Drawable" | "Image" => TypeRef::BundleImage,
         "Enum" => TypeRef::Enum(type_name.unwrap()),
@@ -743,6 +751,56 @@ mod unit_tests {
         Ok(())
     }
 
+    #[test]
+    fn test_convert_to_typeref_float() -> Result<()> {
+        // Testing converting to TypeRef::Float
+        let types = Default::default();
+        assert_eq!(
+            get_typeref_from_string("Float".to_string(), &types).unwrap(),
+            TypeRef::Float
+        );
+        assert_eq!(
+            get_typeref_from_string("Double".to_string(), &types).unwrap(),
+            TypeRef::Float
+        );
+        assert_eq!(
+            get_typeref_from_string("List<Float>".to_string(), &types).unwrap(),
+            TypeRef::List(Box::new(TypeRef::Float))
+        );
+        get_typeref_from_string("float".to_string(), &types).unwrap_err();
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_to_typeref_url_color_duration() -> Result<()> {
+        let types = Default::default();
+        assert_eq!(
+            get_typeref_from_string("Url".to_string(), &types).unwrap(),
+            TypeRef::Url
+        );
+        assert_eq!(
+            get_typeref_from_string("Color".to_string(), &types).unwrap(),
+            TypeRef::Color
+        );
+        assert_eq!(
+            get_typeref_from_string("Duration".to_string(), &types).unwrap(),
+            TypeRef::Duration
+        );
+        assert_eq!(
+            get_typeref_from_string("Map<String, Color>".to_string(), &types).unwrap(),
+            TypeRef::StringMap(Box::new(TypeRef::Color))
+        );
+        assert_eq!(
+            get_typeref_from_string("Option<Duration>".to_string(), &types).unwrap(),
+            TypeRef::Option(Box::new(TypeRef::Duration))
+        );
+        get_typeref_from_string("URL".to_string(), &types).unwrap_err();
+        get_typeref_from_string("Colour".to_string(), &types).unwrap_err();
+
+        Ok(())
+    }
+
     #[test]
     fn test_convert_to_typeref_bundletext() -> Result<()> {
         // Testing converting to TypeRef::BundleText
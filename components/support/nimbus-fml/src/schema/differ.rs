@@ -0,0 +1,518 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Display,
+};
+
+use serde_json::Number;
+
+use crate::intermediate_representation::{
+    EnumDef, FeatureDef, FeatureManifest, ObjectDef, PropDef, TypeRef,
+};
+
+use super::{SchemaHasher, TypeQuery};
+
+/// Whether a change to a manifest is safe for the experiments which are already running
+/// against the old version of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Compatibility {
+    /// Every feature configuration which was valid for the old manifest is valid for the new one.
+    Compatible,
+    /// Some feature configurations which were valid for the old manifest are not valid for the
+    /// new one.
+    Breaking,
+}
+
+impl Display for Compatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Compatible => f.write_str("compatible"),
+            Self::Breaking => f.write_str("breaking"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SchemaChange {
+    pub(crate) path: String,
+    pub(crate) message: String,
+    pub(crate) compatibility: Compatibility,
+}
+
+impl SchemaChange {
+    fn compatible(path: &str, message: String) -> Self {
+        Self {
+            path: path.to_string(),
+            message,
+            compatibility: Compatibility::Compatible,
+        }
+    }
+
+    fn breaking(path: &str, message: String) -> Self {
+        Self {
+            path: path.to_string(),
+            message,
+            compatibility: Compatibility::Breaking,
+        }
+    }
+
+    fn new(path: &str, message: String, is_compatible: bool) -> Self {
+        if is_compatible {
+            Self::compatible(path, message)
+        } else {
+            Self::breaking(path, message)
+        }
+    }
+
+    pub(crate) fn is_breaking(&self) -> bool {
+        self.compatibility == Compatibility::Breaking
+    }
+}
+
+impl Display for SchemaChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Compares two versions of a manifest, to find the changes which would affect experiments.
+///
+/// Only the schema is compared: changes to the defaults don't affect whether an experiment's
+/// feature configuration is valid.
+pub(crate) struct SchemaDiffer<'a> {
+    old: &'a FeatureManifest,
+    new: &'a FeatureManifest,
+}
+
+impl<'a> SchemaDiffer<'a> {
+    pub(crate) fn new(old: &'a FeatureManifest, new: &'a FeatureManifest) -> Self {
+        Self { old, new }
+    }
+
+    pub(crate) fn diff(&self) -> Vec<SchemaChange> {
+        let old_features = features_by_name(self.old);
+        let new_features = features_by_name(self.new);
+
+        let mut changes = Default::default();
+        // Objects and enums are shared between features, so we only report on them once.
+        let mut seen = Default::default();
+
+        for (nm, old) in &old_features {
+            let path = format!("features/{nm}");
+            match new_features.get(nm) {
+                Some(new) => diff_feature(&path, *old, *new, &mut seen, &mut changes),
+                None => changes.push(SchemaChange::breaking(
+                    &path,
+                    "The feature has been removed".to_string(),
+                )),
+            }
+        }
+
+        for nm in new_features.keys() {
+            if !old_features.contains_key(nm) {
+                changes.push(SchemaChange::compatible(
+                    &format!("features/{nm}"),
+                    "The feature has been added".to_string(),
+                ));
+            }
+        }
+
+        changes
+    }
+}
+
+type FeatureInManifest<'a> = (&'a FeatureManifest, &'a FeatureDef);
+
+fn features_by_name(fm: &FeatureManifest) -> BTreeMap<String, FeatureInManifest<'_>> {
+    fm.iter_all_feature_defs()
+        .map(|(fm, f)| (f.name(), (fm, f)))
+        .collect()
+}
+
+fn schema_hash(fm: &FeatureManifest, feature_def: &FeatureDef) -> u64 {
+    SchemaHasher::new(&fm.enum_defs, &fm.obj_defs).hash(feature_def)
+}
+
+fn diff_feature(
+    path: &str,
+    (old_fm, old_def): FeatureInManifest,
+    (new_fm, new_def): FeatureInManifest,
+    seen: &mut HashSet<String>,
+    changes: &mut Vec<SchemaChange>,
+) {
+    // The schema hash covers the feature and all the objects and enums it uses, so if it hasn't
+    // changed, there's nothing more to look at.
+    if schema_hash(old_fm, old_def) == schema_hash(new_fm, new_def) {
+        return;
+    }
+
+    if old_def.allow_coenrollment != new_def.allow_coenrollment {
+        changes.push(SchemaChange::new(
+            path,
+            format!(
+                "allow-coenrollment has changed from {} to {}",
+                old_def.allow_coenrollment, new_def.allow_coenrollment
+            ),
+            new_def.allow_coenrollment,
+        ));
+    }
+
+    diff_props(path, &old_def.props, &new_def.props, changes);
+
+    let old_types = TypeQuery::new(&old_fm.obj_defs).all_types(old_def);
+    let new_types = TypeQuery::new(&new_fm.obj_defs).all_types(new_def);
+    for t in old_types.intersection(&new_types) {
+        match t {
+            TypeRef::Object(nm) if seen.insert(format!("objects/{nm}")) => {
+                if let (Some(old), Some(new)) = (old_fm.find_object(nm), new_fm.find_object(nm)) {
+                    diff_object(&format!("objects/{nm}"), old, new, changes);
+                }
+            }
+            TypeRef::Enum(nm) if seen.insert(format!("enums/{nm}")) => {
+                if let (Some(old), Some(new)) = (old_fm.find_enum(nm), new_fm.find_enum(nm)) {
+                    diff_enum(&format!("enums/{nm}"), old, new, changes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn diff_object(path: &str, old: &ObjectDef, new: &ObjectDef, changes: &mut Vec<SchemaChange>) {
+    diff_props(path, &old.props, &new.props, changes);
+}
+
+fn diff_enum(path: &str, old: &EnumDef, new: &EnumDef, changes: &mut Vec<SchemaChange>) {
+    let old_variants: BTreeSet<_> = old.variants.iter().map(|v| v.name()).collect();
+    let new_variants: BTreeSet<_> = new.variants.iter().map(|v| v.name()).collect();
+    for nm in old_variants.difference(&new_variants) {
+        changes.push(SchemaChange::breaking(
+            &format!("{path}/{nm}"),
+            "The variant has been removed".to_string(),
+        ));
+    }
+    for nm in new_variants.difference(&old_variants) {
+        changes.push(SchemaChange::compatible(
+            &format!("{path}/{nm}"),
+            "The variant has been added".to_string(),
+        ));
+    }
+}
+
+fn diff_props(path: &str, old: &[PropDef], new: &[PropDef], changes: &mut Vec<SchemaChange>) {
+    let old_props: BTreeMap<_, _> = old.iter().map(|p| (p.name(), p)).collect();
+    let new_props: BTreeMap<_, _> = new.iter().map(|p| (p.name(), p)).collect();
+
+    for (nm, old) in &old_props {
+        let path = format!("{path}/{nm}");
+        match new_props.get(nm) {
+            Some(new) => diff_prop(&path, old, new, changes),
+            None => changes.push(SchemaChange::breaking(
+                &path,
+                "The property has been removed".to_string(),
+            )),
+        }
+    }
+
+    for nm in new_props.keys() {
+        if !old_props.contains_key(nm) {
+            changes.push(SchemaChange::compatible(
+                &format!("{path}/{nm}"),
+                "The property has been added".to_string(),
+            ));
+        }
+    }
+}
+
+fn diff_prop(path: &str, old: &PropDef, new: &PropDef, changes: &mut Vec<SchemaChange>) {
+    if old.typ != new.typ {
+        changes.push(SchemaChange::new(
+            path,
+            format!("The type has changed from {} to {}", old.typ, new.typ),
+            is_assignable(&old.typ, &new.typ),
+        ));
+    }
+
+    if old.string_alias != new.string_alias {
+        let message = match (&old.string_alias, &new.string_alias) {
+            (None, Some(sa)) => format!("The property now defines the string-alias {sa}"),
+            (Some(sa), None) => format!("The property no longer defines the string-alias {sa}"),
+            (Some(old), Some(new)) => {
+                format!("The string-alias has changed from {old} to {new}")
+            }
+            (None, None) => unreachable!(),
+        };
+        // Defining a new string-alias doesn't change what this property accepts.
+        changes.push(SchemaChange::new(path, message, old.string_alias.is_none()));
+    }
+
+    diff_bound(
+        path,
+        "minimum",
+        &old.min,
+        &new.min,
+        |old, new| new <= old,
+        changes,
+    );
+    diff_bound(
+        path,
+        "maximum",
+        &old.max,
+        &new.max,
+        |old, new| new >= old,
+        changes,
+    );
+}
+
+fn diff_bound(
+    path: &str,
+    name: &str,
+    old: &Option<Number>,
+    new: &Option<Number>,
+    is_looser: impl Fn(f64, f64) -> bool,
+    changes: &mut Vec<SchemaChange>,
+) {
+    let change = match (old, new) {
+        (Some(old), Some(new)) if old != new => SchemaChange::new(
+            path,
+            format!("The {name} has changed from {old} to {new}"),
+            is_looser(as_f64(old), as_f64(new)),
+        ),
+        (None, Some(new)) => {
+            SchemaChange::breaking(path, format!("The {name} of {new} has been added"))
+        }
+        (Some(old), None) => {
+            SchemaChange::compatible(path, format!("The {name} of {old} has been removed"))
+        }
+        _ => return,
+    };
+    changes.push(change);
+}
+
+fn as_f64(n: &Number) -> f64 {
+    n.as_f64().unwrap_or_default()
+}
+
+/// Is every value which is valid for the `old` type also valid for the `new` type?
+///
+/// Objects and enums with the same name are assumed to be assignable here; changes within them
+/// are found separately.
+fn is_assignable(old: &TypeRef, new: &TypeRef) -> bool {
+    match (old, new) {
+        (old, new) if old == new => true,
+        (TypeRef::Int, TypeRef::Float) => true,
+        (TypeRef::Enum(_) | TypeRef::StringAlias(_), TypeRef::String) => true,
+        (TypeRef::Option(old), TypeRef::Option(new)) => is_assignable(old, new),
+        (old, TypeRef::Option(new)) => is_assignable(old, new),
+        (TypeRef::List(old), TypeRef::List(new)) => is_assignable(old, new),
+        (TypeRef::StringMap(old), TypeRef::StringMap(new)) => is_assignable(old, new),
+        (TypeRef::EnumMap(old_k, old_v), TypeRef::EnumMap(new_k, new_v)) => {
+            is_assignable(old_k, new_k) && is_assignable(old_v, new_v)
+        }
+        (TypeRef::EnumMap(_, old_v), TypeRef::StringMap(new_v)) => is_assignable(old_v, new_v),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::collections::HashMap;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::fixtures::intermediate_representation::get_feature_manifest;
+
+    fn feature(props: &[PropDef]) -> FeatureDef {
+        FeatureDef::new("my-feature", "Documentation", props.to_vec(), false)
+    }
+
+    fn manifest(objs: &[ObjectDef], enums: &[EnumDef], props: &[PropDef]) -> FeatureManifest {
+        get_feature_manifest(
+            objs.to_vec(),
+            enums.to_vec(),
+            vec![feature(props)],
+            HashMap::new(),
+        )
+    }
+
+    fn diff(old: &FeatureManifest, new: &FeatureManifest) -> Vec<(String, Compatibility)> {
+        SchemaDiffer::new(old, new)
+            .diff()
+            .into_iter()
+            .map(|c| (c.path, c.compatibility))
+            .collect()
+    }
+
+    fn compatible(path: &str) -> (String, Compatibility) {
+        (path.to_string(), Compatibility::Compatible)
+    }
+
+    fn breaking(path: &str) -> (String, Compatibility) {
+        (path.to_string(), Compatibility::Breaking)
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let props = [PropDef::new("p", &TypeRef::Int, &json!(1))];
+        let old = manifest(&[], &[], &props);
+
+        // Changing the defaults doesn't change the schema.
+        let new = manifest(&[], &[], &[PropDef::new("p", &TypeRef::Int, &json!(2))]);
+        assert_eq!(diff(&old, &new), vec![]);
+    }
+
+    #[test]
+    fn test_features_added_and_removed() {
+        let old = manifest(&[], &[], &[]);
+        let new = get_feature_manifest(
+            vec![],
+            vec![],
+            vec![FeatureDef::new("another-feature", "", vec![], false)],
+            HashMap::new(),
+        );
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                breaking("features/my-feature"),
+                compatible("features/another-feature")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_props_added_and_removed() {
+        let old = manifest(&[], &[], &[PropDef::new("a", &TypeRef::Int, &json!(1))]);
+        let new = manifest(&[], &[], &[PropDef::new("b", &TypeRef::Int, &json!(1))]);
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                breaking("features/my-feature/a"),
+                compatible("features/my-feature/b")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_changes() {
+        let opt = |t: TypeRef| TypeRef::Option(Box::new(t));
+        let list = |t: TypeRef| TypeRef::List(Box::new(t));
+        let cases = [
+            (TypeRef::Int, TypeRef::Float, true),
+            (TypeRef::Float, TypeRef::Int, false),
+            (TypeRef::String, opt(TypeRef::String), true),
+            (opt(TypeRef::String), TypeRef::String, false),
+            (TypeRef::Enum("E".into()), TypeRef::String, true),
+            (TypeRef::String, TypeRef::Enum("E".into()), false),
+            (list(TypeRef::Int), list(TypeRef::Float), true),
+            (list(TypeRef::Int), list(TypeRef::String), false),
+            (TypeRef::Int, TypeRef::Duration, false),
+        ];
+        for (old_t, new_t, is_compatible) in cases {
+            let old = manifest(&[], &[], &[PropDef::new("p", &old_t, &json!(null))]);
+            let new = manifest(&[], &[], &[PropDef::new("p", &new_t, &json!(null))]);
+            let changes = SchemaDiffer::new(&old, &new).diff();
+            assert_eq!(changes.len(), 1);
+            assert_eq!(
+                changes[0].is_breaking(),
+                !is_compatible,
+                "{old_t} to {new_t}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_range_changes() {
+        let with_range = |min: Value, max: Value| {
+            [PropDef::with_range(
+                "p",
+                &TypeRef::Int,
+                &json!(5),
+                &min,
+                &max,
+            )]
+        };
+        let old = manifest(&[], &[], &with_range(json!(0), json!(10)));
+
+        let new = manifest(&[], &[], &with_range(json!(-1), json!(11)));
+        let changes = SchemaDiffer::new(&old, &new).diff();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|c| !c.is_breaking()));
+
+        let new = manifest(&[], &[], &with_range(json!(1), json!(10)));
+        assert_eq!(diff(&old, &new), vec![breaking("features/my-feature/p")]);
+
+        let new = manifest(&[], &[], &with_range(json!(null), json!(null)));
+        let changes = SchemaDiffer::new(&old, &new).diff();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|c| !c.is_breaking()));
+
+        let new = manifest(&[], &[], &with_range(json!(0), json!(10)));
+        let old = manifest(&[], &[], &with_range(json!(null), json!(10)));
+        assert_eq!(diff(&old, &new), vec![breaking("features/my-feature/p")]);
+    }
+
+    #[test]
+    fn test_object_and_enum_changes() {
+        let obj_t = TypeRef::Object("MyObject".into());
+        let enum_t = TypeRef::Enum("MyEnum".into());
+        let props = [
+            PropDef::new("obj", &obj_t, &json!({})),
+            PropDef::new("list", &TypeRef::List(Box::new(enum_t.clone())), &json!([])),
+        ];
+
+        let old = manifest(
+            &[ObjectDef::new(
+                "MyObject",
+                &[
+                    PropDef::new("a", &TypeRef::String, &json!("")),
+                    PropDef::new("b", &enum_t, &json!("x")),
+                ],
+            )],
+            &[EnumDef::new("MyEnum", &["x", "y"])],
+            &props,
+        );
+        let new = manifest(
+            &[ObjectDef::new(
+                "MyObject",
+                &[
+                    PropDef::new("b", &enum_t, &json!("x")),
+                    PropDef::new("c", &TypeRef::String, &json!("")),
+                ],
+            )],
+            &[EnumDef::new("MyEnum", &["x", "z"])],
+            &props,
+        );
+
+        let mut changes = diff(&old, &new);
+        changes.sort();
+        assert_eq!(
+            changes,
+            vec![
+                breaking("enums/MyEnum/y"),
+                compatible("enums/MyEnum/z"),
+                breaking("objects/MyObject/a"),
+                compatible("objects/MyObject/c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coenrollment_changes() {
+        let old = manifest(&[], &[], &[]);
+        let mut new = get_feature_manifest(
+            vec![],
+            vec![],
+            vec![FeatureDef::new("my-feature", "Documentation", vec![], true)],
+            HashMap::new(),
+        );
+        assert_eq!(diff(&old, &new), vec![compatible("features/my-feature")]);
+        assert_eq!(diff(&new, &old), vec![breaking("features/my-feature")]);
+
+        new.add_feature(feature(&[]));
+        assert_eq!(diff(&old, &new), vec![]);
+    }
+}
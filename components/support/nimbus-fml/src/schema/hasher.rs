@@ -98,6 +98,16 @@ impl SchemaHash for PropDef {
         self.name.hash(state);
         self.typ.hash(state);
         self.string_alias.hash(state);
+        // Only hash the constraints if they're present, so the hash of properties
+        // without them is unchanged.
+        if let Some(min) = &self.min {
+            "min".hash(state);
+            min.hash(state);
+        }
+        if let Some(max) = &self.max {
+            "max".hash(state);
+            max.hash(state);
+        }
     }
 }
 
@@ -139,7 +149,7 @@ impl std::hash::Hasher for Sha256Hasher {
 mod unit_tests {
 
     use crate::error::Result;
-    use serde_json::json;
+    use serde_json::{json, Value};
 
     use super::*;
 
@@ -251,6 +261,58 @@ mod unit_tests {
         };
         assert_ne!(hasher.hash(&f1), hasher.hash(&ne));
 
+        // Sensitive to change in the range of properties
+        let with_range = |min: Value, max: Value| {
+            let prop1 = PropDef::new("p1", &TypeRef::String, &json!("Nope"));
+            let prop2 = PropDef::with_range("p2", &TypeRef::Int, &json!(1), &min, &max);
+            FeatureDef::new("test_feature", "documentation", vec![prop1, prop2], false)
+        };
+        assert_eq!(
+            hasher.hash(&f1),
+            hasher.hash(&with_range(json!(null), json!(null)))
+        );
+        let h_min = hasher.hash(&with_range(json!(0), json!(null)));
+        let h_max = hasher.hash(&with_range(json!(null), json!(0)));
+        assert_ne!(hasher.hash(&f1), h_min);
+        assert_ne!(hasher.hash(&f1), h_max);
+        assert_ne!(h_min, h_max);
+        assert_ne!(h_min, hasher.hash(&with_range(json!(1), json!(null))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_schema_hash_is_unchanged() -> Result<()> {
+        // Changing the hash of an existing feature makes it look like a
+        // breaking change, so pin one that uses each kind of type reference.
+        let objs = Default::default();
+        let enums = EnumDef::into_map(&[EnumDef::new("Color", &["red", "blue"])]);
+
+        let feature_def = FeatureDef::new(
+            "test_feature",
+            "documentation",
+            vec![
+                PropDef::new("c-flag", &TypeRef::Boolean, &json!(false)),
+                PropDef::new(
+                    "a-list",
+                    &TypeRef::List(Box::new(TypeRef::String)),
+                    &json!([]),
+                ),
+                PropDef::new(
+                    "b-map",
+                    &TypeRef::EnumMap(
+                        Box::new(TypeRef::Enum("Color".to_string())),
+                        Box::new(TypeRef::Option(Box::new(TypeRef::Int))),
+                    ),
+                    &json!({}),
+                ),
+            ],
+            false,
+        );
+
+        let hasher = SchemaHasher::new(&enums, &objs);
+        assert_eq!(hasher.hash(&feature_def), 11058966695416428545);
+
         Ok(())
     }
 
@@ -2,10 +2,14 @@
 * License, v. 2.0. If a copy of the MPL was not distributed with this
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+mod differ;
 mod hasher;
+mod recipes;
 mod types;
 mod validator;
 
+pub(crate) use differ::SchemaDiffer;
 pub(crate) use hasher::SchemaHasher;
+pub(crate) use recipes::{ExperimentRecipe, RecipeValidator};
 pub(crate) use types::TypeQuery;
 pub(crate) use validator::SchemaValidator;
@@ -0,0 +1,202 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::fmt::Display;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{error::Result, intermediate_representation::FeatureManifest};
+
+/// The parts of a Nimbus experiment recipe which we need to check its feature configurations.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ExperimentRecipe {
+    pub(crate) slug: String,
+    #[serde(default)]
+    pub(crate) branches: Vec<RecipeBranch>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct RecipeBranch {
+    pub(crate) slug: String,
+    #[serde(default)]
+    pub(crate) feature: Option<RecipeFeatureConfig>,
+    #[serde(default)]
+    pub(crate) features: Option<Vec<RecipeFeatureConfig>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecipeFeatureConfig {
+    pub(crate) feature_id: String,
+    #[serde(default)]
+    pub(crate) value: Value,
+}
+
+impl RecipeBranch {
+    fn feature_configs(&self) -> impl Iterator<Item = &RecipeFeatureConfig> {
+        self.feature.iter().chain(self.features.iter().flatten())
+    }
+}
+
+impl ExperimentRecipe {
+    /// Reads recipes from JSON, in any of the shapes we're likely to be handed:
+    /// a list of recipes, the `{ "data": [ … ] }` envelope of a Remote Settings collection,
+    /// or a single recipe.
+    pub(crate) fn from_json(value: Value) -> Result<Vec<Self>> {
+        Ok(match value {
+            Value::Array(_) => serde_json::from_value(value)?,
+            Value::Object(mut map) if map.contains_key("data") => {
+                serde_json::from_value(map.remove("data").unwrap_or_default())?
+            }
+            _ => vec![serde_json::from_value(value)?],
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RecipeProblem {
+    pub(crate) path: String,
+    pub(crate) message: String,
+}
+
+impl Display for RecipeProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Checks that the feature configurations in experiment recipes, written against the old
+/// manifest, are still valid for the new manifest.
+pub(crate) struct RecipeValidator<'a> {
+    old: &'a FeatureManifest,
+    new: &'a FeatureManifest,
+}
+
+impl<'a> RecipeValidator<'a> {
+    pub(crate) fn new(old: &'a FeatureManifest, new: &'a FeatureManifest) -> Self {
+        Self { old, new }
+    }
+
+    pub(crate) fn validate(&self, recipes: &[ExperimentRecipe]) -> Vec<RecipeProblem> {
+        let mut problems = Vec::new();
+        for recipe in recipes {
+            for branch in &recipe.branches {
+                for config in branch.feature_configs() {
+                    let path = format!("{}/{}/{}", recipe.slug, branch.slug, config.feature_id);
+                    if let Some(message) = self.validate_feature_config(config) {
+                        problems.push(RecipeProblem { path, message });
+                    }
+                }
+            }
+        }
+        problems
+    }
+
+    fn validate_feature_config(&self, config: &RecipeFeatureConfig) -> Option<String> {
+        let id = &config.feature_id;
+        if self.new.find_feature(id).is_none() {
+            // Recipes often configure features from other apps, or other manifests, so we only
+            // complain about features which this manifest used to define.
+            return self
+                .old
+                .find_feature(id)
+                .map(|_| "The feature has been removed from the manifest".to_string());
+        }
+
+        self.new
+            .validate_feature_config(id, config.value.clone())
+            .err()
+            .map(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        fixtures::intermediate_representation::get_feature_manifest,
+        intermediate_representation::{FeatureDef, PropDef, TypeRef},
+    };
+
+    fn manifest(features: &[(&str, TypeRef, Value)]) -> FeatureManifest {
+        let features = features
+            .iter()
+            .map(|(nm, typ, default)| {
+                FeatureDef::new(nm, "", vec![PropDef::new("p", typ, default)], false)
+            })
+            .collect();
+        get_feature_manifest(vec![], vec![], features, HashMap::new())
+    }
+
+    fn recipe(feature_id: &str, value: Value) -> Value {
+        json!({
+            "slug": "my-experiment",
+            "appName": "my-app",
+            "branches": [
+                { "slug": "control", "feature": { "featureId": feature_id, "value": {} } },
+                { "slug": "treatment", "features": [ { "featureId": feature_id, "value": value } ] },
+            ]
+        })
+    }
+
+    #[test]
+    fn test_from_json() -> Result<()> {
+        let single = recipe("my-feature", json!({}));
+        assert_eq!(ExperimentRecipe::from_json(single.clone())?.len(), 1);
+        assert_eq!(
+            ExperimentRecipe::from_json(json!([single.clone(), single.clone()]))?.len(),
+            2
+        );
+        assert_eq!(
+            ExperimentRecipe::from_json(json!({ "data": [single] }))?.len(),
+            1
+        );
+        assert!(ExperimentRecipe::from_json(json!({ "branches": [] })).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_valid_recipes() -> Result<()> {
+        let old = manifest(&[("my-feature", TypeRef::Int, json!(0))]);
+        let new = manifest(&[("my-feature", TypeRef::Float, json!(0.0))]);
+        let recipes = ExperimentRecipe::from_json(json!([
+            recipe("my-feature", json!({ "p": 1 })),
+            recipe("not-my-feature", json!({ "q": "anything" })),
+        ]))?;
+        assert_eq!(RecipeValidator::new(&old, &new).validate(&recipes), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_recipes() -> Result<()> {
+        let old = manifest(&[
+            ("my-feature", TypeRef::Int, json!(0)),
+            ("removed-feature", TypeRef::Int, json!(0)),
+        ]);
+        let new = manifest(&[("my-feature", TypeRef::String, json!(""))]);
+        let recipes = ExperimentRecipe::from_json(json!([
+            recipe("my-feature", json!({ "p": 1 })),
+            recipe("removed-feature", json!({ "p": 1 })),
+        ]))?;
+        let paths: Vec<_> = RecipeValidator::new(&old, &new)
+            .validate(&recipes)
+            .into_iter()
+            .map(|p| p.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "my-experiment/treatment/my-feature",
+                "my-experiment/control/removed-feature",
+                "my-experiment/treatment/removed-feature",
+            ]
+        );
+        Ok(())
+    }
+}
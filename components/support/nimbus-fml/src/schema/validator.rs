@@ -3,7 +3,7 @@
 * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::error::FMLError;
-use crate::intermediate_representation::{FeatureDef, TypeFinder, TypeRef};
+use crate::intermediate_representation::{FeatureDef, PropDef, TypeFinder, TypeRef};
 use crate::{
     error::Result,
     intermediate_representation::{EnumDef, ObjectDef},
@@ -42,6 +42,9 @@ impl<'a> SchemaValidator<'a> {
             // Check the types exist for this property.
             let path = format!("objects/{obj_nm}/{prop_nm}");
             self.validate_type_ref(&path, &prop.typ)?;
+
+            // Check min and max are used with numeric types.
+            self.validate_range(&path, prop)?;
         }
 
         Ok(())
@@ -60,6 +63,9 @@ impl<'a> SchemaValidator<'a> {
             // Check the types exist for this property.
             self.validate_type_ref(&path, prop_t)?;
 
+            // Check min and max are used with numeric types.
+            self.validate_range(&path, prop)?;
+
             // Check pref support for this type.
             if prop.pref_key.is_some() && !prop.typ.supports_prefs() {
                 return Err(FMLError::ValidationError(
@@ -142,6 +148,30 @@ impl<'a> SchemaValidator<'a> {
         Ok(())
     }
 
+    fn validate_range(&self, path: &str, prop: &PropDef) -> Result<()> {
+        if prop.min.is_none() && prop.max.is_none() {
+            return Ok(());
+        }
+        if !prop.typ.supports_range() {
+            return Err(FMLError::ValidationError(
+                path.to_string(),
+                format!(
+                    "min and max can only be used with Int, Float and Duration variables, found: {}",
+                    prop.typ
+                ),
+            ));
+        }
+        if let (Some(min), Some(max)) = (&prop.min, &prop.max) {
+            if min.as_f64() > max.as_f64() {
+                return Err(FMLError::ValidationError(
+                    path.to_string(),
+                    format!("min ({min}) is greater than max ({max})"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
     fn validate_type_ref(&self, path: &str, type_ref: &TypeRef) -> Result<()> {
         match type_ref {
             TypeRef::Enum(name) => {
@@ -263,6 +293,64 @@ mod manifest_schema {
         Ok(())
     }
 
+    #[test]
+    fn validate_range_on_numeric_types() -> Result<()> {
+        let enums = Default::default();
+        let objs = Default::default();
+        let validator = SchemaValidator::new(&enums, &objs);
+        let feature = |prop: PropDef| FeatureDef::new("some_def", "test doc", vec![prop], false);
+
+        let fm = feature(PropDef::with_range(
+            "prop_name",
+            &TypeRef::Int,
+            &json!(1),
+            &json!(0),
+            &json!(10),
+        ));
+        validator.validate_feature_def(&fm)?;
+
+        let fm = feature(PropDef::with_range(
+            "prop_name",
+            &TypeRef::List(Box::new(TypeRef::Float)),
+            &json!([0.5]),
+            &json!(0.0),
+            &json!(null),
+        ));
+        validator.validate_feature_def(&fm)?;
+
+        let fm = feature(PropDef::with_range(
+            "prop_name",
+            &TypeRef::Option(Box::new(TypeRef::Duration)),
+            &json!(null),
+            &json!(null),
+            &json!(60000),
+        ));
+        validator.validate_feature_def(&fm)?;
+
+        let fm = feature(PropDef::with_range(
+            "prop_name",
+            &TypeRef::String,
+            &json!("a string"),
+            &json!(0),
+            &json!(null),
+        ));
+        validator
+            .validate_feature_def(&fm)
+            .expect_err("Should fail since min cannot be used with a String");
+
+        let fm = feature(PropDef::with_range(
+            "prop_name",
+            &TypeRef::Int,
+            &json!(1),
+            &json!(10),
+            &json!(0),
+        ));
+        validator
+            .validate_feature_def(&fm)
+            .expect_err("Should fail since min is greater than max");
+        Ok(())
+    }
+
     #[test]
     fn validate_list_with_enum_with_no_def() -> Result<()> {
         let enums = Default::default();
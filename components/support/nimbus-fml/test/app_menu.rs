@@ -0,0 +1,122 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+mod app_menu_release;
+
+use app_menu_release::*;
+use serde_json::json;
+
+struct MockNimbus(Option<String>);
+
+impl FeaturesInterface for MockNimbus {
+    fn get_feature_config_variables(&self, _feature_id: &str) -> Option<String> {
+        self.0.clone()
+    }
+}
+
+fn main() {
+    // Exercise a map of booleans
+    let feature = AppMenu::default();
+    assert_eq!(feature.item_enabled[&MenuItemId::StartGame], true);
+    assert_eq!(feature.item_enabled[&MenuItemId::ResumeGame], false);
+    assert_eq!(feature.item_enabled[&MenuItemId::Settings], true);
+    assert_eq!(feature.item_enabled[&MenuItemId::Community], false);
+
+    // Exercise a list of enums.
+    assert_eq!(feature.item_ordering[0], MenuItemId::ResumeGame);
+    assert_eq!(MenuItemId::ALL.len(), 4);
+    assert_eq!(MenuItemId::StartGame.as_str(), "start-game");
+
+    // Excercise a map of Objects.
+    assert_eq!(feature.items[&MenuItemId::StartGame].label, "Start Game");
+    assert_eq!(feature.items[&MenuItemId::ResumeGame].label, "Resume Game");
+    assert_eq!(feature.items[&MenuItemId::Settings].label, "Settings");
+    assert_eq!(feature.items[&MenuItemId::Community].label, "Community");
+
+    // Exercise a map of map of objects.
+    let child = &feature.profile_items[&PlayerProfile::Child];
+    assert_eq!(child[&MenuItemId::StartGame].label, "start child-friendly game");
+    assert_eq!(child[&MenuItemId::ResumeGame].label, "resume child-friendly game");
+    assert_eq!(child[&MenuItemId::Settings].label, "child-friendly tweaks");
+
+    let adult = &feature.profile_items[&PlayerProfile::Adult];
+    assert_eq!(adult[&MenuItemId::StartGame].label, "START");
+    assert_eq!(adult[&MenuItemId::ResumeGame].label, "RESUME");
+    assert_eq!(adult[&MenuItemId::Settings].label, "SETTINGS");
+
+    // The defaults round trip through JSON.
+    assert_eq!(AppMenu::from_json(feature.to_json()), feature);
+    assert_eq!(AppMenu::from_json(json!({})), feature);
+    assert_eq!(MyNimbus::get_default_json()["app-menu"], feature.to_json());
+
+    // Now let's merge it with JSON we might have got from the SDK.
+    let api = MockNimbus(Some(
+        json!({
+            "items": {
+                "start-game": {
+                    "label": "Start Nimbus",
+                },
+                "resume-game": {
+                    "label": "Resume Nimbus",
+                },
+                "settings": {
+                    "label": "Nimbus Settings",
+                },
+                "community": {
+                    "label": "Share Nimbus",
+                },
+            },
+            "profile-items": {
+                "adult": {
+                    "start-game": {
+                        "label": "START NIMBUS",
+                    },
+                    "resume-game": {
+                        "label": "RESUME NIMBUS",
+                    },
+                    "settings": {
+                        "label": "NIMBUS settings",
+                    },
+                },
+            },
+        })
+        .to_string(),
+    ));
+
+    let feature1: AppMenu = MyNimbus::get_feature(&api);
+    assert_eq!(feature1.items[&MenuItemId::StartGame].label, "Start Nimbus");
+    assert_eq!(feature1.items[&MenuItemId::ResumeGame].label, "Resume Nimbus");
+    assert_eq!(feature1.items[&MenuItemId::Settings].label, "Nimbus Settings");
+    assert_eq!(feature1.items[&MenuItemId::Community].label, "Share Nimbus");
+
+    assert_eq!(feature1.items[&MenuItemId::StartGame].deeplink, "deeplink://start");
+    assert_eq!(feature1.items[&MenuItemId::ResumeGame].deeplink, "deeplink://start?continue=true");
+    assert_eq!(feature1.items[&MenuItemId::Settings].deeplink, "deeplink://settings");
+    assert_eq!(feature1.items[&MenuItemId::Community].deeplink, "deeplink://community");
+
+    // Check that we're merging the maps properly.
+    let child = &feature1.profile_items[&PlayerProfile::Child];
+    assert_eq!(child[&MenuItemId::StartGame].label, "start child-friendly game");
+    assert_eq!(child[&MenuItemId::ResumeGame].label, "resume child-friendly game");
+    assert_eq!(child[&MenuItemId::Settings].label, "child-friendly tweaks");
+
+    let adult = &feature1.profile_items[&PlayerProfile::Adult];
+    assert_eq!(adult[&MenuItemId::StartGame].label, "START NIMBUS");
+    assert_eq!(adult[&MenuItemId::ResumeGame].label, "RESUME NIMBUS");
+    assert_eq!(adult[&MenuItemId::Settings].label, "NIMBUS settings");
+
+    // Properties which don't fit the schema are ignored, but the rest are still applied.
+    let feature2 = AppMenu::from_json(json!({
+        "item-ordering": ["not-a-menu-item"],
+        "item-enabled": {
+            "community": true,
+        },
+    }));
+    assert_eq!(feature2.item_ordering, feature.item_ordering);
+    assert_eq!(feature2.item_enabled[&MenuItemId::Community], true);
+
+    // No configuration from the SDK gives the defaults.
+    let feature3: AppMenu = MyNimbus::get_feature(&MockNimbus(None));
+    assert_eq!(feature3, feature);
+}
@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+import { FeaturesInterface, JSONObject, MenuItemId, MyNimbus, PlayerProfile } from "./app_menu_release";
+
+function assert(condition: boolean, message?: string): void {
+    if (!condition) {
+        throw new Error(message ?? "Assertion failed");
+    }
+}
+
+class MockNimbus implements FeaturesInterface {
+    constructor(private readonly configs: Record<string, JSONObject>) {}
+
+    getFeatureConfig(featureId: string): JSONObject | undefined {
+        return this.configs[featureId];
+    }
+
+    recordExposureEvent(_featureId: string): void {}
+}
+
+// Exercise a map of booleans
+const feature = MyNimbus.features.appMenu.value();
+assert(feature.itemEnabled[MenuItemId.START_GAME] === true);
+assert(feature.itemEnabled[MenuItemId.RESUME_GAME] === false);
+assert(feature.itemEnabled[MenuItemId.SETTINGS] === true);
+assert(feature.itemEnabled[MenuItemId.COMMUNITY] === false);
+
+// Exercise a list of enums.
+assert(feature.itemOrdering[0] === MenuItemId.RESUME_GAME);
+
+// Excercise a map of Objects.
+assert(feature.items[MenuItemId.START_GAME]?.label === "Start Game");
+assert(feature.items[MenuItemId.RESUME_GAME]?.label === "Resume Game");
+assert(feature.items[MenuItemId.SETTINGS]?.label === "Settings");
+assert(feature.items[MenuItemId.COMMUNITY]?.label === "Community");
+
+// Exercise a map of map of objects.
+assert(feature.profileItems[PlayerProfile.CHILD]?.[MenuItemId.START_GAME]?.label === "start child-friendly game");
+assert(feature.profileItems[PlayerProfile.CHILD]?.[MenuItemId.RESUME_GAME]?.label === "resume child-friendly game");
+assert(feature.profileItems[PlayerProfile.CHILD]?.[MenuItemId.SETTINGS]?.label === "child-friendly tweaks");
+
+assert(feature.profileItems[PlayerProfile.ADULT]?.[MenuItemId.START_GAME]?.label === "START");
+assert(feature.profileItems[PlayerProfile.ADULT]?.[MenuItemId.RESUME_GAME]?.label === "RESUME");
+assert(feature.profileItems[PlayerProfile.ADULT]?.[MenuItemId.SETTINGS]?.label === "SETTINGS");
+
+// Now let's merge it with JSON we might have got from Rust.
+MyNimbus.api = new MockNimbus({
+    "app-menu": {
+        "items": {
+            "start-game": {
+                "label": "Start Nimbus",
+            },
+            "resume-game": {
+                "label": "Resume Nimbus",
+            },
+            "settings": {
+                "label": "Nimbus Settings",
+            },
+            "community": {
+                "label": "Share Nimbus",
+            },
+            "not-a-menu-item": {
+                "label": "Ignored",
+            },
+        },
+        "profile-items": {
+            "adult": {
+                "start-game": {
+                    "label": "START NIMBUS",
+                },
+                "resume-game": {
+                    "label": "RESUME NIMBUS",
+                },
+                "settings": {
+                    "label": "NIMBUS settings",
+                },
+            },
+        },
+    },
+});
+
+MyNimbus.invalidateCachedValues();
+const feature1 = MyNimbus.features.appMenu.value();
+assert(feature1.items[MenuItemId.START_GAME]?.label === "Start Nimbus");
+assert(feature1.items[MenuItemId.RESUME_GAME]?.label === "Resume Nimbus");
+assert(feature1.items[MenuItemId.SETTINGS]?.label === "Nimbus Settings");
+assert(feature1.items[MenuItemId.COMMUNITY]?.label === "Share Nimbus");
+assert(Object.keys(feature1.items).length === 4);
+
+assert(feature1.items[MenuItemId.START_GAME]?.deeplink === "deeplink://start");
+assert(feature1.items[MenuItemId.RESUME_GAME]?.deeplink === "deeplink://start?continue=true");
+assert(feature1.items[MenuItemId.SETTINGS]?.deeplink === "deeplink://settings");
+assert(feature1.items[MenuItemId.COMMUNITY]?.deeplink === "deeplink://community");
+
+// Check that we're merging the maps properly.
+assert(feature1.profileItems[PlayerProfile.CHILD]?.[MenuItemId.START_GAME]?.label === "start child-friendly game");
+assert(feature1.profileItems[PlayerProfile.CHILD]?.[MenuItemId.RESUME_GAME]?.label === "resume child-friendly game");
+assert(feature1.profileItems[PlayerProfile.CHILD]?.[MenuItemId.SETTINGS]?.label === "child-friendly tweaks");
+
+assert(feature1.profileItems[PlayerProfile.ADULT]?.[MenuItemId.START_GAME]?.label === "START NIMBUS");
+assert(feature1.profileItems[PlayerProfile.ADULT]?.[MenuItemId.RESUME_GAME]?.label === "RESUME NIMBUS");
+assert(feature1.profileItems[PlayerProfile.ADULT]?.[MenuItemId.SETTINGS]?.label === "NIMBUS settings");
@@ -0,0 +1,58 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+import android.net.Uri
+import com.example.nimbus.TypedValues
+import org.mozilla.experiments.nimbus.MockNimbus
+import kotlin.time.Duration.Companion.milliseconds
+
+var injected: MockNimbus? = null
+TypedValues.initialize { injected }
+
+// The defaults from the manifest.
+val feature = TypedValues.features.typedValues.value()
+assert(feature.ratio == 0.5)
+assert(feature.homepage == Uri.parse("https://www.mozilla.org/"))
+assert(feature.background == 0xFF336699.toInt())
+assert(feature.overlay == 0x80000000.toInt())
+assert(feature.timeout == 1500.milliseconds)
+assert(feature.backoff == listOf(100.milliseconds, 1000.milliseconds, 10000.milliseconds))
+assert(feature.weights == mapOf("a" to 1.0, "b" to 2.5))
+assert(feature.link == null)
+assert(feature.settings.scale == 1.0)
+assert(feature.settings.accent == 0xFFFF0000.toInt())
+assert(feature.settings.delay == 250.milliseconds)
+
+// Values from Nimbus. Integers are acceptable as Floats.
+injected = MockNimbus("typed-values" to """{
+    "ratio": 1,
+    "homepage": "https://example.com/",
+    "background": "#000000",
+    "overlay": "not a color",
+    "timeout": 30000,
+    "link": "https://example.com/link",
+    "settings": {
+        "scale": 2.5,
+        "delay": 1000
+    }
+}""")
+TypedValues.invalidateCachedValues()
+
+val feature1 = TypedValues.features.typedValues.value()
+assert(feature1.ratio == 1.0)
+assert(feature1.homepage == Uri.parse("https://example.com/"))
+assert(feature1.background == 0xFF000000.toInt())
+assert(feature1.overlay == 0x80000000.toInt())
+assert(feature1.timeout == 30000.milliseconds)
+assert(feature1.link == Uri.parse("https://example.com/link"))
+assert(feature1.settings.scale == 2.5)
+assert(feature1.settings.accent == 0xFFFF0000.toInt())
+assert(feature1.settings.delay == 1000.milliseconds)
+
+// The values round trip through JSON.
+val json = feature1.toJSONObject()
+assert(json.getString("homepage") == "https://example.com/")
+assert(json.getString("background") == "#FF000000")
+assert(json.getLong("timeout") == 30000L)
+assert(json.getJSONObject("settings").getDouble("scale") == 2.5)
@@ -0,0 +1,56 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+mod typed_values_release;
+
+use serde_json::json;
+use std::time::Duration;
+use typed_values_release::*;
+
+fn main() {
+    // The defaults from the manifest.
+    let feature = TypedValues::default();
+    assert_eq!(feature.ratio, 0.5);
+    assert_eq!(feature.homepage, "https://www.mozilla.org/");
+    assert_eq!(feature.background, "#336699");
+    assert_eq!(*feature.timeout, Duration::from_millis(1500));
+    assert_eq!(
+        feature.backoff,
+        vec![
+            FMLDuration::from_millis(100),
+            FMLDuration::from_millis(1000),
+            FMLDuration::from_millis(10000),
+        ]
+    );
+    assert_eq!(feature.weights["a"], 1.0);
+    assert_eq!(feature.weights["b"], 2.5);
+    assert_eq!(feature.link, None);
+    assert_eq!(feature.settings.scale, 1.0);
+    assert_eq!(feature.settings.delay, FMLDuration::from_millis(250));
+
+    // Values from Nimbus. Integers are acceptable as Floats, but Durations must be whole,
+    // non-negative numbers of milliseconds.
+    let feature1 = TypedValues::from_json(json!({
+        "ratio": 1,
+        "timeout": -5,
+        "backoff": [1.5],
+        "link": "https://example.com/link",
+        "settings": {
+            "scale": 2.5,
+            "delay": 1000,
+        },
+    }));
+    assert_eq!(feature1.ratio, 1.0);
+    assert_eq!(feature1.timeout, feature.timeout);
+    assert_eq!(feature1.backoff, feature.backoff);
+    assert_eq!(feature1.link.as_deref(), Some("https://example.com/link"));
+    assert_eq!(feature1.settings.scale, 2.5);
+    assert_eq!(Duration::from(feature1.settings.delay), Duration::from_secs(1));
+
+    // The values round trip through JSON.
+    let json = feature1.to_json();
+    assert_eq!(json["timeout"], 1500);
+    assert_eq!(json["settings"]["delay"], 1000);
+    assert_eq!(TypedValues::from_json(json), feature1);
+}
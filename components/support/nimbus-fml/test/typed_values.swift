@@ -0,0 +1,58 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+import FeatureManifest
+import Foundation
+
+let nimbus = TypedValues.shared
+
+// The defaults from the manifest.
+let feature = nimbus.features.typedValues.value()
+assert(feature.ratio == 0.5)
+assert(feature.homepage == URL(string: "https://www.mozilla.org/"))
+assert(feature.background.hexString == "#FF336699")
+assert(feature.overlay.hexString == "#80000000")
+assert(feature.timeout == 1.5)
+assert(feature.backoff == [0.1, 1.0, 10.0])
+assert(feature.weights == ["a": 1.0, "b": 2.5])
+assert(feature.link == nil)
+assert(feature.settings.scale == 1.0)
+assert(feature.settings.accent.hexString == "#FFFF0000")
+assert(feature.settings.delay == 0.25)
+
+// Values from Nimbus. Integers are acceptable as Floats.
+let api = HardcodedNimbusFeatures(with: ["typed-values": """
+{
+    "ratio": 1,
+    "homepage": "https://example.com/",
+    "background": "#000000",
+    "overlay": "not a color",
+    "timeout": 30000,
+    "link": "https://example.com/link",
+    "settings": {
+        "scale": 2.5,
+        "delay": 1000
+    }
+}
+"""])
+nimbus.api = api
+nimbus.invalidateCachedValues()
+
+let feature1 = nimbus.features.typedValues.value()
+assert(feature1.ratio == 1.0)
+assert(feature1.homepage == URL(string: "https://example.com/"))
+assert(feature1.background.hexString == "#FF000000")
+assert(feature1.overlay.hexString == "#80000000")
+assert(feature1.timeout == 30.0)
+assert(feature1.link == URL(string: "https://example.com/link"))
+assert(feature1.settings.scale == 2.5)
+assert(feature1.settings.accent.hexString == "#FFFF0000")
+assert(feature1.settings.delay == 1.0)
+
+// The values are encoded back to JSON as they appear in the manifest.
+let data = try! JSONEncoder().encode(feature1)
+let json = try! JSONSerialization.jsonObject(with: data) as! [String: Any]
+assert(json["homepage"] as? String == "https://example.com/")
+assert(json["background"] as? String == "#FF000000")
+assert(json["timeout"] as? Int == 30000)
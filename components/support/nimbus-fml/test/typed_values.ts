@@ -0,0 +1,63 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+import { FeaturesInterface, JSONObject, MyNimbus } from "./typed_values_release";
+
+function assert(condition: boolean, message?: string): void {
+    if (!condition) {
+        throw new Error(message ?? "Assertion failed");
+    }
+}
+
+class MockNimbus implements FeaturesInterface {
+    constructor(private readonly configs: Record<string, JSONObject>) {}
+
+    getFeatureConfig(featureId: string): JSONObject | undefined {
+        return this.configs[featureId];
+    }
+
+    recordExposureEvent(_featureId: string): void {}
+}
+
+let injected: MockNimbus | undefined = undefined;
+MyNimbus.initialize(() => injected);
+
+// The defaults from the manifest.
+const feature = MyNimbus.features.typedValues.value();
+assert(feature.ratio === 0.5);
+assert(feature.homepage.href === "https://www.mozilla.org/");
+assert(feature.background === "#336699");
+assert(feature.timeout === 1500);
+assert(JSON.stringify(feature.backoff) === JSON.stringify([100, 1000, 10000]));
+assert(feature.weights["b"] === 2.5);
+assert(feature.link === null);
+assert(feature.settings.scale === 1.0);
+assert(feature.settings.delay === 250);
+
+// Values from Nimbus. Integers are acceptable as Floats, but not as Durations.
+injected = new MockNimbus({
+    "typed-values": {
+        "ratio": 1,
+        "homepage": "not a url",
+        "timeout": 2.5,
+        "link": "https://example.com/link",
+        "settings": {
+            "scale": 2.5,
+        },
+    },
+});
+MyNimbus.invalidateCachedValues();
+
+const feature1 = MyNimbus.features.typedValues.value();
+assert(feature1.ratio === 1);
+assert(feature1.homepage.href === "https://www.mozilla.org/");
+assert(feature1.timeout === 1500);
+assert(feature1.link?.href === "https://example.com/link");
+assert(feature1.settings.scale === 2.5);
+
+// The values are turned back into JSON as they appear in the manifest.
+const json = feature1.toJSON();
+assert(json["homepage"] === "https://www.mozilla.org/");
+assert(json["link"] === "https://example.com/link");
+assert(json["timeout"] === 1500);
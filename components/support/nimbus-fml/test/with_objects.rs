@@ -0,0 +1,67 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+mod with_objects_release;
+
+use serde_json::json;
+use with_objects_release::*;
+
+fn main() {
+    let feature = WithObjectsFeature::default();
+
+    // Show the property level defaults.
+    assert_eq!(feature.an_object.a_string, "yes");
+    assert_eq!(feature.an_object_with_new_defaults.a_string, "YES: overridden from the CONSTRUCTOR!");
+    assert_eq!(feature.an_object_with_feature_defaults.a_string, "yes");
+
+    assert_eq!(feature.an_object.nested.property_source, "example-object-property-via-constructor");
+    assert_eq!(feature.an_object_with_new_defaults.nested.property_source, "an-object-with-new-defaults-constructor");
+    assert_eq!(feature.an_object_with_feature_defaults.nested.property_source, "example-object-property-via-constructor");
+
+    // The object level defaults.
+    assert_eq!(Nested::default().property_source, "nested-object-property");
+
+    // Test if we can override the defaults with JSON coming from Nimbus.
+    let feature1 = WithObjectsFeature::from_json_str(
+        &json!({
+            "an-object-with-feature-defaults": {
+                "a-string": "Sounds good",
+                "nested": {
+                    "property-source": "from-json",
+                },
+            },
+        })
+        .to_string(),
+    );
+
+    // Now test the selectively overidden properties of the feature.
+    assert_eq!(feature1.an_object.a_string, "yes");
+    assert_eq!(feature1.an_object_with_feature_defaults.a_string, "Sounds good");
+    assert_eq!(feature1.an_object_with_feature_defaults.a_number, 2);
+
+    assert_eq!(feature1.an_object.nested.property_source, "example-object-property-via-constructor");
+    assert_eq!(feature1.an_object_with_new_defaults.nested.property_source, "an-object-with-new-defaults-constructor");
+    assert_eq!(feature1.an_object_with_feature_defaults.nested.property_source, "from-json");
+
+    // Values of the wrong type are ignored.
+    let feature2 = WithObjectsFeature::from_json(json!({
+        "an-object": {
+            "a-number": "forty-two",
+        },
+        "an-object-with-new-defaults": {
+            "a-number": 42,
+        },
+    }));
+    assert_eq!(feature2.an_object.a_number, 1);
+    assert_eq!(feature2.an_object_with_new_defaults.a_number, 42);
+
+    // Invalid JSON gives the defaults.
+    assert_eq!(WithObjectsFeature::from_json_str("not json"), feature);
+
+    // The features round trip through JSON.
+    let json = feature1.to_json();
+    assert_eq!(json["an-object-with-feature-defaults"]["a-string"], "Sounds good");
+    assert_eq!(json["an-object-with-feature-defaults"]["nested"]["property-source"], "from-json");
+    assert_eq!(WithObjectsFeature::from_json(json), feature1);
+}
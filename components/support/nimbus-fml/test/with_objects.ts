@@ -0,0 +1,98 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+* License, v. 2.0. If a copy of the MPL was not distributed with this
+* file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+import { FeaturesInterface, JSONObject, MyNimbus } from "./with_objects_release";
+
+function assert(condition: boolean, message?: string): void {
+    if (!condition) {
+        throw new Error(message ?? "Assertion failed");
+    }
+}
+
+class MockNimbus implements FeaturesInterface {
+    readonly exposures: Array<string> = [];
+
+    constructor(private readonly configs: Record<string, JSONObject>) {}
+
+    getFeatureConfig(featureId: string): JSONObject | undefined {
+        return this.configs[featureId];
+    }
+
+    recordExposureEvent(featureId: string): void {
+        this.exposures.push(featureId);
+    }
+}
+
+// Get the feature from the MyNimbus.features.
+// The api isn't ready yet.
+let injected: MockNimbus | undefined = undefined;
+MyNimbus.initialize(() => injected);
+const feature = MyNimbus.features.withObjectsFeature.value();
+
+// Show the property level defaults.
+assert(feature.anObject.aString === "yes");
+assert(feature.anObjectWithNewDefaults.aString === "YES: overridden from the CONSTRUCTOR!");
+assert(feature.anObjectWithFeatureDefaults.aString === "yes");
+
+// It's the same class.
+assert(feature.anObject.constructor === feature.anObjectWithNewDefaults.constructor);
+assert(feature.anObject.constructor === feature.anObjectWithFeatureDefaults.constructor);
+
+assert(feature.anObject.nested.propertySource === "example-object-property-via-constructor");
+assert(feature.anObjectWithNewDefaults.nested.propertySource === "an-object-with-new-defaults-constructor");
+assert(feature.anObjectWithFeatureDefaults.nested.propertySource === "example-object-property-via-constructor");
+
+// Test if we can override the defaults with JSON coming from Nimbus.
+const api = new MockNimbus({
+    "with-objects-feature": {
+        "an-object-with-feature-defaults": {
+            "a-string": "Sounds good",
+            "nested": {
+                "property-source": "from-json",
+            },
+        },
+    },
+});
+injected = api;
+MyNimbus.invalidateCachedValues();
+
+// Now test the selectively overidden properties of the feature.
+const feature1 = MyNimbus.features.withObjectsFeature.value();
+
+assert(feature1.anObject.aString === "yes");
+assert(feature1.anObjectWithFeatureDefaults.aString === "Sounds good");
+
+assert(feature1.anObject.nested.propertySource === "example-object-property-via-constructor");
+assert(feature1.anObjectWithNewDefaults.nested.propertySource === "an-object-with-new-defaults-constructor");
+assert(feature1.anObjectWithFeatureDefaults.nested.propertySource === "from-json");
+
+// Values of the wrong type are ignored.
+injected = new MockNimbus({
+    "with-objects-feature": {
+        "an-object": {
+            "a-string": 42,
+            "a-number": "forty-two",
+        },
+    },
+});
+MyNimbus.invalidateCachedValues();
+const feature2 = MyNimbus.features.withObjectsFeature.value();
+assert(feature2.anObject.aString === "yes");
+assert(feature2.anObject.aNumber === 1);
+
+// Record the exposure and test it.
+injected = api;
+MyNimbus.features.withObjectsFeature.recordExposure();
+assert(api.exposures.includes("with-objects-feature"));
+
+// Just to make sure, the `feature` object that we used earlier is still giving the same values, taken
+// from the property defaults.
+assert(feature.anObject.aString === "yes");
+assert(feature.anObjectWithFeatureDefaults.aString === "yes");
+
+// The features can be turned back into JSON.
+const json = feature1.toJSON();
+const anObject = json["an-object-with-feature-defaults"] as JSONObject;
+assert(anObject["a-string"] === "Sounds good");
+assert((anObject["nested"] as JSONObject)["property-source"] === "from-json");
@@ -168,8 +168,8 @@ mod tests {
     use super::super::request::{InfoCollections, InfoConfiguration};
     use super::super::CollectionKeys;
     use super::*;
-    use crate::bso::{IncomingBso, OutgoingBso};
-    use crate::engine::CollectionRequest;
+    use crate::bso::IncomingBso;
+    use crate::engine::{CollectionRequest, OutgoingRecords};
     use crate::record_types::{MetaGlobalEngine, MetaGlobalRecord};
     use crate::{telemetry, CollectionName};
     use anyhow::Result;
@@ -238,11 +238,11 @@ mod tests {
             unreachable!("these tests shouldn't call these");
         }
 
-        fn apply(
+        fn apply_chunked(
             &self,
             _timestamp: ServerTimestamp,
             _telem: &mut telemetry::Engine,
-        ) -> Result<Vec<OutgoingBso>> {
+        ) -> Result<OutgoingRecords<'_>> {
             unreachable!("these tests shouldn't call these");
         }
 
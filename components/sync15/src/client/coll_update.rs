@@ -7,9 +7,10 @@ use super::{
     CollState, Sync15ClientResponse, Sync15StorageClient,
 };
 use crate::bso::{IncomingBso, OutgoingBso, OutgoingEncryptedBso};
-use crate::engine::CollectionRequest;
+use crate::engine::{CollectionRequest, OutgoingRecords};
 use crate::error::{self, Error, Result};
 use crate::{CollectionName, KeyBundle, ServerTimestamp};
+use interrupt_support::Interruptee;
 
 fn encrypt_outgoing(o: Vec<OutgoingBso>, key: &KeyBundle) -> Result<Vec<OutgoingEncryptedBso>> {
     o.into_iter()
@@ -47,8 +48,9 @@ pub struct CollectionUpdate<'a> {
     state: &'a CollState,
     collection: CollectionName,
     xius: ServerTimestamp,
-    to_update: Vec<OutgoingEncryptedBso>,
+    to_update: OutgoingRecords<'a>,
     fully_atomic: bool,
+    interruptee: Option<&'a dyn Interruptee>,
 }
 
 impl<'a> CollectionUpdate<'a> {
@@ -57,7 +59,7 @@ impl<'a> CollectionUpdate<'a> {
         state: &'a CollState,
         collection: CollectionName,
         xius: ServerTimestamp,
-        records: Vec<OutgoingEncryptedBso>,
+        records: OutgoingRecords<'a>,
         fully_atomic: bool,
     ) -> CollectionUpdate<'a> {
         CollectionUpdate {
@@ -67,6 +69,7 @@ impl<'a> CollectionUpdate<'a> {
             xius,
             to_update: records,
             fully_atomic,
+            interruptee: None,
         }
     }
 
@@ -77,17 +80,40 @@ impl<'a> CollectionUpdate<'a> {
         changeset: Vec<OutgoingBso>,
         fully_atomic: bool,
     ) -> Result<CollectionUpdate<'a>> {
-        let to_update = encrypt_outgoing(changeset, &state.key)?;
-        Ok(CollectionUpdate::new(
+        Ok(CollectionUpdate::new_from_records(
             client,
             state,
             collection,
-            state.last_modified,
-            to_update,
+            OutgoingRecords::from_vec(changeset),
             fully_atomic,
         ))
     }
 
+    /// Creates an update which reads the records a chunk at a time as they're uploaded.
+    pub fn new_from_records(
+        client: &'a Sync15StorageClient,
+        state: &'a CollState,
+        collection: CollectionName,
+        records: OutgoingRecords<'a>,
+        fully_atomic: bool,
+    ) -> CollectionUpdate<'a> {
+        CollectionUpdate::new(
+            client,
+            state,
+            collection,
+            state.last_modified,
+            records,
+            fully_atomic,
+        )
+    }
+
+    /// Checks `interruptee` before queuing each chunk of outgoing records, so the upload
+    /// can be stopped before its batch is committed.
+    pub fn interruptible(mut self, interruptee: &'a dyn Interruptee) -> Self {
+        self.interruptee = Some(interruptee);
+        self
+    }
+
     /// Returns a list of the IDs that failed if allowed_dropped_records is true, otherwise
     /// returns an empty vec.
     pub fn upload(self) -> error::Result<UploadInfo> {
@@ -99,12 +125,23 @@ impl<'a> CollectionUpdate<'a> {
             NormalResponseHandler::new(!self.fully_atomic),
         )?;
 
-        for record in self.to_update.into_iter() {
-            let enqueued = q.enqueue(&record)?;
-            if !enqueued && self.fully_atomic {
-                return Err(Error::RecordTooLargeError);
+        // Each chunk is encrypted and queued before we ask for the next, so we only hold
+        // one chunk, and whatever the queue hasn't posted yet, in memory.
+        let mut num_records = 0;
+        for chunk in self.to_update {
+            if let Some(interruptee) = self.interruptee {
+                interruptee.err_if_interrupted()?;
+            }
+            let chunk = encrypt_outgoing(chunk?, &self.state.key)?;
+            num_records += chunk.len();
+            for record in chunk.iter() {
+                let enqueued = q.enqueue(record)?;
+                if !enqueued && self.fully_atomic {
+                    return Err(Error::RecordTooLargeError);
+                }
             }
         }
+        log::info!("Queued {} outgoing changes", num_records);
 
         q.flush(true)?;
         let mut info = q.completed_upload_info();
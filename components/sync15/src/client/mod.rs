@@ -16,6 +16,7 @@
 mod coll_state;
 mod coll_update;
 mod collection_keys;
+mod progress;
 mod request;
 mod state;
 mod status;
@@ -28,8 +29,11 @@ mod util;
 pub(crate) use coll_state::{CollState, LocalCollStateMachine};
 pub(crate) use coll_update::{fetch_incoming, CollectionUpdate};
 pub(crate) use collection_keys::CollectionKeys;
+pub(crate) use progress::EngineInterruptee;
+pub use progress::SyncProgressObserver;
 pub(crate) use request::InfoConfiguration;
 pub(crate) use state::GlobalState;
+pub use state::PersistedGlobalState;
 pub use status::{ServiceStatus, SyncResult};
 pub use storage_client::{
     SetupStorageClient, Sync15ClientResponse, Sync15StorageClient, Sync15StorageClientInit,
@@ -0,0 +1,101 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::error::Error;
+use interrupt_support::Interruptee;
+
+/// Receives progress updates as `sync_multiple` syncs each engine, and can
+/// cancel engines individually.
+///
+/// Every method has a default implementation, so observers only need to
+/// implement the ones they care about. They're called on the syncing thread,
+/// so they should return quickly.
+pub trait SyncProgressObserver {
+    /// Called before an engine starts syncing.
+    fn engine_started(&self, _engine: &str) {}
+
+    /// Called with the number of records downloaded for an engine. This is
+    /// called again for records the engine asked to download after validating.
+    fn records_downloaded(&self, _engine: &str, _count: usize) {}
+
+    /// Called with the number of incoming records an engine applied.
+    fn records_applied(&self, _engine: &str, _count: usize) {}
+
+    /// Called with the number of records uploaded for an engine.
+    fn records_uploaded(&self, _engine: &str, _count: usize) {}
+
+    /// Called when an engine has synced successfully.
+    fn engine_finished(&self, _engine: &str) {}
+
+    /// Called when an engine failed to sync, including when it was cancelled.
+    fn engine_failed(&self, _engine: &str, _error: &Error) {}
+
+    /// Whether an engine should stop syncing. This is checked between each
+    /// step of an engine's sync, and between chunks of outgoing records. A
+    /// cancelled engine fails with `Error::Interrupted`, and the sync moves on
+    /// to the next engine.
+    fn is_engine_cancelled(&self, _engine: &str) -> bool {
+        false
+    }
+}
+
+/// Interrupts a single engine's sync if either the whole sync was
+/// interrupted, or the observer cancelled the engine.
+pub(crate) struct EngineInterruptee<'a> {
+    pub sync: &'a dyn Interruptee,
+    pub progress: Option<&'a dyn SyncProgressObserver>,
+    pub engine: &'a str,
+}
+
+impl<'a> Interruptee for EngineInterruptee<'a> {
+    fn was_interrupted(&self) -> bool {
+        self.sync.was_interrupted()
+            || self
+                .progress
+                .map_or(false, |progress| progress.is_engine_cancelled(self.engine))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use interrupt_support::NeverInterrupts;
+
+    struct CancelsTabs;
+
+    impl SyncProgressObserver for CancelsTabs {
+        fn is_engine_cancelled(&self, engine: &str) -> bool {
+            engine == "tabs"
+        }
+    }
+
+    struct AlwaysInterrupted;
+
+    impl Interruptee for AlwaysInterrupted {
+        fn was_interrupted(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_engine_interruptee() {
+        let interruptee = |sync: &'static dyn Interruptee, engine: &'static str| {
+            EngineInterruptee {
+                sync,
+                progress: Some(&CancelsTabs),
+                engine,
+            }
+            .was_interrupted()
+        };
+        assert!(interruptee(&NeverInterrupts, "tabs"));
+        assert!(!interruptee(&NeverInterrupts, "history"));
+        assert!(interruptee(&AlwaysInterrupted, "history"));
+        assert!(!EngineInterruptee {
+            sync: &NeverInterrupts,
+            progress: None,
+            engine: "tabs",
+        }
+        .was_interrupted());
+    }
+}
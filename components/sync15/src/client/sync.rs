@@ -2,14 +2,22 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use super::{CollectionUpdate, GlobalState, LocalCollStateMachine, Sync15StorageClient};
+use super::{
+    CollState, CollectionUpdate, GlobalState, LocalCollStateMachine, Sync15StorageClient,
+    SyncProgressObserver,
+};
 use crate::clients_engine;
-use crate::engine::SyncEngine;
+use crate::engine::{CollectionRequest, SyncEngine};
 use crate::error::Error;
 use crate::telemetry;
+use crate::Guid;
 use crate::KeyBundle;
 use interrupt_support::Interruptee;
 
+/// The most record IDs we ask the server for in a single request, so that
+/// requests for records to download again stay well under URL length limits.
+const MAX_IDS_PER_REQUEST: usize = 100;
+
 #[allow(clippy::too_many_arguments)]
 pub fn synchronize_with_clients_engine(
     client: &Sync15StorageClient,
@@ -20,8 +28,11 @@ pub fn synchronize_with_clients_engine(
     fully_atomic: bool,
     telem_engine: &mut telemetry::Engine,
     interruptee: &dyn Interruptee,
+    progress: Option<&dyn SyncProgressObserver>,
 ) -> Result<(), Error> {
     let collection = engine.collection_name();
+    // The number of records we staged, for engines which don't report how many they applied.
+    let mut num_staged = 0;
     log::info!("Syncing collection {}", collection);
 
     // our global state machine is ready - get the collection machine going.
@@ -41,6 +52,39 @@ pub fn synchronize_with_clients_engine(
         engine.prepare_for_sync(&|| clients.get_client_data())?;
     }
     interruptee.err_if_interrupted()?;
+
+    if let Some(validation_request) = engine.get_validation_request()? {
+        log::info!("Validating {}", collection);
+        let validated = super::fetch_incoming(client, &coll_state, validation_request).and_then(
+            |server_records| {
+                engine
+                    .validate(server_records, telem_engine)
+                    .map_err(Error::from)
+            },
+        );
+        match validated {
+            Ok(ids) => {
+                let num_redownloaded =
+                    stage_redownloaded(client, &coll_state, engine, ids, telem_engine)?;
+                if num_redownloaded > 0 {
+                    if let Some(progress) = progress {
+                        progress.records_downloaded(&collection, num_redownloaded);
+                    }
+                }
+                num_staged += num_redownloaded;
+            }
+            Err(e) => {
+                // Failing to download the records, or to validate them, is reported,
+                // but shouldn't stop us from syncing.
+                log::warn!("Failed to validate {}: {}", collection, e);
+                let mut validation = telemetry::Validation::default();
+                validation.failure(&e);
+                telem_engine.validation(validation);
+            }
+        }
+        interruptee.err_if_interrupted()?;
+    }
+
     // We assume an "engine" manages exactly one "collection" with the engine's name.
     match engine.get_collection_request(coll_state.last_modified)? {
         None => {
@@ -65,17 +109,27 @@ pub fn synchronize_with_clients_engine(
             // very end when we know we've staged them all.
             let incoming = super::fetch_incoming(client, &coll_state, collection_request)?;
             log::info!("Downloaded {} remote changes", incoming.len());
+            if let Some(progress) = progress {
+                progress.records_downloaded(&collection, incoming.len());
+            }
+            num_staged += incoming.len();
             engine.stage_incoming(incoming, telem_engine)?;
             interruptee.err_if_interrupted()?;
         }
     };
 
-    // Should consider adding a new `fetch_outgoing()` and having `apply()` only apply.
-    // It *might* even make sense to only call `apply()` when something was staged,
+    // It *might* make sense to only call `apply_chunked()` when something was staged,
     // but that's not clear - see the discussion at
     // https://github.com/mozilla/application-services/pull/5441/files/f36274f455a6299f10e7ce56b167882c369aa806#r1189267540
     log::info!("Applying changes");
-    let outgoing = engine.apply(coll_state.last_modified, telem_engine)?;
+    let outgoing = engine.apply_chunked(coll_state.last_modified, telem_engine)?;
+    if let Some(progress) = progress {
+        let num_applied = match telem_engine.get_incoming() {
+            Some(incoming) => incoming.get_applied() as usize,
+            None => num_staged,
+        };
+        progress.records_applied(&collection, num_applied);
+    }
     interruptee.err_if_interrupted()?;
 
     // XXX - this upload strategy is buggy due to batching. With enough records, we will commit
@@ -83,14 +137,16 @@ pub fn synchronize_with_clients_engine(
     // engine about the successful server batch commit.
     // Most stuff below should be called per-batch rather than at the successful end of all
     // batches, but that's not trivial.
-    log::info!("Uploading {} outgoing changes", outgoing.len());
-    let upload_info = CollectionUpdate::new_from_changeset(
+    // The outgoing records are read from the engine a chunk at a time as they're uploaded.
+    log::info!("Uploading outgoing changes");
+    let upload_info = CollectionUpdate::new_from_records(
         client,
         &coll_state,
-        collection,
+        collection.clone(),
         outgoing,
         fully_atomic,
-    )?
+    )
+    .interruptible(interruptee)
     .upload()?;
     log::info!(
         "Upload success ({} records success, {} records failed)",
@@ -102,6 +158,9 @@ pub fn synchronize_with_clients_engine(
     telem_outgoing.sent(upload_info.successful_ids.len() + upload_info.failed_ids.len());
     telem_outgoing.failed(upload_info.failed_ids.len());
     telem_engine.outgoing(telem_outgoing);
+    if let Some(progress) = progress {
+        progress.records_uploaded(&collection, upload_info.successful_ids.len());
+    }
 
     engine.set_uploaded(upload_info.modified_timestamp, upload_info.successful_ids)?;
 
@@ -112,3 +171,28 @@ pub fn synchronize_with_clients_engine(
     log::info!("Sync finished!");
     Ok(())
 }
+
+// Downloads the records an engine asked for when it validated, and stages them so they're
+// applied along with the rest of the incoming records. Returns the number of records staged.
+fn stage_redownloaded(
+    client: &Sync15StorageClient,
+    coll_state: &CollState,
+    engine: &dyn SyncEngine,
+    ids: Vec<Guid>,
+    telem_engine: &mut telemetry::Engine,
+) -> Result<usize, Error> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    log::info!("Downloading {} records again to repair them", ids.len());
+    let mut num_redownloaded = 0;
+    for chunk in ids.chunks(MAX_IDS_PER_REQUEST) {
+        let request = CollectionRequest::new(engine.collection_name())
+            .full()
+            .ids(chunk.iter().cloned());
+        let incoming = super::fetch_incoming(client, coll_state, request)?;
+        num_redownloaded += incoming.len();
+        engine.stage_incoming(incoming, telem_engine)?;
+    }
+    Ok(num_redownloaded)
+}
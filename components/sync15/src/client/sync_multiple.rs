@@ -8,6 +8,7 @@
 use super::state::{EngineChangesNeeded, GlobalState, PersistedGlobalState, SetupStateMachine};
 use super::status::{ServiceStatus, SyncResult};
 use super::storage_client::{BackoffListener, Sync15StorageClient, Sync15StorageClientInit};
+use super::{EngineInterruptee, SyncProgressObserver};
 use crate::clients_engine::{self, CommandProcessor, CLIENTS_TTL_REFRESH};
 use crate::engine::{EngineSyncAssociation, SyncEngine};
 use crate::error::Error;
@@ -15,6 +16,7 @@ use crate::telemetry;
 use crate::KeyBundle;
 use interrupt_support::Interruptee;
 use std::collections::HashMap;
+use std::fmt;
 use std::result;
 use std::time::{Duration, SystemTime};
 
@@ -149,6 +151,7 @@ pub fn sync_multiple_with_command_processor(
         mem_cached_state,
         saw_auth_error: false,
         ignore_soft_backoff: req_info.is_user_action,
+        progress: req_info.progress,
     };
     match driver.sync() {
         Ok(()) => {
@@ -177,10 +180,22 @@ pub fn sync_multiple_with_command_processor(
 /// This is essentially a bag of information that the sync manager knows, but
 /// otherwise we won't. It should probably be rethought if it gains many more
 /// fields.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct SyncRequestInfo<'a> {
     pub engines_to_state_change: Option<&'a HashMap<String, bool>>,
     pub is_user_action: bool,
+    /// Told about each engine's progress, and asked whether to cancel it.
+    pub progress: Option<&'a dyn SyncProgressObserver>,
+}
+
+impl<'a> fmt::Debug for SyncRequestInfo<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncRequestInfo")
+            .field("engines_to_state_change", &self.engines_to_state_change)
+            .field("is_user_action", &self.is_user_action)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
 }
 
 // The sync multiple driver
@@ -197,6 +212,7 @@ struct SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
     mem_cached_state: &'mcs mut MemoryCachedState,
     ignore_soft_backoff: bool,
     saw_auth_error: bool,
+    progress: Option<&'info dyn SyncProgressObserver>,
 }
 
 impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
@@ -307,8 +323,16 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
                 continue;
             }
             log::info!("Syncing {} engine!", name);
+            if let Some(progress) = self.progress {
+                progress.engine_started(&name);
+            }
 
             let mut telem_engine = telemetry::Engine::new(&*name);
+            let engine_interruptee = EngineInterruptee {
+                sync: self.interruptee,
+                progress: self.progress,
+                engine: &name,
+            };
             let result = super::sync::synchronize_with_clients_engine(
                 &client_info.client,
                 global_state,
@@ -317,14 +341,30 @@ impl<'info, 'res, 'pgs, 'mcs> SyncMultipleDriver<'info, 'res, 'pgs, 'mcs> {
                 *engine,
                 true,
                 &mut telem_engine,
-                self.interruptee,
+                &engine_interruptee,
+                self.progress,
             );
 
             match result {
-                Ok(()) => log::info!("Sync of {} was successful!", name),
+                Ok(()) => {
+                    log::info!("Sync of {} was successful!", name);
+                    if let Some(progress) = self.progress {
+                        progress.engine_finished(&name);
+                    }
+                }
                 Err(ref e) => {
                     log::warn!("Sync of {} failed! {:?}", name, e);
-                    let this_status = ServiceStatus::from_err(e);
+                    if let Some(progress) = self.progress {
+                        progress.engine_failed(&name, e);
+                    }
+                    let this_status = match e {
+                        // If only this engine was cancelled, carry on with the others.
+                        Error::Interrupted(_) if !self.interruptee.was_interrupted() => {
+                            log::info!("The {} engine was cancelled", name);
+                            ServiceStatus::OtherError
+                        }
+                        _ => ServiceStatus::from_err(e),
+                    };
                     // The only error which forces us to discard our state is an
                     // auth error.
                     self.saw_auth_error =
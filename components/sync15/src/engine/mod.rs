@@ -26,6 +26,7 @@
 //! types and payload management used by these traits, then to combine the
 //! requirements into a single trait that captures both use-cases.
 mod bridged_engine;
+mod outgoing;
 mod request;
 mod sync_engine;
 
@@ -33,5 +34,6 @@ pub use bridged_engine::{ApplyResults, BridgedEngine, BridgedEngineAdaptor};
 #[cfg(feature = "sync-client")]
 pub(crate) use request::CollectionPost;
 
+pub use outgoing::OutgoingRecords;
 pub use request::{CollectionRequest, RequestOrder};
 pub use sync_engine::{CollSyncIds, EngineSyncAssociation, SyncEngine, SyncEngineId};
@@ -0,0 +1,116 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::bso::OutgoingBso;
+use anyhow::Result;
+
+/// The outgoing records for a sync, supplied by an engine in chunks.
+///
+/// The sync client asks for the next chunk only once it has queued the previous one for
+/// upload, so an engine can read its outgoing records from storage a chunk at a time, rather
+/// than holding every record in memory at once. A chunk may be empty - the records are
+/// exhausted only when the iterator returns `None`.
+///
+/// Chunk sizes are up to the engine, and don't need to match the server's upload limits - the
+/// sync client splits and combines records into POSTs and batches as usual.
+pub struct OutgoingRecords<'a> {
+    chunks: Box<dyn Iterator<Item = Result<Vec<OutgoingBso>>> + 'a>,
+}
+
+impl<'a> OutgoingRecords<'a> {
+    /// Creates outgoing records that are already all in memory. This is fine for engines
+    /// which only ever have a small number of records.
+    pub fn from_vec(records: Vec<OutgoingBso>) -> Self {
+        Self {
+            chunks: Box::new(std::iter::once(Ok(records))),
+        }
+    }
+
+    /// Creates outgoing records from a function that returns the next chunk each time it's
+    /// called, and `None` once there are no more records.
+    pub fn from_fn<F>(mut next_chunk: F) -> Self
+    where
+        F: FnMut() -> Result<Option<Vec<OutgoingBso>>> + 'a,
+    {
+        Self {
+            chunks: Box::new(std::iter::from_fn(move || next_chunk().transpose())),
+        }
+    }
+
+    /// Reads every remaining chunk, returning all the records in a single vec.
+    pub fn collect_all(self) -> Result<Vec<OutgoingBso>> {
+        let mut records = Vec::new();
+        for chunk in self {
+            records.extend(chunk?);
+        }
+        Ok(records)
+    }
+}
+
+impl<'a> Iterator for OutgoingRecords<'a> {
+    type Item = Result<Vec<OutgoingBso>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next()
+    }
+}
+
+impl<'a> From<Vec<OutgoingBso>> for OutgoingRecords<'a> {
+    fn from(records: Vec<OutgoingBso>) -> Self {
+        Self::from_vec(records)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bso::OutgoingEnvelope;
+
+    fn tombstone(id: &str) -> OutgoingBso {
+        OutgoingBso::new_tombstone(OutgoingEnvelope {
+            id: id.into(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_from_fn() {
+        let mut remaining = vec![
+            vec![tombstone("a"), tombstone("b")],
+            vec![],
+            vec![tombstone("c")],
+        ];
+        remaining.reverse();
+        let records = OutgoingRecords::from_fn(|| Ok(remaining.pop()));
+        let ids = records
+            .collect_all()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.envelope.id.as_str().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_stops_on_error() {
+        let mut calls = 0;
+        let mut records = OutgoingRecords::from_fn(|| {
+            calls += 1;
+            if calls == 1 {
+                Ok(Some(vec![tombstone("a")]))
+            } else {
+                Err(anyhow::anyhow!("failed"))
+            }
+        });
+        assert_eq!(records.next().unwrap().unwrap().len(), 1);
+        assert!(records.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_from_vec() {
+        let records = OutgoingRecords::from(vec![tombstone("a")]);
+        assert_eq!(records.collect_all().unwrap().len(), 1);
+        assert_eq!(OutgoingRecords::from_vec(vec![]).count(), 1);
+    }
+}
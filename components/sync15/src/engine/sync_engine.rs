@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use super::CollectionRequest;
+use super::{CollectionRequest, OutgoingRecords};
 use crate::bso::{IncomingBso, OutgoingBso};
 use crate::client_types::ClientData;
 use crate::{telemetry, CollectionName, Guid, ServerTimestamp};
@@ -42,6 +42,9 @@ pub enum SyncEngineId {
     Addresses,
     CreditCards,
     History,
+    Forms,
+    Prefs,
+    ExtensionStorage,
 }
 
 impl SyncEngineId {
@@ -56,6 +59,9 @@ impl SyncEngineId {
             Self::Addresses,
             Self::CreditCards,
             Self::History,
+            Self::Forms,
+            Self::Prefs,
+            Self::ExtensionStorage,
         ]
         .into_iter()
     }
@@ -69,6 +75,9 @@ impl SyncEngineId {
             Self::Tabs => "tabs",
             Self::Addresses => "addresses",
             Self::CreditCards => "creditcards",
+            Self::Forms => "forms",
+            Self::Prefs => "prefs",
+            Self::ExtensionStorage => "storage-sync2",
         }
     }
 }
@@ -90,6 +99,9 @@ impl TryFrom<&str> for SyncEngineId {
             "tabs" => Ok(Self::Tabs),
             "addresses" => Ok(Self::Addresses),
             "creditcards" => Ok(Self::CreditCards),
+            "forms" => Ok(Self::Forms),
+            "prefs" => Ok(Self::Prefs),
+            "storage-sync2" => Ok(Self::ExtensionStorage),
             _ => Err(value.into()),
         }
     }
@@ -107,13 +119,13 @@ impl TryFrom<&str> for SyncEngineId {
 /// Some engines will "stage" these into a database temp table, while ones expecting less records
 /// might just store them in memory.
 ///
-/// For outgoing records, a single vec is supplied by the engine. The sync client will use the
-/// batch facilities of the server to make multiple POST requests and commit them.
+/// Outgoing records are supplied by the engine as [OutgoingRecords] - chunks of records which
+/// the sync client reads as it uploads them, so an engine with many outgoing records doesn't need
+/// to read them all into memory at once. The sync client will use the batch facilities of the
+/// server to make multiple POST requests and commit them.
 /// Sadly it's not truly atomic (there's a batch size limit) - so the model reflects that in that
 /// the engine gets told each time a batch is committed, which might happen more than once for the
-/// supplied vec. We should upgrade this model so the engine can avoid reading every outgoing
-/// record into memory at once (ie, we should try and better reflect the upload batch model at
-/// this level)
+/// supplied records.
 ///
 /// Sync Engines should not assume they live for exactly one sync, so `prepare_for_sync()` should
 /// clean up any state, including staged records, from previous syncs.
@@ -180,14 +192,31 @@ pub trait SyncEngine {
         telem: &mut telemetry::Engine,
     ) -> Result<()>;
 
-    /// Apply the staged records, returning outgoing records.
-    /// Ideally we would adjust this model to better support batching of outgoing records
-    /// without needing to keep them all in memory (ie, an iterator or similar?)
+    /// Apply the staged records, returning all the outgoing records in a single vec.
+    ///
+    /// This is a convenience wrapper around `apply_chunked()`, which collects all of its
+    /// outgoing records, for callers which don't upload them in chunks (such as bridged
+    /// engines and tests). Engines shouldn't need to override it.
     fn apply(
         &self,
         timestamp: ServerTimestamp,
         telem: &mut telemetry::Engine,
-    ) -> Result<Vec<OutgoingBso>>;
+    ) -> Result<Vec<OutgoingBso>> {
+        self.apply_chunked(timestamp, telem)?.collect_all()
+    }
+
+    /// Apply the staged records, returning the outgoing records.
+    ///
+    /// The staged records are applied before this returns, but the outgoing records are
+    /// read from the returned [OutgoingRecords] a chunk at a time during the upload, so
+    /// engines with many outgoing records can avoid keeping them all in memory. The chunks
+    /// are all read before `set_uploaded()` is called. Engines which build all their
+    /// outgoing records up front can return them with [OutgoingRecords::from_vec].
+    fn apply_chunked(
+        &self,
+        timestamp: ServerTimestamp,
+        telem: &mut telemetry::Engine,
+    ) -> Result<OutgoingRecords<'_>>;
 
     /// Indicates that the given record IDs were uploaded successfully to the server.
     /// This may be called multiple times per sync, once for each batch. Batching is determined
@@ -202,6 +231,13 @@ pub trait SyncEngine {
         Ok(())
     }
 
+    /// The number of conflicts between local and incoming records the engine resolved during
+    /// this sync - that is, the number of entries it added to its merge log. Engines which
+    /// don't keep a merge log can leave this as 0.
+    fn conflicts_resolved(&self) -> usize {
+        0
+    }
+
     /// The engine is responsible for building a single collection request. Engines
     /// typically will store a lastModified timestamp and use that to build a
     /// request saying "give me full records since that date" - however, other
@@ -214,6 +250,29 @@ pub trait SyncEngine {
         server_timestamp: ServerTimestamp,
     ) -> Result<Option<CollectionRequest>>;
 
+    /// Returns the request for the server records that the engine's local data should be
+    /// validated against, or `None` if the engine doesn't want to validate on this sync.
+    /// Validating usually means fetching every record in the collection, so engines should
+    /// only ask for it occasionally. Most engines don't validate.
+    fn get_validation_request(&self) -> Result<Option<CollectionRequest>> {
+        Ok(None)
+    }
+
+    /// Compares the engine's local data with the records fetched using the request from
+    /// `get_validation_request()`, and records any problems it finds in `telem`.
+    ///
+    /// This is called before the incoming records are fetched. An engine that repairs its
+    /// data should flag any local items that need uploading, so they're included in the
+    /// outgoing records from `apply_chunked()`, and return the IDs of any server records
+    /// that should be downloaded and staged again.
+    fn validate(
+        &self,
+        _server_records: Vec<IncomingBso>,
+        _telem: &mut telemetry::Engine,
+    ) -> Result<Vec<Guid>> {
+        Ok(vec![])
+    }
+
     /// Get persisted sync IDs. If they don't match the global state we'll be
     /// `reset()` with the new IDs.
     fn get_sync_assoc(&self) -> Result<EngineSyncAssociation>;
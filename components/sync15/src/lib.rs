@@ -23,6 +23,8 @@ pub mod engine;
 mod error;
 #[cfg(feature = "crypto")]
 mod key_bundle;
+// Types for the merge logs engines keep of the conflicts they resolve
+mod merge_log;
 mod record_types;
 mod server_timestamp;
 pub mod telemetry;
@@ -34,6 +36,7 @@ pub use crate::error::{Error, Result};
 pub use enc_payload::EncryptedPayload;
 #[cfg(feature = "crypto")]
 pub use key_bundle::KeyBundle;
+pub use merge_log::{MergeLogEntry, MergeWinner, MERGE_LOG_MAX_ENTRIES};
 pub use server_timestamp::ServerTimestamp;
 pub use sync_guid::Guid;
 
@@ -0,0 +1,75 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Types for the merge logs that engines keep, so that support can answer
+//! questions like "why did my password change?".
+//!
+//! Engines store their logs locally, and expose them through their stores.
+//! Each store keeps at most [MERGE_LOG_MAX_ENTRIES], dropping the oldest.
+
+use std::str::FromStr;
+
+/// The most entries each engine keeps in its merge log.
+pub const MERGE_LOG_MAX_ENTRIES: usize = 100;
+
+/// Which side won when an engine resolved a conflict.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MergeWinner {
+    Local,
+    Remote,
+    /// The engine couldn't merge the records, so kept both of them.
+    Both,
+}
+
+impl MergeWinner {
+    /// The string engines store in their merge log tables.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergeWinner::Local => "local",
+            MergeWinner::Remote => "remote",
+            MergeWinner::Both => "both",
+        }
+    }
+}
+
+impl FromStr for MergeWinner {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "local" => MergeWinner::Local,
+            "remote" => MergeWinner::Remote,
+            "both" => MergeWinner::Both,
+            _ => return Err(format!("Invalid merge winner: {s}")),
+        })
+    }
+}
+
+/// A conflict an engine resolved between a local and an incoming record.
+///
+/// Entries only name the fields that conflicted, and never hold their values,
+/// so they don't leak passwords or other secrets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeLogEntry {
+    pub collection: String,
+    pub guid: String,
+    /// The names of the fields that changed on both sides.
+    pub fields: Vec<String>,
+    pub winner: MergeWinner,
+    /// When the conflict was resolved, in milliseconds since the Unix epoch.
+    pub timestamp: i64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_winner_roundtrip() {
+        for winner in [MergeWinner::Local, MergeWinner::Remote, MergeWinner::Both] {
+            assert_eq!(winner.as_str().parse::<MergeWinner>(), Ok(winner));
+        }
+        assert!("neither".parse::<MergeWinner>().is_err());
+    }
+}
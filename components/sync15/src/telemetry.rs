@@ -466,8 +466,11 @@ impl Engine {
     }
 
     pub fn validation(&mut self, v: Validation) {
-        assert!(self.validation.is_none());
-        self.validation = Some(v);
+        // An engine's validator and its merge might both record problems.
+        match &mut self.validation {
+            None => self.validation = Some(v),
+            Some(ref mut existing) => existing.accum(v),
+        };
     }
 
     fn finished(&mut self) {
@@ -501,6 +504,23 @@ impl Validation {
         }
         self
     }
+
+    pub fn failure(&mut self, err: impl Into<SyncFailure>) -> &mut Self {
+        // As for engines, we keep the first failure.
+        if self.failure.is_none() {
+            self.failure = Some(err.into());
+        }
+        self
+    }
+
+    /// Combines the problems from another validation of the same engine.
+    fn accum(&mut self, other: Validation) {
+        self.version = self.version.max(other.version);
+        self.problems.extend(other.problems);
+        if self.failure.is_none() {
+            self.failure = other.failure;
+        }
+    }
 }
 
 #[derive(Debug, Default, Serialize)]
@@ -588,6 +608,28 @@ mod engine_tests {
         );
     }
 
+    #[test]
+    fn test_validation_accum() {
+        let mut e = Engine::new("TestEngine");
+        let mut v1 = Validation::default();
+        v1.problem("orphans", 2);
+        e.validation(v1);
+        let mut v2 = Validation::with_version(1);
+        v2.problem("missingChildren", 1).problem("serverMissing", 0);
+        e.validation(v2);
+        e.finished();
+        assert_json(
+            &e,
+            serde_json::json!({"name": "TestEngine",
+             "when": 0.0,
+             "validation": {
+                 "version": 1,
+                 "problems": [{"name": "orphans", "count": 2}, {"name": "missingChildren", "count": 1}]
+             }
+            }),
+        );
+    }
+
     #[test]
     fn test_raw() {
         let mut e = Engine::new("TestEngine");
@@ -0,0 +1,39 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+package mozilla.appservices.syncmanager
+
+/**
+ * A [SyncProgressCallback] which ignores every event, so listeners only need to
+ * override the ones they care about.
+ *
+ * The methods are called on the syncing thread, so they should return quickly.
+ */
+open class SyncProgressListener : SyncProgressCallback {
+    override fun engineStarted(engine: String) = Unit
+
+    override fun recordsDownloaded(engine: String, count: UInt) = Unit
+
+    override fun recordsApplied(engine: String, count: UInt) = Unit
+
+    override fun recordsUploaded(engine: String, count: UInt) = Unit
+
+    override fun engineFinished(engine: String) = Unit
+
+    override fun engineFailed(engine: String, reason: String) = Unit
+}
+
+/**
+ * Performs a sync, reporting each engine's progress to [progress].
+ *
+ * If [cancellation] is given, calling [SyncCancellationHandle.cancel] or
+ * [SyncCancellationHandle.cancelEngine] on it from another thread stops the
+ * sync, or a single engine. Cancelled engines are listed in
+ * [SyncResult.cancelled], and a cancelled sync has the
+ * [ServiceStatus.CANCELLED] status.
+ */
+fun SyncManager.syncWithProgress(
+    params: SyncParams,
+    progress: SyncProgressCallback,
+): SyncResult = syncWithProgress(params, progress, null)
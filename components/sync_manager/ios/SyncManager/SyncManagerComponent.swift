@@ -19,6 +19,14 @@ open class SyncManagerComponent {
         return try api.sync(params: params)
     }
 
+    public func syncWithProgress(
+        params: SyncParams,
+        progress: SyncProgressCallback,
+        cancellation: SyncCancellationHandle? = nil
+    ) throws -> SyncResult {
+        return try api.syncWithProgress(params: params, progress: progress, cancellation: cancellation)
+    }
+
     public func getAvailableEngines() -> [String] {
         return api.getAvailableEngines()
     }
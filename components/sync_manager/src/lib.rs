@@ -7,11 +7,15 @@
 
 pub mod error;
 pub mod manager;
+pub mod progress;
+pub mod scheduler;
 mod types;
 
 pub use sync15::DeviceType;
 
 pub use error::{Result, SyncManagerError};
+pub use progress::{SyncCancellationHandle, SyncProgressCallback};
+pub use scheduler::{NetworkState, SchedulerConfig, SchedulerEvent, SyncSchedule, SyncScheduler};
 pub use types::*;
 
 use manager::SyncManager;
@@ -3,12 +3,15 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::error::*;
+use crate::progress::{count_to_u32, SyncCancellationHandle, SyncProgress, SyncProgressCallback};
+use crate::scheduler::preserve_scheduler_state;
 use crate::types::{ServiceStatus, SyncEngineSelection, SyncParams, SyncReason, SyncResult};
 use crate::{reset, reset_all, wipe};
 use error_support::breadcrumb;
 use parking_lot::Mutex;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
+use std::sync::Arc;
 use std::time::SystemTime;
 use sync15::client::{
     sync_multiple_with_command_processor, MemoryCachedState, Sync15StorageClientInit,
@@ -37,8 +40,11 @@ impl SyncManager {
             SyncEngineId::Bookmarks => places::get_registered_sync_engine(engine_id),
             SyncEngineId::Addresses => autofill::get_registered_sync_engine(engine_id),
             SyncEngineId::CreditCards => autofill::get_registered_sync_engine(engine_id),
+            SyncEngineId::Forms => autofill::get_registered_sync_engine(engine_id),
+            SyncEngineId::Prefs => autofill::get_registered_sync_engine(engine_id),
             SyncEngineId::Passwords => logins::get_registered_sync_engine(engine_id),
             SyncEngineId::Tabs => tabs::get_registered_sync_engine(engine_id),
+            SyncEngineId::ExtensionStorage => webext_storage::get_registered_sync_engine(engine_id),
         }
     }
 
@@ -84,13 +90,37 @@ impl SyncManager {
 
     /// Perform a sync.  See [SyncParams] and [SyncResult] for details on how this works
     pub fn sync(&self, params: SyncParams) -> Result<SyncResult> {
+        self.sync_impl(params, None, None)
+    }
+
+    /// Like `sync`, but reports each engine's progress to `progress`, and
+    /// stops the sync, or individual engines, when `cancellation` says to.
+    pub fn sync_with_progress(
+        &self,
+        params: SyncParams,
+        progress: Box<dyn SyncProgressCallback>,
+        cancellation: Option<Arc<SyncCancellationHandle>>,
+    ) -> Result<SyncResult> {
+        self.sync_impl(params, Some(progress), cancellation)
+    }
+
+    fn sync_impl(
+        &self,
+        params: SyncParams,
+        callback: Option<Box<dyn SyncProgressCallback>>,
+        cancellation: Option<Arc<SyncCancellationHandle>>,
+    ) -> Result<SyncResult> {
         breadcrumb!("SyncManager::sync started");
         let mut state = self.mem_cached_state.lock();
         let engines = self.calc_engines_to_sync(&params.engines)?;
         let next_sync_after = state.as_ref().and_then(|mcs| mcs.get_next_sync_after());
         let result = if !backoff_in_effect(next_sync_after, &params) {
             log::info!("No backoff in effect (or we decided to ignore it), starting sync");
-            self.do_sync(params, &mut state, engines)
+            let progress = SyncProgress {
+                callback,
+                cancellation: cancellation.unwrap_or_default(),
+            };
+            self.do_sync(params, &mut state, engines, &progress)
         } else {
             breadcrumb!(
                 "Backoff still in effect (until {:?}), bailing out early",
@@ -100,11 +130,13 @@ impl SyncManager {
                 status: ServiceStatus::BackedOff,
                 successful: Default::default(),
                 failures: Default::default(),
+                cancelled: Default::default(),
                 declined: None,
                 next_sync_allowed_at: next_sync_after,
                 persisted_state: params.persisted_state.unwrap_or_default(),
                 // It would be nice to record telemetry here.
                 telemetry_json: None,
+                conflicts_resolved: Default::default(),
             })
         };
         breadcrumb!("SyncManager sync ended");
@@ -116,12 +148,13 @@ impl SyncManager {
         mut params: SyncParams,
         state: &mut Option<MemoryCachedState>,
         mut engines: Vec<Box<dyn SyncEngine>>,
+        progress: &SyncProgress,
     ) -> Result<SyncResult> {
         let key_bundle = sync15::KeyBundle::from_ksync_base64(&params.auth_info.sync_key)?;
         let tokenserver_url = url::Url::parse(&params.auth_info.tokenserver_url)?;
-        let interruptee = interrupt_support::ShutdownInterruptee;
         let mut mem_cached_state = state.take().unwrap_or_default();
         let mut disk_cached_state = params.persisted_state.take();
+        let initial_persisted_state = disk_cached_state.clone();
 
         // tell engines about the local encryption key.
         for engine in engines.iter_mut() {
@@ -156,10 +189,11 @@ impl SyncManager {
             &mut mem_cached_state,
             &client_init,
             &key_bundle,
-            &interruptee,
+            &*progress.cancellation,
             Some(SyncRequestInfo {
                 engines_to_state_change: engines_to_change,
                 is_user_action: matches!(params.reason, SyncReason::User),
+                progress: Some(progress),
             }),
         );
         *state = Some(mem_cached_state);
@@ -171,26 +205,42 @@ impl SyncManager {
         }
         let mut successful: Vec<String> = Vec::new();
         let mut failures: HashMap<String, String> = HashMap::new();
+        let mut cancelled: Vec<String> = Vec::new();
         for (engine, result) in result.engine_results.into_iter() {
             match result {
                 Ok(_) => {
                     successful.push(engine);
                 }
+                Err(sync15::Error::Interrupted(_)) => {
+                    cancelled.push(engine);
+                }
                 Err(err) => {
                     failures.insert(engine, err.to_string());
                 }
             }
         }
+        let conflicts_resolved = engines
+            .iter()
+            .filter_map(|engine| match engine.conflicts_resolved() {
+                0 => None,
+                count => Some((engine.collection_name().to_string(), count_to_u32(count))),
+            })
+            .collect();
         let telemetry_json = serde_json::to_string(&result.telemetry).unwrap();
 
         Ok(SyncResult {
             status,
             successful,
             failures,
+            cancelled,
             declined: result.declined,
             next_sync_allowed_at: result.next_sync_after,
-            persisted_state: disk_cached_state.unwrap_or_default(),
+            persisted_state: preserve_scheduler_state(
+                initial_persisted_state.as_deref(),
+                disk_cached_state.unwrap_or_default(),
+            ),
             telemetry_json: Some(telemetry_json),
+            conflicts_resolved,
         })
     }
 
@@ -274,7 +324,7 @@ impl From<sync15::client::ServiceStatus> for ServiceStatus {
             ServiceError => ServiceStatus::ServiceError,
             AuthenticationError => ServiceStatus::AuthError,
             BackedOff => ServiceStatus::BackedOff,
-            Interrupted => ServiceStatus::OtherError, // Eh...
+            Interrupted => ServiceStatus::Cancelled,
             OtherError => ServiceStatus::OtherError,
         }
     }
@@ -0,0 +1,203 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Progress reporting and cancellation for `SyncManager::sync_with_progress`.
+//!
+//! Apps pass a [SyncProgressCallback] to hear about each engine as it syncs,
+//! and can pass a [SyncCancellationHandle] to stop the sync, or a single
+//! engine, from another thread. Cancellation is checked between the steps of
+//! each engine's sync - after fetching and staging incoming records, after
+//! applying them, and between chunks of outgoing records. A cancelled engine
+//! stops at the next check, so incoming records it already applied stay
+//! applied, and records it didn't upload are uploaded by the next sync.
+
+use interrupt_support::{Interruptee, ShutdownInterruptee};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use sync15::client::SyncProgressObserver;
+
+/// Told about each engine's progress during a sync. These are called on the
+/// syncing thread, so they should return quickly.
+pub trait SyncProgressCallback: Send + Sync {
+    fn engine_started(&self, engine: String);
+    fn records_downloaded(&self, engine: String, count: u32);
+    fn records_applied(&self, engine: String, count: u32);
+    fn records_uploaded(&self, engine: String, count: u32);
+    fn engine_finished(&self, engine: String);
+    fn engine_failed(&self, engine: String, reason: String);
+}
+
+/// Stops a sync, or some of its engines, from another thread.
+#[derive(Default)]
+pub struct SyncCancellationHandle {
+    cancelled: AtomicBool,
+    cancelled_engines: Mutex<HashSet<String>>,
+}
+
+impl SyncCancellationHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the whole sync. The sync returns once the current engine stops.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops a single engine. It fails with an "interrupted" error, and the
+    /// sync carries on with the other engines.
+    pub fn cancel_engine(&self, engine: String) {
+        self.cancelled_engines.lock().insert(engine);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn is_engine_cancelled(&self, engine: &str) -> bool {
+        self.cancelled_engines.lock().contains(engine)
+    }
+}
+
+// The whole sync is interrupted when it's cancelled, or when the app shuts down.
+impl Interruptee for SyncCancellationHandle {
+    fn was_interrupted(&self) -> bool {
+        self.is_cancelled() || ShutdownInterruptee.was_interrupted()
+    }
+}
+
+/// Adapts the app's callback and cancellation handle to sync15's observer.
+pub(crate) struct SyncProgress {
+    pub(crate) callback: Option<Box<dyn SyncProgressCallback>>,
+    pub(crate) cancellation: Arc<SyncCancellationHandle>,
+}
+
+pub(crate) fn count_to_u32(count: usize) -> u32 {
+    u32::try_from(count).unwrap_or(u32::MAX)
+}
+
+impl SyncProgressObserver for SyncProgress {
+    fn engine_started(&self, engine: &str) {
+        if let Some(callback) = &self.callback {
+            callback.engine_started(engine.to_string());
+        }
+    }
+
+    fn records_downloaded(&self, engine: &str, count: usize) {
+        if let Some(callback) = &self.callback {
+            callback.records_downloaded(engine.to_string(), count_to_u32(count));
+        }
+    }
+
+    fn records_applied(&self, engine: &str, count: usize) {
+        if let Some(callback) = &self.callback {
+            callback.records_applied(engine.to_string(), count_to_u32(count));
+        }
+    }
+
+    fn records_uploaded(&self, engine: &str, count: usize) {
+        if let Some(callback) = &self.callback {
+            callback.records_uploaded(engine.to_string(), count_to_u32(count));
+        }
+    }
+
+    fn engine_finished(&self, engine: &str) {
+        if let Some(callback) = &self.callback {
+            callback.engine_finished(engine.to_string());
+        }
+    }
+
+    fn engine_failed(&self, engine: &str, error: &sync15::Error) {
+        if let Some(callback) = &self.callback {
+            callback.engine_failed(engine.to_string(), error.to_string());
+        }
+    }
+
+    fn is_engine_cancelled(&self, engine: &str) -> bool {
+        self.cancellation.is_engine_cancelled(engine)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingCallback {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl SyncProgressCallback for RecordingCallback {
+        fn engine_started(&self, engine: String) {
+            self.events.lock().push(format!("started {engine}"));
+        }
+        fn records_downloaded(&self, engine: String, count: u32) {
+            self.events
+                .lock()
+                .push(format!("downloaded {engine} {count}"));
+        }
+        fn records_applied(&self, engine: String, count: u32) {
+            self.events.lock().push(format!("applied {engine} {count}"));
+        }
+        fn records_uploaded(&self, engine: String, count: u32) {
+            self.events
+                .lock()
+                .push(format!("uploaded {engine} {count}"));
+        }
+        fn engine_finished(&self, engine: String) {
+            self.events.lock().push(format!("finished {engine}"));
+        }
+        fn engine_failed(&self, engine: String, reason: String) {
+            self.events
+                .lock()
+                .push(format!("failed {engine}: {reason}"));
+        }
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let handle = SyncCancellationHandle::new();
+        assert!(!handle.was_interrupted());
+        handle.cancel_engine("tabs".to_string());
+        assert!(handle.is_engine_cancelled("tabs"));
+        assert!(!handle.is_engine_cancelled("history"));
+        // Cancelling an engine doesn't cancel the sync.
+        assert!(!handle.was_interrupted());
+        handle.cancel();
+        assert!(handle.is_cancelled());
+        assert!(handle.was_interrupted());
+    }
+
+    #[test]
+    fn test_progress() {
+        let callback = RecordingCallback::default();
+        let events = Arc::clone(&callback.events);
+        let progress = SyncProgress {
+            callback: Some(Box::new(callback)),
+            cancellation: Arc::new(SyncCancellationHandle::new()),
+        };
+        progress.engine_started("tabs");
+        progress.records_downloaded("tabs", 3);
+        progress.records_applied("tabs", 2);
+        progress.records_uploaded("tabs", usize::MAX);
+        progress.engine_finished("tabs");
+        progress.engine_failed("history", &sync15::Error::RecordTooLargeError);
+        assert_eq!(
+            *events.lock(),
+            vec![
+                "started tabs".to_string(),
+                "downloaded tabs 3".to_string(),
+                "applied tabs 2".to_string(),
+                format!("uploaded tabs {}", u32::MAX),
+                "finished tabs".to_string(),
+                "failed history: Outgoing record is too large to upload".to_string(),
+            ]
+        );
+        progress.cancellation.cancel_engine("history".to_string());
+        assert!(progress.is_engine_cancelled("history"));
+    }
+}
@@ -0,0 +1,915 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Decides when the app should sync next, and which engines to sync.
+//!
+//! The app tells the [SyncScheduler] about things that happen - the app being
+//! foregrounded or backgrounded, local changes, network changes, and explicit
+//! sync requests - and about the results of each sync. [SyncScheduler::next_sync]
+//! then says when the next sync should happen. The scheduler doesn't sync or
+//! set any timers itself: the app is expected to wake up at the returned time,
+//! call `SyncManager::sync`, and ask again.
+//!
+//! The scheduler's state is stored alongside the sync state, in the
+//! `persisted_state` string that apps already round-trip through
+//! `SyncManager::sync`.
+
+use crate::error::*;
+use crate::types::{ServiceStatus, SyncEngineSelection, SyncReason, SyncResult};
+use parking_lot::Mutex;
+use serde_derive::*;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sync15::client::PersistedGlobalState;
+use sync15::engine::SyncEngineId;
+
+/// The key in the persisted state JSON object that holds the scheduler's
+/// state. Sync15 ignores keys it doesn't know about when it reads the
+/// persisted state, but drops them when it writes it, so `SyncManager::sync`
+/// copies this key over after each sync.
+const PERSISTED_STATE_KEY: &str = "scheduler";
+
+/// Caps how many times the error backoff doubles, so that it can't overflow.
+const MAX_ERROR_BACKOFF_DOUBLINGS: u32 = 16;
+
+/// The longest the scheduler delays anything by. Intervals and delays in the
+/// config come from the app, so we clamp them rather than overflow.
+const MAX_DELAY: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// A source of the current time. Tests use a fake clock to check schedules
+/// without waiting.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The real clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkState {
+    Offline,
+    /// Connected, but the user might pay for data, like a cellular connection.
+    Metered,
+    Unmetered,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedulerEvent {
+    AppForegrounded,
+    AppBackgrounded,
+    /// The number of local changes to an engine's data since it last synced.
+    /// Apps can report this as often as they like - it replaces the previous
+    /// count, rather than adding to it.
+    LocalChanges {
+        engine: String,
+        count: u32,
+    },
+    NetworkChanged {
+        state: NetworkState,
+    },
+    /// Something asked for a sync. User-requested syncs, and syncs for
+    /// engine state changes, happen immediately, even if the server asked us
+    /// to back off. Syncs before sleeping or when backgrounded only sync
+    /// engines with local changes.
+    SyncRequested {
+        reason: SyncReason,
+    },
+}
+
+/// How often to sync. Intervals are in seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerConfig {
+    /// The engines the app syncs.
+    pub engines: Vec<String>,
+    /// How often to sync each engine while the app is in the foreground.
+    pub foreground_interval_secs: u64,
+    /// How often to sync each engine while the app is in the background.
+    pub background_interval_secs: u64,
+    /// The shortest interval between syncs on a metered network. Local changes
+    /// are still synced promptly.
+    pub metered_interval_secs: u64,
+    /// Per-engine intervals, which replace the foreground and background
+    /// intervals for those engines.
+    pub engine_interval_secs: HashMap<String, u64>,
+    /// How long to wait after the first local change to an engine before
+    /// syncing it, so that a burst of changes is uploaded together.
+    pub local_change_delay_secs: u64,
+    /// The number of local changes to an engine that syncs it immediately.
+    pub local_change_threshold: u32,
+    /// How long to wait after a failed sync. This doubles after each
+    /// consecutive failure, up to `max_error_backoff_secs`.
+    pub error_backoff_secs: u64,
+    pub max_error_backoff_secs: u64,
+    /// Engines due within this long of the first engine are synced with it,
+    /// rather than in a sync of their own.
+    pub coalesce_window_secs: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            engines: SyncEngineId::iter()
+                .map(|id| id.name().to_string())
+                .collect(),
+            foreground_interval_secs: 10 * 60,
+            background_interval_secs: 60 * 60,
+            metered_interval_secs: 4 * 60 * 60,
+            engine_interval_secs: HashMap::new(),
+            local_change_delay_secs: 60,
+            local_change_threshold: 50,
+            error_backoff_secs: 5 * 60,
+            max_error_backoff_secs: 4 * 60 * 60,
+            coalesce_window_secs: 5 * 60,
+        }
+    }
+}
+
+/// When to sync next, and what to sync.
+#[derive(Debug)]
+pub struct SyncSchedule {
+    /// When to start the sync. This might be in the past, in which case the
+    /// app should sync now.
+    pub at: SystemTime,
+    pub reason: SyncReason,
+    pub engines: SyncEngineSelection,
+}
+
+// Timestamps in the persisted state are milliseconds since the epoch.
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn from_millis(millis: u64) -> SystemTime {
+    // Times we can't represent can only come from a corrupt state.
+    UNIX_EPOCH
+        .checked_add(Duration::from_millis(millis))
+        .unwrap_or(UNIX_EPOCH)
+}
+
+/// Returns `time` plus `delay`, with the delay clamped to `MAX_DELAY`.
+fn add_delay(time: SystemTime, delay: Duration) -> SystemTime {
+    time.checked_add(delay.min(MAX_DELAY)).unwrap_or(time)
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+struct EngineState {
+    last_sync: Option<u64>,
+    local_changes: u32,
+    first_change_at: Option<u64>,
+    failures: u32,
+    last_failure_at: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+struct SchedulerState {
+    engines: BTreeMap<String, EngineState>,
+    consecutive_failures: u32,
+    last_failure_at: Option<u64>,
+    /// When the server said we can sync again.
+    backoff_until: Option<u64>,
+    /// The last sync failed to authenticate, so there's no point syncing
+    /// again until something asks us to.
+    needs_reauth: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PendingRequest {
+    reason: SyncReason,
+    /// The engines to sync, or `None` for all of them.
+    engines: Option<Vec<String>>,
+}
+
+impl PendingRequest {
+    fn is_urgent(&self) -> bool {
+        matches!(self.reason, SyncReason::User | SyncReason::EnabledChange)
+    }
+}
+
+struct SchedulerInner {
+    state: SchedulerState,
+    /// The persisted state from the last sync, which we add our state to.
+    persisted_state: Option<String>,
+    // The app tells us about these as they change, so we don't persist them.
+    foreground: bool,
+    network: NetworkState,
+    pending: Option<PendingRequest>,
+    /// Each engine's local changes when we last said when to sync. The sync
+    /// uploads these, but changes reported while it runs still need syncing.
+    scheduled_changes: HashMap<String, u32>,
+}
+
+pub struct SyncScheduler {
+    config: SchedulerConfig,
+    clock: Box<dyn Clock>,
+    inner: Mutex<SchedulerInner>,
+}
+
+impl SyncScheduler {
+    /// Creates a scheduler, restoring its state from the persisted state
+    /// returned by the last sync.
+    pub fn new(config: SchedulerConfig, persisted_state: Option<String>) -> Result<Self> {
+        Self::new_with_clock(config, persisted_state, Box::new(SystemClock))
+    }
+
+    pub fn new_with_clock(
+        config: SchedulerConfig,
+        persisted_state: Option<String>,
+        clock: Box<dyn Clock>,
+    ) -> Result<Self> {
+        for engine in &config.engines {
+            SyncEngineId::try_from(engine.as_str()).map_err(SyncManagerError::UnknownEngine)?;
+        }
+        let state = persisted_state
+            .as_deref()
+            .map(read_scheduler_state)
+            .unwrap_or_default();
+        Ok(Self {
+            config,
+            clock,
+            inner: Mutex::new(SchedulerInner {
+                state,
+                persisted_state,
+                foreground: false,
+                network: NetworkState::Unmetered,
+                pending: None,
+                scheduled_changes: HashMap::new(),
+            }),
+        })
+    }
+
+    pub fn handle_event(&self, event: SchedulerEvent) {
+        let now = to_millis(self.clock.now());
+        let mut inner = self.inner.lock();
+        match event {
+            SchedulerEvent::AppForegrounded => inner.foreground = true,
+            SchedulerEvent::AppBackgrounded => {
+                inner.foreground = false;
+                // Upload any local changes before the app might be killed.
+                inner.request_sync(SyncReason::Backgrounded);
+            }
+            SchedulerEvent::LocalChanges { engine, count } => {
+                if !self.config.engines.contains(&engine) {
+                    log::warn!("Ignoring local changes for unscheduled engine {}", engine);
+                    return;
+                }
+                let engine_state = inner.state.engines.entry(engine).or_default();
+                engine_state.local_changes = count;
+                if count == 0 {
+                    engine_state.first_change_at = None;
+                } else if engine_state.first_change_at.is_none() {
+                    engine_state.first_change_at = Some(now);
+                }
+            }
+            SchedulerEvent::NetworkChanged { state } => inner.network = state,
+            SchedulerEvent::SyncRequested { reason } => {
+                // Something - hopefully signing in again - wants us to try.
+                inner.state.needs_reauth = false;
+                inner.request_sync(reason);
+            }
+        }
+    }
+
+    /// Returns when to sync next, and what to sync, or `None` if we
+    /// shouldn't sync until something changes, like the network coming back.
+    ///
+    /// The local changes reported so far are the ones the sync is expected to
+    /// upload, so apps should ask for the next sync just before syncing.
+    pub fn next_sync(&self) -> Option<SyncSchedule> {
+        let now = self.clock.now();
+        let mut inner = self.inner.lock();
+        if inner.network == NetworkState::Offline {
+            return None;
+        }
+        inner.scheduled_changes = inner
+            .state
+            .engines
+            .iter()
+            .map(|(engine, engine_state)| (engine.clone(), engine_state.local_changes))
+            .collect();
+        let earliest = inner.earliest_allowed(&self.config).max(now);
+        if let Some(pending) = &inner.pending {
+            return Some(SyncSchedule {
+                at: if pending.is_urgent() { now } else { earliest },
+                reason: pending.reason,
+                engines: self.selection(pending.engines.as_deref()),
+            });
+        }
+        if inner.state.needs_reauth {
+            return None;
+        }
+
+        let due = self
+            .config
+            .engines
+            .iter()
+            .map(|engine| (engine, inner.engine_due_at(&self.config, engine, now)))
+            .collect::<Vec<_>>();
+        let first = due.iter().map(|(_, at)| *at).min()?.max(earliest);
+        let window_end = add_delay(first, Duration::from_secs(self.config.coalesce_window_secs));
+        let engines = due
+            .into_iter()
+            .filter(|(_, at)| *at <= window_end)
+            .map(|(engine, _)| engine.clone())
+            .collect::<Vec<_>>();
+        Some(SyncSchedule {
+            at: first,
+            reason: SyncReason::Scheduled,
+            engines: self.selection(Some(&engines)),
+        })
+    }
+
+    /// Records the result of a sync. The scheduler takes the new persisted
+    /// state from the result, so apps should persist the scheduler's
+    /// `persisted_state()` afterwards.
+    pub fn sync_finished(&self, result: &SyncResult) {
+        let now = to_millis(self.clock.now());
+        let mut inner = self.inner.lock();
+        inner.persisted_state = Some(result.persisted_state.clone());
+        inner.pending = None;
+        let scheduled_changes = std::mem::take(&mut inner.scheduled_changes);
+        let state = &mut inner.state;
+        for engine in &result.successful {
+            let engine_state = state.engines.entry(engine.clone()).or_default();
+            // Changes reported during the sync weren't uploaded, so we wait
+            // to sync them like any other new changes.
+            let local_changes = engine_state
+                .local_changes
+                .saturating_sub(scheduled_changes.get(engine).copied().unwrap_or_default());
+            *engine_state = EngineState {
+                last_sync: Some(now),
+                local_changes,
+                first_change_at: (local_changes > 0).then_some(now),
+                ..Default::default()
+            };
+        }
+        for engine in result.failures.keys() {
+            let engine_state = state.engines.entry(engine.clone()).or_default();
+            engine_state.failures += 1;
+            engine_state.last_failure_at = Some(now);
+        }
+        match result.status {
+            ServiceStatus::Ok => {
+                state.consecutive_failures = 0;
+                state.last_failure_at = None;
+            }
+            ServiceStatus::AuthError => state.needs_reauth = true,
+            // The server's backoff is recorded below.
+            ServiceStatus::BackedOff => (),
+            // Cancelling isn't a failure, and cancelled engines keep their
+            // state, so they're synced again when they're due.
+            ServiceStatus::Cancelled => (),
+            ServiceStatus::NetworkError
+            | ServiceStatus::ServiceError
+            | ServiceStatus::OtherError => {
+                state.consecutive_failures += 1;
+                state.last_failure_at = Some(now);
+            }
+        }
+        state.backoff_until = result.next_sync_allowed_at.map(to_millis);
+    }
+
+    /// Returns the persisted state, including the scheduler's state, to pass
+    /// to the next `SyncManager::sync`, and to restore the scheduler from.
+    pub fn persisted_state(&self) -> Result<String> {
+        let inner = self.inner.lock();
+        write_scheduler_state(inner.persisted_state.as_deref(), &inner.state)
+    }
+
+    fn selection(&self, engines: Option<&[String]>) -> SyncEngineSelection {
+        match engines {
+            Some(engines) if engines.len() < self.config.engines.len() => {
+                SyncEngineSelection::Some {
+                    engines: engines.to_vec(),
+                }
+            }
+            _ => SyncEngineSelection::All,
+        }
+    }
+}
+
+impl SchedulerInner {
+    fn request_sync(&mut self, reason: SyncReason) {
+        let engines = match reason {
+            SyncReason::PreSleep | SyncReason::Backgrounded => {
+                let changed = self
+                    .state
+                    .engines
+                    .iter()
+                    .filter(|(_, engine_state)| engine_state.local_changes > 0)
+                    .map(|(engine, _)| engine.clone())
+                    .collect::<Vec<_>>();
+                if changed.is_empty() {
+                    return;
+                }
+                Some(changed)
+            }
+            _ => None,
+        };
+        let request = PendingRequest { reason, engines };
+        // Don't let a background sync replace a sync the user asked for.
+        if !matches!(&self.pending, Some(pending) if pending.is_urgent() && !request.is_urgent()) {
+            self.pending = Some(request);
+        }
+    }
+
+    /// The earliest we can sync, because of server or error backoff.
+    fn earliest_allowed(&self, config: &SchedulerConfig) -> SystemTime {
+        let mut earliest = UNIX_EPOCH;
+        if let Some(backoff_until) = self.state.backoff_until {
+            earliest = earliest.max(from_millis(backoff_until));
+        }
+        if let Some(last_failure_at) = self.state.last_failure_at {
+            earliest = earliest.max(add_delay(
+                from_millis(last_failure_at),
+                error_backoff(config, self.state.consecutive_failures),
+            ));
+        }
+        earliest
+    }
+
+    fn engine_due_at(&self, config: &SchedulerConfig, engine: &str, now: SystemTime) -> SystemTime {
+        let engine_state = self.state.engines.get(engine).cloned().unwrap_or_default();
+        let mut interval =
+            config
+                .engine_interval_secs
+                .get(engine)
+                .copied()
+                .unwrap_or(if self.foreground {
+                    config.foreground_interval_secs
+                } else {
+                    config.background_interval_secs
+                });
+        if self.network == NetworkState::Metered {
+            interval = interval.max(config.metered_interval_secs);
+        }
+        let mut due_at = match engine_state.last_sync {
+            Some(last_sync) => add_delay(from_millis(last_sync), Duration::from_secs(interval)),
+            // Never synced, so sync as soon as we can.
+            None => now,
+        };
+        if engine_state.local_changes >= config.local_change_threshold {
+            due_at = now;
+        } else if let Some(first_change_at) = engine_state.first_change_at {
+            due_at = due_at.min(add_delay(
+                from_millis(first_change_at),
+                Duration::from_secs(config.local_change_delay_secs),
+            ));
+        }
+        if let Some(last_failure_at) = engine_state.last_failure_at {
+            due_at = due_at.max(add_delay(
+                from_millis(last_failure_at),
+                error_backoff(config, engine_state.failures),
+            ));
+        }
+        due_at.max(now)
+    }
+}
+
+fn error_backoff(config: &SchedulerConfig, failures: u32) -> Duration {
+    if failures == 0 {
+        return Duration::ZERO;
+    }
+    let doublings = (failures - 1).min(MAX_ERROR_BACKOFF_DOUBLINGS);
+    Duration::from_secs(
+        config
+            .error_backoff_secs
+            .saturating_mul(1 << doublings)
+            .min(config.max_error_backoff_secs),
+    )
+}
+
+fn read_scheduler_state(persisted_state: &str) -> SchedulerState {
+    let value = match serde_json::from_str::<serde_json::Value>(persisted_state) {
+        Ok(serde_json::Value::Object(mut map)) => map.remove(PERSISTED_STATE_KEY),
+        _ => None,
+    };
+    match value.map(serde_json::from_value) {
+        Some(Ok(state)) => state,
+        Some(Err(e)) => {
+            log::warn!("Failed to parse the persisted scheduler state: {}", e);
+            SchedulerState::default()
+        }
+        None => SchedulerState::default(),
+    }
+}
+
+fn write_scheduler_state(persisted_state: Option<&str>, state: &SchedulerState) -> Result<String> {
+    // Sync15 reports an error for a state it can't parse, so if we don't have
+    // a valid one yet, we start from the state it uses on the first sync.
+    let sync15_state =
+        match persisted_state.filter(|s| serde_json::from_str::<PersistedGlobalState>(s).is_ok()) {
+            Some(s) => serde_json::from_str(s)?,
+            None => serde_json::to_value(PersistedGlobalState::default())?,
+        };
+    let mut map = match sync15_state {
+        serde_json::Value::Object(map) => map,
+        // `PersistedGlobalState` is always an object.
+        _ => serde_json::Map::new(),
+    };
+    map.insert(PERSISTED_STATE_KEY.into(), serde_json::to_value(state)?);
+    Ok(serde_json::to_string(&map)?)
+}
+
+/// Copies the scheduler's state from the persisted state passed to a sync to
+/// the persisted state the sync returns.
+pub(crate) fn preserve_scheduler_state(old: Option<&str>, new: String) -> String {
+    let value = match old.map(serde_json::from_str::<serde_json::Value>) {
+        Some(Ok(serde_json::Value::Object(mut map))) => map.remove(PERSISTED_STATE_KEY),
+        _ => None,
+    };
+    let value = match value {
+        Some(value) => value,
+        None => return new,
+    };
+    match serde_json::from_str::<serde_json::Value>(&new) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            map.insert(PERSISTED_STATE_KEY.into(), value);
+            serde_json::to_string(&map).unwrap_or(new)
+        }
+        _ => new,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone)]
+    struct FakeClock(Arc<Mutex<SystemTime>>);
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self(Arc::new(Mutex::new(
+                UNIX_EPOCH + Duration::from_secs(1_000_000),
+            )))
+        }
+
+        fn advance(&self, secs: u64) {
+            *self.0.lock() += Duration::from_secs(secs);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> SystemTime {
+            *self.0.lock()
+        }
+    }
+
+    fn config() -> SchedulerConfig {
+        SchedulerConfig {
+            engines: vec!["bookmarks".into(), "history".into(), "tabs".into()],
+            ..Default::default()
+        }
+    }
+
+    fn scheduler(clock: &FakeClock, persisted_state: Option<String>) -> SyncScheduler {
+        SyncScheduler::new_with_clock(config(), persisted_state, Box::new(clock.clone())).unwrap()
+    }
+
+    fn result(status: ServiceStatus, successful: &[&str], failed: &[&str]) -> SyncResult {
+        SyncResult {
+            status,
+            successful: successful.iter().map(|e| e.to_string()).collect(),
+            failures: failed
+                .iter()
+                .map(|e| (e.to_string(), "failed".to_string()))
+                .collect(),
+            cancelled: Vec::new(),
+            persisted_state: r#"{"schema_version":"V2","declined":[]}"#.into(),
+            declined: None,
+            next_sync_allowed_at: None,
+            telemetry_json: None,
+            conflicts_resolved: Default::default(),
+        }
+    }
+
+    fn engines(schedule: &SyncSchedule) -> Option<Vec<String>> {
+        match &schedule.engines {
+            SyncEngineSelection::All => None,
+            SyncEngineSelection::Some { engines } => Some(engines.clone()),
+        }
+    }
+
+    fn secs_from_now(clock: &FakeClock, schedule: &SyncSchedule) -> u64 {
+        schedule.at.duration_since(clock.now()).unwrap().as_secs()
+    }
+
+    #[test]
+    fn test_unknown_engine() {
+        let config = SchedulerConfig {
+            engines: vec!["nope".into()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            SyncScheduler::new(config, None),
+            Err(SyncManagerError::UnknownEngine(_))
+        ));
+    }
+
+    #[test]
+    fn test_intervals() {
+        let clock = FakeClock::new();
+        let scheduler = scheduler(&clock, None);
+        // Never synced, so sync everything now.
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(schedule.at, clock.now());
+        assert_eq!(schedule.reason, SyncReason::Scheduled);
+        assert_eq!(engines(&schedule), None);
+
+        scheduler.sync_finished(&result(
+            ServiceStatus::Ok,
+            &["bookmarks", "history", "tabs"],
+            &[],
+        ));
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 3600);
+        scheduler.handle_event(SchedulerEvent::AppForegrounded);
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 600);
+        scheduler.handle_event(SchedulerEvent::NetworkChanged {
+            state: NetworkState::Metered,
+        });
+        assert_eq!(
+            secs_from_now(&clock, &scheduler.next_sync().unwrap()),
+            4 * 3600
+        );
+        scheduler.handle_event(SchedulerEvent::NetworkChanged {
+            state: NetworkState::Offline,
+        });
+        assert!(scheduler.next_sync().is_none());
+    }
+
+    #[test]
+    fn test_engine_intervals() {
+        let clock = FakeClock::new();
+        let config = SchedulerConfig {
+            engine_interval_secs: [("tabs".to_string(), 120)].into_iter().collect(),
+            ..config()
+        };
+        let scheduler =
+            SyncScheduler::new_with_clock(config, None, Box::new(clock.clone())).unwrap();
+        scheduler.sync_finished(&result(
+            ServiceStatus::Ok,
+            &["bookmarks", "history", "tabs"],
+            &[],
+        ));
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(secs_from_now(&clock, &schedule), 120);
+        assert_eq!(engines(&schedule), Some(vec!["tabs".to_string()]));
+    }
+
+    #[test]
+    fn test_huge_intervals() {
+        let clock = FakeClock::new();
+        let config = SchedulerConfig {
+            foreground_interval_secs: u64::MAX,
+            background_interval_secs: u64::MAX,
+            local_change_delay_secs: u64::MAX,
+            error_backoff_secs: u64::MAX,
+            max_error_backoff_secs: u64::MAX,
+            coalesce_window_secs: u64::MAX,
+            ..config()
+        };
+        let scheduler =
+            SyncScheduler::new_with_clock(config, None, Box::new(clock.clone())).unwrap();
+        scheduler.sync_finished(&result(
+            ServiceStatus::Ok,
+            &["bookmarks", "tabs"],
+            &["history"],
+        ));
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "tabs".into(),
+            count: 1,
+        });
+        // Delays are clamped, instead of overflowing.
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(secs_from_now(&clock, &schedule), MAX_DELAY.as_secs());
+        assert_eq!(engines(&schedule), None);
+    }
+
+    #[test]
+    fn test_local_changes() {
+        let clock = FakeClock::new();
+        let scheduler = scheduler(&clock, None);
+        scheduler.sync_finished(&result(
+            ServiceStatus::Ok,
+            &["bookmarks", "history", "tabs"],
+            &[],
+        ));
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "bookmarks".into(),
+            count: 1,
+        });
+        clock.advance(30);
+        // More changes don't push the sync back.
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "bookmarks".into(),
+            count: 2,
+        });
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(secs_from_now(&clock, &schedule), 30);
+        assert_eq!(engines(&schedule), Some(vec!["bookmarks".to_string()]));
+
+        // Lots of changes sync right away.
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "history".into(),
+            count: 50,
+        });
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(schedule.at, clock.now());
+        assert_eq!(
+            engines(&schedule),
+            Some(vec!["bookmarks".to_string(), "history".to_string()])
+        );
+
+        // Backgrounding syncs the changed engines now.
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "history".into(),
+            count: 1,
+        });
+        scheduler.handle_event(SchedulerEvent::AppBackgrounded);
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(schedule.at, clock.now());
+        assert_eq!(schedule.reason, SyncReason::Backgrounded);
+        scheduler.sync_finished(&result(ServiceStatus::Ok, &["bookmarks", "history"], &[]));
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 3570);
+    }
+
+    #[test]
+    fn test_local_changes_during_sync() {
+        let clock = FakeClock::new();
+        let scheduler = scheduler(&clock, None);
+        scheduler.sync_finished(&result(
+            ServiceStatus::Ok,
+            &["bookmarks", "history", "tabs"],
+            &[],
+        ));
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "bookmarks".into(),
+            count: 3,
+        });
+        clock.advance(60);
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(schedule.at, clock.now());
+        // Two more changes while the sync is running...
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "bookmarks".into(),
+            count: 5,
+        });
+        scheduler.sync_finished(&result(ServiceStatus::Ok, &["bookmarks"], &[]));
+        // ...still need syncing.
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(secs_from_now(&clock, &schedule), 60);
+        assert_eq!(engines(&schedule), Some(vec!["bookmarks".to_string()]));
+        scheduler.sync_finished(&result(ServiceStatus::Ok, &["bookmarks"], &[]));
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(secs_from_now(&clock, &schedule), 3540);
+    }
+
+    #[test]
+    fn test_errors() {
+        let clock = FakeClock::new();
+        let scheduler = scheduler(&clock, None);
+        scheduler.sync_finished(&result(ServiceStatus::NetworkError, &[], &[]));
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 300);
+        scheduler.sync_finished(&result(ServiceStatus::ServiceError, &[], &[]));
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 600);
+        for _ in 0..10 {
+            scheduler.sync_finished(&result(ServiceStatus::ServiceError, &[], &[]));
+        }
+        assert_eq!(
+            secs_from_now(&clock, &scheduler.next_sync().unwrap()),
+            4 * 3600
+        );
+
+        // The user can always sync.
+        scheduler.handle_event(SchedulerEvent::SyncRequested {
+            reason: SyncReason::User,
+        });
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(schedule.at, clock.now());
+        assert_eq!(schedule.reason, SyncReason::User);
+
+        // A failed engine backs off on its own.
+        scheduler.sync_finished(&result(
+            ServiceStatus::Ok,
+            &["bookmarks", "tabs"],
+            &["history"],
+        ));
+        let schedule = scheduler.next_sync().unwrap();
+        assert_eq!(secs_from_now(&clock, &schedule), 300);
+        assert_eq!(engines(&schedule), Some(vec!["history".to_string()]));
+
+        // Cancelled syncs don't back off.
+        scheduler.sync_finished(&result(ServiceStatus::Cancelled, &[], &[]));
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 300);
+
+        // Auth errors stop scheduled syncs until something asks for one.
+        scheduler.sync_finished(&result(ServiceStatus::AuthError, &[], &[]));
+        assert!(scheduler.next_sync().is_none());
+        scheduler.handle_event(SchedulerEvent::SyncRequested {
+            reason: SyncReason::Startup,
+        });
+        assert_eq!(scheduler.next_sync().unwrap().reason, SyncReason::Startup);
+    }
+
+    #[test]
+    fn test_server_backoff() {
+        let clock = FakeClock::new();
+        let scheduler = scheduler(&clock, None);
+        let mut backed_off = result(ServiceStatus::BackedOff, &[], &[]);
+        backed_off.next_sync_allowed_at = Some(clock.now() + Duration::from_secs(1000));
+        scheduler.sync_finished(&backed_off);
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 1000);
+        scheduler.handle_event(SchedulerEvent::SyncRequested {
+            reason: SyncReason::Startup,
+        });
+        assert_eq!(secs_from_now(&clock, &scheduler.next_sync().unwrap()), 1000);
+        scheduler.handle_event(SchedulerEvent::SyncRequested {
+            reason: SyncReason::EnabledChange,
+        });
+        assert_eq!(scheduler.next_sync().unwrap().at, clock.now());
+    }
+
+    #[test]
+    fn test_persisted_state_fresh_install() {
+        let clock = FakeClock::new();
+        let scheduler = scheduler(&clock, None);
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "tabs".into(),
+            count: 1,
+        });
+        // Before the first sync, the state is still one that sync15 can use.
+        let persisted = scheduler.persisted_state().unwrap();
+        assert!(serde_json::from_str::<PersistedGlobalState>(&persisted).is_ok());
+
+        // And it's carried through the first sync.
+        let synced = preserve_scheduler_state(
+            Some(&persisted),
+            r#"{"schema_version":"V2","declined":[]}"#.into(),
+        );
+        assert!(serde_json::from_str::<PersistedGlobalState>(&synced).is_ok());
+        assert_eq!(
+            read_scheduler_state(&synced),
+            read_scheduler_state(&persisted)
+        );
+    }
+
+    #[test]
+    fn test_persisted_state() {
+        let clock = FakeClock::new();
+        let scheduler = scheduler(&clock, None);
+        scheduler.sync_finished(&result(
+            ServiceStatus::Ok,
+            &["bookmarks", "history", "tabs"],
+            &[],
+        ));
+        scheduler.handle_event(SchedulerEvent::LocalChanges {
+            engine: "tabs".into(),
+            count: 3,
+        });
+        let persisted = scheduler.persisted_state().unwrap();
+        // The sync15 state is still there.
+        let value: serde_json::Value = serde_json::from_str(&persisted).unwrap();
+        assert_eq!(value["schema_version"], "V2");
+
+        let restored = self::scheduler(&clock, Some(persisted.clone()));
+        let schedule = restored.next_sync().unwrap();
+        assert_eq!(secs_from_now(&clock, &schedule), 60);
+        assert_eq!(engines(&schedule), Some(vec!["tabs".to_string()]));
+
+        // Syncing keeps our state.
+        let synced = preserve_scheduler_state(
+            Some(&persisted),
+            r#"{"schema_version":"V2","declined":["passwords"]}"#.into(),
+        );
+        let value: serde_json::Value = serde_json::from_str(&synced).unwrap();
+        assert_eq!(value["declined"][0], "passwords");
+        assert_eq!(
+            read_scheduler_state(&synced),
+            read_scheduler_state(&persisted)
+        );
+
+        // Bad state is ignored.
+        assert_eq!(read_scheduler_state("not json"), SchedulerState::default());
+        assert!(serde_json::from_str::<PersistedGlobalState>(
+            &write_scheduler_state(Some("not json"), &SchedulerState::default()).unwrap()
+        )
+        .is_ok());
+        assert_eq!(
+            read_scheduler_state(r#"{"scheduler":"nope"}"#),
+            SchedulerState::default()
+        );
+    }
+}
@@ -28,7 +28,7 @@ pub struct SyncParams {
     pub device_settings: DeviceSettings,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SyncReason {
     Scheduled,
     User,
@@ -67,6 +67,8 @@ pub struct SyncResult {
     pub successful: Vec<String>,
     // Maps the names of engines that failed to sync to the reason why
     pub failures: HashMap<String, String>,
+    // Engines that were cancelled before they finished syncing
+    pub cancelled: Vec<String>,
     // State that should be persisted to disk and supplied to the sync method
     // on the next sync (See SyncParams.persisted_state).
     pub persisted_state: String,
@@ -81,6 +83,10 @@ pub struct SyncResult {
     pub next_sync_allowed_at: Option<SystemTime>,
     // JSON string encoding a `SyncTelemetryPing` object
     pub telemetry_json: Option<String>,
+    // Maps the names of engines to the number of conflicts they resolved.
+    // Engines that didn't resolve any are omitted. The engine's store has a
+    // merge log with the details.
+    pub conflicts_resolved: HashMap<String, u32>,
 }
 
 #[derive(Debug)]
@@ -90,6 +96,8 @@ pub enum ServiceStatus {
     ServiceError,
     AuthError,
     BackedOff,
+    /// The sync was cancelled, or the app is shutting down.
+    Cancelled,
     OtherError,
 }
 
@@ -11,7 +11,8 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock, Weak};
 use sync15::bso::{IncomingBso, OutgoingBso, OutgoingEnvelope};
 use sync15::engine::{
-    CollSyncIds, CollectionRequest, EngineSyncAssociation, SyncEngine, SyncEngineId,
+    CollSyncIds, CollectionRequest, EngineSyncAssociation, OutgoingRecords, SyncEngine,
+    SyncEngineId,
 };
 use sync15::{telemetry, ClientData, CollectionName, DeviceType, RemoteClient, ServerTimestamp};
 use sync_guid::Guid;
@@ -192,11 +193,11 @@ impl SyncEngine for TabsEngine {
         Ok(())
     }
 
-    fn apply(
+    fn apply_chunked(
         &self,
         timestamp: ServerTimestamp,
         _telem: &mut telemetry::Engine,
-    ) -> Result<Vec<OutgoingBso>> {
+    ) -> Result<OutgoingRecords<'_>> {
         // We've already applied them - really we just need to fetch outgoing.
         let (local_tabs, remote_clients) = {
             let mut storage = self.store.storage.lock().unwrap();
@@ -241,7 +242,7 @@ impl SyncEngine for TabsEngine {
         } else {
             vec![]
         };
-        Ok(outgoing)
+        Ok(outgoing.into())
     }
 
     fn set_uploaded(&self, new_timestamp: ServerTimestamp, ids: Vec<Guid>) -> Result<()> {
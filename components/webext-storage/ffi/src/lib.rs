@@ -5,7 +5,7 @@
 use std::os::raw::c_char;
 
 use ffi_support::{define_handle_map_deleter, ConcurrentHandleMap, ExternError, FfiStr};
-use webext_storage::{error, store::Store};
+use webext_storage::{error::ApiResult, store::Store};
 
 lazy_static::lazy_static! {
     static ref STORES: ConcurrentHandleMap<Store> = ConcurrentHandleMap::new();
@@ -14,7 +14,7 @@ lazy_static::lazy_static! {
 #[no_mangle]
 pub extern "C" fn webext_store_new(db_path: FfiStr<'_>, error: &mut ExternError) -> u64 {
     log::debug!("webext_store_new");
-    STORES.insert_with_result(error, || -> error::Result<Store> {
+    STORES.insert_with_result(error, || -> ApiResult<Store> {
         let path = db_path.as_str();
         Store::new(path)
     })
@@ -28,7 +28,7 @@ pub extern "C" fn webext_store_set(
     error: &mut ExternError,
 ) -> *mut c_char {
     log::debug!("webext_store_set");
-    STORES.call_with_result(error, handle, |store| -> error::Result<_> {
+    STORES.call_with_result(error, handle, |store| -> ApiResult<_> {
         let val = serde_json::from_str(json.as_str())?;
         let changes = store.set(ext_id.as_str(), val)?;
         Ok(serde_json::to_string(&changes)?)
@@ -43,7 +43,7 @@ pub extern "C" fn webext_store_get(
     error: &mut ExternError,
 ) -> *mut c_char {
     log::debug!("webext_store_get");
-    STORES.call_with_result(error, handle, |store| -> error::Result<_> {
+    STORES.call_with_result(error, handle, |store| -> ApiResult<_> {
         let keys = serde_json::from_str(keys.as_str())?;
         let val = store.get(ext_id.as_str(), keys)?;
         Ok(serde_json::to_string(&val)?)
@@ -58,7 +58,7 @@ pub extern "C" fn webext_store_remove(
     error: &mut ExternError,
 ) -> *mut c_char {
     log::debug!("webext_store_remove");
-    STORES.call_with_result(error, handle, |store| -> error::Result<_> {
+    STORES.call_with_result(error, handle, |store| -> ApiResult<_> {
         let keys = serde_json::from_str(keys.as_str())?;
         let changes = store.remove(ext_id.as_str(), keys)?;
         Ok(serde_json::to_string(&changes)?)
@@ -72,7 +72,7 @@ pub extern "C" fn webext_store_clear(
     error: &mut ExternError,
 ) -> *mut c_char {
     log::debug!("webext_store_clear");
-    STORES.call_with_result(error, handle, |store| -> error::Result<_> {
+    STORES.call_with_result(error, handle, |store| -> ApiResult<_> {
         let changes = store.clear(ext_id.as_str())?;
         Ok(serde_json::to_string(&changes)?)
     })
@@ -2,10 +2,11 @@
 -- License, v. 2.0. If a copy of the MPL was not distributed with this
 -- file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
--- This is a very simple schema for a chrome.storage.* implementation. At time
--- of writing, only chrome.storage.sync is supported, but this can be trivially
--- enhanced to support chrome.storage.local (the api is identical, it's just a
--- different "bucket" and doesn't sync).
+-- This is a very simple schema for a chrome.storage.* implementation. Both
+-- chrome.storage.sync and chrome.storage.local are stored here - the api is
+-- identical, it's just a different "bucket" and .local doesn't sync.
+-- chrome.storage.session lives in a temp table, created as the connection is
+-- opened.
 --
 -- Even though the spec allows for a single extension to have any number of
 -- "keys", we've made the decision to store all keys for a given extension in a
@@ -47,6 +48,15 @@ CREATE TABLE IF NOT EXISTS storage_sync_mirror (
     CHECK((ext_id IS NULL AND data IS NULL) OR (ext_id IS NOT NULL AND data IS NOT NULL))
 );
 
+-- chrome.storage.local data. This never syncs, so there are no tombstones or
+-- change counters.
+CREATE TABLE IF NOT EXISTS storage_local_data (
+    ext_id TEXT NOT NULL PRIMARY KEY,
+
+    /* The JSON payload. */
+    data TEXT NOT NULL
+);
+
 -- This table holds key-value metadata - primarily for sync.
 CREATE TABLE IF NOT EXISTS meta (
     key TEXT PRIMARY KEY,
@@ -12,28 +12,98 @@ use sql_support::{self, ConnExt};
 // These constants are defined by the chrome.storage.sync spec. We export them
 // publicly from this module, then from the crate, so they wind up in the
 // clients.
-// Note the limits for `chrome.storage.sync` and `chrome.storage.local` are
-// different, and these are from `.sync` - we'll have work to do if we end up
-// wanting this to be used for `.local` too!
 pub const SYNC_QUOTA_BYTES: usize = 102_400;
 pub const SYNC_QUOTA_BYTES_PER_ITEM: usize = 8_192;
 pub const SYNC_MAX_ITEMS: usize = 512;
 // Note there are also constants for "operations per minute" etc, which aren't
 // enforced here.
 
+// `chrome.storage.local` and `chrome.storage.session` only limit the total
+// number of bytes each extension stores.
+pub const LOCAL_QUOTA_BYTES: usize = 10_485_760;
+pub const SESSION_QUOTA_BYTES: usize = 10_485_760;
+
 type JsonMap = Map<String, JsonValue>;
 
+/// The `chrome.storage` areas an extension can store data in. They share the
+/// same API, but each area has its own data and quotas, and only `Sync` is
+/// synced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageArea {
+    /// `storage.sync`: synced between the user's devices, with small quotas.
+    Sync,
+    /// `storage.local`: stored on this device only, with a large quota.
+    Local,
+    /// `storage.session`: held in memory, and lost when the store is closed.
+    Session,
+}
+
+/// The limits an area puts on the data each extension stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageQuota {
+    /// The most bytes all of an extension's items can use.
+    pub bytes: usize,
+    /// The most bytes a single item can use, if the area limits them.
+    pub bytes_per_item: Option<usize>,
+    /// The most items an extension can store, if the area limits them.
+    pub max_items: Option<usize>,
+}
+
+impl StorageArea {
+    /// The area's name, as passed to `storage.onChanged` listeners.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageArea::Sync => "sync",
+            StorageArea::Local => "local",
+            StorageArea::Session => "session",
+        }
+    }
+
+    pub fn quota(&self) -> StorageQuota {
+        match self {
+            StorageArea::Sync => StorageQuota {
+                bytes: SYNC_QUOTA_BYTES,
+                bytes_per_item: Some(SYNC_QUOTA_BYTES_PER_ITEM),
+                max_items: Some(SYNC_MAX_ITEMS),
+            },
+            StorageArea::Local => StorageQuota {
+                bytes: LOCAL_QUOTA_BYTES,
+                bytes_per_item: None,
+                max_items: None,
+            },
+            StorageArea::Session => StorageQuota {
+                bytes: SESSION_QUOTA_BYTES,
+                bytes_per_item: None,
+                max_items: None,
+            },
+        }
+    }
+
+    // The table holding the area's data. The session area's table is a temp
+    // table, so it only lives as long as the connection.
+    fn table(&self) -> &'static str {
+        match self {
+            StorageArea::Sync => "storage_sync_data",
+            StorageArea::Local => "storage_local_data",
+            StorageArea::Session => "temp.storage_session_data",
+        }
+    }
+}
+
 enum StorageChangeOp {
     Clear,
     Set(JsonValue),
     SetWithoutQuota(JsonValue),
 }
 
-fn get_from_db(conn: &Connection, ext_id: &str) -> Result<Option<JsonMap>> {
+fn get_from_db(conn: &Connection, area: StorageArea, ext_id: &str) -> Result<Option<JsonMap>> {
     Ok(
         match conn.try_query_one::<String, _>(
-            "SELECT data FROM storage_sync_data
-             WHERE ext_id = :ext_id",
+            &format!(
+                "SELECT data FROM {}
+                 WHERE ext_id = :ext_id",
+                area.table()
+            ),
             &[(":ext_id", &ext_id)],
             true,
         )? {
@@ -48,7 +118,12 @@ fn get_from_db(conn: &Connection, ext_id: &str) -> Result<Option<JsonMap>> {
     )
 }
 
-fn save_to_db(tx: &Transaction<'_>, ext_id: &str, val: &StorageChangeOp) -> Result<()> {
+fn save_to_db(
+    tx: &Transaction<'_>,
+    area: StorageArea,
+    ext_id: &str,
+    val: &StorageChangeOp,
+) -> Result<()> {
     // This function also handles removals. Either an empty map or explicit null
     // is a removal. If there's a mirror record for this extension ID, then we
     // must leave a tombstone behind for syncing.
@@ -58,6 +133,11 @@ fn save_to_db(tx: &Transaction<'_>, ext_id: &str, val: &StorageChangeOp) -> Resu
         StorageChangeOp::SetWithoutQuota(JsonValue::Object(v)) => v.is_empty(),
         _ => false,
     };
+    if area != StorageArea::Sync {
+        // The other areas don't sync, so don't need tombstones or change
+        // counters.
+        return save_unsynced_to_db(tx, area, ext_id, val, is_delete);
+    }
     if is_delete {
         let in_mirror = tx
             .try_query_one(
@@ -91,19 +171,7 @@ fn save_to_db(tx: &Transaction<'_>, ext_id: &str, val: &StorageChangeOp) -> Resu
             )?;
         }
     } else {
-        // Convert to bytes so we can enforce the quota if necessary.
-        let sval = match val {
-            StorageChangeOp::Set(v) => {
-                let sv = v.to_string();
-                if sv.len() > SYNC_QUOTA_BYTES {
-                    return Err(ErrorKind::QuotaError(QuotaReason::TotalBytes).into());
-                }
-                sv
-            }
-            StorageChangeOp::SetWithoutQuota(v) => v.to_string(),
-            StorageChangeOp::Clear => unreachable!(),
-        };
-
+        let sval = to_quota_checked_string(area, val)?;
         log::trace!("saving data for '{}': writing", ext_id);
         tx.execute_cached(
             "INSERT INTO storage_sync_data(ext_id, data, sync_change_counter)
@@ -119,8 +187,62 @@ fn save_to_db(tx: &Transaction<'_>, ext_id: &str, val: &StorageChangeOp) -> Resu
     Ok(())
 }
 
-fn remove_from_db(tx: &Transaction<'_>, ext_id: &str) -> Result<()> {
-    save_to_db(tx, ext_id, &StorageChangeOp::Clear)
+fn save_unsynced_to_db(
+    tx: &Transaction<'_>,
+    area: StorageArea,
+    ext_id: &str,
+    val: &StorageChangeOp,
+    is_delete: bool,
+) -> Result<()> {
+    if is_delete {
+        log::trace!(
+            "saving {} data for '{}': removing the row",
+            area.as_str(),
+            ext_id
+        );
+        tx.execute_cached(
+            &format!("DELETE FROM {} WHERE ext_id = :ext_id", area.table()),
+            rusqlite::named_params! {
+                ":ext_id": ext_id,
+            },
+        )?;
+    } else {
+        let sval = to_quota_checked_string(area, val)?;
+        log::trace!("saving {} data for '{}': writing", area.as_str(), ext_id);
+        tx.execute_cached(
+            &format!(
+                "INSERT INTO {}(ext_id, data)
+                    VALUES (:ext_id, :data)
+                    ON CONFLICT (ext_id) DO UPDATE
+                    SET data = :data",
+                area.table()
+            ),
+            rusqlite::named_params! {
+                ":ext_id": ext_id,
+                ":data": &sval,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+// Convert to bytes so we can enforce the area's total quota if necessary.
+fn to_quota_checked_string(area: StorageArea, val: &StorageChangeOp) -> Result<String> {
+    Ok(match val {
+        StorageChangeOp::Set(v) => {
+            let sv = v.to_string();
+            if sv.len() > area.quota().bytes {
+                return Err(ErrorKind::QuotaError(QuotaReason::TotalBytes).into());
+            }
+            sv
+        }
+        StorageChangeOp::SetWithoutQuota(v) => v.to_string(),
+        StorageChangeOp::Clear => unreachable!(),
+    })
+}
+
+fn remove_from_db(tx: &Transaction<'_>, area: StorageArea, ext_id: &str) -> Result<()> {
+    save_to_db(tx, area, ext_id, &StorageChangeOp::Clear)
 }
 
 // This is a "helper struct" for the callback part of the chrome.storage spec,
@@ -142,7 +264,7 @@ pub struct StorageValueChange {
 // be a plain vec
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct StorageChanges {
-    changes: Vec<StorageValueChange>,
+    pub changes: Vec<StorageValueChange>,
 }
 
 impl StorageChanges {
@@ -191,25 +313,39 @@ pub fn get_quota_size_of(key: &str, v: &JsonValue) -> usize {
 /// StorageChanges defined by the chrome API - it's assumed the caller will
 /// arrange to deliver this to observers as defined in that API.
 pub fn set(tx: &Transaction<'_>, ext_id: &str, val: JsonValue) -> Result<StorageChanges> {
+    set_in_area(tx, StorageArea::Sync, ext_id, val)
+}
+
+/// Like `set`, but for the given storage area.
+pub fn set_in_area(
+    tx: &Transaction<'_>,
+    area: StorageArea,
+    ext_id: &str,
+    val: JsonValue,
+) -> Result<StorageChanges> {
     let val_map = match val {
         JsonValue::Object(m) => m,
         // Not clear what the error semantics should be yet. For now, pretend an empty map.
         _ => Map::new(),
     };
 
-    let mut current = get_from_db(tx, ext_id)?.unwrap_or_default();
+    let mut current = get_from_db(tx, area, ext_id)?.unwrap_or_default();
 
+    let quota = area.quota();
     let mut changes = StorageChanges::with_capacity(val_map.len());
 
     // iterate over the value we are adding/updating.
     for (k, v) in val_map.into_iter() {
         let old_value = current.remove(&k);
-        if current.len() >= SYNC_MAX_ITEMS {
+        if quota.max_items.map_or(false, |max| current.len() >= max) {
             return Err(ErrorKind::QuotaError(QuotaReason::MaxItems).into());
         }
         // Reading the chrome docs literally re the quota, the length of the key
         // is just the string len, but the value is the json val, as bytes
-        if get_quota_size_of(&k, &v) > SYNC_QUOTA_BYTES_PER_ITEM {
+        if quota
+            .bytes_per_item
+            .map_or(false, |max| get_quota_size_of(&k, &v) > max)
+        {
             return Err(ErrorKind::QuotaError(QuotaReason::ItemBytes).into());
         }
         let change = StorageValueChange {
@@ -223,6 +359,7 @@ pub fn set(tx: &Transaction<'_>, ext_id: &str, val: JsonValue) -> Result<Storage
 
     save_to_db(
         tx,
+        area,
         ext_id,
         &StorageChangeOp::Set(JsonValue::Object(current)),
     )?;
@@ -250,8 +387,18 @@ fn get_keys(keys: JsonValue) -> Vec<(String, Option<JsonValue>)> {
 /// The implementation of `storage[.sync].get()` - on success this always
 /// returns a Json object.
 pub fn get(conn: &Connection, ext_id: &str, keys: JsonValue) -> Result<JsonValue> {
+    get_in_area(conn, StorageArea::Sync, ext_id, keys)
+}
+
+/// Like `get`, but for the given storage area.
+pub fn get_in_area(
+    conn: &Connection,
+    area: StorageArea,
+    ext_id: &str,
+    keys: JsonValue,
+) -> Result<JsonValue> {
     // key is optional, or string or array of string or object keys
-    let maybe_existing = get_from_db(conn, ext_id)?;
+    let maybe_existing = get_from_db(conn, area, ext_id)?;
     let mut existing = match (maybe_existing, keys.is_object()) {
         (None, true) => return Ok(keys),
         (None, false) => return Ok(JsonValue::Object(Map::new())),
@@ -280,7 +427,17 @@ pub fn get(conn: &Connection, ext_id: &str, keys: JsonValue) -> Result<JsonValue
 /// StorageChanges defined by the chrome API - it's assumed the caller will
 /// arrange to deliver this to observers as defined in that API.
 pub fn remove(tx: &Transaction<'_>, ext_id: &str, keys: JsonValue) -> Result<StorageChanges> {
-    let mut existing = match get_from_db(tx, ext_id)? {
+    remove_in_area(tx, StorageArea::Sync, ext_id, keys)
+}
+
+/// Like `remove`, but for the given storage area.
+pub fn remove_in_area(
+    tx: &Transaction<'_>,
+    area: StorageArea,
+    ext_id: &str,
+    keys: JsonValue,
+) -> Result<StorageChanges> {
+    let mut existing = match get_from_db(tx, area, ext_id)? {
         None => return Ok(StorageChanges::new()),
         Some(v) => v,
     };
@@ -302,6 +459,7 @@ pub fn remove(tx: &Transaction<'_>, ext_id: &str, keys: JsonValue) -> Result<Sto
     if !result.is_empty() {
         save_to_db(
             tx,
+            area,
             ext_id,
             &StorageChangeOp::SetWithoutQuota(JsonValue::Object(existing)),
         )?;
@@ -313,7 +471,16 @@ pub fn remove(tx: &Transaction<'_>, ext_id: &str, keys: JsonValue) -> Result<Sto
 /// StorageChanges defined by the chrome API - it's assumed the caller will
 /// arrange to deliver this to observers as defined in that API.
 pub fn clear(tx: &Transaction<'_>, ext_id: &str) -> Result<StorageChanges> {
-    let existing = match get_from_db(tx, ext_id)? {
+    clear_in_area(tx, StorageArea::Sync, ext_id)
+}
+
+/// Like `clear`, but for the given storage area.
+pub fn clear_in_area(
+    tx: &Transaction<'_>,
+    area: StorageArea,
+    ext_id: &str,
+) -> Result<StorageChanges> {
+    let existing = match get_from_db(tx, area, ext_id)? {
         None => return Ok(StorageChanges::new()),
         Some(v) => v,
     };
@@ -325,13 +492,23 @@ pub fn clear(tx: &Transaction<'_>, ext_id: &str) -> Result<StorageChanges> {
             old_value: Some(val),
         });
     }
-    remove_from_db(tx, ext_id)?;
+    remove_from_db(tx, area, ext_id)?;
     Ok(result)
 }
 
 /// The implementation of `storage[.sync].getBytesInUse()`.
 pub fn get_bytes_in_use(conn: &Connection, ext_id: &str, keys: JsonValue) -> Result<usize> {
-    let maybe_existing = get_from_db(conn, ext_id)?;
+    get_bytes_in_use_in_area(conn, StorageArea::Sync, ext_id, keys)
+}
+
+/// Like `get_bytes_in_use`, but for the given storage area.
+pub fn get_bytes_in_use_in_area(
+    conn: &Connection,
+    area: StorageArea,
+    ext_id: &str,
+    keys: JsonValue,
+) -> Result<usize> {
+    let maybe_existing = get_from_db(conn, area, ext_id)?;
     let existing = match maybe_existing {
         None => return Ok(0),
         Some(v) => v,
@@ -360,13 +537,13 @@ pub struct UsageInfo {
     /// The extension id.
     pub ext_id: String,
     /// The number of keys the extension uses.
-    pub num_keys: usize,
+    pub num_keys: u64,
     /// The number of bytes used by the extension. This result is somewhat rough
     /// -- it doesn't bother counting the size of the extension ID, or data in
     /// the mirror, and favors returning the exact number of bytes used by the
     /// column (that is, the size of the JSON object) rather than replicating
     /// the `get_bytes_in_use` return value for all keys.
-    pub num_bytes: usize,
+    pub num_bytes: u64,
 }
 
 /// Exposes information about per-collection usage for the purpose of telemetry.
@@ -383,8 +560,8 @@ pub fn usage(db: &Connection) -> Result<Vec<UsageInfo>> {
     db.query_rows_into(sql, [], |row| {
         let ext_id: String = row.get("ext_id")?;
         let data: String = row.get("data")?;
-        let num_bytes = data.len();
-        let num_keys = serde_json::from_str::<JsonObject>(&data)?.len();
+        let num_bytes = data.len() as u64;
+        let num_keys = serde_json::from_str::<JsonObject>(&data)?.len() as u64;
         Ok(UsageInfo {
             ext_id,
             num_keys,
@@ -653,6 +830,7 @@ mod tests {
         // Init an over quota db with a single key.
         save_to_db(
             &tx,
+            StorageArea::Sync,
             ext_id,
             &StorageChangeOp::SetWithoutQuota(json!({ "x": val })),
         )?;
@@ -670,6 +848,7 @@ mod tests {
         // Restore the over quota data.
         save_to_db(
             &tx,
+            StorageArea::Sync,
             ext_id,
             &StorageChangeOp::SetWithoutQuota(json!({ "y": val })),
         )?;
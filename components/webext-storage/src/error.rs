@@ -2,15 +2,32 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use error_support::{ErrorHandling, GetErrorHandling};
 use interrupt_support::Interrupted;
 
-#[derive(Debug)]
+/// Result enum for the public interface
+pub type ApiResult<T> = std::result::Result<T, WebExtStorageApiError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum QuotaReason {
     TotalBytes,
     ItemBytes,
     MaxItems,
 }
 
+// Errors we return via the public interface.
+#[derive(Debug, thiserror::Error)]
+pub enum WebExtStorageApiError {
+    #[error("Unexpected webext-storage error: {reason}")]
+    UnexpectedError { reason: String },
+
+    #[error("Error parsing JSON data: {reason}")]
+    JsonError { reason: String },
+
+    #[error("Quota exceeded: {reason:?}")]
+    QuotaError { reason: QuotaReason },
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ErrorKind {
     #[error("Quota exceeded: {0:?}")]
@@ -70,3 +87,60 @@ error_support::define_error! {
         (OpenDatabaseError, sql_support::open_database::Error),
     }
 }
+
+// Define how our internal errors are handled and converted to external errors
+// See `support/error/README.md` for how this works, especially the warning about PII.
+impl GetErrorHandling for Error {
+    type ExternalError = WebExtStorageApiError;
+
+    fn get_error_handling(&self) -> ErrorHandling<Self::ExternalError> {
+        match self.kind() {
+            ErrorKind::QuotaError(reason) => {
+                ErrorHandling::convert(WebExtStorageApiError::QuotaError { reason: *reason })
+                    .log_info()
+            }
+            ErrorKind::JsonError(e) => ErrorHandling::convert(WebExtStorageApiError::JsonError {
+                reason: e.to_string(),
+            })
+            .log_warning(),
+            ErrorKind::InterruptedError(e) => {
+                ErrorHandling::convert(WebExtStorageApiError::UnexpectedError {
+                    reason: e.to_string(),
+                })
+                .log_info()
+            }
+            ErrorKind::SqlError(e) => {
+                ErrorHandling::convert(WebExtStorageApiError::UnexpectedError {
+                    reason: e.to_string(),
+                })
+                .report_error("webext-storage-sql-error")
+            }
+            ErrorKind::OpenDatabaseError(e) => {
+                ErrorHandling::convert(WebExtStorageApiError::UnexpectedError {
+                    reason: e.to_string(),
+                })
+                .report_error("webext-storage-open-database-error")
+            }
+            _ => ErrorHandling::convert(WebExtStorageApiError::UnexpectedError {
+                reason: self.to_string(),
+            })
+            .report_error("webext-storage-unexpected-error"),
+        }
+    }
+}
+
+impl From<anyhow::Error> for WebExtStorageApiError {
+    fn from(value: anyhow::Error) -> Self {
+        WebExtStorageApiError::UnexpectedError {
+            reason: value.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for WebExtStorageApiError {
+    fn from(value: serde_json::Error) -> Self {
+        WebExtStorageApiError::JsonError {
+            reason: value.to_string(),
+        }
+    }
+}
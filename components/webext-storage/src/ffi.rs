@@ -9,7 +9,7 @@
 /// a trait in `ffi_support` for a type in `webext_storage`).
 use ffi_support::{ErrorCode, ExternError};
 
-use crate::error::{Error, ErrorKind, QuotaReason};
+use crate::error::{Error, ErrorKind, QuotaReason, WebExtStorageApiError};
 
 mod error_codes {
     /// An unexpected error occurred which likely cannot be meaningfully handled
@@ -46,3 +46,22 @@ impl From<Error> for ExternError {
         ExternError::new_error(code, err.to_string())
     }
 }
+
+impl From<WebExtStorageApiError> for ExternError {
+    fn from(err: WebExtStorageApiError) -> ExternError {
+        let code = ErrorCode::new(match &err {
+            WebExtStorageApiError::JsonError { .. } => error_codes::INVALID_JSON,
+            WebExtStorageApiError::QuotaError {
+                reason: QuotaReason::TotalBytes,
+            } => error_codes::QUOTA_TOTAL_BYTES_EXCEEDED,
+            WebExtStorageApiError::QuotaError {
+                reason: QuotaReason::ItemBytes,
+            } => error_codes::QUOTA_ITEM_BYTES_EXCEEDED,
+            WebExtStorageApiError::QuotaError {
+                reason: QuotaReason::MaxItems,
+            } => error_codes::QUOTA_MAX_ITEMS_EXCEEDED,
+            WebExtStorageApiError::UnexpectedError { .. } => error_codes::UNEXPECTED,
+        });
+        ExternError::new_error(code, err.to_string())
+    }
+}
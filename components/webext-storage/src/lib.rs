@@ -14,6 +14,8 @@ mod schema;
 pub mod store;
 mod sync;
 
+uniffi::include_scaffolding!("webextstorage");
+
 pub use migration::MigrationInfo;
 
 // We publish some constants from non-public modules.
@@ -23,4 +25,34 @@ pub use api::SYNC_MAX_ITEMS;
 pub use api::SYNC_QUOTA_BYTES;
 pub use api::SYNC_QUOTA_BYTES_PER_ITEM;
 
+pub use api::LOCAL_QUOTA_BYTES;
+pub use api::SESSION_QUOTA_BYTES;
+
+pub use api::StorageArea;
+pub use api::StorageQuota;
 pub use api::UsageInfo;
+
+pub use crate::sync::engine::get_registered_sync_engine;
+
+// Our UDL uses types from these modules directly.
+use crate::api::{StorageChanges, StorageValueChange};
+use crate::error::{QuotaReason, WebExtStorageApiError};
+use crate::sync::SyncedExtensionChange;
+
+// `Store` is too generic a name for the Swift bindings, where all our
+// components share a module.
+type WebExtStorageStore = crate::store::Store;
+
+// Our UDL passes JSON values across the FFI as strings.
+use serde_json::Value as JsonValue;
+impl UniffiCustomTypeConverter for JsonValue {
+    type Builtin = String;
+
+    fn into_custom(val: Self::Builtin) -> uniffi::Result<JsonValue> {
+        Ok(serde_json::from_str(&val)?)
+    }
+
+    fn from_custom(obj: Self) -> Self::Builtin {
+        obj.to_string()
+    }
+}
@@ -12,15 +12,26 @@ use sql_support::open_database::{
 const CREATE_SCHEMA_SQL: &str = include_str!("../sql/create_schema.sql");
 const CREATE_SYNC_TEMP_TABLES_SQL: &str = include_str!("../sql/create_sync_temp_tables.sql");
 
+// chrome.storage.session data is only kept in memory, so it lives in a temp
+// table, which goes away when the connection is closed. `temp_store = 2`
+// (MEMORY) keeps temp tables in memory, instead of in a temp file on disk.
+const CREATE_SESSION_TABLE_SQL: &str = "
+    CREATE TEMP TABLE IF NOT EXISTS storage_session_data (
+        ext_id TEXT NOT NULL PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+";
+
 pub struct WebExtMigrationLogin;
 
 impl MigrationLogic for WebExtMigrationLogin {
     const NAME: &'static str = "webext storage db";
-    const END_VERSION: u32 = 2;
+    const END_VERSION: u32 = 3;
 
     fn prepare(&self, conn: &Connection, _db_empty: bool) -> MigrationResult<()> {
         let initial_pragmas = "
-            -- We don't care about temp tables being persisted to disk.
+            -- Keep temp tables, including storage.session data, in memory
+            -- instead of persisting them to disk.
             PRAGMA temp_store = 2;
             -- we unconditionally want write-ahead-logging mode
             PRAGMA journal_mode=WAL;
@@ -28,6 +39,7 @@ impl MigrationLogic for WebExtMigrationLogin {
             PRAGMA foreign_keys = ON;
         ";
         conn.execute_batch(initial_pragmas)?;
+        conn.execute_batch(CREATE_SESSION_TABLE_SQL)?;
         define_functions(conn)?;
         conn.set_prepared_statement_cache_capacity(128);
         Ok(())
@@ -42,6 +54,7 @@ impl MigrationLogic for WebExtMigrationLogin {
     fn upgrade_from(&self, db: &Transaction<'_>, version: u32) -> MigrationResult<()> {
         match version {
             1 => upgrade_from_1(db),
+            2 => upgrade_from_2(db),
             _ => Err(MigrationError::IncompatibleVersion(version)),
         }
     }
@@ -72,6 +85,13 @@ fn upgrade_from_1(db: &Connection) -> MigrationResult<()> {
     Ok(())
 }
 
+fn upgrade_from_2(db: &Connection) -> MigrationResult<()> {
+    // Add the storage.local table. Re-running the full schema only creates
+    // the tables that don't exist yet.
+    db.execute_batch(CREATE_SCHEMA_SQL)?;
+    Ok(())
+}
+
 // Note that we expect this to be called before and after a sync - before to
 // ensure we are syncing with a clean state, after to be good memory citizens
 // given the temp tables are in memory.
@@ -98,6 +118,15 @@ mod tests {
             .expect("should allow running twice");
     }
 
+    #[test]
+    fn test_session_data_in_memory() {
+        let db = new_mem_db();
+        let temp_store: i64 = db
+            .query_row("PRAGMA temp_store", [], |row| row.get(0))
+            .expect("should get temp_store");
+        assert_eq!(temp_store, 2);
+    }
+
     #[test]
     fn test_create_empty_sync_temp_tables_twice() {
         let db = new_mem_db();
@@ -174,4 +203,20 @@ mod tests {
         )?;
         Ok(())
     }
+
+    #[test]
+    fn test_upgrade_3() -> Result<()> {
+        let _ = env_logger::try_init();
+
+        let db_file = MigratedDatabaseFile::new(WebExtMigrationLogin, CREATE_SCHEMA_V1_SQL);
+        db_file.upgrade_to(3);
+        let db = db_file.open();
+
+        // Should be able to store storage.local data.
+        db.execute_batch(
+            "INSERT INTO storage_local_data(ext_id, data)
+             VALUES ('ext-id', '{}');",
+        )?;
+        Ok(())
+    }
 }
@@ -2,11 +2,12 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use crate::api::{self, StorageChanges};
+use crate::api::{self, StorageArea, StorageChanges};
 use crate::db::{StorageDb, ThreadSafeStorageDb};
 use crate::error::*;
 use crate::migration::{migrate, MigrationInfo};
 use crate::sync;
+use error_support::handle_error;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -18,6 +19,9 @@ use serde_json::Value as JsonValue;
 /// items scoped to an extension ID. Each item is a JSON object, with one or
 /// more string keys, and values of any type that can serialize to JSON.
 ///
+/// The store also holds `storage.local` and `storage.session` data, through
+/// the `*_in_area` methods. These areas have their own quotas, and don't sync.
+///
 /// An application should create only one store, and manage the instance as a
 /// singleton. While this isn't enforced, if you make multiple stores pointing
 /// to the same database file, you are going to have a bad time: each store will
@@ -29,13 +33,14 @@ use serde_json::Value as JsonValue;
 /// connection with our sync engines - ie, these engines also hold an Arc<>
 /// around the same object.
 pub struct Store {
-    db: Arc<ThreadSafeStorageDb>,
+    pub(crate) db: Arc<ThreadSafeStorageDb>,
 }
 
 impl Store {
     /// Creates a store backed by a database at `db_path`. The path can be a
     /// file path or `file:` URI.
-    pub fn new(db_path: impl AsRef<Path>) -> Result<Self> {
+    #[handle_error(Error)]
+    pub fn new(db_path: impl AsRef<Path>) -> ApiResult<Self> {
         let db = StorageDb::new(db_path)?;
         Ok(Self {
             db: Arc::new(ThreadSafeStorageDb::new(db)),
@@ -58,7 +63,8 @@ impl Store {
 
     /// Sets one or more JSON key-value pairs for an extension ID. Returns a
     /// list of changes, with existing and new values for each key in `val`.
-    pub fn set(&self, ext_id: &str, val: JsonValue) -> Result<StorageChanges> {
+    #[handle_error(Error)]
+    pub fn set(&self, ext_id: &str, val: JsonValue) -> ApiResult<StorageChanges> {
         let db = self.db.lock();
         let tx = db.unchecked_transaction()?;
         let result = api::set(&tx, ext_id, val)?;
@@ -66,8 +72,25 @@ impl Store {
         Ok(result)
     }
 
+    /// Like `set`, but for the given storage area. The changes should be
+    /// delivered to `storage.onChanged` listeners, along with the area's name.
+    #[handle_error(Error)]
+    pub fn set_in_area(
+        &self,
+        area: StorageArea,
+        ext_id: &str,
+        val: JsonValue,
+    ) -> ApiResult<StorageChanges> {
+        let db = self.db.lock();
+        let tx = db.unchecked_transaction()?;
+        let result = api::set_in_area(&tx, area, ext_id, val)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
     /// Returns information about per-extension usage
-    pub fn usage(&self) -> Result<Vec<crate::UsageInfo>> {
+    #[handle_error(Error)]
+    pub fn usage(&self) -> ApiResult<Vec<crate::UsageInfo>> {
         let db = self.db.lock();
         api::usage(&db)
     }
@@ -88,17 +111,32 @@ impl Store {
     ///
     /// This method always returns an object (that is, a
     /// `serde_json::Value::Object`).
-    pub fn get(&self, ext_id: &str, keys: JsonValue) -> Result<JsonValue> {
+    #[handle_error(Error)]
+    pub fn get(&self, ext_id: &str, keys: JsonValue) -> ApiResult<JsonValue> {
         // Don't care about transactions here.
         let db = self.db.lock();
         api::get(&db, ext_id, keys)
     }
 
+    /// Like `get`, but for the given storage area.
+    #[handle_error(Error)]
+    pub fn get_in_area(
+        &self,
+        area: StorageArea,
+        ext_id: &str,
+        keys: JsonValue,
+    ) -> ApiResult<JsonValue> {
+        // Don't care about transactions here.
+        let db = self.db.lock();
+        api::get_in_area(&db, area, ext_id, keys)
+    }
+
     /// Deletes the values for one or more keys. As with `get`, `keys` can be
     /// either a single string key, or an array of string keys. Returns a list
     /// of changes, where each change contains the old value for each deleted
     /// key.
-    pub fn remove(&self, ext_id: &str, keys: JsonValue) -> Result<StorageChanges> {
+    #[handle_error(Error)]
+    pub fn remove(&self, ext_id: &str, keys: JsonValue) -> ApiResult<StorageChanges> {
         let db = self.db.lock();
         let tx = db.unchecked_transaction()?;
         let result = api::remove(&tx, ext_id, keys)?;
@@ -106,10 +144,26 @@ impl Store {
         Ok(result)
     }
 
+    /// Like `remove`, but for the given storage area.
+    #[handle_error(Error)]
+    pub fn remove_in_area(
+        &self,
+        area: StorageArea,
+        ext_id: &str,
+        keys: JsonValue,
+    ) -> ApiResult<StorageChanges> {
+        let db = self.db.lock();
+        let tx = db.unchecked_transaction()?;
+        let result = api::remove_in_area(&tx, area, ext_id, keys)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
     /// Deletes all key-value pairs for the extension. As with `remove`, returns
     /// a list of changes, where each change contains the old value for each
     /// deleted key.
-    pub fn clear(&self, ext_id: &str) -> Result<StorageChanges> {
+    #[handle_error(Error)]
+    pub fn clear(&self, ext_id: &str) -> ApiResult<StorageChanges> {
         let db = self.db.lock();
         let tx = db.unchecked_transaction()?;
         let result = api::clear(&tx, ext_id)?;
@@ -117,11 +171,35 @@ impl Store {
         Ok(result)
     }
 
+    /// Like `clear`, but for the given storage area.
+    #[handle_error(Error)]
+    pub fn clear_in_area(&self, area: StorageArea, ext_id: &str) -> ApiResult<StorageChanges> {
+        let db = self.db.lock();
+        let tx = db.unchecked_transaction()?;
+        let result = api::clear_in_area(&tx, area, ext_id)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
     /// Returns the bytes in use for the specified items (which can be null,
     /// a string, or an array)
-    pub fn get_bytes_in_use(&self, ext_id: &str, keys: JsonValue) -> Result<usize> {
+    #[handle_error(Error)]
+    pub fn get_bytes_in_use(&self, ext_id: &str, keys: JsonValue) -> ApiResult<u64> {
+        let db = self.db.lock();
+        Ok(api::get_bytes_in_use(&db, ext_id, keys)? as u64)
+    }
+
+    /// Like `get_bytes_in_use`, but for the given storage area. Each area's
+    /// usage counts towards its own quota.
+    #[handle_error(Error)]
+    pub fn get_bytes_in_use_in_area(
+        &self,
+        area: StorageArea,
+        ext_id: &str,
+        keys: JsonValue,
+    ) -> ApiResult<u64> {
         let db = self.db.lock();
-        api::get_bytes_in_use(&db, ext_id, keys)
+        Ok(api::get_bytes_in_use_in_area(&db, area, ext_id, keys)? as u64)
     }
 
     /// Returns a bridged sync engine for Desktop for this store.
@@ -163,10 +241,11 @@ impl Store {
 
     /// Gets the changes which the current sync applied. Should be used
     /// immediately after the bridged engine is told to apply incoming changes,
-    /// and can be used to notify observers of the StorageArea of the changes
-    /// that were applied.
+    /// or after the sync manager syncs, and can be used to notify observers of
+    /// the StorageArea of the changes that were applied.
     /// The result is a Vec of already JSON stringified changes.
-    pub fn get_synced_changes(&self) -> Result<Vec<sync::SyncedExtensionChange>> {
+    #[handle_error(Error)]
+    pub fn get_synced_changes(&self) -> ApiResult<Vec<sync::SyncedExtensionChange>> {
         let db = self.db.lock();
         sync::get_synced_changes(&db)
     }
@@ -203,6 +282,8 @@ impl Store {
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use serde_json::json;
+    use sql_support::ConnExt;
     #[test]
     fn test_send() {
         fn ensure_send<T: Send>() {}
@@ -215,4 +296,74 @@ pub mod test {
             db: Arc::new(ThreadSafeStorageDb::new(crate::db::test::new_mem_db())),
         }
     }
+
+    #[test]
+    fn test_storage_areas() -> ApiResult<()> {
+        let store = new_mem_store();
+        store.set("ext-id", json!({"area": "sync"}))?;
+        store.set_in_area(StorageArea::Local, "ext-id", json!({"area": "local"}))?;
+        store.set_in_area(StorageArea::Session, "ext-id", json!({"area": "session"}))?;
+
+        // Each area has its own data.
+        assert_eq!(store.get("ext-id", json!(null))?, json!({"area": "sync"}));
+        for area in [StorageArea::Local, StorageArea::Session] {
+            assert_eq!(
+                store.get_in_area(area, "ext-id", json!(null))?,
+                json!({ "area": area.as_str() })
+            );
+        }
+        assert_eq!(
+            store.get_bytes_in_use_in_area(StorageArea::Session, "ext-id", json!(null))?,
+            ("area".len() + "\"session\"".len()) as u64
+        );
+
+        // Only storage.sync data is synced.
+        let changes = store.clear_in_area(StorageArea::Local, "ext-id")?;
+        assert!(!changes.is_empty());
+        assert_eq!(
+            store.get_in_area(StorageArea::Local, "ext-id", json!(null))?,
+            json!({})
+        );
+        assert_eq!(store.get("ext-id", json!(null))?, json!({"area": "sync"}));
+        let db = store.db.lock();
+        assert_eq!(
+            db.query_one::<i64>("SELECT COUNT(*) FROM storage_sync_data")
+                .unwrap(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_area_quotas() -> ApiResult<()> {
+        let store = new_mem_store();
+        // storage.local and storage.session don't limit the number of items,
+        // or the size of each item.
+        let big = "x".repeat(crate::SYNC_QUOTA_BYTES_PER_ITEM + 1);
+        for area in [StorageArea::Local, StorageArea::Session] {
+            for i in 0..crate::SYNC_MAX_ITEMS + 1 {
+                store.set_in_area(area, "ext-id", json!({ format!("key-{}", i): i }))?;
+            }
+            store.set_in_area(area, "ext-id", json!({ "big": big }))?;
+            // But they do limit the total size.
+            let huge = "x".repeat(area.quota().bytes);
+            let e = store
+                .set_in_area(area, "ext-id", json!({ "huge": huge }))
+                .unwrap_err();
+            assert!(matches!(
+                e,
+                WebExtStorageApiError::QuotaError {
+                    reason: QuotaReason::TotalBytes
+                }
+            ));
+        }
+        let e = store.set("ext-id", json!({ "big": big })).unwrap_err();
+        assert!(matches!(
+            e,
+            WebExtStorageApiError::QuotaError {
+                reason: QuotaReason::ItemBytes
+            }
+        ));
+        Ok(())
+    }
 }
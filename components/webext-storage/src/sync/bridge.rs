@@ -14,8 +14,26 @@ use crate::schema;
 use crate::sync::incoming::{apply_actions, get_incoming, plan_incoming, stage_incoming};
 use crate::sync::outgoing::{get_outgoing, record_uploaded, stage_outgoing};
 
-const LAST_SYNC_META_KEY: &str = "last_sync_time";
-const SYNC_ID_META_KEY: &str = "sync_id";
+pub(super) const LAST_SYNC_META_KEY: &str = "last_sync_time";
+pub(super) const SYNC_ID_META_KEY: &str = "sync_id";
+
+// Resets the sync state, so that everything is uploaded on the next sync.
+pub(super) fn do_reset(tx: &Transaction<'_>) -> Result<()> {
+    tx.execute_batch(
+        "DELETE FROM storage_sync_mirror;
+         UPDATE storage_sync_data SET sync_change_counter = 1;",
+    )?;
+    delete_meta(tx, LAST_SYNC_META_KEY)?;
+    Ok(())
+}
+
+pub(super) fn do_wipe(tx: &Transaction<'_>) -> Result<()> {
+    // We assume the meta table is only used by sync.
+    tx.execute_batch(
+        "DELETE FROM storage_sync_data; DELETE FROM storage_sync_mirror; DELETE FROM meta;",
+    )?;
+    Ok(())
+}
 
 /// A bridged engine implements all the methods needed to make the
 /// `storage.sync` store work with Desktop's Sync implementation.
@@ -38,15 +56,6 @@ impl BridgedEngine {
         }
     }
 
-    fn do_reset(&self, tx: &Transaction<'_>) -> Result<()> {
-        tx.execute_batch(
-            "DELETE FROM storage_sync_mirror;
-             UPDATE storage_sync_data SET sync_change_counter = 1;",
-        )?;
-        delete_meta(tx, LAST_SYNC_META_KEY)?;
-        Ok(())
-    }
-
     fn thread_safe_storage_db(&self) -> Result<Arc<ThreadSafeStorageDb>> {
         self.db
             .upgrade()
@@ -79,7 +88,7 @@ impl sync15::engine::BridgedEngine for BridgedEngine {
         let db = shared_db.lock();
         let tx = db.unchecked_transaction()?;
         let new_id = SyncGuid::random().to_string();
-        self.do_reset(&tx)?;
+        do_reset(&tx)?;
         put_meta(&tx, SYNC_ID_META_KEY, &new_id)?;
         tx.commit()?;
         Ok(new_id)
@@ -93,7 +102,7 @@ impl sync15::engine::BridgedEngine for BridgedEngine {
             Some(current) if current == sync_id => current,
             _ => {
                 let tx = db.unchecked_transaction()?;
-                self.do_reset(&tx)?;
+                do_reset(&tx)?;
                 let result = sync_id.to_string();
                 put_meta(&tx, SYNC_ID_META_KEY, &result)?;
                 tx.commit()?;
@@ -163,7 +172,7 @@ impl sync15::engine::BridgedEngine for BridgedEngine {
         let shared_db = self.thread_safe_storage_db()?;
         let db = shared_db.lock();
         let tx = db.unchecked_transaction()?;
-        self.do_reset(&tx)?;
+        do_reset(&tx)?;
         delete_meta(&tx, SYNC_ID_META_KEY)?;
         tx.commit()?;
         Ok(())
@@ -173,10 +182,7 @@ impl sync15::engine::BridgedEngine for BridgedEngine {
         let shared_db = self.thread_safe_storage_db()?;
         let db = shared_db.lock();
         let tx = db.unchecked_transaction()?;
-        // We assume the meta table is only used by sync.
-        tx.execute_batch(
-            "DELETE FROM storage_sync_data; DELETE FROM storage_sync_mirror; DELETE FROM meta;",
-        )?;
+        do_wipe(&tx)?;
         tx.commit()?;
         Ok(())
     }
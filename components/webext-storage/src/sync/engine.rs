@@ -0,0 +1,298 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex, Weak};
+use sync15::bso::IncomingBso;
+use sync15::engine::{
+    CollSyncIds, CollectionRequest, EngineSyncAssociation, OutgoingRecords, SyncEngine,
+    SyncEngineId,
+};
+use sync15::{telemetry, ClientData, CollectionName, ServerTimestamp};
+use sync_guid::Guid as SyncGuid;
+
+use super::bridge::{do_reset, do_wipe, LAST_SYNC_META_KEY, SYNC_ID_META_KEY};
+use crate::db::{delete_meta, get_meta, put_meta, ThreadSafeStorageDb};
+use crate::schema;
+use crate::store::Store;
+use crate::sync::incoming::{apply_actions, get_incoming, plan_incoming, stage_incoming};
+use crate::sync::outgoing::{get_outgoing, record_uploaded, stage_outgoing};
+use crate::sync::WebextRecord;
+
+// The bridged engine only knows about the collection's sync ID, which it
+// keeps under `SYNC_ID_META_KEY`; this engine also tracks the global one.
+const GLOBAL_SYNC_ID_META_KEY: &str = "global_sync_id";
+
+// The collection desktop syncs `storage.sync` data to.
+const COLLECTION_NAME: &str = "storage-sync2";
+
+// Our "sync manager" will use whatever is stashed here.
+lazy_static::lazy_static! {
+    // Mutex: just taken long enough to update the inner stuff
+    static ref STORE_FOR_MANAGER: Mutex<Weak<Store>> = Mutex::new(Weak::new());
+}
+
+/// Called by the sync manager to get a sync engine via the store previously
+/// registered with the sync manager.
+pub fn get_registered_sync_engine(engine_id: &SyncEngineId) -> Option<Box<dyn SyncEngine>> {
+    let weak = STORE_FOR_MANAGER.lock().unwrap();
+    match weak.upgrade() {
+        None => None,
+        Some(store) => match engine_id {
+            SyncEngineId::ExtensionStorage => {
+                Some(Box::new(WebExtStorageEngine::new(Arc::clone(&store.db))))
+            }
+            // panicing here seems reasonable - it's a static error if this
+            // it hit, not something that runtime conditions can influence.
+            _ => unreachable!("can't provide unknown engine: {}", engine_id),
+        },
+    }
+}
+
+/// A `storage.sync` sync engine for the sync manager. Desktop, which drives
+/// its own syncs, uses the `BridgedEngine` instead.
+///
+/// Incoming changes are staged and applied in the same temp tables as the
+/// bridged engine uses, so `Store::get_synced_changes` returns the changes
+/// the most recent sync applied.
+pub struct WebExtStorageEngine {
+    db: Arc<ThreadSafeStorageDb>,
+}
+
+impl WebExtStorageEngine {
+    pub fn new(db: Arc<ThreadSafeStorageDb>) -> Self {
+        Self { db }
+    }
+}
+
+impl SyncEngine for WebExtStorageEngine {
+    fn collection_name(&self) -> CollectionName {
+        COLLECTION_NAME.into()
+    }
+
+    fn prepare_for_sync(&self, _get_client_data: &dyn Fn() -> ClientData) -> Result<()> {
+        // This clears anything staged by, or applied in, a previous sync.
+        let db = self.db.lock();
+        schema::create_empty_sync_temp_tables(&db)?;
+        Ok(())
+    }
+
+    fn stage_incoming(
+        &self,
+        inbound: Vec<IncomingBso>,
+        _telem: &mut telemetry::Engine,
+    ) -> Result<()> {
+        let db = self.db.lock();
+        let signal = db.begin_interrupt_scope()?;
+        let tx = db.unchecked_transaction()?;
+        let incoming_content: Vec<_> = inbound
+            .into_iter()
+            .map(IncomingBso::into_content::<WebextRecord>)
+            .collect();
+        stage_incoming(&tx, &incoming_content, &signal)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn apply_chunked(
+        &self,
+        timestamp: ServerTimestamp,
+        telem: &mut telemetry::Engine,
+    ) -> Result<OutgoingRecords<'_>> {
+        let db = self.db.lock();
+        let signal = db.begin_interrupt_scope()?;
+
+        let tx = db.unchecked_transaction()?;
+        let incoming = get_incoming(&tx)?;
+        let actions: Vec<_> = incoming
+            .into_iter()
+            .map(|(item, state)| (item, plan_incoming(state)))
+            .collect();
+        let num_incoming = u32::try_from(actions.len()).unwrap_or(u32::MAX);
+        apply_actions(&tx, actions, &signal)?;
+        stage_outgoing(&tx)?;
+        put_meta(&tx, LAST_SYNC_META_KEY, &timestamp.as_millis())?;
+        tx.commit()?;
+
+        let mut incoming_telemetry = telemetry::EngineIncoming::new();
+        incoming_telemetry.applied(num_incoming);
+        telem.incoming(incoming_telemetry);
+
+        Ok(OutgoingRecords::from_vec(get_outgoing(&db, &signal)?))
+    }
+
+    fn set_uploaded(&self, new_timestamp: ServerTimestamp, ids: Vec<SyncGuid>) -> Result<()> {
+        let db = self.db.lock();
+        let signal = db.begin_interrupt_scope()?;
+        let tx = db.unchecked_transaction()?;
+        record_uploaded(&tx, &ids, &signal)?;
+        put_meta(&tx, LAST_SYNC_META_KEY, &new_timestamp.as_millis())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_collection_request(
+        &self,
+        server_timestamp: ServerTimestamp,
+    ) -> Result<Option<CollectionRequest>> {
+        let db = self.db.lock();
+        let since = ServerTimestamp(get_meta(&db, LAST_SYNC_META_KEY)?.unwrap_or_default());
+        Ok(if since == server_timestamp {
+            None
+        } else {
+            Some(
+                CollectionRequest::new(COLLECTION_NAME.into())
+                    .full()
+                    .newer_than(since),
+            )
+        })
+    }
+
+    fn get_sync_assoc(&self) -> Result<EngineSyncAssociation> {
+        let db = self.db.lock();
+        let global = get_meta::<String>(&db, GLOBAL_SYNC_ID_META_KEY)?;
+        let coll = get_meta::<String>(&db, SYNC_ID_META_KEY)?;
+        Ok(if let (Some(global), Some(coll)) = (global, coll) {
+            EngineSyncAssociation::Connected(CollSyncIds {
+                global: SyncGuid::from_string(global),
+                coll: SyncGuid::from_string(coll),
+            })
+        } else {
+            EngineSyncAssociation::Disconnected
+        })
+    }
+
+    fn reset(&self, assoc: &EngineSyncAssociation) -> Result<()> {
+        let db = self.db.lock();
+        let tx = db.unchecked_transaction()?;
+        do_reset(&tx)?;
+        match assoc {
+            EngineSyncAssociation::Disconnected => {
+                delete_meta(&tx, GLOBAL_SYNC_ID_META_KEY)?;
+                delete_meta(&tx, SYNC_ID_META_KEY)?;
+            }
+            EngineSyncAssociation::Connected(ids) => {
+                put_meta(&tx, GLOBAL_SYNC_ID_META_KEY, &ids.global.to_string())?;
+                put_meta(&tx, SYNC_ID_META_KEY, &ids.coll.to_string())?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn wipe(&self) -> Result<()> {
+        let db = self.db.lock();
+        let tx = db.unchecked_transaction()?;
+        do_wipe(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl Store {
+    // This allows the embedding app to say "make this instance available to
+    // the sync manager". The implementation is more like "offer to sync mgr"
+    // (thereby avoiding us needing to link with the sync manager) but
+    // `register_with_sync_manager()` is logically what's happening so that's
+    // the name it gets.
+    pub fn register_with_sync_manager(self: Arc<Self>) {
+        let mut state = STORE_FOR_MANAGER.lock().unwrap();
+        *state = Arc::downgrade(&self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::test::new_mem_store;
+    use serde_json::json;
+
+    fn new_engine(store: &Store) -> WebExtStorageEngine {
+        let engine = WebExtStorageEngine::new(Arc::clone(&store.db));
+        engine
+            .prepare_for_sync(&|| unreachable!("engine doesn't need client data"))
+            .expect("should prepare");
+        engine
+    }
+
+    #[test]
+    fn test_sync() -> Result<()> {
+        let store = new_mem_store();
+        store.set("ext-a", json!({"a": "local"}))?;
+        let engine = new_engine(&store);
+
+        let mut telem = telemetry::Engine::new(COLLECTION_NAME);
+        engine.stage_incoming(
+            vec![IncomingBso::from_test_content(json!({
+                "id": "guid-b",
+                "extId": "ext-b",
+                "data": r#"{"b": "remote"}"#,
+            }))],
+            &mut telem,
+        )?;
+        // Staged records aren't counted until they're applied.
+        assert!(telem.get_incoming().is_none());
+        let outgoing = engine.apply(ServerTimestamp(1000), &mut telem)?;
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(telem.get_incoming().as_ref().unwrap().get_applied(), 1);
+
+        // The incoming record was applied, and reported as a synced change.
+        assert_eq!(store.get("ext-b", json!(null))?, json!({"b": "remote"}));
+        let changes = store.get_synced_changes()?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].ext_id, "ext-b");
+
+        // Once the local change is uploaded, there's nothing more to sync.
+        engine.set_uploaded(
+            ServerTimestamp(2000),
+            outgoing.into_iter().map(|bso| bso.envelope.id).collect(),
+        )?;
+        assert!(engine
+            .get_collection_request(ServerTimestamp(2000))?
+            .is_none());
+        assert!(engine
+            .get_collection_request(ServerTimestamp(3000))?
+            .is_some());
+        engine.prepare_for_sync(&|| unreachable!())?;
+        assert!(engine.apply(ServerTimestamp(2000), &mut telem)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_assoc() -> Result<()> {
+        let store = new_mem_store();
+        let engine = new_engine(&store);
+        assert_eq!(
+            engine.get_sync_assoc()?,
+            EngineSyncAssociation::Disconnected
+        );
+        let ids = CollSyncIds {
+            global: SyncGuid::random(),
+            coll: SyncGuid::random(),
+        };
+        engine.reset(&EngineSyncAssociation::Connected(ids.clone()))?;
+        assert_eq!(
+            engine.get_sync_assoc()?,
+            EngineSyncAssociation::Connected(ids)
+        );
+        engine.wipe()?;
+        assert_eq!(
+            engine.get_sync_assoc()?,
+            EngineSyncAssociation::Disconnected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_with_sync_manager() {
+        let engine_id = SyncEngineId::ExtensionStorage;
+        let store = Arc::new(new_mem_store());
+        Arc::clone(&store).register_with_sync_manager();
+        let engine = get_registered_sync_engine(&engine_id).expect("should be registered");
+        assert_eq!(engine.collection_name(), COLLECTION_NAME);
+        drop(engine);
+        drop(store);
+        assert!(get_registered_sync_engine(&engine_id).is_none());
+    }
+}
@@ -54,6 +54,16 @@ fn poll(account: &FirefoxAccount) -> Result<()> {
                             None => println!("Tab received: {}", tab.url),
                         };
                     }
+                    IncomingDeviceCommand::TabsClosed { sender, payload } => {
+                        match sender {
+                            Some(ref d) => println!(
+                                "Tabs closed by {}: {}",
+                                d.display_name,
+                                payload.urls.join(", ")
+                            ),
+                            None => println!("Tabs closed: {}", payload.urls.join(", ")),
+                        };
+                    }
                 }
             }
         }
@@ -7,6 +7,7 @@
 use cli_support::fxa_creds::{get_cli_fxa, get_default_fxa_config};
 use interrupt_support::Interruptee;
 use places::storage::bookmarks::{
+    html,
     json_tree::{
         fetch_tree, insert_tree, BookmarkNode, BookmarkTreeNode, FetchDepth, FolderNode,
         SeparatorNode,
@@ -169,6 +170,19 @@ fn run_native_export(db: &PlacesDb, filename: String) -> Result<()> {
     Ok(())
 }
 
+fn run_html_export(db: &PlacesDb, filename: String) -> Result<()> {
+    println!("export to {}", filename);
+    std::fs::write(filename, html::export_html(db)?)?;
+    Ok(())
+}
+
+fn run_html_import(db: &PlacesDb, filename: String) -> Result<()> {
+    println!("import from {}", filename);
+    let num_imported = html::import_html(db, &std::fs::read_to_string(filename)?)?;
+    println!("Imported {} bookmarks", num_imported);
+    Ok(())
+}
+
 fn run_maintenance(conn: &PlacesDb, db_size_limit: u32, count: u32) -> Result<()> {
     for _ in 0..count {
         let prune_metrics = places::storage::run_maintenance_prune(conn, db_size_limit, 6)?;
@@ -420,6 +434,24 @@ enum Command {
         input_file: String,
     },
 
+    #[structopt(name = "export-bookmarks-html")]
+    /// Exports bookmarks as a Netscape bookmark HTML file, which any browser
+    /// can import.
+    ExportBookmarksHtml {
+        #[structopt(name = "output-file", long, short = "o")]
+        /// The name of the output file where the HTML will be written.
+        output_file: String,
+    },
+
+    #[structopt(name = "import-bookmarks-html")]
+    /// Import bookmarks from a Netscape bookmark HTML file, as exported by
+    /// any browser.
+    ImportBookmarksHtml {
+        #[structopt(name = "input-file", long, short = "i")]
+        /// The name of the file to read.
+        input_file: String,
+    },
+
     #[structopt(name = "create-fake-visits")]
     /// Create a lot of fake visits to a lot of fake sites.
     CreateFakeVisits {
@@ -490,6 +522,8 @@ fn main() -> Result<()> {
         Command::ExportBookmarks { output_file } => run_native_export(&db, output_file),
         Command::ImportBookmarks { input_file } => run_native_import(&db, input_file),
         Command::ImportDesktopBookmarks { input_file } => run_desktop_import(&db, input_file),
+        Command::ExportBookmarksHtml { output_file } => run_html_export(&db, output_file),
+        Command::ImportBookmarksHtml { input_file } => run_html_import(&db, input_file),
         Command::ImportIosHistory { input_file } => run_ios_import_history(&db, input_file),
         Command::CreateFakeVisits {
             num_sites,
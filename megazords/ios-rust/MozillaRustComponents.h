@@ -16,6 +16,7 @@
 #import "pushFFI.h"
 #import "sync15FFI.h"
 #import "tabsFFI.h"
+#import "webextstorageFFI.h"
 #import "errorFFI.h"
 #import "syncmanagerFFI.h"
 #import "remote_settingsFFI.h"
@@ -24,3 +24,4 @@ pub use sync15;
 pub use sync_manager;
 pub use tabs;
 pub use viaduct_reqwest;
+pub use webext_storage;
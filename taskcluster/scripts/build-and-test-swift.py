@@ -23,6 +23,7 @@ BINDINGS_UDL_PATHS = [
     "components/sync15/src/sync15.udl",
     "components/sync_manager/src/syncmanager.udl",
     "components/tabs/src/tabs.udl",
+    "components/webext-storage/src/webextstorage.udl",
     "components/support/rust-log-forwarder/src/rust_log_forwarder.udl",
 ]
 
@@ -0,0 +1,23 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Malformed request: {0}")]
+    BadRequest(String),
+
+    #[error("Error reading or writing a request: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Error executing SQL: {0}")]
+    SqlError(#[from] rusqlite::Error),
+
+    #[error("Error parsing JSON data: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Error parsing URL: {0}")]
+    UrlParseError(#[from] url::ParseError),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,226 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![warn(rust_2018_idioms)]
+
+//! An in-process Sync 1.5 storage server and tokenserver, for testing sync
+//! clients end to end without an FxA account or the production servers.
+//!
+//! The server listens on localhost, and implements the parts of the storage
+//! API that `sync15::client::Sync15StorageClient` uses: `info/collections`,
+//! `info/configuration`, fetching and deleting records, batched uploads with
+//! `X-If-Unmodified-Since`, and `X-Weave-Backoff` and `Retry-After`. The
+//! tokenserver treats each distinct access token as a different account, so
+//! several simulated clients can share an account by using the same access
+//! token and key ID. Hawk signatures aren't checked.
+//!
+//! ```no_run
+//! # fn main() -> sync_test_server::Result<()> {
+//! let server = sync_test_server::SyncTestServer::start()?;
+//! // Point a `Sync15StorageClientInit` at the server.
+//! let tokenserver_url = server.tokenserver_url();
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+mod http;
+mod server;
+mod storage;
+
+pub use crate::error::{Error, Result};
+pub use crate::storage::{format_timestamp, ServerBso};
+
+use crate::http::{Request, Response};
+use crate::server::ServerState;
+use crate::storage::{BsoQuery, ServerStorage, Sort};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use url::Url;
+
+/// Limits reported in `info/configuration`, and enforced by the server.
+/// Lowering them is a good way to exercise batching in clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub max_request_bytes: usize,
+    pub max_post_records: usize,
+    pub max_post_bytes: usize,
+    pub max_total_records: usize,
+    pub max_total_bytes: usize,
+    pub max_record_payload_bytes: usize,
+    /// How long tokens from the tokenserver are valid for.
+    pub token_duration_secs: u64,
+}
+
+impl Default for ServerConfig {
+    // The production server's limits.
+    fn default() -> Self {
+        Self {
+            max_request_bytes: 2_101_248,
+            max_post_records: 100,
+            max_post_bytes: 2_097_152,
+            max_total_records: 10_000,
+            max_total_bytes: 104_857_600,
+            max_record_payload_bytes: 2_097_152,
+            token_duration_secs: 3600,
+        }
+    }
+}
+
+/// A running server. It shuts down when dropped.
+pub struct SyncTestServer {
+    addr: SocketAddr,
+    state: Arc<ServerState>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncTestServer {
+    /// Starts a server with in-memory storage and the default config.
+    pub fn start() -> Result<Self> {
+        Self::start_with(ServerConfig::default(), ServerStorage::new_in_memory()?)
+    }
+
+    /// Starts a server that stores its data in an SQLite database at `path`,
+    /// so it can be inspected after a test, or shared between runs.
+    pub fn start_with_path(path: impl AsRef<Path>, config: ServerConfig) -> Result<Self> {
+        Self::start_with(config, ServerStorage::open(path)?)
+    }
+
+    /// Starts a server with in-memory storage.
+    pub fn start_with_config(config: ServerConfig) -> Result<Self> {
+        Self::start_with(config, ServerStorage::new_in_memory()?)
+    }
+
+    fn start_with(config: ServerConfig, storage: ServerStorage) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let base_url = Url::parse(&format!("http://{}/", addr))?;
+        let state = Arc::new(ServerState::new(config, base_url, storage));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let state = Arc::clone(&state);
+            let shutdown = Arc::clone(&shutdown);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    match stream {
+                        Ok(stream) => {
+                            let state = Arc::clone(&state);
+                            std::thread::spawn(move || serve_connection(&state, stream));
+                        }
+                        Err(e) => log::warn!("Failed to accept connection: {}", e),
+                    }
+                }
+            })
+        };
+        log::info!("Sync test server listening on {}", addr);
+        Ok(Self {
+            addr,
+            state,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    /// The URL to use as the `tokenserver_url` for `Sync15StorageClientInit`.
+    pub fn tokenserver_url(&self) -> Url {
+        self.state
+            .base_url
+            .join("token/")
+            .expect("Tokenserver URL should be valid")
+    }
+
+    /// Returns the uid of the account for an access token, if a client has
+    /// fetched a token for it.
+    pub fn uid(&self, access_token: &str) -> Result<Option<u64>> {
+        self.state.storage.lock().uid_for_access_token(access_token)
+    }
+
+    /// Returns the collections for a user, and when they were last modified,
+    /// in milliseconds.
+    pub fn collections(&self, uid: u64) -> Result<Vec<(String, i64)>> {
+        self.state.storage.lock().collection_timestamps(uid)
+    }
+
+    /// Returns all the records in a collection, oldest first.
+    pub fn records(&self, uid: u64, collection: &str) -> Result<Vec<ServerBso>> {
+        let query = BsoQuery {
+            sort: Some(Sort::Oldest),
+            ..Default::default()
+        };
+        let (bsos, _) = self
+            .state
+            .storage
+            .lock()
+            .get_bsos(uid, collection, &query)?;
+        Ok(bsos)
+    }
+
+    /// Deletes everything stored for a user, as if another client wiped the
+    /// server.
+    pub fn wipe(&self, uid: u64) -> Result<()> {
+        let mut storage = self.state.storage.lock();
+        storage.next_timestamp();
+        storage.delete_all(uid)
+    }
+
+    /// Adds `X-Weave-Backoff` to every storage response, or stops adding it
+    /// if `secs` is `None`.
+    pub fn set_backoff(&self, secs: Option<u32>) {
+        self.state.faults.lock().backoff_secs = secs;
+    }
+
+    /// Responds to the next `count` storage requests with a 503 and a
+    /// `Retry-After` header.
+    pub fn fail_requests(&self, count: u32, retry_after_secs: u32) {
+        let mut faults = self.state.faults.lock();
+        faults.failures_left = count;
+        faults.retry_after_secs = retry_after_secs;
+    }
+
+    /// Invalidates every token the tokenserver has issued, so clients get a
+    /// 401 and have to fetch new ones.
+    pub fn expire_tokens(&self) {
+        self.state.expire_tokens();
+    }
+}
+
+impl Drop for SyncTestServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the listener thread so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve_connection(state: &ServerState, stream: TcpStream) {
+    let resp = match Request::read_from(&stream) {
+        Ok(req) => match state.handle(&req) {
+            Ok(resp) => resp,
+            Err(e) => error_to_response(e),
+        },
+        Err(e) => error_to_response(e),
+    };
+    if let Err(e) = resp.write_to(&stream) {
+        log::warn!("Failed to write response: {}", e);
+    }
+}
+
+fn error_to_response(e: Error) -> Response {
+    let status = match e {
+        Error::BadRequest(_) | Error::JsonError(_) | Error::UrlParseError(_) => 400,
+        Error::IoError(_) | Error::SqlError(_) => 500,
+    };
+    log::warn!("Request failed with {}: {}", status, e);
+    Response::json(status, &serde_json::json!({ "status": e.to_string() }))
+}
@@ -0,0 +1,544 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Request handling for the tokenserver and the storage server. The
+// tokenserver lives at `/token/1.0/sync/1.5`, and each user's storage at
+// `/storage/1.5/{uid}`, like the real servers.
+
+use crate::error::{Error, Result};
+use crate::http::{Request, Response};
+use crate::storage::{
+    format_timestamp, parse_timestamp, timestamp_json, BsoQuery, ServerStorage, Sort, UploadedBso,
+};
+use crate::ServerConfig;
+use parking_lot::Mutex;
+use serde_json::json;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use url::Url;
+
+// The real server's limit for the `ids` query parameter.
+const MAX_IDS_PER_REQUEST: usize = 100;
+
+struct IssuedToken {
+    uid: u64,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+pub(crate) struct Faults {
+    pub backoff_secs: Option<u32>,
+    pub failures_left: u32,
+    pub retry_after_secs: u32,
+}
+
+pub(crate) struct ServerState {
+    pub config: ServerConfig,
+    pub base_url: Url,
+    pub storage: Mutex<ServerStorage>,
+    pub faults: Mutex<Faults>,
+    tokens: Mutex<HashMap<String, IssuedToken>>,
+    next_token_id: AtomicU64,
+}
+
+impl ServerState {
+    pub fn new(config: ServerConfig, base_url: Url, storage: ServerStorage) -> Self {
+        Self {
+            config,
+            base_url,
+            storage: Mutex::new(storage),
+            faults: Mutex::new(Faults::default()),
+            tokens: Mutex::new(HashMap::new()),
+            next_token_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn expire_tokens(&self) {
+        self.tokens.lock().clear();
+    }
+
+    pub fn handle(&self, req: &Request) -> Result<Response> {
+        log::trace!("{} {}", req.method, req.url);
+        let segments: Vec<&str> = req
+            .url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        match segments.as_slice() {
+            ["token", "1.0", "sync", "1.5"] => self.handle_token(req),
+            ["storage", "1.5", uid, rest @ ..] => match uid.parse::<u64>() {
+                Ok(uid) => self.handle_storage(req, uid, rest),
+                Err(_) => Ok(error_response(404, "Unknown user")),
+            },
+            _ => Ok(error_response(404, "Unknown endpoint")),
+        }
+    }
+
+    fn handle_token(&self, req: &Request) -> Result<Response> {
+        let access_token = match req
+            .header("Authorization")
+            .and_then(|auth| auth.strip_prefix("Bearer "))
+        {
+            Some(token) => token.trim(),
+            None => return Ok(error_response(401, "invalid-credentials")),
+        };
+        let key_id = match req.header("X-KeyID") {
+            Some(key_id) => key_id,
+            None => return Ok(error_response(401, "invalid-key-id")),
+        };
+
+        let (uid, now) = {
+            let storage = self.storage.lock();
+            (
+                storage.user_for_token(access_token, key_id)?,
+                storage.current_timestamp(),
+            )
+        };
+        let id = format!(
+            "{}-{}",
+            uid,
+            self.next_token_id.fetch_add(1, Ordering::SeqCst)
+        );
+        self.tokens.lock().insert(
+            id.clone(),
+            IssuedToken {
+                uid,
+                expires_at: Instant::now() + Duration::from_secs(self.config.token_duration_secs),
+            },
+        );
+        let mut hasher = DefaultHasher::new();
+        access_token.hash(&mut hasher);
+        Ok(Response::json(
+            200,
+            &json!({
+                "id": id,
+                // We don't check Hawk signatures, so the key doesn't matter.
+                "key": format!("key-{}", id),
+                "api_endpoint": self.base_url.join(&format!("storage/1.5/{}", uid))?.as_str(),
+                "uid": uid,
+                "duration": self.config.token_duration_secs,
+                "hashed_fxa_uid": format!("{:032x}", hasher.finish()),
+            }),
+        )
+        .header("X-Timestamp", format_timestamp(now)))
+    }
+
+    // Checks the Hawk `id` in the `Authorization` header. We don't verify
+    // the signature, just that the token was issued for this user and hasn't
+    // expired.
+    fn is_authorized(&self, req: &Request, uid: u64) -> bool {
+        let token_id = req
+            .header("Authorization")
+            .and_then(|auth| auth.strip_prefix("Hawk "))
+            .and_then(|params| {
+                params.split(',').find_map(|param| {
+                    param
+                        .trim()
+                        .strip_prefix("id=")
+                        .map(|id| id.trim_matches('"'))
+                })
+            });
+        match token_id.and_then(|id| self.tokens.lock().get(id).map(|t| (t.uid, t.expires_at))) {
+            Some((token_uid, expires_at)) => token_uid == uid && Instant::now() < expires_at,
+            None => false,
+        }
+    }
+
+    fn handle_storage(&self, req: &Request, uid: u64, path: &[&str]) -> Result<Response> {
+        let (backoff_secs, failure) = {
+            let mut faults = self.faults.lock();
+            let failure = if faults.failures_left > 0 {
+                faults.failures_left -= 1;
+                Some(faults.retry_after_secs)
+            } else {
+                None
+            };
+            (faults.backoff_secs, failure)
+        };
+
+        let resp = if let Some(retry_after_secs) = failure {
+            error_response(503, "Service unavailable").header("Retry-After", retry_after_secs)
+        } else if !self.is_authorized(req, uid) {
+            error_response(401, "Unauthorized")
+        } else {
+            let mut storage = self.storage.lock();
+            match (req.method.as_str(), path) {
+                ("GET", ["info", "collections"]) => info_collections(&storage, uid)?,
+                ("GET", ["info", "collection_counts"]) => info_collection_counts(&storage, uid)?,
+                ("GET", ["info", "configuration"]) => self.info_configuration(),
+                ("GET", ["info", "quota"]) => {
+                    Response::json(200, &json!([0, null])).header("X-Last-Modified", "0.00")
+                }
+                ("DELETE", []) | ("DELETE", ["storage"]) => {
+                    let modified = storage.next_timestamp();
+                    storage.delete_all(uid)?;
+                    Response::json(200, &json!({}))
+                        .header("X-Last-Modified", format_timestamp(modified))
+                }
+                ("GET", ["storage", collection]) => get_collection(&storage, req, uid, collection)?,
+                ("POST", ["storage", collection]) => {
+                    self.post_collection(&mut storage, req, uid, collection)?
+                }
+                ("DELETE", ["storage", collection]) => {
+                    delete_collection(&mut storage, req, uid, collection)?
+                }
+                ("GET", ["storage", collection, id]) => {
+                    get_bso(&storage, req, uid, collection, id)?
+                }
+                ("PUT", ["storage", collection, id]) => {
+                    self.put_bso(&mut storage, req, uid, collection, id)?
+                }
+                ("DELETE", ["storage", collection, id]) => {
+                    delete_bso(&mut storage, req, uid, collection, id)?
+                }
+                (_, ["info", ..]) | (_, ["storage", ..]) | (_, []) => {
+                    error_response(405, "Method not allowed")
+                }
+                _ => error_response(404, "Unknown endpoint"),
+            }
+        };
+
+        let now = self.storage.lock().current_timestamp();
+        let resp = resp.header("X-Weave-Timestamp", format_timestamp(now));
+        Ok(match backoff_secs {
+            Some(secs) => resp.header("X-Weave-Backoff", secs),
+            None => resp,
+        })
+    }
+
+    fn info_configuration(&self) -> Response {
+        let config = &self.config;
+        Response::json(
+            200,
+            &json!({
+                "max_request_bytes": config.max_request_bytes,
+                "max_post_records": config.max_post_records,
+                "max_post_bytes": config.max_post_bytes,
+                "max_total_records": config.max_total_records,
+                "max_total_bytes": config.max_total_bytes,
+                "max_record_payload_bytes": config.max_record_payload_bytes,
+            }),
+        )
+        .header("X-Last-Modified", "0.00")
+    }
+
+    fn post_collection(
+        &self,
+        storage: &mut ServerStorage,
+        req: &Request,
+        uid: u64,
+        collection: &str,
+    ) -> Result<Response> {
+        let modified = storage.collection_timestamp(uid, collection)?;
+        if let Some(resp) = check_preconditions(req, modified)? {
+            return Ok(resp);
+        }
+        let uploaded = parse_uploaded_bsos(req)?;
+        if uploaded.len() > self.config.max_post_records {
+            return Ok(error_response(400, "Too many records"));
+        }
+        if req.body.len() > self.config.max_request_bytes {
+            return Ok(error_response(413, "Request too large"));
+        }
+
+        let mut success = Vec::new();
+        let mut failed = serde_json::Map::new();
+        let mut valid = Vec::new();
+        for bso in uploaded {
+            match self.validate(&bso) {
+                Ok(()) => {
+                    success.push(bso.id.clone());
+                    valid.push(bso);
+                }
+                Err(reason) => {
+                    failed.insert(bso.id.clone(), json!(reason));
+                }
+            }
+        }
+
+        let commit = req.query("commit").as_deref() == Some("true");
+        let batch = match req.query("batch") {
+            None => None,
+            Some(batch) if batch == "true" => Some(storage.create_batch(uid, collection)?),
+            Some(batch) => match batch.parse::<i64>() {
+                Ok(batch) if storage.batch_exists(uid, collection, batch)? => Some(batch),
+                _ => return Ok(error_response(400, "Invalid batch ID")),
+            },
+        };
+        match batch {
+            Some(batch) if !commit => {
+                storage.append_to_batch(batch, &valid)?;
+                Ok(Response::json(
+                    202,
+                    &json!({
+                        "batch": batch.to_string(),
+                        "success": success,
+                        "failed": failed,
+                    }),
+                )
+                .header("X-Last-Modified", format_timestamp(modified.unwrap_or(0))))
+            }
+            _ => {
+                let modified = storage.next_timestamp();
+                if let Some(batch) = batch {
+                    storage.append_to_batch(batch, &valid)?;
+                    storage.commit_batch(uid, collection, batch, modified)?;
+                } else {
+                    storage.put_bsos(uid, collection, &valid, modified)?;
+                }
+                Ok(Response::json(
+                    200,
+                    &json!({
+                        "modified": timestamp_json(modified),
+                        "success": success,
+                        "failed": failed,
+                    }),
+                )
+                .header("X-Last-Modified", format_timestamp(modified)))
+            }
+        }
+    }
+
+    fn put_bso(
+        &self,
+        storage: &mut ServerStorage,
+        req: &Request,
+        uid: u64,
+        collection: &str,
+        id: &str,
+    ) -> Result<Response> {
+        let existing = storage.get_bso(uid, collection, id)?;
+        if let Some(resp) = check_preconditions(req, existing.map(|bso| bso.modified))? {
+            return Ok(resp);
+        }
+        let mut bso: UploadedBso = serde_json::from_slice(&req.body)?;
+        bso.id = id.to_string();
+        if let Err(reason) = self.validate(&bso) {
+            return Ok(error_response(400, reason));
+        }
+        let modified = storage.next_timestamp();
+        storage.put_bsos(uid, collection, &[bso], modified)?;
+        Ok(Response::json(200, &timestamp_json(modified))
+            .header("X-Last-Modified", format_timestamp(modified)))
+    }
+
+    fn validate(&self, bso: &UploadedBso) -> std::result::Result<(), &'static str> {
+        if bso.id.is_empty()
+            || bso.id.len() > 64
+            || !bso.id.chars().all(|c| (' '..='~').contains(&c) && c != ',')
+        {
+            return Err("invalid id");
+        }
+        if matches!(&bso.payload, Some(p) if p.len() > self.config.max_record_payload_bytes) {
+            return Err("retry bytes");
+        }
+        if matches!(bso.sortindex, Some(s) if !(-999_999_999..=999_999_999).contains(&s)) {
+            return Err("invalid sortindex");
+        }
+        Ok(())
+    }
+}
+
+fn error_response(status: u16, message: &str) -> Response {
+    Response::json(status, &json!({ "status": message }))
+}
+
+// Handles `X-If-Modified-Since` and `X-If-Unmodified-Since` for a resource
+// last modified at `modified`, returning the response to send if a
+// precondition fails.
+fn check_preconditions(req: &Request, modified: Option<i64>) -> Result<Option<Response>> {
+    let modified = modified.unwrap_or(0);
+    if let Some(since) = req.header("X-If-Unmodified-Since") {
+        let since = parse_timestamp(since)
+            .ok_or_else(|| Error::BadRequest(format!("Bad X-If-Unmodified-Since: {}", since)))?;
+        if modified > since {
+            return Ok(Some(
+                error_response(412, "Precondition failed")
+                    .header("X-Last-Modified", format_timestamp(modified)),
+            ));
+        }
+    }
+    if let Some(since) = req.header("X-If-Modified-Since") {
+        let since = parse_timestamp(since)
+            .ok_or_else(|| Error::BadRequest(format!("Bad X-If-Modified-Since: {}", since)))?;
+        if modified <= since {
+            return Ok(Some(
+                Response::new(304).header("X-Last-Modified", format_timestamp(modified)),
+            ));
+        }
+    }
+    Ok(None)
+}
+
+// Uploads are either a JSON array, or one record per line.
+fn parse_uploaded_bsos(req: &Request) -> Result<Vec<UploadedBso>> {
+    if req.header("Content-Type") == Some("application/newlines") {
+        let body = String::from_utf8_lossy(&req.body);
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    } else {
+        Ok(serde_json::from_slice(&req.body)?)
+    }
+}
+
+fn parse_ids(req: &Request) -> Result<Option<Vec<String>>> {
+    Ok(match req.query("ids") {
+        Some(ids) => {
+            let ids: Vec<String> = ids
+                .split(',')
+                .filter(|id| !id.is_empty())
+                .map(str::to_string)
+                .collect();
+            if ids.len() > MAX_IDS_PER_REQUEST {
+                return Err(Error::BadRequest(format!("Too many IDs: {}", ids.len())));
+            }
+            Some(ids)
+        }
+        None => None,
+    })
+}
+
+fn info_collections(storage: &ServerStorage, uid: u64) -> Result<Response> {
+    let timestamps = storage.collection_timestamps(uid)?;
+    let last_modified = timestamps.iter().map(|(_, t)| *t).max().unwrap_or(0);
+    let body: serde_json::Map<_, _> = timestamps
+        .into_iter()
+        .map(|(name, modified)| (name, timestamp_json(modified)))
+        .collect();
+    Ok(
+        Response::json(200, &body.into())
+            .header("X-Last-Modified", format_timestamp(last_modified)),
+    )
+}
+
+fn info_collection_counts(storage: &ServerStorage, uid: u64) -> Result<Response> {
+    let last_modified = storage
+        .collection_timestamps(uid)?
+        .into_iter()
+        .map(|(_, t)| t)
+        .max()
+        .unwrap_or(0);
+    let body: serde_json::Map<_, _> = storage
+        .collection_counts(uid)?
+        .into_iter()
+        .map(|(name, count)| (name, json!(count)))
+        .collect();
+    Ok(
+        Response::json(200, &body.into())
+            .header("X-Last-Modified", format_timestamp(last_modified)),
+    )
+}
+
+fn get_collection(
+    storage: &ServerStorage,
+    req: &Request,
+    uid: u64,
+    collection: &str,
+) -> Result<Response> {
+    let modified = storage.collection_timestamp(uid, collection)?;
+    if let Some(resp) = check_preconditions(req, modified)? {
+        return Ok(resp);
+    }
+    let parse_ts = |name: &str| -> Result<Option<i64>> {
+        req.query(name)
+            .map(|ts| {
+                parse_timestamp(&ts)
+                    .ok_or_else(|| Error::BadRequest(format!("Bad `{}`: {}", name, ts)))
+            })
+            .transpose()
+    };
+    let query = BsoQuery {
+        ids: parse_ids(req)?,
+        newer: parse_ts("newer")?,
+        older: parse_ts("older")?,
+        sort: match req.query("sort").as_deref() {
+            None => None,
+            Some("newest") => Some(Sort::Newest),
+            Some("oldest") => Some(Sort::Oldest),
+            Some("index") => Some(Sort::Index),
+            Some(sort) => return Err(Error::BadRequest(format!("Bad `sort`: {}", sort))),
+        },
+        limit: req.query("limit").and_then(|limit| limit.parse().ok()),
+        offset: req
+            .query("offset")
+            .and_then(|offset| offset.parse().ok())
+            .unwrap_or(0),
+    };
+    let (bsos, next_offset) = storage.get_bsos(uid, collection, &query)?;
+    let body: Vec<serde_json::Value> = if req.query("full").is_some() {
+        bsos.iter().map(|bso| bso.to_json()).collect()
+    } else {
+        bsos.iter().map(|bso| json!(bso.id)).collect()
+    };
+    let resp = Response::json(200, &body.into())
+        .header("X-Last-Modified", format_timestamp(modified.unwrap_or(0)))
+        .header("X-Weave-Records", bsos.len());
+    Ok(match next_offset {
+        Some(offset) => resp.header("X-Weave-Next-Offset", offset),
+        None => resp,
+    })
+}
+
+fn delete_collection(
+    storage: &mut ServerStorage,
+    req: &Request,
+    uid: u64,
+    collection: &str,
+) -> Result<Response> {
+    if let Some(resp) = check_preconditions(req, storage.collection_timestamp(uid, collection)?)? {
+        return Ok(resp);
+    }
+    let ids = parse_ids(req)?;
+    let modified = storage.next_timestamp();
+    storage.delete_bsos(uid, collection, ids.as_deref(), modified)?;
+    Ok(
+        Response::json(200, &json!({ "modified": timestamp_json(modified) }))
+            .header("X-Last-Modified", format_timestamp(modified)),
+    )
+}
+
+fn get_bso(
+    storage: &ServerStorage,
+    req: &Request,
+    uid: u64,
+    collection: &str,
+    id: &str,
+) -> Result<Response> {
+    let bso = match storage.get_bso(uid, collection, id)? {
+        Some(bso) => bso,
+        None => return Ok(error_response(404, "Not found")),
+    };
+    if let Some(resp) = check_preconditions(req, Some(bso.modified))? {
+        return Ok(resp);
+    }
+    Ok(Response::json(200, &bso.to_json())
+        .header("X-Last-Modified", format_timestamp(bso.modified)))
+}
+
+fn delete_bso(
+    storage: &mut ServerStorage,
+    req: &Request,
+    uid: u64,
+    collection: &str,
+    id: &str,
+) -> Result<Response> {
+    let bso = match storage.get_bso(uid, collection, id)? {
+        Some(bso) => bso,
+        None => return Ok(error_response(404, "Not found")),
+    };
+    if let Some(resp) = check_preconditions(req, Some(bso.modified))? {
+        return Ok(resp);
+    }
+    let modified = storage.next_timestamp();
+    storage.delete_bsos(uid, collection, Some(&[id.to_string()]), modified)?;
+    Ok(
+        Response::json(200, &json!({ "modified": timestamp_json(modified) }))
+            .header("X-Last-Modified", format_timestamp(modified)),
+    )
+}
@@ -0,0 +1,653 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// The server's storage, in an SQLite database. In-memory servers use an
+// in-memory database, so there's only one implementation to keep honest.
+//
+// Timestamps are in milliseconds, but Sync 1.5 only has 10ms precision, so
+// every timestamp we hand out is a multiple of 10. Each write gets a new
+// timestamp that's greater than every timestamp before it, so clients can
+// rely on `X-Last-Modified` and `newer` even if they sync faster than the
+// clock ticks.
+
+use crate::error::Result;
+use rusqlite::{named_params, Connection, OptionalExtension};
+use serde_derive::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS users (
+        uid INTEGER PRIMARY KEY AUTOINCREMENT,
+        access_token TEXT NOT NULL,
+        key_id TEXT NOT NULL,
+        -- Users are retired when their key ID changes, like the real
+        -- tokenserver does, which leaves their data behind.
+        retired INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE IF NOT EXISTS collections (
+        uid INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        modified INTEGER NOT NULL,
+        PRIMARY KEY(uid, name)
+    );
+
+    CREATE TABLE IF NOT EXISTS bsos (
+        uid INTEGER NOT NULL,
+        collection TEXT NOT NULL,
+        id TEXT NOT NULL,
+        sortindex INTEGER,
+        payload TEXT NOT NULL DEFAULT '',
+        modified INTEGER NOT NULL,
+        expiry INTEGER,
+        PRIMARY KEY(uid, collection, id)
+    );
+
+    CREATE TABLE IF NOT EXISTS batches (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        uid INTEGER NOT NULL,
+        collection TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS batch_bsos (
+        batch_id INTEGER NOT NULL REFERENCES batches(id) ON DELETE CASCADE,
+        id TEXT NOT NULL,
+        sortindex INTEGER,
+        payload TEXT,
+        ttl INTEGER,
+        PRIMARY KEY(batch_id, id)
+    );
+";
+
+/// Formats a timestamp the way Sync 1.5 does in headers: seconds, with two
+/// decimal places.
+pub fn format_timestamp(ms: i64) -> String {
+    format!("{}.{:02}", ms / 1000, (ms % 1000) / 10)
+}
+
+/// Parses a timestamp in seconds, as sent in headers and query parameters.
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let secs = s.parse::<f64>().ok()?;
+    if !secs.is_finite() || secs < 0.0 {
+        return None;
+    }
+    Some((secs * 1000.0).round() as i64)
+}
+
+/// The JSON representation of a timestamp, for response bodies.
+pub fn timestamp_json(ms: i64) -> serde_json::Value {
+    json!(ms as f64 / 1000.0)
+}
+
+fn now_ms() -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Current time before the epoch")
+        .as_millis() as i64;
+    now - now % 10
+}
+
+/// A record as stored on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerBso {
+    pub id: String,
+    pub modified: i64,
+    pub sortindex: Option<i64>,
+    pub payload: String,
+}
+
+impl ServerBso {
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut value = json!({
+            "id": self.id,
+            "modified": timestamp_json(self.modified),
+            "payload": self.payload,
+        });
+        if let Some(sortindex) = self.sortindex {
+            value["sortindex"] = json!(sortindex);
+        }
+        value
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            modified: row.get("modified")?,
+            sortindex: row.get("sortindex")?,
+            payload: row.get("payload")?,
+        })
+    }
+}
+
+/// A record uploaded by a client. Every field except the ID is optional, and
+/// missing fields keep their current values when updating a record. PUTs
+/// take the ID from the URL.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct UploadedBso {
+    #[serde(default)]
+    pub id: String,
+    pub payload: Option<String>,
+    pub sortindex: Option<i64>,
+    /// The number of seconds until the record expires.
+    pub ttl: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    Newest,
+    Oldest,
+    Index,
+}
+
+/// The parameters for fetching records from a collection.
+#[derive(Debug, Clone, Default)]
+pub struct BsoQuery {
+    pub ids: Option<Vec<String>>,
+    pub newer: Option<i64>,
+    pub older: Option<i64>,
+    pub sort: Option<Sort>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+pub struct ServerStorage {
+    conn: Connection,
+    last_timestamp: i64,
+}
+
+impl ServerStorage {
+    pub fn new_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+        let last_timestamp = conn.query_row(
+            "SELECT IFNULL(MAX(modified), 0) FROM collections",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(Self {
+            conn,
+            last_timestamp,
+        })
+    }
+
+    /// Returns the timestamp for a new write.
+    pub fn next_timestamp(&mut self) -> i64 {
+        self.last_timestamp = now_ms().max(self.last_timestamp + 10);
+        self.last_timestamp
+    }
+
+    /// Returns the current server time, which is never earlier than the last
+    /// write.
+    pub fn current_timestamp(&self) -> i64 {
+        now_ms().max(self.last_timestamp)
+    }
+
+    /// Returns the uid for an account, creating a user if this is the first
+    /// time we've seen the access token. If the key ID changed, the user is
+    /// replaced with a new one, like the real tokenserver does when the
+    /// account's sync keys change.
+    pub fn user_for_token(&self, access_token: &str, key_id: &str) -> Result<u64> {
+        let existing: Option<(u64, String)> = self
+            .conn
+            .query_row(
+                "SELECT uid, key_id FROM users
+                 WHERE access_token = :access_token AND NOT retired",
+                named_params! { ":access_token": access_token },
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match existing {
+            Some((uid, existing_key_id)) if existing_key_id == key_id => return Ok(uid),
+            Some((uid, _)) => {
+                log::info!("Key ID changed for user {}; retiring them", uid);
+                self.conn.execute(
+                    "UPDATE users SET retired = 1 WHERE uid = :uid",
+                    named_params! { ":uid": uid },
+                )?;
+            }
+            None => {}
+        }
+        self.conn.execute(
+            "INSERT INTO users(access_token, key_id) VALUES(:access_token, :key_id)",
+            named_params! { ":access_token": access_token, ":key_id": key_id },
+        )?;
+        Ok(self.conn.last_insert_rowid() as u64)
+    }
+
+    /// Returns the current uid for an access token, if we've issued a token
+    /// for it.
+    pub fn uid_for_access_token(&self, access_token: &str) -> Result<Option<u64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT uid FROM users WHERE access_token = :access_token AND NOT retired",
+                named_params! { ":access_token": access_token },
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn collection_timestamps(&self, uid: u64) -> Result<Vec<(String, i64)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, modified FROM collections WHERE uid = :uid")?;
+        let rows = stmt.query_map(named_params! { ":uid": uid }, |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn collection_counts(&self, uid: u64) -> Result<Vec<(String, u64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT collection, COUNT(*) FROM bsos
+             WHERE uid = :uid AND (expiry IS NULL OR expiry > :now)
+             GROUP BY collection",
+        )?;
+        let rows = stmt.query_map(
+            named_params! { ":uid": uid, ":now": self.current_timestamp() },
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Returns when a collection was last modified, or `None` if it doesn't
+    /// exist.
+    pub fn collection_timestamp(&self, uid: u64, collection: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT modified FROM collections WHERE uid = :uid AND name = :name",
+                named_params! { ":uid": uid, ":name": collection },
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Fetches records matching `query`, and the offset to pass to fetch the
+    /// next page, if there are more.
+    pub fn get_bsos(
+        &self,
+        uid: u64,
+        collection: &str,
+        query: &BsoQuery,
+    ) -> Result<(Vec<ServerBso>, Option<usize>)> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT id, modified, sortindex, payload FROM bsos
+             WHERE uid = :uid AND collection = :collection
+               AND modified > :newer AND modified < :older
+               AND (expiry IS NULL OR expiry > :now)
+             ORDER BY {}",
+            match query.sort {
+                Some(Sort::Newest) | None => "modified DESC, id",
+                Some(Sort::Oldest) => "modified ASC, id",
+                Some(Sort::Index) => "sortindex DESC, id",
+            }
+        ))?;
+        let rows = stmt.query_and_then(
+            named_params! {
+                ":uid": uid,
+                ":collection": collection,
+                ":newer": query.newer.unwrap_or(-1),
+                ":older": query.older.unwrap_or(i64::MAX),
+                ":now": self.current_timestamp(),
+            },
+            ServerBso::from_row,
+        )?;
+        let ids = query
+            .ids
+            .as_ref()
+            .map(|ids| ids.iter().map(String::as_str).collect::<HashSet<_>>());
+        let mut matching = Vec::new();
+        for bso in rows {
+            let bso = bso?;
+            if !matches!(&ids, Some(ids) if !ids.contains(bso.id.as_str())) {
+                matching.push(bso);
+            }
+        }
+        let end = match query.limit {
+            Some(limit) => (query.offset + limit).min(matching.len()),
+            None => matching.len(),
+        };
+        let next_offset = (end < matching.len()).then_some(end);
+        let page = matching.into_iter().take(end).skip(query.offset).collect();
+        Ok((page, next_offset))
+    }
+
+    pub fn get_bso(&self, uid: u64, collection: &str, id: &str) -> Result<Option<ServerBso>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id, modified, sortindex, payload FROM bsos
+                 WHERE uid = :uid AND collection = :collection AND id = :id
+                   AND (expiry IS NULL OR expiry > :now)",
+                named_params! {
+                    ":uid": uid,
+                    ":collection": collection,
+                    ":id": id,
+                    ":now": self.current_timestamp(),
+                },
+                ServerBso::from_row,
+            )
+            .optional()?)
+    }
+
+    /// Inserts or updates records, and bumps the collection's timestamp.
+    pub fn put_bsos(
+        &mut self,
+        uid: u64,
+        collection: &str,
+        bsos: &[UploadedBso],
+        modified: i64,
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for bso in bsos {
+            let expiry = bso.ttl.map(|ttl| modified + ttl * 1000);
+            // Changing only the TTL doesn't change the record's timestamp.
+            tx.execute(
+                "INSERT INTO bsos(uid, collection, id, sortindex, payload, modified, expiry)
+                 VALUES(:uid, :collection, :id, :sortindex, IFNULL(:payload, ''), :modified,
+                        :expiry)
+                 ON CONFLICT(uid, collection, id) DO UPDATE SET
+                   sortindex = IFNULL(:sortindex, sortindex),
+                   payload = IFNULL(:payload, payload),
+                   modified = CASE WHEN :payload IS NULL AND :sortindex IS NULL
+                                   THEN modified ELSE :modified END,
+                   expiry = IFNULL(:expiry, expiry)",
+                named_params! {
+                    ":uid": uid,
+                    ":collection": collection,
+                    ":id": bso.id,
+                    ":sortindex": bso.sortindex,
+                    ":payload": bso.payload,
+                    ":modified": modified,
+                    ":expiry": expiry,
+                },
+            )?;
+        }
+        touch_collection(&tx, uid, collection, modified)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Deletes records from a collection, or the whole collection if `ids`
+    /// is `None`. Returns the number of records deleted.
+    pub fn delete_bsos(
+        &mut self,
+        uid: u64,
+        collection: &str,
+        ids: Option<&[String]>,
+        modified: i64,
+    ) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let deleted = match ids {
+            Some(ids) => {
+                let mut deleted = 0;
+                for id in ids {
+                    deleted += tx.execute(
+                        "DELETE FROM bsos WHERE uid = :uid AND collection = :collection
+                                            AND id = :id",
+                        named_params! { ":uid": uid, ":collection": collection, ":id": id },
+                    )?;
+                }
+                touch_collection(&tx, uid, collection, modified)?;
+                deleted
+            }
+            None => {
+                tx.execute(
+                    "DELETE FROM collections WHERE uid = :uid AND name = :collection",
+                    named_params! { ":uid": uid, ":collection": collection },
+                )?;
+                tx.execute(
+                    "DELETE FROM bsos WHERE uid = :uid AND collection = :collection",
+                    named_params! { ":uid": uid, ":collection": collection },
+                )?
+            }
+        };
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Deletes everything stored for a user.
+    pub fn delete_all(&mut self, uid: u64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "DELETE FROM collections WHERE uid = :uid",
+            named_params! { ":uid": uid },
+        )?;
+        tx.execute(
+            "DELETE FROM bsos WHERE uid = :uid",
+            named_params! { ":uid": uid },
+        )?;
+        tx.execute(
+            "DELETE FROM batches WHERE uid = :uid",
+            named_params! { ":uid": uid },
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn create_batch(&self, uid: u64, collection: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO batches(uid, collection) VALUES(:uid, :collection)",
+            named_params! { ":uid": uid, ":collection": collection },
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Returns `true` if the batch exists, and belongs to this collection.
+    pub fn batch_exists(&self, uid: u64, collection: &str, batch: i64) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM batches
+                 WHERE id = :batch AND uid = :uid AND collection = :collection",
+                named_params! { ":batch": batch, ":uid": uid, ":collection": collection },
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    pub fn append_to_batch(&mut self, batch: i64, bsos: &[UploadedBso]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for bso in bsos {
+            // Later uploads of the same record within a batch win.
+            tx.execute(
+                "INSERT INTO batch_bsos(batch_id, id, sortindex, payload, ttl)
+                 VALUES(:batch, :id, :sortindex, :payload, :ttl)
+                 ON CONFLICT(batch_id, id) DO UPDATE SET
+                   sortindex = IFNULL(:sortindex, sortindex),
+                   payload = IFNULL(:payload, payload),
+                   ttl = IFNULL(:ttl, ttl)",
+                named_params! {
+                    ":batch": batch,
+                    ":id": bso.id,
+                    ":sortindex": bso.sortindex,
+                    ":payload": bso.payload,
+                    ":ttl": bso.ttl,
+                },
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Applies all the records in a batch at once, and deletes the batch.
+    pub fn commit_batch(
+        &mut self,
+        uid: u64,
+        collection: &str,
+        batch: i64,
+        modified: i64,
+    ) -> Result<()> {
+        let bsos = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, sortindex, payload, ttl FROM batch_bsos WHERE batch_id = :batch",
+            )?;
+            let rows = stmt.query_map(named_params! { ":batch": batch }, |row| {
+                Ok(UploadedBso {
+                    id: row.get(0)?,
+                    sortindex: row.get(1)?,
+                    payload: row.get(2)?,
+                    ttl: row.get(3)?,
+                })
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        self.put_bsos(uid, collection, &bsos, modified)?;
+        self.conn.execute(
+            "DELETE FROM batches WHERE id = :batch",
+            named_params! { ":batch": batch },
+        )?;
+        Ok(())
+    }
+}
+
+fn touch_collection(conn: &Connection, uid: u64, collection: &str, modified: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO collections(uid, name, modified) VALUES(:uid, :name, :modified)
+         ON CONFLICT(uid, name) DO UPDATE SET modified = :modified",
+        named_params! { ":uid": uid, ":name": collection, ":modified": modified },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(id: &str, payload: &str) -> UploadedBso {
+        UploadedBso {
+            id: id.into(),
+            payload: Some(payload.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(format_timestamp(1_234_560), "1234.56");
+        assert_eq!(format_timestamp(1_234_500), "1234.50");
+        assert_eq!(parse_timestamp("1234.56"), Some(1_234_560));
+        assert_eq!(parse_timestamp("-1"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+
+        let mut storage = ServerStorage::new_in_memory().unwrap();
+        let first = storage.next_timestamp();
+        let second = storage.next_timestamp();
+        assert_eq!(first % 10, 0);
+        assert!(second > first);
+    }
+
+    #[test]
+    fn test_users() -> Result<()> {
+        let storage = ServerStorage::new_in_memory()?;
+        let uid = storage.user_for_token("token", "kid1")?;
+        assert_eq!(storage.user_for_token("token", "kid1")?, uid);
+        assert_ne!(storage.user_for_token("other", "kid1")?, uid);
+        // A new key ID means a new user.
+        let new_uid = storage.user_for_token("token", "kid2")?;
+        assert_ne!(new_uid, uid);
+        assert_eq!(storage.uid_for_access_token("token")?, Some(new_uid));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bsos() -> Result<()> {
+        let mut storage = ServerStorage::new_in_memory()?;
+        let t1 = storage.next_timestamp();
+        storage.put_bsos(1, "coll", &[upload("a", "1"), upload("b", "2")], t1)?;
+        let t2 = storage.next_timestamp();
+        storage.put_bsos(
+            1,
+            "coll",
+            &[
+                upload("c", "3"),
+                UploadedBso {
+                    id: "a".into(),
+                    sortindex: Some(5),
+                    ..Default::default()
+                },
+            ],
+            t2,
+        )?;
+        assert_eq!(storage.collection_timestamp(1, "coll")?, Some(t2));
+        assert_eq!(storage.collection_timestamp(2, "coll")?, None);
+
+        let a = storage.get_bso(1, "coll", "a")?.unwrap();
+        assert_eq!(a.payload, "1");
+        assert_eq!(a.sortindex, Some(5));
+        assert_eq!(a.modified, t2);
+
+        let (newer, next) = storage.get_bsos(
+            1,
+            "coll",
+            &BsoQuery {
+                newer: Some(t1),
+                sort: Some(Sort::Oldest),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(
+            newer.iter().map(|b| b.id.as_str()).collect::<Vec<_>>(),
+            vec!["a", "c"]
+        );
+        assert_eq!(next, None);
+
+        let (page, next) = storage.get_bsos(
+            1,
+            "coll",
+            &BsoQuery {
+                sort: Some(Sort::Oldest),
+                limit: Some(2),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(
+            page.iter().map(|b| b.id.as_str()).collect::<Vec<_>>(),
+            vec!["b", "a"]
+        );
+        assert_eq!(next, Some(2));
+
+        let t3 = storage.next_timestamp();
+        assert_eq!(
+            storage.delete_bsos(1, "coll", Some(&["a".to_string()]), t3)?,
+            1
+        );
+        assert!(storage.get_bso(1, "coll", "a")?.is_none());
+        storage.delete_bsos(1, "coll", None, storage.current_timestamp())?;
+        assert_eq!(storage.collection_timestamp(1, "coll")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_batches() -> Result<()> {
+        let mut storage = ServerStorage::new_in_memory()?;
+        let batch = storage.create_batch(1, "coll")?;
+        assert!(storage.batch_exists(1, "coll", batch)?);
+        assert!(!storage.batch_exists(1, "other", batch)?);
+        storage.append_to_batch(batch, &[upload("a", "1")])?;
+        storage.append_to_batch(batch, &[upload("a", "2"), upload("b", "3")])?;
+        assert_eq!(storage.collection_timestamp(1, "coll")?, None);
+
+        let modified = storage.next_timestamp();
+        storage.commit_batch(1, "coll", batch, modified)?;
+        assert!(!storage.batch_exists(1, "coll", batch)?);
+        assert_eq!(storage.collection_timestamp(1, "coll")?, Some(modified));
+        assert_eq!(storage.get_bso(1, "coll", "a")?.unwrap().payload, "2");
+        assert_eq!(storage.get_bso(1, "coll", "b")?.unwrap().payload, "3");
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Tests that talk to the server over HTTP, like a sync client would.
+
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use sync_test_server::{ServerConfig, SyncTestServer};
+use url::Url;
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Value,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn request(method: &str, url: &Url, headers: &[(&str, &str)], body: Option<&Value>) -> Response {
+    let mut stream = TcpStream::connect(format!(
+        "{}:{}",
+        url.host_str().unwrap(),
+        url.port().unwrap()
+    ))
+    .unwrap();
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let mut req = format!(
+        "{} {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Length: {}\r\n",
+        method,
+        target,
+        url.host_str().unwrap(),
+        url.port().unwrap(),
+        body.len()
+    );
+    for (name, value) in headers {
+        req.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    req.push_str("\r\n");
+    req.push_str(&body);
+    stream.write_all(req.as_bytes()).unwrap();
+
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw).unwrap();
+    let (head, body) = raw.split_once("\r\n\r\n").unwrap();
+    let mut lines = head.lines();
+    let status = lines.next().unwrap().split(' ').nth(1).unwrap();
+    let headers = lines
+        .map(|line| {
+            let (name, value) = line.split_once(':').unwrap();
+            (name.to_string(), value.trim().to_string())
+        })
+        .collect();
+    Response {
+        status: status.parse().unwrap(),
+        headers,
+        body: if body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(body).unwrap()
+        },
+    }
+}
+
+// A client for one account, which fetches a token like `TokenProvider` does.
+struct Client {
+    endpoint: Url,
+    auth: String,
+}
+
+impl Client {
+    fn new(server: &SyncTestServer, access_token: &str) -> Self {
+        let url = server.tokenserver_url().join("1.0/sync/1.5").unwrap();
+        let resp = request(
+            "GET",
+            &url,
+            &[
+                ("Authorization", &format!("Bearer {}", access_token)),
+                ("X-KeyID", "1234-abcd"),
+            ],
+            None,
+        );
+        assert_eq!(resp.status, 200);
+        assert!(resp.header("X-Timestamp").is_some());
+        let token = resp.body;
+        Self {
+            endpoint: Url::parse(&format!("{}/", token["api_endpoint"].as_str().unwrap())).unwrap(),
+            auth: format!(
+                r#"Hawk id="{}", ts="1", nonce="abc", mac="unchecked""#,
+                token["id"].as_str().unwrap()
+            ),
+        }
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Response {
+        let mut all_headers = vec![("Authorization", self.auth.as_str())];
+        all_headers.extend_from_slice(headers);
+        request(
+            method,
+            &self.endpoint.join(path).unwrap(),
+            &all_headers,
+            body,
+        )
+    }
+}
+
+#[test]
+fn test_tokenserver() {
+    let server = SyncTestServer::start().unwrap();
+    let url = server.tokenserver_url().join("1.0/sync/1.5").unwrap();
+    assert_eq!(request("GET", &url, &[], None).status, 401);
+
+    let first = Client::new(&server, "account-1");
+    let second = Client::new(&server, "account-1");
+    let other = Client::new(&server, "account-2");
+    // Clients for the same account share storage.
+    assert_eq!(first.endpoint, second.endpoint);
+    assert_ne!(first.endpoint, other.endpoint);
+    assert!(server.uid("account-1").unwrap().is_some());
+    assert!(server.uid("account-3").unwrap().is_none());
+
+    // Tokens only work for their own user.
+    let wrong = Client {
+        endpoint: other.endpoint.clone(),
+        auth: first.auth.clone(),
+    };
+    assert_eq!(wrong.send("GET", "info/collections", &[], None).status, 401);
+
+    server.expire_tokens();
+    assert_eq!(first.send("GET", "info/collections", &[], None).status, 401);
+    let refreshed = Client::new(&server, "account-1");
+    assert_eq!(
+        refreshed.send("GET", "info/collections", &[], None).status,
+        200
+    );
+}
+
+#[test]
+fn test_records() {
+    let server = SyncTestServer::start().unwrap();
+    let client = Client::new(&server, "account");
+
+    let resp = client.send("GET", "info/collections", &[], None);
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body, json!({}));
+    assert_eq!(resp.header("X-Last-Modified"), Some("0.00"));
+
+    let resp = client.send(
+        "PUT",
+        "storage/meta/global",
+        &[("X-If-Unmodified-Since", "0.00")],
+        Some(&json!({ "payload": "{\"syncID\":\"abc\"}" })),
+    );
+    assert_eq!(resp.status, 200);
+    let modified = resp.header("X-Last-Modified").unwrap().to_string();
+
+    // Stale writes fail.
+    let resp = client.send(
+        "PUT",
+        "storage/meta/global",
+        &[("X-If-Unmodified-Since", "1.00")],
+        Some(&json!({ "payload": "{}" })),
+    );
+    assert_eq!(resp.status, 412);
+
+    let resp = client.send("GET", "storage/meta/global", &[], None);
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body["payload"], "{\"syncID\":\"abc\"}");
+    assert_eq!(resp.header("X-Last-Modified"), Some(modified.as_str()));
+
+    let resp = client.send("GET", "info/collections", &[], None);
+    assert_eq!(resp.header("X-Last-Modified"), Some(modified.as_str()));
+    assert!(resp.body["meta"].is_number());
+
+    let resp = client.send(
+        "POST",
+        "storage/bookmarks",
+        &[],
+        Some(&json!([
+            { "id": "a", "payload": "1", "sortindex": 5 },
+            { "id": "b", "payload": "2" },
+            { "id": "bad,id", "payload": "3" },
+        ])),
+    );
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.body["success"], json!(["a", "b"]));
+    assert!(resp.body["failed"]["bad,id"].is_string());
+
+    let resp = client.send("GET", "storage/bookmarks?full=1&sort=index", &[], None);
+    assert_eq!(resp.header("X-Weave-Records"), Some("2"));
+    assert_eq!(resp.body[0]["id"], "a");
+    assert_eq!(resp.body[0]["sortindex"], 5);
+
+    let resp = client.send("GET", "storage/bookmarks?ids=b", &[], None);
+    assert_eq!(resp.body, json!(["b"]));
+
+    let resp = client.send("GET", "storage/bookmarks?sort=oldest&limit=1", &[], None);
+    assert_eq!(resp.header("X-Weave-Next-Offset"), Some("1"));
+
+    let resp = client.send(
+        "GET",
+        &format!("storage/bookmarks?newer={}", modified),
+        &[],
+        None,
+    );
+    assert_eq!(resp.body.as_array().unwrap().len(), 2);
+
+    assert_eq!(
+        client
+            .send("DELETE", "storage/bookmarks/a", &[], None)
+            .status,
+        200
+    );
+    assert_eq!(
+        client
+            .send("DELETE", "storage/bookmarks/a", &[], None)
+            .status,
+        404
+    );
+    let uid = server.uid("account").unwrap().unwrap();
+    assert_eq!(server.records(uid, "bookmarks").unwrap().len(), 1);
+
+    // Wiping everything.
+    assert_eq!(client.send("DELETE", "", &[], None).status, 200);
+    assert!(server.collections(uid).unwrap().is_empty());
+}
+
+#[test]
+fn test_batches() {
+    let server = SyncTestServer::start_with_config(ServerConfig {
+        max_post_records: 2,
+        ..Default::default()
+    })
+    .unwrap();
+    let client = Client::new(&server, "account");
+    let uid = server.uid("account").unwrap().unwrap();
+
+    let config = client.send("GET", "info/configuration", &[], None);
+    assert_eq!(config.body["max_post_records"], 2);
+
+    let resp = client.send(
+        "POST",
+        "storage/passwords",
+        &[],
+        Some(&json!([{ "id": "a" }, { "id": "b" }, { "id": "c" }])),
+    );
+    assert_eq!(resp.status, 400);
+
+    let resp = client.send(
+        "POST",
+        "storage/passwords?batch=true",
+        &[("X-If-Unmodified-Since", "0.00")],
+        Some(&json!([{ "id": "a", "payload": "1" }, { "id": "b", "payload": "2" }])),
+    );
+    assert_eq!(resp.status, 202);
+    let batch = resp.body["batch"].as_str().unwrap().to_string();
+    assert!(server.records(uid, "passwords").unwrap().is_empty());
+
+    let resp = client.send(
+        "POST",
+        &format!("storage/passwords?batch={}&commit=true", batch),
+        &[("X-If-Unmodified-Since", "0.00")],
+        Some(&json!([{ "id": "c", "payload": "3" }])),
+    );
+    assert_eq!(resp.status, 200);
+    assert_eq!(
+        resp.header("X-Last-Modified"),
+        resp.body["modified"]
+            .as_f64()
+            .map(|m| format!("{:.2}", m))
+            .as_deref()
+    );
+    assert_eq!(server.records(uid, "passwords").unwrap().len(), 3);
+
+    // The batch is gone once it's committed.
+    let resp = client.send(
+        "POST",
+        &format!("storage/passwords?batch={}&commit=true", batch),
+        &[],
+        Some(&json!([])),
+    );
+    assert_eq!(resp.status, 400);
+
+    // Another client wrote in the meantime.
+    let resp = client.send(
+        "POST",
+        "storage/passwords?batch=true",
+        &[("X-If-Unmodified-Since", "0.00")],
+        Some(&json!([{ "id": "d", "payload": "4" }])),
+    );
+    assert_eq!(resp.status, 412);
+}
+
+#[test]
+fn test_backoff() {
+    let server = SyncTestServer::start().unwrap();
+    let client = Client::new(&server, "account");
+
+    server.set_backoff(Some(60));
+    let resp = client.send("GET", "info/collections", &[], None);
+    assert_eq!(resp.status, 200);
+    assert_eq!(resp.header("X-Weave-Backoff"), Some("60"));
+
+    server.set_backoff(None);
+    server.fail_requests(1, 30);
+    let resp = client.send("GET", "info/collections", &[], None);
+    assert_eq!(resp.status, 503);
+    assert_eq!(resp.header("Retry-After"), Some("30"));
+    assert!(resp.header("X-Weave-Backoff").is_none());
+
+    let resp = client.send("GET", "info/collections", &[], None);
+    assert_eq!(resp.status, 200);
+}
@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Tests that sync real engines through the server, with `sync_multiple`, like
+// two devices on the same account would.
+
+use error_support::{set_application_error_reporter, ApplicationErrorReporter};
+use interrupt_support::NeverInterrupts;
+use logins::encryption::create_key;
+use logins::{LoginEntry, LoginFields, LoginStore, LoginsSyncEngine, SecureLoginFields};
+use std::sync::{Arc, Mutex};
+use sync15::client::{sync_multiple, MemoryCachedState, Sync15StorageClientInit};
+use sync15::engine::SyncEngine;
+use sync15::KeyBundle;
+use sync_manager::{SchedulerConfig, SyncScheduler};
+use sync_test_server::SyncTestServer;
+
+// A device with its own logins store, and its own sync state.
+struct TestClient {
+    store: Arc<LoginStore>,
+    encryption_key: String,
+    persisted_state: Option<String>,
+    mem_cached_state: MemoryCachedState,
+}
+
+impl TestClient {
+    fn new() -> Self {
+        Self {
+            store: Arc::new(LoginStore::new_in_memory().unwrap()),
+            encryption_key: create_key().unwrap(),
+            persisted_state: None,
+            mem_cached_state: MemoryCachedState::default(),
+        }
+    }
+
+    fn add_login(&self, origin: &str, username: &str) -> String {
+        let entry = LoginEntry {
+            fields: LoginFields {
+                origin: origin.into(),
+                form_action_origin: Some(origin.into()),
+                ..Default::default()
+            },
+            sec_fields: SecureLoginFields {
+                username: username.into(),
+                password: "hunter2".into(),
+            },
+        };
+        self.store
+            .add(entry, &self.encryption_key)
+            .unwrap()
+            .guid()
+            .to_string()
+    }
+
+    fn login_ids(&self) -> Vec<String> {
+        let mut ids: Vec<_> = self
+            .store
+            .list()
+            .unwrap()
+            .iter()
+            .map(|login| login.guid().to_string())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn sync(&mut self, storage_init: &Sync15StorageClientInit, root_sync_key: &KeyBundle) {
+        let mut engine = LoginsSyncEngine::new(Arc::clone(&self.store)).unwrap();
+        engine
+            .set_local_encryption_key(&self.encryption_key)
+            .unwrap();
+        let result = sync_multiple(
+            &[&engine],
+            &mut self.persisted_state,
+            &mut self.mem_cached_state,
+            storage_init,
+            root_sync_key,
+            &NeverInterrupts,
+            None,
+        );
+        result.result.expect("sync should succeed");
+        for (engine, result) in result.engine_results {
+            result.unwrap_or_else(|e| panic!("{} engine failed: {}", engine, e));
+        }
+    }
+}
+
+#[test]
+fn test_logins_converge() {
+    let _ = env_logger::try_init();
+    viaduct_reqwest::use_reqwest_backend();
+
+    let server = SyncTestServer::start().unwrap();
+    let storage_init = Sync15StorageClientInit {
+        key_id: "1234-abcd".into(),
+        access_token: "test-account".into(),
+        tokenserver_url: server.tokenserver_url(),
+    };
+    let root_sync_key = KeyBundle::new_random().unwrap();
+    let mut c0 = TestClient::new();
+    let mut c1 = TestClient::new();
+
+    // Each client uploads a login, and gets the other's.
+    let first = c0.add_login("https://example.com", "first");
+    c0.sync(&storage_init, &root_sync_key);
+    let second = c1.add_login("https://example.org", "second");
+    c1.sync(&storage_init, &root_sync_key);
+    c0.sync(&storage_init, &root_sync_key);
+
+    let mut expected = vec![first.clone(), second.clone()];
+    expected.sort();
+    assert_eq!(c0.login_ids(), expected);
+    assert_eq!(c1.login_ids(), expected);
+
+    let uid = server.uid("test-account").unwrap().unwrap();
+    assert_eq!(server.records(uid, "passwords").unwrap().len(), 2);
+
+    // Deletions sync as tombstones.
+    assert!(c1.store.delete(&first).unwrap());
+    c1.sync(&storage_init, &root_sync_key);
+    c0.sync(&storage_init, &root_sync_key);
+
+    assert_eq!(c0.login_ids(), vec![second.clone()]);
+    assert_eq!(c1.login_ids(), vec![second]);
+    assert_eq!(server.records(uid, "passwords").unwrap().len(), 2);
+}
+
+// Records the type names of reported errors.
+#[derive(Clone, Default)]
+struct TestErrorReporter(Arc<Mutex<Vec<String>>>);
+
+impl ApplicationErrorReporter for TestErrorReporter {
+    fn report_error(&self, type_name: String, _message: String) {
+        self.0.lock().unwrap().push(type_name);
+    }
+
+    fn report_breadcrumb(&self, _message: String, _module: String, _line: u32, _column: u32) {}
+}
+
+#[test]
+fn test_scheduler_state_on_fresh_install() {
+    let _ = env_logger::try_init();
+    viaduct_reqwest::use_reqwest_backend();
+    let reporter = TestErrorReporter::default();
+    set_application_error_reporter(Box::new(reporter.clone()));
+
+    let server = SyncTestServer::start().unwrap();
+    let storage_init = Sync15StorageClientInit {
+        key_id: "1234-abcd".into(),
+        access_token: "fresh-install".into(),
+        tokenserver_url: server.tokenserver_url(),
+    };
+    let root_sync_key = KeyBundle::new_random().unwrap();
+
+    // A fresh install has no persisted state, so the first sync uses the
+    // state the scheduler writes.
+    let scheduler = SyncScheduler::new(
+        SchedulerConfig {
+            engines: vec!["passwords".into()],
+            ..Default::default()
+        },
+        None,
+    )
+    .unwrap();
+    let mut client = TestClient::new();
+    client.add_login("https://example.com", "first");
+    client.persisted_state = Some(scheduler.persisted_state().unwrap());
+    client.sync(&storage_init, &root_sync_key);
+
+    assert!(!reporter
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|type_name| type_name == "sync15-prepare-persisted-state"));
+    let uid = server.uid("fresh-install").unwrap().unwrap();
+    assert_eq!(server.records(uid, "passwords").unwrap().len(), 1);
+}
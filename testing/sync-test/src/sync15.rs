@@ -17,7 +17,7 @@ use std::mem;
 use sync15::bso::{IncomingBso, OutgoingBso};
 use sync15::client::{sync_multiple, MemoryCachedState};
 use sync15::engine::{
-    CollectionRequest, EngineSyncAssociation, SyncEngine,
+    CollectionRequest, EngineSyncAssociation, OutgoingRecords, SyncEngine,
 };
 use sync15::{telemetry, ServerTimestamp};
 use sync_guid::Guid;
@@ -76,19 +76,20 @@ impl SyncEngine for TestEngine {
         Ok(())
     }
 
-    fn apply(
+    fn apply_chunked(
         &self,
         _timestamp: ServerTimestamp,
         _telem: &mut telemetry::Engine,
-    ) -> anyhow::Result<Vec<OutgoingBso>> {
+    ) -> anyhow::Result<OutgoingRecords<'_>> {
         // Notice the `&mut *` and `.borrow_mut()` to extract the Vec from
         // the RefCell.
         let temp: Vec<TestRecord> = mem::take(&mut *self.test_records.borrow_mut());
 
-        Ok(temp.into_iter()
+        Ok(OutgoingRecords::from_vec(
+            temp.into_iter()
                 .map(OutgoingBso::from_content_with_id)
-                .collect::<Result<_, _>>()?
-        )
+                .collect::<Result<_, _>>()?,
+        ))
     }
 
     fn set_uploaded(